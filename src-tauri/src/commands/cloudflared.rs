@@ -1,5 +1,7 @@
 use tauri::State;
-use crate::modules::cloudflared::{CloudflaredConfig, CloudflaredManager, CloudflaredStatus};
+use crate::modules::cloudflared::{
+    CloudflaredConfig, CloudflaredLogLine, CloudflaredManager, CloudflaredStatus,
+};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -40,9 +42,7 @@ pub async fn cloudflared_check(
         Ok(CloudflaredStatus {
             installed,
             version,
-            running: false,
-            url: None,
-            error: None,
+            ..Default::default()
         })
     } else {
         Err("Manager not initialized".to_string())
@@ -118,3 +118,17 @@ pub async fn cloudflared_get_status(
     }
 }
 
+/// 获取cloudflared最近的输出日志
+#[tauri::command]
+pub async fn cloudflared_get_logs(
+    state: State<'_, CloudflaredState>,
+    limit: Option<usize>,
+) -> Result<Vec<CloudflaredLogLine>, String> {
+    state.ensure_manager().await?;
+
+    let lock = state.manager.read().await;
+    Ok(lock
+        .as_ref()
+        .map(|manager| manager.get_logs(limit))
+        .unwrap_or_default())
+}
@@ -337,13 +337,25 @@ pub async fn load_config() -> Result<AppConfig, String> {
 pub async fn save_config(
     app: tauri::AppHandle,
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
-    config: AppConfig,
+    mut config: AppConfig,
 ) -> Result<(), String> {
+    crate::proxy::security::validate_monitor_cidrs(&config.proxy.security_monitor)?;
+    crate::proxy::transforms::validate_transforms(&config.proxy.transforms)?;
+    config.proxy.warmup_schedule.validate()?;
+    // [NEW] 保留管理用户会话签名密钥 (前端未回传时)
+    if config.proxy.admin_session_secret.is_empty() {
+        if let Ok(current) = modules::load_app_config() {
+            config.proxy.admin_session_secret = current.proxy.admin_session_secret;
+        }
+    }
     modules::save_app_config(&config)?;
 
     // 通知托盘配置已更新
     let _ = app.emit("config://updated", ());
 
+    // [FIX] 环境变量覆盖只作用于运行时配置 (已保存的是文件值)
+    let config = modules::config::with_env_overrides(config);
+
     // 热更新正在运行的服务
     let instance_lock = proxy_state.instance.read().await;
     if let Some(instance) = instance_lock.as_ref() {
@@ -370,12 +382,19 @@ pub async fn save_config(
             .await;
         // [NEW] 更新 User-Agent 配置
         instance.axum_server.update_user_agent(&config.proxy).await;
-        // 更新 Thinking Budget 配置
-        crate::proxy::update_thinking_budget_config(config.proxy.thinking_budget.clone());
-        // [NEW] 更新全局系统提示词配置
-        crate::proxy::update_global_system_prompt_config(config.proxy.global_system_prompt.clone());
-        // [NEW] 更新全局图像思维模式配置
-        crate::proxy::update_image_thinking_mode(config.proxy.image_thinking_mode.clone());
+        // 更新全局配置 (Thinking Budget、系统提示词、响应头、超时、预算等)
+        crate::proxy::apply_global_proxy_config(&config.proxy);
+        // [NEW] 更新账号并发限制配置
+        instance
+            .token_manager
+            .update_concurrency_config(config.proxy.concurrency.clone());
+        instance
+            .token_manager
+            .update_auto_disable_threshold(config.proxy.auto_disable_failure_threshold);
+        // [NEW] 更新多实例协调模式
+        instance
+            .token_manager
+            .update_coordination_mode(config.proxy.coordination_mode);
         // 更新代理池配置
         instance
             .axum_server
@@ -772,41 +791,13 @@ pub async fn toggle_proxy_status(
         if enable { "启用" } else { "禁用" }
     ));
 
-    // 1. 读取账号文件
-    let data_dir = modules::account::get_data_dir()?;
-    let account_path = data_dir
-        .join("accounts")
-        .join(format!("{}.json", account_id));
-
-    if !account_path.exists() {
-        return Err(format!("账号文件不存在: {}", account_id));
-    }
-
-    let content =
-        std::fs::read_to_string(&account_path).map_err(|e| format!("读取账号文件失败: {}", e))?;
-
-    let mut account_json: serde_json::Value =
-        serde_json::from_str(&content).map_err(|e| format!("解析账号文件失败: {}", e))?;
-
-    // 2. 更新 proxy_disabled 字段
-    if enable {
-        // 启用反代
-        account_json["proxy_disabled"] = serde_json::Value::Bool(false);
-        account_json["proxy_disabled_reason"] = serde_json::Value::Null;
-        account_json["proxy_disabled_at"] = serde_json::Value::Null;
+    // 1. 更新账号文件 (同时记录禁用历史)
+    let reason = if enable {
+        None
     } else {
-        // 禁用反代
-        let now = chrono::Utc::now().timestamp();
-        account_json["proxy_disabled"] = serde_json::Value::Bool(true);
-        account_json["proxy_disabled_at"] = serde_json::Value::Number(now.into());
-        account_json["proxy_disabled_reason"] =
-            serde_json::Value::String(reason.unwrap_or_else(|| "用户手动禁用".to_string()));
-    }
-
-    // 3. 保存到磁盘
-    let json_str = serde_json::to_string_pretty(&account_json)
-        .map_err(|e| format!("序列化账号数据失败: {}", e))?;
-    std::fs::write(&account_path, json_str).map_err(|e| format!("写入账号文件失败: {}", e))?;
+        Some(reason.unwrap_or_else(|| "用户手动禁用".to_string()))
+    };
+    modules::account::toggle_proxy_status(&account_id, enable, reason.as_deref())?;
 
     modules::logger::log_info(&format!(
         "账号反代状态已更新: {} ({})",
@@ -814,7 +805,7 @@ pub async fn toggle_proxy_status(
         if enable { "已启用" } else { "已禁用" }
     ));
 
-    // 4. 如果反代服务正在运行,立刻同步到内存池（避免禁用后仍被选中）
+    // 2. 如果反代服务正在运行,立刻同步到内存池（避免禁用后仍被选中）
     {
         let instance_lock = proxy_state.instance.read().await;
         if let Some(instance) = instance_lock.as_ref() {
@@ -833,6 +824,10 @@ pub async fn toggle_proxy_status(
                 }
             }
 
+            // [NEW] 显式重新启用时清除连续硬失败计数
+            if enable {
+                instance.token_manager.clear_hard_failures(&account_id);
+            }
             instance
                 .token_manager
                 .reload_account(&account_id)
@@ -841,7 +836,7 @@ pub async fn toggle_proxy_status(
         }
     }
 
-    // 5. 更新托盘菜单
+    // 3. 更新托盘菜单
     crate::modules::tray::update_tray_menus(&app);
 
     Ok(())
@@ -855,8 +850,16 @@ pub async fn warm_up_all_accounts() -> Result<String, String> {
 
 /// 预热指定账号
 #[tauri::command]
-pub async fn warm_up_account(account_id: String) -> Result<String, String> {
-    modules::quota::warm_up_account(&account_id).await
+pub async fn warm_up_account(
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+    account_id: String,
+) -> Result<String, String> {
+    let result = modules::scheduler::warm_up_and_record(&account_id).await?;
+    // [NEW] 手动预热成功，清除连续硬失败计数
+    if let Some(instance) = proxy_state.instance.read().await.as_ref() {
+        instance.token_manager.clear_hard_failures(&account_id);
+    }
+    Ok(result)
 }
 
 /// 更新账号自定义标签
@@ -1,4 +1,4 @@
-use crate::proxy::monitor::{ProxyMonitor, ProxyRequestLog, ProxyStats};
+use crate::proxy::monitor::{ActiveRequest, ProxyMonitor, ProxyRequestLog, ProxyStats};
 use crate::proxy::{ProxyConfig, ProxyPoolConfig, TokenManager};
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -80,6 +80,9 @@ pub async fn internal_start_proxy_service(
     integration: crate::modules::integration::SystemManager,
     cloudflared_state: Arc<crate::commands::cloudflared::CloudflaredState>,
 ) -> Result<ProxyStatus, String> {
+    // [NEW] 运行时配置叠加 ABV_* 环境变量覆盖
+    let config = crate::modules::config::with_proxy_env_overrides(config);
+
     // 1. 检查状态并加锁
     {
         let instance_lock = state.instance.read().await;
@@ -145,6 +148,9 @@ pub async fn internal_start_proxy_service(
     token_manager
         .update_sticky_config(config.scheduling.clone())
         .await;
+    token_manager.update_concurrency_config(config.concurrency.clone());
+    token_manager.update_auto_disable_threshold(config.auto_disable_failure_threshold);
+    token_manager.update_coordination_mode(config.coordination_mode);
 
     // [NEW] 加载熔断配置 (从主配置加载)
     let app_config = crate::modules::config::load_app_config()
@@ -216,6 +222,8 @@ pub async fn ensure_admin_server(
     if admin_lock.is_some() {
         return Ok(());
     }
+    // [NEW] 运行时配置叠加 ABV_* 环境变量覆盖
+    let config = crate::modules::config::with_proxy_env_overrides(config);
 
     // Ensure monitor exists
     let monitor = {
@@ -241,11 +249,13 @@ pub async fn ensure_admin_server(
     let (axum_server, server_handle) = match crate::proxy::AxumServer::start(
         config.get_bind_address().to_string(),
         config.port,
+        config.bind_addresses.clone(),
         token_manager,
         config.custom_mapping.clone(),
         config.request_timeout,
         config.upstream_proxy.clone(),
         config.user_agent_override.clone(),
+        config.user_agent_pool.clone(),
         crate::proxy::ProxySecurityConfig::from_proxy_config(&config),
         config.zai.clone(),
         monitor,
@@ -254,6 +264,7 @@ pub async fn ensure_admin_server(
         integration.clone(),
         cloudflared_state,
         config.proxy_pool.clone(),
+        config.config_file_watch,
     )
     .await
     {
@@ -266,12 +277,8 @@ pub async fn ensure_admin_server(
         server_handle,
     });
 
-    // [NEW] 初始化全局 Thinking Budget 配置
-    crate::proxy::update_thinking_budget_config(config.thinking_budget.clone());
-    // [NEW] 初始化全局系统提示词配置
-    crate::proxy::update_global_system_prompt_config(config.global_system_prompt.clone());
-    // [NEW] 初始化全局图像思维模式配置
-    crate::proxy::update_image_thinking_mode(config.image_thinking_mode.clone());
+    // [NEW] 初始化全局配置 (Thinking Budget、系统提示词、响应头、超时、预算等)
+    crate::proxy::apply_global_proxy_config(&config);
 
     Ok(())
 }
@@ -295,6 +302,24 @@ pub async fn stop_proxy_service(state: State<'_, ProxyServiceState>) -> Result<(
     Ok(())
 }
 
+/// 关闭管理服务器 (进程退出时): 停止监听，存量连接最多等待 shutdown_drain_seconds
+/// (或 ABV_SHUTDOWN_GRACE_SECS) 后强制关闭
+pub async fn shutdown_admin_server(state: &ProxyServiceState) {
+    let Some(admin) = state.admin_server.write().await.take() else {
+        return;
+    };
+    let configured = crate::modules::config::load_app_config()
+        .map(|c| c.proxy.shutdown_drain_seconds)
+        .unwrap_or_else(|_| ProxyConfig::default().shutdown_drain_seconds);
+    let grace_period = crate::proxy::server::shutdown_grace_period(configured);
+    admin.axum_server.stop(grace_period).await;
+    // 服务器任务在排空完成 (或超时强制关闭) 后结束
+    let deadline = grace_period + std::time::Duration::from_secs(1);
+    if tokio::time::timeout(deadline, admin.server_handle).await.is_err() {
+        tracing::warn!("Admin server did not shut down within {}s", deadline.as_secs());
+    }
+}
+
 /// 获取反代服务状态
 #[tauri::command]
 pub async fn get_proxy_status(state: State<'_, ProxyServiceState>) -> Result<ProxyStatus, String> {
@@ -349,6 +374,31 @@ pub async fn get_proxy_stats(state: State<'_, ProxyServiceState>) -> Result<Prox
     }
 }
 
+/// 获取正在处理中的反代请求
+#[tauri::command]
+pub async fn get_active_requests(
+    state: State<'_, ProxyServiceState>,
+) -> Result<Vec<ActiveRequest>, String> {
+    let monitor_lock = state.monitor.read().await;
+    Ok(monitor_lock
+        .as_ref()
+        .map(|monitor| monitor.active.list())
+        .unwrap_or_default())
+}
+
+/// 取消正在处理中的反代请求
+#[tauri::command]
+pub async fn cancel_active_request(
+    state: State<'_, ProxyServiceState>,
+    request_id: String,
+) -> Result<(), String> {
+    let monitor_lock = state.monitor.read().await;
+    match monitor_lock.as_ref() {
+        Some(monitor) if monitor.active.cancel(&request_id) => Ok(()),
+        _ => Err(format!("Request {} is not in flight", request_id)),
+    }
+}
+
 /// 获取反代请求日志
 #[tauri::command]
 pub async fn get_proxy_logs(
@@ -473,7 +523,7 @@ pub async fn reload_proxy_accounts(state: State<'_, ProxyServiceState>) -> Resul
         // [FIX #820] Clear stale session bindings before reloading accounts
         // This ensures that after switching accounts in the UI, API requests
         // won't be routed to the previously bound (wrong) account
-        instance.token_manager.clear_all_sessions();
+        instance.token_manager.clear_all_sessions().await;
 
         // 重新加载账号
         let count = instance
@@ -664,7 +714,7 @@ pub async fn clear_proxy_session_bindings(
 ) -> Result<(), String> {
     let instance_lock = state.instance.read().await;
     if let Some(instance) = instance_lock.as_ref() {
-        instance.token_manager.clear_all_sessions();
+        instance.token_manager.clear_all_sessions().await;
         Ok(())
     } else {
         Err("服务未运行".to_string())
@@ -734,7 +784,7 @@ pub async fn clear_proxy_rate_limit(
 ) -> Result<bool, String> {
     let instance_lock = state.instance.read().await;
     if let Some(instance) = instance_lock.as_ref() {
-        Ok(instance.token_manager.clear_rate_limit(&account_id))
+        Ok(instance.token_manager.clear_rate_limit(&account_id).await)
     } else {
         Err("服务未运行".to_string())
     }
@@ -747,7 +797,7 @@ pub async fn clear_all_proxy_rate_limits(
 ) -> Result<(), String> {
     let instance_lock = state.instance.read().await;
     if let Some(instance) = instance_lock.as_ref() {
-        instance.token_manager.clear_all_rate_limits();
+        instance.token_manager.clear_all_rate_limits().await;
         Ok(())
     } else {
         Err("服务未运行".to_string())
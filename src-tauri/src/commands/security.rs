@@ -262,47 +262,8 @@ pub async fn get_ip_token_stats(
 
 /// 验证 IP 模式格式 (支持单个 IP 和 CIDR)
 fn is_valid_ip_pattern(pattern: &str) -> bool {
-    // 检查是否为 CIDR 格式
-    if pattern.contains('/') {
-        let parts: Vec<&str> = pattern.split('/').collect();
-        if parts.len() != 2 {
-            return false;
-        }
-        
-        // 验证 IP 部分
-        if !is_valid_ip(parts[0]) {
-            return false;
-        }
-        
-        // 验证掩码部分
-        if let Ok(mask) = parts[1].parse::<u8>() {
-            return mask <= 32;
-        }
-        return false;
-    }
-    
-    // 单个 IP 地址
-    is_valid_ip(pattern)
-}
-
-/// 验证 IP 地址格式
-fn is_valid_ip(ip: &str) -> bool {
-    let parts: Vec<&str> = ip.split('.').collect();
-    if parts.len() != 4 {
-        return false;
-    }
-    
-    for part in parts {
-        if let Ok(num) = part.parse::<u8>() {
-            if num > 255 {
-                return false;
-            }
-        } else {
-            return false;
-        }
-    }
-    
-    true
+    // 单个 IP 或 CIDR (IPv4 / IPv6)
+    crate::proxy::security::parse_cidr(pattern).is_some()
 }
 
 #[cfg(test)]
@@ -316,6 +277,8 @@ mod tests {
         assert!(is_valid_ip_pattern("172.16.0.0/16"));
         assert!(is_valid_ip_pattern("192.168.1.0/24"));
         assert!(is_valid_ip_pattern("8.8.8.8/32"));
+        assert!(is_valid_ip_pattern("2001:db8::/32"));
+        assert!(is_valid_ip_pattern("::1"));
     }
 
     #[test]
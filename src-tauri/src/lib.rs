@@ -71,6 +71,16 @@ pub fn run() {
         error!("Failed to initialize user token database: {}", e);
     }
 
+    // Initialize admin audit log database
+    if let Err(e) = modules::audit_log::init_db() {
+        error!("Failed to initialize audit log database: {}", e);
+    }
+
+    // Initialize admin users database
+    if let Err(e) = modules::admin_users::init_db() {
+        error!("Failed to initialize admin users database: {}", e);
+    }
+
     if is_headless {
         info!("Starting in HEADLESS mode...");
 
@@ -212,6 +222,8 @@ pub fn run() {
             // Wait for Ctrl-C
             tokio::signal::ctrl_c().await.ok();
             info!("Headless mode shutting down");
+            // 停止监听并排空在途请求 (shutdown_drain_seconds / ABV_SHUTDOWN_GRACE_SECS)
+            commands::proxy::shutdown_admin_server(&proxy_state).await;
         });
         return;
     }
@@ -386,6 +398,8 @@ pub fn run() {
             commands::proxy::stop_proxy_service,
             commands::proxy::get_proxy_status,
             commands::proxy::get_proxy_stats,
+            commands::proxy::get_active_requests,
+            commands::proxy::cancel_active_request,
             commands::proxy::get_proxy_logs,
             commands::proxy::get_proxy_logs_paginated,
             commands::proxy::get_proxy_log_detail,
@@ -471,6 +485,7 @@ pub fn run() {
             commands::cloudflared::cloudflared_start,
             commands::cloudflared::cloudflared_stop,
             commands::cloudflared::cloudflared_get_status,
+            commands::cloudflared::cloudflared_get_logs,
             // Debug console commands
             modules::log_bridge::enable_debug_console,
             modules::log_bridge::disable_debug_console,
@@ -34,6 +34,9 @@ pub struct Account {
     /// Unix timestamp when the proxy was disabled.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub proxy_disabled_at: Option<i64>,
+    /// 反代禁用历史 (最近 PROXY_DISABLE_HISTORY_LIMIT 条，旧到新)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub proxy_disable_history: Vec<ProxyDisableEvent>,
     /// 受配额保护禁用的模型列表 [NEW #621]
     #[serde(default, skip_serializing_if = "HashSet::is_empty")]
     pub protected_models: HashSet<String>,
@@ -57,6 +60,38 @@ pub struct Account {
     /// 用户自定义标签
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub custom_label: Option<String>,
+    /// 分组标签 (如 production / staging / personal)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// 运维备注 (如 "工作账号, Claude Pro, 2025-12 到期")，同步到索引用于搜索
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// 最近一次预热 (定时或手动) 的时间与结果
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_warmup: Option<WarmupRecord>,
+}
+
+/// 每个账号保留的反代禁用历史条数
+pub const PROXY_DISABLE_HISTORY_LIMIT: usize = 10;
+
+/// 一次反代禁用记录
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProxyDisableEvent {
+    pub reason: String,
+    pub disabled_at: i64,
+    /// 重新启用时间 (仍处于禁用状态时为 None)
+    #[serde(default)]
+    pub re_enabled_at: Option<i64>,
+}
+
+/// 单次预热结果
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WarmupRecord {
+    /// 预热时间 (Unix 秒)
+    pub at: i64,
+    pub ok: bool,
+    /// 成功时为预热摘要，失败时为错误信息
+    pub message: String,
 }
 
 impl Account {
@@ -76,6 +111,7 @@ impl Account {
             proxy_disabled: false,
             proxy_disabled_reason: None,
             proxy_disabled_at: None,
+            proxy_disable_history: Vec::new(),
             protected_models: HashSet::new(),
             validation_blocked: false,
             validation_blocked_until: None,
@@ -85,6 +121,9 @@ impl Account {
             proxy_id: None,
             proxy_bound_at: None,
             custom_label: None,
+            tags: Vec::new(),
+            notes: None,
+            last_warmup: None,
         }
     }
 
@@ -95,6 +134,41 @@ impl Account {
     pub fn update_quota(&mut self, quota: QuotaData) {
         self.quota = Some(quota);
     }
+
+    /// 更新反代禁用状态并记录历史
+    /// 禁用时追加一条记录；启用时补上最近一条未结束记录的 re_enabled_at
+    pub fn set_proxy_disabled(&mut self, disabled: bool, reason: Option<String>, now: i64) {
+        if disabled {
+            self.proxy_disable_history.push(ProxyDisableEvent {
+                reason: reason.clone().unwrap_or_default(),
+                disabled_at: now,
+                re_enabled_at: None,
+            });
+        } else if self.proxy_disabled {
+            match self
+                .proxy_disable_history
+                .last_mut()
+                .filter(|e| e.re_enabled_at.is_none())
+            {
+                Some(event) => event.re_enabled_at = Some(now),
+                // 升级前禁用的账号没有历史，用当前状态补一条
+                None => self.proxy_disable_history.push(ProxyDisableEvent {
+                    reason: self.proxy_disabled_reason.clone().unwrap_or_default(),
+                    disabled_at: self.proxy_disabled_at.unwrap_or(now),
+                    re_enabled_at: Some(now),
+                }),
+            }
+        }
+        let overflow = self
+            .proxy_disable_history
+            .len()
+            .saturating_sub(PROXY_DISABLE_HISTORY_LIMIT);
+        self.proxy_disable_history.drain(..overflow);
+
+        self.proxy_disabled = disabled;
+        self.proxy_disabled_reason = if disabled { reason } else { None };
+        self.proxy_disabled_at = if disabled { Some(now) } else { None };
+    }
 }
 
 /// 账号索引数据（accounts.json）
@@ -117,6 +191,25 @@ pub struct AccountSummary {
     pub proxy_disabled: bool,
     pub created_at: i64,
     pub last_used: i64,
+    /// 账号备注副本，搜索时无需逐个加载账号文件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+impl AccountSummary {
+    /// 邮箱或备注包含关键字 (大小写不敏感，语义同 SQL `LIKE '%keyword%'`)
+    pub fn matches_search(&self, keyword: &str) -> bool {
+        let keyword = keyword.trim().to_lowercase();
+        if keyword.is_empty() {
+            return true;
+        }
+        self.email.to_lowercase().contains(&keyword)
+            || self
+                .notes
+                .as_deref()
+                .map(|n| n.to_lowercase().contains(&keyword))
+                .unwrap_or(false)
+    }
 }
 
 impl AccountIndex {
@@ -167,3 +260,26 @@ pub struct AccountExportItem {
 pub struct AccountExportResponse {
     pub accounts: Vec<AccountExportItem>,
 }
+
+/// [NEW] 账号部分更新请求 (PATCH)；字段缺省表示不修改
+/// `name` / `notes` 区分缺省与显式 null: `Some(None)` 表示清除
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PatchAccountRequest {
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub notes: Option<Option<String>>,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub protected_models: Option<Vec<String>>,
+}
+
+/// 字段存在时 (包括 null) 包一层 Some，配合 `serde(default)` 区分缺省与 null
+fn deserialize_nullable<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
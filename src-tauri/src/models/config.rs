@@ -1,8 +1,9 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use crate::proxy::ProxyConfig;
 
 /// Application configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AppConfig {
     pub language: String,
     pub theme: String,
@@ -27,10 +28,30 @@ pub struct AppConfig {
     pub circuit_breaker: CircuitBreakerConfig, // [NEW] Circuit breaker configuration
     #[serde(default)]
     pub hidden_menu_items: Vec<String>, // Hidden menu item path list
+    #[serde(default = "default_quota_refresh_concurrency")]
+    pub quota_refresh_concurrency: usize, // [NEW] Max concurrent accounts during batch quota refresh
+    #[serde(default = "default_quota_forecast_warning_hours")]
+    pub quota_forecast_warning_hours: f64, // [NEW] Warn when a model's forecast time-to-exhaustion drops below this
+    #[serde(default)]
+    pub backup: BackupConfig, // [NEW] Scheduled config & account store backups
+}
+
+/// Default concurrency for batch quota refresh
+pub const DEFAULT_QUOTA_REFRESH_CONCURRENCY: usize = 5;
+
+fn default_quota_refresh_concurrency() -> usize {
+    DEFAULT_QUOTA_REFRESH_CONCURRENCY
+}
+
+/// Default quota forecast warning threshold (hours)
+pub const DEFAULT_QUOTA_FORECAST_WARNING_HOURS: f64 = 2.0;
+
+fn default_quota_forecast_warning_hours() -> f64 {
+    DEFAULT_QUOTA_FORECAST_WARNING_HOURS
 }
 
 /// Scheduled warmup configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ScheduledWarmupConfig {
     /// Whether smart warmup is enabled
     pub enabled: bool,
@@ -65,7 +86,7 @@ impl Default for ScheduledWarmupConfig {
 }
 
 /// Quota protection configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct QuotaProtectionConfig {
     /// Whether quota protection is enabled
     pub enabled: bool,
@@ -104,7 +125,7 @@ impl Default for QuotaProtectionConfig {
 }
 
 /// Pinned quota models configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PinnedQuotaModelsConfig {
     /// List of pinned models (displayed outside the account list)
     #[serde(default = "default_pinned_models")]
@@ -134,8 +155,46 @@ impl Default for PinnedQuotaModelsConfig {
     }
 }
 
+/// Scheduled backup of the app config and account store (data_dir/backups)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BackupConfig {
+    /// Whether scheduled backups are enabled
+    pub enabled: bool,
+
+    /// Hours between backups
+    #[serde(default = "default_backup_interval_hours")]
+    pub interval_hours: u64,
+
+    /// Number of backups to keep (oldest are deleted first)
+    #[serde(default = "default_backup_keep")]
+    pub keep: usize,
+
+    /// Also back up the log / stats SQLite databases (can be large)
+    #[serde(default)]
+    pub include_databases: bool,
+}
+
+fn default_backup_interval_hours() -> u64 {
+    24
+}
+
+fn default_backup_keep() -> usize {
+    7
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_hours: default_backup_interval_hours(),
+            keep: default_backup_keep(),
+            include_databases: false,
+        }
+    }
+}
+
 /// Circuit breaker configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CircuitBreakerConfig {
     /// Whether circuit breaker is enabled
     pub enabled: bool,
@@ -184,6 +243,9 @@ impl AppConfig {
             pinned_quota_models: PinnedQuotaModelsConfig::default(),
             circuit_breaker: CircuitBreakerConfig::default(),
             hidden_menu_items: Vec::new(),
+            quota_refresh_concurrency: DEFAULT_QUOTA_REFRESH_CONCURRENCY,
+            quota_forecast_warning_hours: DEFAULT_QUOTA_FORECAST_WARNING_HOURS,
+            backup: BackupConfig::default(),
         }
     }
 }
@@ -5,8 +5,8 @@ use std::path::PathBuf;
 use uuid::Uuid;
 
 use crate::models::{
-    Account, AccountIndex, AccountSummary, DeviceProfile, DeviceProfileVersion, QuotaData,
-    TokenData,
+    Account, AccountIndex, AccountSummary, DeviceProfile, DeviceProfileVersion,
+    PatchAccountRequest, QuotaData, TokenData,
 };
 use crate::modules;
 use once_cell::sync::Lazy;
@@ -17,8 +17,12 @@ static ACCOUNT_INDEX_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
 
 // ... existing constants ...
 const DATA_DIR: &str = ".antigravity_tools";
-const ACCOUNTS_INDEX: &str = "accounts.json";
-const ACCOUNTS_DIR: &str = "accounts";
+pub(crate) const ACCOUNTS_INDEX: &str = "accounts.json";
+pub(crate) const ACCOUNTS_DIR: &str = "accounts";
+const DEVICE_BULK_PROGRESS: &str = "device_bind_progress.json";
+
+/// 批量绑定设备指纹互斥锁 (同一时间只允许一个批量任务写进度文件)
+static DEVICE_BULK_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
 
 // ... existing functions get_data_dir, get_accounts_dir, load_account_index, save_account_index ...
 /// Get data directory path
@@ -134,9 +138,141 @@ pub fn save_account(account: &Account) -> Result<(), String> {
 pub fn list_accounts() -> Result<Vec<Account>, String> {
     crate::modules::logger::log_info("Listing accounts...");
     let index = load_account_index()?;
+    Ok(load_accounts_from_summaries(&index.accounts))
+}
+
+/// 按关键字搜索账号 (匹配邮箱与备注)，只加载索引中命中的账号文件
+pub fn search_accounts(keyword: &str) -> Result<Vec<Account>, String> {
+    let index = load_account_index()?;
+    let matched: Vec<AccountSummary> = index
+        .accounts
+        .into_iter()
+        .filter(|summary| summary.matches_search(keyword))
+        .collect();
+    Ok(load_accounts_from_summaries(&matched))
+}
+
+/// 账号列表排序字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountSortKey {
+    LastUsed,
+    Email,
+    /// 各模型剩余配额百分比中的最小值 (无配额数据的账号排在最后)
+    QuotaMin,
+    Created,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// 账号状态筛选
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountStatusFilter {
+    /// 未禁用、未禁止反代且未被 403
+    Active,
+    /// 已禁用或已禁止反代
+    Disabled,
+    /// 配额接口返回 403
+    Forbidden,
+}
+
+impl AccountStatusFilter {
+    pub fn matches(&self, account: &Account) -> bool {
+        let forbidden = account.quota.as_ref().is_some_and(|q| q.is_forbidden);
+        match self {
+            AccountStatusFilter::Active => {
+                !account.disabled && !account.proxy_disabled && !forbidden
+            }
+            AccountStatusFilter::Disabled => account.disabled || account.proxy_disabled,
+            AccountStatusFilter::Forbidden => forbidden,
+        }
+    }
+}
+
+/// 账号列表的筛选 / 排序 / 分页参数，全部为空时保持索引顺序返回全部账号
+#[derive(Debug, Clone, Default)]
+pub struct AccountListOptions {
+    pub sort: Option<AccountSortKey>,
+    /// 默认: email / quota_min 升序，last_used / created 降序
+    pub order: Option<SortOrder>,
+    pub disabled: Option<bool>,
+    pub proxy_disabled: Option<bool>,
+    pub status: Option<AccountStatusFilter>,
+    pub tag: Option<String>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+fn quota_min(account: &Account) -> Option<i32> {
+    account
+        .quota
+        .as_ref()
+        .and_then(|q| q.models.iter().map(|m| m.percentage).min())
+}
+
+/// 依次筛选、排序、分页，返回 (筛选后的总数, 当前页账号)
+pub fn apply_list_options(mut accounts: Vec<Account>, options: &AccountListOptions) -> (usize, Vec<Account>) {
+    if let Some(disabled) = options.disabled {
+        accounts.retain(|a| a.disabled == disabled);
+    }
+    if let Some(proxy_disabled) = options.proxy_disabled {
+        accounts.retain(|a| a.proxy_disabled == proxy_disabled);
+    }
+    if let Some(status) = options.status {
+        accounts.retain(|a| status.matches(a));
+    }
+    if let Some(tag) = options.tag.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+        accounts.retain(|a| a.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)));
+    }
+
+    if let Some(key) = options.sort {
+        let order = options.order.unwrap_or(match key {
+            AccountSortKey::Email | AccountSortKey::QuotaMin => SortOrder::Asc,
+            AccountSortKey::LastUsed | AccountSortKey::Created => SortOrder::Desc,
+        });
+        accounts.sort_by(|a, b| {
+            let ord = match key {
+                AccountSortKey::LastUsed => a.last_used.cmp(&b.last_used),
+                AccountSortKey::Email => a.email.to_lowercase().cmp(&b.email.to_lowercase()),
+                AccountSortKey::Created => a.created_at.cmp(&b.created_at),
+                AccountSortKey::QuotaMin => {
+                    // 无配额数据的账号无论升降序都排在最后
+                    return match (quota_min(a), quota_min(b)) {
+                        (Some(x), Some(y)) if order == SortOrder::Asc => x.cmp(&y),
+                        (Some(x), Some(y)) => y.cmp(&x),
+                        (Some(_), None) => std::cmp::Ordering::Less,
+                        (None, Some(_)) => std::cmp::Ordering::Greater,
+                        (None, None) => std::cmp::Ordering::Equal,
+                    };
+                }
+            };
+            match order {
+                SortOrder::Asc => ord,
+                SortOrder::Desc => ord.reverse(),
+            }
+        });
+    }
+
+    let total = accounts.len();
+    let offset = options.offset.unwrap_or(0);
+    let page = accounts
+        .into_iter()
+        .skip(offset)
+        .take(options.limit.unwrap_or(usize::MAX))
+        .collect();
+    (total, page)
+}
+
+fn load_accounts_from_summaries(summaries: &[AccountSummary]) -> Vec<Account> {
     let mut accounts = Vec::new();
 
-    for summary in &index.accounts {
+    for summary in summaries {
         match load_account(&summary.id) {
             Ok(account) => accounts.push(account),
             Err(e) => {
@@ -151,7 +287,7 @@ pub fn list_accounts() -> Result<Vec<Account>, String> {
         }
     }
 
-    Ok(accounts)
+    accounts
 }
 
 /// Add account
@@ -187,6 +323,7 @@ pub fn add_account(
         proxy_disabled: false,
         created_at: account.created_at,
         last_used: account.last_used,
+        notes: None,
     });
 
     // If first account, set as current
@@ -196,6 +333,14 @@ pub fn add_account(
 
     save_account_index(&index)?;
 
+    crate::proxy::webhook::emit(
+        crate::proxy::config::WebhookEvent::AccountAdded,
+        serde_json::json!({
+            "account_id": account.id,
+            "email": account.email,
+        }),
+    );
+
     Ok(account)
 }
 
@@ -517,6 +662,303 @@ pub fn bind_device_profile_with_profile(
     Ok(profile)
 }
 
+/// 批量绑定的指纹生成方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkDeviceBindMode {
+    /// 每个账号独立随机生成
+    #[default]
+    Generate,
+    /// 以模板为准，未覆盖的字段按账号随机生成
+    FromTemplate,
+}
+
+/// 指纹模板 (字段为空则随机生成)
+#[derive(Debug, Clone, Default, Serialize, serde::Deserialize)]
+pub struct DeviceProfileTemplate {
+    #[serde(default, alias = "machineId")]
+    pub machine_id: Option<String>,
+    #[serde(default, alias = "macMachineId")]
+    pub mac_machine_id: Option<String>,
+    #[serde(default, alias = "devDeviceId")]
+    pub dev_device_id: Option<String>,
+    #[serde(default, alias = "sqmId")]
+    pub sqm_id: Option<String>,
+}
+
+impl DeviceProfileTemplate {
+    fn build(&self) -> DeviceProfile {
+        let mut profile = crate::modules::device::generate_profile();
+        let pick = |field: &Option<String>| {
+            field
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        if let Some(v) = pick(&self.machine_id) {
+            profile.machine_id = v;
+        }
+        if let Some(v) = pick(&self.mac_machine_id) {
+            profile.mac_machine_id = v;
+        }
+        if let Some(v) = pick(&self.dev_device_id) {
+            profile.dev_device_id = v;
+        }
+        if let Some(v) = pick(&self.sqm_id) {
+            profile.sqm_id = v;
+        }
+        profile
+    }
+}
+
+/// 批量绑定请求
+#[derive(Debug, Clone, Default, Serialize, serde::Deserialize)]
+pub struct BulkDeviceBindRequest {
+    /// 目标账号 ID 列表 (与 all_unbound 二选一)
+    #[serde(default, alias = "accountIds")]
+    pub account_ids: Vec<String>,
+    /// 选中所有尚未绑定指纹的账号
+    #[serde(default, alias = "allUnbound")]
+    pub all_unbound: bool,
+    #[serde(default)]
+    pub mode: BulkDeviceBindMode,
+    #[serde(default)]
+    pub template: Option<DeviceProfileTemplate>,
+    /// 跳过已绑定指纹的账号
+    #[serde(default, alias = "skipExisting")]
+    pub skip_existing: bool,
+    /// 仅返回将要生成的指纹，不写入账号
+    #[serde(default, alias = "dryRun")]
+    pub dry_run: bool,
+    /// 从上次中断的进度继续 (忽略本次请求中的其他参数)
+    #[serde(default)]
+    pub resume: bool,
+}
+
+/// 单个账号的处理结果
+#[derive(Debug, Clone, Serialize, serde::Deserialize)]
+pub struct BulkDeviceBindResult {
+    pub account_id: String,
+    pub email: Option<String>,
+    /// "bound" / "skipped" / "failed" / "preview"
+    pub status: String,
+    pub device_profile: Option<DeviceProfile>,
+    pub error: Option<String>,
+}
+
+/// 持久化的批量任务进度 (每处理一个账号写一次，崩溃后可 resume)
+#[derive(Debug, Clone, Serialize, serde::Deserialize)]
+pub struct BulkDeviceBindProgress {
+    pub job_id: String,
+    pub started_at: i64,
+    pub request: BulkDeviceBindRequest,
+    /// 任务开始时解析出的目标账号，resume 时沿用
+    pub account_ids: Vec<String>,
+    pub results: Vec<BulkDeviceBindResult>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkDeviceBindReport {
+    pub job_id: Option<String>,
+    pub dry_run: bool,
+    pub resumed: bool,
+    pub total: usize,
+    pub bound: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub results: Vec<BulkDeviceBindResult>,
+}
+
+fn device_bulk_progress_path() -> Result<PathBuf, String> {
+    Ok(get_data_dir()?.join(DEVICE_BULK_PROGRESS))
+}
+
+/// 读取未完成的批量绑定进度 (没有中断的任务时返回 None)
+pub fn load_bulk_bind_progress() -> Result<Option<BulkDeviceBindProgress>, String> {
+    let path = device_bulk_progress_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("failed_to_read_bulk_bind_progress: {}", e))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("failed_to_parse_bulk_bind_progress: {}", e))
+}
+
+fn save_bulk_bind_progress(progress: &BulkDeviceBindProgress) -> Result<(), String> {
+    let path = device_bulk_progress_path()?;
+    let temp_path = path.with_extension("json.tmp");
+    let content = serde_json::to_string_pretty(progress)
+        .map_err(|e| format!("failed_to_serialize_bulk_bind_progress: {}", e))?;
+    fs::write(&temp_path, content)
+        .map_err(|e| format!("failed_to_write_bulk_bind_progress: {}", e))?;
+    fs::rename(temp_path, path).map_err(|e| format!("failed_to_replace_bulk_bind_progress: {}", e))
+}
+
+fn resolve_bulk_bind_targets(request: &BulkDeviceBindRequest) -> Result<Vec<String>, String> {
+    if request.all_unbound {
+        return Ok(list_accounts()?
+            .into_iter()
+            .filter(|a| a.device_profile.is_none())
+            .map(|a| a.id)
+            .collect());
+    }
+
+    let mut ids: Vec<String> = Vec::with_capacity(request.account_ids.len());
+    for id in request.account_ids.iter().map(|id| id.trim()) {
+        if !id.is_empty() && !ids.iter().any(|existing| existing == id) {
+            ids.push(id.to_string());
+        }
+    }
+    if ids.is_empty() {
+        return Err("account_ids must not be empty unless all_unbound is set".to_string());
+    }
+    Ok(ids)
+}
+
+fn process_bulk_bind_account(account_id: &str, request: &BulkDeviceBindRequest) -> BulkDeviceBindResult {
+    let mut result = BulkDeviceBindResult {
+        account_id: account_id.to_string(),
+        email: None,
+        status: "failed".to_string(),
+        device_profile: None,
+        error: None,
+    };
+
+    let account = match load_account(account_id) {
+        Ok(account) => account,
+        Err(e) => {
+            result.error = Some(e);
+            return result;
+        }
+    };
+    result.email = Some(account.email.clone());
+
+    if request.skip_existing && account.device_profile.is_some() {
+        result.status = "skipped".to_string();
+        result.device_profile = account.device_profile;
+        return result;
+    }
+
+    let (profile, label) = match (request.mode, &request.template) {
+        (BulkDeviceBindMode::FromTemplate, Some(template)) => (template.build(), "bulk_template"),
+        _ => (crate::modules::device::generate_profile(), "bulk_generate"),
+    };
+
+    if request.dry_run {
+        result.status = "preview".to_string();
+        result.device_profile = Some(profile);
+        return result;
+    }
+
+    match bind_device_profile_with_profile(account_id, profile, Some(label.to_string())) {
+        Ok(bound) => {
+            result.status = "bound".to_string();
+            result.device_profile = Some(bound);
+        }
+        Err(e) => result.error = Some(e),
+    }
+    result
+}
+
+/// 批量绑定设备指纹：串行处理，每个账号处理完立即持久化进度
+pub fn bulk_bind_device_profiles(
+    request: BulkDeviceBindRequest,
+) -> Result<BulkDeviceBindReport, String> {
+    let _lock = DEVICE_BULK_LOCK
+        .try_lock()
+        .map_err(|_| "A bulk device bind job is already running".to_string())?;
+
+    let make_report = |job_id: Option<String>,
+                       dry_run: bool,
+                       resumed: bool,
+                       results: Vec<BulkDeviceBindResult>| {
+        let count = |status: &str| results.iter().filter(|r| r.status == status).count();
+        BulkDeviceBindReport {
+            job_id,
+            dry_run,
+            resumed,
+            total: results.len(),
+            bound: count("bound"),
+            skipped: count("skipped"),
+            failed: count("failed"),
+            results,
+        }
+    };
+
+    if request.dry_run {
+        let results = resolve_bulk_bind_targets(&request)?
+            .iter()
+            .map(|id| process_bulk_bind_account(id, &request))
+            .collect();
+        return Ok(make_report(None, true, false, results));
+    }
+
+    let (mut progress, resumed) = match load_bulk_bind_progress()? {
+        Some(progress) if request.resume => (progress, true),
+        existing => {
+            if request.resume {
+                return Err("No interrupted bulk device bind job to resume".to_string());
+            }
+            if let Some(stale) = existing {
+                modules::logger::log_warn(&format!(
+                    "Discarding interrupted bulk device bind job {} ({}/{} done)",
+                    stale.job_id,
+                    stale.results.len(),
+                    stale.account_ids.len()
+                ));
+            }
+            let account_ids = resolve_bulk_bind_targets(&request)?;
+            (
+                BulkDeviceBindProgress {
+                    job_id: Uuid::new_v4().to_string(),
+                    started_at: chrono::Utc::now().timestamp(),
+                    request,
+                    account_ids,
+                    results: Vec::new(),
+                },
+                false,
+            )
+        }
+    };
+    save_bulk_bind_progress(&progress)?;
+
+    let pending: Vec<String> = progress
+        .account_ids
+        .iter()
+        .filter(|id| !progress.results.iter().any(|r| &r.account_id == *id))
+        .cloned()
+        .collect();
+    modules::logger::log_info(&format!(
+        "Bulk device bind job {}: {} pending of {} account(s){}",
+        progress.job_id,
+        pending.len(),
+        progress.account_ids.len(),
+        if resumed { " (resumed)" } else { "" }
+    ));
+
+    for account_id in pending {
+        let result = process_bulk_bind_account(&account_id, &progress.request);
+        if let Some(e) = &result.error {
+            modules::logger::log_warn(&format!(
+                "Bulk device bind failed for {}: {}",
+                account_id, e
+            ));
+        }
+        progress.results.push(result);
+        save_bulk_bind_progress(&progress)?;
+    }
+
+    // 全部完成后清除进度文件
+    if let Ok(path) = device_bulk_progress_path() {
+        let _ = fs::remove_file(path);
+    }
+
+    Ok(make_report(Some(progress.job_id), false, resumed, progress.results))
+}
+
 fn apply_profile_to_account(
     account: &mut Account,
     profile: DeviceProfile,
@@ -714,9 +1156,7 @@ pub fn update_account_quota(account_id: &str, quota: QuotaData) -> Result<(), St
                         "[Quota] Migrating account {} from account-level to model-level protection",
                         account.email
                     ));
-                    account.proxy_disabled = false;
-                    account.proxy_disabled_reason = None;
-                    account.proxy_disabled_at = None;
+                    account.set_proxy_disabled(false, None, chrono::Utc::now().timestamp());
                 }
             }
         }
@@ -740,18 +1180,13 @@ pub fn toggle_proxy_status(
     reason: Option<&str>,
 ) -> Result<(), String> {
     let mut account = load_account(account_id)?;
+    let newly_disabled = !enable && !account.proxy_disabled;
 
-    account.proxy_disabled = !enable;
-    account.proxy_disabled_reason = if !enable {
-        reason.map(|s| s.to_string())
-    } else {
-        None
-    };
-    account.proxy_disabled_at = if !enable {
-        Some(chrono::Utc::now().timestamp())
-    } else {
-        None
-    };
+    account.set_proxy_disabled(
+        !enable,
+        reason.map(|s| s.to_string()),
+        chrono::Utc::now().timestamp(),
+    );
 
     save_account(&account)?;
 
@@ -762,9 +1197,258 @@ pub fn toggle_proxy_status(
         save_account_index(&index)?;
     }
 
+    if newly_disabled {
+        crate::proxy::webhook::emit(
+            crate::proxy::config::WebhookEvent::AccountDisabled,
+            serde_json::json!({
+                "account_id": account.id,
+                "email": account.email,
+                "reason": reason,
+                "scope": "proxy",
+            }),
+        );
+    }
+
     Ok(())
 }
 
+/// 账号的反代禁用历史 (旧到新)
+pub fn get_proxy_disable_history(account_id: &str) -> Result<Vec<crate::models::ProxyDisableEvent>, String> {
+    Ok(load_account(account_id)?.proxy_disable_history)
+}
+
+/// 闲置检测自动停用的记录
+#[derive(Debug, Clone, Serialize)]
+pub struct IdleDisabledAccount {
+    pub account_id: String,
+    pub email: String,
+    /// 最近活跃时间 (含反代用量)
+    pub last_used: i64,
+    pub idle_days: u64,
+}
+
+/// 账号最近活跃时间: 切换使用 (last_used)、反代用量与创建时间取最晚
+/// 从未使用过的账号按创建时间计算，避免新导入的账号被立即停用
+fn last_activity(account: &Account, proxy_last_used: Option<i64>) -> i64 {
+    account
+        .last_used
+        .max(account.created_at)
+        .max(proxy_last_used.unwrap_or(0))
+}
+
+/// 停用超过 `idle_days` 天未使用的账号 (proxy_disabled_reason = "idle")
+/// 跳过: 已全局禁用 / 已停用反代 / 配置了 protected_models / 当前活跃账号
+pub fn disable_idle_accounts(idle_days: u64) -> Result<Vec<IdleDisabledAccount>, String> {
+    let now = chrono::Utc::now().timestamp();
+    let threshold = idle_days.saturating_mul(86400) as i64;
+    let current_id = get_current_account_id()?;
+    // 反代请求不会更新 last_used，以 token_stats 中的最近用量作为活跃时间
+    let proxy_usage = modules::token_stats::get_last_usage_by_account()?;
+
+    let mut disabled = Vec::new();
+    for account in list_accounts()? {
+        if account.disabled
+            || account.proxy_disabled
+            || !account.protected_models.is_empty()
+            || current_id.as_deref() == Some(account.id.as_str())
+        {
+            continue;
+        }
+
+        let last_active = last_activity(&account, proxy_usage.get(&account.email).copied());
+        let idle_secs = now - last_active;
+        if idle_secs <= threshold {
+            continue;
+        }
+
+        match toggle_proxy_status(&account.id, false, Some("idle")) {
+            Ok(()) => {
+                modules::logger::log_info(&format!(
+                    "[IdleCheck] Disabled proxy for idle account {} ({} days since last use)",
+                    account.email,
+                    idle_secs / 86400
+                ));
+                disabled.push(IdleDisabledAccount {
+                    account_id: account.id,
+                    email: account.email,
+                    last_used: last_active,
+                    idle_days: (idle_secs / 86400) as u64,
+                });
+            }
+            Err(e) => modules::logger::log_warn(&format!(
+                "[IdleCheck] Failed to disable idle account {}: {}",
+                account.email, e
+            )),
+        }
+    }
+
+    Ok(disabled)
+}
+
+/// 标签最大长度 (按字符计)
+const MAX_TAG_LEN: usize = 32;
+
+/// 规范化标签: 去除首尾空白、丢弃空值、大小写不敏感去重
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, String> {
+    let mut out: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            continue;
+        }
+        if tag.chars().count() > MAX_TAG_LEN {
+            return Err(format!("Tag too long (max {} chars): {}", MAX_TAG_LEN, tag));
+        }
+        if !out.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            out.push(tag.to_string());
+        }
+    }
+    Ok(out)
+}
+
+/// 更新账号分组标签
+pub fn update_account_tags(account_id: &str, tags: &[String]) -> Result<Vec<String>, String> {
+    let tags = normalize_tags(tags)?;
+    let mut account = load_account(account_id)?;
+    account.tags = tags.clone();
+    save_account(&account)?;
+
+    // 通知 TokenManager 重新加载，使标签筛选立即生效
+    crate::proxy::server::trigger_account_reload(account_id);
+    Ok(tags)
+}
+
+/// 备注最大长度 (按字符计)
+const MAX_NOTES_LEN: usize = 2000;
+
+/// 规范化备注: 去除首尾空白，空值视为清除
+fn normalize_notes(notes: Option<&str>) -> Result<Option<String>, String> {
+    let Some(notes) = notes.map(str::trim).filter(|n| !n.is_empty()) else {
+        return Ok(None);
+    };
+    if notes.chars().count() > MAX_NOTES_LEN {
+        return Err(format!("Notes too long (max {} chars)", MAX_NOTES_LEN));
+    }
+    Ok(Some(notes.to_string()))
+}
+
+/// 更新账号备注，并同步到账号索引以便搜索
+pub fn update_account_notes(account_id: &str, notes: Option<&str>) -> Result<Option<String>, String> {
+    let notes = normalize_notes(notes)?;
+    let mut account = load_account(account_id)?;
+    account.notes = notes.clone();
+    save_account(&account)?;
+
+    let _lock = ACCOUNT_INDEX_LOCK
+        .lock()
+        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let mut index = load_account_index()?;
+    if let Some(summary) = index.accounts.iter_mut().find(|s| s.id == account_id) {
+        summary.notes = notes.clone();
+        save_account_index(&index)?;
+    }
+    Ok(notes)
+}
+
+/// 账号名称最大长度 (按字符计)
+const MAX_NAME_LEN: usize = 64;
+
+/// 将 PATCH 请求合并到账号上 (先完成全部校验，失败时账号保持不变)
+fn apply_account_patch(account: &mut Account, patch: PatchAccountRequest) -> Result<(), String> {
+    let name = match patch.name {
+        Some(name) => {
+            let name = name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+            if let Some(n) = &name {
+                if n.chars().count() > MAX_NAME_LEN {
+                    return Err(format!("Name too long (max {} chars)", MAX_NAME_LEN));
+                }
+            }
+            Some(name)
+        }
+        None => None,
+    };
+    let notes = match patch.notes {
+        Some(notes) => Some(normalize_notes(notes.as_deref())?),
+        None => None,
+    };
+    let tags = patch.tags.as_deref().map(normalize_tags).transpose()?;
+    let protected_models = match patch.protected_models {
+        Some(models) => {
+            let mut out = std::collections::HashSet::new();
+            for model in models.iter().map(|m| m.trim()).filter(|m| !m.is_empty()) {
+                let id = crate::proxy::common::model_mapping::normalize_to_standard_id(model)
+                    .ok_or_else(|| format!("Invalid protected model: {}", model))?;
+                out.insert(id);
+            }
+            Some(out)
+        }
+        None => None,
+    };
+
+    if let Some(name) = name {
+        account.name = name;
+    }
+    if let Some(notes) = notes {
+        account.notes = notes;
+    }
+    if let Some(tags) = tags {
+        account.tags = tags;
+    }
+    if let Some(protected_models) = protected_models {
+        account.protected_models = protected_models;
+    }
+    Ok(())
+}
+
+/// [NEW] 部分更新账号 (名称 / 备注 / 标签 / 受保护模型)，同步账号索引并通知 TokenManager 重新加载
+pub fn patch_account(account_id: &str, patch: PatchAccountRequest) -> Result<Account, String> {
+    let mut account = load_account(account_id)?;
+    apply_account_patch(&mut account, patch)?;
+    save_account(&account)?;
+
+    {
+        let _lock = ACCOUNT_INDEX_LOCK
+            .lock()
+            .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+        let mut index = load_account_index()?;
+        if let Some(summary) = index.accounts.iter_mut().find(|s| s.id == account_id) {
+            summary.name = account.name.clone();
+            summary.notes = account.notes.clone();
+            save_account_index(&index)?;
+        }
+    }
+
+    crate::proxy::server::trigger_account_reload(account_id);
+    Ok(account)
+}
+
+/// 记录账号最近一次预热的结果
+pub fn record_warmup_result(account_id: &str, result: &Result<String, String>) -> Result<(), String> {
+    let mut account = load_account(account_id)?;
+    account.last_warmup = Some(crate::models::WarmupRecord {
+        at: chrono::Utc::now().timestamp(),
+        ok: result.is_ok(),
+        message: match result {
+            Ok(msg) | Err(msg) => msg.clone(),
+        },
+    });
+    save_account(&account)
+}
+
+/// 列出所有账号中使用过的标签 (去重并排序)
+pub fn list_all_tags() -> Result<Vec<String>, String> {
+    let mut tags: Vec<String> = Vec::new();
+    for account in list_accounts()? {
+        for tag in account.tags {
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+                tags.push(tag);
+            }
+        }
+    }
+    tags.sort_by_key(|t| t.to_lowercase());
+    Ok(tags)
+}
+
 /// Export accounts by IDs (for backup/migration)
 pub fn export_accounts_by_ids(account_ids: &[String]) -> Result<crate::models::AccountExportResponse, String> {
     use crate::models::{AccountExportItem, AccountExportResponse};
@@ -989,109 +1673,152 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
 
 #[derive(Serialize)]
 pub struct RefreshStats {
+    /// 账号总数 (= success + failed + skipped)
     pub total: usize,
     pub success: usize,
     pub failed: usize,
+    pub skipped: usize,
+    /// 失败原因 (兼容旧版: 每个失败账号一条)
     pub details: Vec<String>,
+    /// [NEW] 每个账号的刷新结果
+    pub results: Vec<AccountRefreshResult>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RefreshOutcome {
+    Success,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountRefreshResult {
+    pub account_id: String,
+    pub email: String,
+    pub status: RefreshOutcome,
+    /// 失败或跳过的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// 批量刷新时跳过的账号及原因
+fn refresh_skip_reason(account: &Account) -> Option<&'static str> {
+    if account.disabled {
+        Some("Disabled")
+    } else if account.proxy_disabled {
+        Some("Proxy Disabled")
+    } else if account.quota.as_ref().is_some_and(|q| q.is_forbidden) {
+        Some("Forbidden")
+    } else {
+        None
+    }
+}
+
+/// 刷新单个账号配额并保存
+async fn refresh_and_save_quota(account: &mut Account) -> Result<(), String> {
+    let quota = fetch_quota_with_retry(account)
+        .await
+        .map_err(|e| format!("Fetch quota failed - {}", e))?;
+    update_account_quota(&account.id, quota).map_err(|e| format!("Save quota failed - {}", e))
 }
 
 /// Core logic to batch refresh all account quotas (decoupled from Tauri status)
+/// 并发数由 AppConfig.quota_refresh_concurrency 控制，失败的账号会重试一次，单个失败不影响整体
 pub async fn refresh_all_quotas_logic() -> Result<RefreshStats, String> {
     use futures::future::join_all;
     use std::sync::Arc;
     use tokio::sync::Semaphore;
 
-    const MAX_CONCURRENT: usize = 5;
+    let max_concurrent = crate::modules::config::load_app_config()
+        .map(|c| c.quota_refresh_concurrency)
+        .unwrap_or(crate::models::config::DEFAULT_QUOTA_REFRESH_CONCURRENCY)
+        .max(1);
     let start = std::time::Instant::now();
 
     crate::modules::logger::log_info(&format!(
         "Starting batch refresh of all account quotas (Concurrent mode, max: {})",
-        MAX_CONCURRENT
+        max_concurrent
     ));
     let accounts = list_accounts()?;
+    let total = accounts.len();
 
-    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT));
+    let semaphore = Arc::new(Semaphore::new(max_concurrent));
+    let mut results = Vec::with_capacity(total);
 
-    let tasks: Vec<_> = accounts
-        .into_iter()
-        .filter(|account| {
-            if account.disabled || account.proxy_disabled {
-                crate::modules::logger::log_info(&format!(
-                    "  - Skipping {} ({})",
-                    account.email,
-                    if account.disabled { "Disabled" } else { "Proxy Disabled" }
-                ));
-                return false;
-            }
-            // [FIX] 检查 proxy_disabled 状态
-            if account.proxy_disabled {
-                crate::modules::logger::log_info(&format!(
-                    "  - Skipping {} (Proxy Disabled)",
-                    account.email
+    let mut tasks = Vec::new();
+    for mut account in accounts {
+        if let Some(reason) = refresh_skip_reason(&account) {
+            crate::modules::logger::log_info(&format!(
+                "  - Skipping {} ({})",
+                account.email, reason
+            ));
+            results.push(AccountRefreshResult {
+                account_id: account.id.clone(),
+                email: account.email.clone(),
+                status: RefreshOutcome::Skipped,
+                reason: Some(reason.to_string()),
+            });
+            continue;
+        }
+
+        let permit = semaphore.clone();
+        tasks.push(async move {
+            let _guard = permit.acquire().await.unwrap();
+            crate::modules::logger::log_info(&format!("  - Processing {}", account.email));
+            let mut outcome = refresh_and_save_quota(&mut account).await;
+            if let Err(e) = &outcome {
+                crate::modules::logger::log_warn(&format!(
+                    "    ⚠️ {} failed ({}), retrying once",
+                    account.email, e
                 ));
-                return false;
+                tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+                outcome = refresh_and_save_quota(&mut account).await;
             }
-            if let Some(ref q) = account.quota {
-                if q.is_forbidden {
-                    crate::modules::logger::log_info(&format!(
-                        "  - Skipping {} (Forbidden)",
-                        account.email
-                    ));
-                    return false;
-                }
-            }
-            true
-        })
-        .map(|mut account| {
-            let email = account.email.clone();
-            let account_id = account.id.clone();
-            let permit = semaphore.clone();
-            async move {
-                let _guard = permit.acquire().await.unwrap();
-                crate::modules::logger::log_info(&format!("  - Processing {}", email));
-                match fetch_quota_with_retry(&mut account).await {
-                    Ok(quota) => {
-                        if let Err(e) = update_account_quota(&account_id, quota) {
-                            let msg = format!("Account {}: Save quota failed - {}", email, e);
-                            crate::modules::logger::log_error(&msg);
-                            Err(msg)
-                        } else {
-                            crate::modules::logger::log_info(&format!("    ✅ {} Success", email));
-                            Ok(())
-                        }
+            match outcome {
+                Ok(()) => {
+                    crate::modules::logger::log_info(&format!("    ✅ {} Success", account.email));
+                    AccountRefreshResult {
+                        account_id: account.id,
+                        email: account.email,
+                        status: RefreshOutcome::Success,
+                        reason: None,
                     }
-                    Err(e) => {
-                        let msg = format!("Account {}: Fetch quota failed - {}", email, e);
-                        crate::modules::logger::log_error(&msg);
-                        Err(msg)
+                }
+                Err(e) => {
+                    crate::modules::logger::log_error(&format!(
+                        "Account {}: {}",
+                        account.email, e
+                    ));
+                    AccountRefreshResult {
+                        account_id: account.id,
+                        email: account.email,
+                        status: RefreshOutcome::Failed,
+                        reason: Some(e),
                     }
                 }
             }
-        })
-        .collect();
-
-    let total = tasks.len();
-    let results = join_all(tasks).await;
+        });
+    }
 
-    let mut success = 0;
-    let mut failed = 0;
-    let mut details = Vec::new();
+    results.extend(join_all(tasks).await);
 
-    for result in results {
-        match result {
-            Ok(()) => success += 1,
-            Err(msg) => {
-                failed += 1;
-                details.push(msg);
-            }
-        }
-    }
+    let count = |status: RefreshOutcome| results.iter().filter(|r| r.status == status).count();
+    let success = count(RefreshOutcome::Success);
+    let failed = count(RefreshOutcome::Failed);
+    let skipped = count(RefreshOutcome::Skipped);
+    let details = results
+        .iter()
+        .filter(|r| r.status == RefreshOutcome::Failed)
+        .map(|r| format!("Account {}: {}", r.email, r.reason.as_deref().unwrap_or_default()))
+        .collect();
 
     let elapsed = start.elapsed();
     crate::modules::logger::log_info(&format!(
-        "Batch refresh completed: {} success, {} failed, took: {}ms",
+        "Batch refresh completed: {} success, {} failed, {} skipped, took: {}ms",
         success,
         failed,
+        skipped,
         elapsed.as_millis()
     ));
 
@@ -1104,7 +1831,9 @@ pub async fn refresh_all_quotas_logic() -> Result<RefreshStats, String> {
         total,
         success,
         failed,
+        skipped,
         details,
+        results,
     })
 }
 
@@ -1141,3 +1870,272 @@ pub async fn check_and_trigger_warmup_for_recovered_models() {
         crate::modules::scheduler::trigger_warmup_for_account(&account).await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_template_overrides_only_given_fields() {
+        let template = DeviceProfileTemplate {
+            machine_id: Some("auth0|user_fixed".to_string()),
+            sqm_id: Some("  ".to_string()),
+            ..Default::default()
+        };
+        let a = template.build();
+        let b = template.build();
+        assert_eq!(a.machine_id, "auth0|user_fixed");
+        assert_eq!(b.machine_id, "auth0|user_fixed");
+        // 未覆盖 (或为空白) 的字段按账号独立生成
+        assert_ne!(a.dev_device_id, b.dev_device_id);
+        assert_ne!(a.sqm_id, b.sqm_id);
+    }
+
+    #[test]
+    fn test_bulk_bind_targets_dedup_and_require_ids() {
+        let request = BulkDeviceBindRequest {
+            account_ids: vec!["a".to_string(), " a ".to_string(), "b".to_string(), "".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(resolve_bulk_bind_targets(&request).unwrap(), vec!["a", "b"]);
+        assert!(resolve_bulk_bind_targets(&BulkDeviceBindRequest::default()).is_err());
+    }
+
+    #[test]
+    fn test_patch_distinguishes_null_from_absent() {
+        let mut account = test_account("a@example.com", 0, None, false);
+        account.name = Some("Old".to_string());
+        account.notes = Some("keep".to_string());
+
+        let patch: PatchAccountRequest =
+            serde_json::from_str(r#"{"name": "  Work  ", "tags": ["a", "A", " b "]}"#).unwrap();
+        apply_account_patch(&mut account, patch).unwrap();
+        assert_eq!(account.name.as_deref(), Some("Work"));
+        assert_eq!(account.notes.as_deref(), Some("keep"));
+        assert_eq!(account.tags, vec!["a", "b"]);
+
+        let patch: PatchAccountRequest =
+            serde_json::from_str(r#"{"name": null, "protected_models": ["gemini-3-flash"]}"#).unwrap();
+        apply_account_patch(&mut account, patch).unwrap();
+        assert!(account.name.is_none());
+        assert!(account.protected_models.contains("gemini-3-flash"));
+
+        // 校验失败时不做任何修改
+        let patch: PatchAccountRequest =
+            serde_json::from_str(r#"{"notes": null, "protected_models": ["unknown-model"]}"#).unwrap();
+        assert!(apply_account_patch(&mut account, patch).is_err());
+        assert_eq!(account.notes.as_deref(), Some("keep"));
+    }
+
+    fn test_account(email: &str, last_used: i64, quota: Option<i32>, disabled: bool) -> Account {
+        let mut account = Account::new(
+            email.to_string(),
+            email.to_string(),
+            TokenData::new(String::new(), String::new(), 3600, None, None, None),
+        );
+        account.last_used = last_used;
+        account.created_at = last_used;
+        account.disabled = disabled;
+        account.quota = quota.map(|pct| {
+            let mut q = QuotaData::new();
+            q.add_model("gemini-2.5-pro".to_string(), pct, String::new());
+            q
+        });
+        account
+    }
+
+    #[test]
+    fn test_last_activity_counts_proxy_usage() {
+        let now = 1_704_067_200;
+        let account = test_account("a@x.com", now - 30 * 86400, None, false);
+        // 仅切换记录时按 30 天前计算；近期有反代用量则视为活跃
+        assert_eq!(last_activity(&account, None), now - 30 * 86400);
+        assert_eq!(last_activity(&account, Some(now - 3600)), now - 3600);
+        // 更早的反代用量不会把活跃时间往前推
+        assert_eq!(last_activity(&account, Some(now - 90 * 86400)), now - 30 * 86400);
+    }
+
+    fn emails(accounts: &[Account]) -> Vec<&str> {
+        accounts.iter().map(|a| a.email.as_str()).collect()
+    }
+
+    #[test]
+    fn test_refresh_skip_reason() {
+        let mut account = test_account("a@x.com", 0, Some(50), false);
+        assert_eq!(refresh_skip_reason(&account), None);
+
+        account.quota.as_mut().unwrap().is_forbidden = true;
+        assert_eq!(refresh_skip_reason(&account), Some("Forbidden"));
+        account.proxy_disabled = true;
+        assert_eq!(refresh_skip_reason(&account), Some("Proxy Disabled"));
+        account.disabled = true;
+        assert_eq!(refresh_skip_reason(&account), Some("Disabled"));
+    }
+
+    fn list_fixture() -> Vec<Account> {
+        vec![
+            test_account("carol@x.com", 300, Some(80), false),
+            test_account("alice@x.com", 100, None, false),
+            test_account("bob@y.com", 200, Some(10), true),
+            test_account("dave@x.com", 400, Some(50), false),
+        ]
+    }
+
+    #[test]
+    fn test_list_options_default_keeps_order() {
+        let (total, page) = apply_list_options(list_fixture(), &AccountListOptions::default());
+        assert_eq!(total, 4);
+        assert_eq!(emails(&page), vec!["carol@x.com", "alice@x.com", "bob@y.com", "dave@x.com"]);
+    }
+
+    #[test]
+    fn test_list_options_sorting() {
+        let sorted = |sort, order| {
+            let options = AccountListOptions {
+                sort: Some(sort),
+                order,
+                ..Default::default()
+            };
+            apply_list_options(list_fixture(), &options).1
+        };
+        assert_eq!(
+            emails(&sorted(AccountSortKey::Email, None)),
+            vec!["alice@x.com", "bob@y.com", "carol@x.com", "dave@x.com"]
+        );
+        assert_eq!(
+            emails(&sorted(AccountSortKey::LastUsed, None)),
+            vec!["dave@x.com", "carol@x.com", "bob@y.com", "alice@x.com"]
+        );
+        assert_eq!(
+            emails(&sorted(AccountSortKey::Created, Some(SortOrder::Asc))),
+            vec!["alice@x.com", "bob@y.com", "carol@x.com", "dave@x.com"]
+        );
+        // 无配额数据的账号始终排在最后
+        assert_eq!(
+            emails(&sorted(AccountSortKey::QuotaMin, None)),
+            vec!["bob@y.com", "dave@x.com", "carol@x.com", "alice@x.com"]
+        );
+        assert_eq!(
+            emails(&sorted(AccountSortKey::QuotaMin, Some(SortOrder::Desc))),
+            vec!["carol@x.com", "dave@x.com", "bob@y.com", "alice@x.com"]
+        );
+    }
+
+    #[test]
+    fn test_list_options_filter_and_page() {
+        let options = AccountListOptions {
+            disabled: Some(false),
+            sort: Some(AccountSortKey::QuotaMin),
+            limit: Some(1),
+            offset: Some(1),
+            ..Default::default()
+        };
+        let (total, page) = apply_list_options(list_fixture(), &options);
+        // 筛选后: dave(50) carol(80) alice(无配额)，第二页 1 条
+        assert_eq!(total, 3);
+        assert_eq!(emails(&page), vec!["carol@x.com"]);
+
+        let disabled_only = AccountListOptions {
+            disabled: Some(true),
+            ..Default::default()
+        };
+        let (total, page) = apply_list_options(list_fixture(), &disabled_only);
+        assert_eq!(total, 1);
+        assert_eq!(emails(&page), vec!["bob@y.com"]);
+
+        let mut fixture = list_fixture();
+        fixture[0].quota.as_mut().unwrap().is_forbidden = true;
+        fixture[3].proxy_disabled = true;
+        let by_status = |status| {
+            let options = AccountListOptions {
+                status: Some(status),
+                ..Default::default()
+            };
+            emails(&apply_list_options(fixture.clone(), &options).1)
+                .into_iter()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(by_status(AccountStatusFilter::Active), vec!["alice@x.com"]);
+        assert_eq!(by_status(AccountStatusFilter::Disabled), vec!["bob@y.com", "dave@x.com"]);
+        assert_eq!(by_status(AccountStatusFilter::Forbidden), vec!["carol@x.com"]);
+
+        let past_end = AccountListOptions {
+            offset: Some(10),
+            ..Default::default()
+        };
+        let (total, page) = apply_list_options(list_fixture(), &past_end);
+        assert_eq!(total, 4);
+        assert!(page.is_empty());
+    }
+
+    #[test]
+    fn test_proxy_disable_history() {
+        let mut account = test_account("a@x.com", 0, None, false);
+        account.set_proxy_disabled(true, Some("quota".to_string()), 100);
+        // 已禁用时再次禁用: 旧原因保留在历史中
+        account.set_proxy_disabled(true, Some("manual".to_string()), 150);
+        account.set_proxy_disabled(false, None, 200);
+
+        assert!(!account.proxy_disabled);
+        assert_eq!(account.proxy_disabled_reason, None);
+        let history = &account.proxy_disable_history;
+        assert_eq!(history.len(), 2);
+        assert_eq!((history[0].reason.as_str(), history[0].re_enabled_at), ("quota", None));
+        assert_eq!((history[1].reason.as_str(), history[1].re_enabled_at), ("manual", Some(200)));
+
+        for i in 0..20 {
+            account.set_proxy_disabled(true, Some(format!("r{}", i)), 300 + i);
+            account.set_proxy_disabled(false, None, 301 + i);
+        }
+        assert_eq!(account.proxy_disable_history.len(), crate::models::account::PROXY_DISABLE_HISTORY_LIMIT);
+        assert_eq!(account.proxy_disable_history.last().unwrap().reason, "r19");
+    }
+
+    #[test]
+    fn test_proxy_enable_backfills_legacy_state() {
+        let mut account = test_account("a@x.com", 0, None, false);
+        account.proxy_disabled = true;
+        account.proxy_disabled_reason = Some("idle".to_string());
+        account.proxy_disabled_at = Some(50);
+
+        account.set_proxy_disabled(false, None, 80);
+        assert_eq!(
+            account.proxy_disable_history,
+            vec![crate::models::ProxyDisableEvent {
+                reason: "idle".to_string(),
+                disabled_at: 50,
+                re_enabled_at: Some(80),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_normalize_notes() {
+        assert_eq!(normalize_notes(None).unwrap(), None);
+        assert_eq!(normalize_notes(Some("   ")).unwrap(), None);
+        assert_eq!(
+            normalize_notes(Some("  Work account, Pro plan  ")).unwrap().as_deref(),
+            Some("Work account, Pro plan")
+        );
+        assert!(normalize_notes(Some(&"x".repeat(MAX_NOTES_LEN + 1))).is_err());
+    }
+
+    #[test]
+    fn test_summary_search_matches_email_and_notes() {
+        let summary = AccountSummary {
+            id: "1".to_string(),
+            email: "Alice@Example.com".to_string(),
+            name: None,
+            disabled: false,
+            proxy_disabled: false,
+            created_at: 0,
+            last_used: 0,
+            notes: Some("Claude Pro plan, expires 2025-12".to_string()),
+        };
+        assert!(summary.matches_search("alice@"));
+        assert!(summary.matches_search("claude pro"));
+        assert!(summary.matches_search(""));
+        assert!(!summary.matches_search("bob"));
+    }
+}
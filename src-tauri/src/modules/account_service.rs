@@ -95,6 +95,11 @@ impl AccountService {
         modules::list_accounts()
     }
 
+    /// 按邮箱或备注搜索账号
+    pub fn search_accounts(&self, keyword: &str) -> Result<Vec<Account>, String> {
+        modules::account::search_accounts(keyword)
+    }
+
     /// 获取当前 ID
     pub fn get_current_id(&self) -> Result<Option<String>, String> {
         modules::get_current_account_id()
@@ -0,0 +1,386 @@
+//! Admin Users Module
+//! Web 管理后台多用户: argon2 哈希密码 + 角色 (viewer / operator / admin)。
+//! 存放于 admin_users.db；旧的 admin_password / api_key 鉴权仍作为完整管理员权限保留
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::http::Method;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const MIN_PASSWORD_LEN: usize = 8;
+const MAX_USERNAME_LEN: usize = 32;
+
+/// 所有角色均可访问的自助接口 (去掉 /api 前缀后的路径)
+const SELF_SERVICE_PATHS: &[&str] = &["/auth/me", "/auth/password", "/auth/logout"];
+
+/// operator 只读的路由前缀 (账号管理)
+const ACCOUNT_PREFIX: &str = "/accounts";
+
+/// 仅 admin 可访问的路由前缀 (管理用户)
+const USER_ADMIN_PREFIX: &str = "/admin/users";
+
+/// 仅 admin 可访问的路由前缀 (涉及凭据或整体替换数据，任何方法)
+const ADMIN_ONLY_PREFIXES: &[&str] = &["/proxy/api-key", "/proxy/keys", "/accounts/import"];
+
+/// 仅 admin 可写的路径 (operator 仍可读取)
+const ADMIN_ONLY_WRITE_PATHS: &[&str] = &["/config", "/config/reload"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AdminRole {
+    /// 只读
+    Viewer,
+    /// 可管理反代与运行时配置，不能管理账号
+    Operator,
+    /// 全部权限
+    Admin,
+}
+
+impl AdminRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdminRole::Viewer => "viewer",
+            AdminRole::Operator => "operator",
+            AdminRole::Admin => "admin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "viewer" => Some(AdminRole::Viewer),
+            "operator" => Some(AdminRole::Operator),
+            "admin" => Some(AdminRole::Admin),
+            _ => None,
+        }
+    }
+
+    /// 判断角色能否访问管理路由；`path` 可带或不带 /api 前缀
+    pub fn permits(&self, method: &Method, path: &str) -> bool {
+        let path = path.strip_prefix("/api").unwrap_or(path);
+        if SELF_SERVICE_PATHS.contains(&path) {
+            return true;
+        }
+        if path.starts_with(USER_ADMIN_PREFIX)
+            || ADMIN_ONLY_PREFIXES.iter().any(|prefix| path.starts_with(prefix))
+        {
+            return *self == AdminRole::Admin;
+        }
+        let read_only = method == Method::GET || method == Method::HEAD;
+        // 写配置与从备份恢复会覆盖凭据，仅 admin 可执行
+        let admin_only_write = ADMIN_ONLY_WRITE_PATHS.contains(&path) || is_backup_restore(path);
+        if admin_only_write && !read_only {
+            return *self == AdminRole::Admin;
+        }
+        match self {
+            AdminRole::Admin => true,
+            AdminRole::Viewer => read_only,
+            AdminRole::Operator => read_only || !path.starts_with(ACCOUNT_PREFIX),
+        }
+    }
+}
+
+/// `/system/backups/:name/restore`
+fn is_backup_restore(path: &str) -> bool {
+    path.strip_prefix("/system/backups/")
+        .and_then(|rest| rest.strip_suffix("/restore"))
+        .map(|name| !name.is_empty() && !name.contains('/'))
+        .unwrap_or(false)
+}
+
+/// 管理用户 (不含密码哈希)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdminUser {
+    pub username: String,
+    pub role: AdminRole,
+    /// 修改密码或吊销会话时递增，旧版本的会话令牌随之失效
+    pub session_version: i64,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// 获取数据库路径
+pub fn get_db_path() -> Result<PathBuf, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(data_dir.join("admin_users.db"))
+}
+
+fn connect_db() -> Result<Connection, String> {
+    let db_path = get_db_path()?;
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+fn init_table(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS admin_users (
+            username TEXT PRIMARY KEY,
+            password_hash TEXT NOT NULL,
+            role TEXT NOT NULL,
+            session_version INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 初始化管理用户数据库
+pub fn init_db() -> Result<(), String> {
+    let conn = connect_db()?;
+    init_table(&conn)
+}
+
+fn validate_username(username: &str) -> Result<(), String> {
+    if username.is_empty() || username.chars().count() > MAX_USERNAME_LEN {
+        return Err(format!("username must be 1-{} characters", MAX_USERNAME_LEN));
+    }
+    if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        return Err("username may only contain letters, digits, '_', '-' and '.'".to_string());
+    }
+    Ok(())
+}
+
+fn hash_password(password: &str) -> Result<String, String> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(format!("password must be at least {} characters", MIN_PASSWORD_LEN));
+    }
+    let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>()).map_err(|e| e.to_string())?;
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("Failed to hash password: {}", e))
+}
+
+fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &parsed)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+fn row_to_user(row: &rusqlite::Row) -> rusqlite::Result<AdminUser> {
+    let role: String = row.get(1)?;
+    Ok(AdminUser {
+        username: row.get(0)?,
+        // 未知角色按最小权限处理
+        role: AdminRole::parse(&role).unwrap_or(AdminRole::Viewer),
+        session_version: row.get(2)?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
+
+const USER_COLUMNS: &str = "username, role, session_version, created_at, updated_at";
+
+fn insert_user(
+    conn: &Connection,
+    username: &str,
+    password: &str,
+    role: AdminRole,
+    now: i64,
+) -> Result<AdminUser, String> {
+    validate_username(username)?;
+    let hash = hash_password(password)?;
+    let inserted = conn
+        .execute(
+            "INSERT OR IGNORE INTO admin_users (username, password_hash, role, session_version, created_at, updated_at)
+             VALUES (?1, ?2, ?3, 0, ?4, ?4)",
+            params![username, hash, role.as_str(), now],
+        )
+        .map_err(|e| e.to_string())?;
+    if inserted == 0 {
+        return Err(format!("user '{}' already exists", username));
+    }
+    Ok(AdminUser {
+        username: username.to_string(),
+        role,
+        session_version: 0,
+        created_at: now,
+        updated_at: now,
+    })
+}
+
+fn find_user(conn: &Connection, username: &str) -> Result<Option<AdminUser>, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM admin_users WHERE username = ?1", USER_COLUMNS),
+        params![username],
+        row_to_user,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+fn all_users(conn: &Connection) -> Result<Vec<AdminUser>, String> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM admin_users ORDER BY created_at, username", USER_COLUMNS))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], row_to_user).map_err(|e| e.to_string())?;
+    let users = rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string());
+    users
+}
+
+fn check_credentials(conn: &Connection, username: &str, password: &str) -> Result<Option<AdminUser>, String> {
+    let hash: Option<String> = conn
+        .query_row(
+            "SELECT password_hash FROM admin_users WHERE username = ?1",
+            params![username],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    match hash {
+        Some(hash) if verify_password(password, &hash) => find_user(conn, username),
+        _ => Ok(None),
+    }
+}
+
+fn update_password(conn: &Connection, username: &str, password: &str, now: i64) -> Result<bool, String> {
+    let hash = hash_password(password)?;
+    conn.execute(
+        "UPDATE admin_users SET password_hash = ?2, session_version = session_version + 1, updated_at = ?3
+         WHERE username = ?1",
+        params![username, hash, now],
+    )
+    .map(|n| n > 0)
+    .map_err(|e| e.to_string())
+}
+
+fn bump_session_version(conn: &Connection, username: &str, now: i64) -> Result<bool, String> {
+    conn.execute(
+        "UPDATE admin_users SET session_version = session_version + 1, updated_at = ?2 WHERE username = ?1",
+        params![username, now],
+    )
+    .map(|n| n > 0)
+    .map_err(|e| e.to_string())
+}
+
+fn update_role(conn: &Connection, username: &str, role: AdminRole, now: i64) -> Result<bool, String> {
+    conn.execute(
+        "UPDATE admin_users SET role = ?2, updated_at = ?3 WHERE username = ?1",
+        params![username, role.as_str(), now],
+    )
+    .map(|n| n > 0)
+    .map_err(|e| e.to_string())
+}
+
+fn remove_user(conn: &Connection, username: &str) -> Result<bool, String> {
+    conn.execute("DELETE FROM admin_users WHERE username = ?1", params![username])
+        .map(|n| n > 0)
+        .map_err(|e| e.to_string())
+}
+
+pub fn create_user(username: &str, password: &str, role: AdminRole) -> Result<AdminUser, String> {
+    let conn = connect_db()?;
+    insert_user(&conn, username, password, role, chrono::Utc::now().timestamp())
+}
+
+pub fn list_users() -> Result<Vec<AdminUser>, String> {
+    all_users(&connect_db()?)
+}
+
+pub fn get_user(username: &str) -> Result<Option<AdminUser>, String> {
+    find_user(&connect_db()?, username)
+}
+
+/// 校验用户名与密码，成功时返回用户
+pub fn authenticate(username: &str, password: &str) -> Result<Option<AdminUser>, String> {
+    check_credentials(&connect_db()?, username, password)
+}
+
+/// 修改密码并使该用户现有会话全部失效
+pub fn set_password(username: &str, password: &str) -> Result<bool, String> {
+    update_password(&connect_db()?, username, password, chrono::Utc::now().timestamp())
+}
+
+/// 吊销该用户的全部会话
+pub fn revoke_sessions(username: &str) -> Result<bool, String> {
+    bump_session_version(&connect_db()?, username, chrono::Utc::now().timestamp())
+}
+
+pub fn set_role(username: &str, role: AdminRole) -> Result<bool, String> {
+    update_role(&connect_db()?, username, role, chrono::Utc::now().timestamp())
+}
+
+pub fn delete_user(username: &str) -> Result<bool, String> {
+    remove_user(&connect_db()?, username)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_permissions() {
+        let get = Method::GET;
+        let post = Method::POST;
+        assert!(AdminRole::Viewer.permits(&get, "/api/accounts"));
+        assert!(!AdminRole::Viewer.permits(&post, "/proxy/mapping"));
+        assert!(AdminRole::Viewer.permits(&post, "/auth/password"));
+
+        assert!(AdminRole::Operator.permits(&post, "/proxy/mapping"));
+        assert!(AdminRole::Operator.permits(&get, "/accounts"));
+        assert!(!AdminRole::Operator.permits(&Method::DELETE, "/accounts/acc-1"));
+        assert!(!AdminRole::Operator.permits(&post, "/api/admin/users"));
+        assert!(!AdminRole::Viewer.permits(&get, "/admin/users"));
+
+        assert!(AdminRole::Admin.permits(&Method::DELETE, "/accounts/acc-1"));
+
+        // 凭据、配置写入、备份恢复与账号导入仅 admin
+        assert!(AdminRole::Operator.permits(&get, "/config"));
+        assert!(!AdminRole::Operator.permits(&post, "/config"));
+        assert!(!AdminRole::Operator.permits(&post, "/api/config/reload"));
+        assert!(AdminRole::Operator.permits(&post, "/config/validate"));
+        assert!(!AdminRole::Operator.permits(&get, "/proxy/keys"));
+        assert!(!AdminRole::Operator.permits(&Method::DELETE, "/proxy/keys/ci"));
+        assert!(!AdminRole::Operator.permits(&post, "/proxy/api-key/rotate"));
+        assert!(!AdminRole::Viewer.permits(&get, "/proxy/api-key/rotation-status"));
+        assert!(!AdminRole::Operator.permits(&post, "/system/backups/2024.json/restore"));
+        assert!(AdminRole::Operator.permits(&get, "/system/backups"));
+        assert!(!AdminRole::Operator.permits(&post, "/accounts/import/db"));
+        assert!(AdminRole::Admin.permits(&post, "/system/backups/2024.json/restore"));
+        assert!(AdminRole::Admin.permits(&post, "/proxy/keys"));
+        assert_eq!(AdminRole::parse("operator"), Some(AdminRole::Operator));
+        assert_eq!(AdminRole::parse("root"), None);
+    }
+
+    #[test]
+    fn test_user_lifecycle() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_table(&conn).unwrap();
+
+        let user = insert_user(&conn, "alice", "correct horse", AdminRole::Operator, 100).unwrap();
+        assert_eq!(user.session_version, 0);
+        assert!(insert_user(&conn, "alice", "correct horse", AdminRole::Admin, 100).is_err());
+        assert!(insert_user(&conn, "bob", "short", AdminRole::Viewer, 100).is_err());
+        assert!(insert_user(&conn, "bad name", "correct horse", AdminRole::Viewer, 100).is_err());
+
+        assert_eq!(check_credentials(&conn, "alice", "correct horse").unwrap(), Some(user));
+        assert_eq!(check_credentials(&conn, "alice", "wrong password").unwrap(), None);
+        assert_eq!(check_credentials(&conn, "nobody", "correct horse").unwrap(), None);
+
+        // 修改密码与吊销会话均递增 session_version
+        assert!(update_password(&conn, "alice", "battery staple", 200).unwrap());
+        assert!(bump_session_version(&conn, "alice", 300).unwrap());
+        assert!(update_role(&conn, "alice", AdminRole::Admin, 300).unwrap());
+        let alice = check_credentials(&conn, "alice", "battery staple").unwrap().unwrap();
+        assert_eq!(alice.session_version, 2);
+        assert_eq!(alice.role, AdminRole::Admin);
+
+        assert_eq!(all_users(&conn).unwrap().len(), 1);
+        assert!(remove_user(&conn, "alice").unwrap());
+        assert!(!remove_user(&conn, "alice").unwrap());
+        assert_eq!(find_user(&conn, "alice").unwrap(), None);
+    }
+}
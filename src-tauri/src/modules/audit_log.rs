@@ -0,0 +1,349 @@
+//! Audit Log Module
+//! 管理操作审计日志: 记录谁 (actor_ip) 在何时修改了什么。
+//! 独立存放于 audit.db，不受 clear_logs / 日志清理影响
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// 写入审计日志前需要脱敏的字段名
+const SECRET_KEYS: &[&str] = &[
+    "api_key",
+    "api_key_previous",
+    "api_keys",
+    "key",
+    "admin_password",
+    "admin_session_secret",
+    "auth_header",
+    "refresh_token",
+    "access_token",
+    "password",
+    "secret",
+    "token",
+];
+
+/// 审计记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditLogEntry {
+    pub id: i64,
+    pub timestamp: i64,
+    pub action: String,
+    pub actor_ip: String,
+    pub target: Option<String>,
+    pub old_value_json: Option<Value>,
+    pub new_value_json: Option<Value>,
+}
+
+/// 分页查询结果
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditLogPage {
+    pub total: u64,
+    pub items: Vec<AuditLogEntry>,
+}
+
+/// 获取审计数据库路径
+pub fn get_audit_db_path() -> Result<PathBuf, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(data_dir.join("audit.db"))
+}
+
+/// 连接数据库
+fn connect_db() -> Result<Connection, String> {
+    let db_path = get_audit_db_path()?;
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .map_err(|e| e.to_string())?;
+
+    Ok(conn)
+}
+
+fn init_table(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp INTEGER NOT NULL,
+            action TEXT NOT NULL,
+            actor_ip TEXT NOT NULL,
+            target TEXT,
+            old_value_json TEXT,
+            new_value_json TEXT
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_audit_timestamp ON audit_log (timestamp DESC)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_audit_action ON audit_log (action)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// 初始化审计数据库
+pub fn init_db() -> Result<(), String> {
+    let conn = connect_db()?;
+    init_table(&conn)
+}
+
+/// 递归脱敏: 命中 SECRET_KEYS 的非空字符串替换为 "***"
+pub fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                if SECRET_KEYS.contains(&key.as_str()) {
+                    redact_secret(v);
+                } else {
+                    redact(v);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// 敏感字段的值: 字符串直接遮盖；数组 (如 api_keys) 逐项遮盖，对象项继续按字段脱敏
+fn redact_secret(value: &mut Value) {
+    match value {
+        Value::String(s) if !s.is_empty() => *value = Value::String("***".to_string()),
+        Value::Array(items) => items.iter_mut().for_each(redact_secret),
+        Value::Object(_) => redact(value),
+        _ => {}
+    }
+}
+
+/// 只保留新旧值之间发生变化的字段 (按对象逐层比较)，避免每次保存配置都记录整份配置
+pub fn diff_values(old: &Value, new: &Value) -> (Value, Value) {
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut old_out = serde_json::Map::new();
+            let mut new_out = serde_json::Map::new();
+            for key in a.keys().chain(b.keys().filter(|k| !a.contains_key(*k))) {
+                match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) if x == y => {}
+                    (Some(x), Some(y)) => {
+                        let (dx, dy) = diff_values(x, y);
+                        old_out.insert(key.clone(), dx);
+                        new_out.insert(key.clone(), dy);
+                    }
+                    (Some(x), None) => {
+                        old_out.insert(key.clone(), x.clone());
+                    }
+                    (None, Some(y)) => {
+                        new_out.insert(key.clone(), y.clone());
+                    }
+                    (None, None) => {}
+                }
+            }
+            (Value::Object(old_out), Value::Object(new_out))
+        }
+        _ => (old.clone(), new.clone()),
+    }
+}
+
+fn insert_entry(
+    conn: &Connection,
+    timestamp: i64,
+    action: &str,
+    actor_ip: &str,
+    target: Option<&str>,
+    old_value: Option<Value>,
+    new_value: Option<Value>,
+) -> Result<(), String> {
+    let encode = |v: Option<Value>| {
+        v.map(|mut v| {
+            redact(&mut v);
+            v.to_string()
+        })
+    };
+    conn.execute(
+        "INSERT INTO audit_log (timestamp, action, actor_ip, target, old_value_json, new_value_json)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            timestamp,
+            action,
+            actor_ip,
+            target,
+            encode(old_value),
+            encode(new_value)
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 记录一次管理操作。写入失败只记录错误日志，不影响操作本身
+/// 在 tokio 运行时中经 spawn_blocking 写入，不阻塞调用方 (handler) 的异步线程
+pub fn record(
+    action: &str,
+    actor_ip: &str,
+    target: Option<&str>,
+    old_value: Option<Value>,
+    new_value: Option<Value>,
+) {
+    let timestamp = chrono::Utc::now().timestamp();
+    let action = action.to_string();
+    let actor_ip = actor_ip.to_string();
+    let target = target.map(|t| t.to_string());
+    let write = move || {
+        let result = connect_db().and_then(|conn| {
+            insert_entry(
+                &conn,
+                timestamp,
+                &action,
+                &actor_ip,
+                target.as_deref(),
+                old_value,
+                new_value,
+            )
+        });
+        if let Err(e) = result {
+            tracing::error!("[Audit] Failed to record '{}': {}", action, e);
+        }
+    };
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => {
+            handle.spawn_blocking(write);
+        }
+        Err(_) => write(),
+    }
+}
+
+fn query_entries(
+    conn: &Connection,
+    action: Option<&str>,
+    limit: Option<usize>,
+    offset: usize,
+    newest_first: bool,
+) -> Result<Vec<AuditLogEntry>, String> {
+    let sql = format!(
+        "SELECT id, timestamp, action, actor_ip, target, old_value_json, new_value_json
+         FROM audit_log
+         WHERE (?1 IS NULL OR action = ?1)
+         ORDER BY id {}
+         LIMIT ?2 OFFSET ?3",
+        if newest_first { "DESC" } else { "ASC" }
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let decode = |s: Option<String>| s.and_then(|s| serde_json::from_str(&s).ok());
+    let rows = stmt
+        .query_map(
+            params![action, limit.map(|l| l as i64).unwrap_or(-1), offset as i64],
+            |row| {
+                Ok(AuditLogEntry {
+                    id: row.get(0)?,
+                    timestamp: row.get(1)?,
+                    action: row.get(2)?,
+                    actor_ip: row.get(3)?,
+                    target: row.get(4)?,
+                    old_value_json: decode(row.get(5)?),
+                    new_value_json: decode(row.get(6)?),
+                })
+            },
+        )
+        .map_err(|e| e.to_string())?;
+
+    let entries = rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string());
+    entries
+}
+
+fn count_entries(conn: &Connection, action: Option<&str>) -> Result<u64, String> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM audit_log WHERE (?1 IS NULL OR action = ?1)",
+        params![action],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|n| n as u64)
+    .map_err(|e| e.to_string())
+}
+
+/// 分页查询 (最新在前)，可按 action 过滤
+pub fn get_audit_logs(action: Option<&str>, limit: usize, offset: usize) -> Result<AuditLogPage, String> {
+    let conn = connect_db()?;
+    Ok(AuditLogPage {
+        total: count_entries(&conn, action)?,
+        items: query_entries(&conn, action, Some(limit), offset, true)?,
+    })
+}
+
+/// 导出全部记录 (按时间正序)，可按 action 过滤
+pub fn export_audit_logs(action: Option<&str>) -> Result<Vec<AuditLogEntry>, String> {
+    let conn = connect_db()?;
+    query_entries(&conn, action, None, 0, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_keeps_only_changed_fields_and_redacts_secrets() {
+        let old = json!({ "proxy": { "port": 8045, "api_key": "sk-old", "auto_start": false } });
+        let new = json!({ "proxy": { "port": 9000, "api_key": "sk-new", "auto_start": false } });
+        let (mut old_diff, mut new_diff) = diff_values(&old, &new);
+        redact(&mut old_diff);
+        redact(&mut new_diff);
+        assert_eq!(old_diff, json!({ "proxy": { "port": 8045, "api_key": "***" } }));
+        assert_eq!(new_diff, json!({ "proxy": { "port": 9000, "api_key": "***" } }));
+    }
+
+    #[test]
+    fn test_redacts_api_key_entries() {
+        let mut value = json!({
+            "proxy": {
+                "api_keys": [
+                    { "key": "sk-alice", "label": "alice-laptop", "disabled": false },
+                    { "key": "sk-bob", "label": "bob", "disabled": true }
+                ]
+            },
+            "legacy": { "api_keys": ["sk-1", ""] }
+        });
+        redact(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "proxy": {
+                    "api_keys": [
+                        { "key": "***", "label": "alice-laptop", "disabled": false },
+                        { "key": "***", "label": "bob", "disabled": true }
+                    ]
+                },
+                "legacy": { "api_keys": ["***", ""] }
+            })
+        );
+    }
+
+    #[test]
+    fn test_record_query_and_filter() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_table(&conn).unwrap();
+        insert_entry(&conn, 100, "config.save", "10.0.0.1", None, Some(json!({ "a": 1 })), Some(json!({ "a": 2 }))).unwrap();
+        insert_entry(&conn, 200, "account.delete", "10.0.0.2", Some("acc-1"), None, None).unwrap();
+        insert_entry(&conn, 300, "config.save", "10.0.0.1", None, None, Some(json!({ "refresh_token": "1//x" }))).unwrap();
+
+        let page = query_entries(&conn, None, Some(2), 0, true).unwrap();
+        assert_eq!(page.len(), 2);
+        assert_eq!(page[0].timestamp, 300);
+        assert_eq!(page[0].new_value_json, Some(json!({ "refresh_token": "***" })));
+
+        let saves = query_entries(&conn, Some("config.save"), None, 0, false).unwrap();
+        assert_eq!(saves.len(), 2);
+        assert_eq!(saves[0].old_value_json, Some(json!({ "a": 1 })));
+        assert_eq!(count_entries(&conn, Some("account.delete")).unwrap(), 1);
+        assert_eq!(count_entries(&conn, None).unwrap(), 3);
+    }
+}
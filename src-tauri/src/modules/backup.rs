@@ -0,0 +1,461 @@
+// 配置与账号数据定时备份
+// 将 gui_config.json、accounts.json 与 accounts/ 打包为 data_dir/backups/backup-<时间戳>.zip，
+// 按数量轮换；恢复前完整校验归档内容，再逐项替换现有文件
+use serde::Serialize;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::models::{Account, AccountIndex, AppConfig, BackupConfig};
+use crate::modules::account::{get_data_dir, ACCOUNTS_DIR, ACCOUNTS_INDEX};
+use crate::modules::config::CONFIG_FILE;
+
+const BACKUP_DIR: &str = "backups";
+const BACKUP_PREFIX: &str = "backup-";
+const BACKUP_EXT: &str = ".zip";
+const RESTORE_STAGING_DIR: &str = ".restore-staging";
+
+/// include_databases 开启时额外备份的 SQLite 数据库 (日志/统计体积较大，默认不备份)
+const DATABASE_FILES: [&str; 4] = ["proxy_logs.db", "token_stats.db", "security.db", "user_tokens.db"];
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BackupInfo {
+    pub name: String,
+    pub size: u64,
+    /// 创建时间 (unix 秒)
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RestoreSummary {
+    pub name: String,
+    pub accounts: usize,
+    pub config_restored: bool,
+    pub databases: Vec<String>,
+    /// 恢复前自动创建的当前数据备份
+    pub pre_restore_backup: String,
+}
+
+/// 备份文件名校验: backup-YYYYMMDD-HHMMSS[-n].zip，防止路径穿越
+pub fn is_backup_name(name: &str) -> bool {
+    name.starts_with(BACKUP_PREFIX)
+        && name.ends_with(BACKUP_EXT)
+        && name.len() > BACKUP_PREFIX.len() + BACKUP_EXT.len()
+        && name[..name.len() - BACKUP_EXT.len()]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn backups_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(BACKUP_DIR)
+}
+
+/// 立即创建一次备份
+pub fn create_backup(include_databases: bool) -> Result<BackupInfo, String> {
+    create_backup_in(&get_data_dir()?, include_databases, chrono::Local::now())
+}
+
+pub fn list_backups() -> Result<Vec<BackupInfo>, String> {
+    list_backups_in(&get_data_dir()?)
+}
+
+/// 只保留最近 keep 个备份，返回删除的数量
+pub fn rotate_backups(keep: usize) -> Result<usize, String> {
+    rotate_backups_in(&get_data_dir()?, keep)
+}
+
+/// 距最近一次备份已超过 interval_hours 时创建新备份并轮换；未到期返回 None
+pub fn run_scheduled_backup(config: &BackupConfig) -> Result<Option<BackupInfo>, String> {
+    let data_dir = get_data_dir()?;
+    let now = chrono::Local::now();
+    let latest = list_backups_in(&data_dir)?
+        .iter()
+        .map(|b| b.created_at)
+        .max()
+        .unwrap_or(0);
+    if now.timestamp() - latest < config.interval_hours as i64 * 3600 {
+        return Ok(None);
+    }
+    let info = create_backup_in(&data_dir, config.include_databases, now)?;
+    rotate_backups_in(&data_dir, config.keep)?;
+    Ok(Some(info))
+}
+
+/// 从备份恢复 (调用方需确保反代服务已暂停)
+pub fn restore_backup(name: &str) -> Result<RestoreSummary, String> {
+    restore_backup_in(&get_data_dir()?, name)
+}
+
+fn create_backup_in(
+    data_dir: &Path,
+    include_databases: bool,
+    now: chrono::DateTime<chrono::Local>,
+) -> Result<BackupInfo, String> {
+    let dir = backups_dir(data_dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("failed_to_create_backup_dir: {}", e))?;
+
+    let stem = format!("{}{}", BACKUP_PREFIX, now.format("%Y%m%d-%H%M%S"));
+    let mut name = format!("{}{}", stem, BACKUP_EXT);
+    let mut n = 1;
+    while dir.join(&name).exists() {
+        name = format!("{}-{}{}", stem, n, BACKUP_EXT);
+        n += 1;
+    }
+
+    // 先写临时文件再重命名，列表中不会出现写了一半的归档
+    let tmp_path = dir.join(format!(".{}.tmp", name));
+    let result = write_archive(data_dir, &tmp_path, include_databases);
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+    let path = dir.join(&name);
+    fs::rename(&tmp_path, &path).map_err(|e| format!("failed_to_finalize_backup: {}", e))?;
+
+    let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    Ok(BackupInfo {
+        name,
+        size,
+        created_at: now.timestamp(),
+    })
+}
+
+fn write_archive(data_dir: &Path, target: &Path, include_databases: bool) -> Result<(), String> {
+    use zip::write::SimpleFileOptions;
+
+    let file = fs::File::create(target).map_err(|e| format!("failed_to_create_backup: {}", e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    let add_file = |zip: &mut zip::ZipWriter<fs::File>, entry: &str, path: &Path| -> Result<(), String> {
+        let content = fs::read(path).map_err(|e| format!("failed_to_read {:?}: {}", path, e))?;
+        zip.start_file(entry, options).map_err(|e| e.to_string())?;
+        zip.write_all(&content).map_err(|e| e.to_string())
+    };
+
+    for entry in [CONFIG_FILE, ACCOUNTS_INDEX] {
+        let path = data_dir.join(entry);
+        if path.is_file() {
+            add_file(&mut zip, entry, &path)?;
+        }
+    }
+
+    let accounts_dir = data_dir.join(ACCOUNTS_DIR);
+    if accounts_dir.is_dir() {
+        zip.add_directory(format!("{}/", ACCOUNTS_DIR), options)
+            .map_err(|e| e.to_string())?;
+        let entries = fs::read_dir(&accounts_dir).map_err(|e| format!("failed_to_read_accounts_dir: {}", e))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if path.is_file() && file_name.ends_with(".json") {
+                add_file(&mut zip, &format!("{}/{}", ACCOUNTS_DIR, file_name), &path)?;
+            }
+        }
+    }
+
+    if include_databases {
+        for db in DATABASE_FILES {
+            let path = data_dir.join(db);
+            if !path.is_file() {
+                continue;
+            }
+            // VACUUM INTO 生成一致性快照，避免直接复制正在写入的 WAL 数据库
+            let snapshot = target.with_extension(format!("{}.snapshot", db));
+            let _ = fs::remove_file(&snapshot);
+            let result = rusqlite::Connection::open(&path)
+                .and_then(|conn| {
+                    conn.execute("VACUUM INTO ?1", [snapshot.to_string_lossy().as_ref()])
+                })
+                .map_err(|e| format!("failed_to_snapshot {}: {}", db, e))
+                .and_then(|_| add_file(&mut zip, db, &snapshot));
+            let _ = fs::remove_file(&snapshot);
+            result?;
+        }
+    }
+
+    zip.finish().map_err(|e| format!("failed_to_write_backup: {}", e))?;
+    Ok(())
+}
+
+fn list_backups_in(data_dir: &Path) -> Result<Vec<BackupInfo>, String> {
+    let dir = backups_dir(data_dir);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut backups: Vec<BackupInfo> = fs::read_dir(&dir)
+        .map_err(|e| format!("failed_to_read_backup_dir: {}", e))?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !is_backup_name(&name) {
+                return None;
+            }
+            let metadata = entry.metadata().ok()?;
+            let created_at = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            Some(BackupInfo {
+                name,
+                size: metadata.len(),
+                created_at,
+            })
+        })
+        .collect();
+    // 文件名中的时间戳可直接按字典序排序，最新的在前
+    backups.sort_by(|a, b| b.name.cmp(&a.name));
+    Ok(backups)
+}
+
+fn rotate_backups_in(data_dir: &Path, keep: usize) -> Result<usize, String> {
+    let dir = backups_dir(data_dir);
+    let mut removed = 0;
+    for backup in list_backups_in(data_dir)?.iter().skip(keep.max(1)) {
+        match fs::remove_file(dir.join(&backup.name)) {
+            Ok(_) => removed += 1,
+            Err(e) => tracing::warn!("[Backup] Failed to remove old backup {}: {}", backup.name, e),
+        }
+    }
+    Ok(removed)
+}
+
+/// 解压并校验归档到 staging 目录；任何条目不合法时整体失败
+fn extract_validated(archive_path: &Path, staging: &Path) -> Result<(), String> {
+    let file = fs::File::open(archive_path).map_err(|e| format!("failed_to_open_backup: {}", e))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("invalid_backup_archive: {}", e))?;
+
+    let mut has_index = false;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| format!("invalid_backup_archive: {}", e))?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry
+            .enclosed_name()
+            .and_then(|p| p.to_str().map(|s| s.replace('\\', "/")))
+            .ok_or_else(|| format!("invalid_backup_entry: {}", entry.name()))?;
+        let mut content = Vec::new();
+        entry
+            .read_to_end(&mut content)
+            .map_err(|e| format!("failed_to_read_backup_entry {}: {}", name, e))?;
+
+        let account_file = name
+            .strip_prefix(&format!("{}/", ACCOUNTS_DIR))
+            .filter(|f| f.ends_with(".json") && !f.contains('/'));
+        if name == CONFIG_FILE {
+            serde_json::from_slice::<AppConfig>(&content)
+                .map_err(|e| format!("invalid {} in backup: {}", CONFIG_FILE, e))?;
+        } else if name == ACCOUNTS_INDEX {
+            serde_json::from_slice::<AccountIndex>(&content)
+                .map_err(|e| format!("invalid {} in backup: {}", ACCOUNTS_INDEX, e))?;
+            has_index = true;
+        } else if let Some(file_name) = account_file {
+            serde_json::from_slice::<Account>(&content)
+                .map_err(|e| format!("invalid account file {} in backup: {}", file_name, e))?;
+        } else if DATABASE_FILES.contains(&name.as_str()) {
+            if !content.starts_with(b"SQLite format 3\0") {
+                return Err(format!("invalid database {} in backup", name));
+            }
+        } else {
+            return Err(format!("unexpected_backup_entry: {}", name));
+        }
+
+        let target = staging.join(&name);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(&target, content).map_err(|e| format!("failed_to_stage {}: {}", name, e))?;
+    }
+
+    if !has_index {
+        return Err(format!("backup does not contain {}", ACCOUNTS_INDEX));
+    }
+    Ok(())
+}
+
+/// 用 staging 中的文件替换 data_dir 中的同名文件/目录
+fn swap_in(staging: &Path, data_dir: &Path, name: &str) -> Result<(), String> {
+    let source = staging.join(name);
+    let target = data_dir.join(name);
+    if target.is_dir() {
+        let old = data_dir.join(format!("{}.restore-old", name));
+        let _ = fs::remove_dir_all(&old);
+        fs::rename(&target, &old).map_err(|e| format!("failed_to_replace {}: {}", name, e))?;
+        if let Err(e) = fs::rename(&source, &target) {
+            // 回滚: 放回原目录
+            let _ = fs::rename(&old, &target);
+            return Err(format!("failed_to_replace {}: {}", name, e));
+        }
+        let _ = fs::remove_dir_all(&old);
+        return Ok(());
+    }
+    if target.exists() {
+        fs::remove_file(&target).map_err(|e| format!("failed_to_replace {}: {}", name, e))?;
+    }
+    fs::rename(&source, &target).map_err(|e| format!("failed_to_replace {}: {}", name, e))
+}
+
+fn restore_backup_in(data_dir: &Path, name: &str) -> Result<RestoreSummary, String> {
+    if !is_backup_name(name) {
+        return Err(format!("invalid_backup_name: {}", name));
+    }
+    let archive_path = backups_dir(data_dir).join(name);
+    if !archive_path.is_file() {
+        return Err(format!("backup_not_found: {}", name));
+    }
+
+    let staging = data_dir.join(RESTORE_STAGING_DIR);
+    let _ = fs::remove_dir_all(&staging);
+    fs::create_dir_all(&staging).map_err(|e| e.to_string())?;
+
+    let result = (|| -> Result<RestoreSummary, String> {
+        extract_validated(&archive_path, &staging)?;
+        // 恢复前先备份当前数据，误操作时可再恢复回来
+        let pre_restore = create_backup_in(data_dir, false, chrono::Local::now())?;
+
+        let config_restored = staging.join(CONFIG_FILE).is_file();
+        if config_restored {
+            swap_in(&staging, data_dir, CONFIG_FILE)?;
+        }
+        if !staging.join(ACCOUNTS_DIR).is_dir() {
+            fs::create_dir_all(staging.join(ACCOUNTS_DIR)).map_err(|e| e.to_string())?;
+        }
+        let accounts = fs::read_dir(staging.join(ACCOUNTS_DIR))
+            .map(|entries| entries.count())
+            .unwrap_or(0);
+        swap_in(&staging, data_dir, ACCOUNTS_DIR)?;
+        swap_in(&staging, data_dir, ACCOUNTS_INDEX)?;
+
+        let mut databases = Vec::new();
+        for db in DATABASE_FILES {
+            if !staging.join(db).is_file() {
+                continue;
+            }
+            // 旧的 WAL/SHM 文件属于被替换的数据库，必须一并删除
+            for suffix in ["-wal", "-shm"] {
+                let _ = fs::remove_file(data_dir.join(format!("{}{}", db, suffix)));
+            }
+            swap_in(&staging, data_dir, db)?;
+            databases.push(db.to_string());
+        }
+
+        Ok(RestoreSummary {
+            name: name.to_string(),
+            accounts,
+            config_restored,
+            databases,
+            pre_restore_backup: pre_restore.name,
+        })
+    })();
+
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn temp_data_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("backup-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join(ACCOUNTS_DIR)).unwrap();
+        dir
+    }
+
+    fn write_accounts(data_dir: &Path, emails: &[&str]) {
+        let mut index = AccountIndex::new();
+        let _ = fs::remove_dir_all(data_dir.join(ACCOUNTS_DIR));
+        fs::create_dir_all(data_dir.join(ACCOUNTS_DIR)).unwrap();
+        for email in emails {
+            let account = Account::new(
+                email.to_string(),
+                email.to_string(),
+                crate::models::TokenData::new(String::new(), String::new(), 3600, None, None, None),
+            );
+            fs::write(
+                data_dir.join(ACCOUNTS_DIR).join(format!("{}.json", email)),
+                serde_json::to_string(&account).unwrap(),
+            )
+            .unwrap();
+            index.accounts.push(crate::models::AccountSummary {
+                id: email.to_string(),
+                email: email.to_string(),
+                name: None,
+                disabled: false,
+                proxy_disabled: false,
+                created_at: 0,
+                last_used: 0,
+                notes: None,
+            });
+        }
+        fs::write(data_dir.join(ACCOUNTS_INDEX), serde_json::to_string(&index).unwrap()).unwrap();
+        fs::write(
+            data_dir.join(CONFIG_FILE),
+            serde_json::to_string(&AppConfig::new()).unwrap(),
+        )
+        .unwrap();
+    }
+
+    fn at(secs: u32) -> chrono::DateTime<chrono::Local> {
+        chrono::Local.with_ymd_and_hms(2024, 1, 1, 0, 0, secs).unwrap()
+    }
+
+    #[test]
+    fn test_backup_rotate_and_restore() {
+        let data_dir = temp_data_dir();
+        write_accounts(&data_dir, &["a@x", "b@x"]);
+
+        let first = create_backup_in(&data_dir, false, at(1)).unwrap();
+        create_backup_in(&data_dir, false, at(2)).unwrap();
+        create_backup_in(&data_dir, false, at(3)).unwrap();
+        assert_eq!(rotate_backups_in(&data_dir, 2).unwrap(), 1);
+        let names: Vec<String> = list_backups_in(&data_dir).unwrap().into_iter().map(|b| b.name).collect();
+        assert_eq!(names, vec!["backup-20240101-000003.zip", "backup-20240101-000002.zip"]);
+        assert!(!names.contains(&first.name));
+
+        // 账号文件损坏后从备份恢复
+        write_accounts(&data_dir, &["c@x"]);
+        fs::write(data_dir.join(ACCOUNTS_INDEX), "{ corrupted").unwrap();
+        let summary = restore_backup_in(&data_dir, "backup-20240101-000002.zip").unwrap();
+        assert_eq!(summary.accounts, 2);
+        assert!(summary.config_restored);
+        assert!(data_dir.join(ACCOUNTS_DIR).join("a@x.json").is_file());
+        assert!(!data_dir.join(ACCOUNTS_DIR).join("c@x.json").exists());
+        let index: AccountIndex =
+            serde_json::from_str(&fs::read_to_string(data_dir.join(ACCOUNTS_INDEX)).unwrap()).unwrap();
+        assert_eq!(index.accounts.len(), 2);
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_restore_rejects_invalid_archive() {
+        let data_dir = temp_data_dir();
+        write_accounts(&data_dir, &["a@x"]);
+        let dir = backups_dir(&data_dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        // 账号文件不是合法 JSON: 校验失败，现有数据保持不变
+        let file = fs::File::create(dir.join("backup-20240101-000000.zip")).unwrap();
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file(ACCOUNTS_INDEX, options).unwrap();
+        zip.write_all(br#"{"version":"2.0","accounts":[],"current_account_id":null}"#).unwrap();
+        zip.start_file("accounts/a@x.json", options).unwrap();
+        zip.write_all(b"not json").unwrap();
+        zip.finish().unwrap();
+
+        let err = restore_backup_in(&data_dir, "backup-20240101-000000.zip").unwrap_err();
+        assert!(err.contains("invalid account file"), "{}", err);
+        assert!(data_dir.join(ACCOUNTS_DIR).join("a@x.json").is_file());
+        assert!(!data_dir.join(RESTORE_STAGING_DIR).exists());
+
+        assert!(restore_backup_in(&data_dir, "../gui_config.json").is_err());
+        assert!(!is_backup_name("backup-../../x.zip"));
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+}
@@ -1,11 +1,13 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::process::{Child, Command};
 use tokio::sync::RwLock;
-use tracing::{debug, info};
+use tracing::{debug, error, info, warn};
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
@@ -17,6 +19,18 @@ const DETACHED_PROCESS: u32 = 0x00000008;
 #[cfg(target_os = "windows")]
 const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
 
+/// 进程存活检查间隔
+const MONITOR_INTERVAL: Duration = Duration::from_secs(3);
+/// 意外退出后的重启退避: 2s 起步逐次翻倍，上限 60s
+const RESTART_BASE_DELAY_SECS: u64 = 2;
+const RESTART_MAX_DELAY_SECS: u64 = 60;
+/// 连续重启失败达到该次数后标记隧道失败，不再重试
+const MAX_RESTART_ATTEMPTS: u32 = 8;
+/// 重启后稳定运行超过该时长即清零重试计数
+const RESTART_STABLE_AFTER: Duration = Duration::from_secs(120);
+/// 日志环形缓冲保留的最大行数
+const MAX_LOG_LINES: usize = 500;
+
 /// Cloudflared隧道模式
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -25,6 +39,8 @@ pub enum TunnelMode {
     Quick,
     /// 认证隧道(使用Token)
     Auth,
+    /// 命名隧道(固定域名，Token 或凭据文件 + 自定义 hostname)
+    Named,
 }
 
 impl Default for TunnelMode {
@@ -45,9 +61,41 @@ pub struct CloudflaredConfig {
     /// 认证模式的Token
     #[serde(default)]
     pub token: Option<String>,
+    /// [NEW] 命名隧道 Token (优先于 `token`)，设置后以 `tunnel run --token` 启动，与快速隧道互斥
+    #[serde(default)]
+    pub tunnel_token: Option<String>,
     /// 使用http2协议(更兼容)
     #[serde(default)]
     pub use_http2: bool,
+    /// 命名隧道的固定域名 (如 `api.example.com`)
+    #[serde(default)]
+    pub hostname: Option<String>,
+    /// 命名隧道凭据文件路径 (`cloudflared tunnel create` 生成的 <id>.json)，与 token 二选一
+    #[serde(default)]
+    pub credentials_file: Option<String>,
+}
+
+impl CloudflaredConfig {
+    /// 生效的隧道 Token: `tunnel_token` 优先，其次 `token`
+    pub fn effective_token(&self) -> Option<&str> {
+        self.tunnel_token
+            .as_deref()
+            .or(self.token.as_deref())
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+    }
+
+    /// 对外报告的固定域名 (命名隧道必填，Token 隧道可选)
+    fn stable_hostname(&self) -> Option<String> {
+        match self.mode {
+            TunnelMode::Named | TunnelMode::Auth => self
+                .hostname
+                .as_deref()
+                .map(normalize_hostname)
+                .filter(|h| !h.is_empty()),
+            TunnelMode::Quick => None,
+        }
+    }
 }
 
 impl Default for CloudflaredConfig {
@@ -57,7 +105,10 @@ impl Default for CloudflaredConfig {
             mode: TunnelMode::Quick,
             port: 8045,
             token: None,
+            tunnel_token: None,
             use_http2: true, // 默认启用http2，更稳定
+            hostname: None,
+            credentials_file: None,
         }
     }
 }
@@ -70,6 +121,23 @@ pub struct CloudflaredStatus {
     pub running: bool,
     pub url: Option<String>,
     pub error: Option<String>,
+    /// 命名隧道的固定域名 (快速隧道为 None)
+    #[serde(default)]
+    pub hostname: Option<String>,
+    /// 进程意外退出，正在等待自动重启
+    #[serde(default)]
+    pub reconnecting: bool,
+    /// 当前连续重启次数 (稳定运行一段时间后清零)
+    #[serde(default)]
+    pub restart_attempts: u32,
+    #[serde(default)]
+    pub max_restart_attempts: u32,
+    /// 下一次重启时间 (Unix 秒)
+    #[serde(default)]
+    pub next_retry_at: Option<i64>,
+    /// 超过最大重试次数，已放弃自动重启
+    #[serde(default)]
+    pub failed: bool,
 }
 
 impl Default for CloudflaredStatus {
@@ -80,15 +148,177 @@ impl Default for CloudflaredStatus {
             running: false,
             url: None,
             error: None,
+            hostname: None,
+            reconnecting: false,
+            restart_attempts: 0,
+            max_restart_attempts: MAX_RESTART_ATTEMPTS,
+            next_retry_at: None,
+            failed: false,
         }
     }
 }
 
+/// 一行 cloudflared 输出
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloudflaredLogLine {
+    /// Unix 毫秒时间戳
+    pub timestamp: i64,
+    /// `stdout` / `stderr` / `supervisor` (自动重启记录)
+    pub stream: String,
+    pub line: String,
+}
+
+/// 最近的 cloudflared 输出 (有界环形缓冲)
+type LogBuffer = Arc<std::sync::Mutex<VecDeque<CloudflaredLogLine>>>;
+
+fn push_log(logs: &LogBuffer, stream: &str, line: &str) {
+    if let Ok(mut buf) = logs.lock() {
+        if buf.len() >= MAX_LOG_LINES {
+            buf.pop_front();
+        }
+        buf.push_back(CloudflaredLogLine {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            stream: stream.to_string(),
+            line: line.to_string(),
+        });
+    }
+}
+
+/// 当前生效的命名隧道公网地址 (供 OAuth 回调地址使用)
+static NAMED_TUNNEL_URL: OnceLock<std::sync::RwLock<Option<String>>> = OnceLock::new();
+
+fn named_tunnel_slot() -> &'static std::sync::RwLock<Option<String>> {
+    NAMED_TUNNEL_URL.get_or_init(|| std::sync::RwLock::new(None))
+}
+
+/// 获取运行中的命名隧道地址 (如 `https://api.example.com`)
+pub fn named_tunnel_public_url() -> Option<String> {
+    named_tunnel_slot().read().ok().and_then(|v| v.clone())
+}
+
+fn set_named_tunnel_url(url: Option<String>) {
+    if let Ok(mut slot) = named_tunnel_slot().write() {
+        *slot = url;
+    }
+}
+
+/// 规范化 hostname: 去掉协议前缀、路径与末尾的点，转小写
+pub fn normalize_hostname(raw: &str) -> String {
+    let host = raw.trim();
+    let host = host
+        .strip_prefix("https://")
+        .or_else(|| host.strip_prefix("http://"))
+        .unwrap_or(host);
+    host.split('/')
+        .next()
+        .unwrap_or("")
+        .trim_end_matches('.')
+        .to_lowercase()
+}
+
+/// 校验隧道配置，返回可直接展示给用户的错误信息
+pub fn validate_config(config: &CloudflaredConfig) -> Result<(), String> {
+    let has_token = config.effective_token().is_some();
+    match config.mode {
+        TunnelMode::Quick => {
+            if has_token {
+                Err("A tunnel token cannot be used with a quick tunnel. Switch mode to \"auth\" or \"named\", or remove the token".to_string())
+            } else {
+                Ok(())
+            }
+        }
+        TunnelMode::Auth => {
+            if !has_token {
+                return Err("Token required for auth mode".to_string());
+            }
+            if let Some(hostname) = config.stable_hostname() {
+                validate_hostname(&hostname)?;
+            }
+            Ok(())
+        }
+        TunnelMode::Named => {
+            let hostname = config.stable_hostname().unwrap_or_default();
+            if hostname.is_empty() {
+                return Err("Hostname required for named tunnel".to_string());
+            }
+            validate_hostname(&hostname)?;
+
+            let credentials = config
+                .credentials_file
+                .as_deref()
+                .map(str::trim)
+                .filter(|p| !p.is_empty());
+            match (has_token, credentials) {
+                (false, None) => Err(
+                    "Named tunnel requires a tunnel token or a credentials file".to_string(),
+                ),
+                (_, Some(path)) if !has_token && !Path::new(path).exists() => {
+                    Err(format!("Credentials file not found: {}", path))
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+}
+
+fn validate_hostname(hostname: &str) -> Result<(), String> {
+    let valid_chars = hostname
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+    if !valid_chars || !hostname.contains('.') || hostname.contains(':') {
+        return Err(format!("Invalid tunnel hostname: {}", hostname));
+    }
+    if hostname.ends_with(".trycloudflare.com") {
+        return Err(format!(
+            "Hostname {} is reserved for quick tunnels",
+            hostname
+        ));
+    }
+    Ok(())
+}
+
+/// 从凭据文件读取 TunnelID
+fn read_tunnel_id(credentials_file: &str) -> Result<String, String> {
+    let content = std::fs::read_to_string(credentials_file)
+        .map_err(|e| format!("Failed to read credentials file: {}", e))?;
+    let json: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid credentials file: {}", e))?;
+    json.get("TunnelID")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .ok_or_else(|| "Credentials file missing TunnelID".to_string())
+}
+
+/// 生成命名隧道的 ingress 配置 (YAML)
+pub fn build_ingress_config(
+    hostname: &str,
+    port: u16,
+    tunnel_id: Option<&str>,
+    credentials_file: Option<&str>,
+) -> String {
+    let mut out = String::new();
+    if let Some(id) = tunnel_id {
+        out.push_str(&format!("tunnel: {}\n", id));
+    }
+    if let Some(path) = credentials_file {
+        out.push_str(&format!("credentials-file: {:?}\n", path));
+    }
+    out.push_str("ingress:\n");
+    out.push_str(&format!("  - hostname: {}\n", hostname));
+    out.push_str(&format!("    service: http://localhost:{}\n", port));
+    out.push_str("  - service: http_status:404\n");
+    out
+}
+
 /// Cloudflared管理器状态
 pub struct CloudflaredManager {
     process: Arc<RwLock<Option<Child>>>,
     status: Arc<RwLock<CloudflaredStatus>>,
+    /// 子进程 stdout/stderr 的最近输出
+    logs: LogBuffer,
     bin_path: PathBuf,
+    /// 命名隧道 ingress 配置文件路径
+    config_path: PathBuf,
     /// 用于通知进程监控任务停止
     shutdown_tx: RwLock<Option<tokio::sync::oneshot::Sender<()>>>,
 }
@@ -101,11 +331,14 @@ impl CloudflaredManager {
             "cloudflared"
         };
         let bin_path = data_dir.join("bin").join(bin_name);
+        let config_path = data_dir.join("cloudflared").join("config.yml");
 
         Self {
             process: Arc::new(RwLock::new(None)),
             status: Arc::new(RwLock::new(CloudflaredStatus::default())),
+            logs: Arc::new(std::sync::Mutex::new(VecDeque::new())),
             bin_path,
+            config_path,
             shutdown_tx: RwLock::new(None),
         }
     }
@@ -142,6 +375,21 @@ impl CloudflaredManager {
         self.status.read().await.clone()
     }
 
+    /// 最近的日志 (按时间正序，`limit` 限制返回最后 N 行)
+    pub fn get_logs(&self, limit: Option<usize>) -> Vec<CloudflaredLogLine> {
+        let Ok(buf) = self.logs.lock() else {
+            return Vec::new();
+        };
+        let skip = limit.map_or(0, |n| buf.len().saturating_sub(n));
+        buf.iter().skip(skip).cloned().collect()
+    }
+
+    fn clear_logs(&self) {
+        if let Ok(mut buf) = self.logs.lock() {
+            buf.clear();
+        }
+    }
+
     /// 更新状态
     async fn update_status(&self, f: impl FnOnce(&mut CloudflaredStatus)) {
         let mut status = self.status.write().await;
@@ -216,11 +464,26 @@ impl CloudflaredManager {
 
     /// 启动隧道
     pub async fn start(&self, config: CloudflaredConfig) -> Result<CloudflaredStatus, String> {
+        validate_config(&config)?;
+        // 命名隧道 / Token 隧道配置了 hostname 时，以固定域名作为对外 URL
+        let named_hostname = config.stable_hostname();
+
         // 检查是否已在运行
         {
             let proc = self.process.read().await;
             if proc.is_some() {
-                return Ok(self.get_status().await);
+                let status = self.get_status().await;
+                // 已有隧道绑定了其他域名时拒绝，避免静默复用错误的 hostname
+                if let Some(wanted) = &named_hostname {
+                    if status.hostname.as_ref() != Some(wanted) {
+                        return Err(format!(
+                            "Hostname collision: a tunnel is already running ({}). Stop it before starting {}",
+                            status.hostname.or(status.url).unwrap_or_else(|| "quick tunnel".to_string()),
+                            wanted
+                        ));
+                    }
+                }
+                return Ok(status);
             }
         }
 
@@ -228,6 +491,7 @@ impl CloudflaredManager {
         if let Some(tx) = self.shutdown_tx.write().await.take() {
             let _ = tx.send(());
         }
+        self.clear_logs();
 
         let (installed, version) = self.check_installed().await;
         if !installed {
@@ -237,142 +501,53 @@ impl CloudflaredManager {
         let local_url = format!("http://localhost:{}", config.port);
         info!("[cloudflared] Starting tunnel to: {}", local_url);
 
-        let mut cmd = Command::new(&self.bin_path);
-        
-        // 设置工作目录
-        // 设置工作目录
-        if let Some(bin_dir) = self.bin_path.parent() {
-            cmd.current_dir(bin_dir);
-            debug!("[cloudflared] Working directory: {:?}", bin_dir);
-        }
-
-        match config.mode {
-            TunnelMode::Quick => {
-                cmd.arg("tunnel")
-                    .arg("--url")
-                    .arg(&local_url);
-                
-                // 注意：--no-autoupdate 参数在较新版本的 cloudflared 中已不被支持，会导致进程立即退出
-                // cmd.arg("--no-autoupdate");
-
-                if config.use_http2 {
-                    cmd.arg("--protocol").arg("http2");
-                }
-                
-                // 注意：--loglevel 参数在此上下文中也会导致 Incorrect Usage 错误，故移除以使用默认值
-                // cmd.arg("--loglevel").arg("info");
-                
-                info!("[cloudflared] Command args: tunnel --url {} ...", local_url);
-            }
-            TunnelMode::Auth => {
-                if let Some(token) = &config.token {
-                    cmd.arg("tunnel")
-                        .arg("run")
-                        .arg("--token")
-                        .arg(token);
-                    
-                    // 注意：--no-autoupdate 参数不被支持
-                    // cmd.arg("--no-autoupdate");
-                    
-                    if config.use_http2 {
-                        cmd.arg("--protocol").arg("http2");
-                    }
-                    
-                    // 注意：--loglevel 参数不被支持
-                    // cmd.arg("--loglevel").arg("info");
-                    
-                    info!("[cloudflared] Command args: tunnel run --token [HIDDEN] ...");
-                } else {
-                    return Err("Token required for auth mode".to_string());
-                }
-            }
-        }
-
-        // 恢复管道
-        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
-        
-        // 使用 DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP 隐藏窗口
-        #[cfg(target_os = "windows")]
-        cmd.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
-
-        let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn: {}", e))?;
-
-        let stdout = child.stdout.take();
-        let stderr = child.stderr.take();
-
-        let status_clone = self.status.clone();
-        if let Some(stdout) = stdout {
-            spawn_log_reader(stdout, status_clone.clone());
-        }
-
-        if let Some(stderr) = stderr {
-            spawn_log_reader(stderr, status_clone.clone());
-        }
+        let child = spawn_tunnel(
+            &self.bin_path,
+            &self.config_path,
+            &config,
+            named_hostname.as_deref(),
+            &self.status,
+            &self.logs,
+        )?;
 
         *self.process.write().await = Some(child);
+        let named_url = named_hostname.as_ref().map(|h| format!("https://{}", h));
         self.update_status(|s| {
             s.installed = installed.clone();
             s.version = version.clone();
             s.running = true;
             s.error = None;
+            s.hostname = named_hostname.clone();
+            if named_url.is_some() {
+                s.url = named_url.clone();
+            }
+            s.reconnecting = false;
+            s.restart_attempts = 0;
+            s.next_retry_at = None;
+            s.failed = false;
         }).await;
+        set_named_tunnel_url(named_url);
 
-        // 启动进程监控任务
+        // 启动进程监控任务 (意外退出时自动重启)
         let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
         *self.shutdown_tx.write().await = Some(shutdown_tx);
 
-        let process_ref = self.process.clone();
-        let status_ref = self.status.clone();
+        let supervisor = Supervisor {
+            process: self.process.clone(),
+            status: self.status.clone(),
+            logs: self.logs.clone(),
+            bin_path: self.bin_path.clone(),
+            config_path: self.config_path.clone(),
+            config,
+            named_hostname,
+        };
 
         tokio::spawn(async move {
             tokio::select! {
                 _ = shutdown_rx => {
                     debug!("[cloudflared] Process monitor shutdown");
                 }
-                _ = async {
-                    loop {
-                        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-
-                        let mut proc_lock = process_ref.write().await;
-                        if let Some(ref mut child) = *proc_lock {
-                            match child.try_wait() {
-                                Ok(Some(exit_status)) => {
-                                    // 进程已退出
-                                    info!("[cloudflared] Process exited with status: {:?}", exit_status);
-                                    *proc_lock = None;
-                                    drop(proc_lock);
-
-                                    let mut s = status_ref.write().await;
-                                    s.running = false;
-                                    s.error = Some(format!("Tunnel process exited (status: {:?})", exit_status));
-                                    break;
-                                }
-                                Ok(None) => {
-                                    // 进程仍在运行
-                                }
-                                Err(e) => {
-                                    info!("[cloudflared] Error checking process: {}", e);
-                                    *proc_lock = None;
-                                    drop(proc_lock);
-
-                                    let mut s = status_ref.write().await;
-                                    s.running = false;
-                                    s.error = Some(format!("Error checking tunnel: {}", e));
-                                    break;
-                                }
-                            }
-                        } else {
-                            // 进程不存在
-                            drop(proc_lock);
-                            let mut s = status_ref.write().await;
-                            if s.running {
-                                s.running = false;
-                                s.error = Some("Tunnel process not found".to_string());
-                            }
-                            break;
-                        }
-                    }
-                } => {}
+                _ = supervisor.run() => {}
             }
         });
 
@@ -381,22 +556,331 @@ impl CloudflaredManager {
 
     /// 停止隧道
     pub async fn stop(&self) -> Result<CloudflaredStatus, String> {
+        // 先停止监控任务，避免把主动停止当成意外退出而重启
+        if let Some(tx) = self.shutdown_tx.write().await.take() {
+            let _ = tx.send(());
+        }
+
         let mut proc_lock = self.process.write().await;
         if let Some(mut child) = proc_lock.take() {
             let _ = child.kill().await;
             info!("[cloudflared] Tunnel stopped");
         }
 
+        drop(proc_lock);
+        self.clear_logs();
+
+        set_named_tunnel_url(None);
         self.update_status(|s| {
             s.running = false;
             s.url = None;
             s.error = None;
+            s.hostname = None;
+            s.reconnecting = false;
+            s.restart_attempts = 0;
+            s.next_retry_at = None;
+            s.failed = false;
         }).await;
 
         Ok(self.get_status().await)
     }
 }
 
+/// 构建 cloudflared 启动命令 (首次启动与自动重启共用)
+fn build_command(
+    bin_path: &Path,
+    config_path: &Path,
+    config: &CloudflaredConfig,
+    named_hostname: Option<&str>,
+) -> Result<Command, String> {
+    let local_url = format!("http://localhost:{}", config.port);
+    info!("[cloudflared] Starting tunnel to: {}", local_url);
+
+    let mut cmd = Command::new(&bin_path);
+    
+    // 设置工作目录
+    // 设置工作目录
+    if let Some(bin_dir) = bin_path.parent() {
+        cmd.current_dir(bin_dir);
+        debug!("[cloudflared] Working directory: {:?}", bin_dir);
+    }
+
+    match config.mode {
+        TunnelMode::Quick => {
+            cmd.arg("tunnel")
+                .arg("--url")
+                .arg(&local_url);
+            
+            // 注意：--no-autoupdate 参数在较新版本的 cloudflared 中已不被支持，会导致进程立即退出
+            // cmd.arg("--no-autoupdate");
+
+            if config.use_http2 {
+                cmd.arg("--protocol").arg("http2");
+            }
+            
+            // 注意：--loglevel 参数在此上下文中也会导致 Incorrect Usage 错误，故移除以使用默认值
+            // cmd.arg("--loglevel").arg("info");
+            
+            info!("[cloudflared] Command args: tunnel --url {} ...", local_url);
+        }
+        TunnelMode::Auth => {
+            if let Some(token) = config.effective_token() {
+                cmd.arg("tunnel")
+                    .arg("run")
+                    .arg("--token")
+                    .arg(token);
+                
+                // 注意：--no-autoupdate 参数不被支持
+                // cmd.arg("--no-autoupdate");
+                
+                if config.use_http2 {
+                    cmd.arg("--protocol").arg("http2");
+                }
+                
+                // 注意：--loglevel 参数不被支持
+                // cmd.arg("--loglevel").arg("info");
+                
+                info!("[cloudflared] Command args: tunnel run --token [HIDDEN] ...");
+            } else {
+                return Err("Token required for auth mode".to_string());
+            }
+        }
+        TunnelMode::Named => {
+            let hostname = named_hostname.unwrap_or_default().to_string();
+            let token = config.effective_token();
+            let credentials = config
+                .credentials_file
+                .as_deref()
+                .map(str::trim)
+                .filter(|p| !p.is_empty());
+            let tunnel_id = match (token, credentials) {
+                (None, Some(path)) => Some(read_tunnel_id(path)?),
+                _ => None,
+            };
+
+            let ingress = build_ingress_config(
+                &hostname,
+                config.port,
+                tunnel_id.as_deref(),
+                if token.is_none() { credentials } else { None },
+            );
+            if let Some(dir) = config_path.parent() {
+                std::fs::create_dir_all(dir)
+                    .map_err(|e| format!("Failed to create config directory: {}", e))?;
+            }
+            std::fs::write(config_path, ingress)
+                .map_err(|e| format!("Failed to write tunnel config: {}", e))?;
+
+            cmd.arg("tunnel").arg("--config").arg(config_path);
+            if config.use_http2 {
+                cmd.arg("--protocol").arg("http2");
+            }
+            cmd.arg("run");
+            match (token, tunnel_id) {
+                (Some(token), _) => {
+                    cmd.arg("--token").arg(token);
+                }
+                (None, Some(id)) => {
+                    cmd.arg(id);
+                }
+                (None, None) => {
+                    return Err(
+                        "Named tunnel requires a tunnel token or a credentials file".to_string(),
+                    )
+                }
+            }
+
+            info!(
+                "[cloudflared] Command args: tunnel --config {:?} run ... (hostname: {})",
+                config_path, hostname
+            );
+        }
+    }
+
+    // 恢复管道
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    
+    // 使用 DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP 隐藏窗口
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+
+    Ok(cmd)
+}
+
+/// 启动 cloudflared 子进程并挂接日志读取
+fn spawn_tunnel(
+    bin_path: &Path,
+    config_path: &Path,
+    config: &CloudflaredConfig,
+    named_hostname: Option<&str>,
+    status_ref: &Arc<RwLock<CloudflaredStatus>>,
+    logs: &LogBuffer,
+) -> Result<Child, String> {
+    let mut cmd = build_command(bin_path, config_path, config, named_hostname)?;
+    let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn: {}", e))?;
+
+    if let Some(stdout) = child.stdout.take() {
+        spawn_log_reader(stdout, "stdout", status_ref.clone(), logs.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        spawn_log_reader(stderr, "stderr", status_ref.clone(), logs.clone());
+    }
+    Ok(child)
+}
+
+/// 第 `attempt` 次重启前的等待时间 (指数退避，封顶 RESTART_MAX_DELAY_SECS)
+fn restart_delay(attempt: u32) -> Duration {
+    let exp = attempt.saturating_sub(1).min(16);
+    Duration::from_secs(
+        RESTART_BASE_DELAY_SECS
+            .saturating_mul(1u64 << exp)
+            .min(RESTART_MAX_DELAY_SECS),
+    )
+}
+
+/// 进程监控: 子进程意外退出时按指数退避自动重启
+struct Supervisor {
+    process: Arc<RwLock<Option<Child>>>,
+    status: Arc<RwLock<CloudflaredStatus>>,
+    logs: LogBuffer,
+    bin_path: PathBuf,
+    config_path: PathBuf,
+    config: CloudflaredConfig,
+    named_hostname: Option<String>,
+}
+
+impl Supervisor {
+    async fn run(self) {
+        let mut attempts: u32 = 0;
+        let mut started_at = Instant::now();
+
+        loop {
+            tokio::time::sleep(MONITOR_INTERVAL).await;
+
+            let exited = {
+                let mut proc_lock = self.process.write().await;
+                let Some(child) = proc_lock.as_mut() else {
+                    // 进程不存在 (已被 stop 取走)
+                    drop(proc_lock);
+                    let mut s = self.status.write().await;
+                    if s.running {
+                        s.running = false;
+                        s.error = Some("Tunnel process not found".to_string());
+                    }
+                    return;
+                };
+                match child.try_wait() {
+                    Ok(None) => None,
+                    Ok(Some(exit_status)) => {
+                        *proc_lock = None;
+                        Some(format!("Tunnel process exited (status: {:?})", exit_status))
+                    }
+                    Err(e) => {
+                        *proc_lock = None;
+                        Some(format!("Error checking tunnel: {}", e))
+                    }
+                }
+            };
+
+            let Some(mut reason) = exited else {
+                // 进程仍在运行，稳定一段时间后清零重试计数
+                if attempts > 0 && started_at.elapsed() >= RESTART_STABLE_AFTER {
+                    info!("[cloudflared] Tunnel stable after {} restart(s), resetting retry counter", attempts);
+                    attempts = 0;
+                    self.status.write().await.restart_attempts = 0;
+                }
+                continue;
+            };
+
+            warn!("[cloudflared] {}", reason);
+            push_log(&self.logs, "supervisor", &reason);
+            set_named_tunnel_url(None);
+
+            loop {
+                attempts += 1;
+                if attempts > MAX_RESTART_ATTEMPTS {
+                    error!(
+                        "[cloudflared] Giving up after {} restart attempts, tunnel marked as failed",
+                        MAX_RESTART_ATTEMPTS
+                    );
+                    let mut s = self.status.write().await;
+                    s.running = false;
+                    s.reconnecting = false;
+                    s.next_retry_at = None;
+                    s.failed = true;
+                    s.url = None;
+                    s.error = Some(format!(
+                        "Tunnel failed after {} restart attempts: {}",
+                        MAX_RESTART_ATTEMPTS, reason
+                    ));
+                    return;
+                }
+
+                let delay = restart_delay(attempts);
+                let msg = format!(
+                    "Restarting tunnel in {}s (attempt {}/{})",
+                    delay.as_secs(),
+                    attempts,
+                    MAX_RESTART_ATTEMPTS
+                );
+                warn!("[cloudflared] {}", msg);
+                push_log(&self.logs, "supervisor", &msg);
+                {
+                    let mut s = self.status.write().await;
+                    s.reconnecting = true;
+                    s.restart_attempts = attempts;
+                    s.next_retry_at = Some(chrono::Utc::now().timestamp() + delay.as_secs() as i64);
+                    s.error = Some(reason.clone());
+                    // 快速隧道重启后会分配新的 URL，旧地址已失效
+                    if self.named_hostname.is_none() {
+                        s.url = None;
+                    }
+                }
+                tokio::time::sleep(delay).await;
+
+                match spawn_tunnel(
+                    &self.bin_path,
+                    &self.config_path,
+                    &self.config,
+                    self.named_hostname.as_deref(),
+                    &self.status,
+                    &self.logs,
+                ) {
+                    Ok(mut child) => {
+                        let mut proc_lock = self.process.write().await;
+                        if proc_lock.is_some() {
+                            // 等待期间隧道已被重新启动，丢弃本次进程
+                            let _ = child.kill().await;
+                            return;
+                        }
+                        *proc_lock = Some(child);
+                        drop(proc_lock);
+
+                        started_at = Instant::now();
+                        let named_url = self.named_hostname.as_ref().map(|h| format!("https://{}", h));
+                        set_named_tunnel_url(named_url.clone());
+                        let mut s = self.status.write().await;
+                        s.running = true;
+                        s.reconnecting = false;
+                        s.next_retry_at = None;
+                        s.error = None;
+                        if named_url.is_some() {
+                            s.url = named_url;
+                        }
+                        info!("[cloudflared] Tunnel restarted (attempt {}/{})", attempts, MAX_RESTART_ATTEMPTS);
+                        break;
+                    }
+                    Err(e) => {
+                        warn!("[cloudflared] Restart attempt {} failed: {}", attempts, e);
+                        push_log(&self.logs, "supervisor", &format!("Restart attempt {} failed: {}", attempts, e));
+                        reason = e;
+                    }
+                }
+            }
+        }
+    }
+}
+
 /// 获取下载URL
 fn get_download_url() -> Result<String, String> {
     let os = std::env::consts::OS;
@@ -417,8 +901,12 @@ fn get_download_url() -> Result<String, String> {
     ))
 }
 
-fn spawn_log_reader<R>(stream: R, status_ref: Arc<RwLock<CloudflaredStatus>>)
-where
+fn spawn_log_reader<R>(
+    stream: R,
+    stream_name: &'static str,
+    status_ref: Arc<RwLock<CloudflaredStatus>>,
+    logs: LogBuffer,
+) where
     R: AsyncRead + Unpin + Send + 'static,
 {
     tokio::spawn(async move {
@@ -427,10 +915,13 @@ where
         while let Ok(Some(line)) = lines.next_line().await {
             // 恢复日志级别为 debug，避免污染生产环境日志
             debug!("[cloudflared output] {}", line);
+            push_log(&logs, stream_name, &line);
             if let Some(url) = extract_tunnel_url(&line) {
-                info!("[cloudflared] Tunnel URL: {}", url);
                 let mut s = status_ref.write().await;
-                s.url = Some(url);
+                if s.url.as_deref() != Some(url.as_str()) {
+                    info!("[cloudflared] Tunnel URL: {}", url);
+                    s.url = Some(url);
+                }
             }
         }
     });
@@ -441,11 +932,9 @@ where
 /// 1. 快速隧道：直接提取 .trycloudflare.com URL
 /// 2. 命名隧道：从 ingress 配置中解析 hostname
 fn extract_tunnel_url(line: &str) -> Option<String> {
-    // 快速隧道模式：直接查找 trycloudflare.com URL
-    if let Some(url) = line.split_whitespace()
-        .find(|s| s.starts_with("https://") && s.contains(".trycloudflare.com"))
-    {
-        return Some(url.to_string());
+    // 快速隧道模式：查找 *.trycloudflare.com URL
+    if let Some(url) = extract_quick_tunnel_url(line) {
+        return Some(url);
     }
     
     // 命名隧道模式：从 "Updated to new configuration" 日志中解析 hostname
@@ -466,3 +955,127 @@ fn extract_tunnel_url(line: &str) -> Option<String> {
     None
 }
 
+/// 提取快速隧道分配的地址。URL 可能被 `|`、引号或 `url=` 等包裹；
+/// 排除 `api.trycloudflare.com` (请求隧道失败时的错误日志里也会出现)
+fn extract_quick_tunnel_url(line: &str) -> Option<String> {
+    let mut rest = line;
+    while let Some(pos) = rest.find("https://") {
+        let candidate = &rest[pos + "https://".len()..];
+        let host: String = candidate
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '.')
+            .collect();
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if let Some(sub) = host.strip_suffix(".trycloudflare.com") {
+            if !sub.is_empty() && !sub.contains('.') && sub != "api" {
+                return Some(format!("https://{}", host));
+            }
+        }
+        rest = candidate;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(hostname: Option<&str>, token: Option<&str>) -> CloudflaredConfig {
+        CloudflaredConfig {
+            mode: TunnelMode::Named,
+            hostname: hostname.map(|s| s.to_string()),
+            token: token.map(|s| s.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_named_tunnel() {
+        assert!(validate_config(&named(Some("api.example.com"), Some("tok"))).is_ok());
+        assert!(validate_config(&named(Some("https://API.example.com/"), Some("tok"))).is_ok());
+
+        let err = validate_config(&named(Some("api.example.com"), None)).unwrap_err();
+        assert!(err.contains("token"));
+        assert!(validate_config(&named(None, Some("tok"))).is_err());
+        assert!(validate_config(&named(Some("foo.trycloudflare.com"), Some("tok"))).is_err());
+        assert!(validate_config(&named(Some("bad host.com"), Some("tok"))).is_err());
+    }
+
+    #[test]
+    fn test_build_ingress_config() {
+        let yaml = build_ingress_config("api.example.com", 8045, Some("abc-123"), Some("/tmp/abc.json"));
+        assert!(yaml.starts_with("tunnel: abc-123\n"));
+        assert!(yaml.contains("credentials-file: \"/tmp/abc.json\"\n"));
+        assert!(yaml.contains("  - hostname: api.example.com\n    service: http://localhost:8045\n"));
+        assert!(yaml.ends_with("  - service: http_status:404\n"));
+
+        assert_eq!(normalize_hostname("https://Api.Example.com/path"), "api.example.com");
+    }
+
+    #[test]
+    fn test_restart_delay_backoff() {
+        assert_eq!(restart_delay(1), Duration::from_secs(2));
+        assert_eq!(restart_delay(2), Duration::from_secs(4));
+        assert_eq!(restart_delay(5), Duration::from_secs(32));
+        assert_eq!(restart_delay(6), Duration::from_secs(RESTART_MAX_DELAY_SECS));
+        assert_eq!(restart_delay(u32::MAX), Duration::from_secs(RESTART_MAX_DELAY_SECS));
+    }
+
+    #[test]
+    fn test_extract_quick_tunnel_url() {
+        assert_eq!(
+            extract_tunnel_url("INF |  https://calm-river-1234.trycloudflare.com                     |"),
+            Some("https://calm-river-1234.trycloudflare.com".to_string())
+        );
+        assert_eq!(
+            extract_tunnel_url("INF Registered tunnel connection url=https://Calm-River-1234.trycloudflare.com/"),
+            Some("https://calm-river-1234.trycloudflare.com".to_string())
+        );
+        assert_eq!(
+            extract_tunnel_url(r#"ERR failed to request quick Tunnel: Post "https://api.trycloudflare.com/tunnel": EOF"#),
+            None
+        );
+        assert_eq!(extract_tunnel_url("INF Requesting new quick Tunnel on trycloudflare.com..."), None);
+    }
+
+    #[test]
+    fn test_log_buffer_is_bounded() {
+        let manager = CloudflaredManager::new(&std::env::temp_dir());
+        for i in 0..MAX_LOG_LINES + 5 {
+            push_log(&manager.logs, "stderr", &format!("line {}", i));
+        }
+        let all = manager.get_logs(None);
+        assert_eq!(all.len(), MAX_LOG_LINES);
+        assert_eq!(all[0].line, "line 5");
+        let tail = manager.get_logs(Some(2));
+        assert_eq!(tail.iter().map(|l| l.line.as_str()).collect::<Vec<_>>(), ["line 503", "line 504"]);
+        manager.clear_logs();
+        assert!(manager.get_logs(None).is_empty());
+    }
+
+    #[test]
+    fn test_tunnel_token_mode_validation() {
+        let quick_with_token = CloudflaredConfig {
+            tunnel_token: Some("tok".to_string()),
+            ..Default::default()
+        };
+        let err = validate_config(&quick_with_token).unwrap_err();
+        assert!(err.contains("quick tunnel"));
+
+        let auth = CloudflaredConfig {
+            mode: TunnelMode::Auth,
+            tunnel_token: Some("tok".to_string()),
+            hostname: Some("https://API.example.com/".to_string()),
+            ..Default::default()
+        };
+        assert!(validate_config(&auth).is_ok());
+        assert_eq!(auth.effective_token(), Some("tok"));
+        assert_eq!(auth.stable_hostname().as_deref(), Some("api.example.com"));
+
+        let bad_host = CloudflaredConfig {
+            hostname: Some("bad host".to_string()),
+            ..auth.clone()
+        };
+        assert!(validate_config(&bad_host).is_err());
+    }
+}
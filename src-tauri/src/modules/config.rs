@@ -1,27 +1,231 @@
 use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::Duration;
 use serde_json;
+use tokio_util::sync::CancellationToken;
 
 use crate::models::AppConfig;
 use super::account::get_data_dir;
 
-const CONFIG_FILE: &str = "gui_config.json";
+pub(crate) const CONFIG_FILE: &str = "gui_config.json";
+
+/// Debounce window for config file change events
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Path of the configuration file
+pub fn config_file_path() -> Result<PathBuf, String> {
+    Ok(get_data_dir()?.join(CONFIG_FILE))
+}
 
 /// Load application configuration
+/// 纯文件读取，不叠加 `ABV_*` 环境变量；构建运行时配置时使用 [`with_env_overrides`]
 pub fn load_app_config() -> Result<AppConfig, String> {
-    let data_dir = get_data_dir()?;
-    let config_path = data_dir.join(CONFIG_FILE);
-    
+    load_app_config_at(&config_file_path()?)
+}
+
+fn load_app_config_at(config_path: &std::path::Path) -> Result<AppConfig, String> {
     if !config_path.exists() {
         let config = AppConfig::new();
         // [FIX #1460] Persist initial config to prevent new API Key on every refresh
-        let _ = save_app_config(&config);
+        let _ = save_app_config_at(config_path, &config);
         return Ok(config);
     }
     
-    let content = fs::read_to_string(&config_path)
+    let content = fs::read_to_string(config_path)
         .map_err(|e| format!("failed_to_read_config_file: {}", e))?;
-    
-    let mut v: serde_json::Value = serde_json::from_str(&content)
+
+    let (config, modified) = parse_app_config(&content)?;
+
+    // If migration occurred, auto-save once to clean up the file
+    if modified {
+        let _ = save_app_config_at(config_path, &config);
+    }
+
+    Ok(config)
+}
+
+/// Prefix of environment variables that override config fields
+const ENV_PREFIX: &str = "ABV_";
+
+/// Result of overlaying environment variables onto a config
+struct EnvOverrides {
+    config: AppConfig,
+    /// Env var names that were applied
+    applied: Vec<String>,
+    /// Parse / validation errors (the offending variable is skipped)
+    errors: Vec<String>,
+}
+
+/// Overlay `ABV_*` environment variables onto the proxy config (env wins over file).
+/// Only for the runtime config (server start / reload); never save the result, or the
+/// env values would be written into the file. Results are logged once per process.
+pub fn with_env_overrides(config: AppConfig) -> AppConfig {
+    static LOGGED: std::sync::Once = std::sync::Once::new();
+
+    let result = apply_env_overrides(config, std::env::vars());
+    LOGGED.call_once(|| {
+        if !result.applied.is_empty() {
+            crate::modules::logger::log_info(&format!(
+                "[Config] Environment overrides applied: {}",
+                result.applied.join(", ")
+            ));
+        }
+        for e in &result.errors {
+            crate::modules::logger::log_warn(&format!("[Config] Ignoring environment override: {}", e));
+        }
+    });
+    result.config
+}
+
+/// Naming scheme: `ABV_[PROXY_]<FIELD>` maps to `proxy.<field>`; nested sections join with `_`
+/// (`ABV_PROXY_PORT` -> `proxy.port`, `ABV_ZAI_ENABLED` -> `proxy.zai.enabled`).
+/// Field names are matched against the config structure, longest name first, so fields containing
+/// underscores resolve unambiguously. Scalars are parsed according to the current value's type;
+/// arrays / objects take JSON (string arrays also accept comma-separated values).
+/// `ABV_*` variables that match no field are left to their own consumers (e.g. `ABV_DATA_DIR`);
+/// only unmatched `ABV_PROXY_*` variables are reported as errors.
+fn apply_env_overrides(
+    config: AppConfig,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> EnvOverrides {
+    let mut result = EnvOverrides {
+        config,
+        applied: Vec::new(),
+        errors: Vec::new(),
+    };
+    let mut vars: Vec<(String, String)> = vars
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+        .collect();
+    if vars.is_empty() {
+        return result;
+    }
+    vars.sort();
+
+    let mut value = match serde_json::to_value(&result.config) {
+        Ok(v) => v,
+        Err(e) => {
+            result.errors.push(format!("failed_to_serialize_config: {}", e));
+            return result;
+        }
+    };
+
+    for (name, raw) in vars {
+        let key = name[ENV_PREFIX.len()..].to_ascii_lowercase();
+        let Some(proxy) = value.get_mut("proxy").and_then(|p| p.as_object_mut()) else {
+            break;
+        };
+        let path = key
+            .strip_prefix("proxy_")
+            .and_then(|rest| resolve_env_path(proxy, rest))
+            .or_else(|| resolve_env_path(proxy, &key));
+        let Some(path) = path else {
+            if key.starts_with("proxy_") {
+                result.errors.push(format!("{}: no matching proxy config field", name));
+            }
+            continue;
+        };
+
+        let mut target = &mut value["proxy"];
+        for segment in &path {
+            target = &mut target[segment.as_str()];
+        }
+        match parse_env_value(target, &raw) {
+            Ok(parsed) => {
+                let previous = std::mem::replace(target, parsed);
+                // 逐项校验，类型不匹配的变量回滚，不影响其余覆盖
+                if let Err(e) = serde_json::from_value::<AppConfig>(value.clone()) {
+                    let mut target = &mut value["proxy"];
+                    for segment in &path {
+                        target = &mut target[segment.as_str()];
+                    }
+                    *target = previous;
+                    result.errors.push(format!("{}: {}", name, e));
+                } else {
+                    result.applied.push(name);
+                }
+            }
+            Err(e) => result.errors.push(format!("{}: {}", name, e)),
+        }
+    }
+
+    if !result.applied.is_empty() {
+        match serde_json::from_value(value) {
+            Ok(config) => result.config = config,
+            Err(e) => {
+                result.applied.clear();
+                result.errors.push(format!("failed_to_apply_overrides: {}", e));
+            }
+        }
+    }
+    result
+}
+
+/// Resolve `zai_enabled` against `{"zai": {"enabled": ..}}` into `["zai", "enabled"]`
+fn resolve_env_path(obj: &serde_json::Map<String, serde_json::Value>, key: &str) -> Option<Vec<String>> {
+    let mut candidates: Vec<&String> = obj
+        .keys()
+        .filter(|k| key == k.as_str() || key.starts_with(&format!("{}_", k)))
+        .collect();
+    candidates.sort_by_key(|k| std::cmp::Reverse(k.len()));
+
+    for k in candidates {
+        if key == k.as_str() {
+            return Some(vec![k.clone()]);
+        }
+        if let Some(serde_json::Value::Object(child)) = obj.get(k) {
+            if let Some(mut path) = resolve_env_path(child, &key[k.len() + 1..]) {
+                path.insert(0, k.clone());
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+/// Parse a raw env value according to the type of the value it replaces
+fn parse_env_value(current: &serde_json::Value, raw: &str) -> Result<serde_json::Value, String> {
+    use serde_json::Value;
+
+    let trimmed = raw.trim();
+    match current {
+        Value::Bool(_) => match trimmed.to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => Ok(Value::Bool(true)),
+            "false" | "0" | "no" | "off" => Ok(Value::Bool(false)),
+            _ => Err(format!("expected a boolean, got '{}'", raw)),
+        },
+        Value::Number(_) => serde_json::from_str::<serde_json::Number>(trimmed)
+            .map(Value::Number)
+            .map_err(|_| format!("expected a number, got '{}'", raw)),
+        Value::String(_) => Ok(Value::String(raw.to_string())),
+        Value::Array(_) => match serde_json::from_str::<Value>(trimmed) {
+            Ok(v @ Value::Array(_)) => Ok(v),
+            _ if !trimmed.starts_with('[') => Ok(Value::Array(
+                trimmed
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(|s| Value::String(s.to_string()))
+                    .collect(),
+            )),
+            _ => Err(format!("expected a JSON array, got '{}'", raw)),
+        },
+        Value::Object(_) => match serde_json::from_str::<Value>(trimmed) {
+            Ok(v @ Value::Object(_)) => Ok(v),
+            _ => Err(format!("expected a JSON object, got '{}'", raw)),
+        },
+        // Option 字段当前为空: 优先按 JSON 解析 (数字 / 布尔 / 对象)，否则视为字符串
+        Value::Null => Ok(serde_json::from_str::<Value>(trimmed)
+            .ok()
+            .filter(|v| !v.is_string())
+            .unwrap_or_else(|| Value::String(raw.to_string()))),
+    }
+}
+
+/// Parse config file content, applying migrations; returns (config, whether migration changed it)
+fn parse_app_config(content: &str) -> Result<(AppConfig, bool), String> {
+    let mut v: serde_json::Value = serde_json::from_str(content)
         .map_err(|e| format!("failed_to_parse_config_file: {}", e))?;
     
     let mut modified = false;
@@ -65,27 +269,264 @@ pub fn load_app_config() -> Result<AppConfig, String> {
         if modified {
             proxy.as_object_mut().unwrap().insert("custom_mapping".to_string(), serde_json::Value::Object(custom_mapping));
         }
+
+        // [FIX] 迁移旧版 `timeouts.models` 到 `model_timeouts` (已存在的新配置优先)
+        let legacy_models = proxy
+            .get_mut("timeouts")
+            .and_then(|t| t.as_object_mut())
+            .and_then(|t| t.remove("models"));
+        if let Some(legacy_models) = legacy_models {
+            if let Some(legacy) = legacy_models.as_object() {
+                let proxy_obj = proxy.as_object_mut().unwrap();
+                let model_timeouts = proxy_obj
+                    .entry("model_timeouts")
+                    .or_insert_with(|| serde_json::json!({}));
+                if let Some(target) = model_timeouts.as_object_mut() {
+                    for (k, v) in legacy {
+                        target.entry(k.clone()).or_insert_with(|| v.clone());
+                    }
+                }
+            }
+            modified = true;
+        }
     }
 
     let config: AppConfig = serde_json::from_value(v)
         .map_err(|e| format!("failed_to_convert_config_after_migration: {}", e))?;
-    
-    // If migration occurred, auto-save once to clean up the file
-    if modified {
-        let _ = save_app_config(&config);
-    }
 
-    Ok(config)
+    // [FIX] 非法 CIDR 条目直接拒绝加载 (与保存配置时的校验一致)，不静默剔除
+    crate::proxy::security::validate_monitor_cidrs(&config.proxy.security_monitor)
+        .map_err(|e| format!("invalid_config: {}", e))?;
+
+    Ok((config, modified))
+}
+
+/// [`with_env_overrides`] for the proxy section only (server start paths receive a `ProxyConfig`)
+pub fn with_proxy_env_overrides(proxy: crate::proxy::ProxyConfig) -> crate::proxy::ProxyConfig {
+    let config = AppConfig {
+        proxy,
+        ..AppConfig::default()
+    };
+    with_env_overrides(config).proxy
 }
 
 /// Save application configuration
 pub fn save_app_config(config: &AppConfig) -> Result<(), String> {
-    let data_dir = get_data_dir()?;
-    let config_path = data_dir.join(CONFIG_FILE);
-    
+    save_app_config_at(&config_file_path()?, config)
+}
+
+fn save_app_config_at(config_path: &std::path::Path, config: &AppConfig) -> Result<(), String> {
     let content = serde_json::to_string_pretty(config)
         .map_err(|e| format!("failed_to_serialize_config: {}", e))?;
     
-    fs::write(&config_path, content)
+    fs::write(config_path, content)
         .map_err(|e| format!("failed_to_save_config: {}", e))
 }
+
+/// Watches the config file and hands each successfully parsed change to a callback
+/// (enabled by `proxy.config_file_watch`, for headless/Docker deployments that replace the file in place)
+pub struct FileWatcher {
+    cancel: CancellationToken,
+}
+
+impl FileWatcher {
+    /// Start watching; must be called inside a tokio runtime. Stops on `stop()` or drop.
+    pub fn start<F, Fut>(path: PathBuf, on_change: F) -> Result<Self, String>
+    where
+        F: Fn(AppConfig) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        use notify::{EventKind, RecursiveMode, Watcher};
+
+        let dir = path
+            .parent()
+            .map(|p| p.to_path_buf())
+            .ok_or_else(|| format!("invalid_config_path: {:?}", path))?;
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_os_string())
+            .ok_or_else(|| format!("invalid_config_path: {:?}", path))?;
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<()>();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let Ok(event) = res else {
+                return;
+            };
+            let relevant = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                && event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name() == Some(file_name.as_os_str()));
+            if relevant {
+                let _ = tx.send(());
+            }
+        })
+        .map_err(|e| format!("failed_to_create_config_watcher: {}", e))?;
+        // Watch the directory: kubectl cp and most editors replace the file (rename), which drops a file-level watch
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .map_err(|e| format!("failed_to_watch_config_dir: {}", e))?;
+
+        let cancel = CancellationToken::new();
+        let token = cancel.clone();
+        tokio::spawn(async move {
+            let _watcher = watcher;
+            let mut last_content = fs::read_to_string(&path).ok();
+            loop {
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    event = rx.recv() => if event.is_none() { break },
+                }
+                // Wait until no new events arrive for WATCH_DEBOUNCE
+                loop {
+                    tokio::select! {
+                        _ = token.cancelled() => return,
+                        next = tokio::time::timeout(WATCH_DEBOUNCE, rx.recv()) => match next {
+                            Ok(Some(())) => continue,
+                            Ok(None) => return,
+                            Err(_) => break,
+                        },
+                    }
+                }
+
+                // The file may briefly not exist while being replaced
+                let Ok(content) = fs::read_to_string(&path) else {
+                    continue;
+                };
+                if last_content.as_deref() == Some(content.as_str()) {
+                    continue;
+                }
+                match parse_app_config(&content) {
+                    Ok((config, _)) => {
+                        last_content = Some(content);
+                        crate::modules::logger::log_info("[Config-Watch] Config file changed, applying");
+                        on_change(config).await;
+                    }
+                    Err(e) => crate::modules::logger::log_warn(&format!(
+                        "[Config-Watch] Ignoring invalid config file, keeping current config: {}",
+                        e
+                    )),
+                }
+            }
+        });
+
+        Ok(Self { cancel })
+    }
+
+    pub fn stop(&self) {
+        self.cancel.cancel();
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_app_config_rejects_invalid_content() {
+        assert!(parse_app_config("{ not json").is_err());
+
+        let content = serde_json::to_string(&AppConfig::new()).unwrap();
+        let (config, modified) = parse_app_config(&content).unwrap();
+        assert!(!modified);
+        assert!(!config.proxy.config_file_watch);
+    }
+
+    #[test]
+    fn test_parse_app_config_migrates_legacy_timeout_models() {
+        let mut v = serde_json::to_value(AppConfig::new()).unwrap();
+        v["proxy"]["timeouts"]["models"] = serde_json::json!({
+            "gemini-3-pro-image*": 300,
+            "gemini-2.5-pro": 600,
+        });
+        v["proxy"]["model_timeouts"] = serde_json::json!({ "gemini-2.5-pro": 900 });
+
+        let (config, modified) = parse_app_config(&v.to_string()).unwrap();
+        assert!(modified);
+        assert_eq!(config.proxy.model_timeouts.get("gemini-3-pro-image*"), Some(&300));
+        // 新字段中已有的配置不被旧值覆盖
+        assert_eq!(config.proxy.model_timeouts.get("gemini-2.5-pro"), Some(&900));
+    }
+
+    #[test]
+    fn test_parse_app_config_rejects_invalid_cidrs() {
+        let mut v = serde_json::to_value(AppConfig::new()).unwrap();
+        v["proxy"]["security_monitor"]["blocked_cidrs"] = serde_json::json!(["10.0.0.0/8", "10.0.0.0/33"]);
+
+        let err = parse_app_config(&v.to_string()).unwrap_err();
+        assert!(err.contains("10.0.0.0/33"));
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_env_overrides_win_over_file() {
+        let mut config = AppConfig::new();
+        config.proxy.port = 8045;
+        config.proxy.zai.enabled = false;
+
+        let result = apply_env_overrides(
+            config,
+            env(&[
+                ("ABV_PROXY_PORT", "9000"),
+                ("ABV_PROXY_API_KEY", "sk-from-env"),
+                ("ABV_ZAI_ENABLED", "true"),
+                ("ABV_DATA_DIR", "/data"),
+                ("PATH", "/usr/bin"),
+            ]),
+        );
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.applied.len(), 3);
+        assert_eq!(result.config.proxy.port, 9000);
+        assert_eq!(result.config.proxy.api_key, "sk-from-env");
+        assert!(result.config.proxy.zai.enabled);
+    }
+
+    #[test]
+    fn test_env_override_errors_are_reported() {
+        let result = apply_env_overrides(
+            AppConfig::new(),
+            env(&[
+                ("ABV_PROXY_PORT", "not-a-port"),
+                ("ABV_PROXY_NO_SUCH_FIELD", "1"),
+                ("ABV_ZAI_ENABLED", "yes"),
+            ]),
+        );
+        assert_eq!(result.errors.len(), 2, "{:?}", result.errors);
+        assert!(result.errors.iter().any(|e| e.starts_with("ABV_PROXY_PORT")));
+        assert_eq!(result.config.proxy.port, AppConfig::new().proxy.port);
+        assert!(result.config.proxy.zai.enabled);
+    }
+
+    #[test]
+    fn test_load_save_does_not_persist_env_overrides() {
+        let dir = std::env::temp_dir().join(format!("abv-config-env-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(CONFIG_FILE);
+
+        let mut config = AppConfig::new();
+        config.proxy.user_agent_override = Some("from-file".to_string());
+        save_app_config_at(&path, &config).unwrap();
+
+        std::env::set_var("ABV_PROXY_USER_AGENT_OVERRIDE", "from-env");
+        let loaded = load_app_config_at(&path).unwrap();
+        save_app_config_at(&path, &loaded).unwrap();
+        let runtime = with_env_overrides(load_app_config_at(&path).unwrap());
+        std::env::remove_var("ABV_PROXY_USER_AGENT_OVERRIDE");
+
+        let saved = load_app_config_at(&path).unwrap();
+        assert_eq!(saved.proxy.user_agent_override.as_deref(), Some("from-file"));
+        assert_eq!(runtime.proxy.user_agent_override.as_deref(), Some("from-env"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
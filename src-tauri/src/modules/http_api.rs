@@ -14,6 +14,7 @@ use tokio::sync::RwLock;
 use tower_http::cors::{Any, CorsLayer};
 
 use crate::modules::{account, logger, proxy_db};
+use crate::proxy::error::{ApiError, ApiErrorCode};
 
 /// Default port for HTTP API server
 pub const DEFAULT_PORT: u16 = 19527;
@@ -172,11 +173,6 @@ struct DeviceProfileResponse {
     sqm_id: String,
 }
 
-#[derive(Serialize)]
-struct ErrorResponse {
-    error: String,
-}
-
 #[derive(Serialize)]
 struct LogsResponse {
     total: u64,
@@ -227,13 +223,8 @@ async fn health() -> impl IntoResponse {
 }
 
 /// GET /accounts - Get all accounts
-async fn list_accounts() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let accounts = account::list_accounts().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+async fn list_accounts() -> Result<impl IntoResponse, ApiError> {
+    let accounts = account::list_accounts().map_err(ApiError::internal)?;
 
     let current_id = account::get_current_account_id()
         .ok()
@@ -273,13 +264,8 @@ async fn list_accounts() -> Result<impl IntoResponse, (StatusCode, Json<ErrorRes
 }
 
 /// GET /accounts/current - Get current account
-async fn get_current_account() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let current = account::get_current_account().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+async fn get_current_account() -> Result<impl IntoResponse, ApiError> {
+    let current = account::get_current_account().map_err(ApiError::from_account_error)?;
 
     let response = current.map(|acc| {
         let quota = acc.quota.map(|q| QuotaResponse {
@@ -311,16 +297,14 @@ async fn get_current_account() -> Result<impl IntoResponse, (StatusCode, Json<Er
 async fn switch_account(
     State(state): State<ApiState>,
     Json(payload): Json<SwitchRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     // Check if another switch operation is already in progress
     {
         let switching = state.switching.read().await;
         if *switching {
-            return Err((
-                StatusCode::CONFLICT,
-                Json(ErrorResponse {
-                    error: "Another switch operation is already in progress".to_string(),
-                }),
+            return Err(ApiError::new(
+                ApiErrorCode::AccountSwitchInProgress,
+                "Another switch operation is already in progress",
             ));
         }
     }
@@ -363,7 +347,7 @@ async fn switch_account(
 }
 
 /// POST /accounts/refresh - Refresh all quotas
-async fn refresh_all_quotas() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+async fn refresh_all_quotas() -> Result<impl IntoResponse, ApiError> {
     logger::log_info("[HTTP API] Starting refresh of all account quotas");
 
     // Execute refresh asynchronously
@@ -395,18 +379,14 @@ async fn refresh_all_quotas() -> Result<impl IntoResponse, (StatusCode, Json<Err
 async fn bind_device(
     Path(account_id): Path<String>,
     Json(payload): Json<BindDeviceRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     logger::log_info(&format!(
         "[HTTP API] Binding device fingerprint: account={}, mode={}",
         account_id, payload.mode
     ));
 
-    let result = account::bind_device_profile(&account_id, &payload.mode).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    let result =
+        account::bind_device_profile(&account_id, &payload.mode).map_err(ApiError::from_account_error)?;
 
     Ok(Json(BindDeviceResponse {
         success: true,
@@ -423,14 +403,14 @@ async fn bind_device(
 /// GET /logs - Get proxy logs
 async fn get_logs(
     Query(params): Query<LogsRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let limit = if params.limit == 0 { 50 } else { params.limit };
 
     let total = proxy_db::get_logs_count_filtered(&params.filter, params.errors_only)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+        .map_err(ApiError::internal)?;
 
     let logs = proxy_db::get_logs_filtered(&params.filter, params.errors_only, limit, params.offset)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+        .map_err(ApiError::internal)?;
 
     Ok(Json(LogsResponse {
         total,
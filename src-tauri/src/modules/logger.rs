@@ -14,6 +14,28 @@ impl tracing_subscriber::fmt::time::FormatTime for LocalTimer {
     }
 }
 
+/// 日志输出格式 (环境变量 `ABV_LOG_FORMAT`: `text` | `json`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// 人类可读的单行文本 (默认)
+    Text,
+    /// 每行一个 JSON 对象，便于 Loki / ELK 等采集
+    Json,
+}
+
+impl LogFormat {
+    pub fn from_env() -> Self {
+        Self::parse(std::env::var("ABV_LOG_FORMAT").ok().as_deref())
+    }
+
+    fn parse(value: Option<&str>) -> Self {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            Some("json") => LogFormat::Json,
+            _ => LogFormat::Text,
+        }
+    }
+}
+
 pub fn get_log_dir() -> Result<PathBuf, String> {
     let data_dir = get_data_dir()?;
     let log_dir = data_dir.join("logs");
@@ -44,19 +66,52 @@ pub fn init_logger() {
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
     
     // 2. Console output layer (using local timezone)
-    let console_layer = fmt::Layer::new()
-        .with_target(false)
-        .with_thread_ids(false)
-        .with_level(true)
-        .with_timer(LocalTimer);
-        
     // 3. File output layer (disable ANSI formatting, use local timezone)
-    let file_layer = fmt::Layer::new()
-        .with_writer(non_blocking)
-        .with_ansi(false)
-        .with_target(true)
-        .with_level(true)
-        .with_timer(LocalTimer);
+    // ABV_LOG_FORMAT=json 时两者均输出 JSON (事件字段展开到顶层，附带当前 span 的 request_id)
+    let log_format = LogFormat::from_env();
+    let (console_layer, file_layer, console_json_layer, file_json_layer) = match log_format {
+        LogFormat::Text => (
+            Some(
+                fmt::Layer::new()
+                    .with_target(false)
+                    .with_thread_ids(false)
+                    .with_level(true)
+                    .with_timer(LocalTimer),
+            ),
+            Some(
+                fmt::Layer::new()
+                    .with_writer(non_blocking)
+                    .with_ansi(false)
+                    .with_target(true)
+                    .with_level(true)
+                    .with_timer(LocalTimer),
+            ),
+            None,
+            None,
+        ),
+        LogFormat::Json => (
+            None,
+            None,
+            Some(
+                fmt::Layer::new()
+                    .json()
+                    .flatten_event(true)
+                    .with_current_span(true)
+                    .with_span_list(false)
+                    .with_timer(LocalTimer),
+            ),
+            Some(
+                fmt::Layer::new()
+                    .json()
+                    .flatten_event(true)
+                    .with_current_span(true)
+                    .with_span_list(false)
+                    .with_writer(non_blocking)
+                    .with_ansi(false)
+                    .with_timer(LocalTimer),
+            ),
+        ),
+    };
 
     // 4. Set filtering layer (default to INFO level to reduce log size)
     let filter_layer = EnvFilter::try_from_default_env()
@@ -70,6 +125,8 @@ pub fn init_logger() {
         .with(filter_layer)
         .with(console_layer)
         .with(file_layer)
+        .with(console_json_layer)
+        .with(file_json_layer)
         .with(bridge_layer)
         .try_init();
 
@@ -77,7 +134,10 @@ pub fn init_logger() {
     // Recommended practice when using tracing_appender::non_blocking (if manual flushing is not needed)
     std::mem::forget(_guard);
     
-    info!("Log system initialized (Console + File persistence)");
+    info!(
+        "Log system initialized (Console + File persistence, format: {:?})",
+        log_format
+    );
     
     // Auto-cleanup logs older than 7 days
     if let Err(e) = cleanup_old_logs(7) {
@@ -14,6 +14,7 @@ pub mod device;
 pub mod update_checker;
 pub mod scheduler;
 pub mod token_stats;
+pub mod quota_forecast;
 pub mod cloudflared;
 pub mod integration;
 pub mod account_service;
@@ -23,6 +24,9 @@ pub mod log_bridge;
 pub mod security_db;
 pub mod user_token_db;
 pub mod version;
+pub mod backup;
+pub mod audit_log;
+pub mod admin_users;
 
 use crate::models;
 
@@ -1,4 +1,4 @@
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
 use std::path::PathBuf;
 use crate::proxy::monitor::ProxyRequestLog;
 
@@ -51,6 +51,10 @@ pub fn init_db() -> Result<(), String> {
     let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN protocol TEXT", []);
     let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN client_ip TEXT", []);
     let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN username TEXT", []);
+    let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN timeout_secs INTEGER", []);
+    let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN cached_tokens INTEGER", []);
+    let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN estimated_cost_usd REAL", []);
+    let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN error_class TEXT", []);
 
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_timestamp ON request_logs (timestamp DESC)",
@@ -63,15 +67,366 @@ pub fn init_db() -> Result<(), String> {
         [],
     ).map_err(|e| e.to_string())?;
 
+    // [NEW] 日志列表按延迟 / 状态码排序
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_duration ON request_logs (duration, timestamp)",
+        [],
+    ).map_err(|e| e.to_string())?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_status_timestamp ON request_logs (status, timestamp)",
+        [],
+    ).map_err(|e| e.to_string())?;
+
+    init_coordination_tables(&conn)?;
+    init_failover_table(&conn)?;
+
+    Ok(())
+}
+
+// ============================================================================
+// 账号故障转移记录
+// 某次尝试因账号级错误 (403/429 等) 失败、下一次尝试换用其他账号时写入一条
+// ============================================================================
+
+fn init_failover_table(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS failover_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp INTEGER NOT NULL,
+            from_account TEXT NOT NULL,
+            to_account TEXT NOT NULL,
+            reason TEXT NOT NULL,
+            model TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_failover_timestamp ON failover_events (timestamp DESC);",
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FailoverEvent {
+    /// Unix 毫秒 (与 request_logs 一致)
+    pub timestamp: i64,
+    pub from_account: String,
+    pub to_account: String,
+    pub reason: String,
+    pub model: Option<String>,
+}
+
+pub fn save_failover_event(event: &FailoverEvent) -> Result<(), String> {
+    let conn = connect_db()?;
+    insert_failover_event(&conn, event)
+}
+
+fn insert_failover_event(conn: &Connection, event: &FailoverEvent) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO failover_events (timestamp, from_account, to_account, reason, model)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            event.timestamp,
+            event.from_account,
+            event.to_account,
+            event.reason,
+            event.model,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 最近 N 小时内的故障转移记录 (新的在前)
+pub fn get_failover_events(hours: i64) -> Result<Vec<FailoverEvent>, String> {
+    let conn = connect_db()?;
+    // 超大范围 (如 ?range=i64::MAX) 饱和为 "全部记录"，避免乘法溢出
+    let since = chrono::Utc::now()
+        .timestamp_millis()
+        .saturating_sub(hours.saturating_mul(3600 * 1000));
+    query_failover_events(&conn, since)
+}
+
+fn query_failover_events(conn: &Connection, since: i64) -> Result<Vec<FailoverEvent>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT timestamp, from_account, to_account, reason, model
+             FROM failover_events
+             WHERE timestamp >= ?1
+             ORDER BY timestamp DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([since], |row| {
+            Ok(FailoverEvent {
+                timestamp: row.get(0)?,
+                from_account: row.get(1)?,
+                to_account: row.get(2)?,
+                reason: row.get(3)?,
+                model: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// 多实例协调 (coordination_mode = shared_database)
+// 多个实例共享同一数据目录时，通过以下表同步会话绑定、固定账号与熔断状态。
+// 每行带 version 列，更新时按 version 做乐观锁校验。
+// ============================================================================
+
+fn init_coordination_tables(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS coord_sessions (
+            session_id TEXT PRIMARY KEY,
+            account_id TEXT NOT NULL,
+            version INTEGER NOT NULL DEFAULT 1,
+            updated_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_coord_sessions_account ON coord_sessions (account_id);
+        CREATE TABLE IF NOT EXISTS coord_state (
+            key TEXT PRIMARY KEY,
+            value TEXT,
+            version INTEGER NOT NULL DEFAULT 1,
+            updated_at INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS coord_rate_limits (
+            limit_key TEXT PRIMARY KEY,
+            account_id TEXT NOT NULL,
+            model TEXT,
+            reset_at INTEGER NOT NULL,
+            reason TEXT NOT NULL,
+            version INTEGER NOT NULL DEFAULT 1,
+            updated_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_coord_rate_limits_account ON coord_rate_limits (account_id);",
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// 共享熔断记录
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoordRateLimit {
+    pub account_id: String,
+    pub model: Option<String>,
+    /// 解锁时间 (Unix 毫秒)
+    pub reset_at: i64,
+    pub reason: String,
+}
+
+/// 查询会话绑定的账号
+pub fn coord_get_session(session_id: &str) -> Result<Option<String>, String> {
+    let conn = connect_db()?;
+    coord_get_session_in(&conn, session_id)
+}
+
+fn coord_get_session_in(conn: &Connection, session_id: &str) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT account_id FROM coord_sessions WHERE session_id = ?1",
+        params![session_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// 绑定会话 (仅当会话尚未绑定时写入)，返回最终生效的账号
+/// 多个实例同时为同一新会话选号时，先写入者获胜，其余实例采用获胜者的绑定
+pub fn coord_bind_session(session_id: &str, account_id: &str) -> Result<String, String> {
+    let conn = connect_db()?;
+    coord_bind_session_in(&conn, session_id, account_id)
+}
+
+fn coord_bind_session_in(
+    conn: &Connection,
+    session_id: &str,
+    account_id: &str,
+) -> Result<String, String> {
+    conn.execute(
+        "INSERT INTO coord_sessions (session_id, account_id, version, updated_at)
+         VALUES (?1, ?2, 1, ?3)
+         ON CONFLICT(session_id) DO NOTHING",
+        params![session_id, account_id, chrono::Utc::now().timestamp()],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.query_row(
+        "SELECT account_id FROM coord_sessions WHERE session_id = ?1",
+        params![session_id],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// 解除会话绑定；指定 `expected_account` 时仅在绑定未被其他实例改写时删除
+pub fn coord_unbind_session(session_id: &str, expected_account: Option<&str>) -> Result<bool, String> {
+    let conn = connect_db()?;
+    coord_unbind_session_in(&conn, session_id, expected_account)
+}
+
+fn coord_unbind_session_in(
+    conn: &Connection,
+    session_id: &str,
+    expected_account: Option<&str>,
+) -> Result<bool, String> {
+    let affected = match expected_account {
+        Some(account_id) => conn.execute(
+            "DELETE FROM coord_sessions WHERE session_id = ?1 AND account_id = ?2",
+            params![session_id, account_id],
+        ),
+        None => conn.execute(
+            "DELETE FROM coord_sessions WHERE session_id = ?1",
+            params![session_id],
+        ),
+    }
+    .map_err(|e| e.to_string())?;
+    Ok(affected > 0)
+}
+
+/// 解除某账号的全部会话绑定 (account_id 为 None 时清空全部)
+pub fn coord_clear_sessions(account_id: Option<&str>) -> Result<usize, String> {
+    let conn = connect_db()?;
+    coord_clear_sessions_in(&conn, account_id)
+}
+
+fn coord_clear_sessions_in(conn: &Connection, account_id: Option<&str>) -> Result<usize, String> {
+    match account_id {
+        Some(id) => conn.execute("DELETE FROM coord_sessions WHERE account_id = ?1", params![id]),
+        None => conn.execute("DELETE FROM coord_sessions", []),
+    }
+    .map_err(|e| e.to_string())
+}
+
+/// 读取共享状态值及其版本号
+pub fn coord_get_state(key: &str) -> Result<Option<(Option<String>, i64)>, String> {
+    let conn = connect_db()?;
+    coord_get_state_in(&conn, key)
+}
+
+fn coord_get_state_in(
+    conn: &Connection,
+    key: &str,
+) -> Result<Option<(Option<String>, i64)>, String> {
+    conn.query_row(
+        "SELECT value, version FROM coord_state WHERE key = ?1",
+        params![key],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// 乐观锁写入: 仅当当前版本等于 `expected_version` (None 表示该键尚不存在) 时生效
+/// 返回 false 表示期间已被其他实例修改，调用方应重新读取后重试
+pub fn coord_compare_and_set_state(
+    key: &str,
+    value: Option<&str>,
+    expected_version: Option<i64>,
+) -> Result<bool, String> {
+    let conn = connect_db()?;
+    coord_compare_and_set_state_in(&conn, key, value, expected_version)
+}
+
+fn coord_compare_and_set_state_in(
+    conn: &Connection,
+    key: &str,
+    value: Option<&str>,
+    expected_version: Option<i64>,
+) -> Result<bool, String> {
+    let now = chrono::Utc::now().timestamp();
+    let affected = match expected_version {
+        Some(version) => conn.execute(
+            "UPDATE coord_state SET value = ?2, version = version + 1, updated_at = ?3
+             WHERE key = ?1 AND version = ?4",
+            params![key, value, now, version],
+        ),
+        None => conn.execute(
+            "INSERT INTO coord_state (key, value, version, updated_at) VALUES (?1, ?2, 1, ?3)
+             ON CONFLICT(key) DO NOTHING",
+            params![key, value, now],
+        ),
+    }
+    .map_err(|e| e.to_string())?;
+    Ok(affected > 0)
+}
+
+/// 写入共享熔断记录；已有记录时只延长不缩短解锁时间
+pub fn coord_upsert_rate_limit(limit_key: &str, limit: &CoordRateLimit) -> Result<(), String> {
+    let conn = connect_db()?;
+    coord_upsert_rate_limit_in(&conn, limit_key, limit)
+}
+
+fn coord_upsert_rate_limit_in(
+    conn: &Connection,
+    limit_key: &str,
+    limit: &CoordRateLimit,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO coord_rate_limits (limit_key, account_id, model, reset_at, reason, version, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, 1, ?6)
+         ON CONFLICT(limit_key) DO UPDATE SET
+            reset_at = MAX(reset_at, excluded.reset_at),
+            reason = CASE WHEN excluded.reset_at >= reset_at THEN excluded.reason ELSE reason END,
+            version = version + 1,
+            updated_at = excluded.updated_at",
+        params![
+            limit_key,
+            limit.account_id,
+            limit.model,
+            limit.reset_at,
+            limit.reason,
+            chrono::Utc::now().timestamp()
+        ],
+    )
+    .map_err(|e| e.to_string())?;
     Ok(())
 }
 
+/// 读取仍未解锁的共享熔断记录，并顺带清除已过期的记录
+pub fn coord_active_rate_limits() -> Result<Vec<CoordRateLimit>, String> {
+    let conn = connect_db()?;
+    coord_active_rate_limits_in(&conn)
+}
+
+fn coord_active_rate_limits_in(conn: &Connection) -> Result<Vec<CoordRateLimit>, String> {
+    let now = chrono::Utc::now().timestamp_millis();
+    conn.execute("DELETE FROM coord_rate_limits WHERE reset_at <= ?1", params![now])
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare("SELECT account_id, model, reset_at, reason FROM coord_rate_limits")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(CoordRateLimit {
+                account_id: row.get(0)?,
+                model: row.get(1)?,
+                reset_at: row.get(2)?,
+                reason: row.get(3)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+/// 清除某账号的共享熔断记录 (account_id 为 None 时清空全部)
+pub fn coord_clear_rate_limits(account_id: Option<&str>) -> Result<usize, String> {
+    let conn = connect_db()?;
+    coord_clear_rate_limits_in(&conn, account_id)
+}
+
+fn coord_clear_rate_limits_in(conn: &Connection, account_id: Option<&str>) -> Result<usize, String> {
+    match account_id {
+        Some(id) => conn.execute("DELETE FROM coord_rate_limits WHERE account_id = ?1", params![id]),
+        None => conn.execute("DELETE FROM coord_rate_limits", []),
+    }
+    .map_err(|e| e.to_string())
+}
+
 pub fn save_log(log: &ProxyRequestLog) -> Result<(), String> {
     let conn = connect_db()?;
 
     conn.execute(
-        "INSERT INTO request_logs (id, timestamp, method, url, status, duration, model, error, request_body, response_body, input_tokens, output_tokens, account_email, mapped_model, protocol, client_ip, username)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+        "INSERT INTO request_logs (id, timestamp, method, url, status, duration, model, error, request_body, response_body, input_tokens, output_tokens, account_email, mapped_model, protocol, client_ip, username, timeout_secs, cached_tokens, estimated_cost_usd, error_class)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
         params![
             log.id,
             log.timestamp,
@@ -90,6 +445,10 @@ pub fn save_log(log: &ProxyRequestLog) -> Result<(), String> {
             log.protocol,
             log.client_ip,
             log.username,
+            log.timeout_secs,
+            log.cached_tokens,
+            log.estimated_cost_usd,
+            log.error_class,
         ],
     ).map_err(|e| e.to_string())?;
 
@@ -128,6 +487,10 @@ pub fn get_logs_summary(limit: usize, offset: usize) -> Result<Vec<ProxyRequestL
             protocol: row.get(14).unwrap_or(None),
             client_ip: row.get(15).unwrap_or(None),
             username: row.get(16).unwrap_or(None),
+            timeout_secs: row.get(17).unwrap_or(None),
+            cached_tokens: row.get(18).unwrap_or(None),
+            estimated_cost_usd: row.get(19).unwrap_or(None),
+            error_class: row.get(20).unwrap_or(None),
         })
 
     }).map_err(|e| e.to_string())?;
@@ -163,6 +526,7 @@ pub fn get_stats() -> Result<crate::proxy::monitor::ProxyStats, String> {
         total_requests,
         success_count,
         error_count,
+        ..Default::default()
     })
 }
 
@@ -173,7 +537,7 @@ pub fn get_log_detail(log_id: &str) -> Result<ProxyRequestLog, String> {
     let mut stmt = conn.prepare(
         "SELECT id, timestamp, method, url, status, duration, model, error,
                 request_body, response_body, input_tokens, output_tokens,
-                account_email, mapped_model, protocol, client_ip, username
+                account_email, mapped_model, protocol, client_ip, username, timeout_secs, cached_tokens, estimated_cost_usd, error_class
          FROM request_logs
          WHERE id = ?1"
     ).map_err(|e| e.to_string())?;
@@ -197,6 +561,10 @@ pub fn get_log_detail(log_id: &str) -> Result<ProxyRequestLog, String> {
             protocol: row.get(14).unwrap_or(None),
             client_ip: row.get(15).unwrap_or(None),
             username: row.get(16).unwrap_or(None),
+            timeout_secs: row.get(17).unwrap_or(None),
+            cached_tokens: row.get(18).unwrap_or(None),
+            estimated_cost_usd: row.get(19).unwrap_or(None),
+            error_class: row.get(20).unwrap_or(None),
         })
     }).map_err(|e| e.to_string())
 }
@@ -211,6 +579,12 @@ pub fn cleanup_old_logs(days: i64) -> Result<usize, String> {
         "DELETE FROM request_logs WHERE timestamp < ?1",
         [cutoff_timestamp],
     ).map_err(|e| e.to_string())?;
+
+    // [NEW] 故障转移记录同步清理 (毫秒时间戳)
+    let _ = conn.execute(
+        "DELETE FROM failover_events WHERE timestamp < ?1",
+        [chrono::Utc::now().timestamp_millis() - days * 24 * 3600 * 1000],
+    );
     
     // Execute VACUUM to reclaim disk space
     conn.execute("VACUUM", []).map_err(|e| e.to_string())?;
@@ -282,114 +656,113 @@ pub fn get_logs_count_filtered(filter: &str, errors_only: bool) -> Result<u64, S
     Ok(count)
 }
 
+/// 日志列表排序字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSortBy {
+    #[default]
+    Timestamp,
+    Latency,
+    Status,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// ORDER BY 子句；次级排序与主排序同向，使 idx_duration / idx_status_timestamp 可直接用于排序
+fn log_order_clause(sort_by: LogSortBy, order: LogSortOrder) -> String {
+    let dir = match order {
+        LogSortOrder::Asc => "ASC",
+        LogSortOrder::Desc => "DESC",
+    };
+    match sort_by {
+        LogSortBy::Timestamp => format!("timestamp {}", dir),
+        LogSortBy::Latency => format!("duration {dir}, timestamp {dir}", dir = dir),
+        LogSortBy::Status => format!("status {dir}, timestamp {dir}", dir = dir),
+    }
+}
+
 /// Get logs with search filter and pagination
 /// filter: search text to match in url, method, model, or status
 /// errors_only: if true, only return logs with status < 200 or >= 400
 pub fn get_logs_filtered(filter: &str, errors_only: bool, limit: usize, offset: usize) -> Result<Vec<ProxyRequestLog>, String> {
+    get_logs_filtered_sorted(filter, errors_only, LogSortBy::default(), LogSortOrder::default(), limit, offset)
+}
+
+/// 日志列表行 (不含 request/response body)
+fn map_log_summary_row(row: &rusqlite::Row) -> rusqlite::Result<ProxyRequestLog> {
+    Ok(ProxyRequestLog {
+        id: row.get(0)?,
+        timestamp: row.get(1)?,
+        method: row.get(2)?,
+        url: row.get(3)?,
+        status: row.get(4)?,
+        duration: row.get(5)?,
+        model: row.get(6)?,
+        mapped_model: row.get(13).unwrap_or(None),
+        account_email: row.get(12).unwrap_or(None),
+        error: row.get(7)?,
+        request_body: None,
+        response_body: None,
+        input_tokens: row.get(10).unwrap_or(None),
+        output_tokens: row.get(11).unwrap_or(None),
+        protocol: row.get(14).unwrap_or(None),
+        client_ip: row.get(15).unwrap_or(None),
+        username: row.get(16).unwrap_or(None),
+        timeout_secs: row.get(17).unwrap_or(None),
+        cached_tokens: row.get(18).unwrap_or(None),
+        estimated_cost_usd: row.get(19).unwrap_or(None),
+        error_class: row.get(20).unwrap_or(None),
+    })
+}
+
+/// Same as `get_logs_filtered`, ordered by `sort_by` / `order` (default: newest first)
+pub fn get_logs_filtered_sorted(
+    filter: &str,
+    errors_only: bool,
+    sort_by: LogSortBy,
+    order: LogSortOrder,
+    limit: usize,
+    offset: usize,
+) -> Result<Vec<ProxyRequestLog>, String> {
     let conn = connect_db()?;
 
     let filter_pattern = format!("%{}%", filter);
-    
-    let sql = if errors_only {
-        "SELECT id, timestamp, method, url, status, duration, model, error,
-                NULL as request_body, NULL as response_body,
-                input_tokens, output_tokens, account_email, mapped_model, protocol, client_ip, username
-         FROM request_logs
-         WHERE (status < 200 OR status >= 400)
-         ORDER BY timestamp DESC
-         LIMIT ?1 OFFSET ?2"
+    let where_clause = if errors_only {
+        "WHERE (status < 200 OR status >= 400)"
     } else if filter.is_empty() {
-        "SELECT id, timestamp, method, url, status, duration, model, error,
-                NULL as request_body, NULL as response_body,
-                input_tokens, output_tokens, account_email, mapped_model, protocol, client_ip, username
-         FROM request_logs
-         ORDER BY timestamp DESC
-         LIMIT ?1 OFFSET ?2"
+        ""
     } else {
+        "WHERE (url LIKE ?3 OR method LIKE ?3 OR model LIKE ?3 OR CAST(status AS TEXT) LIKE ?3 OR account_email LIKE ?3 OR client_ip LIKE ?3)"
+    };
+    let sql = format!(
         "SELECT id, timestamp, method, url, status, duration, model, error,
                 NULL as request_body, NULL as response_body,
-                input_tokens, output_tokens, account_email, mapped_model, protocol, client_ip, username
+                input_tokens, output_tokens, account_email, mapped_model, protocol, client_ip, username, timeout_secs, cached_tokens, estimated_cost_usd, error_class
          FROM request_logs
-         WHERE (url LIKE ?3 OR method LIKE ?3 OR model LIKE ?3 OR CAST(status AS TEXT) LIKE ?3 OR account_email LIKE ?3 OR client_ip LIKE ?3)
-         ORDER BY timestamp DESC
-         LIMIT ?1 OFFSET ?2"
-    };
-
-    let logs: Vec<ProxyRequestLog> = if filter.is_empty() && !errors_only {
-        let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
-        let logs_iter = stmt.query_map([limit, offset], |row| {
-            Ok(ProxyRequestLog {
-                id: row.get(0)?,
-                timestamp: row.get(1)?,
-                method: row.get(2)?,
-                url: row.get(3)?,
-                status: row.get(4)?,
-                duration: row.get(5)?,
-                model: row.get(6)?,
-                mapped_model: row.get(13).unwrap_or(None),
-                account_email: row.get(12).unwrap_or(None),
-                error: row.get(7)?,
-                request_body: None,
-                response_body: None,
-                input_tokens: row.get(10).unwrap_or(None),
-                output_tokens: row.get(11).unwrap_or(None),
-                protocol: row.get(14).unwrap_or(None),
-                client_ip: row.get(15).unwrap_or(None),
-                username: row.get(16).unwrap_or(None),
-            })
-
-        }).map_err(|e| e.to_string())?;
-        logs_iter.filter_map(|r| r.ok()).collect()
-    } else if errors_only {
-        let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
-        let logs_iter = stmt.query_map([limit, offset], |row| {
-            Ok(ProxyRequestLog {
-                id: row.get(0)?,
-                timestamp: row.get(1)?,
-                method: row.get(2)?,
-                url: row.get(3)?,
-                status: row.get(4)?,
-                duration: row.get(5)?,
-                model: row.get(6)?,
-                mapped_model: row.get(13).unwrap_or(None),
-                account_email: row.get(12).unwrap_or(None),
-                error: row.get(7)?,
-                request_body: None,
-                response_body: None,
-                input_tokens: row.get(10).unwrap_or(None),
-                output_tokens: row.get(11).unwrap_or(None),
-                protocol: row.get(14).unwrap_or(None),
-                client_ip: row.get(15).unwrap_or(None),
-                username: row.get(16).unwrap_or(None),
-            })
-
-        }).map_err(|e| e.to_string())?;
-        logs_iter.filter_map(|r| r.ok()).collect()
+         {}
+         ORDER BY {}
+         LIMIT ?1 OFFSET ?2",
+        where_clause,
+        log_order_clause(sort_by, order)
+    );
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let logs: Vec<ProxyRequestLog> = if filter.is_empty() || errors_only {
+        stmt.query_map(params![limit, offset], map_log_summary_row)
+            .map_err(|e| e.to_string())?
+            .filter_map(|r| r.ok())
+            .collect()
     } else {
-        let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
-        let logs_iter = stmt.query_map(rusqlite::params![limit, offset, filter_pattern], |row| {
-            Ok(ProxyRequestLog {
-                id: row.get(0)?,
-                timestamp: row.get(1)?,
-                method: row.get(2)?,
-                url: row.get(3)?,
-                status: row.get(4)?,
-                duration: row.get(5)?,
-                model: row.get(6)?,
-                mapped_model: row.get(13).unwrap_or(None),
-                account_email: row.get(12).unwrap_or(None),
-                error: row.get(7)?,
-                request_body: None,
-                response_body: None,
-                input_tokens: row.get(10).unwrap_or(None),
-                output_tokens: row.get(11).unwrap_or(None),
-                protocol: row.get(14).unwrap_or(None),
-                client_ip: row.get(15).unwrap_or(None),
-                username: row.get(16).unwrap_or(None),
-            })
-
-        }).map_err(|e| e.to_string())?;
-        logs_iter.filter_map(|r| r.ok()).collect()
+        stmt.query_map(params![limit, offset, filter_pattern], map_log_summary_row)
+            .map_err(|e| e.to_string())?
+            .filter_map(|r| r.ok())
+            .collect()
     };
 
     Ok(logs)
@@ -402,7 +775,7 @@ pub fn get_all_logs_for_export() -> Result<Vec<ProxyRequestLog>, String> {
     let mut stmt = conn.prepare(
         "SELECT id, timestamp, method, url, status, duration, model, error,
                 request_body, response_body, input_tokens, output_tokens,
-                account_email, mapped_model, protocol, client_ip, username
+                account_email, mapped_model, protocol, client_ip, username, timeout_secs, cached_tokens, estimated_cost_usd, error_class
          FROM request_logs
          ORDER BY timestamp DESC"
     ).map_err(|e| e.to_string())?;
@@ -426,6 +799,10 @@ pub fn get_all_logs_for_export() -> Result<Vec<ProxyRequestLog>, String> {
             protocol: row.get(14).unwrap_or(None),
             client_ip: row.get(15).unwrap_or(None),
             username: row.get(16).unwrap_or(None),
+            timeout_secs: row.get(17).unwrap_or(None),
+            cached_tokens: row.get(18).unwrap_or(None),
+            estimated_cost_usd: row.get(19).unwrap_or(None),
+            error_class: row.get(20).unwrap_or(None),
         })
 
     }).map_err(|e| e.to_string())?;
@@ -504,3 +881,125 @@ pub fn get_token_usage_by_ip(limit: usize, hours: i64) -> Result<Vec<IpTokenStat
     Ok(stats)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_order_clause() {
+        assert_eq!(
+            log_order_clause(LogSortBy::default(), LogSortOrder::default()),
+            "timestamp DESC"
+        );
+        assert_eq!(
+            log_order_clause(LogSortBy::Latency, LogSortOrder::Desc),
+            "duration DESC, timestamp DESC"
+        );
+        assert_eq!(
+            log_order_clause(LogSortBy::Status, LogSortOrder::Asc),
+            "status ASC, timestamp ASC"
+        );
+    }
+
+    #[test]
+    fn test_failover_events_roundtrip() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_failover_table(&conn).unwrap();
+        let event = |timestamp: i64, from: &str| FailoverEvent {
+            timestamp,
+            from_account: from.to_string(),
+            to_account: "b@example.com".to_string(),
+            reason: "permission_denied".to_string(),
+            model: Some("gemini-2.5-pro".to_string()),
+        };
+        insert_failover_event(&conn, &event(1_000, "old@example.com")).unwrap();
+        insert_failover_event(&conn, &event(5_000, "a@example.com")).unwrap();
+        insert_failover_event(&conn, &event(9_000, "c@example.com")).unwrap();
+
+        let events = query_failover_events(&conn, 2_000).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0], event(9_000, "c@example.com"));
+        assert_eq!(events[1].from_account, "a@example.com");
+    }
+
+    #[test]
+    fn test_coord_sessions_roundtrip() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_coordination_tables(&conn).unwrap();
+
+        assert_eq!(coord_get_session_in(&conn, "sid-1").unwrap(), None);
+        assert_eq!(coord_bind_session_in(&conn, "sid-1", "acc-a").unwrap(), "acc-a");
+        // 先写入者获胜，后来的实例采用已有绑定
+        assert_eq!(coord_bind_session_in(&conn, "sid-1", "acc-b").unwrap(), "acc-a");
+        assert_eq!(coord_get_session_in(&conn, "sid-1").unwrap().as_deref(), Some("acc-a"));
+
+        // 绑定已被改写时不误删
+        assert!(!coord_unbind_session_in(&conn, "sid-1", Some("acc-b")).unwrap());
+        assert!(coord_unbind_session_in(&conn, "sid-1", Some("acc-a")).unwrap());
+        assert_eq!(coord_get_session_in(&conn, "sid-1").unwrap(), None);
+
+        coord_bind_session_in(&conn, "sid-1", "acc-a").unwrap();
+        coord_bind_session_in(&conn, "sid-2", "acc-a").unwrap();
+        coord_bind_session_in(&conn, "sid-3", "acc-b").unwrap();
+        assert_eq!(coord_clear_sessions_in(&conn, Some("acc-a")).unwrap(), 2);
+        assert_eq!(coord_get_session_in(&conn, "sid-3").unwrap().as_deref(), Some("acc-b"));
+        assert_eq!(coord_clear_sessions_in(&conn, None).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_coord_state_compare_and_set() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_coordination_tables(&conn).unwrap();
+
+        assert_eq!(coord_get_state_in(&conn, "preferred").unwrap(), None);
+        assert!(coord_compare_and_set_state_in(&conn, "preferred", Some("acc-a"), None).unwrap());
+        // 键已存在时按 "不存在" 写入失败
+        assert!(!coord_compare_and_set_state_in(&conn, "preferred", Some("acc-b"), None).unwrap());
+        assert_eq!(
+            coord_get_state_in(&conn, "preferred").unwrap(),
+            Some((Some("acc-a".to_string()), 1))
+        );
+
+        // 版本过期的写入被拒绝，最新版本的写入生效并递增版本
+        assert!(!coord_compare_and_set_state_in(&conn, "preferred", None, Some(0)).unwrap());
+        assert!(coord_compare_and_set_state_in(&conn, "preferred", None, Some(1)).unwrap());
+        assert_eq!(coord_get_state_in(&conn, "preferred").unwrap(), Some((None, 2)));
+    }
+
+    #[test]
+    fn test_coord_rate_limits_only_extend() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_coordination_tables(&conn).unwrap();
+        let now = chrono::Utc::now().timestamp_millis();
+        let limit = |account_id: &str, reset_at: i64, reason: &str| CoordRateLimit {
+            account_id: account_id.to_string(),
+            model: None,
+            reset_at,
+            reason: reason.to_string(),
+        };
+
+        coord_upsert_rate_limit_in(&conn, "acc-a", &limit("acc-a", now + 60_000, "quota_exhausted"))
+            .unwrap();
+        // 更早的解锁时间不会缩短已有锁定
+        coord_upsert_rate_limit_in(&conn, "acc-a", &limit("acc-a", now + 10_000, "rate_limit_exceeded"))
+            .unwrap();
+        coord_upsert_rate_limit_in(&conn, "acc-b", &limit("acc-b", now - 1_000, "server_error"))
+            .unwrap();
+        coord_upsert_rate_limit_in(&conn, "acc-c", &limit("acc-c", now + 5_000, "server_error"))
+            .unwrap();
+
+        let mut active = coord_active_rate_limits_in(&conn).unwrap();
+        active.sort_by(|a, b| a.account_id.cmp(&b.account_id));
+        assert_eq!(
+            active,
+            vec![
+                limit("acc-a", now + 60_000, "quota_exhausted"),
+                limit("acc-c", now + 5_000, "server_error"),
+            ]
+        );
+
+        assert_eq!(coord_clear_rate_limits_in(&conn, Some("acc-a")).unwrap(), 1);
+        assert_eq!(coord_clear_rate_limits_in(&conn, None).unwrap(), 1);
+        assert!(coord_active_rate_limits_in(&conn).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,349 @@
+// 配额耗尽预测
+// 按模型汇总账号池的剩余配额 (QuotaData.models[].percentage) 与最近的 Token 消耗速度
+// (token_stats::get_upstream_model_trend_hourly)，估算按当前速度多久耗尽；
+// 配额会在 reset_time 恢复，因此按时间步模拟消耗与重置，而不是简单的 剩余 / 速度
+use chrono::{DateTime, NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::models::Account;
+use crate::modules::token_stats::{self, ModelTrendPoint};
+use crate::modules::{account, config, logger};
+
+/// 配额窗口时长 (小时): 重置后下一次重置在此之后
+pub const QUOTA_WINDOW_HOURS: f64 = 5.0;
+/// 估算当前消耗速度时回看的小时数
+const BURN_LOOKBACK_HOURS: f64 = 3.0;
+/// 读取的小时级趋势范围 (需覆盖一个完整配额窗口)
+const TREND_HOURS: i64 = 24;
+/// 模拟的最长时间，超出视为不会耗尽
+const HORIZON_HOURS: f64 = 48.0;
+const STEP_HOURS: f64 = 5.0 / 60.0;
+
+/// 单个账号某模型的配额状态
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountQuotaSnapshot {
+    /// 剩余百分比 0-100
+    pub remaining: f64,
+    /// 距离重置的小时数 (无法解析 reset_time 时为 None)
+    pub hours_to_reset: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelForecast {
+    pub model: String,
+    pub accounts: usize,
+    /// 账号平均剩余百分比
+    pub remaining_percent: f64,
+    pub burn_tokens_per_hour: f64,
+    /// 每 1% 配额对应的 Token 数 (由当前窗口内已用百分比与 Token 消耗推算)
+    pub tokens_per_percent: Option<f64>,
+    /// 预计耗尽时间 (小时)，None 表示数据不足或预测范围内不会耗尽
+    pub hours_to_exhaustion: Option<f64>,
+    pub next_reset: Option<String>,
+    pub warning: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaForecast {
+    pub generated_at: i64,
+    pub threshold_hours: f64,
+    pub models: Vec<ModelForecast>,
+    pub warning_models: Vec<String>,
+}
+
+/// 最近一次预测中低于阈值的模型 (供 /api/proxy/status 使用)
+static WARNING_MODELS: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+fn hours_until(reset_time: &str, now: DateTime<Utc>) -> Option<f64> {
+    let reset = DateTime::parse_from_rfc3339(reset_time.trim()).ok()?;
+    Some((reset.with_timezone(&Utc) - now).num_seconds() as f64 / 3600.0)
+}
+
+/// 小时桶 `YYYY-MM-DD HH:00` (UTC) 的起始时间戳
+fn bucket_start(period: &str) -> Option<i64> {
+    NaiveDateTime::parse_from_str(&format!("{}:00", period), "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|dt| dt.and_utc().timestamp())
+}
+
+/// `since` 之后该模型消耗的 Token；跨越 `since` 或当前小时的桶按时间比例计入
+fn tokens_since(trend: &[ModelTrendPoint], model: &str, since: i64, now: i64) -> f64 {
+    trend
+        .iter()
+        .filter_map(|point| {
+            let tokens = *point.model_data.get(model)? as f64;
+            let start = bucket_start(&point.period)?;
+            let end = (start + 3600).min(now).max(start + 1);
+            let overlap = (end - start.max(since)).max(0) as f64;
+            Some(tokens * overlap / (end - start) as f64)
+        })
+        .sum()
+}
+
+/// 按当前消耗速度 (百分比/小时，账号间平均分摊) 模拟，返回全部账号剩余为 0 的时间
+pub fn simulate_exhaustion(
+    accounts: &[AccountQuotaSnapshot],
+    burn_percent_per_hour: f64,
+    horizon_hours: f64,
+) -> Option<f64> {
+    if accounts.is_empty() || burn_percent_per_hour <= 0.0 {
+        return None;
+    }
+    let mut remaining: Vec<f64> = accounts.iter().map(|a| a.remaining.max(0.0)).collect();
+    let mut resets: Vec<Option<f64>> = accounts.iter().map(|a| a.hours_to_reset).collect();
+
+    let mut t = 0.0;
+    while t < horizon_hours {
+        for (r, reset) in remaining.iter_mut().zip(resets.iter_mut()) {
+            if let Some(at) = reset {
+                if *at <= t {
+                    *r = 100.0;
+                    *at = at.max(t) + QUOTA_WINDOW_HOURS;
+                }
+            }
+        }
+
+        let before: f64 = remaining.iter().sum();
+        let mut demand = burn_percent_per_hour * STEP_HOURS;
+        while demand > 1e-9 {
+            let active = remaining.iter().filter(|r| **r > 0.0).count();
+            if active == 0 {
+                break;
+            }
+            let share = demand / active as f64;
+            demand = 0.0;
+            for r in remaining.iter_mut().filter(|r| **r > 0.0) {
+                if *r >= share {
+                    *r -= share;
+                } else {
+                    demand += share - *r;
+                    *r = 0.0;
+                }
+            }
+        }
+        if remaining.iter().all(|r| *r <= 0.0) {
+            return Some(t + before / burn_percent_per_hour);
+        }
+        t += STEP_HOURS;
+    }
+    None
+}
+
+fn forecast_model(
+    model: &str,
+    snapshots: &[AccountQuotaSnapshot],
+    next_reset: Option<String>,
+    trend: &[ModelTrendPoint],
+    now: i64,
+    threshold_hours: f64,
+) -> ModelForecast {
+    let burn_tokens_per_hour = tokens_since(
+        trend,
+        model,
+        now - (BURN_LOOKBACK_HOURS * 3600.0) as i64,
+        now,
+    ) / BURN_LOOKBACK_HOURS;
+
+    // 当前窗口内已用的百分比与同期 Token 消耗 -> 每 1% 对应的 Token 数
+    let used_percent: f64 = snapshots.iter().map(|s| 100.0 - s.remaining).sum();
+    let window_elapsed = snapshots
+        .iter()
+        .map(|s| match s.hours_to_reset {
+            Some(h) => (QUOTA_WINDOW_HOURS - h).clamp(0.0, QUOTA_WINDOW_HOURS),
+            None => QUOTA_WINDOW_HOURS,
+        })
+        .fold(0.0, f64::max);
+    let window_tokens = tokens_since(trend, model, now - (window_elapsed * 3600.0) as i64, now);
+    let tokens_per_percent =
+        (used_percent > 0.0 && window_tokens > 0.0).then(|| window_tokens / used_percent);
+
+    let hours_to_exhaustion = tokens_per_percent.and_then(|k| {
+        simulate_exhaustion(snapshots, burn_tokens_per_hour / k, HORIZON_HOURS)
+    });
+
+    ModelForecast {
+        model: model.to_string(),
+        accounts: snapshots.len(),
+        remaining_percent: snapshots.iter().map(|s| s.remaining).sum::<f64>()
+            / snapshots.len().max(1) as f64,
+        burn_tokens_per_hour,
+        tokens_per_percent,
+        hours_to_exhaustion,
+        next_reset,
+        warning: hours_to_exhaustion.is_some_and(|h| h < threshold_hours),
+    }
+}
+
+/// 根据账号配额与小时级趋势计算预测 (不访问磁盘，便于测试)
+pub fn compute_forecast(
+    accounts: &[Account],
+    trend: &[ModelTrendPoint],
+    now: DateTime<Utc>,
+    threshold_hours: f64,
+) -> QuotaForecast {
+    // 模型 -> (各账号快照, 最早的下一次重置)
+    let mut per_model: BTreeMap<String, (Vec<AccountQuotaSnapshot>, Option<(f64, String)>)> =
+        BTreeMap::new();
+    for acc in accounts.iter().filter(|a| !a.disabled && !a.proxy_disabled) {
+        let Some(quota) = acc.quota.as_ref().filter(|q| !q.is_forbidden) else {
+            continue;
+        };
+        for m in &quota.models {
+            let hours_to_reset = hours_until(&m.reset_time, now);
+            let entry = per_model.entry(m.name.clone()).or_default();
+            entry.0.push(AccountQuotaSnapshot {
+                remaining: m.percentage.clamp(0, 100) as f64,
+                hours_to_reset,
+            });
+            if let Some(h) = hours_to_reset.filter(|h| *h > 0.0) {
+                let earlier = match &entry.1 {
+                    Some((earliest, _)) => h < *earliest,
+                    None => true,
+                };
+                if earlier {
+                    entry.1 = Some((h, m.reset_time.clone()));
+                }
+            }
+        }
+    }
+
+    let now_ts = now.timestamp();
+    let models: Vec<ModelForecast> = per_model
+        .into_iter()
+        .map(|(model, (snapshots, next_reset))| {
+            forecast_model(
+                &model,
+                &snapshots,
+                next_reset.map(|(_, r)| r),
+                trend,
+                now_ts,
+                threshold_hours,
+            )
+        })
+        .collect();
+    let warning_models = models
+        .iter()
+        .filter(|m| m.warning)
+        .map(|m| m.model.clone())
+        .collect();
+
+    QuotaForecast {
+        generated_at: now_ts,
+        threshold_hours,
+        models,
+        warning_models,
+    }
+}
+
+/// 读取账号与 Token 统计计算预测，并更新告警状态 (阻塞 IO，异步上下文中请放入 spawn_blocking)
+pub fn refresh_forecast() -> Result<QuotaForecast, String> {
+    let threshold_hours = config::load_app_config()
+        .map(|c| c.quota_forecast_warning_hours)
+        .unwrap_or(crate::models::config::DEFAULT_QUOTA_FORECAST_WARNING_HOURS);
+    let accounts = account::list_accounts()?;
+    // 配额按上游模型计算，消耗速度也按映射后的模型统计 (无映射记录时回退到请求模型)
+    let trend = token_stats::get_upstream_model_trend_hourly(TREND_HOURS)?;
+    let forecast = compute_forecast(&accounts, &trend, Utc::now(), threshold_hours);
+    record_warnings(&forecast);
+    Ok(forecast)
+}
+
+/// 更新告警模型列表；新进入告警的模型输出一次警告日志
+fn record_warnings(forecast: &QuotaForecast) {
+    let Ok(mut current) = WARNING_MODELS.lock() else {
+        return;
+    };
+    for m in forecast.models.iter().filter(|m| m.warning) {
+        if !current.contains(&m.model) {
+            logger::log_warn(&format!(
+                "[Quota-Forecast] {} quota across {} account(s) exhausts in ~{:.1}h at current burn rate (threshold {}h)",
+                m.model,
+                m.accounts,
+                m.hours_to_exhaustion.unwrap_or_default(),
+                forecast.threshold_hours
+            ));
+        }
+    }
+    *current = forecast.warning_models.clone();
+}
+
+pub fn current_warnings() -> Vec<String> {
+    WARNING_MODELS.lock().map(|w| w.clone()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn snapshot(remaining: f64, hours_to_reset: Option<f64>) -> AccountQuotaSnapshot {
+        AccountQuotaSnapshot {
+            remaining,
+            hours_to_reset,
+        }
+    }
+
+    #[test]
+    fn test_simulate_monotonic_drain() {
+        let h = simulate_exhaustion(&[snapshot(50.0, None), snapshot(30.0, None)], 20.0, 48.0);
+        assert!((h.unwrap() - 4.0).abs() < 1e-6, "{:?}", h);
+        assert_eq!(simulate_exhaustion(&[snapshot(50.0, None)], 0.0, 48.0), None);
+    }
+
+    #[test]
+    fn test_simulate_with_reset() {
+        // 25%/h 消耗 20%，0.8 小时耗尽；但 0.5 小时后重置为 100%，再过 4 小时才耗尽
+        let no_reset = simulate_exhaustion(&[snapshot(20.0, None)], 25.0, 48.0).unwrap();
+        assert!((no_reset - 0.8).abs() < 1e-6);
+        let with_reset = simulate_exhaustion(&[snapshot(20.0, Some(0.5))], 25.0, 48.0).unwrap();
+        assert!((with_reset - 4.5).abs() < 0.1, "{}", with_reset);
+        // 每个窗口的配额足以覆盖消耗时不会耗尽
+        assert_eq!(simulate_exhaustion(&[snapshot(20.0, Some(1.0))], 5.0, 48.0), None);
+    }
+
+    #[test]
+    fn test_compute_forecast_flags_fast_burn() {
+        let now = DateTime::parse_from_rfc3339("2026-03-01T12:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let trend: Vec<ModelTrendPoint> = ["2026-03-01 10:00", "2026-03-01 11:00", "2026-03-01 12:00"]
+            .iter()
+            .map(|p| ModelTrendPoint {
+                period: p.to_string(),
+                model_data: HashMap::from([("gemini-3-pro-high".to_string(), 100_000)]),
+            })
+            .collect();
+
+        let mut acc = Account::new(
+            "a1".to_string(),
+            "a@example.com".to_string(),
+            crate::models::TokenData::new(
+                "at".to_string(),
+                "rt".to_string(),
+                3600,
+                None,
+                None,
+                None,
+            ),
+        );
+        let mut quota = crate::models::QuotaData::new();
+        quota.add_model(
+            "gemini-3-pro-high".to_string(),
+            10,
+            "2026-03-01T16:00:00Z".to_string(),
+        );
+        acc.quota = Some(quota);
+
+        let forecast = compute_forecast(&[acc], &trend, now, 2.0);
+        let m = &forecast.models[0];
+        assert_eq!(m.model, "gemini-3-pro-high");
+        assert!(m.tokens_per_percent.is_some());
+        assert!(m.hours_to_exhaustion.unwrap() < 2.0, "{:?}", m);
+        assert!(m.warning);
+        assert_eq!(forecast.warning_models, vec!["gemini-3-pro-high".to_string()]);
+        assert_eq!(m.next_reset.as_deref(), Some("2026-03-01T16:00:00Z"));
+    }
+}
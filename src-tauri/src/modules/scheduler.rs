@@ -1,6 +1,9 @@
-use chrono::Utc;
+use chrono::{DateTime, TimeZone, Utc};
+use futures::stream::{self, StreamExt};
 use once_cell::sync::Lazy;
+use serde::Serialize;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 use tokio::time::{self, Duration};
 use crate::modules::{config, logger, quota, account};
@@ -52,6 +55,11 @@ pub fn check_cooldown(key: &str, cooldown_seconds: i64) -> bool {
 }
 
 pub fn start_scheduler(app_handle: Option<tauri::AppHandle>, proxy_state: crate::commands::proxy::ProxyServiceState) {
+    start_idle_account_check(proxy_state.clone());
+    start_warmup_schedule(proxy_state.clone());
+    start_quota_forecast_check();
+    start_backup_schedule();
+
     tauri::async_runtime::spawn(async move {
         logger::log_info("Smart Warmup Scheduler started. Monitoring quota at 100%...");
         
@@ -264,6 +272,47 @@ pub fn start_scheduler(app_handle: Option<tauri::AppHandle>, proxy_state: crate:
     });
 }
 
+/// 每日扫描闲置账号并自动停用 (阈值为 `proxy.idle_disable_after_days`)
+fn start_idle_account_check(proxy_state: crate::commands::proxy::ProxyServiceState) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(86400));
+
+        loop {
+            interval.tick().await;
+
+            let Ok(app_config) = config::load_app_config() else {
+                continue;
+            };
+            let Some(days) = app_config.proxy.idle_disable_after_days.filter(|d| *d > 0) else {
+                continue;
+            };
+
+            let disabled = match account::disable_idle_accounts(days) {
+                Ok(list) => list,
+                Err(e) => {
+                    logger::log_warn(&format!("[Scheduler] Idle account check failed: {}", e));
+                    continue;
+                }
+            };
+            if disabled.is_empty() {
+                continue;
+            }
+
+            logger::log_info(&format!(
+                "[Scheduler] Idle account check disabled {} account(s)",
+                disabled.len()
+            ));
+
+            // 同步到运行中的反代服务
+            if let Some(instance) = proxy_state.instance.read().await.as_ref() {
+                for item in &disabled {
+                    let _ = instance.token_manager.reload_account(&item.account_id).await;
+                }
+            }
+        }
+    });
+}
+
 /// Trigger immediate smart warmup check for a single account
 pub async fn trigger_warmup_for_account(account: &Account) {
     if account.disabled || account.proxy_disabled {
@@ -346,3 +395,240 @@ pub async fn trigger_warmup_for_account(account: &Account) {
         }
     }
 }
+
+/// 定时预热最近一次执行时间 (None 表示本次启动后尚未执行，回退到账号上记录的最近预热时间)
+static WARMUP_SCHEDULE_LAST_RUN: Lazy<Mutex<Option<i64>>> = Lazy::new(|| Mutex::new(None));
+static WARMUP_SCHEDULE_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// 定时预热运行状态
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmupScheduleStatus {
+    pub running: bool,
+    pub last_run_at: Option<i64>,
+    pub next_run_at: Option<i64>,
+}
+
+fn warmup_schedule_last_run() -> Option<i64> {
+    if let Some(ts) = *WARMUP_SCHEDULE_LAST_RUN.lock().unwrap() {
+        return Some(ts);
+    }
+    account::list_accounts()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|a| a.last_warmup.as_ref().map(|w| w.at))
+        .max()
+}
+
+/// 计算下一次定时预热时间 (Unix 秒)，未配置时返回 None
+pub fn next_warmup_at<Tz: TimeZone>(
+    schedule: &crate::proxy::config::WarmupScheduleConfig,
+    last_run: Option<i64>,
+    now: &DateTime<Tz>,
+) -> Option<i64> {
+    if let Some((hour, minute)) = schedule.daily_time_hm() {
+        let today = now.date_naive().and_hms_opt(hour, minute, 0)?;
+        let today_ts = now.timezone().from_local_datetime(&today).earliest()?.timestamp();
+        // 今天的时间点已执行过则顺延到明天 (启动时已过今天的时间点且未执行则立即补跑)
+        return Some(match last_run {
+            Some(last) if last >= today_ts => today_ts + 86400,
+            _ => today_ts,
+        });
+    }
+    let hours = schedule.interval_hours.filter(|h| *h > 0)?;
+    Some(match last_run {
+        Some(last) => last + hours as i64 * 3600,
+        None => now.timestamp(),
+    })
+}
+
+pub fn get_warmup_schedule_status() -> WarmupScheduleStatus {
+    let schedule = config::load_app_config()
+        .map(|c| c.proxy.warmup_schedule)
+        .unwrap_or_default();
+    let last_run_at = warmup_schedule_last_run();
+    WarmupScheduleStatus {
+        running: WARMUP_SCHEDULE_RUNNING.load(Ordering::SeqCst),
+        last_run_at,
+        next_run_at: if schedule.enabled {
+            next_warmup_at(&schedule, last_run_at, &chrono::Local::now())
+        } else {
+            None
+        },
+    }
+}
+
+/// 预热单个账号并记录结果到账号文件
+pub async fn warm_up_and_record(account_id: &str) -> Result<String, String> {
+    let result = quota::warm_up_account(account_id).await;
+    if let Err(e) = account::record_warmup_result(account_id, &result) {
+        logger::log_warn(&format!(
+            "[Warmup-Schedule] Failed to record warmup result for {}: {}",
+            account_id, e
+        ));
+    }
+    result
+}
+
+/// 每 10 分钟重新计算配额耗尽预测，低于 quota_forecast_warning_hours 的模型记录告警
+fn start_quota_forecast_check() {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(600));
+
+        loop {
+            interval.tick().await;
+            match tokio::task::spawn_blocking(crate::modules::quota_forecast::refresh_forecast).await {
+                Ok(Err(e)) => logger::log_warn(&format!("[Quota-Forecast] Forecast failed: {}", e)),
+                Err(e) => logger::log_warn(&format!("[Quota-Forecast] Forecast task failed: {}", e)),
+                Ok(Ok(_)) => {}
+            }
+        }
+    });
+}
+
+/// 按 `backup` 配置定时备份配置与账号数据 (每 10 分钟检查一次是否到期，配置修改后无需重启)
+fn start_backup_schedule() {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(600));
+
+        loop {
+            interval.tick().await;
+
+            let Ok(app_config) = config::load_app_config() else {
+                continue;
+            };
+            let backup = app_config.backup;
+            if !backup.enabled || backup.interval_hours == 0 {
+                continue;
+            }
+            match tokio::task::spawn_blocking(move || crate::modules::backup::run_scheduled_backup(&backup)).await {
+                Ok(Ok(Some(info))) => logger::log_info(&format!(
+                    "[Backup] Created {} ({} bytes)",
+                    info.name, info.size
+                )),
+                Ok(Ok(None)) => {}
+                Ok(Err(e)) => logger::log_warn(&format!("[Backup] Scheduled backup failed: {}", e)),
+                Err(e) => logger::log_warn(&format!("[Backup] Backup task failed: {}", e)),
+            }
+        }
+    });
+}
+
+/// 按 `proxy.warmup_schedule` 定时预热账号 (每分钟检查一次是否到期，配置修改后无需重启)
+fn start_warmup_schedule(proxy_state: crate::commands::proxy::ProxyServiceState) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(60));
+
+        loop {
+            interval.tick().await;
+
+            let Ok(app_config) = config::load_app_config() else {
+                continue;
+            };
+            let schedule = app_config.proxy.warmup_schedule;
+            if !schedule.enabled || WARMUP_SCHEDULE_RUNNING.load(Ordering::SeqCst) {
+                continue;
+            }
+            let now = chrono::Local::now();
+            let Some(next_at) = next_warmup_at(&schedule, warmup_schedule_last_run(), &now) else {
+                continue;
+            };
+            if next_at > now.timestamp() {
+                continue;
+            }
+
+            WARMUP_SCHEDULE_RUNNING.store(true, Ordering::SeqCst);
+            *WARMUP_SCHEDULE_LAST_RUN.lock().unwrap() = Some(now.timestamp());
+            run_scheduled_warmup(&schedule, &proxy_state).await;
+            WARMUP_SCHEDULE_RUNNING.store(false, Ordering::SeqCst);
+        }
+    });
+}
+
+async fn run_scheduled_warmup(
+    schedule: &crate::proxy::config::WarmupScheduleConfig,
+    proxy_state: &crate::commands::proxy::ProxyServiceState,
+) {
+    let Ok(accounts) = account::list_accounts() else {
+        return;
+    };
+    let now_ts = Utc::now().timestamp();
+
+    let mut targets = Vec::new();
+    let mut skipped = 0;
+    {
+        let instance = proxy_state.instance.read().await;
+        for acc in accounts.iter().filter(|a| schedule.includes_account(&a.id)) {
+            let blocked = acc.validation_blocked
+                && acc.validation_blocked_until.map_or(true, |until| until > now_ts);
+            // 限流冷却中的账号本身就不可用，预热只会再次触发 429
+            let cooling_down = match instance.as_ref() {
+                Some(inst) => inst.token_manager.is_rate_limited(&acc.id, None).await,
+                None => false,
+            };
+            if acc.disabled || acc.proxy_disabled || blocked || cooling_down {
+                skipped += 1;
+                continue;
+            }
+            targets.push(acc.id.clone());
+        }
+    }
+
+    logger::log_info(&format!(
+        "[Warmup-Schedule] Warming up {} account(s), skipped {} disabled/cooling-down",
+        targets.len(),
+        skipped
+    ));
+
+    let results: Vec<bool> = stream::iter(targets)
+        .map(|id| async move { warm_up_and_record(&id).await.is_ok() })
+        .buffer_unordered(schedule.max_concurrency.max(1))
+        .collect()
+        .await;
+
+    logger::log_info(&format!(
+        "[Warmup-Schedule] ✅ Completed: {}/{} successful",
+        results.iter().filter(|ok| **ok).count(),
+        results.len()
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::config::WarmupScheduleConfig;
+
+    fn schedule(daily: Option<&str>, interval: Option<u64>) -> WarmupScheduleConfig {
+        WarmupScheduleConfig {
+            enabled: true,
+            interval_hours: interval,
+            daily_time: daily.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_next_warmup_daily_time() {
+        let at = |h, m| Utc.with_ymd_and_hms(2026, 3, 2, h, m, 0).unwrap();
+        let daily = schedule(Some("08:00"), Some(1));
+        let eight = at(8, 0).timestamp();
+
+        // 尚未到时间
+        assert_eq!(next_warmup_at(&daily, None, &at(6, 0)), Some(eight));
+        // 已过时间但今天尚未执行 -> 立即到期
+        assert_eq!(next_warmup_at(&daily, Some(eight - 86400), &at(9, 0)), Some(eight));
+        // 今天已执行 -> 明天
+        assert_eq!(next_warmup_at(&daily, Some(eight + 60), &at(9, 0)), Some(eight + 86400));
+    }
+
+    #[test]
+    fn test_next_warmup_interval() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 2, 12, 0, 0).unwrap();
+        let every_six = schedule(None, Some(6));
+        assert_eq!(next_warmup_at(&every_six, None, &now), Some(now.timestamp()));
+        assert_eq!(
+            next_warmup_at(&every_six, Some(now.timestamp() - 3600), &now),
+            Some(now.timestamp() + 5 * 3600)
+        );
+        assert_eq!(next_warmup_at(&schedule(None, None), None, &now), None);
+    }
+}
@@ -515,42 +515,14 @@ pub fn get_blacklist_entry_for_ip(ip: &str) -> Result<Option<IpBlacklistEntry>,
     Ok(None)
 }
 
-/// 简单的 CIDR 匹配
-fn cidr_match(ip: &str, cidr: &str) -> bool {
-    let parts: Vec<&str> = cidr.split('/').collect();
-    if parts.len() != 2 {
+/// CIDR 匹配 (支持 IPv4 / IPv6，单个 IP 视为 /32 或 /128)
+pub(crate) fn cidr_match(ip: &str, cidr: &str) -> bool {
+    let Ok(addr) = ip.trim().parse::<std::net::IpAddr>() else {
         return false;
-    }
-
-    let network = parts[0];
-    let prefix_len: u8 = match parts[1].parse() {
-        Ok(p) => p,
-        Err(_) => return false,
     };
-
-    let ip_parts: Vec<u8> = ip
-        .split('.')
-        .filter_map(|s| s.parse().ok())
-        .collect();
-    let net_parts: Vec<u8> = network
-        .split('.')
-        .filter_map(|s| s.parse().ok())
-        .collect();
-
-    if ip_parts.len() != 4 || net_parts.len() != 4 {
-        return false;
-    }
-
-    let ip_u32 = u32::from_be_bytes([ip_parts[0], ip_parts[1], ip_parts[2], ip_parts[3]]);
-    let net_u32 = u32::from_be_bytes([net_parts[0], net_parts[1], net_parts[2], net_parts[3]]);
-
-    let mask = if prefix_len == 0 {
-        0
-    } else {
-        !0u32 << (32 - prefix_len)
-    };
-
-    (ip_u32 & mask) == (net_u32 & mask)
+    crate::proxy::security::parse_cidr(cidr)
+        .map(|net| net.contains(&addr))
+        .unwrap_or(false)
 }
 
 // ============================================================================
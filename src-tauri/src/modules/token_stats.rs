@@ -12,6 +12,18 @@ pub struct TokenStatsAggregated {
     pub request_count: u64,
 }
 
+/// One row of the token usage export (bucketed by account + model)
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenUsageExportRow {
+    /// Bucket start (Unix seconds, UTC)
+    pub timestamp: i64,
+    pub account_email: String,
+    pub model: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub requests: u64,
+}
+
 /// Per-account token statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccountTokenStats {
@@ -20,6 +32,9 @@ pub struct AccountTokenStats {
     pub total_output_tokens: u64,
     pub total_tokens: u64,
     pub request_count: u64,
+    /// 按模型单价估算的费用 (USD)
+    #[serde(default)]
+    pub estimated_cost_usd: f64,
 }
 
 /// Summary statistics
@@ -30,6 +45,12 @@ pub struct TokenStatsSummary {
     pub total_tokens: u64,
     pub total_requests: u64,
     pub unique_accounts: u64,
+    /// 上游缓存命中的输入 Token
+    #[serde(default)]
+    pub total_cached_tokens: u64,
+    /// 按模型单价估算的费用 (USD)
+    #[serde(default)]
+    pub estimated_cost_usd: f64,
 }
 
 /// Per-model token statistics
@@ -78,7 +99,10 @@ fn connect_db() -> Result<Connection, String> {
 /// Initialize the token stats database
 pub fn init_db() -> Result<(), String> {
     let conn = connect_db()?;
+    init_schema(&conn)
+}
 
+fn init_schema(conn: &Connection) -> Result<(), String> {
     // Create main usage table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS token_usage (
@@ -122,15 +146,108 @@ pub fn init_db() -> Result<(), String> {
     )
     .map_err(|e| e.to_string())?;
 
+    // [NEW] 缓存命中 Token 列 (旧库迁移，列已存在时忽略错误)
+    let _ = conn.execute(
+        "ALTER TABLE token_usage ADD COLUMN cached_tokens INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE token_stats_hourly ADD COLUMN total_cached_tokens INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+
+    // [NEW] 估算费用列 (美元)
+    let _ = conn.execute(
+        "ALTER TABLE token_usage ADD COLUMN estimated_cost_usd REAL NOT NULL DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE token_stats_hourly ADD COLUMN total_cost_usd REAL NOT NULL DEFAULT 0",
+        [],
+    );
+
+    // [NEW] 映射后的上游模型 (配额按上游模型计算；旧记录为 NULL)
+    let _ = conn.execute("ALTER TABLE token_usage ADD COLUMN mapped_model TEXT", []);
+
     Ok(())
 }
 
+/// [NEW] 清除结果: 删除的原始记录与小时聚合行数
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ClearStatsResult {
+    pub usage_rows: usize,
+    pub hourly_rows: usize,
+}
+
+/// 删除 Token 用量记录；`before` (unix 秒) 为 None 时清空全部，否则只删除早于该时间的记录
+pub fn clear_stats(before: Option<i64>) -> Result<ClearStatsResult, String> {
+    let mut conn = connect_db()?;
+    clear_stats_in(&mut conn, before)
+}
+
+fn clear_stats_in(conn: &mut Connection, before: Option<i64>) -> Result<ClearStatsResult, String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let result = match before {
+        None => ClearStatsResult {
+            usage_rows: tx
+                .execute("DELETE FROM token_usage", [])
+                .map_err(|e| e.to_string())?,
+            hourly_rows: tx
+                .execute("DELETE FROM token_stats_hourly", [])
+                .map_err(|e| e.to_string())?,
+        },
+        Some(before) => {
+            let usage_rows = tx
+                .execute("DELETE FROM token_usage WHERE timestamp < ?1", params![before])
+                .map_err(|e| e.to_string())?;
+
+            // 完全早于截止时间的小时桶直接删除；截止时间所在的小时桶按剩余原始记录重建
+            let hour_start = before - before.rem_euclid(3600);
+            let bucket = chrono::DateTime::from_timestamp(hour_start, 0)
+                .ok_or_else(|| format!("Invalid timestamp: {}", before))?
+                .format("%Y-%m-%d %H:00")
+                .to_string();
+            let hourly_rows = tx
+                .execute(
+                    "DELETE FROM token_stats_hourly WHERE hour_bucket < ?1",
+                    params![bucket],
+                )
+                .map_err(|e| e.to_string())?;
+            if usage_rows > 0 {
+                tx.execute(
+                    "DELETE FROM token_stats_hourly WHERE hour_bucket = ?1",
+                    params![bucket],
+                )
+                .map_err(|e| e.to_string())?;
+                tx.execute(
+                    "INSERT INTO token_stats_hourly (hour_bucket, account_email, total_input_tokens, total_output_tokens, total_tokens, request_count, total_cached_tokens, total_cost_usd)
+                     SELECT ?1, account_email, SUM(input_tokens), SUM(output_tokens), SUM(total_tokens), COUNT(*), SUM(cached_tokens), SUM(estimated_cost_usd)
+                     FROM token_usage
+                     WHERE timestamp >= ?2 AND timestamp < ?3
+                     GROUP BY account_email",
+                    params![bucket, hour_start, hour_start + 3600],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            ClearStatsResult {
+                usage_rows,
+                hourly_rows,
+            }
+        }
+    };
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(result)
+}
+
 /// Record token usage from a request
 pub fn record_usage(
     account_email: &str,
     model: &str,
+    mapped_model: Option<&str>,
     input_tokens: u32,
     output_tokens: u32,
+    cached_tokens: u32,
+    estimated_cost_usd: f64,
 ) -> Result<(), String> {
     let conn = connect_db()?;
     let timestamp = chrono::Utc::now().timestamp();
@@ -138,26 +255,114 @@ pub fn record_usage(
 
     // Insert into raw usage table
     conn.execute(
-        "INSERT INTO token_usage (timestamp, account_email, model, input_tokens, output_tokens, total_tokens)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![timestamp, account_email, model, input_tokens, output_tokens, total_tokens],
+        "INSERT INTO token_usage (timestamp, account_email, model, input_tokens, output_tokens, total_tokens, cached_tokens, estimated_cost_usd, mapped_model)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![timestamp, account_email, model, input_tokens, output_tokens, total_tokens, cached_tokens, estimated_cost_usd, mapped_model],
     ).map_err(|e| e.to_string())?;
 
     let hour_bucket = chrono::Utc::now().format("%Y-%m-%d %H:00").to_string();
     conn.execute(
-        "INSERT INTO token_stats_hourly (hour_bucket, account_email, total_input_tokens, total_output_tokens, total_tokens, request_count)
-         VALUES (?1, ?2, ?3, ?4, ?5, 1)
+        "INSERT INTO token_stats_hourly (hour_bucket, account_email, total_input_tokens, total_output_tokens, total_tokens, request_count, total_cached_tokens, total_cost_usd)
+         VALUES (?1, ?2, ?3, ?4, ?5, 1, ?6, ?7)
          ON CONFLICT(hour_bucket, account_email) DO UPDATE SET
             total_input_tokens = total_input_tokens + ?3,
             total_output_tokens = total_output_tokens + ?4,
             total_tokens = total_tokens + ?5,
-            request_count = request_count + 1",
-        params![hour_bucket, account_email, input_tokens, output_tokens, total_tokens],
+            request_count = request_count + 1,
+            total_cached_tokens = total_cached_tokens + ?6,
+            total_cost_usd = total_cost_usd + ?7",
+        params![hour_bucket, account_email, input_tokens, output_tokens, total_tokens, cached_tokens, estimated_cost_usd],
     ).map_err(|e| e.to_string())?;
 
     Ok(())
 }
 
+/// 按时间桶 (bucket_secs 秒) 聚合 [start_ts, end_ts) 区间内的用量，逐行回调
+/// 回调返回 false 时提前结束 (例如下载连接已断开)，整个结果集不会一次性载入内存
+pub fn for_each_usage_bucket(
+    start_ts: i64,
+    end_ts: i64,
+    bucket_secs: i64,
+    mut on_row: impl FnMut(TokenUsageExportRow) -> bool,
+) -> Result<(), String> {
+    let conn = connect_db()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT (timestamp / ?3) * ?3 AS bucket, account_email, model,
+                SUM(input_tokens), SUM(output_tokens), COUNT(*)
+             FROM token_usage
+             WHERE timestamp >= ?1 AND timestamp < ?2
+             GROUP BY bucket, account_email, model
+             ORDER BY bucket ASC, account_email ASC, model ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut rows = stmt
+        .query(params![start_ts, end_ts, bucket_secs.max(1)])
+        .map_err(|e| e.to_string())?;
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let record = TokenUsageExportRow {
+            timestamp: row.get(0).map_err(|e| e.to_string())?,
+            account_email: row.get(1).map_err(|e| e.to_string())?,
+            model: row.get(2).map_err(|e| e.to_string())?,
+            prompt_tokens: row.get::<_, i64>(3).map_err(|e| e.to_string())?.max(0) as u64,
+            completion_tokens: row.get::<_, i64>(4).map_err(|e| e.to_string())?.max(0) as u64,
+            requests: row.get::<_, i64>(5).map_err(|e| e.to_string())?.max(0) as u64,
+        };
+        if !on_row(record) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// 统计某时刻 (Unix 秒) 之后各账号的 Token 用量 (用于每日预算)
+pub fn get_usage_since(since_ts: i64) -> Result<Vec<(String, u64)>, String> {
+    let conn = connect_db()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT account_email, SUM(total_tokens)
+             FROM token_usage
+             WHERE timestamp >= ?1
+             GROUP BY account_email",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map([since_ts], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?.max(0) as u64))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(result)
+}
+
+/// 各账号最近一次经反代产生用量的时间 (Unix 秒)，用于闲置检测
+pub fn get_last_usage_by_account() -> Result<std::collections::HashMap<String, i64>, String> {
+    let conn = connect_db()?;
+    last_usage_by_account(&conn)
+}
+
+fn last_usage_by_account(conn: &Connection) -> Result<std::collections::HashMap<String, i64>, String> {
+    let mut stmt = conn
+        .prepare("SELECT account_email, MAX(timestamp) FROM token_usage GROUP BY account_email")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(|e| e.to_string())?;
+
+    let mut result = std::collections::HashMap::new();
+    for row in rows {
+        let (email, ts) = row.map_err(|e| e.to_string())?;
+        result.insert(email, ts);
+    }
+    Ok(result)
+}
+
 /// Get hourly aggregated stats for a time range
 pub fn get_hourly_stats(hours: i64) -> Result<Vec<TokenStatsAggregated>, String> {
     let conn = connect_db()?;
@@ -287,7 +492,8 @@ pub fn get_account_stats(hours: i64) -> Result<Vec<AccountTokenStats>, String> {
                 SUM(total_input_tokens) as input, 
                 SUM(total_output_tokens) as output,
                 SUM(total_tokens) as total,
-                SUM(request_count) as count
+                SUM(request_count) as count,
+                COALESCE(SUM(total_cost_usd), 0) as cost
          FROM token_stats_hourly 
          WHERE hour_bucket >= ?1
          GROUP BY account_email
@@ -303,6 +509,7 @@ pub fn get_account_stats(hours: i64) -> Result<Vec<AccountTokenStats>, String> {
                 total_output_tokens: row.get(2)?,
                 total_tokens: row.get(3)?,
                 request_count: row.get(4)?,
+                estimated_cost_usd: row.get(5)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -320,16 +527,18 @@ pub fn get_summary_stats(hours: i64) -> Result<TokenStatsSummary, String> {
     let cutoff = chrono::Utc::now() - chrono::Duration::hours(hours);
     let cutoff_bucket = cutoff.format("%Y-%m-%d %H:00").to_string();
 
-    let (total_input, total_output, total, requests): (u64, u64, u64, u64) = conn
+    let (total_input, total_output, total, requests, total_cached, total_cost): (u64, u64, u64, u64, u64, f64) = conn
         .query_row(
             "SELECT COALESCE(SUM(total_input_tokens), 0),
                 COALESCE(SUM(total_output_tokens), 0),
                 COALESCE(SUM(total_tokens), 0),
-                COALESCE(SUM(request_count), 0)
+                COALESCE(SUM(request_count), 0),
+                COALESCE(SUM(total_cached_tokens), 0),
+                COALESCE(SUM(total_cost_usd), 0)
          FROM token_stats_hourly 
          WHERE hour_bucket >= ?1",
             [&cutoff_bucket],
-            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)),
         )
         .map_err(|e| e.to_string())?;
 
@@ -347,6 +556,8 @@ pub fn get_summary_stats(hours: i64) -> Result<TokenStatsSummary, String> {
         total_tokens: total,
         total_requests: requests,
         unique_accounts,
+        total_cached_tokens: total_cached,
+        estimated_cost_usd: total_cost,
     })
 }
 
@@ -427,6 +638,51 @@ pub fn get_model_trend_hourly(hours: i64) -> Result<Vec<ModelTrendPoint>, String
         .collect())
 }
 
+/// [NEW] 按映射后的上游模型 (无映射记录时回退到请求模型) 统计每小时消耗，供配额预测使用
+pub fn get_upstream_model_trend_hourly(hours: i64) -> Result<Vec<ModelTrendPoint>, String> {
+    let conn = connect_db()?;
+    let cutoff = chrono::Utc::now().timestamp() - (hours * 3600);
+    query_upstream_model_trend_hourly(&conn, cutoff)
+}
+
+fn query_upstream_model_trend_hourly(
+    conn: &Connection,
+    cutoff: i64,
+) -> Result<Vec<ModelTrendPoint>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT strftime('%Y-%m-%d %H:00', datetime(timestamp, 'unixepoch')) as hour_bucket,
+                COALESCE(NULLIF(mapped_model, ''), model) as upstream_model,
+                SUM(total_tokens) as total
+         FROM token_usage
+         WHERE timestamp >= ?1
+         GROUP BY hour_bucket, upstream_model
+         ORDER BY hour_bucket ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut trend_map: std::collections::BTreeMap<String, std::collections::HashMap<String, u64>> =
+        std::collections::BTreeMap::new();
+    let rows = stmt
+        .query_map([cutoff], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, u64>(2)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let (period, model, total) = row.map_err(|e| e.to_string())?;
+        trend_map.entry(period).or_default().insert(model, total);
+    }
+
+    Ok(trend_map
+        .into_iter()
+        .map(|(period, model_data)| ModelTrendPoint { period, model_data })
+        .collect())
+}
+
 pub fn get_model_trend_daily(days: i64) -> Result<Vec<ModelTrendPoint>, String> {
     let conn = connect_db()?;
     let cutoff = chrono::Utc::now().timestamp() - (days * 24 * 3600);
@@ -563,4 +819,100 @@ mod tests {
         // For now, just verify the module compiles
         assert!(true);
     }
+
+    fn insert_usage(conn: &Connection, timestamp: i64, email: &str, tokens: u32) {
+        let bucket = chrono::DateTime::from_timestamp(timestamp, 0)
+            .unwrap()
+            .format("%Y-%m-%d %H:00")
+            .to_string();
+        conn.execute(
+            "INSERT INTO token_usage (timestamp, account_email, model, input_tokens, output_tokens, total_tokens)
+             VALUES (?1, ?2, 'm', ?3, 0, ?3)",
+            params![timestamp, email, tokens],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO token_stats_hourly (hour_bucket, account_email, total_input_tokens, total_tokens, request_count)
+             VALUES (?1, ?2, ?3, ?3, 1)
+             ON CONFLICT(hour_bucket, account_email) DO UPDATE SET
+                total_input_tokens = total_input_tokens + ?3,
+                total_tokens = total_tokens + ?3,
+                request_count = request_count + 1",
+            params![bucket, email, tokens],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_upstream_trend_prefers_mapped_model() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        // 2024-01-01 00:00 UTC
+        let base = 1_704_067_200;
+        for (model, mapped, tokens) in [
+            ("claude-sonnet-4-5", Some("gemini-3-pro-high"), 100),
+            ("gpt-4o", Some("gemini-3-pro-high"), 50),
+            ("gemini-3-flash", None, 30),
+            ("gemini-2.5-pro", Some(""), 20),
+        ] {
+            conn.execute(
+                "INSERT INTO token_usage (timestamp, account_email, model, mapped_model, input_tokens, output_tokens, total_tokens)
+                 VALUES (?1, 'a@x', ?2, ?3, ?4, 0, ?4)",
+                params![base + 60, model, mapped, tokens],
+            )
+            .unwrap();
+        }
+
+        let trend = query_upstream_model_trend_hourly(&conn, base).unwrap();
+        assert_eq!(trend.len(), 1);
+        assert_eq!(trend[0].period, "2024-01-01 00:00");
+        let data = &trend[0].model_data;
+        assert_eq!(data.get("gemini-3-pro-high"), Some(&150));
+        assert_eq!(data.get("gemini-3-flash"), Some(&30));
+        assert_eq!(data.get("gemini-2.5-pro"), Some(&20));
+        assert!(data.get("claude-sonnet-4-5").is_none());
+    }
+
+    #[test]
+    fn test_clear_stats_before_cutoff() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        // 2024-01-01 00:00 UTC
+        let base = 1_704_067_200;
+        insert_usage(&conn, base + 100, "a@x", 10);
+        insert_usage(&conn, base + 3600 + 100, "a@x", 20);
+        insert_usage(&conn, base + 3600 + 2000, "a@x", 40);
+        insert_usage(&conn, base + 7200 + 100, "b@x", 80);
+
+        let result = clear_stats_in(&mut conn, Some(base + 3600 + 1000)).unwrap();
+        assert_eq!(result, ClearStatsResult { usage_rows: 2, hourly_rows: 1 });
+
+        // 截止时间所在的小时桶只保留剩余记录
+        let (tokens, count): (i64, i64) = conn
+            .query_row(
+                "SELECT total_tokens, request_count FROM token_stats_hourly WHERE hour_bucket = '2024-01-01 01:00'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((tokens, count), (40, 1));
+
+        let all = clear_stats_in(&mut conn, None).unwrap();
+        assert_eq!(all, ClearStatsResult { usage_rows: 2, hourly_rows: 2 });
+    }
+
+    #[test]
+    fn test_last_usage_by_account() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let base = 1_704_067_200;
+        insert_usage(&conn, base, "a@x", 10);
+        insert_usage(&conn, base + 500, "a@x", 10);
+        insert_usage(&conn, base + 100, "b@x", 10);
+
+        let last = last_usage_by_account(&conn).unwrap();
+        assert_eq!(last.len(), 2);
+        assert_eq!(last["a@x"], base + 500);
+        assert_eq!(last["b@x"], base + 100);
+    }
 }
@@ -0,0 +1,381 @@
+// 管理接口短期 JWT
+// 签名密钥由 api_key 经 HMAC-SHA256 派生 (无需额外保存密钥，轮换 api_key 即令所有 JWT 失效)；
+// 吊销的 JTI 只保留到令牌原本的过期时间。
+// 管理用户会话令牌使用配置中持久化的 admin_session_secret 签名，sub 为用户名
+use hmac::{Hmac, Mac};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+const KEY_DERIVATION_CONTEXT: &[u8] = b"antigravity-admin-jwt";
+const SESSION_KEY_DERIVATION_CONTEXT: &[u8] = b"antigravity-admin-session";
+const SUBJECT: &str = "admin";
+/// 同一 IP 在窗口内允许的密码错误次数，超过后锁定
+const MAX_LOGIN_FAILURES: u32 = 5;
+const LOGIN_FAILURE_WINDOW: Duration = Duration::from_secs(15 * 60);
+const LOGIN_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AdminClaims {
+    pub sub: String,
+    pub jti: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+fn derive_key(secret: &str, context: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(context);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn signing_key(api_key: &str) -> Vec<u8> {
+    derive_key(api_key, KEY_DERIVATION_CONTEXT)
+}
+
+/// 签发管理 JWT，返回 (token, claims)
+pub fn issue_token(api_key: &str, expiry_secs: u64) -> Result<(String, AdminClaims), String> {
+    if api_key.is_empty() {
+        return Err("api_key is not configured; cannot sign admin tokens".to_string());
+    }
+    let now = chrono::Utc::now().timestamp();
+    let claims = AdminClaims {
+        sub: SUBJECT.to_string(),
+        jti: uuid::Uuid::new_v4().simple().to_string(),
+        iat: now,
+        exp: now + expiry_secs.max(1) as i64,
+    };
+    let token = encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(&signing_key(api_key)),
+    )
+    .map_err(|e| format!("Failed to sign token: {}", e))?;
+    Ok((token, claims))
+}
+
+/// 校验签名与过期时间 (不检查吊销列表)
+pub fn decode_token(api_key: &str, token: &str) -> Result<AdminClaims, String> {
+    if api_key.is_empty() {
+        return Err("api_key is not configured".to_string());
+    }
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.leeway = 0;
+    validation.set_required_spec_claims(&["exp", "sub"]);
+    validation.sub = Some(SUBJECT.to_string());
+    decode::<AdminClaims>(
+        token,
+        &DecodingKey::from_secret(&signing_key(api_key)),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .map_err(|e| e.to_string())
+}
+
+/// 先用当前 api_key 校验，失败时再用轮换宽限期内的旧 api_key 校验 (不检查吊销列表)
+pub fn decode_token_with_previous(
+    api_key: &str,
+    previous_api_key: Option<&str>,
+    token: &str,
+) -> Result<AdminClaims, String> {
+    decode_token(api_key, token).or_else(|e| match previous_api_key {
+        Some(previous) => decode_token(previous, token),
+        None => Err(e),
+    })
+}
+
+/// 管理接口鉴权: 合法且未吊销的 JWT
+pub fn verify_token(api_key: &str, token: &str) -> Result<AdminClaims, String> {
+    let claims = decode_token(api_key, token)?;
+    if revocations().is_revoked(&claims.jti) {
+        return Err("token has been revoked".to_string());
+    }
+    Ok(claims)
+}
+
+/// 形如 JWT (header.payload.signature)
+pub fn looks_like_jwt(value: &str) -> bool {
+    value.split('.').count() == 3 && value.starts_with("ey")
+}
+
+/// 管理用户会话
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionClaims {
+    /// 用户名
+    pub sub: String,
+    /// 签发时用户的 session_version，不一致即视为已吊销
+    pub ver: i64,
+    pub jti: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+/// 生成会话签名密钥
+pub fn generate_session_secret() -> String {
+    rand::random::<[u8; 32]>()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 签发管理用户会话令牌，返回 (token, claims)
+pub fn issue_session(
+    secret: &str,
+    username: &str,
+    session_version: i64,
+    expiry_secs: u64,
+) -> Result<(String, SessionClaims), String> {
+    if secret.is_empty() {
+        return Err("admin session secret is not configured".to_string());
+    }
+    let now = chrono::Utc::now().timestamp();
+    let claims = SessionClaims {
+        sub: username.to_string(),
+        ver: session_version,
+        jti: uuid::Uuid::new_v4().simple().to_string(),
+        iat: now,
+        exp: now + expiry_secs.max(1) as i64,
+    };
+    let token = encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(&derive_key(secret, SESSION_KEY_DERIVATION_CONTEXT)),
+    )
+    .map_err(|e| format!("Failed to sign token: {}", e))?;
+    Ok((token, claims))
+}
+
+/// 校验会话令牌签名、过期时间与吊销列表 (session_version 由调用方比对)
+pub fn verify_session(secret: &str, token: &str) -> Result<SessionClaims, String> {
+    if secret.is_empty() {
+        return Err("admin session secret is not configured".to_string());
+    }
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.leeway = 0;
+    validation.set_required_spec_claims(&["exp", "sub"]);
+    let claims = decode::<SessionClaims>(
+        token,
+        &DecodingKey::from_secret(&derive_key(secret, SESSION_KEY_DERIVATION_CONTEXT)),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .map_err(|e| e.to_string())?;
+    if revocations().is_revoked(&claims.jti) {
+        return Err("token has been revoked".to_string());
+    }
+    Ok(claims)
+}
+
+/// 已吊销的 JTI -> 原过期时间
+#[derive(Default)]
+pub struct RevocationSet {
+    entries: Mutex<HashMap<String, i64>>,
+}
+
+impl RevocationSet {
+    pub fn revoke(&self, jti: String, exp: i64) {
+        if let Ok(mut entries) = self.entries.lock() {
+            let now = chrono::Utc::now().timestamp();
+            entries.retain(|_, e| *e > now);
+            entries.insert(jti, exp);
+        }
+    }
+
+    pub fn is_revoked(&self, jti: &str) -> bool {
+        self.entries
+            .lock()
+            .map(|entries| entries.contains_key(jti))
+            .unwrap_or(false)
+    }
+}
+
+pub fn revocations() -> &'static RevocationSet {
+    static INSTANCE: OnceLock<RevocationSet> = OnceLock::new();
+    INSTANCE.get_or_init(RevocationSet::default)
+}
+
+/// 常量时间比较两个密钥 (先取 SHA-256 摘要，比较耗时与内容和长度均无关)
+pub fn secrets_equal(a: &str, b: &str) -> bool {
+    let (da, db) = (Sha256::digest(a.as_bytes()), Sha256::digest(b.as_bytes()));
+    da.iter().zip(db.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+struct LoginFailures {
+    count: u32,
+    window_start: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// [NEW] 管理密码换取令牌的失败限速: 按客户端 IP 统计，窗口内错误过多即锁定
+#[derive(Default)]
+pub struct LoginThrottle {
+    entries: Mutex<HashMap<String, LoginFailures>>,
+}
+
+impl LoginThrottle {
+    /// 锁定中返回剩余锁定时长
+    pub fn check(&self, ip: &str) -> Result<(), Duration> {
+        self.check_at(ip, Instant::now())
+    }
+
+    pub fn record_failure(&self, ip: &str) {
+        self.record_failure_at(ip, Instant::now());
+    }
+
+    pub fn record_success(&self, ip: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(ip);
+        }
+    }
+
+    fn check_at(&self, ip: &str, now: Instant) -> Result<(), Duration> {
+        let Ok(entries) = self.entries.lock() else {
+            return Ok(());
+        };
+        match entries.get(ip).and_then(|e| e.locked_until) {
+            Some(until) if until > now => Err(until - now),
+            _ => Ok(()),
+        }
+    }
+
+    fn record_failure_at(&self, ip: &str, now: Instant) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        entries.retain(|_, e| {
+            e.locked_until.is_some_and(|t| t > now)
+                || now.duration_since(e.window_start) < LOGIN_FAILURE_WINDOW
+        });
+        let entry = entries.entry(ip.to_string()).or_insert(LoginFailures {
+            count: 0,
+            window_start: now,
+            locked_until: None,
+        });
+        if entry.locked_until.is_some_and(|t| t <= now)
+            || now.duration_since(entry.window_start) >= LOGIN_FAILURE_WINDOW
+        {
+            *entry = LoginFailures {
+                count: 0,
+                window_start: now,
+                locked_until: None,
+            };
+        }
+        entry.count += 1;
+        if entry.count >= MAX_LOGIN_FAILURES {
+            entry.locked_until = Some(now + LOGIN_LOCKOUT);
+        }
+    }
+}
+
+pub fn login_throttle() -> &'static LoginThrottle {
+    static INSTANCE: OnceLock<LoginThrottle> = OnceLock::new();
+    INSTANCE.get_or_init(LoginThrottle::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_equal() {
+        assert!(secrets_equal("hunter2", "hunter2"));
+        assert!(!secrets_equal("hunter2", "hunter3"));
+        assert!(!secrets_equal("hunter2", "hunter22"));
+        assert!(!secrets_equal("", "x"));
+    }
+
+    #[test]
+    fn test_login_throttle_locks_after_repeated_failures() {
+        let throttle = LoginThrottle::default();
+        let start = Instant::now();
+        for _ in 0..MAX_LOGIN_FAILURES - 1 {
+            throttle.record_failure_at("203.0.113.7", start);
+        }
+        assert!(throttle.check_at("203.0.113.7", start).is_ok());
+
+        throttle.record_failure_at("203.0.113.7", start);
+        assert_eq!(throttle.check_at("203.0.113.7", start), Err(LOGIN_LOCKOUT));
+        // 其他 IP 不受影响
+        assert!(throttle.check_at("198.51.100.1", start).is_ok());
+        // 锁定到期后解除，且重新计数
+        let later = start + LOGIN_LOCKOUT;
+        assert!(throttle.check_at("203.0.113.7", later).is_ok());
+        throttle.record_failure_at("203.0.113.7", later);
+        assert!(throttle.check_at("203.0.113.7", later).is_ok());
+
+        throttle.record_success("203.0.113.7");
+        assert!(throttle.entries.lock().unwrap().get("203.0.113.7").is_none());
+    }
+
+    #[test]
+    fn test_issue_and_verify() {
+        let (token, claims) = issue_token("sk-test", 3600).unwrap();
+        assert!(looks_like_jwt(&token));
+        assert_eq!(verify_token("sk-test", &token).unwrap(), claims);
+        assert_eq!(claims.exp - claims.iat, 3600);
+
+        // 轮换 api_key 后旧令牌失效
+        assert!(verify_token("sk-rotated", &token).is_err());
+        assert!(issue_token("", 3600).is_err());
+        assert!(!looks_like_jwt("sk-test"));
+    }
+
+    #[test]
+    fn test_decode_with_previous_key_during_grace() {
+        let (token, claims) = issue_token("sk-old", 3600).unwrap();
+        assert_eq!(
+            decode_token_with_previous("sk-new", Some("sk-old"), &token).unwrap(),
+            claims
+        );
+        assert!(decode_token_with_previous("sk-new", None, &token).is_err());
+        assert!(decode_token_with_previous("sk-new", Some("sk-other"), &token).is_err());
+    }
+
+    #[test]
+    fn test_expired_token_rejected() {
+        let now = chrono::Utc::now().timestamp();
+        let claims = AdminClaims {
+            sub: SUBJECT.to_string(),
+            jti: "expired".to_string(),
+            iat: now - 120,
+            exp: now - 60,
+        };
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(&signing_key("sk-test")),
+        )
+        .unwrap();
+        assert!(verify_token("sk-test", &token).is_err());
+    }
+
+    #[test]
+    fn test_session_tokens_use_separate_secret() {
+        let secret = generate_session_secret();
+        let (token, claims) = issue_session(&secret, "alice", 3, 3600).unwrap();
+        assert_eq!(verify_session(&secret, &token).unwrap(), claims);
+        assert_eq!(claims.ver, 3);
+
+        // 会话令牌与 api_key 签发的管理 JWT 互不通用
+        assert!(verify_session("other-secret", &token).is_err());
+        assert!(verify_token(&secret, &token).is_err());
+        let (legacy, _) = issue_token("sk-test", 3600).unwrap();
+        assert!(verify_session("sk-test", &legacy).is_err());
+
+        revocations().revoke(claims.jti.clone(), claims.exp);
+        assert!(verify_session(&secret, &token).is_err());
+    }
+
+    #[test]
+    fn test_revoked_token_rejected() {
+        let (token, claims) = issue_token("sk-test", 3600).unwrap();
+        revocations().revoke(claims.jti.clone(), claims.exp);
+        assert!(verify_token("sk-test", &token).is_err());
+        // 签名仍然合法，只是被吊销
+        assert!(decode_token("sk-test", &token).is_ok());
+    }
+}
@@ -0,0 +1,145 @@
+// API Key 轮换
+// 轮换时生成新 Key，旧 Key 在 rotation_grace_seconds 宽限期内仍然有效；
+// 旧 Key 与过期时间持久化到配置文件，重启后继续生效，到期后由后台任务清除
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::proxy::config::ProxyConfig;
+use crate::proxy::ProxySecurityConfig;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RotationStatus {
+    /// 是否处于宽限期 (旧 Key 仍然有效)
+    pub in_grace_period: bool,
+    /// 旧 Key (脱敏)
+    pub previous_key: Option<String>,
+    /// 旧 Key 失效时间 (unix 秒)
+    pub expires_at: Option<i64>,
+    pub remaining_seconds: i64,
+    pub rotation_grace_seconds: u64,
+}
+
+pub fn generate_api_key() -> String {
+    format!("sk-{}", uuid::Uuid::new_v4().simple())
+}
+
+/// `sk-1234abcd...` → `sk-1234***cdef`
+pub fn mask_key(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= 12 {
+        return "***".to_string();
+    }
+    let head: String = chars[..7].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}***{}", head, tail)
+}
+
+/// 轮换 API Key: 当前 Key 转为旧 Key 并开始宽限期，返回新 Key
+/// 宽限期内再次轮换时，上一次的旧 Key 立即失效
+pub fn rotate(config: &mut ProxyConfig, now: i64) -> String {
+    let new_key = generate_api_key();
+    let old_key = std::mem::replace(&mut config.api_key, new_key.clone());
+    if config.rotation_grace_seconds == 0 || old_key.is_empty() {
+        config.api_key_previous = None;
+        config.api_key_previous_expires_at = None;
+    } else {
+        config.api_key_previous = Some(old_key);
+        config.api_key_previous_expires_at = Some(now + config.rotation_grace_seconds as i64);
+    }
+    new_key
+}
+
+/// 清除已过期的旧 Key，返回是否有改动
+pub fn clear_expired(config: &mut ProxyConfig, now: i64) -> bool {
+    let expired = match config.api_key_previous_expires_at {
+        Some(expires_at) => expires_at <= now,
+        None => config.api_key_previous.is_some(),
+    };
+    if expired {
+        config.api_key_previous = None;
+        config.api_key_previous_expires_at = None;
+    }
+    expired
+}
+
+pub fn status(config: &ProxyConfig, now: i64) -> RotationStatus {
+    let active = config
+        .api_key_previous
+        .as_deref()
+        .zip(config.api_key_previous_expires_at)
+        .filter(|(_, expires_at)| *expires_at > now);
+    RotationStatus {
+        in_grace_period: active.is_some(),
+        previous_key: active.map(|(key, _)| mask_key(key)),
+        expires_at: active.map(|(_, expires_at)| expires_at),
+        remaining_seconds: active.map(|(_, expires_at)| expires_at - now).unwrap_or(0),
+        rotation_grace_seconds: config.rotation_grace_seconds,
+    }
+}
+
+/// 宽限期结束后从配置文件与内存安全配置中删除旧 Key
+/// 鉴权本身会检查过期时间，此任务只负责清理持久化状态
+pub fn schedule_expiry(security: Arc<RwLock<ProxySecurityConfig>>, expires_at: i64) {
+    tokio::spawn(async move {
+        let wait = (expires_at - chrono::Utc::now().timestamp()).max(0) as u64;
+        tokio::time::sleep(std::time::Duration::from_secs(wait)).await;
+
+        let now = chrono::Utc::now().timestamp();
+        let mut app_config = match crate::modules::config::load_app_config() {
+            Ok(cfg) => cfg,
+            Err(e) => {
+                tracing::warn!("[API-Key-Rotation] Failed to load config: {}", e);
+                return;
+            }
+        };
+        // 期间发生了新的轮换 (过期时间更晚)，交给新的任务处理
+        if !clear_expired(&mut app_config.proxy, now) {
+            return;
+        }
+        if let Err(e) = crate::modules::config::save_app_config(&app_config) {
+            tracing::warn!("[API-Key-Rotation] Failed to persist expired key removal: {}", e);
+            return;
+        }
+        let mut sec = security.write().await;
+        sec.api_key_previous = None;
+        sec.api_key_previous_expires_at = None;
+        tracing::info!("[API-Key-Rotation] Grace period ended, previous API key removed");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_keeps_previous_key_for_grace_period() {
+        let mut config = ProxyConfig::default();
+        config.api_key = "sk-old".to_string();
+        config.rotation_grace_seconds = 100;
+
+        let new_key = rotate(&mut config, 1_000);
+        assert_eq!(config.api_key, new_key);
+        assert_eq!(config.api_key_previous.as_deref(), Some("sk-old"));
+        assert_eq!(config.api_key_previous_expires_at, Some(1_100));
+
+        let s = status(&config, 1_040);
+        assert!(s.in_grace_period);
+        assert_eq!(s.remaining_seconds, 60);
+        assert!(!status(&config, 1_100).in_grace_period);
+
+        assert!(!clear_expired(&mut config, 1_099));
+        assert!(clear_expired(&mut config, 1_100));
+        assert!(config.api_key_previous.is_none());
+    }
+
+    #[test]
+    fn test_zero_grace_drops_old_key_immediately() {
+        let mut config = ProxyConfig::default();
+        config.rotation_grace_seconds = 0;
+        rotate(&mut config, 1_000);
+        assert!(config.api_key_previous.is_none());
+        assert_eq!(mask_key("sk-0123456789abcdef"), "sk-0123***cdef");
+        assert_eq!(mask_key("short"), "***");
+    }
+}
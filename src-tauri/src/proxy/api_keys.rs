@@ -0,0 +1,183 @@
+// 附加 API Key 管理
+// 按客户端/成员签发带标签的 Key，可单独禁用或删除而无需轮换主 Key；
+// 标签在配置中唯一，作为管理接口中的标识
+use axum::http::StatusCode;
+use serde::Serialize;
+
+use crate::proxy::api_key_rotation::{generate_api_key, mask_key};
+use crate::proxy::config::{ApiKeyEntry, ProxyConfig};
+use crate::proxy::error::ApiError;
+
+const MAX_LABEL_LEN: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiKeyError {
+    NotFound(String),
+    Invalid(String),
+}
+
+impl std::fmt::Display for ApiKeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiKeyError::NotFound(label) => write!(f, "API key '{}' not found", label),
+            ApiKeyError::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<ApiKeyError> for ApiError {
+    fn from(e: ApiKeyError) -> Self {
+        match e {
+            ApiKeyError::NotFound(_) => ApiError::from_status(StatusCode::NOT_FOUND, e.to_string()),
+            ApiKeyError::Invalid(_) => ApiError::invalid_request(e.to_string()),
+        }
+    }
+}
+
+/// 列表展示用 (Key 脱敏)
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ApiKeySummary {
+    pub label: String,
+    pub key: String,
+    pub disabled: bool,
+}
+
+impl From<&ApiKeyEntry> for ApiKeySummary {
+    fn from(entry: &ApiKeyEntry) -> Self {
+        Self {
+            label: entry.label.clone(),
+            key: mask_key(&entry.key),
+            disabled: entry.disabled,
+        }
+    }
+}
+
+pub fn list(config: &ProxyConfig) -> Vec<ApiKeySummary> {
+    config.api_keys.iter().map(ApiKeySummary::from).collect()
+}
+
+fn normalize_label(config: &ProxyConfig, label: &str, current: Option<&str>) -> Result<String, ApiKeyError> {
+    let label = label.trim();
+    if label.is_empty() {
+        return Err(ApiKeyError::Invalid("label must not be empty".to_string()));
+    }
+    if label.chars().count() > MAX_LABEL_LEN {
+        return Err(ApiKeyError::Invalid(format!(
+            "label must be at most {} characters",
+            MAX_LABEL_LEN
+        )));
+    }
+    if Some(label) != current && config.api_keys.iter().any(|e| e.label == label) {
+        return Err(ApiKeyError::Invalid(format!("label '{}' already exists", label)));
+    }
+    Ok(label.to_string())
+}
+
+/// 新增 Key；未指定 key 时自动生成。返回完整 Key (仅此一次可见)
+pub fn add(config: &mut ProxyConfig, label: &str, key: Option<String>) -> Result<ApiKeyEntry, ApiKeyError> {
+    let label = normalize_label(config, label, None)?;
+    let key = match key.map(|k| k.trim().to_string()) {
+        Some(k) if k.is_empty() => {
+            return Err(ApiKeyError::Invalid("key must not be empty".to_string()))
+        }
+        Some(k) => k,
+        None => generate_api_key(),
+    };
+    let clashes_with_admin = config.admin_password.as_deref() == Some(key.as_str());
+    if key == config.api_key || clashes_with_admin || config.api_keys.iter().any(|e| e.key == key) {
+        return Err(ApiKeyError::Invalid("key is already in use".to_string()));
+    }
+
+    let entry = ApiKeyEntry {
+        key,
+        label,
+        disabled: false,
+    };
+    config.api_keys.push(entry.clone());
+    Ok(entry)
+}
+
+/// 修改标签或启用状态
+pub fn update(
+    config: &mut ProxyConfig,
+    label: &str,
+    new_label: Option<&str>,
+    disabled: Option<bool>,
+) -> Result<ApiKeySummary, ApiKeyError> {
+    let index = config
+        .api_keys
+        .iter()
+        .position(|e| e.label == label)
+        .ok_or_else(|| ApiKeyError::NotFound(label.to_string()))?;
+    let new_label = match new_label {
+        Some(new_label) => Some(normalize_label(config, new_label, Some(label))?),
+        None => None,
+    };
+
+    let entry = &mut config.api_keys[index];
+    if let Some(new_label) = new_label {
+        entry.label = new_label;
+    }
+    if let Some(disabled) = disabled {
+        entry.disabled = disabled;
+    }
+    Ok(ApiKeySummary::from(&*entry))
+}
+
+pub fn remove(config: &mut ProxyConfig, label: &str) -> Result<ApiKeyEntry, ApiKeyError> {
+    let index = config
+        .api_keys
+        .iter()
+        .position(|e| e.label == label)
+        .ok_or_else(|| ApiKeyError::NotFound(label.to_string()))?;
+    Ok(config.api_keys.remove(index))
+}
+
+/// 配置中的重复标签与重复 Key (手动编辑配置文件时可能出现)
+pub fn find_duplicates(config: &ProxyConfig) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (i, entry) in config.api_keys.iter().enumerate() {
+        let earlier = &config.api_keys[..i];
+        if earlier.iter().any(|e| e.label == entry.label) {
+            problems.push(format!("duplicate label '{}'", entry.label));
+        }
+        if earlier.iter().any(|e| e.key == entry.key) {
+            problems.push(format!("key of '{}' duplicates another entry", entry.label));
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_update_remove() {
+        let mut config = ProxyConfig::default();
+        config.api_key = "sk-main".to_string();
+
+        let alice = add(&mut config, " alice ", None).unwrap();
+        assert_eq!(alice.label, "alice");
+        assert!(alice.key.starts_with("sk-"));
+        assert!(matches!(add(&mut config, "alice", None), Err(ApiKeyError::Invalid(_))));
+        assert!(matches!(
+            add(&mut config, "bob", Some("sk-main".to_string())),
+            Err(ApiKeyError::Invalid(_))
+        ));
+        add(&mut config, "bob", Some("sk-bob".to_string())).unwrap();
+
+        let updated = update(&mut config, "bob", Some("bob-laptop"), Some(true)).unwrap();
+        assert_eq!(updated.label, "bob-laptop");
+        assert!(updated.disabled);
+        assert!(matches!(
+            update(&mut config, "bob-laptop", Some("alice"), None),
+            Err(ApiKeyError::Invalid(_))
+        ));
+
+        assert_eq!(remove(&mut config, "alice").unwrap().key, alice.key);
+        assert_eq!(remove(&mut config, "alice"), Err(ApiKeyError::NotFound("alice".to_string())));
+        assert_eq!(list(&config).len(), 1);
+        assert!(find_duplicates(&config).is_empty());
+    }
+}
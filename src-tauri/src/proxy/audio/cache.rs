@@ -0,0 +1,118 @@
+// 音频转录结果缓存
+// 以音频内容的 SHA-256 (连同模型与提示词) 作为键，客户端重试重复上传同一文件时直接返回已有转录，
+// 受 experimental.enable_response_cache 控制，TTL 为 experimental.audio_cache_ttl_secs
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// 最多缓存的转录条数 (超出时淘汰最早写入的条目)
+const MAX_ENTRIES: usize = 256;
+
+pub struct AudioCache {
+    entries: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl AudioCache {
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn global() -> &'static AudioCache {
+        static INSTANCE: OnceLock<AudioCache> = OnceLock::new();
+        INSTANCE.get_or_init(AudioCache::new)
+    }
+
+    /// 缓存键: 同一音频在不同模型 / 提示词下的转录结果可能不同
+    pub fn key(audio: &[u8], model: &str, prompt: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(audio);
+        hasher.update([0u8]);
+        hasher.update(model.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(prompt.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    pub fn get(&self, key: &str, ttl: Duration) -> Option<String> {
+        self.get_at(key, ttl, Instant::now())
+    }
+
+    fn get_at(&self, key: &str, ttl: Duration, now: Instant) -> Option<String> {
+        let mut entries = self.entries.lock().ok()?;
+        match entries.get(key) {
+            Some((text, at)) if now.saturating_duration_since(*at) < ttl => Some(text.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, key: String, text: String, ttl: Duration) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        if entries.len() >= MAX_ENTRIES && !entries.contains_key(&key) {
+            let now = Instant::now();
+            entries.retain(|_, (_, at)| now.saturating_duration_since(*at) < ttl);
+            if entries.len() >= MAX_ENTRIES {
+                if let Some(oldest) = entries
+                    .iter()
+                    .min_by_key(|(_, (_, at))| *at)
+                    .map(|(k, _)| k.clone())
+                {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(key, (text, Instant::now()));
+    }
+}
+
+impl Default for AudioCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_upload_hits_cache() {
+        let cache = AudioCache::new();
+        let ttl = Duration::from_secs(3600);
+        let audio = b"RIFF....WAVEfmt fake audio payload".to_vec();
+        let prompt = "Generate a transcript of the speech.";
+
+        let key = AudioCache::key(&audio, "gemini-2.0-flash-exp", prompt);
+        assert_eq!(cache.get(&key, ttl), None);
+        cache.insert(key, "hello world".to_string(), ttl);
+
+        // 客户端重试: 同一文件再次上传
+        let retry = audio.clone();
+        let retry_key = AudioCache::key(&retry, "gemini-2.0-flash-exp", prompt);
+        assert_eq!(cache.get(&retry_key, ttl).as_deref(), Some("hello world"));
+
+        // 不同提示词 / 不同音频不命中
+        assert_eq!(cache.get(&AudioCache::key(&audio, "gemini-2.0-flash-exp", "Translate"), ttl), None);
+        assert_eq!(cache.get(&AudioCache::key(b"other", "gemini-2.0-flash-exp", prompt), ttl), None);
+    }
+
+    #[test]
+    fn test_expired_entry_is_evicted() {
+        let cache = AudioCache::new();
+        let ttl = Duration::from_secs(60);
+        let key = AudioCache::key(b"audio", "m", "p");
+        cache.insert(key.clone(), "text".to_string(), ttl);
+
+        let later = Instant::now() + Duration::from_secs(61);
+        assert_eq!(cache.get_at(&key, ttl, later), None);
+        assert!(cache.entries.lock().unwrap().is_empty());
+    }
+}
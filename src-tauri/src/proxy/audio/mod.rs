@@ -1,3 +1,5 @@
+pub mod cache;
+
 use base64::{engine::general_purpose, Engine as _};
 use std::path::Path;
 
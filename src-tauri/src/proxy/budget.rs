@@ -0,0 +1,333 @@
+// 每日 Token 用量预算
+// 用量以 token_stats 为准: 每个本地自然日首次使用时从数据库加载当天的聚合值，
+// 之后随每次请求的用量增量累加，避免在请求路径上反复查询数据库
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard, OnceLock, RwLock};
+
+use crate::proxy::config::BudgetConfig;
+use crate::proxy::error::{ApiError, ApiErrorCode};
+
+#[derive(Debug, Default)]
+struct DailyUsage {
+    day: Option<NaiveDate>,
+    total: u64,
+    per_account: HashMap<String, u64>,
+    /// 超限告警每天只打印一次
+    global_warned: bool,
+    warned_accounts: HashSet<String>,
+}
+
+/// 全局预算超限
+#[derive(Debug, Clone)]
+pub struct BudgetExceeded {
+    pub used: u64,
+    pub limit: u64,
+    pub reset_at: DateTime<Local>,
+}
+
+impl BudgetExceeded {
+    pub fn into_api_error(self) -> ApiError {
+        let retry_after = (self.reset_at - Local::now()).num_seconds().max(1);
+        ApiError::new(
+            ApiErrorCode::BudgetExceeded,
+            format!(
+                "Daily token budget exceeded ({} / {} tokens). Resets at {}",
+                self.used,
+                self.limit,
+                self.reset_at.to_rfc3339()
+            ),
+        )
+        .with_header("Retry-After", &retry_after.to_string())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountBudgetStatus {
+    pub email: String,
+    pub used: u64,
+    pub limit: Option<u64>,
+    pub exceeded: bool,
+}
+
+/// `GET /api/budget/status` 响应
+#[derive(Debug, Serialize)]
+pub struct BudgetStatus {
+    pub date: String,
+    pub reset_at: String,
+    pub daily_token_budget: Option<u64>,
+    pub per_account_daily_budget: Option<u64>,
+    pub used: u64,
+    pub remaining: Option<u64>,
+    pub exceeded: bool,
+    pub accounts: Vec<AccountBudgetStatus>,
+}
+
+pub struct BudgetTracker {
+    config: RwLock<BudgetConfig>,
+    usage: Mutex<DailyUsage>,
+}
+
+impl BudgetTracker {
+    pub fn new() -> Self {
+        Self {
+            config: RwLock::new(BudgetConfig::default()),
+            usage: Mutex::new(DailyUsage::default()),
+        }
+    }
+
+    pub fn global() -> &'static BudgetTracker {
+        static INSTANCE: OnceLock<BudgetTracker> = OnceLock::new();
+        INSTANCE.get_or_init(BudgetTracker::new)
+    }
+
+    pub fn update_config(&self, config: BudgetConfig) {
+        let Ok(mut current) = self.config.write() else {
+            return;
+        };
+        if *current == config {
+            return;
+        }
+        *current = config;
+        // 未启用预算期间不累加用量，配置变化后下次使用时从数据库重新加载
+        if let Ok(mut usage) = self.usage.lock() {
+            *usage = DailyUsage::default();
+        }
+    }
+
+    /// [NEW] 丢弃内存中的当日用量，下次使用时从数据库重新加载 (Token 统计被清除后调用)
+    pub fn invalidate_usage(&self) {
+        if let Ok(mut usage) = self.usage.lock() {
+            *usage = DailyUsage::default();
+        }
+    }
+
+    fn limits(&self) -> (Option<u64>, Option<u64>) {
+        self.config
+            .read()
+            .map(|c| {
+                (
+                    c.daily_token_budget.filter(|v| *v > 0),
+                    c.per_account_daily_budget.filter(|v| *v > 0),
+                )
+            })
+            .unwrap_or((None, None))
+    }
+
+    fn is_enabled(&self) -> bool {
+        let (global, per_account) = self.limits();
+        global.is_some() || per_account.is_some()
+    }
+
+    /// 当天的用量 (跨天时从 token_stats 重新加载)
+    fn today(&self) -> Option<MutexGuard<'_, DailyUsage>> {
+        let today = Local::now().date_naive();
+        {
+            let usage = self.usage.lock().ok()?;
+            if usage.day == Some(today) {
+                return Some(usage);
+            }
+        }
+        // [FIX] 数据库查询不持有锁，避免阻塞其他请求的预算检查
+        let loaded = load_usage(today);
+        let mut usage = self.usage.lock().ok()?;
+        // 并发加载时以先写入者为准，保留其后已累加的用量
+        if usage.day != Some(today) {
+            *usage = loaded;
+        }
+        Some(usage)
+    }
+
+    /// 记录一次请求的 Token 用量
+    pub fn record(&self, account_email: &str, tokens: u64) {
+        if tokens == 0 || !self.is_enabled() {
+            return;
+        }
+        if let Some(mut usage) = self.today() {
+            usage.total += tokens;
+            *usage
+                .per_account
+                .entry(account_email.to_string())
+                .or_default() += tokens;
+        }
+    }
+
+    /// 检查全局每日预算
+    pub fn check_global(&self) -> Result<(), BudgetExceeded> {
+        let (Some(limit), _) = self.limits() else {
+            return Ok(());
+        };
+        let Some(mut usage) = self.today() else {
+            return Ok(());
+        };
+        if usage.total < limit {
+            return Ok(());
+        }
+        let reset_at = next_reset(Local::now());
+        if !usage.global_warned {
+            usage.global_warned = true;
+            tracing::warn!(
+                "[Budget] Daily token budget exhausted ({} / {}), rejecting requests until {}",
+                usage.total,
+                limit,
+                reset_at.to_rfc3339()
+            );
+        }
+        Err(BudgetExceeded {
+            used: usage.total,
+            limit,
+            reset_at,
+        })
+    }
+
+    /// 账号是否已超出单账号每日预算 (超出后退出轮换)
+    pub fn is_account_exhausted(&self, account_email: &str) -> bool {
+        let (_, Some(limit)) = self.limits() else {
+            return false;
+        };
+        let Some(mut usage) = self.today() else {
+            return false;
+        };
+        let used = usage.per_account.get(account_email).copied().unwrap_or(0);
+        if used < limit {
+            return false;
+        }
+        if usage.warned_accounts.insert(account_email.to_string()) {
+            tracing::warn!(
+                "[Budget] Account {} exhausted its daily token budget ({} / {}), excluded from rotation",
+                account_email,
+                used,
+                limit
+            );
+        }
+        true
+    }
+
+    pub fn status(&self) -> BudgetStatus {
+        let (global, per_account) = self.limits();
+        let now = Local::now();
+        let (used, mut accounts) = match self.today() {
+            Some(usage) => (
+                usage.total,
+                usage
+                    .per_account
+                    .iter()
+                    .map(|(email, used)| AccountBudgetStatus {
+                        email: email.clone(),
+                        used: *used,
+                        limit: per_account,
+                        exceeded: per_account.map(|l| *used >= l).unwrap_or(false),
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            None => (0, Vec::new()),
+        };
+        accounts.sort_by(|a, b| b.used.cmp(&a.used));
+
+        BudgetStatus {
+            date: now.date_naive().to_string(),
+            reset_at: next_reset(now).to_rfc3339(),
+            daily_token_budget: global,
+            per_account_daily_budget: per_account,
+            used,
+            remaining: global.map(|l| l.saturating_sub(used)),
+            exceeded: global.map(|l| used >= l).unwrap_or(false),
+            accounts,
+        }
+    }
+}
+
+impl Default for BudgetTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 热更新预算配置
+pub fn update_budget_config(config: BudgetConfig) {
+    BudgetTracker::global().update_config(config);
+}
+
+fn local_midnight(day: NaiveDate) -> DateTime<Local> {
+    let naive = day.and_hms_opt(0, 0, 0).unwrap_or_default();
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .unwrap_or_else(Local::now)
+}
+
+/// 下一次重置时间 (本地午夜)
+pub fn next_reset(now: DateTime<Local>) -> DateTime<Local> {
+    let tomorrow = now.date_naive().succ_opt().unwrap_or(now.date_naive());
+    local_midnight(tomorrow)
+}
+
+fn load_usage(day: NaiveDate) -> DailyUsage {
+    let mut usage = DailyUsage {
+        day: Some(day),
+        ..Default::default()
+    };
+    match crate::modules::token_stats::get_usage_since(local_midnight(day).timestamp()) {
+        Ok(rows) => {
+            for (email, tokens) in rows {
+                usage.total += tokens;
+                usage.per_account.insert(email, tokens);
+            }
+        }
+        Err(e) => tracing::warn!("[Budget] Failed to load today's token usage: {}", e),
+    }
+    usage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(global: Option<u64>, per_account: Option<u64>) -> BudgetTracker {
+        let tracker = BudgetTracker::new();
+        tracker.update_config(BudgetConfig {
+            daily_token_budget: global,
+            per_account_daily_budget: per_account,
+        });
+        // 预置当天状态，跳过数据库加载
+        tracker.usage.lock().unwrap().day = Some(Local::now().date_naive());
+        tracker
+    }
+
+    #[test]
+    fn test_global_budget() {
+        let t = tracker(Some(1000), None);
+        t.record("a@example.com", 600);
+        assert!(t.check_global().is_ok());
+        t.record("b@example.com", 400);
+        let err = t.check_global().unwrap_err();
+        assert_eq!((err.used, err.limit), (1000, 1000));
+        assert!(err.reset_at > Local::now());
+        // 未配置单账号预算时不排除账号
+        assert!(!t.is_account_exhausted("a@example.com"));
+    }
+
+    #[test]
+    fn test_account_budget_excludes_only_that_account() {
+        let t = tracker(None, Some(500));
+        t.record("a@example.com", 500);
+        t.record("b@example.com", 100);
+        assert!(t.is_account_exhausted("a@example.com"));
+        assert!(!t.is_account_exhausted("b@example.com"));
+        assert!(t.check_global().is_ok());
+
+        let status = t.status();
+        assert_eq!(status.used, 600);
+        assert_eq!(status.accounts[0].email, "a@example.com");
+        assert!(status.accounts[0].exceeded);
+    }
+
+    #[test]
+    fn test_disabled_budget_does_not_track() {
+        let t = BudgetTracker::new();
+        t.record("a@example.com", 1_000_000);
+        assert!(t.check_global().is_ok());
+        assert!(t.usage.lock().unwrap().day.is_none());
+    }
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::fs;
 
@@ -10,6 +10,13 @@ use std::os::windows::process::CommandExt;
 #[cfg(target_os = "windows")]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
+/// 同步前的配置快照目录 (位于数据目录下，按 CLI 分子目录)
+const CLI_BACKUP_DIR: &str = "cli_backups";
+/// 每个配置文件保留的快照数量
+const MAX_BACKUPS_PER_FILE: usize = 10;
+/// 首次同步前的原始配置后缀 (固定保留，不参与轮换)
+const ORIGINAL_BACKUP_SUFFIX: &str = "original";
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub enum CliApp {
     Claude,
@@ -24,6 +31,11 @@ pub struct CliConfigFile {
 }
 
 impl CliApp {
+    /// 所有支持同步的 CLI
+    pub fn all() -> [CliApp; 3] {
+        [CliApp::Claude, CliApp::Codex, CliApp::Gemini]
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             CliApp::Claude => "claude",
@@ -197,10 +209,7 @@ pub fn get_sync_status(app: &CliApp, proxy_url: &str) -> (bool, bool, Option<Str
     let mut current_base_url = None;
 
     for file in &files {
-        // 使用更简单的命名规则: original_name + .antigravity.bak
-        let backup_path = file.path.with_file_name(format!("{}.antigravity.bak", file.name));
-        
-        if backup_path.exists() {
+        if restore_source(app, file).is_some() {
             has_backup = true;
         }
 
@@ -295,17 +304,10 @@ pub fn sync_config(app: &CliApp, proxy_url: &str, api_key: &str, model: Option<&
             fs::create_dir_all(parent).map_err(|e| format!("无法创建目录: {}", e))?;
         }
 
-        // [New Feature] 自动备份：如果文件存在且没有备份，创建 .antigravity.bak 备份
-        // 这样可以保留用户最初的配置，后续多次同步不会覆盖这个备份
+        // 每次改写前都对现有文件做带时间戳的快照，快照失败则中止同步，避免丢失用户手改的配置
         if file.path.exists() {
-            let backup_path = file.path.with_file_name(format!("{}.antigravity.bak", file.name));
-            if !backup_path.exists() {
-                if let Err(e) = fs::copy(&file.path, &backup_path) {
-                    tracing::warn!("Failed to create backup for {}: {}", file.name, e);
-                } else {
-                    tracing::info!("Created backup for {}: {:?}", file.name, backup_path);
-                }
-            }
+            let backup_path = snapshot_config_file(app, file)?;
+            tracing::info!("Created backup for {}: {:?}", file.name, backup_path);
         }
 
         let mut content = if file.path.exists() {
@@ -440,6 +442,130 @@ pub fn sync_config(app: &CliApp, proxy_url: &str, api_key: &str, model: Option<&
     Ok(())
 }
 
+/// 某个 CLI 的快照目录: <data_dir>/cli_backups/<app>
+fn backup_dir(app: &CliApp) -> Result<PathBuf, String> {
+    Ok(crate::modules::account::get_data_dir()?
+        .join(CLI_BACKUP_DIR)
+        .join(app.as_str()))
+}
+
+/// 列出某文件的所有快照 (从旧到新)
+fn list_backups(dir: &Path, file_name: &str) -> Vec<PathBuf> {
+    let prefix = format!("{}.", file_name);
+    let mut backups: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.file_name()
+                        .and_then(|n| n.to_str())
+                        .map(|n| n.starts_with(&prefix) && n.ends_with(".bak"))
+                        .unwrap_or(false)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    backups.sort();
+    backups
+}
+
+/// 首次同步前的原始配置: <原文件名>.original (不以 .bak 结尾，不会被轮换清理)
+fn original_backup_path(dir: &Path, file_name: &str) -> PathBuf {
+    dir.join(format!("{}.{}", file_name, ORIGINAL_BACKUP_SUFFIX))
+}
+
+/// 将 `src` 复制为 `dir` 下的新快照，并只保留最近 MAX_BACKUPS_PER_FILE 份
+/// 尚无原始配置时同时固定一份，恢复时以它为准
+fn snapshot_into(src: &Path, dir: &Path, file_name: &str) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("无法创建备份目录: {}", e))?;
+    let original = original_backup_path(dir, file_name);
+    if !original.exists() {
+        fs::copy(src, &original).map_err(|e| format!("备份原始 {} 失败: {}", file_name, e))?;
+    }
+    // 快照文件名: <原文件名>.<时间戳>-<序号>.bak，定长字段保证按文件名排序即按时间排序
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S%.3f").to_string();
+    let mut seq = 0;
+    let backup_path = loop {
+        let candidate = dir.join(format!("{}.{}-{:03}.bak", file_name, stamp, seq));
+        if !candidate.exists() {
+            break candidate;
+        }
+        seq += 1;
+    };
+    fs::copy(src, &backup_path).map_err(|e| format!("备份 {} 失败: {}", file_name, e))?;
+
+    let backups = list_backups(dir, file_name);
+    if backups.len() > MAX_BACKUPS_PER_FILE {
+        for old in &backups[..backups.len() - MAX_BACKUPS_PER_FILE] {
+            let _ = fs::remove_file(old);
+        }
+    }
+    Ok(backup_path)
+}
+
+/// 同步前为现有配置文件创建快照
+fn snapshot_config_file(app: &CliApp, file: &CliConfigFile) -> Result<PathBuf, String> {
+    snapshot_into(&file.path, &backup_dir(app)?, &file.name)
+}
+
+/// 恢复来源
+#[derive(Debug, PartialEq)]
+enum RestoreSource {
+    /// 首次同步前固定的原始配置
+    Original(PathBuf),
+    /// 旧版放在配置文件旁的 .antigravity.bak
+    Legacy(PathBuf),
+    /// 固定原始配置之前的版本只留下轮换快照: 取最早的一份
+    Oldest(PathBuf),
+}
+
+impl RestoreSource {
+    fn path(&self) -> &Path {
+        match self {
+            RestoreSource::Original(p) | RestoreSource::Legacy(p) | RestoreSource::Oldest(p) => p,
+        }
+    }
+}
+
+/// 恢复来源: 固定的原始配置 > 旧版 .antigravity.bak > 最早的快照
+fn restore_source_in(dir: Option<&Path>, file: &CliConfigFile) -> Option<RestoreSource> {
+    if let Some(dir) = dir {
+        let original = original_backup_path(dir, &file.name);
+        if original.exists() {
+            return Some(RestoreSource::Original(original));
+        }
+    }
+    let legacy = file.path.with_file_name(format!("{}.antigravity.bak", file.name));
+    if legacy.exists() {
+        return Some(RestoreSource::Legacy(legacy));
+    }
+    dir.and_then(|dir| list_backups(dir, &file.name).into_iter().next())
+        .map(RestoreSource::Oldest)
+}
+
+fn restore_source(app: &CliApp, file: &CliConfigFile) -> Option<RestoreSource> {
+    let dir = backup_dir(app).ok();
+    restore_source_in(dir.as_deref(), file)
+}
+
+/// 将配置文件恢复为同步前的原始内容，返回使用的来源
+fn restore_file_in(dir: Option<&Path>, file: &CliConfigFile) -> Result<Option<RestoreSource>, String> {
+    let Some(source) = restore_source_in(dir, file) else {
+        return Ok(None);
+    };
+    if let Some(parent) = file.path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("无法创建目录: {}", e))?;
+    }
+    fs::copy(source.path(), &file.path)
+        .map_err(|e| format!("恢复备份失败 {}: {}", file.name, e))?;
+    // 原始配置与旧版备份被消费 (下次同步重新固定)；轮换快照作为历史保留
+    if !matches!(source, RestoreSource::Oldest(_)) {
+        let _ = fs::remove_file(source.path());
+    }
+    Ok(Some(source))
+}
+
 // Tauri Commands
 
 #[tauri::command]
@@ -471,14 +597,11 @@ pub async fn execute_cli_restore(app_type: CliApp) -> Result<(), String> {
     let files = app_type.config_files();
     let mut restored_count = 0;
 
-    // 尝试从备份恢复
+    // 恢复为首次同步前的原始配置 (轮换快照可能已全部是同步后的内容)
+    let dir = backup_dir(&app_type).ok();
     for file in &files {
-        let backup_path = file.path.with_file_name(format!("{}.antigravity.bak", file.name));
-        if backup_path.exists() {
-            // 还原：覆盖原文件
-            if let Err(e) = fs::rename(&backup_path, &file.path) {
-                return Err(format!("恢复备份失败 {}: {}", file.name, e));
-            }
+        if let Some(source) = restore_file_in(dir.as_deref(), file)? {
+            tracing::info!("Restored {} from backup {:?}", file.name, source.path());
             restored_count += 1;
         }
     }
@@ -491,7 +614,78 @@ pub async fn execute_cli_restore(app_type: CliApp) -> Result<(), String> {
     // 如果没有备份，则执行原来的逻辑：恢复为默认配置
     let default_url = app_type.default_url();
     // 恢复默认时清空 API Key，让用户重新授权或使用官方 Key
-    sync_config(&app_type, default_url, "", None)
+    sync_config(&app_type, default_url, "", None)?;
+    // 上面的改写会把当前 (已同步) 内容固定为原始配置，丢弃它，下次同步重新固定
+    if let Some(dir) = dir {
+        for file in &files {
+            let _ = fs::remove_file(original_backup_path(&dir, &file.name));
+        }
+    }
+    Ok(())
+}
+
+/// 批量操作中单个 CLI 的结果
+#[derive(Debug, Serialize, Clone)]
+pub struct CliBatchResult {
+    /// "success" / "skipped" / "error"
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl CliBatchResult {
+    fn from_result(result: Result<(), String>) -> Self {
+        match result {
+            Ok(()) => Self {
+                status: "success",
+                message: None,
+            },
+            Err(e) => Self {
+                status: "error",
+                message: Some(e),
+            },
+        }
+    }
+
+    fn skipped(reason: &str) -> Self {
+        Self {
+            status: "skipped",
+            message: Some(reason.to_string()),
+        }
+    }
+}
+
+/// 同步所有已安装的 CLI，单个失败不影响其余
+pub async fn execute_cli_sync_all(
+    proxy_url: String,
+    api_key: String,
+) -> std::collections::BTreeMap<&'static str, CliBatchResult> {
+    let mut results = std::collections::BTreeMap::new();
+    for app in CliApp::all() {
+        let result = if check_cli_installed(&app).0 {
+            CliBatchResult::from_result(
+                execute_cli_sync(app.clone(), proxy_url.clone(), api_key.clone(), None).await,
+            )
+        } else {
+            CliBatchResult::skipped("not installed")
+        };
+        results.insert(app.as_str(), result);
+    }
+    results
+}
+
+/// 恢复所有已安装 CLI 的配置，单个失败不影响其余
+pub async fn execute_cli_restore_all() -> std::collections::BTreeMap<&'static str, CliBatchResult> {
+    let mut results = std::collections::BTreeMap::new();
+    for app in CliApp::all() {
+        let result = if check_cli_installed(&app).0 {
+            CliBatchResult::from_result(execute_cli_restore(app.clone()).await)
+        } else {
+            CliBatchResult::skipped("not installed")
+        };
+        results.insert(app.as_str(), result);
+    }
+    results
 }
 
 #[tauri::command]
@@ -508,3 +702,82 @@ pub async fn get_cli_config_content(app_type: CliApp, file_name: Option<String>)
     }
     fs::read_to_string(&file.path).map_err(|e| format!("读取配置文件失败: {}", e))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_keeps_latest_backups() {
+        let dir = std::env::temp_dir().join(format!("cli-backup-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let src = dir.join("config.toml");
+        let backups = dir.join("backups");
+
+        for i in 0..(MAX_BACKUPS_PER_FILE + 2) {
+            fs::write(&src, format!("version = {}", i)).unwrap();
+            snapshot_into(&src, &backups, "config.toml").unwrap();
+        }
+
+        let list = list_backups(&backups, "config.toml");
+        assert_eq!(list.len(), MAX_BACKUPS_PER_FILE);
+        let latest = fs::read_to_string(list.last().unwrap()).unwrap();
+        assert_eq!(latest, format!("version = {}", MAX_BACKUPS_PER_FILE + 1));
+
+        // 其他文件名的快照互不干扰
+        assert!(list_backups(&backups, "auth.json").is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_restore_uses_pinned_original_after_rotation() {
+        let dir = std::env::temp_dir().join(format!("cli-restore-test-{}", uuid::Uuid::new_v4()));
+        let backups = dir.join("backups");
+        fs::create_dir_all(&dir).unwrap();
+        let file = CliConfigFile {
+            name: "config.toml".to_string(),
+            path: dir.join("config.toml"),
+        };
+
+        // 多次同步后轮换快照已全部是同步后的内容
+        fs::write(&file.path, "user = true").unwrap();
+        for i in 0..(MAX_BACKUPS_PER_FILE + 2) {
+            snapshot_into(&file.path, &backups, &file.name).unwrap();
+            fs::write(&file.path, format!("synced = {}", i)).unwrap();
+        }
+        assert_eq!(list_backups(&backups, &file.name).len(), MAX_BACKUPS_PER_FILE);
+
+        let source = restore_file_in(Some(&backups), &file).unwrap().unwrap();
+        assert!(matches!(source, RestoreSource::Original(_)));
+        assert_eq!(fs::read_to_string(&file.path).unwrap(), "user = true");
+        // 原始配置被消费，下次同步重新固定
+        assert!(!original_backup_path(&backups, &file.name).exists());
+        snapshot_into(&file.path, &backups, &file.name).unwrap();
+        assert_eq!(
+            fs::read_to_string(original_backup_path(&backups, &file.name)).unwrap(),
+            "user = true"
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_restore_falls_back_to_legacy_backup() {
+        let dir = std::env::temp_dir().join(format!("cli-restore-legacy-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = CliConfigFile {
+            name: "settings.json".to_string(),
+            path: dir.join("settings.json"),
+        };
+        fs::write(&file.path, "{\"synced\":true}").unwrap();
+        fs::write(dir.join("settings.json.antigravity.bak"), "{}").unwrap();
+
+        let source = restore_file_in(Some(&dir.join("missing")), &file).unwrap().unwrap();
+        assert!(matches!(source, RestoreSource::Legacy(_)));
+        assert_eq!(fs::read_to_string(&file.path).unwrap(), "{}");
+        assert!(restore_file_in(Some(&dir.join("missing")), &file).unwrap().is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -173,7 +173,7 @@ pub async fn get_all_dynamic_models(
 /// - `claude-*-sonnet-*` matches `claude-3-5-sonnet-20241022` ✓
 /// - `*-thinking` matches `claude-opus-4-5-thinking` ✓
 /// - `a*b*c` matches `a123b456c` ✓
-fn wildcard_match(pattern: &str, text: &str) -> bool {
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
     let parts: Vec<&str> = pattern.split('*').collect();
 
     // No wildcard - exact match
@@ -210,54 +210,189 @@ fn wildcard_match(pattern: &str, text: &str) -> bool {
     true
 }
 
+/// `custom_mapping` 中正则规则的键前缀, 例如 `regex:^claude-3-5-(sonnet|haiku)-\d+$`
+pub const REGEX_RULE_PREFIX: &str = "regex:";
+
+/// 已编译的正则规则缓存 (键为去掉前缀后的表达式)
+static REGEX_CACHE: Lazy<std::sync::RwLock<HashMap<String, Option<regex::Regex>>>> =
+    Lazy::new(|| std::sync::RwLock::new(HashMap::new()));
+
+fn cached_regex(expr: &str) -> Option<regex::Regex> {
+    if let Ok(cache) = REGEX_CACHE.read() {
+        if let Some(entry) = cache.get(expr) {
+            return entry.clone();
+        }
+    }
+    let compiled = regex::Regex::new(expr).ok();
+    if compiled.is_none() {
+        tracing::warn!("[Router] Ignoring invalid regex mapping rule: {}", expr);
+    }
+    if let Ok(mut cache) = REGEX_CACHE.write() {
+        cache.insert(expr.to_string(), compiled.clone());
+    }
+    compiled
+}
+
+/// 校验 `custom_mapping` 中的 `regex:` 规则，返回所有无法编译的键
+pub fn find_invalid_mapping_rules(custom_mapping: &HashMap<String, String>) -> Vec<String> {
+    let mut invalid: Vec<String> = custom_mapping
+        .keys()
+        .filter(|key| {
+            key.strip_prefix(REGEX_RULE_PREFIX)
+                .map(|expr| regex::Regex::new(expr).is_err())
+                .unwrap_or(false)
+        })
+        .cloned()
+        .collect();
+    invalid.sort();
+    invalid
+}
+
+/// 模型路由命中的规则类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelRouteRule {
+    Exact,
+    Glob,
+    Regex,
+    /// 未命中自定义规则，使用系统默认映射 (可能原样透传)
+    Default,
+}
+
+/// 模型路由解析结果 (用于调试接口)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModelRouteMatch {
+    pub target: String,
+    pub rule: ModelRouteRule,
+    /// 命中的 `custom_mapping` 键
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
 /// 核心模型路由解析引擎
-/// 优先级：精确匹配 > 通配符匹配 > 系统默认映射
-/// 
+/// 优先级：精确匹配 > 通配符匹配 (最具体者优先) > `regex:` 正则匹配 > 系统默认映射
+///
 /// # 参数
 /// - `original_model`: 原始模型名称
 /// - `custom_mapping`: 用户自定义映射表
-/// 
+///
 /// # 返回
 /// 映射后的目标模型名称
 pub fn resolve_model_route(
     original_model: &str,
     custom_mapping: &std::collections::HashMap<String, String>,
 ) -> String {
+    let matched = explain_model_route(original_model, custom_mapping);
+    match matched.rule {
+        ModelRouteRule::Exact => {
+            crate::modules::logger::log_info(&format!("[Router] 精确映射: {} -> {}", original_model, matched.target));
+        }
+        ModelRouteRule::Glob | ModelRouteRule::Regex => {
+            let kind = if matched.rule == ModelRouteRule::Glob { "Wildcard" } else { "Regex" };
+            crate::modules::logger::log_info(&format!(
+                "[Router] {} match: {} -> {} (rule: {})",
+                kind,
+                original_model,
+                matched.target,
+                matched.pattern.as_deref().unwrap_or_default()
+            ));
+        }
+        ModelRouteRule::Default => {
+            if matched.target != original_model {
+                crate::modules::logger::log_info(&format!("[Router] 系统默认映射: {} -> {}", original_model, matched.target));
+            }
+        }
+    }
+    matched.target
+}
+
+/// 与 [`resolve_model_route`] 相同的解析过程，但返回命中的规则 (不输出日志)
+pub fn explain_model_route(
+    original_model: &str,
+    custom_mapping: &HashMap<String, String>,
+) -> ModelRouteMatch {
+    if let Some((pattern, target, rule)) = find_mapping_rule(original_model, custom_mapping) {
+        return ModelRouteMatch {
+            target: target.clone(),
+            rule,
+            pattern: Some(pattern.clone()),
+        };
+    }
+
+    // 4. 系统默认映射
+    ModelRouteMatch {
+        target: map_claude_model_to_gemini(original_model),
+        rule: ModelRouteRule::Default,
+        pattern: None,
+    }
+}
+
+/// 在按模型名索引的规则表中查找命中的规则 (键语法同 `custom_mapping`)
+/// 优先级：精确匹配 > 通配符匹配 (最具体者优先) > `regex:` 正则匹配
+fn find_mapping_rule<'a, V>(
+    model: &str,
+    rules: &'a HashMap<String, V>,
+) -> Option<(&'a String, &'a V, ModelRouteRule)> {
     // 1. 精确匹配 (最高优先级)
-    if let Some(target) = custom_mapping.get(original_model) {
-        crate::modules::logger::log_info(&format!("[Router] 精确映射: {} -> {}", original_model, target));
-        return target.clone();
+    if let Some((pattern, value)) = rules.get_key_value(model) {
+        return Some((pattern, value, ModelRouteRule::Exact));
     }
-    
+
     // 2. Wildcard match - most specific (highest non-wildcard chars) wins
     // Note: When multiple patterns have the SAME specificity, HashMap iteration order
     // determines the result (non-deterministic). Users can avoid this by making patterns
     // more specific. Future improvement: use IndexMap + frontend sorting for full control.
-    let mut best_match: Option<(&str, &str, usize)> = None;
+    let mut best_match: Option<(&String, &V, usize)> = None;
 
-    for (pattern, target) in custom_mapping.iter() {
-        if pattern.contains('*') && wildcard_match(pattern, original_model) {
+    for (pattern, value) in rules.iter() {
+        if pattern.starts_with(REGEX_RULE_PREFIX) {
+            continue;
+        }
+        if pattern.contains('*') && wildcard_match(pattern, model) {
             let specificity = pattern.chars().count() - pattern.matches('*').count();
-            if best_match.is_none() || specificity > best_match.unwrap().2 {
-                best_match = Some((pattern.as_str(), target.as_str(), specificity));
+            if best_match.map_or(true, |(_, _, best)| specificity > best) {
+                best_match = Some((pattern, value, specificity));
             }
         }
     }
 
-    if let Some((pattern, target, _)) = best_match {
-        crate::modules::logger::log_info(&format!(
-            "[Router] Wildcard match: {} -> {} (rule: {})",
-            original_model, target, pattern
-        ));
-        return target.to_string();
+    if let Some((pattern, value, _)) = best_match {
+        return Some((pattern, value, ModelRouteRule::Glob));
     }
-    
-    // 3. 系统默认映射
-    let result = map_claude_model_to_gemini(original_model);
-    if result != original_model {
-        crate::modules::logger::log_info(&format!("[Router] 系统默认映射: {} -> {}", original_model, result));
+
+    // 3. 正则匹配 (按键名排序，保证多条规则同时命中时结果稳定)
+    let mut regex_rules: Vec<(&String, &V)> = rules
+        .iter()
+        .filter(|(key, _)| key.starts_with(REGEX_RULE_PREFIX))
+        .collect();
+    regex_rules.sort_by(|a, b| a.0.cmp(b.0));
+    for (key, value) in regex_rules {
+        let expr = &key[REGEX_RULE_PREFIX.len()..];
+        if let Some(re) = cached_regex(expr) {
+            if re.is_match(model) {
+                return Some((key, value, ModelRouteRule::Regex));
+            }
+        }
     }
-    result
+
+    None
+}
+
+/// 解析模型要求的账号标签 (`model_account_tags`，键语法同 `custom_mapping`)
+/// 未命中任何规则时返回空列表，表示使用完整账号池
+pub fn resolve_required_account_tags(
+    model: &str,
+    model_account_tags: &HashMap<String, Vec<String>>,
+) -> Vec<String> {
+    find_mapping_rule(model, model_account_tags)
+        .map(|(_, tags, _)| {
+            tags.iter()
+                .map(|t| t.trim())
+                .filter(|t| !t.is_empty())
+                .map(|t| t.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
 /// Normalize any physical model name to one of the 3 standard protection IDs.
@@ -380,4 +515,49 @@ mod tests {
         // Multi-wildcard: "a*b*c" (3)
         assert_eq!(resolve_model_route("a-test-b-foo-c", &custom), "multi-wild");
     }
+
+    #[test]
+    fn test_regex_mapping_precedence() {
+        let mut custom = HashMap::new();
+        custom.insert("claude-3-5-sonnet-20241022".to_string(), "exact".to_string());
+        custom.insert("claude-3-5-sonnet-*".to_string(), "glob".to_string());
+        custom.insert(r"regex:^claude-3-5-(sonnet|haiku)-\d+$".to_string(), "regex".to_string());
+
+        // 精确 > 通配符 > 正则 > 默认
+        assert_eq!(resolve_model_route("claude-3-5-sonnet-20241022", &custom), "exact");
+        assert_eq!(resolve_model_route("claude-3-5-sonnet-20240620", &custom), "glob");
+        assert_eq!(resolve_model_route("claude-3-5-haiku-20241022", &custom), "regex");
+
+        let m = explain_model_route("claude-3-5-haiku-20241022", &custom);
+        assert_eq!(m.rule, ModelRouteRule::Regex);
+        assert_eq!(m.pattern.as_deref(), Some(r"regex:^claude-3-5-(sonnet|haiku)-\d+$"));
+
+        let m = explain_model_route("some-unknown-model", &custom);
+        assert_eq!(m.rule, ModelRouteRule::Default);
+        assert_eq!(m.target, "some-unknown-model");
+    }
+
+    #[test]
+    fn test_invalid_regex_rules_detected() {
+        let mut custom = HashMap::new();
+        custom.insert("regex:^gpt-(4|5".to_string(), "bad".to_string());
+        custom.insert("regex:^gpt-4o.*$".to_string(), "good".to_string());
+        custom.insert("gpt-*".to_string(), "glob".to_string());
+
+        assert_eq!(find_invalid_mapping_rules(&custom), vec!["regex:^gpt-(4|5".to_string()]);
+        // 非法规则在解析时被跳过
+        custom.remove("gpt-*");
+        assert_eq!(resolve_model_route("gpt-4o-mini", &custom), "good");
+    }
+
+    #[test]
+    fn test_required_account_tags() {
+        let mut rules = HashMap::new();
+        rules.insert("gemini-3-pro-*".to_string(), vec!["team-a".to_string(), " ".to_string()]);
+        rules.insert("gemini-3-pro-high".to_string(), vec!["ultra".to_string()]);
+
+        assert_eq!(resolve_required_account_tags("gemini-3-pro-high", &rules), vec!["ultra"]);
+        assert_eq!(resolve_required_account_tags("gemini-3-pro-low", &rules), vec!["team-a"]);
+        assert!(resolve_required_account_tags("gemini-3-flash", &rules).is_empty());
+    }
 }
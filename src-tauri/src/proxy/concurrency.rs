@@ -0,0 +1,315 @@
+// 账号级并发限制 + 双通道优先级排队
+// - 每个账号同时在途的上游请求数受 `max_per_account` 限制
+// - 携带 `X-ABV-Priority: low` 的请求在争用时排在普通请求之后
+// - 排队超过 `queue_timeout_secs` 的请求返回 429 + Retry-After
+
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use crate::proxy::TokenManager;
+
+pub const PRIORITY_HEADER: &str = "x-abv-priority";
+
+/// 请求优先级通道
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RequestPriority {
+    #[default]
+    Normal,
+    Low,
+}
+
+impl RequestPriority {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        match headers
+            .get(PRIORITY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("low") => RequestPriority::Low,
+            _ => RequestPriority::Normal,
+        }
+    }
+}
+
+/// 单账号在途/排队统计 (用于 /api/proxy/stats)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AccountInFlight {
+    pub in_flight: usize,
+    pub queued_normal: usize,
+    pub queued_low: usize,
+}
+
+/// 单账号并发限制器
+#[derive(Default)]
+pub struct AccountLimiter {
+    state: Arc<Mutex<AccountInFlight>>,
+    notify: Arc<Notify>,
+}
+
+/// 并发许可，Drop 时释放名额并唤醒排队请求
+pub struct AccountPermit {
+    state: Arc<Mutex<AccountInFlight>>,
+    notify: Arc<Notify>,
+}
+
+impl Drop for AccountPermit {
+    fn drop(&mut self) {
+        if let Ok(mut s) = self.state.lock() {
+            s.in_flight = s.in_flight.saturating_sub(1);
+        }
+        self.notify.notify_waiters();
+    }
+}
+
+impl AccountLimiter {
+    pub fn snapshot(&self) -> AccountInFlight {
+        self.state.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    fn set_queued(&self, priority: RequestPriority, delta: isize) {
+        if let Ok(mut s) = self.state.lock() {
+            let slot = match priority {
+                RequestPriority::Normal => &mut s.queued_normal,
+                RequestPriority::Low => &mut s.queued_low,
+            };
+            *slot = slot.saturating_add_signed(delta);
+        }
+    }
+
+    /// 尝试立即获取许可；低优先级请求在有普通请求排队时让行
+    fn try_acquire(&self, priority: RequestPriority, limit: usize, queued: bool) -> Option<AccountPermit> {
+        let mut s = self.state.lock().ok()?;
+        let normal_ahead = match priority {
+            RequestPriority::Normal => false,
+            RequestPriority::Low => s.queued_normal > 0,
+        };
+        if s.in_flight >= limit || normal_ahead {
+            return None;
+        }
+        s.in_flight += 1;
+        if queued {
+            match priority {
+                RequestPriority::Normal => s.queued_normal = s.queued_normal.saturating_sub(1),
+                RequestPriority::Low => s.queued_low = s.queued_low.saturating_sub(1),
+            }
+        }
+        Some(AccountPermit {
+            state: self.state.clone(),
+            notify: self.notify.clone(),
+        })
+    }
+
+    /// 在 `timeout` 内获取许可，超时返回 None
+    pub async fn acquire(
+        &self,
+        priority: RequestPriority,
+        limit: usize,
+        timeout: Duration,
+    ) -> Option<AccountPermit> {
+        if let Some(permit) = self.try_acquire(priority, limit, false) {
+            return Some(permit);
+        }
+
+        self.set_queued(priority, 1);
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            // 先注册唤醒再检查，避免丢失 notify_waiters
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            if let Some(permit) = self.try_acquire(priority, limit, true) {
+                return Some(permit);
+            }
+
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                self.set_queued(priority, -1);
+                // 普通请求退出队列后，低优先级请求可能已经可以继续
+                self.notify.notify_waiters();
+                return None;
+            }
+        }
+    }
+}
+
+tokio::task_local! {
+    static REQUEST_SCOPE: RequestScope;
+}
+
+/// 单个反代请求的并发上下文 (由中间件建立)
+#[derive(Clone, Default)]
+pub struct RequestScope {
+    pub priority: RequestPriority,
+    /// [NEW] Ip 粘性会话模式下解析出的客户端 IP
+    pub client_ip: Option<std::net::IpAddr>,
+    permit: Arc<Mutex<Option<AccountPermit>>>,
+}
+
+impl RequestScope {
+    pub fn new(priority: RequestPriority) -> Self {
+        Self {
+            priority,
+            client_ip: None,
+            permit: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn with_client_ip(mut self, client_ip: Option<std::net::IpAddr>) -> Self {
+        self.client_ip = client_ip;
+        self
+    }
+
+    /// 取出当前持有的许可 (用于绑定到响应体，直到流式响应结束才释放)
+    pub fn take_permit(&self) -> Option<AccountPermit> {
+        self.permit.lock().ok().and_then(|mut p| p.take())
+    }
+
+    /// 在该作用域内执行请求处理
+    pub async fn run<F: std::future::Future>(self, fut: F) -> F::Output {
+        REQUEST_SCOPE.scope(self, fut).await
+    }
+}
+
+/// 当前请求的客户端 IP (不在中间件作用域内或未解析时为 None)
+pub fn current_client_ip() -> Option<std::net::IpAddr> {
+    REQUEST_SCOPE.try_with(|s| s.client_ip).ok().flatten()
+}
+
+/// 为当前请求在选定账号上申请并发许可。
+/// 重试切换账号时会替换 (释放) 之前账号的许可；不在中间件作用域内时直接放行。
+pub async fn acquire_account_slot(
+    token_manager: &TokenManager,
+    account_id: &str,
+) -> Result<(), Response> {
+    let Ok(scope) = REQUEST_SCOPE.try_with(|s| s.clone()) else {
+        return Ok(());
+    };
+
+    // 先释放旧账号的许可，避免重试期间占用两个名额
+    drop(scope.take_permit());
+
+    let permit = acquire_permit(token_manager, account_id, scope.priority).await?;
+    if let Ok(mut slot) = scope.permit.lock() {
+        *slot = permit;
+    }
+    Ok(())
+}
+
+/// [NEW] 为同一请求内的并行子任务 (如 Embeddings 分批) 单独申请许可。
+/// 许可由调用方持有，不写入请求作用域，避免并行子任务互相替换 (释放) 对方的许可；
+/// 不在中间件作用域内时直接放行 (返回 None)
+pub async fn acquire_detached_slot(
+    token_manager: &TokenManager,
+    account_id: &str,
+) -> Result<Option<AccountPermit>, Response> {
+    let Ok(priority) = REQUEST_SCOPE.try_with(|s| s.priority) else {
+        return Ok(None);
+    };
+    acquire_permit(token_manager, account_id, priority).await
+}
+
+async fn acquire_permit(
+    token_manager: &TokenManager,
+    account_id: &str,
+    priority: RequestPriority,
+) -> Result<Option<AccountPermit>, Response> {
+    token_manager
+        .acquire_account_permit(account_id, priority)
+        .await
+        .map_err(|retry_after| {
+            tracing::warn!(
+                "[Concurrency] Account {} queue timeout ({:?} priority), rejecting with 429",
+                account_id,
+                priority
+            );
+            too_many_requests(retry_after)
+        })
+}
+
+fn too_many_requests(retry_after: Duration) -> Response {
+    let secs = retry_after.as_secs().max(1);
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, secs.to_string())],
+        Json(json!({
+            "error": {
+                "type": "rate_limit_error",
+                "message": format!(
+                    "Account concurrency limit reached, retry after {}s",
+                    secs
+                )
+            }
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(RequestPriority::from_headers(&headers), RequestPriority::Normal);
+        headers.insert(PRIORITY_HEADER, "LOW".parse().unwrap());
+        assert_eq!(RequestPriority::from_headers(&headers), RequestPriority::Low);
+    }
+
+    #[tokio::test]
+    async fn test_limit_and_timeout() {
+        let limiter = AccountLimiter::default();
+        let p1 = limiter
+            .acquire(RequestPriority::Normal, 1, Duration::from_millis(10))
+            .await;
+        assert!(p1.is_some());
+        assert_eq!(limiter.snapshot().in_flight, 1);
+
+        let p2 = limiter
+            .acquire(RequestPriority::Normal, 1, Duration::from_millis(20))
+            .await;
+        assert!(p2.is_none());
+        assert_eq!(limiter.snapshot().queued_normal, 0);
+
+        drop(p1);
+        assert_eq!(limiter.snapshot().in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn test_low_priority_yields_to_normal() {
+        let limiter = Arc::new(AccountLimiter::default());
+        let held = limiter
+            .acquire(RequestPriority::Normal, 1, Duration::from_millis(10))
+            .await
+            .unwrap();
+
+        let normal = {
+            let limiter = limiter.clone();
+            tokio::spawn(async move {
+                limiter
+                    .acquire(RequestPriority::Normal, 1, Duration::from_secs(2))
+                    .await
+                    .is_some()
+            })
+        };
+        // 等待普通请求进入队列
+        while limiter.snapshot().queued_normal == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        // 有普通请求排队时，低优先级请求不能插队
+        assert!(limiter.try_acquire(RequestPriority::Low, 1, false).is_none());
+
+        drop(held);
+        assert!(normal.await.unwrap());
+    }
+}
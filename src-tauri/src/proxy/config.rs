@@ -1,3 +1,4 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 // use std::path::PathBuf;
 use std::collections::HashMap;
@@ -37,20 +38,48 @@ pub fn get_thinking_budget_config() -> ThinkingBudgetConfig {
 
 /// 更新全局 Thinking Budget 配置
 pub fn update_thinking_budget_config(config: ThinkingBudgetConfig) {
-    if let Some(lock) = GLOBAL_THINKING_BUDGET_CONFIG.get() {
+    store_thinking_config(&GLOBAL_THINKING_BUDGET_CONFIG, "Thinking-Budget", config);
+}
+
+// [NEW] Claude 协议 thinking.budget_tokens 的独立配置 (与 Gemini 的 thinking_budget 分开存储)
+static GLOBAL_CLAUDE_THINKING_CONFIG: OnceLock<RwLock<ThinkingBudgetConfig>> = OnceLock::new();
+
+/// 获取当前 Claude Thinking 配置
+pub fn get_claude_thinking_config() -> ThinkingBudgetConfig {
+    GLOBAL_CLAUDE_THINKING_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .map(|cfg| cfg.clone())
+        .unwrap_or_else(default_claude_thinking)
+}
+
+/// 更新全局 Claude Thinking 配置
+pub fn update_claude_thinking_config(config: ThinkingBudgetConfig) {
+    store_thinking_config(&GLOBAL_CLAUDE_THINKING_CONFIG, "Claude-Thinking", config);
+}
+
+fn store_thinking_config(
+    slot: &OnceLock<RwLock<ThinkingBudgetConfig>>,
+    tag: &str,
+    config: ThinkingBudgetConfig,
+) {
+    if let Some(lock) = slot.get() {
         if let Ok(mut cfg) = lock.write() {
             *cfg = config.clone();
             tracing::info!(
-                "[Thinking-Budget] Global config updated: mode={:?}, custom_value={}",
+                "[{}] Global config updated: mode={:?}, custom_value={}, per_model_overrides={}",
+                tag,
                 config.mode,
-                config.custom_value
+                config.custom_value,
+                config.per_model_overrides.len()
             );
         }
     } else {
         // 首次初始化
-        let _ = GLOBAL_THINKING_BUDGET_CONFIG.set(RwLock::new(config.clone()));
+        let _ = slot.set(RwLock::new(config.clone()));
         tracing::info!(
-            "[Thinking-Budget] Global config initialized: mode={:?}, custom_value={}",
+            "[{}] Global config initialized: mode={:?}, custom_value={}",
+            tag,
             config.mode,
             config.custom_value
         );
@@ -94,6 +123,217 @@ pub fn update_global_system_prompt_config(config: GlobalSystemPromptConfig) {
     }
 }
 
+// ============================================================================
+// 全局身份注入配置存储
+// wrap_request 据此决定是否 / 如何注入 Antigravity 身份
+// ============================================================================
+static GLOBAL_IDENTITY_INJECTION_CONFIG: OnceLock<RwLock<IdentityInjectionConfig>> =
+    OnceLock::new();
+
+/// 获取当前身份注入配置
+pub fn get_identity_injection_config() -> IdentityInjectionConfig {
+    GLOBAL_IDENTITY_INJECTION_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .map(|cfg| cfg.clone())
+        .unwrap_or_default()
+}
+
+/// 更新全局身份注入配置
+pub fn update_identity_injection_config(config: IdentityInjectionConfig) {
+    if let Some(lock) = GLOBAL_IDENTITY_INJECTION_CONFIG.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = config.clone();
+            tracing::info!(
+                "[Identity-Injection] Config updated: mode={:?}, template_len={}",
+                config.mode,
+                config.custom_template.len()
+            );
+        }
+    } else {
+        // 首次初始化
+        let _ = GLOBAL_IDENTITY_INJECTION_CONFIG.set(RwLock::new(config.clone()));
+        tracing::info!(
+            "[Identity-Injection] Config initialized: mode={:?}, template_len={}",
+            config.mode,
+            config.custom_template.len()
+        );
+    }
+}
+
+// ============================================================================
+// 全局错误响应模板配置存储
+// ============================================================================
+static GLOBAL_ERROR_TEMPLATES: OnceLock<RwLock<ErrorTemplateConfig>> = OnceLock::new();
+
+/// 获取当前错误响应模板配置
+pub fn get_error_templates() -> ErrorTemplateConfig {
+    GLOBAL_ERROR_TEMPLATES
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .map(|cfg| cfg.clone())
+        .unwrap_or_default()
+}
+
+/// 更新错误响应模板配置
+pub fn update_error_templates(config: ErrorTemplateConfig) {
+    let count = config.configured_count();
+    if let Some(lock) = GLOBAL_ERROR_TEMPLATES.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = config;
+            tracing::info!("[Error-Templates] Config updated: {} template(s)", count);
+        }
+    } else {
+        let _ = GLOBAL_ERROR_TEMPLATES.set(RwLock::new(config));
+        tracing::info!("[Error-Templates] Config initialized: {} template(s)", count);
+    }
+}
+
+// ============================================================================
+// 全局访问日志格式存储
+// ============================================================================
+static GLOBAL_ACCESS_LOG_FORMAT: OnceLock<RwLock<AccessLogFormat>> = OnceLock::new();
+
+/// 获取当前访问日志格式
+pub fn get_access_log_format() -> AccessLogFormat {
+    GLOBAL_ACCESS_LOG_FORMAT
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .map(|cfg| *cfg)
+        .unwrap_or_default()
+}
+
+/// 更新访问日志格式
+pub fn update_access_log_format(format: AccessLogFormat) {
+    if let Some(lock) = GLOBAL_ACCESS_LOG_FORMAT.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = format;
+            tracing::info!("[Access-Log] Format updated: {:?}", format);
+        }
+    } else {
+        let _ = GLOBAL_ACCESS_LOG_FORMAT.set(RwLock::new(format));
+        tracing::info!("[Access-Log] Format initialized: {:?}", format);
+    }
+}
+
+// ============================================================================
+// 全局 Gemini safetySettings 策略存储
+// ============================================================================
+static GLOBAL_SAFETY_SETTINGS: OnceLock<RwLock<SafetySettingsPolicy>> = OnceLock::new();
+
+/// 获取当前 safetySettings 策略
+pub fn get_safety_settings_policy() -> SafetySettingsPolicy {
+    GLOBAL_SAFETY_SETTINGS
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .map(|cfg| cfg.clone())
+        .unwrap_or_default()
+}
+
+/// 更新 safetySettings 策略
+pub fn update_safety_settings_policy(policy: SafetySettingsPolicy) {
+    let count = policy.defaults.as_ref().map(|d| d.len()).unwrap_or(0);
+    if let Some(lock) = GLOBAL_SAFETY_SETTINGS.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = policy;
+            tracing::info!("[Safety-Settings] Policy updated: {} default setting(s)", count);
+        }
+    } else {
+        let _ = GLOBAL_SAFETY_SETTINGS.set(RwLock::new(policy));
+        tracing::info!("[Safety-Settings] Policy initialized: {} default setting(s)", count);
+    }
+}
+
+// ============================================================================
+// 全局响应头注入配置存储
+// ============================================================================
+static GLOBAL_RESPONSE_HEADERS: OnceLock<RwLock<std::collections::HashMap<String, String>>> =
+    OnceLock::new();
+
+/// 获取当前需要注入到反代响应中的自定义响应头
+pub fn get_response_headers() -> std::collections::HashMap<String, String> {
+    GLOBAL_RESPONSE_HEADERS
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .map(|h| h.clone())
+        .unwrap_or_default()
+}
+
+/// 更新自定义响应头配置
+pub fn update_response_headers(headers: std::collections::HashMap<String, String>) {
+    let count = headers.len();
+    if let Some(lock) = GLOBAL_RESPONSE_HEADERS.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = headers;
+            tracing::info!("[Response-Headers] Config updated: {} header(s)", count);
+        }
+    } else {
+        let _ = GLOBAL_RESPONSE_HEADERS.set(RwLock::new(headers));
+        tracing::info!("[Response-Headers] Config initialized: {} header(s)", count);
+    }
+}
+
+// ============================================================================
+// 全局模型账号标签路由配置存储
+// ============================================================================
+static GLOBAL_MODEL_ACCOUNT_TAGS: OnceLock<RwLock<std::collections::HashMap<String, Vec<String>>>> =
+    OnceLock::new();
+
+/// 获取当前模型 → 账号标签要求
+pub fn get_model_account_tags() -> std::collections::HashMap<String, Vec<String>> {
+    GLOBAL_MODEL_ACCOUNT_TAGS
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .map(|r| r.clone())
+        .unwrap_or_default()
+}
+
+/// 更新模型 → 账号标签要求
+pub fn update_model_account_tags(rules: std::collections::HashMap<String, Vec<String>>) {
+    let count = rules.len();
+    if let Some(lock) = GLOBAL_MODEL_ACCOUNT_TAGS.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = rules;
+            tracing::info!("[Account-Tags] Config updated: {} rule(s)", count);
+        }
+    } else {
+        let _ = GLOBAL_MODEL_ACCOUNT_TAGS.set(RwLock::new(rules));
+        tracing::info!("[Account-Tags] Config initialized: {} rule(s)", count);
+    }
+}
+
+// ============================================================================
+// 全局模型 → 上游地址路由配置存储
+// ============================================================================
+static GLOBAL_MODEL_ROUTES: OnceLock<RwLock<Vec<ModelRoute>>> = OnceLock::new();
+
+/// 按声明顺序查找第一个匹配 `model` 的路由
+pub fn find_model_route<'a>(routes: &'a [ModelRoute], model: &str) -> Option<&'a ModelRoute> {
+    routes.iter().find(|r| r.matches(model))
+}
+
+/// 在当前生效的路由表中查找 `model` 的上游路由
+pub fn resolve_model_route(model: &str) -> Option<ModelRoute> {
+    GLOBAL_MODEL_ROUTES
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .and_then(|routes| find_model_route(&routes, model).cloned())
+}
+
+/// 更新模型 → 上游地址路由表
+pub fn update_model_routes(routes: Vec<ModelRoute>) {
+    let count = routes.len();
+    if let Some(lock) = GLOBAL_MODEL_ROUTES.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = routes;
+            tracing::info!("[Model-Routes] Config updated: {} route(s)", count);
+        }
+    } else {
+        let _ = GLOBAL_MODEL_ROUTES.set(RwLock::new(routes));
+        tracing::info!("[Model-Routes] Config initialized: {} route(s)", count);
+    }
+}
+
 // ============================================================================
 // 全局图像思维模式配置存储
 // ============================================================================
@@ -122,8 +362,37 @@ pub fn update_image_thinking_mode(mode: Option<String>) {
     }
 }
 
+/// [NEW] 更新所有进程级全局配置 (Thinking Budget、系统提示词、响应头、超时、预算等)
+/// 启动反代、Tauri 保存配置与 Web 端保存 / 重新加载 / 文件监听共用，保证各入口更新的配置项一致
+pub fn apply_global_proxy_config(config: &ProxyConfig) {
+    update_thinking_budget_config(config.thinking_budget.clone());
+    update_claude_thinking_config(config.claude_thinking.clone());
+    update_global_system_prompt_config(config.global_system_prompt.clone());
+    update_identity_injection_config(config.identity_injection.clone());
+    update_image_thinking_mode(config.image_thinking_mode.clone());
+    update_response_headers(config.response_headers.clone());
+    update_error_templates(config.error_templates.clone());
+    update_access_log_format(config.access_log_format);
+    update_safety_settings_policy(SafetySettingsPolicy::from_proxy_config(config));
+    update_model_account_tags(config.model_account_tags.clone());
+    update_model_routes(config.model_routes.clone());
+    crate::proxy::webhook::WebhookDispatcher::global().update_config(config.webhooks.clone());
+    // 模型映射等配置可能已变化，重新配置时缓存同时失效
+    crate::proxy::model_list_cache::ModelListCache::global()
+        .configure(config.model_list_cache_ttl_secs);
+    crate::proxy::response_cache::ResponseCache::global()
+        .configure(config.response_cache_ttl_secs);
+    crate::proxy::timeouts::update_timeout_policy(config);
+    crate::proxy::budget::update_budget_config(config.budget.clone());
+    crate::proxy::transforms::update_transforms_config(config.transforms.clone());
+    crate::proxy::mappers::system_prompt_budget::update_system_prompt_budget(config);
+    crate::proxy::mappers::openai::embeddings::update_embedding_batch_size(
+        config.embedding_batch_size,
+    );
+}
+
 /// 全局系统提示词配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GlobalSystemPromptConfig {
     /// 是否启用全局系统提示词
     #[serde(default)]
@@ -142,7 +411,100 @@ impl Default for GlobalSystemPromptConfig {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Antigravity 身份注入模式
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentityInjectionMode {
+    /// 始终注入 (已包含身份时不重复注入)
+    Always,
+    /// 仅在请求未携带 systemInstruction 时注入
+    OnlyWhenMissing,
+    /// 从不注入，systemInstruction 原样透传
+    Never,
+    /// 注入用户自定义的身份模板
+    Custom,
+}
+
+impl Default for IdentityInjectionMode {
+    fn default() -> Self {
+        Self::Always
+    }
+}
+
+/// 身份注入配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct IdentityInjectionConfig {
+    #[serde(default)]
+    pub mode: IdentityInjectionMode,
+    /// custom 模式下注入的身份文本 (为空时不注入)
+    #[serde(default)]
+    pub custom_template: String,
+}
+
+/// 常见 AI 错误的自定义响应模板 (未配置的类型原样返回上游错误)
+/// 模板变量: `{{account_id}}`, `{{model}}`, `{{upstream_error}}`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq)]
+pub struct ErrorTemplateConfig {
+    /// 配额耗尽 / 限流 (429)
+    #[serde(default)]
+    pub quota_exceeded: Option<String>,
+    /// 模型不存在 (404)
+    #[serde(default)]
+    pub model_not_found: Option<String>,
+    /// 上下文超长
+    #[serde(default)]
+    pub context_too_long: Option<String>,
+}
+
+impl ErrorTemplateConfig {
+    pub fn configured_count(&self) -> usize {
+        [&self.quota_exceeded, &self.model_not_found, &self.context_too_long]
+            .iter()
+            .filter(|t| t.as_deref().is_some_and(|t| !t.trim().is_empty()))
+            .count()
+    }
+}
+
+/// 反代访问日志格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessLogFormat {
+    /// 结构化事件写入主日志 (target = access_log，配合 ABV_LOG_FORMAT=json 采集)
+    #[default]
+    Json,
+    /// Apache Combined Log Format，写入独立的 access.log (按天滚动)
+    Combined,
+    /// 不输出访问日志
+    None,
+}
+
+/// [NEW] Gemini safetySettings 条目 (字段与 Gemini API 一致)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct SafetySetting {
+    /// 如 `HARM_CATEGORY_DANGEROUS_CONTENT`
+    pub category: String,
+    /// 如 `BLOCK_NONE` / `OFF`
+    pub threshold: String,
+}
+
+/// safetySettings 运行时策略 (由 ProxyConfig 的两个字段组成)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SafetySettingsPolicy {
+    pub defaults: Option<Vec<SafetySetting>>,
+    /// 为 true 时无条件用 defaults 替换客户端传入的设置
+    pub override_client: bool,
+}
+
+impl SafetySettingsPolicy {
+    pub fn from_proxy_config(config: &ProxyConfig) -> Self {
+        Self {
+            defaults: config.default_safety_settings.clone(),
+            override_client: config.safety_settings_override,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ProxyAuthMode {
     Off,
@@ -157,7 +519,51 @@ impl Default for ProxyAuthMode {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// [NEW] 模型 → 上游地址路由: 命中的请求发往 `base_url` 而非默认的 v1internal 端点
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct ModelRoute {
+    /// 路由后目标模型名的 `*` 通配符模式 (大小写敏感)，如 `claude-*`
+    pub pattern: String,
+    /// v1internal 兼容的基础地址，如 `http://10.0.0.2:8045/v1internal`
+    pub base_url: String,
+    /// 覆盖 Authorization 请求头的完整值 (如 `Bearer sk-xxx`)；为空时仅 googleapis.com 端点沿用账号令牌，
+    /// 其他地址不携带凭据
+    #[serde(default)]
+    pub auth_header: Option<String>,
+}
+
+impl ModelRoute {
+    pub fn matches(&self, model: &str) -> bool {
+        crate::proxy::common::model_mapping::wildcard_match(self.pattern.trim(), model)
+    }
+
+    /// 去掉末尾的 `/`，以便拼接 `:method`
+    pub fn normalized_base_url(&self) -> &str {
+        self.base_url.trim().trim_end_matches('/')
+    }
+
+    /// 目标是否为 Google 官方端点 (*.googleapis.com, https)。
+    /// 只有官方端点才会收到账号的 OAuth 令牌
+    pub fn is_google_endpoint(&self) -> bool {
+        url::Url::parse(self.normalized_base_url())
+            .ok()
+            .filter(|u| u.scheme() == "https")
+            .and_then(|u| u.host_str().map(|h| h.to_ascii_lowercase()))
+            .is_some_and(|host| host == "googleapis.com" || host.ends_with(".googleapis.com"))
+    }
+}
+
+/// [NEW] 附加 API 密钥: 按客户端/成员分别签发，可单独禁用，label 用于用量归属
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct ApiKeyEntry {
+    pub key: String,
+    /// 唯一标签，如 `alice-laptop`
+    pub label: String,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum ZaiDispatchMode {
     /// Never use z.ai.
@@ -176,7 +582,7 @@ impl Default for ZaiDispatchMode {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ZaiModelDefaults {
     /// Default model for "opus" family (when the incoming model is a Claude id).
     #[serde(default = "default_zai_opus_model")]
@@ -199,7 +605,7 @@ impl Default for ZaiModelDefaults {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ZaiMcpConfig {
     #[serde(default)]
     pub enabled: bool,
@@ -222,7 +628,7 @@ impl Default for ZaiMcpConfig {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ZaiConfig {
     #[serde(default)]
     pub enabled: bool,
@@ -232,8 +638,9 @@ pub struct ZaiConfig {
     pub api_key: String,
     #[serde(default)]
     pub dispatch_mode: ZaiDispatchMode,
-    /// Optional per-model mapping overrides for Anthropic/Claude model ids.
-    /// Key: incoming `model` string, Value: upstream z.ai model id (e.g. `glm-4.7`).
+    /// 可选的 Anthropic/Claude 模型 ID 映射覆盖
+    /// 键: 请求中的 `model`，值: 上游 z.ai 模型 ID (如 `glm-4.7`)
+    /// 键支持 `*` 通配符 (如 `claude-3-5-*`)，优先级: 精确匹配 > 最具体的通配符 > `models` 按系列的默认值
     #[serde(default)]
     pub model_mapping: HashMap<String, String>,
     #[serde(default)]
@@ -257,7 +664,7 @@ impl Default for ZaiConfig {
 }
 
 /// 实验性功能配置 (Feature Flags)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ExperimentalConfig {
     /// 启用双层签名缓存 (Signature Cache)
     #[serde(default = "default_true")]
@@ -288,6 +695,47 @@ pub struct ExperimentalConfig {
     /// 上下文压缩阈值 L3 (Fork + Summary)
     #[serde(default = "default_threshold_l3")]
     pub context_compression_threshold_l3: f32,
+
+    /// Claude 流式响应 `ping` 保活间隔 (秒)，0 表示关闭
+    /// 上游长时间无输出 (如 thinking 阶段) 时防止中间代理因空闲断开连接
+    #[serde(default = "default_stream_ping_interval_secs")]
+    pub stream_ping_interval_secs: u64,
+
+    /// 将会话签名缓存持久化到数据目录 (SQLite)，重启后恢复
+    #[serde(default = "default_false")]
+    pub signature_cache_persistence: bool,
+
+    /// 会话签名缓存过期时间 (秒)，默认 2 小时
+    #[serde(default = "default_signature_cache_ttl_secs")]
+    pub signature_cache_ttl_secs: u64,
+
+    /// 缓存可复用的响应 (目前用于音频转录: 相同音频重复上传直接返回缓存结果)
+    #[serde(default = "default_false")]
+    pub enable_response_cache: bool,
+
+    /// 音频转录缓存过期时间 (秒)，默认 1 小时
+    #[serde(default = "default_audio_cache_ttl_secs")]
+    pub audio_cache_ttl_secs: u64,
+
+    /// functionCall 参数不符合声明的 schema 时返回错误 (默认仅记录警告)
+    #[serde(default = "default_false")]
+    pub strict_function_schema_validation: bool,
+
+    /// Gemini 请求中单张 inlineData 图片解码后的最大字节数，默认 4 MB，0 表示不限制
+    #[serde(default = "default_max_image_bytes")]
+    pub max_image_bytes: usize,
+
+    /// 图片最长边上限 (像素)，超出的 JPEG/PNG 会被等比缩小，0 表示不缩放
+    #[serde(default)]
+    pub max_image_dimension: u32,
+
+    /// [NEW] Gemini 流式响应在上游读取与客户端写出之间最多缓冲的分块数
+    #[serde(default = "default_stream_buffer_chunks")]
+    pub stream_buffer_chunks: usize,
+
+    /// [NEW] 缓冲区持续写满 (客户端读取过慢) 超过该时长 (毫秒) 时中断连接，0 表示不中断
+    #[serde(default = "default_stream_backpressure_timeout_ms")]
+    pub stream_backpressure_timeout_ms: u64,
 }
 
 impl Default for ExperimentalConfig {
@@ -300,10 +748,44 @@ impl Default for ExperimentalConfig {
             context_compression_threshold_l1: 0.4,
             context_compression_threshold_l2: 0.55,
             context_compression_threshold_l3: 0.7,
+            stream_ping_interval_secs: default_stream_ping_interval_secs(),
+            signature_cache_persistence: false,
+            signature_cache_ttl_secs: default_signature_cache_ttl_secs(),
+            enable_response_cache: false,
+            audio_cache_ttl_secs: default_audio_cache_ttl_secs(),
+            strict_function_schema_validation: false,
+            max_image_bytes: default_max_image_bytes(),
+            max_image_dimension: 0,
+            stream_buffer_chunks: default_stream_buffer_chunks(),
+            stream_backpressure_timeout_ms: default_stream_backpressure_timeout_ms(),
         }
     }
 }
 
+fn default_stream_buffer_chunks() -> usize {
+    64
+}
+
+fn default_stream_backpressure_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_stream_ping_interval_secs() -> u64 {
+    15
+}
+
+fn default_signature_cache_ttl_secs() -> u64 {
+    2 * 60 * 60
+}
+
+fn default_audio_cache_ttl_secs() -> u64 {
+    60 * 60
+}
+
+fn default_max_image_bytes() -> usize {
+    4 * 1024 * 1024
+}
+
 fn default_threshold_l1() -> f32 {
     0.4
 }
@@ -316,7 +798,7 @@ fn default_threshold_l3() -> f32 {
 
 /// Thinking Budget 模式
 /// 控制如何处理调用方传入的 thinking_budget 参数
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum ThinkingBudgetMode {
     /// 自动限制：对特定模型（Flash/Thinking）应用 24576 上限
@@ -334,7 +816,7 @@ impl Default for ThinkingBudgetMode {
 }
 
 /// Thinking Budget 配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ThinkingBudgetConfig {
     /// 模式选择
     #[serde(default)]
@@ -342,6 +824,9 @@ pub struct ThinkingBudgetConfig {
     /// 自定义固定值（仅在 mode=Custom 时生效）
     #[serde(default = "default_thinking_budget_custom_value")]
     pub custom_value: u32,
+    /// [NEW] 按模型覆盖 (key 为模型名或模型名前缀，如 `gemini-2.5-flash`)
+    #[serde(default)]
+    pub per_model_overrides: HashMap<String, ThinkingBudgetOverride>,
 }
 
 impl Default for ThinkingBudgetConfig {
@@ -349,14 +834,74 @@ impl Default for ThinkingBudgetConfig {
         Self {
             mode: ThinkingBudgetMode::Auto,
             custom_value: default_thinking_budget_custom_value(),
+            per_model_overrides: HashMap::new(),
         }
     }
 }
 
+/// 单个模型的 Thinking Budget 覆盖配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct ThinkingBudgetOverride {
+    #[serde(default)]
+    pub mode: ThinkingBudgetMode,
+    #[serde(default = "default_thinking_budget_custom_value")]
+    pub custom_value: u32,
+}
+
+impl ThinkingBudgetConfig {
+    /// 查找模型的覆盖配置: 精确匹配优先，其次最长前缀匹配 (均忽略大小写)
+    pub fn override_for(&self, model: &str) -> Option<&ThinkingBudgetOverride> {
+        let model = model.to_lowercase();
+        let mut best: Option<(usize, &ThinkingBudgetOverride)> = None;
+        for (key, ov) in &self.per_model_overrides {
+            let key = key.trim().to_lowercase();
+            if key.is_empty() {
+                continue;
+            }
+            if key == model {
+                return Some(ov);
+            }
+            if model.starts_with(&key) && best.map_or(true, |(len, _)| key.len() > len) {
+                best = Some((key.len(), ov));
+            }
+        }
+        best.map(|(_, ov)| ov)
+    }
+
+    /// [FIX] 各协议共用的 thinking budget 计算
+    /// 先按全局模式处理 (`is_limited` 的 Gemini 类模型受 24576 上限约束)，
+    /// 再应用按模型覆盖: 显式配置的值在上限之后生效，不受其约束
+    pub fn resolve_budget(&self, model: &str, requested: u64, is_limited: bool) -> u64 {
+        let capped = |value: u64| if is_limited { value.min(THINKING_BUDGET_CAP) } else { value };
+        match self.override_for(model) {
+            Some(ov) => match ov.mode {
+                ThinkingBudgetMode::Passthrough => requested,
+                ThinkingBudgetMode::Custom => ov.custom_value as u64,
+                ThinkingBudgetMode::Auto => capped(requested),
+            },
+            None => match self.mode {
+                ThinkingBudgetMode::Passthrough => requested,
+                ThinkingBudgetMode::Custom => capped(self.custom_value as u64),
+                ThinkingBudgetMode::Auto => capped(requested),
+            },
+        }
+    }
+}
+
+/// Gemini 类模型的 thinking budget 上限
+pub const THINKING_BUDGET_CAP: u64 = 24576;
+
 fn default_thinking_budget_custom_value() -> u32 {
     24576
 }
 
+fn default_claude_thinking() -> ThinkingBudgetConfig {
+    ThinkingBudgetConfig {
+        mode: ThinkingBudgetMode::Passthrough,
+        ..Default::default()
+    }
+}
+
 fn default_true() -> bool {
     true
 }
@@ -365,12 +910,19 @@ fn default_false() -> bool {
     false
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DebugLoggingConfig {
     #[serde(default)]
     pub enabled: bool,
     #[serde(default)]
     pub output_dir: Option<String>,
+    /// 单个抓包文件 (`{request_id}.response.sse`) 的大小上限 (字节)，超出后截断
+    #[serde(default = "default_max_capture_bytes")]
+    pub max_capture_bytes: u64,
+    /// [NEW] 额外脱敏的字段: 字段名 (不区分大小写，递归匹配) 或以 `/` 开头的 JSON Pointer，
+    /// 值在落盘前替换为 `[REDACTED]`；Authorization 等鉴权字段始终脱敏，无需配置
+    #[serde(default)]
+    pub redact_keys: Vec<String>,
 }
 
 impl Default for DebugLoggingConfig {
@@ -378,12 +930,18 @@ impl Default for DebugLoggingConfig {
         Self {
             enabled: false,
             output_dir: None,
+            max_capture_bytes: default_max_capture_bytes(),
+            redact_keys: Vec::new(),
         }
     }
 }
 
+fn default_max_capture_bytes() -> u64 {
+    10 * 1024 * 1024 // 10MB
+}
+
 /// IP 黑名单配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct IpBlacklistConfig {
     /// 是否启用黑名单
     #[serde(default)]
@@ -407,8 +965,108 @@ fn default_block_message() -> String {
     "Access denied".to_string()
 }
 
+/// 每日 Token 用量预算 (基于 token_stats 统计)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct BudgetConfig {
+    /// 全局每日 Token 上限，超出后反代拒绝新请求 (429，附带午夜重置时间)
+    #[serde(default)]
+    pub daily_token_budget: Option<u64>,
+
+    /// 单账号每日 Token 上限，超出后该账号退出轮换 (其余账号继续服务)
+    #[serde(default)]
+    pub per_account_daily_budget: Option<u64>,
+}
+
+/// 请求/响应 JSON 改写规则 (按声明顺序依次执行)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct TransformsConfig {
+    /// 在协议转换之前作用于客户端请求体
+    #[serde(default)]
+    pub request: Vec<TransformRule>,
+
+    /// 作用于返回给客户端的非流式 JSON 响应体
+    #[serde(default)]
+    pub response: Vec<TransformRule>,
+}
+
+/// 单条改写规则: 匹配条件均为可选，未设置视为匹配全部
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct TransformRule {
+    /// 规则名称 (仅用于日志与校验错误提示)
+    #[serde(default)]
+    pub name: Option<String>,
+
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// 请求路径，支持 `*` 通配 (如 `/v1/chat/*`)
+    #[serde(default)]
+    pub route: Option<String>,
+
+    /// 客户端请求的模型名，支持 `*` 通配 (如 `gemini-*`)
+    #[serde(default)]
+    pub model: Option<String>,
+
+    #[serde(default)]
+    pub actions: Vec<TransformAction>,
+}
+
+/// 改写动作，字段路径使用 JSON Pointer (RFC 6901，如 `/generationConfig/temperature`)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransformAction {
+    /// 设置字段 (中间对象不存在时自动创建)
+    SetField { path: String, value: serde_json::Value },
+    /// 删除字段
+    DeleteField { path: String },
+    /// 将数值限制在 [min, max] 区间内 (字段不存在或不是数值时跳过)
+    ClampNumber {
+        path: String,
+        #[serde(default)]
+        min: Option<f64>,
+        #[serde(default)]
+        max: Option<f64>,
+    },
+    /// 移动字段到新路径 (原字段不存在时跳过)
+    RenameField { from: String, to: String },
+}
+
+/// 按客户端 IP 的令牌桶限流配置 (白名单 IP 不受限)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IpRateLimitConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// 每分钟回填的请求数
+    #[serde(default = "default_ip_rate_limit_rpm")]
+    pub rpm: u32,
+
+    /// 桶容量 (允许的瞬时突发请求数)
+    #[serde(default = "default_ip_rate_limit_burst")]
+    pub burst: u32,
+}
+
+impl Default for IpRateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rpm: default_ip_rate_limit_rpm(),
+            burst: default_ip_rate_limit_burst(),
+        }
+    }
+}
+
+fn default_ip_rate_limit_rpm() -> u32 {
+    60
+}
+
+fn default_ip_rate_limit_burst() -> u32 {
+    20
+}
+
 /// IP 白名单配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct IpWhitelistConfig {
     /// 是否启用白名单模式 (启用后只允许白名单IP访问)
     #[serde(default)]
@@ -429,7 +1087,7 @@ impl Default for IpWhitelistConfig {
 }
 
 /// 安全监控配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SecurityMonitorConfig {
     /// IP 黑名单配置
     #[serde(default)]
@@ -438,6 +1096,25 @@ pub struct SecurityMonitorConfig {
     /// IP 白名单配置
     #[serde(default)]
     pub whitelist: IpWhitelistConfig,
+
+    /// 配置文件中的封禁网段 (CIDR，如 `10.0.0.0/8`，也可写单个 IP)
+    /// 与数据库黑名单叠加生效，受 blacklist.enabled 控制
+    #[serde(default)]
+    pub blocked_cidrs: Vec<String>,
+
+    /// 配置文件中的放行网段 (CIDR)，与数据库白名单叠加生效
+    #[serde(default)]
+    pub allowed_cidrs: Vec<String>,
+
+    /// 受信任的反向代理 (CIDR 或单个 IP)
+    /// 仅当 TCP 对端命中该列表时，才从 `CF-Connecting-IP` / `X-Forwarded-For` 提取真实客户端 IP；
+    /// 为空时保持旧行为
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+
+    /// 按客户端 IP 的令牌桶限流
+    #[serde(default)]
+    pub rate_limit: IpRateLimitConfig,
 }
 
 impl Default for SecurityMonitorConfig {
@@ -445,12 +1122,16 @@ impl Default for SecurityMonitorConfig {
         Self {
             blacklist: IpBlacklistConfig::default(),
             whitelist: IpWhitelistConfig::default(),
+            blocked_cidrs: Vec::new(),
+            allowed_cidrs: Vec::new(),
+            trusted_proxies: Vec::new(),
+            rate_limit: IpRateLimitConfig::default(),
         }
     }
 }
 
 /// 反代服务配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ProxyConfig {
     /// 是否启用反代服务
     pub enabled: bool,
@@ -461,6 +1142,11 @@ pub struct ProxyConfig {
     #[serde(default)]
     pub allow_lan_access: bool,
 
+    /// [NEW] 监听地址列表，非空时取代 allow_lan_access
+    /// 支持 `127.0.0.1`、`100.64.0.1:8046` (未写端口时使用 port) 及 unix 平台的 `unix:/path/to.sock`
+    #[serde(default)]
+    pub bind_addresses: Vec<String>,
+
     /// Authorization policy for the proxy.
     /// - off: no auth required
     /// - strict: auth required for all routes
@@ -475,20 +1161,82 @@ pub struct ProxyConfig {
     /// API 密钥
     pub api_key: String,
 
+    /// [NEW] 轮换前的旧 API 密钥 (宽限期内仍然有效)
+    #[serde(default)]
+    pub api_key_previous: Option<String>,
+
+    /// [NEW] 旧 API 密钥的失效时间 (unix 秒)
+    #[serde(default)]
+    pub api_key_previous_expires_at: Option<i64>,
+
+    /// [NEW] 附加的带标签 API 密钥，与 api_key 同时生效 (不可用于管理接口)
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyEntry>,
+
+    /// [NEW] 账号连续硬失败 (403 权限错误) 达到该次数时自动禁用反代，0 表示关闭
+    #[serde(default = "default_auto_disable_failure_threshold")]
+    pub auto_disable_failure_threshold: u32,
+
+    /// [NEW] 轮换 API 密钥后旧密钥的宽限期 (秒)，0 表示立即失效
+    #[serde(default = "default_rotation_grace_seconds")]
+    pub rotation_grace_seconds: u64,
+
+    /// [NEW] 优雅停机时等待在途请求完成的最长时间 (秒)，超时后强制关闭剩余连接
+    #[serde(default = "default_shutdown_drain_seconds")]
+    pub shutdown_drain_seconds: u64,
+
     /// Web UI 管理后台密码 (可选，如未设置则使用 api_key)
     pub admin_password: Option<String>,
 
+    /// [NEW] POST /api/auth/token 签发的管理 JWT 有效期 (秒)
+    #[serde(default = "default_jwt_expiry_seconds")]
+    pub jwt_expiry_seconds: u64,
+
+    /// [NEW] 管理后台多用户会话令牌的 HMAC 签名密钥 (创建首个管理用户时自动生成)
+    #[serde(default)]
+    pub admin_session_secret: String,
+
     /// 是否自动启动
     pub auto_start: bool,
 
-    /// 自定义精确模型映射表 (key: 原始模型名, value: 目标模型名)
+    /// 自定义模型映射表 (key: 原始模型名, value: 目标模型名)
+    /// key 支持精确名称、`*` 通配符 (如 `claude-3-5-sonnet-*`) 以及 `regex:` 前缀的正则表达式
+    /// 优先级: 精确 > 通配符 (最具体者优先) > 正则 > 系统默认映射
     #[serde(default)]
     pub custom_mapping: std::collections::HashMap<String, String>,
 
+    /// [NEW] 模型 → 账号分组标签要求，key 语法同 `custom_mapping`，按路由后的目标模型匹配
+    /// 命中时仅在携带全部所需标签的账号中轮换；未命中则使用完整账号池
+    #[serde(default)]
+    pub model_account_tags: std::collections::HashMap<String, Vec<String>>,
+
+    /// [NEW] 模型 → 上游地址路由 (按声明顺序，首个匹配生效)
+    /// 与 `custom_mapping` 不同: 不改写模型名，只改变请求发往的上游主机
+    #[serde(default)]
+    pub model_routes: Vec<ModelRoute>,
+
+    /// [NEW] 模型列表 (/v1/models, /v1beta/models) 缓存时间 (秒)，0 表示不缓存
+    #[serde(default = "default_model_list_cache_ttl_secs")]
+    pub model_list_cache_ttl_secs: u64,
+
+    /// [NEW] countTokens 等幂等接口的响应缓存时间 (秒)，0 表示不缓存
+    #[serde(default = "default_response_cache_ttl_secs")]
+    pub response_cache_ttl_secs: u64,
+
     /// API 请求超时时间(秒)
     #[serde(default = "default_request_timeout")]
     pub request_timeout: u64,
 
+    /// 按模型覆盖的请求超时 (秒)，key 为模型名模式 (大小写不敏感)
+    /// 查找顺序: 精确匹配 → 前缀匹配 (最长者优先，`gemini-2.5-pro` 与 `gemini-2.5-pro*` 等价)
+    ///          → `timeouts.routes` 路由类别 → 全局 `request_timeout`
+    #[serde(default)]
+    pub model_timeouts: std::collections::HashMap<String, u64>,
+
+    /// 按路由类别覆盖的超时 (未命中时回退到 request_timeout)
+    #[serde(default)]
+    pub timeouts: RequestTimeoutsConfig,
+
     /// 是否开启请求日志记录 (监控)
     #[serde(default)]
     pub enable_logging: bool,
@@ -509,6 +1257,10 @@ pub struct ProxyConfig {
     #[serde(default)]
     pub user_agent_override: Option<String>,
 
+    /// [NEW] User-Agent 轮换池: 非空时每个请求轮流使用其中一个，优先于 user_agent_override
+    #[serde(default)]
+    pub user_agent_pool: Vec<String>,
+
     /// 账号调度配置 (粘性会话/限流重试)
     #[serde(default)]
     pub scheduling: crate::proxy::sticky_config::StickySessionConfig,
@@ -536,6 +1288,10 @@ pub struct ProxyConfig {
     #[serde(default)]
     pub thinking_budget: ThinkingBudgetConfig,
 
+    /// [NEW] Claude 协议 thinking.budget_tokens 配置 (默认透传，保持旧行为)
+    #[serde(default = "default_claude_thinking")]
+    pub claude_thinking: ThinkingBudgetConfig,
+
     /// 全局系统提示词配置
     /// 自动注入到所有 API 请求的 systemInstruction 中
     #[serde(default)]
@@ -550,15 +1306,346 @@ pub struct ProxyConfig {
     /// 代理池配置
     #[serde(default)]
     pub proxy_pool: ProxyPoolConfig,
+
+    /// 注入到所有反代响应中的自定义响应头 (不影响管理 API)
+    /// 值支持模板变量: `{{account_id}}`, `{{model}}`, `{{request_id}}`
+    #[serde(default)]
+    pub response_headers: std::collections::HashMap<String, String>,
+
+    /// [NEW] 常见 AI 错误 (429 / 404 / 上下文超长) 的自定义响应模板
+    #[serde(default)]
+    pub error_templates: ErrorTemplateConfig,
+
+    /// [NEW] 访问日志格式: json (主日志) / combined (access.log) / none
+    #[serde(default)]
+    pub access_log_format: AccessLogFormat,
+
+    /// [NEW] Gemini 原生请求未携带 safetySettings 时注入的默认设置
+    #[serde(default)]
+    pub default_safety_settings: Option<Vec<SafetySetting>>,
+
+    /// [NEW] 为 true 时用 default_safety_settings 替换客户端传入的 safetySettings
+    #[serde(default)]
+    pub safety_settings_override: bool,
+
+    /// 账号级并发限制与排队配置
+    #[serde(default)]
+    pub concurrency: AccountConcurrencyConfig,
+
+    /// 账号事件 Webhook 通知
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+
+    /// 跨域 (CORS) 策略
+    #[serde(default)]
+    pub cors: CorsConfig,
+
+    /// 闲置账号自动停用阈值 (天)，超过该天数未使用的账号将被禁止参与反代
+    /// None 表示不启用；每天扫描一次，也可通过 `POST /api/accounts/idle-check` 立即触发
+    #[serde(default)]
+    pub idle_disable_after_days: Option<u64>,
+
+    /// 多实例协调模式
+    /// - standalone: 会话绑定 / 固定账号 / 熔断状态仅保存在本实例内存中 (默认)
+    /// - shared_database: 通过共享数据目录下的 SQLite (WAL) 在多个实例间同步上述状态
+    #[serde(default)]
+    pub coordination_mode: CoordinationMode,
+
+    /// 每日 Token 用量预算 (全局 / 单账号)，按本地自然日统计，午夜重置
+    #[serde(default)]
+    pub budget: BudgetConfig,
+
+    /// [NEW] 请求/响应 JSON 改写规则 (保存配置时校验路径，支持热更新)
+    #[serde(default)]
+    pub transforms: TransformsConfig,
+
+    /// [NEW] 系统提示词预算 (仅在 experimental.enable_usage_scaling 启用时生效):
+    /// 系统指令估算 Token 超出该值时，注入的 Antigravity 身份替换为短版本
+    #[serde(default = "default_system_prompt_max_tokens")]
+    pub system_prompt_max_tokens: usize,
+
+    /// [NEW] 短版本 Antigravity 身份 (未设置时使用内置默认值)
+    #[serde(default)]
+    pub identity_short: Option<String>,
+
+    /// [NEW] Antigravity 身份注入方式 (always / only_when_missing / never / custom)
+    #[serde(default)]
+    pub identity_injection: IdentityInjectionConfig,
+
+    /// 定时预热 (每隔 N 小时或每天固定时间自动预热账号)
+    #[serde(default)]
+    pub warmup_schedule: WarmupScheduleConfig,
+
+    /// [NEW] /v1/embeddings 单次上游请求的最大输入条数，超出时拆分为多个并行请求
+    #[serde(default = "default_embedding_batch_size")]
+    pub embedding_batch_size: usize,
+
+    /// [NEW] 监听配置文件变更并自动热更新 (无需调用管理接口)，修改后需重启服务生效
+    #[serde(default)]
+    pub config_file_watch: bool,
+}
+
+/// 定时预热配置
+/// `daily_time` 与 `interval_hours` 同时设置时以 `daily_time` 为准
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct WarmupScheduleConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 每隔 N 小时预热一次
+    #[serde(default)]
+    pub interval_hours: Option<u64>,
+    /// 每天的预热时间 (本地时间，`HH:MM`)
+    #[serde(default)]
+    pub daily_time: Option<String>,
+    /// 参与定时预热的账号 ID (为空表示全部账号)
+    #[serde(default)]
+    pub account_ids: Vec<String>,
+    /// 同时预热的最大账号数
+    #[serde(default = "default_warmup_max_concurrency")]
+    pub max_concurrency: usize,
+}
+
+impl Default for WarmupScheduleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_hours: None,
+            daily_time: None,
+            account_ids: Vec::new(),
+            max_concurrency: default_warmup_max_concurrency(),
+        }
+    }
+}
+
+fn default_warmup_max_concurrency() -> usize {
+    2
+}
+
+impl WarmupScheduleConfig {
+    /// 解析 `daily_time` 为 (时, 分)
+    pub fn daily_time_hm(&self) -> Option<(u32, u32)> {
+        parse_hh_mm(self.daily_time.as_deref()?.trim())
+    }
+
+    pub fn includes_account(&self, account_id: &str) -> bool {
+        self.account_ids.is_empty() || self.account_ids.iter().any(|id| id == account_id)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(time) = self.daily_time.as_deref().filter(|t| !t.trim().is_empty()) {
+            if parse_hh_mm(time.trim()).is_none() {
+                return Err(format!(
+                    "warmup_schedule.daily_time {:?} is invalid, expected HH:MM",
+                    time
+                ));
+            }
+        }
+        if self.interval_hours == Some(0) {
+            return Err("warmup_schedule.interval_hours must be greater than 0".to_string());
+        }
+        if self.max_concurrency == 0 {
+            return Err("warmup_schedule.max_concurrency must be greater than 0".to_string());
+        }
+        if self.enabled && self.interval_hours.is_none() && self.daily_time_hm().is_none() {
+            return Err(
+                "warmup_schedule requires interval_hours or daily_time when enabled".to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+fn parse_hh_mm(value: &str) -> Option<(u32, u32)> {
+    let (h, m) = value.split_once(':')?;
+    if h.is_empty() || h.len() > 2 || m.len() != 2 {
+        return None;
+    }
+    let (h, m) = (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?);
+    (h < 24 && m < 60).then_some((h, m))
+}
+
+/// 多实例协调模式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CoordinationMode {
+    #[default]
+    Standalone,
+    SharedDatabase,
+}
+
+/// Webhook 订阅的账号事件类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    AccountDisabled,
+    QuotaExceeded,
+    CircuitBreakerOpen,
+    AccountAdded,
+}
+
+/// 单个 Webhook 目标配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// 订阅的事件 (为空表示订阅全部)
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+    /// HMAC-SHA256 签名密钥，签名放在 `X-ABV-Signature: sha256=<hex>` 头中
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// 投递失败后的重试次数 (指数退避)
+    #[serde(default = "default_webhook_retry_count")]
+    pub retry_count: u8,
+}
+
+fn default_webhook_retry_count() -> u8 {
+    3
+}
+
+/// 账号级并发限制配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AccountConcurrencyConfig {
+    /// 单账号最大在途请求数 (0 表示不限制)
+    #[serde(default = "default_max_per_account")]
+    pub max_per_account: usize,
+
+    /// 获取并发名额的最长排队时间 (秒)，超时返回 429
+    #[serde(default = "default_queue_timeout_secs")]
+    pub queue_timeout_secs: u64,
+}
+
+impl Default for AccountConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            max_per_account: default_max_per_account(),
+            queue_timeout_secs: default_queue_timeout_secs(),
+        }
+    }
+}
+
+fn default_max_per_account() -> usize {
+    8
+}
+
+fn default_queue_timeout_secs() -> u64 {
+    30
+}
+
+/// 超时覆盖的路由类别
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutRouteClass {
+    /// 对话生成 (Claude Messages / OpenAI Chat & Completions / Gemini generateContent)
+    Chat,
+    /// 图像生成与编辑
+    Images,
+    /// 音频转录
+    Audio,
+    /// Token 计数
+    CountTokens,
+}
+
+/// 请求超时覆盖配置
+/// 非流式请求按总耗时计算；流式请求按空闲时间 (N 秒内无上游数据) 计算
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq)]
+pub struct RequestTimeoutsConfig {
+    /// 按路由类别设置超时 (秒)，优先级低于 `model_timeouts`
+    #[serde(default)]
+    pub routes: std::collections::HashMap<TimeoutRouteClass, u64>,
+}
+
+/// 跨域 (CORS) 策略配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct CorsConfig {
+    /// 允许的来源列表
+    /// - `*`: 允许任意来源 (此时不会下发 Allow-Credentials)
+    /// - `https://app.example.com`: 精确匹配完整 Origin
+    /// - `app.example.com`: 匹配该主机名 (任意协议/端口)
+    /// - `*.example.com`: 匹配子域名
+    #[serde(default = "default_cors_allowed_origins")]
+    pub allowed_origins: Vec<String>,
+
+    /// 是否允许携带凭据 (Cookie / Authorization)，仅对管理 API (`/api/*`) 生效
+    #[serde(default)]
+    pub allow_credentials: bool,
+
+    /// 预检结果缓存时间 (秒)
+    #[serde(default = "default_cors_max_age_seconds")]
+    pub max_age_seconds: u32,
+
+    /// [NEW] 按路径前缀限制允许的方法 (如 `"/v1": ["GET", "POST"]`)，最长前缀优先；
+    /// 未命中时允许全部常用方法
+    #[serde(default)]
+    pub path_methods: std::collections::HashMap<String, Vec<String>>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: default_cors_allowed_origins(),
+            allow_credentials: false,
+            max_age_seconds: default_cors_max_age_seconds(),
+            path_methods: std::collections::HashMap::new(),
+        }
+    }
+}
+
+fn default_cors_allowed_origins() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+fn default_cors_max_age_seconds() -> u32 {
+    3600
 }
 
 /// 上游代理配置
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct UpstreamProxyConfig {
     /// 是否启用
     pub enabled: bool,
     /// 代理地址 (http://, https://, socks5://)
     pub url: String,
+    /// [NEW] 每个上游主机保留的最大空闲连接数
+    #[serde(default = "default_pool_max_idle_per_host")]
+    pub pool_max_idle_per_host: usize,
+    /// [NEW] 空闲连接保留时间 (秒)
+    #[serde(default = "default_pool_idle_timeout_seconds")]
+    pub pool_idle_timeout_seconds: u64,
+    /// [NEW] 以 trace 级别输出连接读写详情 (排查连接问题用)
+    #[serde(default)]
+    pub connection_verbose: bool,
+    /// [NEW] 允许通过 ALPN 协商 HTTP/2 (单连接多路复用)；关闭时仅使用 HTTP/1.1
+    #[serde(default = "default_true")]
+    pub http2_enabled: bool,
+    /// [NEW] TCP 保活探测间隔 (秒)，同时作为 HTTP/2 PING 间隔；0 表示关闭
+    #[serde(default = "default_tcp_keepalive_seconds")]
+    pub tcp_keepalive_seconds: u64,
+}
+
+impl Default for UpstreamProxyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: String::new(),
+            pool_max_idle_per_host: default_pool_max_idle_per_host(),
+            pool_idle_timeout_seconds: default_pool_idle_timeout_seconds(),
+            connection_verbose: false,
+            http2_enabled: true,
+            tcp_keepalive_seconds: default_tcp_keepalive_seconds(),
+        }
+    }
+}
+
+fn default_pool_max_idle_per_host() -> usize {
+    20
+}
+
+fn default_pool_idle_timeout_seconds() -> u64 {
+    90
+}
+
+fn default_tcp_keepalive_seconds() -> u64 {
+    60
 }
 
 impl Default for ProxyConfig {
@@ -566,13 +1653,28 @@ impl Default for ProxyConfig {
         Self {
             enabled: false,
             allow_lan_access: false, // 默认仅本机访问，隐私优先
+            bind_addresses: Vec::new(),
             auth_mode: ProxyAuthMode::default(),
             port: 8045,
             api_key: format!("sk-{}", uuid::Uuid::new_v4().simple()),
+            api_key_previous: None,
+            api_key_previous_expires_at: None,
+            api_keys: Vec::new(),
+            rotation_grace_seconds: default_rotation_grace_seconds(),
+            auto_disable_failure_threshold: default_auto_disable_failure_threshold(),
+            shutdown_drain_seconds: default_shutdown_drain_seconds(),
             admin_password: None,
+            jwt_expiry_seconds: default_jwt_expiry_seconds(),
+            admin_session_secret: String::new(),
             auto_start: false,
             custom_mapping: std::collections::HashMap::new(),
+            model_account_tags: std::collections::HashMap::new(),
+            model_routes: Vec::new(),
+            model_list_cache_ttl_secs: default_model_list_cache_ttl_secs(),
+            response_cache_ttl_secs: default_response_cache_ttl_secs(),
             request_timeout: default_request_timeout(),
+            model_timeouts: std::collections::HashMap::new(),
+            timeouts: RequestTimeoutsConfig::default(),
             enable_logging: true, // 默认开启，支持 token 统计功能
             debug_logging: DebugLoggingConfig::default(),
             upstream_proxy: UpstreamProxyConfig::default(),
@@ -582,15 +1684,67 @@ impl Default for ProxyConfig {
             security_monitor: SecurityMonitorConfig::default(),
             preferred_account_id: None, // 默认使用轮询模式
             user_agent_override: None,
+            user_agent_pool: Vec::new(),
             saved_user_agent: None,
             thinking_budget: ThinkingBudgetConfig::default(),
+            claude_thinking: default_claude_thinking(),
             global_system_prompt: GlobalSystemPromptConfig::default(),
             proxy_pool: ProxyPoolConfig::default(),
             image_thinking_mode: None,
+            response_headers: std::collections::HashMap::new(),
+            error_templates: ErrorTemplateConfig::default(),
+            access_log_format: AccessLogFormat::default(),
+            default_safety_settings: None,
+            safety_settings_override: false,
+            concurrency: AccountConcurrencyConfig::default(),
+            webhooks: Vec::new(),
+            cors: CorsConfig::default(),
+            idle_disable_after_days: None,
+            coordination_mode: CoordinationMode::default(),
+            budget: BudgetConfig::default(),
+            transforms: TransformsConfig::default(),
+            system_prompt_max_tokens: default_system_prompt_max_tokens(),
+            identity_short: None,
+            identity_injection: IdentityInjectionConfig::default(),
+            warmup_schedule: WarmupScheduleConfig::default(),
+            embedding_batch_size: default_embedding_batch_size(),
+            config_file_watch: false,
         }
     }
 }
 
+fn default_jwt_expiry_seconds() -> u64 {
+    3600
+}
+
+fn default_rotation_grace_seconds() -> u64 {
+    3600
+}
+
+fn default_shutdown_drain_seconds() -> u64 {
+    30
+}
+
+fn default_auto_disable_failure_threshold() -> u32 {
+    crate::proxy::token_manager::DEFAULT_AUTO_DISABLE_FAILURE_THRESHOLD
+}
+
+fn default_embedding_batch_size() -> usize {
+    crate::proxy::mappers::openai::embeddings::DEFAULT_EMBEDDING_BATCH_SIZE
+}
+
+fn default_system_prompt_max_tokens() -> usize {
+    500
+}
+
+fn default_model_list_cache_ttl_secs() -> u64 {
+    crate::proxy::model_list_cache::DEFAULT_MODEL_LIST_CACHE_TTL_SECS
+}
+
+fn default_response_cache_ttl_secs() -> u64 {
+    crate::proxy::response_cache::DEFAULT_RESPONSE_CACHE_TTL_SECS
+}
+
 fn default_request_timeout() -> u64 {
     120 // 默认 120 秒,原来 60 秒太短
 }
@@ -612,7 +1766,7 @@ fn default_zai_haiku_model() -> String {
 }
 
 impl ProxyConfig {
-    /// 获取实际的监听地址
+    /// 获取实际的监听地址 (bind_addresses 为空时使用)
     /// - allow_lan_access = false: 返回 "127.0.0.1"（默认，隐私优先）
     /// - allow_lan_access = true: 返回 "0.0.0.0"（允许局域网访问）
     pub fn get_bind_address(&self) -> &str {
@@ -625,7 +1779,7 @@ impl ProxyConfig {
 }
 
 /// 代理认证信息
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ProxyAuth {
     pub username: String,
     #[serde(
@@ -636,7 +1790,7 @@ pub struct ProxyAuth {
 }
 
 /// 单个代理配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ProxyEntry {
     pub id: String,                       // 唯一标识
     pub name: String,                     // 显示名称
@@ -653,7 +1807,7 @@ pub struct ProxyEntry {
 }
 
 /// 代理池配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ProxyPoolConfig {
     pub enabled: bool, // 是否启用代理池
     // pub mode: ProxyPoolMode,        // [REMOVED] 代理池模式，统一为 Hybrid 逻辑
@@ -681,7 +1835,7 @@ impl Default for ProxyPoolConfig {
 }
 
 /// 代理选择策略
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum ProxySelectionStrategy {
     /// 轮询: 依次使用
@@ -716,4 +1870,178 @@ mod tests {
         assert_eq!(normalize_proxy_url(""), "");
         assert_eq!(normalize_proxy_url("   "), "");
     }
+
+    #[test]
+    fn test_thinking_budget_override_precedence() {
+        let ov = |mode: ThinkingBudgetMode, custom_value: u32| ThinkingBudgetOverride { mode, custom_value };
+        let mut config = ThinkingBudgetConfig::default();
+        config.per_model_overrides.insert("gemini-2.5-flash".into(), ov(ThinkingBudgetMode::Custom, 8192));
+        config.per_model_overrides.insert("gemini-2.5".into(), ov(ThinkingBudgetMode::Custom, 32768));
+        config.per_model_overrides.insert("gemini-2.5-pro".into(), ov(ThinkingBudgetMode::Passthrough, 0));
+        config.per_model_overrides.insert("gemini-2.5-pro-exp".into(), ov(ThinkingBudgetMode::Custom, 1024));
+
+        // 精确匹配优先
+        assert_eq!(config.override_for("gemini-2.5-pro").unwrap().mode, ThinkingBudgetMode::Passthrough);
+        assert_eq!(config.override_for("GEMINI-2.5-FLASH").unwrap().custom_value, 8192);
+        // 前缀匹配取最长前缀
+        assert_eq!(config.override_for("gemini-2.5-flash-lite").unwrap().custom_value, 8192);
+        assert_eq!(config.override_for("gemini-2.5-pro-exp-0827").unwrap().custom_value, 1024);
+        assert_eq!(config.override_for("gemini-2.5-pro-preview").unwrap().mode, ThinkingBudgetMode::Passthrough);
+        assert_eq!(config.override_for("gemini-2.5-ultra").unwrap().custom_value, 32768);
+        // 未命中时回退全局配置
+        assert!(config.override_for("gemini-3-pro").is_none());
+    }
+
+    #[test]
+    fn test_thinking_budget_resolve_applies_overrides_after_cap() {
+        let mut config = ThinkingBudgetConfig {
+            mode: ThinkingBudgetMode::Custom,
+            custom_value: 32000,
+            ..Default::default()
+        };
+        config.per_model_overrides.insert(
+            "gemini-2.5-pro".into(),
+            ThinkingBudgetOverride { mode: ThinkingBudgetMode::Custom, custom_value: 32768 },
+        );
+        config.per_model_overrides.insert(
+            "claude-opus".into(),
+            ThinkingBudgetOverride { mode: ThinkingBudgetMode::Passthrough, custom_value: 0 },
+        );
+
+        // 全局 Custom 受上限约束
+        assert_eq!(config.resolve_budget("gemini-3-pro", 8000, true), 24576);
+        assert_eq!(config.resolve_budget("gpt-4o", 8000, false), 32000);
+        // 按模型覆盖在上限之后生效
+        assert_eq!(config.resolve_budget("gemini-2.5-pro", 8000, true), 32768);
+        assert_eq!(config.resolve_budget("claude-opus-4-thinking", 40000, true), 40000);
+
+        config.mode = ThinkingBudgetMode::Auto;
+        assert_eq!(config.resolve_budget("gemini-3-pro", 40000, true), 24576);
+        assert_eq!(config.resolve_budget("gemini-3-pro", 4000, true), 4000);
+    }
+
+    #[test]
+    fn test_warmup_schedule_validation() {
+        let schedule = |daily: Option<&str>, interval: Option<u64>| WarmupScheduleConfig {
+            enabled: true,
+            interval_hours: interval,
+            daily_time: daily.map(str::to_string),
+            ..Default::default()
+        };
+        assert_eq!(schedule(Some("07:30"), None).daily_time_hm(), Some((7, 30)));
+        assert_eq!(schedule(Some("7:05"), None).daily_time_hm(), Some((7, 5)));
+        assert!(schedule(Some("07:30"), None).validate().is_ok());
+        assert!(schedule(None, Some(6)).validate().is_ok());
+
+        assert!(schedule(Some("24:00"), None).validate().is_err());
+        assert!(schedule(Some("07:5"), None).validate().is_err());
+        assert!(schedule(Some("0730"), None).validate().is_err());
+        assert!(schedule(None, Some(0)).validate().is_err());
+        assert!(schedule(None, None).validate().is_err());
+        assert!(WarmupScheduleConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_config_schema_lists_enum_values_and_defaults() {
+        let schema = serde_json::to_value(schemars::schema_for!(crate::models::AppConfig)).unwrap();
+        let definitions = &schema["definitions"];
+
+        // 带文档注释的枚举会生成 oneOf，逐个收集 enum 取值
+        fn enum_values(schema: &serde_json::Value) -> Vec<String> {
+            let mut values: Vec<String> = schema["enum"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                .collect();
+            for variant in schema["oneOf"].as_array().into_iter().flatten() {
+                values.extend(enum_values(variant));
+            }
+            values
+        }
+        assert_eq!(
+            enum_values(&definitions["ProxyAuthMode"]),
+            vec!["off", "strict", "all_except_health", "auto"]
+        );
+        assert_eq!(
+            enum_values(&definitions["ZaiDispatchMode"]),
+            vec!["off", "exclusive", "pooled", "fallback"]
+        );
+        assert_eq!(
+            enum_values(&definitions["ThinkingBudgetMode"]),
+            vec!["auto", "passthrough", "custom"]
+        );
+
+        let proxy = &definitions["ProxyConfig"]["properties"];
+        assert_eq!(proxy["auth_mode"]["default"], "auto");
+        // 没有 serde 默认值的字段为必填
+        let required = definitions["ProxyConfig"]["required"].as_array().unwrap();
+        assert!(required.contains(&serde_json::json!("port")));
+        assert!(!required.contains(&serde_json::json!("auth_mode")));
+        assert!(schema["properties"]["proxy"].is_object());
+    }
+
+    #[test]
+    fn test_model_routes_first_match_wins() {
+        let route = |pattern: &str, base_url: &str| ModelRoute {
+            pattern: pattern.to_string(),
+            base_url: base_url.to_string(),
+            auth_header: None,
+        };
+        let routes = vec![
+            route("claude-opus-*", "http://10.0.0.3:8045/v1internal/"),
+            route("claude-*", "http://10.0.0.2:8045/v1internal"),
+            route("*", "http://10.0.0.9:8045/v1internal"),
+        ];
+
+        let opus = find_model_route(&routes, "claude-opus-4-5-thinking").unwrap();
+        assert_eq!(opus.normalized_base_url(), "http://10.0.0.3:8045/v1internal");
+        assert_eq!(
+            find_model_route(&routes, "claude-sonnet-4-5").unwrap().base_url,
+            "http://10.0.0.2:8045/v1internal"
+        );
+        assert_eq!(
+            find_model_route(&routes, "gemini-2.5-pro").unwrap().base_url,
+            "http://10.0.0.9:8045/v1internal"
+        );
+        assert!(find_model_route(&routes[..2], "gemini-2.5-pro").is_none());
+
+        // 旧配置缺少该字段时为空表
+        let mut value = serde_json::to_value(ProxyConfig::default()).unwrap();
+        value.as_object_mut().unwrap().remove("model_routes");
+        let config: ProxyConfig = serde_json::from_value(value).unwrap();
+        assert!(config.model_routes.is_empty());
+    }
+
+    #[test]
+    fn test_rotation_grace_defaults_to_one_hour() {
+        assert_eq!(ProxyConfig::default().rotation_grace_seconds, 3600);
+
+        // 旧配置缺少该字段时同样为 1 小时
+        let mut value = serde_json::to_value(ProxyConfig::default()).unwrap();
+        value.as_object_mut().unwrap().remove("rotation_grace_seconds");
+        let config: ProxyConfig = serde_json::from_value(value).unwrap();
+        assert_eq!(config.rotation_grace_seconds, 3600);
+    }
+
+    #[test]
+    fn test_apply_global_proxy_config_updates_thinking_budget() {
+        // 重新加载的配置文件修改了 Thinking Budget；其余全局项沿用当前值，避免干扰并行测试
+        let mut config = ProxyConfig::default();
+        config.thinking_budget = ThinkingBudgetConfig {
+            mode: ThinkingBudgetMode::Custom,
+            custom_value: 4321,
+            ..Default::default()
+        };
+        config.image_thinking_mode = Some(get_image_thinking_mode());
+        config.response_headers = get_response_headers();
+        config.error_templates = get_error_templates();
+
+        apply_global_proxy_config(&config);
+        let applied = get_thinking_budget_config();
+        assert_eq!(applied.mode, ThinkingBudgetMode::Custom);
+        assert_eq!(applied.custom_value, 4321);
+
+        update_thinking_budget_config(ThinkingBudgetConfig::default());
+    }
 }
@@ -0,0 +1,275 @@
+// 配置预检 (dry-run)
+// 对候选配置执行保存与热更新所依赖的全部检查，按字段路径返回错误与警告；
+// 不写入磁盘，也不修改任何运行时状态
+use serde::Serialize;
+
+use crate::models::AppConfig;
+use crate::proxy::config::{normalize_proxy_url, ProxyAuthMode};
+use crate::proxy::listener::{resolve_bind_targets, BindTarget};
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ConfigIssue {
+    /// 字段路径，如 `proxy.zai.base_url`
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct ValidationReport {
+    /// 没有错误即可保存 (警告不阻止保存)
+    pub valid: bool,
+    pub errors: Vec<ConfigIssue>,
+    pub warnings: Vec<ConfigIssue>,
+}
+
+impl ValidationReport {
+    fn error(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.errors.push(ConfigIssue {
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+
+    fn warning(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.warnings.push(ConfigIssue {
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+}
+
+/// 校验候选配置。`bound_addresses` 为当前服务已占用的监听地址，这些地址不做端口占用检测
+pub fn validate_config(config: &AppConfig, bound_addresses: &[String]) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let proxy = &config.proxy;
+
+    // 1. 与保存接口相同的结构校验
+    if let Err(e) = crate::proxy::transforms::validate_transforms(&proxy.transforms) {
+        report.error("proxy.transforms", e);
+    }
+    if let Err(e) = proxy.warmup_schedule.validate() {
+        report.error("proxy.warmup_schedule", e);
+    }
+    if let Err(e) = crate::proxy::security::validate_monitor_cidrs(&proxy.security_monitor) {
+        report.error("proxy.security_monitor", e);
+    }
+
+    // 2. 监听地址与端口占用
+    if proxy.port == 0 {
+        report.error("proxy.port", "port must be between 1 and 65535");
+    } else {
+        match resolve_bind_targets(&proxy.bind_addresses, proxy.get_bind_address(), proxy.port) {
+            Ok(targets) => {
+                for target in targets {
+                    let addr = target.to_string();
+                    if bound_addresses.contains(&addr) {
+                        continue;
+                    }
+                    if let BindTarget::Tcp(tcp) = &target {
+                        // 绑定后立即释放，仅用于探测
+                        if let Err(e) = std::net::TcpListener::bind(tcp.as_str()) {
+                            let path = if proxy.bind_addresses.is_empty() {
+                                "proxy.port"
+                            } else {
+                                "proxy.bind_addresses"
+                            };
+                            report.error(path, format!("{} is not available: {}", addr, e));
+                        }
+                    }
+                }
+            }
+            Err(e) => report.error("proxy.bind_addresses", e),
+        }
+    }
+
+    // 3. 鉴权 (与安全建议一致: 开放局域网访问时必须启用鉴权)
+    if proxy.allow_lan_access && matches!(proxy.auth_mode, ProxyAuthMode::Off) {
+        report.warning(
+            "proxy.auth_mode",
+            "allow_lan_access is enabled but auth_mode is off; anyone on the network can use the proxy",
+        );
+    }
+    if !matches!(proxy.auth_mode, ProxyAuthMode::Off) && proxy.api_key.trim().is_empty() {
+        report.warning("proxy.api_key", "api_key is empty while authentication is enabled");
+    }
+    for problem in crate::proxy::api_keys::find_duplicates(proxy) {
+        report.error("proxy.api_keys", problem);
+    }
+
+    // 4. 上游代理与代理池地址
+    if proxy.upstream_proxy.enabled {
+        if proxy.upstream_proxy.url.trim().is_empty() {
+            report.error("proxy.upstream_proxy.url", "upstream proxy is enabled but url is empty");
+        } else if let Err(e) = reqwest::Proxy::all(normalize_proxy_url(&proxy.upstream_proxy.url)) {
+            report.error("proxy.upstream_proxy.url", format!("invalid proxy url: {}", e));
+        }
+    }
+    for (i, entry) in proxy.proxy_pool.proxies.iter().enumerate() {
+        if let Err(e) = reqwest::Proxy::all(normalize_proxy_url(&entry.url)) {
+            report.error(
+                format!("proxy.proxy_pool.proxies[{}].url", i),
+                format!("invalid proxy url: {}", e),
+            );
+        }
+    }
+    if proxy.proxy_pool.enabled && !proxy.proxy_pool.proxies.iter().any(|p| p.enabled) {
+        report.warning("proxy.proxy_pool", "proxy pool is enabled but has no enabled proxies");
+    }
+
+    // 5. z.ai
+    if proxy.zai.enabled {
+        match url::Url::parse(proxy.zai.base_url.trim()) {
+            Ok(u) if matches!(u.scheme(), "http" | "https") && u.host().is_some() => {}
+            Ok(_) => report.error("proxy.zai.base_url", "base_url must be an http(s) url"),
+            Err(e) => report.error("proxy.zai.base_url", format!("invalid url: {}", e)),
+        }
+        if proxy.zai.api_key.trim().is_empty() {
+            report.warning("proxy.zai.api_key", "z.ai is enabled but api_key is empty");
+        }
+    }
+
+    // 6. 模型映射
+    for key in crate::proxy::common::model_mapping::find_invalid_mapping_rules(&proxy.custom_mapping) {
+        report.error(format!("proxy.custom_mapping[{:?}]", key), "invalid regex rule");
+    }
+    let mut empty_targets: Vec<String> = proxy
+        .custom_mapping
+        .iter()
+        .filter(|(_, target)| target.trim().is_empty())
+        .map(|(key, _)| format!("proxy.custom_mapping[{:?}]", key))
+        .chain(
+            proxy
+                .zai
+                .model_mapping
+                .iter()
+                .filter(|(_, target)| target.trim().is_empty())
+                .map(|(key, _)| format!("proxy.zai.model_mapping[{:?}]", key)),
+        )
+        .collect();
+    empty_targets.sort();
+    for path in empty_targets {
+        report.error(path, "mapping target model is empty");
+    }
+
+    // 7. 模型 → 上游地址路由
+    for (i, route) in proxy.model_routes.iter().enumerate() {
+        let path = format!("proxy.model_routes[{}]", i);
+        if route.pattern.trim().is_empty() {
+            report.error(format!("{}.pattern", path), "pattern must not be empty");
+        }
+        match url::Url::parse(route.base_url.trim()) {
+            Ok(u) if matches!(u.scheme(), "http" | "https") && u.host().is_some() => {
+                if u.scheme() == "http" {
+                    report.warning(
+                        format!("{}.base_url", path),
+                        "plain http route: requests and auth_header are sent unencrypted",
+                    );
+                }
+                let has_auth = route.auth_header.as_deref().is_some_and(|v| !v.trim().is_empty());
+                if !has_auth && !route.is_google_endpoint() {
+                    report.warning(
+                        format!("{}.auth_header", path),
+                        "account tokens are only sent to googleapis.com; set auth_header if this upstream requires authentication",
+                    );
+                }
+            }
+            Ok(_) => report.error(format!("{}.base_url", path), "base_url must be an http(s) url"),
+            Err(e) => report.error(format!("{}.base_url", path), format!("invalid url: {}", e)),
+        }
+        if let Some(value) = route.auth_header.as_deref() {
+            if reqwest::header::HeaderValue::from_str(value.trim()).is_err() {
+                report.error(format!("{}.auth_header", path), "auth_header is not a valid header value");
+            }
+        }
+        let shadowed = proxy.model_routes[..i]
+            .iter()
+            .any(|earlier| earlier.pattern.trim() == "*" || earlier.pattern.trim() == route.pattern.trim());
+        if shadowed {
+            report.warning(path, "route is never used because an earlier route matches first");
+        }
+    }
+
+    report.valid = report.errors.is_empty();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_bound(config: &AppConfig) -> Vec<String> {
+        resolve_bind_targets(
+            &config.proxy.bind_addresses,
+            config.proxy.get_bind_address(),
+            config.proxy.port,
+        )
+        .unwrap()
+        .iter()
+        .map(|t| t.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn test_default_config_is_valid() {
+        let config = AppConfig::new();
+        let report = validate_config(&config, &default_bound(&config));
+        assert!(report.valid, "{:?}", report.errors);
+    }
+
+    #[test]
+    fn test_reports_errors_and_warnings_with_paths() {
+        let mut config = AppConfig::new();
+        config.proxy.allow_lan_access = true;
+        config.proxy.auth_mode = ProxyAuthMode::Off;
+        config.proxy.zai.enabled = true;
+        config.proxy.zai.base_url = "not a url".to_string();
+        config.proxy.zai.api_key = "key".to_string();
+        config.proxy.upstream_proxy.enabled = true;
+        config.proxy.upstream_proxy.url = String::new();
+        config
+            .proxy
+            .custom_mapping
+            .insert("regex:^gpt-(4".to_string(), "gemini-2.5-pro".to_string());
+        config.proxy.custom_mapping.insert("gpt-4o".to_string(), " ".to_string());
+
+        let report = validate_config(&config, &default_bound(&config));
+        assert!(!report.valid);
+        let error_paths: Vec<&str> = report.errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            error_paths,
+            vec![
+                "proxy.upstream_proxy.url",
+                "proxy.zai.base_url",
+                "proxy.custom_mapping[\"regex:^gpt-(4\"]",
+                "proxy.custom_mapping[\"gpt-4o\"]",
+            ]
+        );
+        assert!(report.warnings.iter().any(|w| w.path == "proxy.auth_mode"));
+    }
+
+    #[test]
+    fn test_model_routes_are_validated() {
+        use crate::proxy::config::ModelRoute;
+        let route = |pattern: &str, base_url: &str| ModelRoute {
+            pattern: pattern.to_string(),
+            base_url: base_url.to_string(),
+            auth_header: None,
+        };
+        let mut config = AppConfig::new();
+        config.proxy.model_routes = vec![
+            route("claude-*", "http://10.0.0.2:8045/v1internal"),
+            route("claude-*", "ftp://10.0.0.3/v1internal"),
+            route(" ", "http://10.0.0.4:8045/v1internal"),
+        ];
+
+        let report = validate_config(&config, &default_bound(&config));
+        let error_paths: Vec<&str> = report.errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            error_paths,
+            vec!["proxy.model_routes[1].base_url", "proxy.model_routes[2].pattern"]
+        );
+        assert!(report.warnings.iter().any(|w| w.path == "proxy.model_routes[1]"));
+        assert!(report.warnings.iter().any(|w| w.path == "proxy.model_routes[0].base_url"));
+        assert!(report.warnings.iter().any(|w| w.path == "proxy.model_routes[0].auth_header"));
+    }
+}
@@ -0,0 +1,169 @@
+// 按模型单价估算请求费用
+// 单价 (美元 / 百万 Token) 内置常见模型的默认值，数据目录下的 model_pricing.json 可覆盖或补充
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// 数据目录下的自定义单价文件
+pub const PRICING_FILE: &str = "model_pricing.json";
+
+/// 单个模型的单价 (USD / 1M tokens)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    #[serde(alias = "input")]
+    pub input_per_million: f64,
+    #[serde(alias = "output")]
+    pub output_per_million: f64,
+}
+
+impl ModelPricing {
+    pub const fn new(input_per_million: f64, output_per_million: f64) -> Self {
+        Self {
+            input_per_million,
+            output_per_million,
+        }
+    }
+}
+
+/// 内置默认单价 (按前缀匹配，更长的前缀优先)
+const DEFAULT_PRICING: &[(&str, ModelPricing)] = &[
+    ("gemini-3-pro", ModelPricing::new(2.0, 12.0)),
+    ("gemini-3-flash", ModelPricing::new(0.5, 3.0)),
+    ("gemini-2.5-pro", ModelPricing::new(1.25, 10.0)),
+    ("gemini-2.5-flash", ModelPricing::new(0.3, 2.5)),
+    ("gemini-2.5-flash-lite", ModelPricing::new(0.1, 0.4)),
+    ("gemini-2.0-flash", ModelPricing::new(0.1, 0.4)),
+    ("claude-opus-4-5", ModelPricing::new(5.0, 25.0)),
+    ("claude-opus-4", ModelPricing::new(15.0, 75.0)),
+    ("claude-sonnet-4", ModelPricing::new(3.0, 15.0)),
+    ("claude-3-7-sonnet", ModelPricing::new(3.0, 15.0)),
+    ("claude-3-5-sonnet", ModelPricing::new(3.0, 15.0)),
+    ("claude-haiku-4-5", ModelPricing::new(1.0, 5.0)),
+    ("claude-3-5-haiku", ModelPricing::new(0.8, 4.0)),
+];
+
+pub struct CostEstimator {
+    pricing: HashMap<String, ModelPricing>,
+}
+
+impl CostEstimator {
+    pub fn new(pricing: HashMap<String, ModelPricing>) -> Self {
+        Self {
+            pricing: pricing
+                .into_iter()
+                .map(|(model, price)| (model.to_lowercase(), price))
+                .collect(),
+        }
+    }
+
+    /// 内置默认单价
+    pub fn with_defaults() -> Self {
+        Self::new(
+            DEFAULT_PRICING
+                .iter()
+                .map(|(model, price)| (model.to_string(), *price))
+                .collect(),
+        )
+    }
+
+    /// 默认单价 + 数据目录下 model_pricing.json 中的覆盖项
+    pub fn load() -> Self {
+        let mut estimator = Self::with_defaults();
+        let Ok(path) = crate::modules::account::get_data_dir().map(|d| d.join(PRICING_FILE)) else {
+            return estimator;
+        };
+        if !path.exists() {
+            return estimator;
+        }
+        match std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|s| {
+                serde_json::from_str::<HashMap<String, ModelPricing>>(&s).map_err(|e| e.to_string())
+            }) {
+            Ok(custom) => {
+                tracing::info!("[Cost] Loaded {} model prices from {}", custom.len(), path.display());
+                for (model, price) in custom {
+                    estimator.pricing.insert(model.to_lowercase(), price);
+                }
+            }
+            Err(e) => tracing::warn!("[Cost] Failed to load {}: {}, using defaults", path.display(), e),
+        }
+        estimator
+    }
+
+    /// 全局实例 (首次使用时加载单价文件)
+    pub fn global() -> &'static CostEstimator {
+        static INSTANCE: OnceLock<CostEstimator> = OnceLock::new();
+        INSTANCE.get_or_init(CostEstimator::load)
+    }
+
+    /// 查找模型单价: 先精确匹配，再取最长的前缀匹配 (不区分大小写)
+    pub fn pricing_for(&self, model: &str) -> Option<ModelPricing> {
+        let model = model.to_lowercase();
+        if let Some(price) = self.pricing.get(&model) {
+            return Some(*price);
+        }
+        self.pricing
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, price)| *price)
+    }
+
+    /// 估算费用 (USD)，未知模型返回 None
+    pub fn estimate(&self, model: &str, input_tokens: u32, output_tokens: u32) -> Option<f64> {
+        let price = self.pricing_for(model)?;
+        Some(
+            (input_tokens as f64 * price.input_per_million
+                + output_tokens as f64 * price.output_per_million)
+                / 1_000_000.0,
+        )
+    }
+}
+
+impl Default for CostEstimator {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let estimator = CostEstimator::with_defaults();
+        assert_eq!(
+            estimator.pricing_for("gemini-2.5-flash-lite-preview"),
+            Some(ModelPricing::new(0.1, 0.4))
+        );
+        assert_eq!(
+            estimator.pricing_for("Gemini-2.5-Flash"),
+            Some(ModelPricing::new(0.3, 2.5))
+        );
+        assert_eq!(
+            estimator.pricing_for("claude-opus-4-5-thinking"),
+            Some(ModelPricing::new(5.0, 25.0))
+        );
+        assert!(estimator.pricing_for("unknown-model").is_none());
+    }
+
+    #[test]
+    fn test_estimate() {
+        let mut pricing = HashMap::new();
+        pricing.insert("my-model".to_string(), ModelPricing::new(2.0, 8.0));
+        let estimator = CostEstimator::new(pricing);
+
+        let cost = estimator.estimate("my-model", 500_000, 250_000).unwrap();
+        assert!((cost - 3.0).abs() < 1e-9);
+        assert!(estimator.estimate("other", 1, 1).is_none());
+    }
+
+    #[test]
+    fn test_pricing_file_format() {
+        let custom: HashMap<String, ModelPricing> =
+            serde_json::from_str(r#"{"gemini-2.5-pro": {"input": 1.0, "output": 5.0}}"#).unwrap();
+        assert_eq!(custom["gemini-2.5-pro"], ModelPricing::new(1.0, 5.0));
+    }
+}
@@ -1,10 +1,133 @@
+use serde::Serialize;
 use serde_json::Value;
 use tokio::fs;
+use tokio::io::AsyncWriteExt;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use futures::StreamExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
 
 use crate::proxy::config::DebugLoggingConfig;
 
+/// 抓包写入通道容量 (chunk 数)，写盘跟不上时丢弃并在文件末尾标记
+const CAPTURE_CHANNEL_CAPACITY: usize = 256;
+/// 单个 SSE 事件的最大缓冲长度，超过后不再等待事件边界直接写出
+const MAX_PENDING_EVENT_BYTES: usize = 256 * 1024;
+
+/// 需要在落盘前脱敏的字段名 (小写比较)
+const SENSITIVE_KEYS: &[&str] = &[
+    "authorization",
+    "x-api-key",
+    "x-goog-api-key",
+    "api_key",
+    "refresh_token",
+    "access_token",
+];
+
+static SENSITIVE_JSON_FIELD_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)("(?:authorization|x-api-key|x-goog-api-key|api_key|refresh_token|access_token)"\s*:\s*")[^"]*(")"#)
+        .unwrap()
+});
+static BEARER_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)(bearer\s+)[A-Za-z0-9._~+/=\-]+").unwrap());
+// Google OAuth refresh token 固定以 "1//" 开头
+static GOOGLE_REFRESH_TOKEN_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"1//[0-9A-Za-z_\-]{10,}").unwrap());
+
+/// 递归脱敏 JSON 中的鉴权头与 token 字段
+pub fn redact_json(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                if SENSITIVE_KEYS.contains(&k.to_ascii_lowercase().as_str()) {
+                    if !v.is_null() {
+                        *v = Value::String("***".to_string());
+                    }
+                } else {
+                    redact_json(v);
+                }
+            }
+        }
+        Value::Array(arr) => {
+            for v in arr {
+                redact_json(v);
+            }
+        }
+        Value::String(s) => {
+            let redacted = redact_text(s);
+            if redacted != *s {
+                *s = redacted;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 用户配置字段的替换值
+pub const REDACTED: &str = "[REDACTED]";
+
+/// 在内置脱敏之外，按 `redact_keys` 脱敏: 以 `/` 开头的条目按 JSON Pointer 定位，其余按字段名递归匹配
+pub fn redact_json_with_keys(value: &mut Value, redact_keys: &[String]) {
+    redact_json(value);
+
+    let mut names = Vec::new();
+    for key in redact_keys.iter().map(|k| k.trim()).filter(|k| !k.is_empty()) {
+        if key.starts_with('/') {
+            if let Some(target) = value.pointer_mut(key) {
+                *target = Value::String(REDACTED.to_string());
+            }
+        } else {
+            names.push(key.to_ascii_lowercase());
+        }
+    }
+    if !names.is_empty() {
+        redact_named_keys(value, &names);
+    }
+}
+
+fn redact_named_keys(value: &mut Value, names: &[String]) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                if names.contains(&k.to_ascii_lowercase()) {
+                    *v = Value::String(REDACTED.to_string());
+                } else {
+                    redact_named_keys(v, names);
+                }
+            }
+        }
+        Value::Array(arr) => {
+            for v in arr {
+                redact_named_keys(v, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 为原始文本 (SSE 抓包) 构建按字段名脱敏的正则，只处理字符串值
+fn build_redact_keys_regex(redact_keys: &[String]) -> Option<Regex> {
+    let names: Vec<String> = redact_keys
+        .iter()
+        .map(|k| k.trim())
+        .filter(|k| !k.is_empty() && !k.starts_with('/'))
+        .map(regex::escape)
+        .collect();
+    if names.is_empty() {
+        return None;
+    }
+    Regex::new(&format!(r#"(?i)("(?:{})"\s*:\s*")(?:[^"\\]|\\.)*(")"#, names.join("|"))).ok()
+}
+
+/// 脱敏原始文本 (SSE 片段等) 中的 Bearer token / refresh token
+pub fn redact_text(text: &str) -> String {
+    let out = SENSITIVE_JSON_FIELD_RE.replace_all(text, "${1}***${2}");
+    let out = BEARER_RE.replace_all(&out, "${1}***");
+    GOOGLE_REFRESH_TOKEN_RE.replace_all(&out, "1//***").into_owned()
+}
+
 fn build_filename(prefix: &str, trace_id: Option<&str>) -> String {
     let ts = chrono::Utc::now().format("%Y%m%d_%H%M%S%.3f");
     let tid = trace_id.unwrap_or("unknown");
@@ -47,7 +170,10 @@ pub async fn write_debug_payload(
     let filename = build_filename(prefix, trace_id);
     let path = output_dir.join(filename);
 
-    match serde_json::to_vec_pretty(payload) {
+    let mut payload = payload.clone();
+    redact_json_with_keys(&mut payload, &cfg.redact_keys);
+
+    match serde_json::to_vec_pretty(&payload) {
         Ok(bytes) => {
             if let Err(e) = fs::write(&path, bytes).await {
                 tracing::warn!("[Debug-Log] Failed to write file: {}", e);
@@ -63,6 +189,218 @@ pub fn is_enabled(cfg: &DebugLoggingConfig) -> bool {
     cfg.enabled
 }
 
+/// 写入 `{request_id}.request.json`，与 `{request_id}.response.sse` 成对出现
+pub async fn write_request_capture(cfg: &DebugLoggingConfig, request_id: &str, payload: &Value) {
+    if !cfg.enabled {
+        return;
+    }
+    let Some(output_dir) = resolve_output_dir(cfg) else {
+        return;
+    };
+    if let Err(e) = fs::create_dir_all(&output_dir).await {
+        tracing::warn!("[Debug-Log] Failed to create output dir: {}", e);
+        return;
+    }
+
+    let mut payload = payload.clone();
+    redact_json_with_keys(&mut payload, &cfg.redact_keys);
+
+    let path = output_dir.join(format!("{}.request.json", request_id));
+    match serde_json::to_vec_pretty(&payload) {
+        Ok(bytes) => {
+            if let Err(e) = fs::write(&path, bytes).await {
+                tracing::warn!("[Debug-Log] Failed to write request capture: {}", e);
+            }
+        }
+        Err(e) => {
+            tracing::warn!("[Debug-Log] Failed to serialize request capture: {}", e);
+        }
+    }
+}
+
+/// 原始 SSE 抓包: 通过有界通道把上游字节流旁路写入 `{request_id}.response.sse`
+struct SseCapture {
+    tx: Option<tokio::sync::mpsc::Sender<bytes::Bytes>>,
+    written: u64,
+    max_bytes: u64,
+    overflowed: Arc<AtomicBool>,
+    redact_keys_re: Option<Regex>,
+    /// 尚未遇到事件边界 (`\n\n`) 的字节，避免密钥被 chunk 切断后逃过脱敏
+    pending: Vec<u8>,
+}
+
+/// 最后一个完整 SSE 事件的结束位置 (兼容 `\n\n` 与 `\r\n\r\n`)
+fn last_event_boundary(buf: &[u8]) -> Option<usize> {
+    let lf = buf.windows(2).rposition(|w| w == b"\n\n").map(|i| i + 2);
+    let crlf = buf.windows(4).rposition(|w| w == b"\r\n\r\n").map(|i| i + 4);
+    lf.max(crlf)
+}
+
+/// 去掉末尾被截断的不完整 UTF-8 字符后的长度
+fn utf8_boundary(buf: &[u8]) -> usize {
+    match std::str::from_utf8(buf) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => buf.len(),
+    }
+}
+
+impl SseCapture {
+    fn start(cfg: &DebugLoggingConfig, request_id: &str) -> Option<Self> {
+        let output_dir = resolve_output_dir(cfg)?;
+        let path = output_dir.join(format!("{}.response.sse", request_id));
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<bytes::Bytes>(CAPTURE_CHANNEL_CAPACITY);
+        let overflowed = Arc::new(AtomicBool::new(false));
+        let overflowed_writer = overflowed.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = fs::create_dir_all(&output_dir).await {
+                tracing::warn!("[Debug-Log] Failed to create output dir: {}", e);
+                return;
+            }
+            let mut file = match fs::File::create(&path).await {
+                Ok(f) => f,
+                Err(e) => {
+                    tracing::warn!("[Debug-Log] Failed to create SSE capture file: {}", e);
+                    return;
+                }
+            };
+            while let Some(chunk) = rx.recv().await {
+                if let Err(e) = file.write_all(&chunk).await {
+                    tracing::warn!("[Debug-Log] Failed to write SSE capture: {}", e);
+                    return;
+                }
+            }
+            if overflowed_writer.load(Ordering::Relaxed) {
+                let _ = file
+                    .write_all(b"\n[CAPTURE INCOMPLETE: writer fell behind, some chunks were dropped]\n")
+                    .await;
+            }
+            let _ = file.flush().await;
+        });
+
+        Some(Self {
+            tx: Some(tx),
+            written: 0,
+            max_bytes: cfg.max_capture_bytes,
+            overflowed,
+            redact_keys_re: build_redact_keys_regex(&cfg.redact_keys),
+            pending: Vec::new(),
+        })
+    }
+
+    /// [FIX] 按完整 SSE 事件写出，保证脱敏正则看到的是完整事件
+    fn push(&mut self, chunk: &[u8]) {
+        if self.tx.is_none() {
+            return;
+        }
+        self.pending.extend_from_slice(chunk);
+        // 超过上限时不等事件边界，但保留末尾不完整的多字节字符留待下个 chunk
+        let cut = last_event_boundary(&self.pending).or_else(|| {
+            (self.pending.len() >= MAX_PENDING_EVENT_BYTES).then(|| utf8_boundary(&self.pending))
+        });
+        if let Some(cut) = cut {
+            let block: Vec<u8> = self.pending.drain(..cut).collect();
+            self.write_block(&block);
+        }
+    }
+
+    fn write_block(&mut self, chunk: &[u8]) {
+        let Some(tx) = self.tx.as_ref() else {
+            return;
+        };
+
+        let remaining = self.max_bytes.saturating_sub(self.written) as usize;
+        let (data, truncated) = if chunk.len() > remaining {
+            // [FIX] 截断点回退到字符边界，不切断多字节字符
+            let data = &chunk[..remaining];
+            (&data[..utf8_boundary(data)], true)
+        } else {
+            (chunk, false)
+        };
+
+        // [FIX] 任何内容落盘前都要脱敏: 非法 UTF-8 字节按替换字符处理，不再原样写出
+        let text = redact_text(&String::from_utf8_lossy(data));
+        let bytes = match self.redact_keys_re.as_ref() {
+            Some(re) => bytes::Bytes::from(
+                re.replace_all(&text, format!("${{1}}{}${{2}}", REDACTED).as_str())
+                    .into_owned(),
+            ),
+            None => bytes::Bytes::from(text),
+        };
+        self.written += data.len() as u64;
+        if !bytes.is_empty() && tx.try_send(bytes).is_err() {
+            self.overflowed.store(true, Ordering::Relaxed);
+        }
+
+        if truncated {
+            let marker = format!(
+                "\n[CAPTURE TRUNCATED: size limit of {} bytes reached]\n",
+                self.max_bytes
+            );
+            if tx.try_send(bytes::Bytes::from(marker)).is_err() {
+                self.overflowed.store(true, Ordering::Relaxed);
+            }
+            // 关闭通道，写入任务收尾
+            self.tx = None;
+        }
+    }
+}
+
+impl Drop for SseCapture {
+    fn drop(&mut self) {
+        // 流结束时写出末尾不完整的事件
+        if !self.pending.is_empty() {
+            let rest = std::mem::take(&mut self.pending);
+            self.write_block(&rest);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureFileInfo {
+    pub name: String,
+    pub size: u64,
+    pub modified: i64,
+}
+
+/// 列出最近的抓包文件 (`*.request.json` / `*.response.sse`)，按修改时间倒序
+pub async fn list_captures(cfg: &DebugLoggingConfig, limit: usize) -> Result<Vec<CaptureFileInfo>, String> {
+    let Some(output_dir) = resolve_output_dir(cfg) else {
+        return Ok(Vec::new());
+    };
+    let mut entries = match fs::read_dir(&output_dir).await {
+        Ok(e) => e,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read debug log dir: {}", e)),
+    };
+
+    let mut files = Vec::new();
+    while let Some(entry) = entries.next_entry().await.map_err(|e| e.to_string())? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.ends_with(".response.sse") && !name.ends_with(".request.json") {
+            continue;
+        }
+        let Ok(meta) = entry.metadata().await else {
+            continue;
+        };
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        files.push(CaptureFileInfo {
+            name,
+            size: meta.len(),
+            modified,
+        });
+    }
+
+    files.sort_by(|a, b| b.modified.cmp(&a.modified));
+    files.truncate(limit);
+    Ok(files)
+}
+
 /// 解析 SSE 流式数据，提取 thinking 和正文内容
 fn parse_sse_stream(raw: &str) -> (String, String) {
     let mut thinking_parts: Vec<String> = Vec::new();
@@ -138,15 +476,21 @@ pub fn wrap_reqwest_stream_with_debug(
         return stream;
     }
 
+    let mut capture = SseCapture::start(&cfg, &trace_id);
+
     let wrapped = async_stream::stream! {
         let mut collected: Vec<u8> = Vec::new();
         let mut inner = stream;
         while let Some(item) = inner.next().await {
             if let Ok(bytes) = &item {
                 collected.extend_from_slice(bytes);
+                if let Some(capture) = capture.as_mut() {
+                    capture.push(bytes);
+                }
             }
             yield item;
         }
+        drop(capture);
 
         let raw_text = String::from_utf8_lossy(&collected).to_string();
         let (thinking_content, response_content) = parse_sse_stream(&raw_text);
@@ -170,3 +514,142 @@ pub fn wrap_reqwest_stream_with_debug(
 
     Box::pin(wrapped)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_json_masks_sensitive_fields() {
+        let mut v = serde_json::json!({
+            "headers": { "Authorization": "Bearer sk-abc", "content-type": "application/json" },
+            "token": { "refresh_token": "1//0gabcdefghijklmnop", "access_token": null },
+            "messages": [{ "content": "hello" }]
+        });
+        redact_json(&mut v);
+        assert_eq!(v["headers"]["Authorization"], "***");
+        assert_eq!(v["headers"]["content-type"], "application/json");
+        assert_eq!(v["token"]["refresh_token"], "***");
+        assert!(v["token"]["access_token"].is_null());
+        assert_eq!(v["messages"][0]["content"], "hello");
+    }
+
+    #[test]
+    fn test_redact_keys_masks_nested_secret() {
+        let mut v = serde_json::json!({
+            "request": {
+                "messages": [{ "role": "user", "content": "my prompt" }],
+                "metadata": { "client": { "Session_Secret": "s3cr3t", "region": "us" } }
+            },
+            "headers": { "authorization": "Bearer sk-abc" }
+        });
+        let keys = vec!["session_secret".to_string(), "/request/messages".to_string()];
+        redact_json_with_keys(&mut v, &keys);
+
+        assert_eq!(v["request"]["metadata"]["client"]["Session_Secret"], REDACTED);
+        assert_eq!(v["request"]["metadata"]["client"]["region"], "us");
+        assert_eq!(v["request"]["messages"], REDACTED);
+        // 鉴权头不依赖配置始终脱敏
+        assert_eq!(v["headers"]["authorization"], "***");
+        assert!(!v.to_string().contains("s3cr3t"));
+    }
+
+    #[test]
+    fn test_redact_keys_regex_for_raw_text() {
+        let re = build_redact_keys_regex(&["session_secret".to_string(), "/ignored".to_string()]).unwrap();
+        let out = re.replace_all(r#"data: {"session_secret":"a\"b","x":"y"}"#, "${1}[REDACTED]${2}");
+        assert_eq!(out, r#"data: {"session_secret":"[REDACTED]","x":"y"}"#);
+        assert!(build_redact_keys_regex(&["/only/pointer".to_string()]).is_none());
+    }
+
+    #[test]
+    fn test_redact_text_masks_tokens_in_raw_sse() {
+        let raw = r#"data: {"refresh_token":"1//0gsecretvalue123","authorization":"Bearer ya29.abc"}"#;
+        let out = redact_text(raw);
+        assert!(!out.contains("secretvalue"));
+        assert!(!out.contains("ya29.abc"));
+        assert!(out.starts_with("data: "));
+
+        assert_eq!(redact_text("Authorization: Bearer ya29.token-x"), "Authorization: Bearer ***");
+    }
+
+    #[test]
+    fn test_sse_capture_redacts_secret_split_across_chunks() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let mut capture = SseCapture {
+            tx: Some(tx),
+            written: 0,
+            max_bytes: 1 << 20,
+            overflowed: Arc::new(AtomicBool::new(false)),
+            redact_keys_re: None,
+            pending: Vec::new(),
+        };
+
+        capture.push(b"data: {\"authorization\":\"Bearer ya2");
+        assert!(rx.try_recv().is_err());
+        capture.push(b"9.abcdef\"}\n\ndata: {\"x\":");
+        capture.push(b"1}");
+        drop(capture);
+
+        let mut out = String::new();
+        while let Ok(chunk) = rx.try_recv() {
+            out.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+        assert!(!out.contains("abcdef"));
+        assert!(out.ends_with("data: {\"x\":1}"));
+    }
+
+    #[test]
+    fn test_sse_capture_cuts_on_char_boundary() {
+        let event = "data: {\"access_token\":\"secret-value\",\"text\":\"你好\"}\n\n";
+        let capture_with_limit = |max_bytes: u64| {
+            let (tx, rx) = tokio::sync::mpsc::channel(16);
+            let capture = SseCapture {
+                tx: Some(tx),
+                written: 0,
+                max_bytes,
+                overflowed: Arc::new(AtomicBool::new(false)),
+                redact_keys_re: None,
+                pending: Vec::new(),
+            };
+            (capture, rx)
+        };
+        let collect = |rx: &mut tokio::sync::mpsc::Receiver<bytes::Bytes>| {
+            let mut out = Vec::new();
+            while let Ok(chunk) = rx.try_recv() {
+                out.extend_from_slice(&chunk);
+            }
+            String::from_utf8(out).expect("capture must stay valid UTF-8")
+        };
+
+        // 大小上限落在 "你" 的第二个字节上
+        let limit = event.find('你').unwrap() + 1;
+        let (mut capture, mut rx) = capture_with_limit(limit as u64);
+        capture.push(event.as_bytes());
+        drop(capture);
+        let out = collect(&mut rx);
+        assert!(!out.contains("secret-value"));
+        assert!(out.contains("\"access_token\":\"***\""));
+        assert!(out.contains("[CAPTURE TRUNCATED"));
+
+        // 无事件边界的超长数据在多字节字符中间被强制写出
+        let (mut capture, mut rx) = capture_with_limit(1 << 20);
+        let mut first = b"data: {\"access_token\":\"secret-value\",\"text\":\"".to_vec();
+        first.resize(MAX_PENDING_EVENT_BYTES, b'a');
+        first.extend_from_slice(&"你".as_bytes()[..1]);
+        capture.push(&first);
+        capture.push(&"你".as_bytes()[1..]);
+        capture.push(b"\"}\n\n");
+        drop(capture);
+        let out = collect(&mut rx);
+        assert!(!out.contains("secret-value"));
+        assert!(out.ends_with("a你\"}\n\n"));
+    }
+
+    #[test]
+    fn test_last_event_boundary() {
+        assert_eq!(last_event_boundary(b"data: a\n\ndata: b"), Some(9));
+        assert_eq!(last_event_boundary(b"data: a\r\n\r\n"), Some(11));
+        assert_eq!(last_event_boundary(b"data: a\n"), None);
+    }
+}
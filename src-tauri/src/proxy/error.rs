@@ -0,0 +1,486 @@
+// 统一的 API 错误类型
+// 管理接口与 AI 协议接口共用一套机器可读的错误码；协议接口再按 OpenAI / Claude / Gemini
+// 各自的原生错误格式包装，保证 SDK 的重试逻辑能够按 status + type 正确判断
+use axum::{
+    http::{HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::json;
+
+/// 机器可读的错误码 (序列化为 snake_case，供客户端分支判断)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiErrorCode {
+    InvalidRequest,
+    NotFound,
+    Conflict,
+    AuthInvalidKey,
+    PermissionDenied,
+    AccountNotFound,
+    AccountAlreadyExists,
+    AccountSwitchInProgress,
+    NoAvailableAccounts,
+    UpstreamQuotaExhausted,
+    BudgetExceeded,
+    UpstreamError,
+    UpstreamTimeout,
+    ContextTooLong,
+    RequestTransformFailed,
+    ServiceUnavailable,
+    InternalError,
+}
+
+impl ApiErrorCode {
+    pub const ALL: [ApiErrorCode; 17] = [
+        ApiErrorCode::InvalidRequest,
+        ApiErrorCode::NotFound,
+        ApiErrorCode::Conflict,
+        ApiErrorCode::AuthInvalidKey,
+        ApiErrorCode::PermissionDenied,
+        ApiErrorCode::AccountNotFound,
+        ApiErrorCode::AccountAlreadyExists,
+        ApiErrorCode::AccountSwitchInProgress,
+        ApiErrorCode::NoAvailableAccounts,
+        ApiErrorCode::UpstreamQuotaExhausted,
+        ApiErrorCode::BudgetExceeded,
+        ApiErrorCode::UpstreamError,
+        ApiErrorCode::UpstreamTimeout,
+        ApiErrorCode::ContextTooLong,
+        ApiErrorCode::RequestTransformFailed,
+        ApiErrorCode::ServiceUnavailable,
+        ApiErrorCode::InternalError,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiErrorCode::InvalidRequest => "invalid_request",
+            ApiErrorCode::NotFound => "not_found",
+            ApiErrorCode::Conflict => "conflict",
+            ApiErrorCode::AuthInvalidKey => "auth_invalid_key",
+            ApiErrorCode::PermissionDenied => "permission_denied",
+            ApiErrorCode::AccountNotFound => "account_not_found",
+            ApiErrorCode::AccountAlreadyExists => "account_already_exists",
+            ApiErrorCode::AccountSwitchInProgress => "account_switch_in_progress",
+            ApiErrorCode::NoAvailableAccounts => "no_available_accounts",
+            ApiErrorCode::UpstreamQuotaExhausted => "upstream_quota_exhausted",
+            ApiErrorCode::BudgetExceeded => "budget_exceeded",
+            ApiErrorCode::UpstreamError => "upstream_error",
+            ApiErrorCode::UpstreamTimeout => "upstream_timeout",
+            ApiErrorCode::ContextTooLong => "context_too_long",
+            ApiErrorCode::RequestTransformFailed => "request_transform_failed",
+            ApiErrorCode::ServiceUnavailable => "service_unavailable",
+            ApiErrorCode::InternalError => "internal_error",
+        }
+    }
+
+    /// 该错误码的默认 HTTP 状态
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiErrorCode::InvalidRequest => StatusCode::BAD_REQUEST,
+            ApiErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ApiErrorCode::Conflict => StatusCode::CONFLICT,
+            ApiErrorCode::AuthInvalidKey => StatusCode::UNAUTHORIZED,
+            ApiErrorCode::PermissionDenied => StatusCode::FORBIDDEN,
+            ApiErrorCode::AccountNotFound => StatusCode::NOT_FOUND,
+            ApiErrorCode::AccountAlreadyExists => StatusCode::CONFLICT,
+            ApiErrorCode::AccountSwitchInProgress => StatusCode::CONFLICT,
+            ApiErrorCode::NoAvailableAccounts => StatusCode::SERVICE_UNAVAILABLE,
+            ApiErrorCode::UpstreamQuotaExhausted => StatusCode::TOO_MANY_REQUESTS,
+            ApiErrorCode::BudgetExceeded => StatusCode::TOO_MANY_REQUESTS,
+            ApiErrorCode::UpstreamError => StatusCode::BAD_GATEWAY,
+            ApiErrorCode::UpstreamTimeout => StatusCode::GATEWAY_TIMEOUT,
+            ApiErrorCode::ContextTooLong => StatusCode::BAD_REQUEST,
+            ApiErrorCode::RequestTransformFailed => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiErrorCode::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ApiErrorCode::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ApiErrorCode::InvalidRequest => "The request body or parameters are invalid.",
+            ApiErrorCode::NotFound => "The requested resource does not exist.",
+            ApiErrorCode::Conflict => "The request conflicts with the current state.",
+            ApiErrorCode::AuthInvalidKey => "The API key or admin password is missing or invalid.",
+            ApiErrorCode::PermissionDenied => "The caller is not allowed to perform this action.",
+            ApiErrorCode::AccountNotFound => "No account with the given ID exists.",
+            ApiErrorCode::AccountAlreadyExists => "An account with the same email has already been added.",
+            ApiErrorCode::AccountSwitchInProgress => "Another account switch operation is already running.",
+            ApiErrorCode::NoAvailableAccounts => "No account in the pool can serve the request right now.",
+            ApiErrorCode::UpstreamQuotaExhausted => "All candidate accounts are rate limited or out of quota.",
+            ApiErrorCode::BudgetExceeded => "The daily token budget is used up; it resets at local midnight.",
+            ApiErrorCode::UpstreamError => "The upstream API returned an error or an unreadable response.",
+            ApiErrorCode::UpstreamTimeout => "The upstream API did not respond in time; the request can be retried.",
+            ApiErrorCode::ContextTooLong => "The prompt exceeds the model context window.",
+            ApiErrorCode::RequestTransformFailed => "The request could not be converted to the upstream protocol.",
+            ApiErrorCode::ServiceUnavailable => "The proxy service is not running or not initialized.",
+            ApiErrorCode::InternalError => "An unexpected internal error occurred.",
+        }
+    }
+
+    /// 按 HTTP 状态推断通用错误码 (迁移旧代码时使用)
+    pub fn from_status(status: StatusCode) -> Self {
+        match status.as_u16() {
+            400 | 413 | 422 => ApiErrorCode::InvalidRequest,
+            401 => ApiErrorCode::AuthInvalidKey,
+            403 => ApiErrorCode::PermissionDenied,
+            404 => ApiErrorCode::NotFound,
+            409 => ApiErrorCode::Conflict,
+            429 => ApiErrorCode::UpstreamQuotaExhausted,
+            502 => ApiErrorCode::UpstreamError,
+            504 => ApiErrorCode::UpstreamTimeout,
+            503 | 529 => ApiErrorCode::ServiceUnavailable,
+            _ => ApiErrorCode::InternalError,
+        }
+    }
+
+    /// OpenAI 错误 envelope 中的 `error.type`
+    fn openai_type(&self) -> &'static str {
+        match self {
+            ApiErrorCode::InvalidRequest
+            | ApiErrorCode::NotFound
+            | ApiErrorCode::Conflict
+            | ApiErrorCode::AccountNotFound
+            | ApiErrorCode::AccountAlreadyExists
+            | ApiErrorCode::ContextTooLong => "invalid_request_error",
+            ApiErrorCode::AuthInvalidKey => "authentication_error",
+            ApiErrorCode::PermissionDenied => "permission_error",
+            ApiErrorCode::UpstreamQuotaExhausted | ApiErrorCode::BudgetExceeded => "rate_limit_error",
+            ApiErrorCode::AccountSwitchInProgress
+            | ApiErrorCode::NoAvailableAccounts
+            | ApiErrorCode::ServiceUnavailable => "service_unavailable_error",
+            ApiErrorCode::UpstreamTimeout => "timeout_error",
+            ApiErrorCode::UpstreamError
+            | ApiErrorCode::RequestTransformFailed
+            | ApiErrorCode::InternalError => "server_error",
+        }
+    }
+
+    /// Claude 错误 envelope 中的 `error.type`
+    fn claude_type(&self) -> &'static str {
+        match self {
+            ApiErrorCode::InvalidRequest
+            | ApiErrorCode::Conflict
+            | ApiErrorCode::AccountAlreadyExists
+            | ApiErrorCode::ContextTooLong => "invalid_request_error",
+            ApiErrorCode::NotFound | ApiErrorCode::AccountNotFound => "not_found_error",
+            ApiErrorCode::AuthInvalidKey => "authentication_error",
+            ApiErrorCode::PermissionDenied => "permission_error",
+            ApiErrorCode::UpstreamQuotaExhausted | ApiErrorCode::BudgetExceeded => "rate_limit_error",
+            ApiErrorCode::AccountSwitchInProgress
+            | ApiErrorCode::NoAvailableAccounts
+            | ApiErrorCode::ServiceUnavailable => "overloaded_error",
+            ApiErrorCode::UpstreamError
+            | ApiErrorCode::UpstreamTimeout
+            | ApiErrorCode::RequestTransformFailed
+            | ApiErrorCode::InternalError => "api_error",
+        }
+    }
+
+    /// Gemini (google.rpc.Status) 错误中的 `error.status`
+    fn gemini_status(&self) -> &'static str {
+        match self {
+            ApiErrorCode::InvalidRequest
+            | ApiErrorCode::ContextTooLong => "INVALID_ARGUMENT",
+            ApiErrorCode::NotFound | ApiErrorCode::AccountNotFound => "NOT_FOUND",
+            ApiErrorCode::AccountAlreadyExists => "ALREADY_EXISTS",
+            ApiErrorCode::Conflict | ApiErrorCode::AccountSwitchInProgress => "ABORTED",
+            ApiErrorCode::AuthInvalidKey => "UNAUTHENTICATED",
+            ApiErrorCode::PermissionDenied => "PERMISSION_DENIED",
+            ApiErrorCode::UpstreamQuotaExhausted | ApiErrorCode::BudgetExceeded => "RESOURCE_EXHAUSTED",
+            ApiErrorCode::NoAvailableAccounts | ApiErrorCode::ServiceUnavailable => "UNAVAILABLE",
+            ApiErrorCode::UpstreamTimeout => "DEADLINE_EXCEEDED",
+            ApiErrorCode::UpstreamError
+            | ApiErrorCode::RequestTransformFailed
+            | ApiErrorCode::InternalError => "INTERNAL",
+        }
+    }
+}
+
+/// 错误响应体的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ApiErrorFormat {
+    /// 管理接口: `{"error": "<message>", "code": "<code>"}` (保持旧版 `error` 字段为字符串)
+    #[default]
+    Admin,
+    /// `{"error": {"message", "type", "code", "param"}}`
+    OpenAI,
+    /// `{"type": "error", "error": {"type", "message", "code"}}`
+    Claude,
+    /// `{"error": {"code": <http>, "message", "status", "details": [{"reason": "<code>"}]}}`
+    Gemini,
+}
+
+impl ApiErrorFormat {
+    /// 按请求路径推断协议 (供中间件等不区分 handler 的位置使用)
+    pub fn for_path(path: &str) -> Self {
+        if path.starts_with("/v1/messages") {
+            ApiErrorFormat::Claude
+        } else if path.starts_with("/v1beta") {
+            ApiErrorFormat::Gemini
+        } else if path.starts_with("/v1") {
+            ApiErrorFormat::OpenAI
+        } else {
+            ApiErrorFormat::Admin
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub code: ApiErrorCode,
+    pub status: StatusCode,
+    pub message: String,
+    /// OpenAI envelope 中的 `error.param` (出错的请求字段)
+    param: Option<String>,
+    /// Gemini envelope 中 `error.details` 的附加条目 (如 google.rpc.BadRequest)
+    details: Vec<serde_json::Value>,
+    format: ApiErrorFormat,
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl ApiError {
+    pub fn new(code: ApiErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            status: code.status(),
+            message: message.into(),
+            param: None,
+            details: Vec::new(),
+            format: ApiErrorFormat::Admin,
+            headers: Vec::new(),
+        }
+    }
+
+    /// 保留既有 HTTP 状态，错误码按状态推断
+    pub fn from_status(status: StatusCode, message: impl Into<String>) -> Self {
+        Self::new(ApiErrorCode::from_status(status), message).with_status(status)
+    }
+
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        Self::new(ApiErrorCode::InvalidRequest, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ApiErrorCode::InternalError, message)
+    }
+
+    /// 将 TokenManager::get_token 的错误归类为账号池错误
+    pub fn from_token_error(message: impl Into<String>) -> Self {
+        let message = message.into();
+        let code = if message.contains("All accounts limited") {
+            ApiErrorCode::UpstreamQuotaExhausted
+        } else if message.contains("daily token budget") {
+            ApiErrorCode::BudgetExceeded
+        } else {
+            ApiErrorCode::NoAvailableAccounts
+        };
+        Self::new(code, format!("Token error: {}", message))
+    }
+
+    /// 将账号存储层 (modules::account) 的错误归类: 账号不存在 / 重复添加，其余为内部错误
+    pub fn from_account_error(message: impl Into<String>) -> Self {
+        let message = message.into();
+        let code = if message.starts_with("Account not found")
+            || message.starts_with("Account ID not found")
+        {
+            ApiErrorCode::AccountNotFound
+        } else if message.starts_with("Account already exists") {
+            ApiErrorCode::AccountAlreadyExists
+        } else {
+            ApiErrorCode::InternalError
+        };
+        Self::new(code, message)
+    }
+
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// 附加响应头 (如 X-Mapped-Model / X-Account-Email)，非法值会被忽略
+    pub fn with_header(mut self, name: &'static str, value: &str) -> Self {
+        if let (Ok(n), Ok(v)) = (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+            self.headers.push((n, v));
+        }
+        self
+    }
+
+    pub fn with_param(mut self, param: impl Into<String>) -> Self {
+        self.param = Some(param.into());
+        self
+    }
+
+    pub fn with_detail(mut self, detail: serde_json::Value) -> Self {
+        self.details.push(detail);
+        self
+    }
+
+    pub fn format(mut self, format: ApiErrorFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn openai(self) -> Self {
+        self.format(ApiErrorFormat::OpenAI)
+    }
+
+    pub fn claude(self) -> Self {
+        self.format(ApiErrorFormat::Claude)
+    }
+
+    pub fn gemini(self) -> Self {
+        self.format(ApiErrorFormat::Gemini)
+    }
+
+    pub(crate) fn body(&self) -> serde_json::Value {
+        let code = self.code.as_str();
+        match self.format {
+            ApiErrorFormat::Admin => json!({
+                "error": self.message,
+                "code": code,
+            }),
+            ApiErrorFormat::OpenAI => json!({
+                "error": {
+                    "message": self.message,
+                    "type": self.code.openai_type(),
+                    "code": code,
+                    "param": self.param,
+                }
+            }),
+            ApiErrorFormat::Claude => json!({
+                "type": "error",
+                "error": {
+                    "type": self.code.claude_type(),
+                    "message": self.message,
+                    "code": code,
+                }
+            }),
+            ApiErrorFormat::Gemini => {
+                let mut details = vec![json!({ "reason": code })];
+                details.extend(self.details.iter().cloned());
+                json!({
+                    "error": {
+                        "code": self.status.as_u16(),
+                        "message": self.message,
+                        "status": self.code.gemini_status(),
+                        "details": details,
+                    }
+                })
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code.as_str(), self.message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = self.body();
+        let mut response = (self.status, Json(body)).into_response();
+        for (name, value) in self.headers {
+            response.headers_mut().insert(name, value);
+        }
+        response
+    }
+}
+
+/// `GET /api/errors` 的条目
+#[derive(Debug, Serialize)]
+pub struct ApiErrorCodeInfo {
+    pub code: &'static str,
+    pub status: u16,
+    pub description: &'static str,
+    pub openai_type: &'static str,
+    pub claude_type: &'static str,
+    pub gemini_status: &'static str,
+}
+
+/// 完整错误码列表 (供客户端自省)
+pub fn error_catalog() -> Vec<ApiErrorCodeInfo> {
+    ApiErrorCode::ALL
+        .iter()
+        .map(|c| ApiErrorCodeInfo {
+            code: c.as_str(),
+            status: c.status().as_u16(),
+            description: c.description(),
+            openai_type: c.openai_type(),
+            claude_type: c.claude_type(),
+            gemini_status: c.gemini_status(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_matches_serde_names() {
+        for code in ApiErrorCode::ALL {
+            let serialized = serde_json::to_value(code).unwrap();
+            assert_eq!(serialized, code.as_str());
+        }
+        assert_eq!(error_catalog().len(), ApiErrorCode::ALL.len());
+    }
+
+    #[test]
+    fn test_envelopes() {
+        let err = ApiError::new(ApiErrorCode::UpstreamQuotaExhausted, "exhausted");
+
+        let admin = err.clone().body();
+        assert_eq!(admin["error"], "exhausted");
+        assert_eq!(admin["code"], "upstream_quota_exhausted");
+
+        let openai = err.clone().openai().body();
+        assert_eq!(openai["error"]["type"], "rate_limit_error");
+        assert_eq!(openai["error"]["code"], "upstream_quota_exhausted");
+
+        let claude = err.clone().claude().body();
+        assert_eq!(claude["type"], "error");
+        assert_eq!(claude["error"]["type"], "rate_limit_error");
+
+        let gemini = err.gemini().body();
+        assert_eq!(gemini["error"]["code"], 429);
+        assert_eq!(gemini["error"]["status"], "RESOURCE_EXHAUSTED");
+    }
+
+    #[test]
+    fn test_from_status_keeps_status_and_headers() {
+        let resp = ApiError::from_status(StatusCode::SERVICE_UNAVAILABLE, "down")
+            .with_header("X-Mapped-Model", "gemini-2.5-pro")
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.headers()["x-mapped-model"], "gemini-2.5-pro");
+
+        let token = ApiError::from_token_error("All accounts limited. Wait 30s.");
+        assert_eq!(token.code, ApiErrorCode::UpstreamQuotaExhausted);
+        assert_eq!(token.status, StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[test]
+    fn test_account_error_codes() {
+        let missing = ApiError::from_account_error("Account not found: abc");
+        assert_eq!(missing.code, ApiErrorCode::AccountNotFound);
+        assert_eq!(missing.status, StatusCode::NOT_FOUND);
+        assert_eq!(missing.body()["code"], "account_not_found");
+
+        let dup = ApiError::from_account_error("Account already exists: a@b.com");
+        assert_eq!(dup.code, ApiErrorCode::AccountAlreadyExists);
+        assert_eq!(dup.status, StatusCode::CONFLICT);
+
+        let other = ApiError::from_account_error("failed_to_acquire_lock: poisoned");
+        assert_eq!(other.code, ApiErrorCode::InternalError);
+
+        let timeout = ApiError::from_status(StatusCode::GATEWAY_TIMEOUT, "slow");
+        assert_eq!(timeout.code, ApiErrorCode::UpstreamTimeout);
+        assert_eq!(timeout.clone().openai().body()["error"]["type"], "timeout_error");
+        assert_eq!(timeout.gemini().body()["error"]["status"], "DEADLINE_EXCEEDED");
+    }
+}
@@ -8,24 +8,26 @@ use serde_json::{json, Value};
 use tracing::{debug, info};
 use uuid::Uuid;
 
-use crate::proxy::{audio::AudioProcessor, server::AppState};
+use crate::proxy::config::TimeoutRouteClass;
+use crate::proxy::error::ApiError;
+use crate::proxy::timeouts::{self, TimeoutKind};
+use crate::proxy::audio::{cache::AudioCache, AudioProcessor};
+use crate::proxy::server::AppState;
 
 /// 处理音频转录请求 (OpenAI Whisper API 兼容)
 pub async fn handle_audio_transcription(
     State(state): State<AppState>,
     mut multipart: Multipart,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     let mut audio_data: Option<Vec<u8>> = None;
     let mut filename: Option<String> = None;
     let mut model = "gemini-2.0-flash-exp".to_string();
     let mut prompt = "Generate a transcript of the speech.".to_string();
 
     // 1. 解析 multipart/form-data
-    while let Some(field) = multipart
-        .next_field()
-        .await
-        .map_err(|e| (StatusCode::BAD_REQUEST, format!("解析表单失败: {}", e)))?
-    {
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        ApiError::from_status(StatusCode::BAD_REQUEST, format!("解析表单失败: {}", e)).openai()
+    })? {
         let name = field.name().unwrap_or("").to_string();
 
         match name.as_str() {
@@ -35,7 +37,13 @@ pub async fn handle_audio_transcription(
                     field
                         .bytes()
                         .await
-                        .map_err(|e| (StatusCode::BAD_REQUEST, format!("读取文件失败: {}", e)))?
+                        .map_err(|e| {
+                            ApiError::from_status(
+                                StatusCode::BAD_REQUEST,
+                                format!("读取文件失败: {}", e),
+                            )
+                            .openai()
+                        })?
                         .to_vec(),
                 );
             }
@@ -49,9 +57,13 @@ pub async fn handle_audio_transcription(
         }
     }
 
-    let audio_bytes = audio_data.ok_or((StatusCode::BAD_REQUEST, "缺少音频文件".to_string()))?;
+    let audio_bytes = audio_data.ok_or_else(|| {
+        ApiError::from_status(StatusCode::BAD_REQUEST, "缺少音频文件").openai()
+    })?;
 
-    let file_name = filename.ok_or((StatusCode::BAD_REQUEST, "无法获取文件名".to_string()))?;
+    let file_name = filename.ok_or_else(|| {
+        ApiError::from_status(StatusCode::BAD_REQUEST, "无法获取文件名").openai()
+    })?;
 
     info!(
         "收到音频转录请求: 文件={}, 大小={} bytes, 模型={}",
@@ -61,26 +73,48 @@ pub async fn handle_audio_transcription(
     );
 
     // 2. 检测 MIME 类型
-    let mime_type =
-        AudioProcessor::detect_mime_type(&file_name).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let mime_type = AudioProcessor::detect_mime_type(&file_name)
+        .map_err(|e| ApiError::from_status(StatusCode::BAD_REQUEST, e).openai())?;
 
     // 3. 验证文件大小
     if AudioProcessor::exceeds_size_limit(audio_bytes.len()) {
         let size_mb = audio_bytes.len() as f64 / (1024.0 * 1024.0);
-        return Err((
+        return Err(ApiError::from_status(
             StatusCode::PAYLOAD_TOO_LARGE,
             format!(
                 "音频文件过大 ({:.1} MB)。最大支持 15 MB (约 16 分钟 MP3)。建议: 1) 压缩音频质量 2) 分段上传",
                 size_mb
             ),
-        ));
+        )
+        .openai());
     }
 
-    // 4. 使用 Inline Data 方式
+    // 4. 转录缓存: 客户端重试时同一音频会被重复上传
+    let cache_ttl = {
+        let experimental = state.experimental.read().await;
+        experimental
+            .enable_response_cache
+            .then(|| std::time::Duration::from_secs(experimental.audio_cache_ttl_secs))
+            .filter(|ttl| !ttl.is_zero())
+    };
+    let cache_key = cache_ttl.map(|_| AudioCache::key(&audio_bytes, &model, &prompt));
+    if let (Some(ttl), Some(key)) = (cache_ttl, cache_key.as_deref()) {
+        if let Some(text) = AudioCache::global().get(key, ttl) {
+            info!("音频转录命中缓存 ({} 字符)", text.len());
+            return Ok((
+                StatusCode::OK,
+                [("X-Audio-Cache", "hit")],
+                Json(json!({ "text": text })),
+            )
+                .into_response());
+        }
+    }
+
+    // 5. 使用 Inline Data 方式
     debug!("使用 Inline Data 方式处理");
     let base64_audio = AudioProcessor::encode_to_base64(&audio_bytes);
 
-    // 5. 构建 Gemini 请求
+    // 6. 构建 Gemini 请求
     let gemini_request = json!({
         "contents": [{
             "parts": [
@@ -95,16 +129,16 @@ pub async fn handle_audio_transcription(
         }]
     });
 
-    // 6. 获取 Token 和上游客户端
+    // 7. 获取 Token 和上游客户端
     let token_manager = state.token_manager;
     let (access_token, project_id, email, account_id, _wait_ms) = token_manager
         .get_token("text", false, None, &model)
         .await
-        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e))?;
+        .map_err(|e| ApiError::from_token_error(e).openai())?;
 
     info!("使用账号: {}", email);
 
-    // 7. 包装请求为 v1internal 格式
+    // 8. 包装请求为 v1internal 格式
     let wrapped_body = json!({
         "project": project_id,
         "requestId": format!("audio-{}", Uuid::new_v4()),
@@ -114,37 +148,50 @@ pub async fn handle_audio_transcription(
         "requestType": "text"
     });
 
-    // 8. 发送请求到 Gemini
+    // 9. 发送请求到 Gemini (按 audio 路由类别 / 模型限制总耗时)
     let upstream = state.upstream.clone();
-    let response = upstream
-        .call_v1_internal(
-            "generateContent",
-            &access_token,
-            wrapped_body,
-            None,
-            Some(account_id.as_str()),
-        )
-        .await
-        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("上游请求失败: {}", e)))?
-        .response;
-
-    if !response.status().is_success() {
-        let error_text = response
-            .text()
+    let upstream_timeout = timeouts::resolve(TimeoutRouteClass::Audio, &[model.as_str()]);
+    let upstream_call = async {
+        let response = upstream
+            .call_v1_internal_with_headers(
+                "generateContent",
+                &access_token,
+                wrapped_body,
+                None,
+                std::collections::HashMap::new(),
+                Some(account_id.as_str()),
+                upstream_timeout.request_override(false),
+            )
             .await
-            .unwrap_or_else(|_| "Unknown error".to_string());
-        return Err((
-            StatusCode::BAD_GATEWAY,
-            format!("Gemini API 错误: {}", error_text),
-        ));
-    }
+            .map_err(|e| {
+                ApiError::from_status(StatusCode::BAD_GATEWAY, format!("上游请求失败: {}", e))
+                    .openai()
+            })?
+            .response;
+
+        if !response.status().is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(ApiError::from_status(
+                StatusCode::BAD_GATEWAY,
+                format!("Gemini API 错误: {}", error_text),
+            )
+            .openai());
+        }
 
-    let result: Value = response
-        .json()
-        .await
-        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("解析响应失败: {}", e)))?;
+        response.json::<Value>().await.map_err(|e| {
+            ApiError::from_status(StatusCode::BAD_GATEWAY, format!("解析响应失败: {}", e)).openai()
+        })
+    };
 
-    // 9. 提取文本响应（解包 v1internal 响应）
+    let result: Value = match tokio::time::timeout(upstream_timeout.duration(), upstream_call).await {
+        Ok(r) => r?,
+        Err(_) => return Ok(upstream_timeout.error_response(TimeoutKind::Total)),
+    };
+
+    // 10. 提取文本响应（解包 v1internal 响应）
     let inner_response = result.get("response").unwrap_or(&result);
     let text = inner_response
         .get("candidates")
@@ -158,7 +205,11 @@ pub async fn handle_audio_transcription(
 
     info!("音频转录完成，返回 {} 字符", text.len());
 
-    // 10. 返回标准格式响应
+    if let (Some(ttl), Some(key)) = (cache_ttl, cache_key) {
+        AudioCache::global().insert(key, text.to_string(), ttl);
+    }
+
+    // 11. 返回标准格式响应
     Ok((
         StatusCode::OK,
         [("X-Account-Email", email.as_str())],
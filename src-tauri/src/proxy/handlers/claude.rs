@@ -22,6 +22,9 @@ use crate::proxy::server::AppState;
 use crate::proxy::mappers::context_manager::ContextManager;
 use crate::proxy::mappers::estimation_calibrator::get_calibrator;
 use crate::proxy::debug_logger;
+use crate::proxy::error::{ApiError, ApiErrorCode};
+use crate::proxy::mappers::error_classifier::{parse_upstream_error, UpstreamError};
+use crate::proxy::timeouts::TimeoutKind;
 use crate::proxy::upstream::client::mask_email;
 use crate::proxy::common::client_adapter::CLIENT_ADAPTERS; // [NEW] Import Adapter Registry
 use axum::http::HeaderMap;
@@ -105,7 +108,7 @@ The structure MUST be as follows:
 
 // ===== 统一退避策略模块 =====
 // 移除本地重复定义，使用 common 中的统一实现
-use super::common::{determine_retry_strategy, apply_retry_strategy, should_rotate_account, RetryStrategy};
+use super::common::{determine_retry_strategy, apply_retry_strategy, should_rotate_account, FailoverTracker, RetryStrategy};
 
 // ===== 退避策略模块结束 =====
 
@@ -146,19 +149,15 @@ pub async fn handle_messages(
     let mut request: crate::proxy::mappers::claude::models::ClaudeRequest = match serde_json::from_value(body) {
         Ok(r) => r,
         Err(e) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(json!({
-                    "type": "error",
-                    "error": {
-                        "type": "invalid_request_error",
-                        "message": format!("Invalid request body: {}", e)
-                    }
-                }))
-            ).into_response();
+            return ApiError::invalid_request(format!("Invalid request body: {}", e))
+                .claude()
+                .into_response();
         }
     };
 
+    // [NEW] 按 claude_thinking 配置调整 thinking.budget_tokens
+    apply_claude_thinking_budget(&mut request, &crate::proxy::get_claude_thinking_config());
+
     if debug_logger::is_enabled(&debug_cfg) {
         // [FIX] 使用原始 body 副本记录日志，确保不丢失任何字段
         let original_payload = json!({
@@ -169,6 +168,7 @@ pub async fn handle_messages(
             "request": original_body,  // 使用原始请求体，不是结构体序列化
         });
         debug_logger::write_debug_payload(&debug_cfg, Some(&trace_id), "original_request", &original_payload).await;
+        debug_logger::write_request_capture(&debug_cfg, &trace_id, &original_payload).await;
     }
 
     // [Issue #703 Fix] 智能兜底判断:需要归一化模型名用于配额保护检查
@@ -182,9 +182,13 @@ pub async fn handle_messages(
             crate::proxy::ZaiDispatchMode::Off => false,
             crate::proxy::ZaiDispatchMode::Exclusive => true,
             crate::proxy::ZaiDispatchMode::Fallback => {
-                if google_accounts == 0 {
+                if !state.zai_health.is_healthy() {
+                    // [NEW] z.ai 探测不健康时不兜底，避免把失败转移到一个已知不可用的提供商
+                    false
+                } else if google_accounts == 0 {
                     // 没有 Google 账号,使用兜底
                     tracing::info!("[{}] No Google accounts available, using fallback provider", trace_id);
+                    state.monitor.record_fallback();
                     true
                 } else {
                     // [Issue #703 Fix] 智能判断:检查是否有可用的 Google 账号
@@ -195,6 +199,7 @@ pub async fn handle_messages(
                             trace_id,
                             request.model
                         );
+                        state.monitor.record_fallback();
                     }
                     !has_available
                 }
@@ -202,9 +207,14 @@ pub async fn handle_messages(
             crate::proxy::ZaiDispatchMode::Pooled => {
                 // Treat z.ai as exactly one extra slot in the pool.
                 // No strict guarantees: it may get 0 requests if selection never hits.
-                let total = google_accounts.saturating_add(1).max(1);
-                let slot = state.provider_rr.fetch_add(1, Ordering::Relaxed) % total;
-                slot == 0
+                // [NEW] z.ai 探测不健康时跳过该槽位 (没有 Google 账号时仍交给 z.ai)
+                if google_accounts > 0 && !state.zai_health.is_healthy() {
+                    false
+                } else {
+                    let total = google_accounts.saturating_add(1).max(1);
+                    let slot = state.provider_rr.fetch_add(1, Ordering::Relaxed) % total;
+                    slot == 0
+                }
             }
         }
     };
@@ -249,13 +259,17 @@ pub async fn handle_messages(
         return create_warmup_response(&request, request.stream);
     }
 
+    state.monitor.record_dispatch(use_zai);
+
     if use_zai {
         // 重新序列化修复后的请求体
         let new_body = match serde_json::to_value(&request) {
             Ok(v) => v,
             Err(e) => {
                 tracing::error!("Failed to serialize fixed request for z.ai: {}", e);
-                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                return ApiError::internal(format!("Failed to serialize request: {}", e))
+                    .claude()
+                    .into_response();
             }
         };
 
@@ -392,6 +406,8 @@ pub async fn handle_messages(
     let mut last_email: Option<String> = None;
     let mut last_mapped_model: Option<String> = None;
     let mut last_status = StatusCode::SERVICE_UNAVAILABLE; // Default to 503 if no response reached
+    let mut last_upstream: Option<UpstreamError> = None;
+    let mut failover = FailoverTracker::default();
     
     for attempt in 0..max_attempts {
         // 2. 模型路由解析
@@ -429,24 +445,22 @@ pub async fn handle_messages(
                 } else {
                     e
                 };
-                let headers = [
-                    ("X-Mapped-Model", mapped_model.as_str()),
-                ];
-                 return (
-                    StatusCode::SERVICE_UNAVAILABLE,
-                    headers,
-                    Json(json!({
-                        "type": "error",
-                        "error": {
-                            "type": "overloaded_error",
-                            "message": format!("No available accounts: {}", safe_message)
-                        }
-                    }))
-                ).into_response();
+                return ApiError::from_token_error(safe_message)
+                    .with_header("X-Mapped-Model", &mapped_model)
+                    .claude()
+                    .into_response();
             }
         };
 
+        // [NEW] 账号级并发限制 (排队超时返回 429)
+        if let Err(resp) =
+            crate::proxy::concurrency::acquire_account_slot(&token_manager, &account_id).await
+        {
+            return resp;
+        }
+
         last_email = Some(email.clone());
+        failover.on_account_selected(&email, &config.final_model);
         info!("✓ Using account: {} (type: {})", email, config.request_type);
         
         
@@ -635,6 +649,7 @@ pub async fn handle_messages(
                                 "type": "error",
                                 "error": {
                                     "type": "invalid_request_error",
+                                    "code": ApiErrorCode::ContextTooLong.as_str(),
                                     "message": format!("Context too long and automatic compression failed: {}", e),
                                     "suggestion": "Please use /compact or /clear command in Claude Code, or switch to a model with larger context window."
                                 }
@@ -664,21 +679,14 @@ pub async fn handle_messages(
                 b
             },
             Err(e) => {
-                 let headers = [
-                    ("X-Mapped-Model", request_with_mapped.model.as_str()),
-                    ("X-Account-Email", email.as_str()),
-                ];
-                 return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    headers,
-                    Json(json!({
-                        "type": "error",
-                        "error": {
-                            "type": "api_error",
-                            "message": format!("Transform error: {}", e)
-                        }
-                    }))
-                ).into_response();
+                return ApiError::new(
+                    ApiErrorCode::RequestTransformFailed,
+                    format!("Transform error: {}", e),
+                )
+                .with_header("X-Mapped-Model", &request_with_mapped.model)
+                .with_header("X-Account-Email", &email)
+                .claude()
+                .into_response();
             }
         };
 
@@ -731,15 +739,24 @@ pub async fn handle_messages(
 
         // Upstream call configuration continued...
 
-        let call_result = match upstream
-            .call_v1_internal_with_headers(method, &access_token, gemini_body, query, extra_headers.clone(), Some(account_id.as_str()))
-            .await {
-            Ok(r) => r,
-            Err(e) => {
+        // [NEW] 按路由/模型解析生效超时: 流式为空闲超时，非流式为总耗时
+        let upstream_timeout = crate::proxy::timeouts::resolve(
+            crate::proxy::config::TimeoutRouteClass::Chat,
+            &[request.model.as_str(), request_with_mapped.model.as_str()],
+        );
+
+        let call_result = match tokio::time::timeout(
+            upstream_timeout.duration(),
+            upstream.call_v1_internal_with_headers(method, &access_token, gemini_body, query, extra_headers.clone(), Some(account_id.as_str()), upstream_timeout.request_override(actual_stream)),
+        )
+        .await {
+            Ok(Ok(r)) => r,
+            Ok(Err(e)) => {
                 last_error = e.clone();
                 debug!("Request failed on attempt {}/{}: {}", attempt + 1, max_attempts, e);
                 continue;
             }
+            Err(_) => return upstream_timeout.error_response(TimeoutKind::Total),
         };
 
         // [NEW] 记录端点降级日志到 debug 文件
@@ -791,7 +808,7 @@ pub async fn handle_messages(
                     "upstream_url": upstream_url,
                 });
                 let gemini_stream = debug_logger::wrap_reqwest_stream_with_debug(
-                    Box::pin(response.bytes_stream()),
+                    upstream_timeout.wrap_idle_stream(Box::pin(response.bytes_stream())),
                     debug_cfg.clone(),
                     trace_id.clone(),
                     "upstream_response",
@@ -843,6 +860,9 @@ pub async fn handle_messages(
                             retry_this_account = true;
                             break;
                         }
+                        Ok(None) if upstream_timeout.idle_fired() => {
+                            return upstream_timeout.error_response(TimeoutKind::StreamIdle);
+                        }
                         Ok(None) => {
                             tracing::warn!("[{}] Stream ended during peek (Empty Response), retrying...", trace_id);
                             last_error = "Empty response stream during peek".to_string();
@@ -876,6 +896,15 @@ pub async fn handle_messages(
 
                         // 判断客户端期望的格式
                         if client_wants_stream {
+                            // [NEW] 空闲期间注入 Claude ping 事件，防止中间代理断开长连接
+                            let ping_interval = std::time::Duration::from_secs(
+                                state.experimental.read().await.stream_ping_interval_secs,
+                            );
+                            let combined_stream = crate::proxy::mappers::claude::with_ping_keepalive(
+                                Box::pin(upstream_timeout.append_idle_error(combined_stream)),
+                                ping_interval,
+                            );
+
                             // 客户端本就要 Stream，直接返回 SSE
                             return Response::builder()
                                 .status(StatusCode::OK)
@@ -892,7 +921,19 @@ pub async fn handle_messages(
                             // 客户端要非 Stream，需要收集完整响应并转换为 JSON
                             use crate::proxy::mappers::claude::collect_stream_to_json;
                             
-                            match collect_stream_to_json(combined_stream).await {
+                            let collected = tokio::time::timeout(
+                                upstream_timeout.remaining(),
+                                collect_stream_to_json(combined_stream),
+                            )
+                            .await;
+                            let collected = match collected {
+                                Ok(_) if upstream_timeout.idle_fired() => {
+                                    return upstream_timeout.error_response(TimeoutKind::StreamIdle);
+                                }
+                                Ok(r) => r,
+                                Err(_) => return upstream_timeout.error_response(TimeoutKind::Total),
+                            };
+                            match collected {
                                 Ok(full_response) => {
                                     info!("[{}] ✓ Stream collected and converted to JSON", trace_id);
                                     return Response::builder()
@@ -905,7 +946,9 @@ pub async fn handle_messages(
                                         .unwrap();
                                 }
                                 Err(e) => {
-                                    return (StatusCode::INTERNAL_SERVER_ERROR, format!("Stream collection error: {}", e)).into_response();
+                                    return ApiError::internal(format!("Stream collection error: {}", e))
+                                        .claude()
+                                        .into_response();
                                 }
                             }
                         }
@@ -919,9 +962,14 @@ pub async fn handle_messages(
                 }
             } else {
                 // 处理非流式响应
-                let bytes = match response.bytes().await {
-                    Ok(b) => b,
-                    Err(e) => return (StatusCode::BAD_GATEWAY, format!("Failed to read body: {}", e)).into_response(),
+                let bytes = match tokio::time::timeout(upstream_timeout.remaining(), response.bytes()).await {
+                    Ok(Ok(b)) => b,
+                    Ok(Err(e)) => {
+                        return ApiError::new(ApiErrorCode::UpstreamError, format!("Failed to read body: {}", e))
+                            .claude()
+                            .into_response()
+                    }
+                    Err(_) => return upstream_timeout.error_response(TimeoutKind::Total),
                 };
                 
                 // Debug print
@@ -931,7 +979,11 @@ pub async fn handle_messages(
 
                 let gemini_resp: Value = match serde_json::from_slice(&bytes) {
                     Ok(v) => v,
-                    Err(e) => return (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)).into_response(),
+                    Err(e) => {
+                        return ApiError::new(ApiErrorCode::UpstreamError, format!("Parse error: {}", e))
+                            .claude()
+                            .into_response()
+                    }
                 };
 
                 // 解包 response 字段（v1internal 格式）
@@ -940,7 +992,7 @@ pub async fn handle_messages(
                 // 转换为 Gemini Response 结构
                 let gemini_response: crate::proxy::mappers::claude::models::GeminiResponse = match serde_json::from_value(raw.clone()) {
                     Ok(r) => r,
-                    Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, format!("Convert error: {}", e)).into_response(),
+                    Err(e) => return ApiError::internal(format!("Convert error: {}", e)).claude().into_response(),
                 };
                 
                 // Determine context limit based on model
@@ -959,7 +1011,7 @@ pub async fn handle_messages(
                     request_with_mapped.messages.len(), // [NEW v4.0.0] Pass message count for rewind detection
                 ) {
                     Ok(r) => r,
-                    Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, format!("Transform error: {}", e)).into_response(),
+                    Err(e) => return ApiError::internal(format!("Transform error: {}", e)).claude().into_response(),
                 };
 
                 // [Optimization] 记录闭环日志：消耗情况
@@ -990,6 +1042,9 @@ pub async fn handle_messages(
         // 2. 获取错误文本并转移 Response 所有权
         let error_text = response.text().await.unwrap_or_else(|_| format!("HTTP {}", status));
         last_error = format!("HTTP {}: {}", status_code, error_text);
+        let upstream_error = parse_upstream_error(status_code, &error_text, retry_after.as_deref());
+        last_upstream = Some(upstream_error.clone());
+        failover.mark_failed(&email, status_code, &error_text);
         debug!("[{}] Upstream Error Response: {}", trace_id, error_text);
         if debug_logger::is_enabled(&debug_cfg) {
             let payload = json!({
@@ -1011,7 +1066,7 @@ pub async fn handle_messages(
         // 3. 标记限流状态(用于 UI 显示) - 使用异步版本以支持实时配额刷新
         // 🆕 传入实际使用的模型,实现模型级别限流,避免不同模型配额互相影响
         if status_code == 429 || status_code == 529 || status_code == 503 || status_code == 500 {
-            token_manager.mark_rate_limited_async(&email, status_code, retry_after.as_deref(), &error_text, Some(&request_with_mapped.model)).await;
+            token_manager.mark_rate_limited_async(&email, status_code, retry_after.as_deref(), &upstream_error.body, Some(&request_with_mapped.model)).await;
         }
 
         // 4. 处理 400 错误 (Thinking 签名失效 或 块顺序错误)
@@ -1150,6 +1205,13 @@ pub async fn handle_messages(
             } else {
                 tracing::warn!("[Claude] Account {} marked as forbidden due to 403", email);
             }
+
+            // [NEW] 连续硬失败达到阈值时自动禁用反代
+            if let Some(class) =
+                crate::proxy::token_manager::classify_hard_failure(status_code, &error_text)
+            {
+                token_manager.record_hard_failure(&account_id, class).await;
+            }
         }
 
         // 确定重试策略
@@ -1173,6 +1235,7 @@ pub async fn handle_messages(
                         "type": "error",
                         "error": {
                             "type": "invalid_request_error",
+                            "code": ApiErrorCode::ContextTooLong.as_str(),
                             "message": "Prompt is too long (server-side context limit reached).",
                             "suggestion": "Please: 1) Executive '/compact' in Claude Code 2) Reduce conversation history 3) Switch to gemini-1.5-pro (2M context limit)"
                         }
@@ -1182,105 +1245,51 @@ pub async fn handle_messages(
 
             // 不可重试的错误，直接返回
             error!("[{}] Non-retryable error {}: {}", trace_id, status_code, error_text);
-            return (status, [
-                ("X-Account-Email", email.as_str()),
-                ("X-Mapped-Model", request_with_mapped.model.as_str())
-            ], error_text).into_response();
+            return upstream_error
+                .to_api_error()
+                .with_header("X-Account-Email", &email)
+                .with_header("X-Mapped-Model", &request_with_mapped.model)
+                .claude()
+                .into_response();
         }
     }
     
     
+    // [FIX] Include X-Mapped-Model in exhaustion error
+    // 错误码按最后一次上游状态推断 (429 -> upstream_quota_exhausted)
+    let mut err = ApiError::from_status(
+        last_status,
+        format!("All {} attempts failed. Last status: {}. Error: {}", max_attempts, last_status, last_error),
+    );
+    // [FIX] 403 时返回 503，避免 Claude Code 客户端退出到登录页
+    if last_status.as_u16() == 403 {
+        err = err.with_status(StatusCode::SERVICE_UNAVAILABLE);
+    }
     if let Some(email) = last_email {
-        // [FIX] Include X-Mapped-Model in exhaustion error
-        let mut headers = HeaderMap::new();
-        headers.insert("X-Account-Email", header::HeaderValue::from_str(&email).unwrap());
-        if let Some(model) = last_mapped_model {
-             if let Ok(v) = header::HeaderValue::from_str(&model) {
-                headers.insert("X-Mapped-Model", v);
-             }
-        }
-
-        let error_type = match last_status.as_u16() {
-            400 => "invalid_request_error",
-            401 => "authentication_error",
-            403 => "permission_error",
-            429 => "rate_limit_error",
-            529 => "overloaded_error",
-            _ => "api_error",
-        };
-
-        // [FIX] 403 时返回 503，避免 Claude Code 客户端退出到登录页
-        let response_status = if last_status.as_u16() == 403 {
-            StatusCode::SERVICE_UNAVAILABLE
-        } else {
-            last_status
-        };
-
-        (response_status, headers, Json(json!({
-            "type": "error",
-            "error": {
-                "id": "err_retry_exhausted",
-                "type": error_type,
-                "message": format!("All {} attempts failed. Last status: {}. Error: {}", max_attempts, last_status, last_error)
-            }
-        }))).into_response()
-    } else {
-        // Fallback if no email (e.g. mapping error before token)
-        let mut headers = HeaderMap::new();
-        if let Some(model) = last_mapped_model {
-             if let Ok(v) = header::HeaderValue::from_str(&model) {
-                headers.insert("X-Mapped-Model", v);
-             }
-        }
-
-        let error_type = match last_status.as_u16() {
-            400 => "invalid_request_error",
-            401 => "authentication_error",
-            403 => "permission_error",
-            429 => "rate_limit_error",
-            529 => "overloaded_error",
-            _ => "api_error",
-        };
-
-        // [FIX] 403 时返回 503，避免 Claude Code 客户端退出到登录页
-        let response_status = if last_status.as_u16() == 403 {
-            StatusCode::SERVICE_UNAVAILABLE
-        } else {
-            last_status
-        };
-
-        (response_status, headers, Json(json!({
-            "type": "error",
-            "error": {
-                "id": "err_retry_exhausted",
-                "type": error_type,
-                "message": format!("All {} attempts failed. Last status: {}. Error: {}", max_attempts, last_status, last_error)
-            }
-        }))).into_response()
+        err = err.with_header("X-Account-Email", &email);
+    }
+    if let Some(model) = last_mapped_model {
+        err = err.with_header("X-Mapped-Model", &model);
     }
+    if let Some(upstream) = last_upstream {
+        err = upstream.annotate(err);
+    }
+    err.claude().into_response()
 }
 
 /// 列出可用模型
-pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoResponse {
-    use crate::proxy::common::model_mapping::get_all_dynamic_models;
-
-    let model_ids = get_all_dynamic_models(
-        &state.custom_mapping,
-    ).await;
-
-    let data: Vec<_> = model_ids.into_iter().map(|id| {
-        json!({
-            "id": id,
-            "object": "model",
-            "created": 1706745600,
-            "owned_by": "antigravity"
-        })
-    }).collect();
-
-    Json(json!({
-        "object": "list",
-        "data": data
-    }))
+pub async fn handle_list_models(
+    State(state): State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<super::openai::ListModelsQuery>,
+) -> impl IntoResponse {
+    Json(
+        super::common::cached_model_list(
+            &state,
+            crate::proxy::model_list_cache::ModelListKind::OpenAi,
+            query.refresh,
+        )
+        .await,
+    )
 }
 
 /// 计算 tokens (占位符)
@@ -1293,7 +1302,15 @@ pub async fn handle_count_tokens(
     let zai_enabled = zai.enabled && !matches!(zai.dispatch_mode, crate::proxy::ZaiDispatchMode::Off);
 
     if zai_enabled {
-        return crate::proxy::providers::zai_anthropic::forward_anthropic_json(
+        // [NEW] z.ai 计数结果与账号无关，按请求体缓存
+        let cache = crate::proxy::response_cache::ResponseCache::global();
+        let cache_key = crate::proxy::response_cache::CacheKey::new("claude/count_tokens", &body);
+        if let Some(hit) = cache.get(&cache_key) {
+            return hit.into_response();
+        }
+        let generation = cache.generation();
+
+        let response = crate::proxy::providers::zai_anthropic::forward_anthropic_json(
             &state,
             axum::http::Method::POST,
             "/v1/messages/count_tokens",
@@ -1302,6 +1319,30 @@ pub async fn handle_count_tokens(
             0, // [NEW v4.0.0] Tokens count doesn't need rewind detection
         )
         .await;
+        if response.status() != StatusCode::OK {
+            return response;
+        }
+
+        let (parts, body) = response.into_parts();
+        let bytes = match axum::body::to_bytes(body, 1024 * 1024).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                return ApiError::from_status(StatusCode::BAD_GATEWAY, e.to_string())
+                    .claude()
+                    .into_response()
+            }
+        };
+        if let Ok(value) = serde_json::from_slice::<Value>(&bytes) {
+            cache.store(
+                cache_key,
+                crate::proxy::response_cache::CachedResponse {
+                    body: value,
+                    headers: Vec::new(),
+                },
+                generation,
+            );
+        }
+        return Response::from_parts(parts, Body::from(bytes));
     }
 
     Json(json!({
@@ -1324,6 +1365,43 @@ mod tests {
 }
 */
 
+/// 对 thinking.budget_tokens 应用 Auto/Passthrough/Custom 策略 (支持按模型覆盖)
+/// - Auto: 超过配置值时截断到配置值
+/// - Custom: 固定为配置值
+/// - Passthrough: 原样转发
+fn apply_claude_thinking_budget(
+    request: &mut ClaudeRequest,
+    config: &crate::proxy::config::ThinkingBudgetConfig,
+) {
+    use crate::proxy::config::ThinkingBudgetMode;
+
+    let Some(thinking) = request.thinking.as_mut() else {
+        return;
+    };
+    if thinking.type_ != "enabled" {
+        return;
+    }
+    let (mode, limit) = match config.override_for(&request.model) {
+        Some(ov) => (ov.mode.clone(), ov.custom_value),
+        None => (config.mode.clone(), config.custom_value),
+    };
+    let original = thinking.budget_tokens;
+    thinking.budget_tokens = match mode {
+        ThinkingBudgetMode::Passthrough => original,
+        ThinkingBudgetMode::Custom => Some(limit),
+        ThinkingBudgetMode::Auto => original.map(|b| b.min(limit)),
+    };
+    if thinking.budget_tokens != original {
+        tracing::debug!(
+            "[Claude-Thinking] {:?} mode: budget_tokens {:?} -> {:?} for model {}",
+            mode,
+            original,
+            thinking.budget_tokens,
+            request.model
+        );
+    }
+}
+
 // ===== 后台任务检测辅助函数 =====
 
 /// 后台任务类型
@@ -1722,6 +1800,8 @@ async fn try_compress_with_summary(
         output_config: None,
         size: None,
         quality: None,
+        stop_sequences: None,
+        tool_choice: None,
     };
     
     debug!("[{}] [Layer-3] Calling {} for summary generation", trace_id, INTERNAL_BACKGROUND_TASK);
@@ -1786,5 +1866,58 @@ async fn try_compress_with_summary(
         output_config: original_request.output_config.clone(),
         size: original_request.size.clone(),
         quality: original_request.quality.clone(),
+        stop_sequences: original_request.stop_sequences.clone(),
+        tool_choice: original_request.tool_choice.clone(),
     })
 }
+
+#[cfg(test)]
+mod thinking_budget_tests {
+    use super::*;
+    use crate::proxy::config::{ThinkingBudgetConfig, ThinkingBudgetMode};
+
+    fn request_with_budget(budget: Option<u32>) -> ClaudeRequest {
+        serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5-thinking",
+            "max_tokens": 128000,
+            "messages": [{ "role": "user", "content": "hi" }],
+            "thinking": { "type": "enabled", "budget_tokens": budget }
+        }))
+        .unwrap()
+    }
+
+    fn config(mode: ThinkingBudgetMode, custom_value: u32) -> ThinkingBudgetConfig {
+        ThinkingBudgetConfig {
+            mode,
+            custom_value,
+            ..Default::default()
+        }
+    }
+
+    fn budget_of(req: &ClaudeRequest) -> Option<u32> {
+        req.thinking.as_ref().and_then(|t| t.budget_tokens)
+    }
+
+    #[test]
+    fn test_auto_caps_budget_to_configured_value() {
+        let mut req = request_with_budget(Some(100000));
+        apply_claude_thinking_budget(&mut req, &config(ThinkingBudgetMode::Auto, 32000));
+        assert_eq!(budget_of(&req), Some(32000));
+
+        // 未超过上限时保持原值
+        let mut req = request_with_budget(Some(8000));
+        apply_claude_thinking_budget(&mut req, &config(ThinkingBudgetMode::Auto, 32000));
+        assert_eq!(budget_of(&req), Some(8000));
+    }
+
+    #[test]
+    fn test_custom_and_passthrough_modes() {
+        let mut req = request_with_budget(Some(100000));
+        apply_claude_thinking_budget(&mut req, &config(ThinkingBudgetMode::Custom, 4096));
+        assert_eq!(budget_of(&req), Some(4096));
+
+        let mut req = request_with_budget(Some(100000));
+        apply_claude_thinking_budget(&mut req, &config(ThinkingBudgetMode::Passthrough, 4096));
+        assert_eq!(budget_of(&req), Some(100000));
+    }
+}
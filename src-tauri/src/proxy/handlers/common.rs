@@ -3,6 +3,7 @@ use tracing::{debug, info};
 use axum::{http::StatusCode, response::{IntoResponse, Response}, Json, extract::State};
 use serde_json::{json, Value};
 use crate::proxy::server::AppState;
+use crate::proxy::model_list_cache::{CacheLookup, ModelListKind};
 
 // ===== 统一重试与退避策略 =====
 
@@ -140,6 +141,63 @@ pub fn should_rotate_account(status_code: u16) -> bool {
     }
 }
 
+/// [NEW] 故障转移原因 (写入 failover_events)；不会触发账号轮换的状态码返回 None
+pub fn failover_reason(status_code: u16, error_text: &str) -> Option<&'static str> {
+    if !should_rotate_account(status_code) {
+        return None;
+    }
+    Some(match status_code {
+        403 => crate::proxy::token_manager::classify_hard_failure(status_code, error_text)
+            .unwrap_or("validation_required"),
+        429 => "rate_limited",
+        401 => "unauthorized",
+        _ => "upstream_error",
+    })
+}
+
+/// [NEW] 账号故障转移记录器 (每个请求的重试循环一个)
+/// 上一次尝试因账号级错误失败，且下一次尝试选中了其他账号时，向 proxy_db 写入一条记录
+#[derive(Debug, Default)]
+pub struct FailoverTracker {
+    /// (失败账号, 原因)
+    pending: Option<(String, &'static str)>,
+}
+
+impl FailoverTracker {
+    /// 本次尝试失败后调用
+    pub fn mark_failed(&mut self, email: &str, status_code: u16, error_text: &str) {
+        self.pending = failover_reason(status_code, error_text).map(|reason| (email.to_string(), reason));
+    }
+
+    /// 每次尝试选中账号后调用，发生账号切换时持久化故障转移事件
+    pub fn on_account_selected(&mut self, email: &str, model: &str) {
+        let Some(event) = self.take_event(email, model) else {
+            return;
+        };
+        info!("[Failover] {} -> {} ({})", event.from_account, event.to_account, event.reason);
+        // 同步 SQLite 写入放到阻塞线程池，避免占用请求所在的异步线程
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = crate::modules::proxy_db::save_failover_event(&event) {
+                tracing::debug!("Failed to save failover event: {}", e);
+            }
+        });
+    }
+
+    fn take_event(&mut self, email: &str, model: &str) -> Option<crate::modules::proxy_db::FailoverEvent> {
+        let (from, reason) = self.pending.take()?;
+        if from == email {
+            return None;
+        }
+        Some(crate::modules::proxy_db::FailoverEvent {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            from_account: from,
+            to_account: email.to_string(),
+            reason: reason.to_string(),
+            model: Some(model.to_string()),
+        })
+    }
+}
+
 /// Detects model capabilities and configuration
 /// POST /v1/models/detect
 pub async fn handle_detect_model(
@@ -187,3 +245,288 @@ pub async fn handle_detect_model(
 
     Json(response).into_response()
 }
+
+/// 模型由哪个上游实际提供 (用于 /v1/models 的 `owned_by`)
+fn model_owner(mapped_model: &str, zai: &crate::proxy::ZaiConfig) -> &'static str {
+    use crate::proxy::ZaiDispatchMode;
+
+    if !mapped_model.starts_with("claude-") || !zai.enabled {
+        return "antigravity";
+    }
+    match zai.dispatch_mode {
+        ZaiDispatchMode::Exclusive => "zai",
+        ZaiDispatchMode::Pooled => "antigravity,zai",
+        ZaiDispatchMode::Off | ZaiDispatchMode::Fallback => "antigravity",
+    }
+}
+
+/// 已知的上下文窗口大小
+fn known_context_window(mapped_model: &str) -> Option<u64> {
+    if mapped_model.starts_with("claude-") {
+        Some(200_000)
+    } else if mapped_model.starts_with("gemini-") {
+        Some(crate::proxy::mappers::claude::utils::get_context_limit_for_model(mapped_model) as u64)
+    } else {
+        None
+    }
+}
+
+/// 合并 Gemini 原生模型、custom_mapping 键、Claude 别名以及 z.ai 模型，生成 OpenAI 风格的模型列表
+///
+/// `refresh` 为 true 时强制重新拉取 z.ai 模型列表 (忽略缓存)
+pub async fn build_model_list(state: &AppState, refresh: bool) -> Value {
+    use crate::proxy::common::model_mapping::{explain_model_route, get_all_dynamic_models};
+
+    let zai = state.zai.read().await.clone();
+    let model_ids = get_all_dynamic_models(&state.custom_mapping).await;
+    let mapping = state.custom_mapping.read().await.clone();
+
+    let mut seen = std::collections::HashSet::new();
+    let mut data: Vec<Value> = Vec::new();
+    for id in model_ids {
+        // 通配符 / 正则规则不是可选择的模型名
+        if id.contains('*') || id.starts_with(crate::proxy::common::model_mapping::REGEX_RULE_PREFIX) {
+            continue;
+        }
+        let mapped = explain_model_route(&id, &mapping).target;
+        let mut entry = json!({
+            "id": id,
+            "object": "model",
+            "created": 1706745600,
+            "owned_by": model_owner(&mapped, &zai),
+        });
+        if let Some(window) = known_context_window(&mapped) {
+            entry["context_window"] = json!(window);
+        }
+        seen.insert(id);
+        data.push(entry);
+    }
+
+    if zai.enabled && !matches!(zai.dispatch_mode, crate::proxy::ZaiDispatchMode::Off) {
+        let upstream_proxy = state.upstream_proxy.read().await.clone();
+        let zai_models = crate::proxy::providers::zai_anthropic::list_zai_models(
+            &zai,
+            Some(upstream_proxy),
+            refresh,
+        )
+        .await;
+        for m in zai_models {
+            if !seen.insert(m.id.clone()) {
+                continue;
+            }
+            let mut entry = json!({
+                "id": m.id,
+                "object": "model",
+                "created": 1706745600,
+                "owned_by": "zai",
+            });
+            if let Some(window) = m.context_window {
+                entry["context_window"] = json!(window);
+            }
+            data.push(entry);
+        }
+    }
+
+    json!({
+        "object": "list",
+        "data": data
+    })
+}
+
+/// 带缓存的模型列表: TTL 内直接返回缓存；过期时返回旧数据并在后台刷新
+///
+/// `force_refresh` 为 true 时跳过缓存同步重建 (同时强制刷新 z.ai 模型列表)
+pub async fn cached_model_list(state: &AppState, kind: ModelListKind, force_refresh: bool) -> Value {
+    let cache = state.model_list_cache.clone();
+    if !force_refresh {
+        match cache.lookup(kind) {
+            CacheLookup::Fresh(value) => return value,
+            CacheLookup::Stale(value) => {
+                if cache.begin_refresh(kind) {
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        let generation = state.model_list_cache.generation();
+                        let fresh = build_model_list_for(&state, kind, false).await;
+                        state.model_list_cache.store(kind, fresh, generation);
+                        state.model_list_cache.end_refresh(kind);
+                        debug!("[Model-List] Refreshed {:?} model list in background", kind);
+                    });
+                }
+                return value;
+            }
+            CacheLookup::Miss => {}
+        }
+    }
+
+    let generation = cache.generation();
+    let value = build_model_list_for(state, kind, force_refresh).await;
+    cache.store(kind, value.clone(), generation);
+    value
+}
+
+async fn build_model_list_for(state: &AppState, kind: ModelListKind, refresh: bool) -> Value {
+    match kind {
+        ModelListKind::OpenAi => build_model_list(state, refresh).await,
+        ModelListKind::Gemini => super::gemini::build_gemini_model_list(state).await,
+    }
+}
+
+// ===== 流式背压 =====
+
+/// 背压中断时结束响应体的错误信息前缀，监控中间件据此识别中断原因
+pub const BACKPRESSURE_ABORT_MESSAGE: &str = "Client read too slowly";
+/// 背压中断在请求日志中的错误分类
+pub const BACKPRESSURE_ABORT_CLASS: &str = "backpressure_abort";
+
+/// 在上游读取任务与客户端写出之间插入容量为 `capacity` 的有界通道。
+/// 客户端读取过慢时通道写满，上游读取在 `send().await` 处暂停；
+/// 持续写满超过 `timeout` (0 表示不限) 时调用 `on_abort` 并以错误结束响应体，使连接被中断
+pub fn with_backpressure<S, E>(
+    stream: S,
+    capacity: usize,
+    timeout: Duration,
+    on_abort: impl FnOnce() + Send + 'static,
+) -> impl futures::Stream<Item = Result<bytes::Bytes, E>> + Send
+where
+    S: futures::Stream<Item = Result<bytes::Bytes, E>> + Send + 'static,
+    E: From<String> + Send + 'static,
+{
+    use futures::StreamExt;
+
+    let (tx, rx) = tokio::sync::mpsc::channel(capacity.max(1));
+    let abort = tokio_util::sync::CancellationToken::new();
+    let abort_reader = abort.clone();
+
+    tokio::spawn(async move {
+        let mut stream = Box::pin(stream);
+        while let Some(item) = stream.next().await {
+            let sent = if timeout.is_zero() {
+                tx.send(item).await.is_ok()
+            } else {
+                match tokio::time::timeout(timeout, tx.send(item)).await {
+                    Ok(result) => result.is_ok(),
+                    Err(_) => {
+                        on_abort();
+                        abort.cancel();
+                        return;
+                    }
+                }
+            };
+            // 客户端已断开，停止读取上游
+            if !sent {
+                return;
+            }
+        }
+    });
+
+    futures::stream::unfold((rx, abort_reader, false), move |(mut rx, abort, done)| async move {
+        if done {
+            return None;
+        }
+        tokio::select! {
+            biased;
+            _ = abort.cancelled() => Some((
+                Err(E::from(format!(
+                    "{}: stream buffer stayed full for {} ms",
+                    BACKPRESSURE_ABORT_MESSAGE,
+                    timeout.as_millis()
+                ))),
+                (rx, abort, true),
+            )),
+            item = rx.recv() => item.map(|item| (item, (rx, abort, false))),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::{ZaiConfig, ZaiDispatchMode};
+
+    #[test]
+    fn test_model_owner_follows_zai_dispatch_mode() {
+        let mut zai = ZaiConfig::default();
+        assert_eq!(model_owner("claude-sonnet-4-5", &zai), "antigravity");
+
+        zai.enabled = true;
+        zai.dispatch_mode = ZaiDispatchMode::Exclusive;
+        assert_eq!(model_owner("claude-sonnet-4-5", &zai), "zai");
+        assert_eq!(model_owner("gemini-3-flash", &zai), "antigravity");
+
+        zai.dispatch_mode = ZaiDispatchMode::Pooled;
+        assert_eq!(model_owner("claude-opus-4-6-thinking", &zai), "antigravity,zai");
+    }
+
+    #[test]
+    fn test_failover_reason() {
+        assert_eq!(failover_reason(403, "PERMISSION_DENIED"), Some("permission_denied"));
+        assert_eq!(failover_reason(403, "VALIDATION_REQUIRED"), Some("validation_required"));
+        assert_eq!(failover_reason(429, "RESOURCE_EXHAUSTED"), Some("rate_limited"));
+        assert_eq!(failover_reason(400, "bad request"), None);
+        assert_eq!(failover_reason(503, "unavailable"), None);
+    }
+
+    #[test]
+    fn test_failover_tracker_only_records_account_change() {
+        let mut tracker = FailoverTracker::default();
+        assert!(tracker.take_event("a@example.com", "gemini-2.5-pro").is_none());
+
+        // 同一账号重试不算故障转移
+        tracker.mark_failed("a@example.com", 429, "RESOURCE_EXHAUSTED");
+        assert!(tracker.take_event("a@example.com", "gemini-2.5-pro").is_none());
+
+        // 不轮换账号的错误不记录
+        tracker.mark_failed("a@example.com", 503, "unavailable");
+        assert!(tracker.take_event("b@example.com", "gemini-2.5-pro").is_none());
+
+        tracker.mark_failed("a@example.com", 403, "PERMISSION_DENIED");
+        let event = tracker.take_event("b@example.com", "gemini-2.5-pro").unwrap();
+        assert_eq!(
+            (event.from_account.as_str(), event.to_account.as_str(), event.reason.as_str()),
+            ("a@example.com", "b@example.com", "permission_denied")
+        );
+    }
+
+    #[test]
+    fn test_known_context_window() {
+        assert_eq!(known_context_window("claude-sonnet-4-5"), Some(200_000));
+        assert_eq!(known_context_window("gemini-3-pro-high"), Some(2_097_152));
+        assert_eq!(known_context_window("gpt-4"), None);
+    }
+
+    #[tokio::test]
+    async fn test_backpressure_passes_through_when_client_keeps_up() {
+        use futures::StreamExt;
+        let source = futures::stream::iter(
+            (0..10).map(|i| Ok::<_, String>(bytes::Bytes::from(i.to_string()))),
+        );
+        let out: Vec<_> = with_backpressure(source, 2, Duration::from_millis(500), || {})
+            .collect()
+            .await;
+        assert_eq!(out.len(), 10);
+        assert!(out.iter().all(|r| r.is_ok()));
+    }
+
+    #[tokio::test]
+    async fn test_backpressure_aborts_slow_client() {
+        use futures::StreamExt;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let aborted = Arc::new(AtomicBool::new(false));
+        let flag = aborted.clone();
+        let source = futures::stream::iter(
+            (0..100).map(|i| Ok::<_, String>(bytes::Bytes::from(i.to_string()))),
+        );
+        let out = with_backpressure(source, 4, Duration::from_millis(50), move || {
+            flag.store(true, Ordering::SeqCst)
+        });
+        futures::pin_mut!(out);
+
+        // 客户端迟迟不读取，通道持续写满
+        sleep(Duration::from_millis(200)).await;
+        assert!(aborted.load(Ordering::SeqCst));
+        assert!(out.next().await.unwrap().is_err());
+        assert!(out.next().await.is_none());
+    }
+}
@@ -9,19 +9,84 @@ use serde_json::{json, Value};
 use tracing::{debug, error, info};
 
 use crate::proxy::common::client_adapter::CLIENT_ADAPTERS;
+use crate::proxy::config::TimeoutRouteClass;
 use crate::proxy::debug_logger;
+use crate::proxy::error::{ApiError, ApiErrorCode};
+use crate::proxy::mappers::error_classifier::{parse_upstream_error, UpstreamError};
 use crate::proxy::handlers::common::{
-    apply_retry_strategy, determine_retry_strategy, should_rotate_account, RetryStrategy,
+    apply_retry_strategy, determine_retry_strategy, should_rotate_account, FailoverTracker,
+    RetryStrategy,
+};
+use crate::proxy::mappers::gemini::image_validator::{ImageValidator, ImageViolation};
+use crate::proxy::mappers::gemini::schema_validator::{
+    collect_function_schemas, validate_function_calls,
 };
 use crate::proxy::mappers::gemini::{unwrap_response, wrap_request};
 use crate::proxy::server::AppState;
 use crate::proxy::session_manager::SessionManager;
+use crate::proxy::timeouts::TimeoutKind;
 use crate::proxy::upstream::client::mask_email;
 use axum::http::HeaderMap;
 use tokio::time::Duration; // [NEW] Adapter Registry
 
 const MAX_RETRY_ATTEMPTS: usize = 3;
 
+/// 校验响应中的 functionCall 参数；不合规时计数并告警，严格模式下返回 502
+async fn check_function_calls(
+    monitor: &crate::proxy::monitor::ProxyMonitor,
+    experimental: &tokio::sync::RwLock<crate::proxy::config::ExperimentalConfig>,
+    schemas: &std::collections::HashMap<String, Value>,
+    response: &Value,
+) -> Result<(), ApiError> {
+    let violations = validate_function_calls(response, schemas);
+    if violations.is_empty() {
+        return Ok(());
+    }
+    let summary = violations
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(" | ");
+    for _ in &violations {
+        monitor.record_function_schema_violation();
+    }
+    tracing::warn!("[Gemini] Function call args do not match declared schema: {}", summary);
+    if experimental.read().await.strict_function_schema_validation {
+        return Err(ApiError::from_status(
+            StatusCode::BAD_GATEWAY,
+            format!("Function call arguments do not match declared schema: {}", summary),
+        )
+        .gemini());
+    }
+    Ok(())
+}
+
+/// 图片校验失败: 400，details 中以 google.rpc.BadRequest 列出每张图片的位置与原因
+fn image_violations_error(violations: &[ImageViolation]) -> ApiError {
+    let summary = violations
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join("; ");
+    let field_violations: Vec<Value> = violations
+        .iter()
+        .map(|v| {
+            json!({
+                "field": v.field(),
+                "description": v.reason,
+                "contentIndex": v.content_index,
+                "partIndex": v.part_index,
+            })
+        })
+        .collect();
+    ApiError::invalid_request(format!("Invalid image input: {}", summary))
+        .with_detail(json!({
+            "@type": "type.googleapis.com/google.rpc.BadRequest",
+            "fieldViolations": field_violations,
+        }))
+        .gemini()
+}
+
 /// 处理 generateContent 和 streamGenerateContent
 /// 路径参数: model_name, method (e.g. "gemini-pro", "generateContent")
 pub async fn handle_generate(
@@ -29,7 +94,7 @@ pub async fn handle_generate(
     Path(model_action): Path<String>,
     headers: HeaderMap,          // [NEW] Extract headers for adapter detection
     Json(mut body): Json<Value>, // 改为 mut 以支持修复提示词注入
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     // 解析 model:method
     let (model_name, method) = if let Some((m, action)) = model_action.rsplit_once(':') {
         (m.to_string(), action.to_string())
@@ -55,10 +120,11 @@ pub async fn handle_generate(
 
     // 1. 验证方法
     if method != "generateContent" && method != "streamGenerateContent" {
-        return Err((
+        return Err(ApiError::from_status(
             StatusCode::BAD_REQUEST,
             format!("Unsupported method: {}", method),
-        ));
+        )
+        .gemini());
     }
     if debug_logger::is_enabled(&debug_cfg) {
         let original_payload = json!({
@@ -76,8 +142,28 @@ pub async fn handle_generate(
             &original_payload,
         )
         .await;
+        debug_logger::write_request_capture(&debug_cfg, &trace_id, &original_payload).await;
     }
     let client_wants_stream = method == "streamGenerateContent";
+    // [NEW] 记录声明的函数参数 schema，用于校验非流式响应中的 functionCall
+    let function_schemas = collect_function_schemas(&body);
+    // [NEW] 转发前校验 inlineData 图片 (MIME 白名单 / 大小)，按需缩放超出尺寸的 JPEG/PNG
+    let image_validator = ImageValidator::from_experimental(&*state.experimental.read().await);
+    let (checked_body, image_check) = tokio::task::spawn_blocking(move || {
+        let result = image_validator.validate(&mut body);
+        (body, result)
+    })
+    .await
+    .map_err(|e| ApiError::internal(format!("Image validation task failed: {}", e)).gemini())?;
+    body = checked_body;
+    match image_check {
+        Ok(0) => {}
+        Ok(resized) => info!("[{}] Downscaled {} oversized image(s)", trace_id, resized),
+        Err(violations) => {
+            tracing::warn!("[{}] Rejected {} invalid image(s)", trace_id, violations.len());
+            return Err(image_violations_error(&violations));
+        }
+    }
     // [AUTO-CONVERSION] 强制内部流式化
     let force_stream_internally = !client_wants_stream;
     let is_stream = client_wants_stream || force_stream_internally;
@@ -94,6 +180,8 @@ pub async fn handle_generate(
 
     let mut last_error = String::new();
     let mut last_email: Option<String> = None;
+    let mut last_upstream: Option<UpstreamError> = None;
+    let mut failover = FailoverTracker::default();
 
     for attempt in 0..max_attempts {
         // 3. 模型路由解析
@@ -143,14 +231,19 @@ pub async fn handle_generate(
         {
             Ok(t) => t,
             Err(e) => {
-                return Err((
-                    StatusCode::SERVICE_UNAVAILABLE,
-                    format!("Token error: {}", e),
-                ));
+                return Err(ApiError::from_token_error(e).gemini());
             }
         };
 
+        // [NEW] 账号级并发限制 (排队超时返回 429)
+        if let Err(resp) =
+            crate::proxy::concurrency::acquire_account_slot(&token_manager, &account_id).await
+        {
+            return Ok(resp);
+        }
+
         last_email = Some(email.clone());
+        failover.on_account_selected(&email, &config.final_model);
         info!("✓ Using account: {} (type: {})", email, config.request_type);
 
         // 5. 包装请求 (project injection)
@@ -195,19 +288,28 @@ pub async fn handle_generate(
             );
         }
 
-        let call_result = match upstream
-            .call_v1_internal_with_headers(
+        // [NEW] 按路由/模型解析生效超时: 流式为空闲超时，非流式为总耗时
+        let upstream_timeout = crate::proxy::timeouts::resolve(
+            TimeoutRouteClass::Chat,
+            &[model_name.as_str(), mapped_model.as_str()],
+        );
+
+        let call_result = match tokio::time::timeout(
+            upstream_timeout.duration(),
+            upstream.call_v1_internal_with_headers(
                 upstream_method,
                 &access_token,
                 wrapped_body,
                 query_string,
                 extra_headers.clone(),
                 Some(account_id.as_str()),
-            )
-            .await
+                upstream_timeout.request_override(is_stream),
+            ),
+        )
+        .await
         {
-            Ok(r) => r,
-            Err(e) => {
+            Ok(Ok(r)) => r,
+            Ok(Err(e)) => {
                 last_error = e.clone();
                 debug!(
                     "Gemini Request failed on attempt {}/{}: {}",
@@ -217,6 +319,7 @@ pub async fn handle_generate(
                 );
                 continue;
             }
+            Err(_) => return Ok(upstream_timeout.error_response(TimeoutKind::Total)),
         };
 
         // [NEW] 记录端点降级日志到 debug 文件
@@ -256,6 +359,8 @@ pub async fn handle_generate(
         let upstream_url = response.url().to_string();
         let status = response.status();
         if status.is_success() {
+            // [NEW] 请求成功，清除该账号的连续硬失败计数
+            token_manager.clear_hard_failures(&account_id);
             // 6. 响应处理
             if is_stream {
                 use axum::body::Body;
@@ -274,7 +379,7 @@ pub async fn handle_generate(
                     "upstream_url": upstream_url,
                 });
                 let mut response_stream = debug_logger::wrap_reqwest_stream_with_debug(
-                    Box::pin(response.bytes_stream()),
+                    upstream_timeout.wrap_idle_stream(Box::pin(response.bytes_stream())),
                     debug_cfg.clone(),
                     trace_id.clone(),
                     "upstream_response",
@@ -306,6 +411,9 @@ pub async fn handle_generate(
                         last_error = format!("Stream error: {}", e);
                         retry_gemini = true;
                     }
+                    Ok(None) if upstream_timeout.idle_fired() => {
+                        return Ok(upstream_timeout.error_response(TimeoutKind::StreamIdle));
+                    }
                     Ok(None) => {
                         tracing::warn!("[Gemini] Stream ended immediately, retrying...");
                         last_error = "Empty response".to_string();
@@ -413,6 +521,19 @@ pub async fn handle_generate(
                 };
 
                 if client_wants_stream {
+                    // [NEW] 有界缓冲: 客户端读取过慢时暂停读取上游，持续阻塞超时则中断连接
+                    let (buffer_chunks, backpressure_timeout_ms) = {
+                        let experimental = state.experimental.read().await;
+                        (experimental.stream_buffer_chunks, experimental.stream_backpressure_timeout_ms)
+                    };
+                    let monitor = state.monitor.clone();
+                    let (abort_email, abort_model) = (email.clone(), mapped_model.clone());
+                    let stream = crate::proxy::handlers::common::with_backpressure(
+                        upstream_timeout.append_idle_error(stream),
+                        buffer_chunks,
+                        Duration::from_millis(backpressure_timeout_ms),
+                        move || monitor.record_backpressure_abort(&mask_email(&abort_email), &abort_model),
+                    );
                     let body = Body::from_stream(stream);
                     return Ok(Response::builder()
                         .header("Content-Type", "text/event-stream")
@@ -427,13 +548,32 @@ pub async fn handle_generate(
                 } else {
                     // Collect to JSON
                     use crate::proxy::mappers::gemini::collector::collect_stream_to_json;
-                    match collect_stream_to_json(Box::pin(stream), &s_id).await {
+                    let collected = match tokio::time::timeout(
+                        upstream_timeout.remaining(),
+                        collect_stream_to_json(Box::pin(stream), &s_id),
+                    )
+                    .await
+                    {
+                        Ok(_) if upstream_timeout.idle_fired() => {
+                            return Ok(upstream_timeout.error_response(TimeoutKind::StreamIdle));
+                        }
+                        Ok(r) => r,
+                        Err(_) => return Ok(upstream_timeout.error_response(TimeoutKind::Total)),
+                    };
+                    match collected {
                         Ok(gemini_resp) => {
                             info!(
                                 "[{}] ✓ Stream collected and converted to JSON (Gemini)",
                                 session_id
                             );
                             let unwrapped = unwrap_response(&gemini_resp);
+                            check_function_calls(
+                                &state.monitor,
+                                &state.experimental,
+                                &function_schemas,
+                                &unwrapped,
+                            )
+                            .await?;
                             return Ok((
                                 StatusCode::OK,
                                 [
@@ -446,20 +586,27 @@ pub async fn handle_generate(
                         }
                         Err(e) => {
                             error!("Stream collection error: {}", e);
-                            return Ok((
-                                StatusCode::INTERNAL_SERVER_ERROR,
-                                format!("Stream collection error: {}", e),
-                            )
-                                .into_response());
+                            return Err(ApiError::internal(format!(
+                                "Stream collection error: {}",
+                                e
+                            ))
+                            .gemini());
                         }
                     }
                 }
             }
 
-            let mut gemini_resp: Value = response
-                .json()
-                .await
-                .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)))?;
+            let mut gemini_resp: Value =
+                match tokio::time::timeout(upstream_timeout.remaining(), response.json()).await {
+                    Ok(r) => r.map_err(|e| {
+                        ApiError::from_status(
+                            StatusCode::BAD_GATEWAY,
+                            format!("Parse error: {}", e),
+                        )
+                        .gemini()
+                    })?,
+                    Err(_) => return Ok(upstream_timeout.error_response(TimeoutKind::Total)),
+                };
 
             // [FIX #1522] Inject Tool ID into Non-streaming Response
             crate::proxy::mappers::gemini::wrapper::inject_ids_to_response(
@@ -500,6 +647,13 @@ pub async fn handle_generate(
             }
 
             let unwrapped = unwrap_response(&gemini_resp);
+            check_function_calls(
+                &state.monitor,
+                &state.experimental,
+                &function_schemas,
+                &unwrapped,
+            )
+            .await?;
             return Ok((
                 StatusCode::OK,
                 [
@@ -523,6 +677,9 @@ pub async fn handle_generate(
             .await
             .unwrap_or_else(|_| format!("HTTP {}", status_code));
         last_error = format!("HTTP {}: {}", status_code, error_text);
+        let upstream_error = parse_upstream_error(status_code, &error_text, retry_after.as_deref());
+        last_upstream = Some(upstream_error.clone());
+        failover.mark_failed(&email, status_code, &error_text);
         if debug_logger::is_enabled(&debug_cfg) {
             let payload = json!({
                 "kind": "upstream_response_error",
@@ -546,6 +703,13 @@ pub async fn handle_generate(
             .await;
         }
 
+        // [NEW] 连续硬失败 (403 权限错误) 达到阈值时自动禁用反代
+        if let Some(class) =
+            crate::proxy::token_manager::classify_hard_failure(status_code, &error_text)
+        {
+            token_manager.record_hard_failure(&account_id, class).await;
+        }
+
         // 确定重试策略
         let strategy = determine_retry_strategy(status_code, &error_text, false);
         let trace_id = format!("gemini_{}", session_id);
@@ -607,43 +771,43 @@ pub async fn handle_generate(
             "Gemini Upstream non-retryable error {}: {}",
             status_code, error_text
         );
-        return Ok((
-            status,
-            [
-                ("X-Account-Email", email.as_str()),
-                ("X-Mapped-Model", mapped_model.as_str()),
-            ],
-            // [FIX] Return JSON error
-            Json(json!({
-                "error": {
-                    "code": status_code,
-                    "message": error_text,
-                    "status": "UPSTREAM_ERROR"
-                }
-            })),
-        )
-            .into_response());
+        // [FIX] Return JSON error
+        return Err(upstream_error
+            .to_api_error()
+            .with_header("X-Account-Email", &email)
+            .with_header("X-Mapped-Model", &mapped_model)
+            .gemini());
     }
 
+    let mut err = ApiError::new(
+        ApiErrorCode::UpstreamQuotaExhausted,
+        format!("All accounts exhausted. Last error: {}", last_error),
+    );
     if let Some(email) = last_email {
-        Ok((
-            StatusCode::TOO_MANY_REQUESTS,
-            [("X-Account-Email", email)],
-            format!("All accounts exhausted. Last error: {}", last_error),
-        )
-            .into_response())
-    } else {
-        Ok((
-            StatusCode::TOO_MANY_REQUESTS,
-            format!("All accounts exhausted. Last error: {}", last_error),
-        )
-            .into_response())
+        err = err.with_header("X-Account-Email", &email);
+    }
+    if let Some(upstream) = last_upstream {
+        err = upstream.annotate(err);
     }
+    Err(err.gemini())
 }
 
 pub async fn handle_list_models(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+    axum::extract::Query(query): axum::extract::Query<super::openai::ListModelsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    Ok(Json(
+        super::common::cached_model_list(
+            &state,
+            crate::proxy::model_list_cache::ModelListKind::Gemini,
+            query.refresh,
+        )
+        .await,
+    ))
+}
+
+/// 构建 Gemini 格式的模型列表 (与 /v1/models 使用同一份动态模型集合)
+pub(crate) async fn build_gemini_model_list(state: &AppState) -> Value {
     use crate::proxy::common::model_mapping::get_all_dynamic_models;
 
     // 获取所有动态模型列表（与 /v1/models 一致）
@@ -668,7 +832,7 @@ pub async fn handle_list_models(
         })
         .collect();
 
-    Ok(Json(json!({ "models": models })))
+    json!({ "models": models })
 }
 
 pub async fn handle_get_model(Path(model_name): Path<String>) -> impl IntoResponse {
@@ -680,20 +844,35 @@ pub async fn handle_get_model(Path(model_name): Path<String>) -> impl IntoRespon
 
 pub async fn handle_count_tokens(
     State(state): State<AppState>,
-    Path(_model_name): Path<String>,
-    Json(_body): Json<Value>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+    Path(model_name): Path<String>,
+    Json(body): Json<Value>,
+) -> Result<axum::response::Response, ApiError> {
+    // [NEW] 结果与账号无关，按模型 + 请求体缓存
+    let cache = crate::proxy::response_cache::ResponseCache::global();
+    let cache_key = crate::proxy::response_cache::CacheKey::new(
+        "gemini/count_tokens",
+        &json!({ "model": model_name, "body": body }),
+    );
+    if let Some(hit) = cache.get(&cache_key) {
+        return Ok(hit.into_response());
+    }
+    let generation = cache.generation();
+
     let model_group = "gemini";
     let (_access_token, _project_id, _, _, _wait_ms) = state
         .token_manager
         .get_token(model_group, false, None, "gemini")
         .await
-        .map_err(|e| {
-            (
-                StatusCode::SERVICE_UNAVAILABLE,
-                format!("Token error: {}", e),
-            )
-        })?;
-
-    Ok(Json(json!({"totalTokens": 0})))
+        .map_err(|e| ApiError::from_token_error(e).gemini())?;
+
+    let result = json!({"totalTokens": 0});
+    cache.store(
+        cache_key,
+        crate::proxy::response_cache::CachedResponse {
+            body: result.clone(),
+            headers: Vec::new(),
+        },
+        generation,
+    );
+    Ok(Json(result).into_response())
 }
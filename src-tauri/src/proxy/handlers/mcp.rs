@@ -195,7 +195,11 @@ async fn handle_vision_get(state: AppState, headers: HeaderMap) -> Response {
     if !state.zai_vision_mcp.has_session(&session_id).await {
         return (StatusCode::BAD_REQUEST, "Invalid Mcp-Session-Id").into_response();
     }
+    keepalive_sse(&session_id)
+}
 
+/// GET 请求: 服务端不主动推送消息，只维持 SSE 连接
+fn keepalive_sse(session_id: &str) -> Response {
     let ping_stream = IntervalStream::new(tokio::time::interval(Duration::from_secs(15))).map(|_| {
         Ok::<axum::response::sse::Event, std::convert::Infallible>(
             axum::response::sse::Event::default()
@@ -212,7 +216,7 @@ async fn handle_vision_get(state: AppState, headers: HeaderMap) -> Response {
         )
         .into_response();
 
-    if let Ok(v) = HeaderValue::from_str(&session_id) {
+    if let Ok(v) = HeaderValue::from_str(session_id) {
         resp.headers_mut().insert("mcp-session-id", v);
     }
     resp
@@ -227,27 +231,29 @@ async fn handle_vision_delete(state: AppState, headers: HeaderMap) -> Response {
     StatusCode::OK.into_response()
 }
 
-async fn handle_vision_post(state: AppState, headers: HeaderMap, body: Body) -> Response {
-    let collected = match to_bytes(body, 100 * 1024 * 1024).await {
-        Ok(b) => b,
-        Err(e) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                format!("Failed to read request body: {}", e),
-            )
-                .into_response();
-        }
-    };
+/// 读取并解析 JSON-RPC 请求体
+async fn read_jsonrpc_request(body: Body) -> Result<Value, Response> {
+    let collected = to_bytes(body, 100 * 1024 * 1024).await.map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Failed to read request body: {}", e),
+        )
+            .into_response()
+    })?;
+
+    serde_json::from_slice(&collected).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            axum::Json(jsonrpc_error(Value::Null, -32700, format!("Parse error: {}", e))),
+        )
+            .into_response()
+    })
+}
 
-    let request_json: Value = match serde_json::from_slice(&collected) {
+async fn handle_vision_post(state: AppState, headers: HeaderMap, body: Body) -> Response {
+    let request_json = match read_jsonrpc_request(body).await {
         Ok(v) => v,
-        Err(e) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                axum::Json(jsonrpc_error(Value::Null, -32700, format!("Parse error: {}", e))),
-            )
-                .into_response();
-        }
+        Err(resp) => return resp,
     };
 
     let id = request_json.get("id").cloned().unwrap_or(Value::Null);
@@ -396,3 +402,147 @@ pub async fn handle_zai_mcp_server(
         _ => StatusCode::METHOD_NOT_ALLOWED.into_response(),
     }
 }
+
+/// [NEW] 聚合 MCP 端点: 合并 z.ai 远程 MCP、内置 Vision 工具与本地工具
+pub async fn handle_abv_mcp(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    method: Method,
+    body: Body,
+) -> Response {
+    match method {
+        Method::GET => {
+            let Some(session_id) = mcp_session_id(&headers) else {
+                return (StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id").into_response();
+            };
+            if !state.mcp_aggregator.has_session(&session_id).await {
+                return (StatusCode::BAD_REQUEST, "Invalid Mcp-Session-Id").into_response();
+            }
+            keepalive_sse(&session_id)
+        }
+        Method::DELETE => {
+            let Some(session_id) = mcp_session_id(&headers) else {
+                return (StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id").into_response();
+            };
+            state.mcp_aggregator.remove_session(&session_id).await;
+            StatusCode::OK.into_response()
+        }
+        Method::POST => handle_abv_post(state, headers, body).await,
+        _ => StatusCode::METHOD_NOT_ALLOWED.into_response(),
+    }
+}
+
+async fn handle_abv_post(state: AppState, headers: HeaderMap, body: Body) -> Response {
+    let request_json = match read_jsonrpc_request(body).await {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
+    let id = request_json.get("id").cloned().unwrap_or(Value::Null);
+    let method = request_json
+        .get("method")
+        .and_then(|m| m.as_str())
+        .unwrap_or_default();
+    if method.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            axum::Json(jsonrpc_error(id, -32600, "Invalid Request: missing method")),
+        )
+            .into_response();
+    }
+
+    // 通知 (无 id) 不需要响应
+    if request_json.get("id").is_none() || request_json.get("id") == Some(&Value::Null) {
+        return StatusCode::ACCEPTED.into_response();
+    }
+
+    if is_initialize_request(&request_json) {
+        let session_id = state.mcp_aggregator.create_session().await;
+        let requested_protocol = request_json
+            .get("params")
+            .and_then(|p| p.get("protocolVersion"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("2024-11-05");
+        let result = json!({
+            "protocolVersion": requested_protocol,
+            "capabilities": { "tools": {} },
+            "serverInfo": {
+                "name": "antigravity-manager",
+                "version": env!("CARGO_PKG_VERSION"),
+            }
+        });
+
+        let mut resp = (StatusCode::OK, axum::Json(jsonrpc_result(id, result))).into_response();
+        if let Ok(v) = HeaderValue::from_str(&session_id) {
+            resp.headers_mut().insert("mcp-session-id", v);
+        }
+        return resp;
+    }
+
+    let Some(session_id) = mcp_session_id(&headers) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            axum::Json(jsonrpc_error(id, -32000, "Bad Request: missing Mcp-Session-Id")),
+        )
+            .into_response();
+    };
+    if !state.mcp_aggregator.has_session(&session_id).await {
+        return (
+            StatusCode::NOT_FOUND,
+            axum::Json(jsonrpc_error(id, -32000, "Bad Request: invalid Mcp-Session-Id")),
+        )
+            .into_response();
+    }
+
+    let ctx = crate::proxy::mcp_aggregator::McpContext {
+        zai: state.zai.read().await.clone(),
+        upstream_proxy: state.upstream_proxy.read().await.clone(),
+        timeout_secs: state.request_timeout,
+        token_manager: state.token_manager.clone(),
+    };
+
+    match method {
+        "ping" => (StatusCode::OK, axum::Json(jsonrpc_result(id, json!({})))).into_response(),
+        "tools/list" => {
+            let tools = state.mcp_aggregator.list_tools(&ctx).await;
+            (
+                StatusCode::OK,
+                axum::Json(jsonrpc_result(id, json!({ "tools": tools }))),
+            )
+                .into_response()
+        }
+        "tools/call" => {
+            let params = request_json.get("params").cloned().unwrap_or(Value::Null);
+            let Some(tool_name) = params.get("name").and_then(|v| v.as_str()) else {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    axum::Json(jsonrpc_error(id, -32602, "Missing params.name")),
+                )
+                    .into_response();
+            };
+            let arguments = params
+                .get("arguments")
+                .cloned()
+                .unwrap_or(Value::Object(Default::default()));
+
+            // 工具执行失败按 MCP 约定返回 isError 结果，而不是 JSON-RPC 错误
+            let result = match state.mcp_aggregator.call_tool(&ctx, tool_name, &arguments).await {
+                Ok(result) => result,
+                Err(e) => json!({
+                    "content": [ { "type": "text", "text": format!("Error: {}", e) } ],
+                    "isError": true
+                }),
+            };
+            (StatusCode::OK, axum::Json(jsonrpc_result(id, result))).into_response()
+        }
+        _ => (
+            StatusCode::BAD_REQUEST,
+            axum::Json(jsonrpc_error(
+                id,
+                -32601,
+                format!("Method not found: {}", method),
+            )),
+        )
+            .into_response(),
+    }
+}
@@ -11,13 +11,17 @@ use crate::proxy::mappers::openai::{
     transform_openai_request, transform_openai_response, OpenAIRequest,
 };
 // use crate::proxy::upstream::client::UpstreamClient; // 通过 state 获取
+use crate::proxy::config::TimeoutRouteClass;
+use crate::proxy::mappers::error_classifier::{parse_upstream_error, UpstreamError};
 use crate::proxy::debug_logger;
+use crate::proxy::timeouts::TimeoutKind;
 use crate::proxy::server::AppState;
 use crate::proxy::upstream::client::mask_email;
 
 const MAX_RETRY_ATTEMPTS: usize = 3;
 use super::common::{
-    apply_retry_strategy, determine_retry_strategy, should_rotate_account, RetryStrategy,
+    apply_retry_strategy, determine_retry_strategy, should_rotate_account, FailoverTracker,
+    RetryStrategy,
 };
 use crate::proxy::common::client_adapter::CLIENT_ADAPTERS; // [NEW] Adapter Registry
 use crate::proxy::session_manager::SessionManager;
@@ -28,7 +32,7 @@ pub async fn handle_chat_completions(
     State(state): State<AppState>,
     headers: HeaderMap, // [CHANGED] Extract headers
     Json(mut body): Json<Value>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     // [FIX] 保存原始请求体的完整副本，用于日志记录
     // 这确保了即使结构体定义遗漏字段，日志也能完整记录所有参数
     let original_body = body.clone();
@@ -82,8 +86,9 @@ pub async fn handle_chat_completions(
         }
     }
 
-    let mut openai_req: OpenAIRequest = serde_json::from_value(body)
-        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)))?;
+    let mut openai_req: OpenAIRequest = serde_json::from_value(body).map_err(|e| {
+        ApiError::from_status(StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)).openai()
+    })?;
 
     // Safety: Ensure messages is not empty
     if openai_req.messages.is_empty() {
@@ -102,6 +107,20 @@ pub async fn handle_chat_completions(
             });
     }
 
+    // [NEW] X-Count-Tokens-Only: true 时只计算 prompt token 数，不生成内容
+    if headers
+        .get("x-count-tokens-only")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("true"))
+    {
+        return count_prompt_tokens(&state, openai_req).await;
+    }
+
+    // [NEW] 规范化 image_url: 下载远程图片、校验大小 (超限返回 413 并指出内容块下标)
+    crate::proxy::mappers::openai::vision::prepare_image_parts(&mut openai_req, &state.upstream)
+        .await
+        .map_err(|e| e.into_api_error())?;
+
     let trace_id = format!("req_{}", chrono::Utc::now().timestamp_subsec_millis());
     info!(
         "[{}] OpenAI Chat Request: {} | {} messages | stream: {}",
@@ -127,6 +146,7 @@ pub async fn handle_chat_completions(
             &original_payload,
         )
         .await;
+        debug_logger::write_request_capture(&debug_cfg, &trace_id, &original_payload).await;
     }
 
     // [NEW] Detect Client Adapter
@@ -147,6 +167,8 @@ pub async fn handle_chat_completions(
 
     let mut last_error = String::new();
     let mut last_email: Option<String> = None;
+    let mut last_upstream: Option<UpstreamError> = None;
+    let mut failover = FailoverTracker::default();
 
     // 2. 模型路由解析 (移到循环外以支持在所有路径返回 X-Mapped-Model)
     let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
@@ -186,17 +208,21 @@ pub async fn handle_chat_completions(
             Ok(t) => t,
             Err(e) => {
                 // [FIX] Attach headers to error response for logging visibility
-                let headers = [("X-Mapped-Model", mapped_model.as_str())];
-                return Ok((
-                    StatusCode::SERVICE_UNAVAILABLE,
-                    headers,
-                    format!("Token error: {}", e),
-                )
-                    .into_response());
+                return Err(ApiError::from_token_error(e)
+                    .with_header("X-Mapped-Model", &mapped_model)
+                    .openai());
             }
         };
 
+        // [NEW] 账号级并发限制 (排队超时返回 429)
+        if let Err(resp) =
+            crate::proxy::concurrency::acquire_account_slot(&token_manager, &account_id).await
+        {
+            return Ok(resp);
+        }
+
         last_email = Some(email.clone());
+        failover.on_account_selected(&email, &mapped_model);
         info!("✓ Using account: {} (type: {})", email, config.request_type);
 
         // 4. 转换请求 (返回内容包含 session_id 和 message_count)
@@ -260,19 +286,28 @@ pub async fn handle_chat_completions(
             );
         }
 
-        let call_result = match upstream
-            .call_v1_internal_with_headers(
+        // [NEW] 按路由/模型解析生效超时: 流式为空闲超时，非流式为总耗时
+        let upstream_timeout = crate::proxy::timeouts::resolve(
+            TimeoutRouteClass::Chat,
+            &[openai_req.model.as_str(), mapped_model.as_str()],
+        );
+
+        let call_result = match tokio::time::timeout(
+            upstream_timeout.duration(),
+            upstream.call_v1_internal_with_headers(
                 method,
                 &access_token,
                 gemini_body,
                 query_string,
                 extra_headers.clone(),
                 Some(account_id.as_str()),
-            )
-            .await
+                upstream_timeout.request_override(actual_stream),
+            ),
+        )
+        .await
         {
-            Ok(r) => r,
-            Err(e) => {
+            Ok(Ok(r)) => r,
+            Ok(Err(e)) => {
                 last_error = e.clone();
                 debug!(
                     "OpenAI Request failed on attempt {}/{}: {}",
@@ -282,6 +317,7 @@ pub async fn handle_chat_completions(
                 );
                 continue;
             }
+            Err(_) => return Ok(upstream_timeout.error_response(TimeoutKind::Total)),
         };
 
         // [NEW] 记录端点降级日志到 debug 文件
@@ -338,7 +374,7 @@ pub async fn handle_chat_completions(
                     "upstream_url": upstream_url,
                 });
                 let gemini_stream = debug_logger::wrap_reqwest_stream_with_debug(
-                    Box::pin(response.bytes_stream()),
+                    upstream_timeout.wrap_idle_stream(Box::pin(response.bytes_stream())),
                     debug_cfg.clone(),
                     trace_id.clone(),
                     "upstream_response",
@@ -353,6 +389,7 @@ pub async fn handle_chat_completions(
                     openai_req.model.clone(),
                     session_id,
                     message_count,
+                    openai_req.wants_stream_usage(),
                 );
 
                 let mut first_data_chunk = None;
@@ -396,6 +433,9 @@ pub async fn handle_chat_completions(
                             retry_this_account = true;
                             break;
                         }
+                        Ok(None) if upstream_timeout.idle_fired() => {
+                            return Ok(upstream_timeout.error_response(TimeoutKind::StreamIdle));
+                        }
                         Ok(None) => {
                             tracing::warn!(
                                 "[OpenAI] Stream ended during peek (Empty Response), retrying..."
@@ -428,7 +468,7 @@ pub async fn handle_chat_completions(
 
                 if client_wants_stream {
                     // 客户端请求流式，返回 SSE
-                    let body = Body::from_stream(combined_stream);
+                    let body = Body::from_stream(upstream_timeout.append_idle_error(combined_stream));
                     return Ok(Response::builder()
                         .header("Content-Type", "text/event-stream")
                         .header("Cache-Control", "no-cache")
@@ -444,7 +484,20 @@ pub async fn handle_chat_completions(
                     // 收集流数据并聚合为 JSON
                     use crate::proxy::mappers::openai::collector::collect_stream_to_json;
 
-                    match collect_stream_to_json(Box::pin(combined_stream)).await {
+                    let collected = match tokio::time::timeout(
+                        upstream_timeout.remaining(),
+                        collect_stream_to_json(Box::pin(combined_stream)),
+                    )
+                    .await
+                    {
+                        Ok(_) if upstream_timeout.idle_fired() => {
+                            return Ok(upstream_timeout.error_response(TimeoutKind::StreamIdle));
+                        }
+                        Ok(r) => r,
+                        Err(_) => return Ok(upstream_timeout.error_response(TimeoutKind::Total)),
+                    };
+
+                    match collected {
                         Ok(full_response) => {
                             info!("[{}] ✓ Stream collected and converted to JSON", trace_id);
                             return Ok((
@@ -459,20 +512,27 @@ pub async fn handle_chat_completions(
                         }
                         Err(e) => {
                             error!("[{}] Stream collection error: {}", trace_id, e);
-                            return Ok((
-                                StatusCode::INTERNAL_SERVER_ERROR,
-                                format!("Stream collection error: {}", e),
-                            )
-                                .into_response());
+                            return Err(ApiError::internal(format!(
+                                "Stream collection error: {}",
+                                e
+                            ))
+                            .openai());
                         }
                     }
                 }
             }
 
-            let gemini_resp: Value = response
-                .json()
-                .await
-                .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)))?;
+            let gemini_resp: Value =
+                match tokio::time::timeout(upstream_timeout.remaining(), response.json()).await {
+                    Ok(r) => r.map_err(|e| {
+                        ApiError::from_status(
+                            StatusCode::BAD_GATEWAY,
+                            format!("Parse error: {}", e),
+                        )
+                        .openai()
+                    })?,
+                    Err(_) => return Ok(upstream_timeout.error_response(TimeoutKind::Total)),
+                };
 
             let openai_response =
                 transform_openai_response(&gemini_resp, Some(&session_id), message_count);
@@ -489,7 +549,7 @@ pub async fn handle_chat_completions(
 
         // 处理特定错误并重试
         let status_code = status.as_u16();
-        let _retry_after = response
+        let retry_after = response
             .headers()
             .get("Retry-After")
             .and_then(|h| h.to_str().ok())
@@ -499,6 +559,9 @@ pub async fn handle_chat_completions(
             .await
             .unwrap_or_else(|_| format!("HTTP {}", status_code));
         last_error = format!("HTTP {}: {}", status_code, error_text);
+        let upstream_error = parse_upstream_error(status_code, &error_text, retry_after.as_deref());
+        last_upstream = Some(upstream_error.clone());
+        failover.mark_failed(&email, status_code, &error_text);
 
         // [New] 打印错误报文日志
         tracing::error!(
@@ -539,8 +602,8 @@ pub async fn handle_chat_completions(
                 .mark_rate_limited_async(
                     &email,
                     status_code,
-                    _retry_after.as_deref(),
-                    &error_text,
+                    retry_after.as_deref(),
+                    &upstream_error.body,
                     Some(&mapped_model),
                 )
                 .await;
@@ -680,6 +743,13 @@ pub async fn handle_chat_completions(
                     if let Err(e) = token_manager.set_forbidden(&acc_id, &error_text).await {
                         tracing::error!("Failed to set forbidden status: {}", e);
                     }
+
+                    // [NEW] 连续硬失败达到阈值时自动禁用反代
+                    if let Some(class) =
+                        crate::proxy::token_manager::classify_hard_failure(status_code, &error_text)
+                    {
+                        token_manager.record_hard_failure(&acc_id, class).await;
+                    }
                 }
             }
 
@@ -701,40 +771,43 @@ pub async fn handle_chat_completions(
             "OpenAI Upstream non-retryable error {} on account {}: {}",
             status_code, email, error_text
         );
-        return Ok((
-            status,
-            [
-                ("X-Account-Email", email.as_str()),
-                ("X-Mapped-Model", mapped_model.as_str()),
-            ],
-            // [FIX] Return JSON error for better client compatibility
-            Json(json!({
-                "error": {
-                    "message": error_text,
-                    "type": "upstream_error",
-                    "code": status_code
-                }
-            })),
-        )
-            .into_response());
+        // [FIX] Return JSON error for better client compatibility
+        return Err(upstream_error
+            .to_api_error()
+            .with_header("X-Account-Email", &email)
+            .with_header("X-Mapped-Model", &mapped_model)
+            .openai());
     }
 
     // 所有尝试均失败
+    Err(exhausted_error(
+        last_email.as_deref(),
+        &mapped_model,
+        &last_error,
+        last_upstream.as_ref(),
+    ))
+}
+
+/// 所有账号均尝试失败时的错误 (OpenAI envelope，code = upstream_quota_exhausted)
+/// 附带最后一次上游错误的 Retry-After 与分类
+fn exhausted_error(
+    last_email: Option<&str>,
+    mapped_model: &str,
+    last_error: &str,
+    last_upstream: Option<&UpstreamError>,
+) -> ApiError {
+    let mut err = ApiError::new(
+        ApiErrorCode::UpstreamQuotaExhausted,
+        format!("All accounts exhausted. Last error: {}", last_error),
+    )
+    .with_header("X-Mapped-Model", mapped_model);
     if let Some(email) = last_email {
-        Ok((
-            StatusCode::TOO_MANY_REQUESTS,
-            [("X-Account-Email", email), ("X-Mapped-Model", mapped_model)],
-            format!("All accounts exhausted. Last error: {}", last_error),
-        )
-            .into_response())
-    } else {
-        Ok((
-            StatusCode::TOO_MANY_REQUESTS,
-            [("X-Mapped-Model", mapped_model)],
-            format!("All accounts exhausted. Last error: {}", last_error),
-        )
-            .into_response())
+        err = err.with_header("X-Account-Email", email);
+    }
+    if let Some(upstream) = last_upstream {
+        err = upstream.annotate(err);
     }
+    err.openai()
 }
 
 /// 处理 Legacy Completions API (/v1/completions)
@@ -1095,7 +1168,9 @@ pub async fn handle_completions(
     let mut openai_req: OpenAIRequest = match serde_json::from_value(body.clone()) {
         Ok(req) => req,
         Err(e) => {
-            return (StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)).into_response();
+            return ApiError::invalid_request(format!("Invalid request: {}", e))
+                .openai()
+                .into_response();
         }
     };
 
@@ -1115,6 +1190,13 @@ pub async fn handle_completions(
             });
     }
 
+    if let Err(e) =
+        crate::proxy::mappers::openai::vision::prepare_image_parts(&mut openai_req, &state.upstream)
+            .await
+    {
+        return e.into_api_error().into_response();
+    }
+
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
     let pool_size = token_manager.len();
@@ -1123,6 +1205,8 @@ pub async fn handle_completions(
 
     let mut last_error = String::new();
     let mut last_email: Option<String> = None;
+    let mut last_upstream: Option<UpstreamError> = None;
+    let mut failover = FailoverTracker::default();
 
     // 2. 模型路由解析 (移到循环外以支持在所有路径返回 X-Mapped-Model)
     let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
@@ -1166,16 +1250,21 @@ pub async fn handle_completions(
         {
             Ok(t) => t,
             Err(e) => {
-                return (
-                    StatusCode::SERVICE_UNAVAILABLE,
-                    [("X-Mapped-Model", mapped_model)],
-                    format!("Token error: {}", e),
-                )
+                return ApiError::from_token_error(e)
+                    .with_header("X-Mapped-Model", &mapped_model)
+                    .openai()
                     .into_response()
             }
         };
 
+        if let Err(resp) =
+            crate::proxy::concurrency::acquire_account_slot(&token_manager, &account_id).await
+        {
+            return resp;
+        }
+
         last_email = Some(email.clone());
+        failover.on_account_selected(&email, &mapped_model);
 
         info!("✓ Using account: {} (type: {})", email, config.request_type);
 
@@ -1203,18 +1292,28 @@ pub async fn handle_completions(
         };
         let query_string = if list_response { Some("alt=sse") } else { None };
 
-        let call_result = match upstream
-            .call_v1_internal(
+        // [NEW] 按路由/模型解析生效超时
+        let upstream_timeout = crate::proxy::timeouts::resolve(
+            TimeoutRouteClass::Chat,
+            &[openai_req.model.as_str(), mapped_model.as_str()],
+        );
+
+        let call_result = match tokio::time::timeout(
+            upstream_timeout.duration(),
+            upstream.call_v1_internal_with_headers(
                 method,
                 &access_token,
                 gemini_body,
                 query_string,
+                std::collections::HashMap::new(),
                 Some(account_id.as_str()),
-            )
-            .await
+                upstream_timeout.request_override(list_response),
+            ),
+        )
+        .await
         {
-            Ok(r) => r,
-            Err(e) => {
+            Ok(Ok(r)) => r,
+            Ok(Err(e)) => {
                 last_error = e.clone();
                 debug!(
                     "Codex Request failed on attempt {}/{}: {}",
@@ -1224,6 +1323,7 @@ pub async fn handle_completions(
                 );
                 continue;
             }
+            Err(_) => return upstream_timeout.error_response(TimeoutKind::Total),
         };
 
         let response = call_result.response;
@@ -1237,7 +1337,8 @@ pub async fn handle_completions(
                 use axum::response::Response;
                 use futures::StreamExt;
 
-                let gemini_stream = response.bytes_stream();
+                let gemini_stream =
+                    upstream_timeout.wrap_idle_stream(Box::pin(response.bytes_stream()));
 
                 // DECISION: Which stream to create?
                 // If client wants stream: give them what they asked (Legacy/Codex SSE).
@@ -1297,6 +1398,9 @@ pub async fn handle_completions(
                                 retry_this_account = true;
                                 break;
                             }
+                            Ok(None) if upstream_timeout.idle_fired() => {
+                                return upstream_timeout.error_response(TimeoutKind::StreamIdle);
+                            }
                             Ok(None) => {
                                 last_error = "Empty response stream".to_string();
                                 retry_this_account = true;
@@ -1325,7 +1429,7 @@ pub async fn handle_completions(
                         .header("Connection", "keep-alive")
                         .header("X-Account-Email", &email)
                         .header("X-Mapped-Model", &mapped_model)
-                        .body(Body::from_stream(combined_stream))
+                        .body(Body::from_stream(upstream_timeout.append_idle_error(combined_stream)))
                         .unwrap()
                         .into_response();
                 } else {
@@ -1339,6 +1443,7 @@ pub async fn handle_completions(
                         openai_req.model.clone(),
                         session_id,
                         message_count,
+                        false,
                     );
 
                     // Peek Logic (Repeated for safety/correctness on this stream type)
@@ -1374,6 +1479,9 @@ pub async fn handle_completions(
                                 retry_this_account = true;
                                 break;
                             }
+                            Ok(None) if upstream_timeout.idle_fired() => {
+                                return upstream_timeout.error_response(TimeoutKind::StreamIdle);
+                            }
                             Ok(None) => {
                                 last_error = "Empty internal stream".to_string();
                                 retry_this_account = true;
@@ -1397,7 +1505,19 @@ pub async fn handle_completions(
 
                     // Collect
                     use crate::proxy::mappers::openai::collector::collect_stream_to_json;
-                    match collect_stream_to_json(Box::pin(combined_stream)).await {
+                    let collected = match tokio::time::timeout(
+                        upstream_timeout.remaining(),
+                        collect_stream_to_json(Box::pin(combined_stream)),
+                    )
+                    .await
+                    {
+                        Ok(_) if upstream_timeout.idle_fired() => {
+                            return upstream_timeout.error_response(TimeoutKind::StreamIdle);
+                        }
+                        Ok(r) => r,
+                        Err(_) => return upstream_timeout.error_response(TimeoutKind::Total),
+                    };
+                    match collected {
                         Ok(chat_resp) => {
                             // NOW: Convert Chat Response -> Legacy Response (Same logic as below)
                             let choices = chat_resp.choices.iter().map(|c| {
@@ -1432,24 +1552,25 @@ pub async fn handle_completions(
                                 .into_response();
                         }
                         Err(e) => {
-                            return (
-                                StatusCode::INTERNAL_SERVER_ERROR,
-                                format!("Stream collection error: {}", e),
-                            )
+                            return ApiError::internal(format!("Stream collection error: {}", e))
+                                .openai()
                                 .into_response();
                         }
                     }
                 }
             }
 
-            let gemini_resp: Value = match response.json().await {
-                Ok(json) => json,
-                Err(e) => {
-                    return (
-                        StatusCode::BAD_GATEWAY,
-                        [("X-Mapped-Model", mapped_model.as_str())],
-                        format!("Parse error: {}", e),
-                    )
+            let gemini_resp: Value =
+                match tokio::time::timeout(upstream_timeout.remaining(), response.json()).await {
+                    Err(_) => return upstream_timeout.error_response(TimeoutKind::Total),
+                    Ok(Ok(json)) => json,
+                    Ok(Err(e)) => {
+                        return ApiError::new(
+                            ApiErrorCode::UpstreamError,
+                            format!("Parse error: {}", e),
+                        )
+                        .with_header("X-Mapped-Model", &mapped_model)
+                        .openai()
                         .into_response();
                 }
             };
@@ -1501,6 +1622,9 @@ pub async fn handle_completions(
             .await
             .unwrap_or_else(|_| format!("HTTP {}", status_code));
         last_error = format!("HTTP {}: {}", status_code, error_text);
+        let upstream_error = parse_upstream_error(status_code, &error_text, retry_after.as_deref());
+        last_upstream = Some(upstream_error.clone());
+        failover.mark_failed(&email, status_code, &error_text);
 
         tracing::error!(
             "[Codex-Upstream] Error Response {}: {}",
@@ -1515,7 +1639,7 @@ pub async fn handle_completions(
                     &email,
                     status_code,
                     retry_after.as_deref(),
-                    &error_text,
+                    &upstream_error.body,
                     Some(&mapped_model),
                 )
                 .await;
@@ -1529,57 +1653,498 @@ pub async fn handle_completions(
             continue;
         } else {
             // 不可重试
-            return (
-                status,
-                [
-                    ("X-Account-Email", email.as_str()),
-                    ("X-Mapped-Model", mapped_model.as_str()),
-                ],
-                error_text,
-            )
+            return upstream_error
+                .to_api_error()
+                .with_header("X-Account-Email", &email)
+                .with_header("X-Mapped-Model", &mapped_model)
+                .openai()
                 .into_response();
         }
     }
 
     // 所有尝试均失败
-    if let Some(email) = last_email {
-        (
-            StatusCode::TOO_MANY_REQUESTS,
-            [("X-Account-Email", email), ("X-Mapped-Model", mapped_model)],
-            format!("All accounts exhausted. Last error: {}", last_error),
-        )
-            .into_response()
-    } else {
-        (
-            StatusCode::TOO_MANY_REQUESTS,
-            [("X-Mapped-Model", mapped_model)],
-            format!("All accounts exhausted. Last error: {}", last_error),
+    exhausted_error(
+        last_email.as_deref(),
+        &mapped_model,
+        &last_error,
+        last_upstream.as_ref(),
+    )
+    .into_response()
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct ListModelsQuery {
+    /// 为 true 时强制刷新 z.ai 模型缓存
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+pub async fn handle_list_models(
+    State(state): State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<ListModelsQuery>,
+) -> impl IntoResponse {
+    Json(
+        super::common::cached_model_list(
+            &state,
+            crate::proxy::model_list_cache::ModelListKind::OpenAi,
+            query.refresh,
         )
-            .into_response()
+        .await,
+    )
+}
+
+/// 未指定 max_tokens 时用于估算补全长度的默认值
+const DEFAULT_ESTIMATED_COMPLETION_TOKENS: u32 = 1024;
+
+/// 本地粗略估算 OpenAI 请求的 prompt token 数 (上游 countTokens 不可用时的兜底)
+fn estimate_openai_prompt_tokens(request: &OpenAIRequest) -> u32 {
+    use crate::proxy::mappers::context_manager::estimate_tokens_from_str;
+    use crate::proxy::mappers::openai::{OpenAIContent, OpenAIContentBlock};
+
+    let mut total = 0u32;
+    for msg in &request.messages {
+        total += 4; // 每条消息的结构开销
+        match &msg.content {
+            Some(OpenAIContent::String(s)) => total += estimate_tokens_from_str(s),
+            Some(OpenAIContent::Array(blocks)) => {
+                for block in blocks {
+                    match block {
+                        OpenAIContentBlock::Text { text } => total += estimate_tokens_from_str(text),
+                        // 图片/音频按固定开销估算
+                        _ => total += 258,
+                    }
+                }
+            }
+            None => {}
+        }
+        if let Some(calls) = &msg.tool_calls {
+            let raw = serde_json::to_string(calls).unwrap_or_default();
+            total += estimate_tokens_from_str(&raw);
+        }
     }
+    if let Some(tools) = &request.tools {
+        let raw = serde_json::to_string(tools).unwrap_or_default();
+        total += estimate_tokens_from_str(&raw);
+    }
+    total
 }
 
-pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoResponse {
-    use crate::proxy::common::model_mapping::get_all_dynamic_models;
+/// Dry-run: POST /v1/chat/completions/estimate
+/// 走正常的模型路由与账号选择流程，但只计算 token 数，不转发生成请求
+pub async fn handle_chat_completions_estimate(
+    State(state): State<AppState>,
+    Json(body): Json<Value>,
+) -> Result<impl IntoResponse, ApiError> {
+    let openai_req: OpenAIRequest = serde_json::from_value(body).map_err(|e| {
+        ApiError::from_status(StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)).openai()
+    })?;
 
-    let model_ids = get_all_dynamic_models(&state.custom_mapping).await;
+    let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+        &openai_req.model,
+        &*state.custom_mapping.read().await,
+    );
+    let tools_val: Option<Vec<Value>> = openai_req.tools.clone();
+    let config = crate::proxy::mappers::common_utils::resolve_request_config(
+        &openai_req.model,
+        &mapped_model,
+        &tools_val,
+        None,
+        None,
+        None,
+    );
+    let session_id = SessionManager::extract_openai_session_id(&openai_req);
 
-    let data: Vec<_> = model_ids
-        .into_iter()
-        .map(|id| {
-            json!({
-                "id": id,
-                "object": "model",
-                "created": 1706745600,
-                "owned_by": "antigravity"
-            })
+    let (access_token, project_id, email, account_id, _wait_ms) = state
+        .token_manager
+        .get_token(&config.request_type, false, Some(&session_id), &mapped_model)
+        .await
+        .map_err(|e| ApiError::from_token_error(e).openai())?;
+
+    // Gemini 模型优先使用上游 countTokens，Claude 模型及失败时退回本地估算
+    let mut prompt_tokens: Option<u32> = None;
+    if !mapped_model.starts_with("claude-") {
+        let (gemini_body, _, _) = transform_openai_request(&openai_req, &project_id, &mapped_model);
+        let count_body = build_count_tokens_body(&gemini_body);
+        let upstream_timeout = crate::proxy::timeouts::resolve(
+            TimeoutRouteClass::CountTokens,
+            &[openai_req.model.as_str(), mapped_model.as_str()],
+        );
+        let counted = tokio::time::timeout(upstream_timeout.duration(), async {
+            let result = state
+                .upstream
+                .call_v1_internal("countTokens", &access_token, count_body, None, Some(account_id.as_str()))
+                .await?;
+            if !result.response.status().is_success() {
+                return Err(format!("countTokens returned {}", result.response.status()));
+            }
+            result.response.json::<Value>().await.map_err(|e| e.to_string())
         })
-        .collect();
+        .await;
+
+        match counted {
+            Ok(Ok(v)) => {
+                let raw = v.get("response").unwrap_or(&v);
+                prompt_tokens = raw
+                    .get("totalTokens")
+                    .and_then(|t| t.as_u64())
+                    .map(|t| t as u32);
+            }
+            Ok(Err(e)) => debug!("[Estimate] {}, using local estimation", e),
+            Err(_) => return Ok(upstream_timeout.error_response(TimeoutKind::Total)),
+        }
+    }
+    let prompt_tokens = prompt_tokens.unwrap_or_else(|| estimate_openai_prompt_tokens(&openai_req));
+    let estimated_completion_tokens = openai_req
+        .max_tokens
+        .unwrap_or(DEFAULT_ESTIMATED_COMPLETION_TOKENS);
+
+    Ok((
+        [
+            ("X-Account-Email", email),
+            ("X-Mapped-Model", mapped_model.clone()),
+        ],
+        Json(json!({
+            "model": mapped_model,
+            "prompt_tokens": prompt_tokens,
+            "estimated_completion_tokens": estimated_completion_tokens,
+            "total": prompt_tokens.saturating_add(estimated_completion_tokens),
+            "account_id": account_id,
+        })),
+    )
+        .into_response())
+}
+
+/// 由完整的生成请求构造 countTokens 请求体
+/// 保留 contents / systemInstruction / tools，与真实调用发送的 prompt 一致
+fn build_count_tokens_body(gemini_body: &Value) -> Value {
+    let inner = &gemini_body["request"];
+    let model = gemini_body["model"].as_str().unwrap_or_default();
+    let mut request = json!({
+        "model": format!("models/{}", model),
+        "contents": inner["contents"].clone(),
+    });
+    for key in ["systemInstruction", "tools", "toolConfig"] {
+        if let Some(value) = inner.get(key) {
+            request[key] = value.clone();
+        }
+    }
+    json!({ "request": request })
+}
+
+/// POST /v1/chat/completions/count_tokens
+/// 执行完整的 OpenAI→Gemini 转换 (含工具与图片) 后调用上游 countTokens，返回 {prompt_tokens}
+pub async fn handle_chat_completions_count_tokens(
+    State(state): State<AppState>,
+    Json(body): Json<Value>,
+) -> Result<Response, ApiError> {
+    let openai_req: OpenAIRequest = serde_json::from_value(body).map_err(|e| {
+        ApiError::from_status(StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)).openai()
+    })?;
+    count_prompt_tokens(&state, openai_req).await
+}
+
+/// 账号选择与错误映射与正常请求一致，只是不发起生成
+async fn count_prompt_tokens(
+    state: &AppState,
+    mut openai_req: OpenAIRequest,
+) -> Result<Response, ApiError> {
+    // [NEW] 结果与账号无关，按规范化请求体缓存，命中时跳过图片下载与上游调用
+    let cache = crate::proxy::response_cache::ResponseCache::global();
+    let cache_key = serde_json::to_value(&openai_req)
+        .ok()
+        .map(|v| crate::proxy::response_cache::CacheKey::new("openai/count_tokens", &v));
+    if let Some(hit) = cache_key.as_ref().and_then(|key| cache.get(key)) {
+        return Ok(hit.into_response());
+    }
+    let generation = cache.generation();
+
+    crate::proxy::mappers::openai::vision::prepare_image_parts(&mut openai_req, &state.upstream)
+        .await
+        .map_err(|e| e.into_api_error())?;
+
+    let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+        &openai_req.model,
+        &*state.custom_mapping.read().await,
+    );
+    let tools_val: Option<Vec<Value>> = openai_req.tools.clone();
+    let config = crate::proxy::mappers::common_utils::resolve_request_config(
+        &openai_req.model,
+        &mapped_model,
+        &tools_val,
+        None,
+        None,
+        None,
+    );
+    let session_id = SessionManager::extract_openai_session_id(&openai_req);
+
+    let (access_token, project_id, email, account_id, _wait_ms) = state
+        .token_manager
+        .get_token(&config.request_type, false, Some(&session_id), &mapped_model)
+        .await
+        .map_err(|e| {
+            ApiError::from_token_error(e)
+                .with_header("X-Mapped-Model", &mapped_model)
+                .openai()
+        })?;
+
+    let (gemini_body, _, _) = transform_openai_request(&openai_req, &project_id, &mapped_model);
+    let count_body = build_count_tokens_body(&gemini_body);
+    let upstream_timeout = crate::proxy::timeouts::resolve(
+        TimeoutRouteClass::CountTokens,
+        &[openai_req.model.as_str(), mapped_model.as_str()],
+    );
 
-    Json(json!({
-        "object": "list",
-        "data": data
-    }))
+    let counted = tokio::time::timeout(upstream_timeout.duration(), async {
+        let result = state
+            .upstream
+            .call_v1_internal("countTokens", &access_token, count_body, None, Some(account_id.as_str()))
+            .await
+            .map_err(|e| ApiError::from_status(StatusCode::BAD_GATEWAY, e))?;
+        let status = result.response.status();
+        if !status.is_success() {
+            let error_text = result.response.text().await.unwrap_or_default();
+            return Err(ApiError::from_status(status, error_text));
+        }
+        result
+            .response
+            .json::<Value>()
+            .await
+            .map_err(|e| ApiError::from_status(StatusCode::BAD_GATEWAY, e.to_string()))
+    })
+    .await;
+
+    let value = match counted {
+        Ok(Ok(v)) => v,
+        Ok(Err(e)) => {
+            return Err(e
+                .with_header("X-Account-Email", &email)
+                .with_header("X-Mapped-Model", &mapped_model)
+                .openai())
+        }
+        Err(_) => return Ok(upstream_timeout.error_response(TimeoutKind::Total)),
+    };
+    let raw = value.get("response").unwrap_or(&value);
+    let prompt_tokens = raw.get("totalTokens").and_then(|t| t.as_u64()).unwrap_or(0);
+    debug!(
+        "[Count-Tokens] {} -> {} prompt tokens (account: {})",
+        mapped_model,
+        prompt_tokens,
+        mask_email(&email)
+    );
+
+    if let Some(key) = cache_key {
+        cache.store(
+            key,
+            crate::proxy::response_cache::CachedResponse {
+                body: json!({ "prompt_tokens": prompt_tokens }),
+                headers: vec![("x-mapped-model", mapped_model.clone())],
+            },
+            generation,
+        );
+    }
+
+    Ok((
+        [
+            ("X-Account-Email", email),
+            ("X-Mapped-Model", mapped_model),
+        ],
+        Json(json!({ "prompt_tokens": prompt_tokens })),
+    )
+        .into_response())
+}
+
+/// OpenAI Embeddings API: POST /v1/embeddings
+/// 转换为 Gemini batchEmbedContents 请求
+pub async fn handle_embeddings(
+    State(state): State<AppState>,
+    Json(body): Json<Value>,
+) -> Result<impl IntoResponse, ApiError> {
+    use crate::proxy::mappers::openai::embeddings::{
+        get_embedding_batch_size, is_embedding_model, map_embedding_model,
+        merge_embedding_responses, transform_embedding_response, EmbeddingRequest,
+        DEFAULT_EMBEDDING_MODEL,
+    };
+
+    let request: EmbeddingRequest = serde_json::from_value(body).map_err(|e| {
+        ApiError::from_status(StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)).openai()
+    })?;
+
+    let requested_model = request
+        .model
+        .clone()
+        .unwrap_or_else(|| DEFAULT_EMBEDDING_MODEL.to_string());
+    let routed = crate::proxy::common::model_mapping::resolve_model_route(
+        &requested_model,
+        &*state.custom_mapping.read().await,
+    );
+    let model = map_embedding_model(&routed);
+    if !is_embedding_model(&model) {
+        return Err(ApiError::from_status(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Model '{}' is not an embedding model (e.g. use {} or text-embedding-004)",
+                requested_model, DEFAULT_EMBEDDING_MODEL
+            ),
+        )
+        .openai());
+    }
+
+    let base64_output = match request.encoding_format.as_deref() {
+        None | Some("float") => false,
+        Some("base64") => true,
+        Some(other) => {
+            return Err(ApiError::from_status(
+                StatusCode::BAD_REQUEST,
+                format!("Unsupported encoding_format '{}'", other),
+            )
+            .openai())
+        }
+    };
+    let dimensions = request.dimensions;
+    let inputs = request.input.into_vec();
+    if inputs.is_empty() {
+        return Err(ApiError::from_status(
+            StatusCode::BAD_REQUEST,
+            "'input' must not be empty".to_string(),
+        )
+        .openai());
+    }
+
+    // [NEW] 超出批次大小时拆分为多个并行上游请求，按原顺序合并
+    let batch_size = get_embedding_batch_size();
+    let batches: Vec<&[String]> = inputs.chunks(batch_size).collect();
+    info!(
+        "[Embeddings] model={} -> {}, inputs={}, batches={}",
+        requested_model,
+        model,
+        inputs.len(),
+        batches.len()
+    );
+
+    let results = futures::future::join_all(
+        batches
+            .iter()
+            .map(|batch| embed_batch(&state, batch, &model, dimensions)),
+    )
+    .await;
+
+    let mut upstream_resps = Vec::with_capacity(results.len());
+    let mut emails: Vec<String> = Vec::new();
+    for result in results {
+        let (gemini_resp, email) = match result {
+            Ok(r) => r,
+            Err(resp) => return Ok(resp),
+        };
+        upstream_resps.push(gemini_resp);
+        if !emails.contains(&email) {
+            emails.push(email);
+        }
+    }
+
+    let merged = merge_embedding_responses(&upstream_resps)
+        .map_err(|e| ApiError::from_status(StatusCode::BAD_GATEWAY, e).openai())?;
+    let openai_resp = transform_embedding_response(&merged, &model, &inputs, base64_output)
+        .map_err(|e| ApiError::from_status(StatusCode::BAD_GATEWAY, e).openai())?;
+
+    Ok((
+        StatusCode::OK,
+        [
+            ("X-Account-Email", emails.join(",")),
+            ("X-Mapped-Model", model.clone()),
+        ],
+        Json(openai_resp),
+    )
+        .into_response())
+}
+
+/// 单个批次的 batchEmbedContents 请求 (含账号轮换重试)，返回上游响应与所用账号
+/// 注: 并行批次各自持有账号许可 (不经请求作用域)，批次结束即释放
+async fn embed_batch(
+    state: &AppState,
+    inputs: &[String],
+    model: &str,
+    dimensions: Option<u32>,
+) -> Result<(Value, String), Response> {
+    use crate::proxy::mappers::openai::embeddings::build_embedding_request;
+
+    let token_manager = state.token_manager.clone();
+    let max_attempts = MAX_RETRY_ATTEMPTS
+        .min(token_manager.len().saturating_add(1))
+        .max(2);
+    let mut last_error = String::new();
+    let mut permit: Option<crate::proxy::concurrency::AccountPermit> = None;
+
+    for attempt in 0..max_attempts {
+        let (access_token, project_id, email, account_id, _wait_ms) = match token_manager
+            .get_token("text", attempt > 0, None, model)
+            .await
+        {
+            Ok(t) => t,
+            Err(e) => return Err(ApiError::from_token_error(e).openai().into_response()),
+        };
+
+        // 先释放上一次尝试的账号许可，再申请新账号的许可
+        drop(permit.take());
+        permit =
+            crate::proxy::concurrency::acquire_detached_slot(&token_manager, &account_id).await?;
+
+        let gemini_body = build_embedding_request(inputs, model, &project_id, dimensions);
+        let response = match state
+            .upstream
+            .call_v1_internal(
+                "batchEmbedContents",
+                &access_token,
+                gemini_body,
+                None,
+                Some(account_id.as_str()),
+            )
+            .await
+        {
+            Ok(r) => r.response,
+            Err(e) => {
+                last_error = format!("Network error: {}", e);
+                continue;
+            }
+        };
+
+        let status = response.status();
+        if !status.is_success() {
+            let err_text = response.text().await.unwrap_or_default();
+            let status_code = status.as_u16();
+            last_error = format!("Upstream error {}: {}", status, err_text);
+            if status_code == 429 || status_code == 503 || status_code == 500 {
+                tracing::warn!(
+                    "[Embeddings] Account {} rate limited/error ({}), rotating...",
+                    mask_email(&email),
+                    status_code
+                );
+                token_manager
+                    .mark_rate_limited_async(&email, status_code, None, &err_text, Some(model))
+                    .await;
+                continue;
+            }
+            return Err(ApiError::from_status(
+                StatusCode::from_u16(status_code).unwrap_or(StatusCode::BAD_GATEWAY),
+                last_error,
+            )
+            .openai()
+            .into_response());
+        }
+
+        let gemini_resp: Value = response.json().await.map_err(|e| {
+            ApiError::from_status(StatusCode::BAD_GATEWAY, format!("Parse error: {}", e))
+                .openai()
+                .into_response()
+        })?;
+        return Ok((gemini_resp, email));
+    }
+
+    Err(ApiError::new(
+        ApiErrorCode::UpstreamQuotaExhausted,
+        format!("All accounts exhausted. Last error: {}", last_error),
+    )
+    .openai()
+    .into_response())
 }
 
 /// OpenAI Images API: POST /v1/images/generations
@@ -1587,12 +2152,12 @@ pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoRespo
 pub async fn handle_images_generations(
     State(state): State<AppState>,
     Json(body): Json<Value>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     // 1. 解析请求参数
-    let prompt = body.get("prompt").and_then(|v| v.as_str()).ok_or((
-        StatusCode::BAD_REQUEST,
-        "Missing 'prompt' field".to_string(),
-    ))?;
+    let prompt = body
+        .get("prompt")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| ApiError::invalid_request("Missing 'prompt' field").openai())?;
 
     let model = body
         .get("model")
@@ -1657,6 +2222,10 @@ pub async fn handle_images_generations(
         .min(max_pool_size.saturating_add(1))
         .max(2);
 
+    // [NEW] 图像生成耗时较长，按 images 路由类别 / 模型解析总耗时超时
+    let upstream_timeout = crate::proxy::timeouts::resolve(TimeoutRouteClass::Images, &[model]);
+    let request_timeout = upstream_timeout.request_override(false);
+
     let mut tasks = Vec::new();
 
     for _ in 0..n {
@@ -1714,12 +2283,14 @@ pub async fn handle_images_generations(
                 });
 
                 match upstream
-                    .call_v1_internal(
+                    .call_v1_internal_with_headers(
                         "generateContent",
                         &access_token,
                         gemini_body,
                         None,
+                        std::collections::HashMap::new(),
                         Some(account_id.as_str()),
+                        request_timeout,
                     )
                     .await
                 {
@@ -1775,8 +2346,13 @@ pub async fn handle_images_generations(
     let mut errors: Vec<String> = Vec::new();
     let mut used_email: Option<String> = None;
 
+    let abort_handles: Vec<_> = tasks.iter().map(|t| t.abort_handle()).collect();
     for (idx, task) in tasks.into_iter().enumerate() {
-        match task.await {
+        let Ok(joined) = tokio::time::timeout(upstream_timeout.remaining(), task).await else {
+            abort_handles.iter().for_each(|h| h.abort());
+            return Ok(upstream_timeout.error_response(TimeoutKind::Total));
+        };
+        match joined {
             Ok(result) => match result {
                 Ok((gemini_resp, email_used)) => {
                     // Capture the email from the first successful task for logging
@@ -1844,7 +2420,7 @@ pub async fn handle_images_generations(
             StatusCode::BAD_GATEWAY
         };
 
-        return Err((status, error_msg));
+        return Err(ApiError::from_status(status, error_msg).openai());
     }
 
     // 部分成功时记录警告
@@ -1884,7 +2460,7 @@ pub async fn handle_images_generations(
 pub async fn handle_images_edits(
     State(state): State<AppState>,
     mut multipart: axum::extract::Multipart,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     tracing::info!("[Images] Received edit request");
 
     let mut image_data = None;
@@ -1899,39 +2475,38 @@ pub async fn handle_images_edits(
     let mut image_size_param: Option<String> = None;
     let mut style: Option<String> = None;
 
-    while let Some(field) = multipart
-        .next_field()
-        .await
-        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Multipart error: {}", e)))?
-    {
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        ApiError::from_status(StatusCode::BAD_REQUEST, format!("Multipart error: {}", e)).openai()
+    })? {
         let name = field.name().unwrap_or("").to_string();
 
         if name == "image" {
-            let data = field
-                .bytes()
-                .await
-                .map_err(|e| (StatusCode::BAD_REQUEST, format!("Image read error: {}", e)))?;
+            let data = field.bytes().await.map_err(|e| {
+                ApiError::from_status(StatusCode::BAD_REQUEST, format!("Image read error: {}", e))
+                    .openai()
+            })?;
             image_data = Some(base64::engine::general_purpose::STANDARD.encode(data));
         } else if name == "mask" {
-            let data = field
-                .bytes()
-                .await
-                .map_err(|e| (StatusCode::BAD_REQUEST, format!("Mask read error: {}", e)))?;
+            let data = field.bytes().await.map_err(|e| {
+                ApiError::from_status(StatusCode::BAD_REQUEST, format!("Mask read error: {}", e))
+                    .openai()
+            })?;
             mask_data = Some(base64::engine::general_purpose::STANDARD.encode(data));
         } else if name.starts_with("image") && name != "image_size" {
             // Support image1, image2, etc.
             let data = field.bytes().await.map_err(|e| {
-                (
+                ApiError::from_status(
                     StatusCode::BAD_REQUEST,
                     format!("Reference image read error: {}", e),
                 )
+                .openai()
             })?;
             reference_images.push(base64::engine::general_purpose::STANDARD.encode(data));
         } else if name == "prompt" {
-            prompt = field
-                .text()
-                .await
-                .map_err(|e| (StatusCode::BAD_REQUEST, format!("Prompt read error: {}", e)))?;
+            prompt = field.text().await.map_err(|e| {
+                ApiError::from_status(StatusCode::BAD_REQUEST, format!("Prompt read error: {}", e))
+                    .openai()
+            })?;
         } else if name == "n" {
             if let Ok(val) = field.text().await {
                 n = val.parse().unwrap_or(1);
@@ -1968,7 +2543,9 @@ pub async fn handle_images_edits(
     // Validation: Require either 'image' (standard edit) OR 'prompt' (generation)
     // If reference images are present, we treat it as generation with image context
     if prompt.is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "Missing prompt".to_string()));
+        return Err(
+            ApiError::from_status(StatusCode::BAD_REQUEST, "Missing prompt".to_string()).openai(),
+        );
     }
 
     tracing::info!(
@@ -2056,6 +2633,10 @@ pub async fn handle_images_edits(
         .min(max_pool_size.saturating_add(1))
         .max(2);
 
+    // [NEW] 图像生成耗时较长，按 images 路由类别 / 模型解析总耗时超时
+    let upstream_timeout = crate::proxy::timeouts::resolve(TimeoutRouteClass::Images, &[model.as_str()]);
+    let request_timeout = upstream_timeout.request_override(false);
+
     let mut tasks = Vec::new();
     for _ in 0..n {
         let upstream = upstream.clone();
@@ -2117,12 +2698,14 @@ pub async fn handle_images_edits(
                 });
 
                 match upstream
-                    .call_v1_internal(
+                    .call_v1_internal_with_headers(
                         "generateContent",
                         &access_token,
                         gemini_body,
                         None,
+                        std::collections::HashMap::new(),
                         Some(account_id.as_str()),
+                        request_timeout,
                     )
                     .await
                 {
@@ -2174,8 +2757,13 @@ pub async fn handle_images_edits(
     let mut errors: Vec<String> = Vec::new();
     let mut used_email: Option<String> = None;
 
+    let abort_handles: Vec<_> = tasks.iter().map(|t| t.abort_handle()).collect();
     for (idx, task) in tasks.into_iter().enumerate() {
-        match task.await {
+        let Ok(joined) = tokio::time::timeout(upstream_timeout.remaining(), task).await else {
+            abort_handles.iter().for_each(|h| h.abort());
+            return Ok(upstream_timeout.error_response(TimeoutKind::Total));
+        };
+        match joined {
             Ok(result) => match result {
                 Ok((gemini_resp, response_format, email_used)) => {
                     if used_email.is_none() {
@@ -2236,7 +2824,7 @@ pub async fn handle_images_edits(
             n,
             error_msg
         );
-        return Err((StatusCode::BAD_GATEWAY, error_msg));
+        return Err(ApiError::from_status(StatusCode::BAD_GATEWAY, error_msg).openai());
     }
 
     if !errors.is_empty() {
@@ -2270,3 +2858,42 @@ pub async fn handle_images_edits(
     )
         .into_response())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_tokens_body_includes_system_and_tools() {
+        let request: OpenAIRequest = serde_json::from_value(json!({
+            "model": "gemini-2.5-flash",
+            "messages": [
+                {"role": "system", "content": "Answer in French."},
+                {"role": "user", "content": "What's the weather?"}
+            ],
+            "tools": [{
+                "type": "function",
+                "function": {
+                    "name": "get_weather",
+                    "description": "Look up the weather",
+                    "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}
+                }
+            }]
+        }))
+        .unwrap();
+        let (gemini_body, _, _) = transform_openai_request(&request, "project", "gemini-2.5-flash");
+        let count_body = build_count_tokens_body(&gemini_body);
+        let counted = &count_body["request"];
+
+        assert_eq!(counted["model"], "models/gemini-2.5-flash");
+        assert_eq!(counted["contents"], gemini_body["request"]["contents"]);
+        let system = counted["systemInstruction"].to_string();
+        assert!(system.contains("Answer in French."), "{}", system);
+        assert_eq!(
+            counted["tools"][0]["functionDeclarations"][0]["name"],
+            "get_weather"
+        );
+        // 生成参数不参与计数
+        assert!(counted.get("generationConfig").is_none());
+    }
+}
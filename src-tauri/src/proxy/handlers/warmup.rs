@@ -131,6 +131,8 @@ pub async fn handle_warmup(
             output_config: None,
             size: None,
             quality: None,
+            stop_sequences: None,
+            tool_choice: None,
         };
 
         match crate::proxy::mappers::claude::transform_claude_request_in(
@@ -255,6 +257,10 @@ pub async fn handle_warmup(
                 output_tokens: Some(0),
                 protocol: Some("warmup".to_string()),
                 username: None,
+                timeout_secs: None,
+                error_class: None,
+                cached_tokens: None,
+                estimated_cost_usd: None,
             };
             state.monitor.log_request(log).await;
 
@@ -323,6 +329,10 @@ pub async fn handle_warmup(
                 output_tokens: None,
                 protocol: Some("warmup".to_string()),
                 username: None,
+                timeout_secs: None,
+                error_class: None,
+                cached_tokens: None,
+                estimated_cost_usd: None,
             };
             state.monitor.log_request(log).await;
 
@@ -0,0 +1,198 @@
+// 监听地址解析与绑定
+// ProxyConfig.bind_addresses 为空时沿用 allow_lan_access 决定的单一地址；
+// 支持 `127.0.0.1`、`100.64.0.1:8045`、`[::1]` 以及 (unix 平台) `unix:/path/to.sock`
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindTarget {
+    /// `host:port`
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+impl std::fmt::Display for BindTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindTarget::Tcp(addr) => write!(f, "{}", addr),
+            BindTarget::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// 解析单个监听地址，未指定端口时使用 `port`
+fn parse_bind_address(value: &str, port: u16) -> Result<BindTarget, String> {
+    if let Some(path) = value.strip_prefix("unix:") {
+        let path = path.trim();
+        if path.is_empty() {
+            return Err(format!("监听地址 {:?} 缺少 socket 路径", value));
+        }
+        return Ok(BindTarget::Unix(PathBuf::from(path)));
+    }
+    if let Ok(addr) = value.parse::<SocketAddr>() {
+        return Ok(BindTarget::Tcp(addr.to_string()));
+    }
+    let bare = value.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = bare.parse::<IpAddr>() {
+        return Ok(BindTarget::Tcp(SocketAddr::new(ip, port).to_string()));
+    }
+    // 主机名 (如 localhost / localhost:8046)
+    match value.rsplit_once(':') {
+        Some((host, p)) if !host.is_empty() && !host.contains(':') => {
+            p.parse::<u16>()
+                .map_err(|_| format!("监听地址 {:?} 的端口无效", value))?;
+            Ok(BindTarget::Tcp(value.to_string()))
+        }
+        None if !value.contains(char::is_whitespace) => {
+            Ok(BindTarget::Tcp(format!("{}:{}", value, port)))
+        }
+        _ => Err(format!("无法解析监听地址 {:?}", value)),
+    }
+}
+
+/// 计算最终的监听目标 (去重)，`bind_addresses` 为空时回退到 `legacy_host:port`
+pub fn resolve_bind_targets(
+    bind_addresses: &[String],
+    legacy_host: &str,
+    port: u16,
+) -> Result<Vec<BindTarget>, String> {
+    let mut targets = Vec::new();
+    for value in bind_addresses.iter().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        let target = parse_bind_address(value, port)?;
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    if targets.is_empty() {
+        targets.push(BindTarget::Tcp(format!("{}:{}", legacy_host, port)));
+    }
+    Ok(targets)
+}
+
+pub enum BoundListener {
+    Tcp(tokio::net::TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener, PathBuf),
+}
+
+impl BoundListener {
+    /// 删除 unix socket 文件 (停机或启动失败时调用)
+    pub fn cleanup(&self) {
+        match self {
+            #[cfg(unix)]
+            BoundListener::Unix(_, path) => {
+                if let Err(e) = std::fs::remove_file(path) {
+                    tracing::debug!("删除 socket 文件 {} 失败: {}", path.display(), e);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+async fn bind_one(target: &BindTarget) -> Result<BoundListener, String> {
+    match target {
+        BindTarget::Tcp(addr) => tokio::net::TcpListener::bind(addr)
+            .await
+            .map(BoundListener::Tcp)
+            .map_err(|e| format!("地址 {} 绑定失败: {}", addr, e)),
+        #[cfg(unix)]
+        BindTarget::Unix(path) => {
+            use std::os::unix::fs::FileTypeExt;
+            // 清理上次异常退出遗留的 socket 文件 (只删除 socket，避免误删普通文件)
+            if let Ok(meta) = std::fs::symlink_metadata(path) {
+                if meta.file_type().is_socket() {
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+            tokio::net::UnixListener::bind(path)
+                .map(|l| BoundListener::Unix(l, path.clone()))
+                .map_err(|e| format!("地址 unix:{} 绑定失败: {}", path.display(), e))
+        }
+        #[cfg(not(unix))]
+        BindTarget::Unix(path) => Err(format!(
+            "地址 unix:{} 绑定失败: 当前平台不支持 unix socket",
+            path.display()
+        )),
+    }
+}
+
+/// 绑定全部地址；任一地址失败则释放已绑定的监听并返回该地址的错误
+pub async fn bind_all(targets: &[BindTarget]) -> Result<Vec<BoundListener>, String> {
+    let mut listeners = Vec::with_capacity(targets.len());
+    for target in targets {
+        match bind_one(target).await {
+            Ok(listener) => listeners.push(listener),
+            Err(e) => {
+                for listener in &listeners {
+                    listener.cleanup();
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok(listeners)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tcp(addr: &str) -> BindTarget {
+        BindTarget::Tcp(addr.to_string())
+    }
+
+    #[test]
+    fn test_resolve_bind_targets() {
+        let addrs: Vec<String> = vec![
+            "127.0.0.1".into(),
+            "100.64.0.7:9000".into(),
+            "[::1]".into(),
+            "localhost".into(),
+            " 127.0.0.1 ".into(),
+            "unix:/run/abv.sock".into(),
+        ];
+        assert_eq!(
+            resolve_bind_targets(&addrs, "0.0.0.0", 8045).unwrap(),
+            vec![
+                tcp("127.0.0.1:8045"),
+                tcp("100.64.0.7:9000"),
+                tcp("[::1]:8045"),
+                tcp("localhost:8045"),
+                BindTarget::Unix(PathBuf::from("/run/abv.sock")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_legacy_fallback_and_errors() {
+        assert_eq!(
+            resolve_bind_targets(&[], "127.0.0.1", 8045).unwrap(),
+            vec![tcp("127.0.0.1:8045")]
+        );
+        assert!(resolve_bind_targets(&["unix:".to_string()], "127.0.0.1", 8045).is_err());
+        assert!(resolve_bind_targets(&["host:abc".to_string()], "127.0.0.1", 8045).is_err());
+        assert_eq!(
+            BindTarget::Unix(PathBuf::from("/tmp/a.sock")).to_string(),
+            "unix:/tmp/a.sock"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bind_failure_names_address() {
+        let first = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let taken = first.local_addr().unwrap().to_string();
+        let err = bind_all(&[tcp("127.0.0.1:0"), tcp(&taken)]).await.err().unwrap();
+        assert!(err.contains(&taken), "{}", err);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_socket_cleanup() {
+        let path = std::env::temp_dir().join(format!("abv-test-{}.sock", uuid::Uuid::new_v4()));
+        let listeners = bind_all(&[BindTarget::Unix(path.clone())]).await.unwrap();
+        assert!(path.exists());
+        listeners[0].cleanup();
+        assert!(!path.exists());
+    }
+}
@@ -144,6 +144,36 @@ pub fn create_claude_sse_stream(
     })
 }
 
+/// Claude 原生 `ping` 事件
+const CLAUDE_PING_EVENT: &[u8] = b"event: ping\ndata: {\"type\": \"ping\"}\n\n";
+
+/// 为下游 Claude SSE 流注入 `ping` 保活事件
+///
+/// 当上游在 `interval` 内没有产出任何事件时 (例如长时间 thinking)，发送一个 `ping`，
+/// 防止隧道/反向代理因空闲断开连接。`ping` 只会插在两个完整的数据块之间，
+/// 上游的每个数据块本身都是完整事件，因此不会拆分事件。`interval` 为 0 时原样返回。
+pub fn with_ping_keepalive(
+    mut inner: Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send>>,
+    interval: std::time::Duration,
+) -> Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send>> {
+    use async_stream::stream;
+    use futures::StreamExt;
+
+    if interval.is_zero() {
+        return inner;
+    }
+
+    Box::pin(stream! {
+        loop {
+            match tokio::time::timeout(interval, inner.next()).await {
+                Ok(Some(item)) => yield item,
+                Ok(None) => break,
+                Err(_) => yield Ok(Bytes::from_static(CLAUDE_PING_EVENT)),
+            }
+        }
+    })
+}
+
 /// 处理单行 SSE 数据
 fn process_sse_line(line: &str, state: &mut StreamingState, trace_id: &str, email: &str) -> Option<Vec<Bytes>> {
     if !line.starts_with("data: ") {
@@ -505,4 +535,43 @@ mod tests {
         assert!(output.contains("\"usage\":"));
         assert!(output.contains("\"output_tokens\":100")); // Should contain the recovery usage
     }
+
+    #[tokio::test]
+    async fn test_ping_keepalive_fills_idle_gaps() {
+        use futures::StreamExt;
+
+        // 模拟上游 thinking 阶段长时间无数据
+        let slow = async_stream::stream! {
+            yield Ok::<Bytes, std::io::Error>(Bytes::from("event: message_start\ndata: {}\n\n"));
+            tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+            yield Ok(Bytes::from("event: message_stop\ndata: {}\n\n"));
+        };
+
+        let wrapped = with_ping_keepalive(Box::pin(slow), std::time::Duration::from_millis(50));
+        let chunks: Vec<String> = wrapped
+            .map(|r| String::from_utf8(r.unwrap().to_vec()).unwrap())
+            .collect()
+            .await;
+
+        assert!(chunks.first().unwrap().starts_with("event: message_start"));
+        assert!(chunks.last().unwrap().starts_with("event: message_stop"));
+        let pings = chunks.iter().filter(|c| c.starts_with("event: ping\n")).count();
+        assert!(pings >= 3, "expected pings during idle gap, got {}", pings);
+        // 每个 ping 都是独立完整事件
+        assert!(chunks.iter().all(|c| c.ends_with("\n\n")));
+    }
+
+    #[tokio::test]
+    async fn test_ping_keepalive_disabled_with_zero_interval() {
+        use futures::StreamExt;
+
+        let slow = async_stream::stream! {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            yield Ok::<Bytes, std::io::Error>(Bytes::from("event: message_stop\ndata: {}\n\n"));
+        };
+        let chunks: Vec<_> = with_ping_keepalive(Box::pin(slow), std::time::Duration::ZERO)
+            .collect()
+            .await;
+        assert_eq!(chunks.len(), 1);
+    }
 }
@@ -34,6 +34,45 @@ pub struct ClaudeRequest {
     pub size: Option<String>,
     #[serde(default)]
     pub quality: Option<String>,
+    /// 自定义停止序列，映射为 Gemini generationConfig.stopSequences (兼容传入单个字符串)
+    #[serde(
+        default,
+        deserialize_with = "deserialize_stop_sequences",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub stop_sequences: Option<Vec<String>>,
+    /// 工具选择策略，映射为 Gemini toolConfig.functionCallingConfig
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+}
+
+/// Claude tool_choice (`{"type": "auto" | "any" | "tool" | "none"}`)
+/// 采用结构体变体，忽略 disable_parallel_tool_use 等 Gemini 无对应项的字段
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ToolChoice {
+    Auto {},
+    Any {},
+    Tool { name: String },
+    None {},
+}
+
+fn deserialize_stop_sequences<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrVec {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(
+        Option::<StringOrVec>::deserialize(deserializer)?.map(|v| match v {
+            StringOrVec::One(s) => vec![s],
+            StringOrVec::Many(v) => v,
+        }),
+    )
 }
 
 /// Thinking 配置
@@ -57,7 +96,11 @@ pub enum SystemPrompt {
 pub struct SystemBlock {
     #[serde(rename = "type")]
     pub block_type: String,
+    #[serde(default)]
     pub text: String,
+    /// Prompt caching 边界标记 (`{"type": "ephemeral"}`)，仅保留用于统计，不透传上游
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<serde_json::Value>,
 }
 
 /// Message
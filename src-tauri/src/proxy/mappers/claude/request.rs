@@ -585,10 +585,15 @@ pub fn transform_claude_request_in(
                 "mode": "VALIDATED"
             }
         });
+        // [NEW] 客户端显式指定 tool_choice 时覆盖默认模式
+        apply_tool_choice(&mut inner_request, claude_req.tool_choice.as_ref())?;
     }
 
+    // tool_choice = none 时不再注入任何工具
+    let tools_disabled = matches!(claude_req.tool_choice, Some(ToolChoice::None {}));
+
     // Inject googleSearch tool if needed (and not already done by build_tools)
-    if config.inject_google_search && !has_web_search_tool {
+    if config.inject_google_search && !has_web_search_tool && !tools_disabled {
         crate::proxy::mappers::common_utils::inject_google_search_tool(&mut inner_request);
     }
 
@@ -801,12 +806,6 @@ fn build_system_instruction(
 ) -> Option<Value> {
     let mut parts = Vec::new();
 
-    // [NEW] Antigravity 身份指令 (原始简化版)
-    let antigravity_identity = "You are Antigravity, a powerful agentic AI coding assistant designed by the Google Deepmind team working on Advanced Agentic Coding.\n\
-    You are pair programming with a USER to solve their coding task. The task may require creating a new codebase, modifying or debugging an existing codebase, or simply answering a question.\n\
-    **Absolute paths only**\n\
-    **Proactiveness**";
-
     // [HYBRID] 检查用户是否已提供 Antigravity 身份
     let mut user_has_antigravity = false;
     if let Some(sys) = system {
@@ -827,11 +826,6 @@ fn build_system_instruction(
         }
     }
 
-    // 如果用户没有提供 Antigravity 身份,则注入
-    if !user_has_antigravity {
-        parts.push(json!({"text": antigravity_identity}));
-    }
-
     // [NEW] 注入全局系统提示词 (紧跟 Antigravity 身份之后)
     let global_prompt_config = crate::proxy::config::get_global_system_prompt();
     if global_prompt_config.enabled && !global_prompt_config.content.trim().is_empty() {
@@ -847,8 +841,17 @@ fn build_system_instruction(
                 parts.push(json!({"text": text}));
             }
             SystemPrompt::Array(blocks) => {
+                // [NEW] 按顺序拼接 text 块; cache_control 边界不透传 (Gemini 不识别该字段),
+                // 上游对稳定前缀做隐式缓存，命中情况通过 cachedContentTokenCount 回传
+                let cache_boundaries = blocks.iter().filter(|b| b.cache_control.is_some()).count();
+                if cache_boundaries > 0 {
+                    tracing::debug!(
+                        "[Claude-Request] System prompt has {} cache_control boundaries, relying on upstream implicit caching",
+                        cache_boundaries
+                    );
+                }
                 for block in blocks {
-                    if block.block_type == "text" {
+                    if block.block_type == "text" && !block.text.is_empty() {
                         // [MODIFIED] No longer filter "You are an interactive CLI tool"
                         parts.push(json!({"text": block.text}));
                     }
@@ -870,6 +873,14 @@ fn build_system_instruction(
         parts.push(json!({"text": mcp_xml_prompt}));
     }
 
+    // 如果用户没有提供 Antigravity 身份,则注入到首位
+    // [NEW] 超出系统提示词预算时使用短版本 (仅影响注入部分)
+    if !user_has_antigravity {
+        let budget = crate::proxy::mappers::system_prompt_budget::get_system_prompt_budget();
+        let identity = budget.identity(parts.iter().filter_map(|p| p["text"].as_str()));
+        parts.insert(0, json!({"text": identity}));
+    }
+
     // 如果用户没有提供任何系统提示词,添加结束标记
     if !user_has_antigravity {
         parts.push(json!({"text": "\n--- [SYSTEM_PROMPT_END] ---"}));
@@ -1641,6 +1652,45 @@ fn merge_adjacent_roles(mut contents: Vec<Value>) -> Vec<Value> {
     merged
 }
 
+/// [NEW] 应用 tool_choice
+/// 将 Claude tool_choice 映射为 Gemini toolConfig.functionCallingConfig:
+/// auto → AUTO, any → ANY, tool → ANY + allowedFunctionNames, none → 移除全部工具
+fn apply_tool_choice(inner_request: &mut Value, tool_choice: Option<&ToolChoice>) -> Result<(), String> {
+    let Some(choice) = tool_choice else {
+        return Ok(());
+    };
+
+    let declared: Vec<String> = inner_request["tools"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|t| t["functionDeclarations"].as_array())
+        .flatten()
+        .filter_map(|f| f["name"].as_str().map(|n| n.to_string()))
+        .collect();
+    let calling_config = match choice {
+        ToolChoice::None {} => {
+            if let Some(obj) = inner_request.as_object_mut() {
+                obj.remove("tools");
+                obj.remove("toolConfig");
+            }
+            return Ok(());
+        }
+        // 仅有 googleSearch 时没有可强制调用的函数，保持默认配置
+        _ if declared.is_empty() => return Ok(()),
+        ToolChoice::Auto {} => json!({ "mode": "AUTO" }),
+        ToolChoice::Any {} => json!({ "mode": "ANY" }),
+        ToolChoice::Tool { name } => {
+            if !declared.iter().any(|d| d == name) {
+                return Err(format!("tool_choice references unknown tool: {}", name));
+            }
+            json!({ "mode": "ANY", "allowedFunctionNames": [name] })
+        }
+    };
+    inner_request["toolConfig"] = json!({ "functionCallingConfig": calling_config });
+    Ok(())
+}
+
 /// 构建 Tools
 fn build_tools(tools: &Option<Vec<Tool>>, has_web_search: bool) -> Result<Option<Value>, String> {
     if let Some(tools_list) = tools {
@@ -1735,42 +1785,18 @@ fn build_generation_config(
             .unwrap_or(16000);
 
         let tb_config = crate::proxy::config::get_thinking_budget_config();
-        let budget = match tb_config.mode {
-            crate::proxy::config::ThinkingBudgetMode::Passthrough => budget_tokens,
-            crate::proxy::config::ThinkingBudgetMode::Custom => {
-                let mut custom_value = tb_config.custom_value;
-                // [FIX #1602] 针对 Gemini 系列模型，在自定义模式下也强制执行 24576 上限
-                let model_lower = mapped_model.to_lowercase();
-                let is_gemini_limited = (model_lower.contains("gemini") && !model_lower.contains("-image"))
-                    || model_lower.contains("flash")
-                    || model_lower.ends_with("-thinking");
-
-                if is_gemini_limited && custom_value > 24576 {
-                    tracing::warn!(
-                        "[Claude-Request] Custom mode: capping thinking_budget from {} to 24576 for Gemini model {}",
-                        custom_value, mapped_model
-                    );
-                    custom_value = 24576;
-                }
-                custom_value
-            }
-            crate::proxy::config::ThinkingBudgetMode::Auto => {
-                // [FIX #1592] Use mapped model for robust detection, same as OpenAI protocol
-                let model_lower = mapped_model.to_lowercase();
-                let is_gemini_limited = (model_lower.contains("gemini") && !model_lower.contains("-image"))
-                    || model_lower.contains("flash")
-                    || model_lower.ends_with("-thinking");
-                if is_gemini_limited && budget_tokens > 24576 {
-                    tracing::info!(
-                        "[Claude-Request] Auto mode: capping thinking_budget from {} to 24576 for Gemini model {}", 
-                        budget_tokens, mapped_model
-                    );
-                    24576
-                } else {
-                    budget_tokens
-                }
-            }
-        };
+        // [FIX #1592/#1602] Use mapped model for robust detection, same as OpenAI protocol
+        let model_lower = mapped_model.to_lowercase();
+        let is_gemini_limited = (model_lower.contains("gemini") && !model_lower.contains("-image"))
+            || model_lower.contains("flash")
+            || model_lower.ends_with("-thinking");
+        let budget = tb_config.resolve_budget(mapped_model, budget_tokens as u64, is_gemini_limited);
+        if budget != budget_tokens as u64 {
+            tracing::info!(
+                "[Claude-Request] {:?} mode: thinking_budget {} -> {} for model {}",
+                tb_config.mode, budget_tokens, budget, mapped_model
+            );
+        }
         thinking_config["thinkingBudget"] = json!(budget);
         config["thinkingConfig"] = thinking_config;
     }
@@ -1842,7 +1868,25 @@ fn build_generation_config(
     //   2. 将其作为 stopSequence 会导致模型输出被意外截断 (如解释 SSE 协议时)
     //   3. Gemini 流的真正结束由 finishReason 字段控制,无需依赖 stopSequence
     //   4. SSE 层面的 "data: [DONE]" 已在 mod.rs 中单独处理
-    config["stopSequences"] = json!(["<|user|>", "<|end_of_turn|>", "\n\nHuman:"]);
+    // [NEW] 客户端 stop_sequences 优先，剩余名额 (Gemini 上限 5 个) 再填充默认序列
+    let mut stop_sequences = claude_req
+        .stop_sequences
+        .as_ref()
+        .map(|seqs| {
+            crate::proxy::mappers::common_utils::normalize_stop_sequences(&json!(seqs))
+        })
+        .unwrap_or_default();
+    for default_seq in ["<|user|>", "<|end_of_turn|>", "\n\nHuman:"] {
+        if stop_sequences.len()
+            >= crate::proxy::mappers::common_utils::GEMINI_MAX_STOP_SEQUENCES
+        {
+            break;
+        }
+        if !stop_sequences.iter().any(|s| s == default_seq) {
+            stop_sequences.push(default_seq.to_string());
+        }
+    }
+    config["stopSequences"] = json!(stop_sequences);
 
     config
 }
@@ -1956,6 +2000,33 @@ mod tests {
             .is_none());
     }
 
+    #[test]
+    fn test_system_array_with_cache_control() {
+        let req: ClaudeRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "system": [
+                {"type": "text", "text": "First block"},
+                {"type": "text", "text": "Second block", "cache_control": {"type": "ephemeral"}}
+            ],
+            "messages": [{"role": "user", "content": "Hello"}]
+        }))
+        .unwrap();
+
+        let instruction = build_system_instruction(&req.system, &req.model, false).unwrap();
+        let texts: Vec<&str> = instruction["parts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|p| p["text"].as_str())
+            .collect();
+        let first = texts.iter().position(|t| *t == "First block").unwrap();
+        let second = texts.iter().position(|t| *t == "Second block").unwrap();
+        // 身份指令在前，用户 system 块保持原顺序
+        assert!(texts[0].contains("You are Antigravity"));
+        assert!(first < second);
+        assert!(!instruction.to_string().contains("cache_control"));
+    }
+
     #[test]
     fn test_simple_request() {
         let req = ClaudeRequest {
@@ -1976,6 +2047,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            stop_sequences: None,
+            tool_choice: None,
         };
 
         let result = transform_claude_request_in(&req, "test-project", false);
@@ -1986,6 +2059,124 @@ mod tests {
         assert!(body["requestId"].as_str().unwrap().starts_with("agent-"));
     }
 
+    fn stop_request(stop_sequences: Value) -> ClaudeRequest {
+        serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 1024,
+            "messages": [{ "role": "user", "content": "count to ten" }],
+            "stop_sequences": stop_sequences
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_stop_sequences_single_and_array() {
+        let result = transform_claude_request_in(&stop_request(json!("5")), "test-project", false)
+            .unwrap();
+        assert_eq!(
+            result["request"]["generationConfig"]["stopSequences"],
+            json!(["5", "<|user|>", "<|end_of_turn|>", "\n\nHuman:"])
+        );
+
+        let result = transform_claude_request_in(
+            &stop_request(json!(["a", "b", "c", "d", "e", "f"])),
+            "test-project",
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            result["request"]["generationConfig"]["stopSequences"],
+            json!(["a", "b", "c", "d", "e"])
+        );
+    }
+
+    fn tool_choice_request(tool_choice: Value) -> ClaudeRequest {
+        serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 1024,
+            "messages": [{ "role": "user", "content": "What's the weather in Paris?" }],
+            "tools": [
+                {
+                    "name": "get_weather",
+                    "description": "Get the weather",
+                    "input_schema": { "type": "object", "properties": { "city": { "type": "string" } } }
+                },
+                {
+                    "name": "get_time",
+                    "description": "Get the time",
+                    "input_schema": { "type": "object", "properties": {} }
+                }
+            ],
+            "tool_choice": tool_choice
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_tool_choice_auto_and_any() {
+        let result =
+            transform_claude_request_in(&tool_choice_request(json!({ "type": "auto" })), "p", false)
+                .unwrap();
+        assert_eq!(
+            result["request"]["toolConfig"],
+            json!({ "functionCallingConfig": { "mode": "AUTO" } })
+        );
+
+        let result = transform_claude_request_in(
+            &tool_choice_request(json!({ "type": "any", "disable_parallel_tool_use": true })),
+            "p",
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            result["request"]["toolConfig"],
+            json!({ "functionCallingConfig": { "mode": "ANY" } })
+        );
+    }
+
+    #[test]
+    fn test_tool_choice_forces_single_tool() {
+        let result = transform_claude_request_in(
+            &tool_choice_request(json!({ "type": "tool", "name": "get_weather" })),
+            "p",
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            result["request"]["toolConfig"],
+            json!({
+                "functionCallingConfig": { "mode": "ANY", "allowedFunctionNames": ["get_weather"] }
+            })
+        );
+
+        let err = transform_claude_request_in(
+            &tool_choice_request(json!({ "type": "tool", "name": "missing" })),
+            "p",
+            false,
+        )
+        .unwrap_err();
+        assert!(err.contains("missing"));
+    }
+
+    #[test]
+    fn test_tool_choice_none_strips_tools() {
+        let result =
+            transform_claude_request_in(&tool_choice_request(json!({ "type": "none" })), "p", false)
+                .unwrap();
+        assert!(result["request"].get("tools").is_none());
+        assert!(result["request"].get("toolConfig").is_none());
+    }
+
+    #[test]
+    fn test_tool_choice_absent_keeps_validated_mode() {
+        let result =
+            transform_claude_request_in(&tool_choice_request(json!(null)), "p", false).unwrap();
+        assert_eq!(
+            result["request"]["toolConfig"]["functionCallingConfig"]["mode"],
+            "VALIDATED"
+        );
+    }
+
     #[test]
     fn test_clean_json_schema() {
         let mut schema = json!({
@@ -2073,6 +2264,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            stop_sequences: None,
+            tool_choice: None,
         };
 
         let result = transform_claude_request_in(&req, "test-project", false);
@@ -2143,6 +2336,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            stop_sequences: None,
+            tool_choice: None,
         };
 
         let result = transform_claude_request_in(&req, "test-project", false);
@@ -2216,6 +2411,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            stop_sequences: None,
+            tool_choice: None,
         };
 
         let result = transform_claude_request_in(&req, "test-project", false);
@@ -2266,6 +2463,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            stop_sequences: None,
+            tool_choice: None,
         };
 
         let result = transform_claude_request_in(&req, "test-project", false);
@@ -2321,6 +2520,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            stop_sequences: None,
+            tool_choice: None,
         };
 
         let result = transform_claude_request_in(&req, "test-project", false);
@@ -2369,6 +2570,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            stop_sequences: None,
+            tool_choice: None,
         };
 
         let result = transform_claude_request_in(&req, "test-project", false);
@@ -2560,6 +2763,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            stop_sequences: None,
+            tool_choice: None,
         };
 
         let result = transform_claude_request_in(&req, "test-v", false).unwrap();
@@ -2596,6 +2801,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            stop_sequences: None,
+            tool_choice: None,
         };
 
         // Should cap at 24576
@@ -2626,6 +2833,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            stop_sequences: None,
+            tool_choice: None,
         };
 
         // Should cap
@@ -2662,6 +2871,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            stop_sequences: None,
+            tool_choice: None,
         };
 
         // Transform
@@ -2702,6 +2913,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            stop_sequences: None,
+            tool_choice: None,
         };
 
         // Transform
@@ -2739,6 +2952,8 @@ mod tests {
             output_config: None,
             size: Some("1024x1024".to_string()),
             quality: Some("hd".to_string()),
+            stop_sequences: None,
+            tool_choice: None,
         };
 
         // 3. Transform request
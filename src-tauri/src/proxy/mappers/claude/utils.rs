@@ -81,7 +81,8 @@ pub fn to_claude_usage(usage_metadata: &super::models::UsageMetadata, scaling_en
     }
     
     // 按比例分配缩放后的总量到 input 和 cache_read
-    let (reported_input, reported_cache) = if total_raw > 0 {
+    // 仅在上游报告了缓存命中时才返回 cache_read_input_tokens
+    let (reported_input, reported_cache) = if total_raw > 0 && cached_tokens > 0 {
         let cache_ratio = (cached_tokens as f64) / (total_raw as f64);
         let sc_cache = (scaled_total as f64 * cache_ratio) as u32;
         (scaled_total.saturating_sub(sc_cache), Some(sc_cache))
@@ -164,5 +165,21 @@ mod tests {
         let res_100 = to_claude_usage(&usage_100, true, 1_000_000);
         // 97% of 195k = 189,150
         assert!(res_100.input_tokens > 185_000 && res_100.input_tokens <= 190_000);
+        assert_eq!(res_100.cache_read_input_tokens, None);
+    }
+
+    #[test]
+    fn test_to_claude_usage_reports_cached_tokens() {
+        use super::super::models::UsageMetadata;
+
+        let usage = UsageMetadata {
+            prompt_token_count: Some(10_000),
+            candidates_token_count: Some(20),
+            total_token_count: Some(10_020),
+            cached_content_token_count: Some(8_000),
+        };
+        let res = to_claude_usage(&usage, true, 1_000_000);
+        assert_eq!(res.cache_read_input_tokens, Some(8_000));
+        assert_eq!(res.input_tokens, 2_000);
     }
 }
@@ -16,6 +16,33 @@ pub struct RequestConfig {
     pub image_config: Option<Value>,
 }
 
+/// Gemini generationConfig.stopSequences 最多允许 5 个
+pub const GEMINI_MAX_STOP_SEQUENCES: usize = 5;
+
+/// 将客户端的停止序列 (字符串或字符串数组) 规整为 Gemini stopSequences
+/// 空字符串被忽略，超出 Gemini 上限的部分丢弃并告警
+pub fn normalize_stop_sequences(value: &Value) -> Vec<String> {
+    let mut sequences: Vec<String> = match value {
+        Value::String(s) => vec![s.clone()],
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    };
+    sequences.retain(|s| !s.is_empty());
+    if sequences.len() > GEMINI_MAX_STOP_SEQUENCES {
+        tracing::warn!(
+            "[Stop-Sequences] {} stop sequences provided, Gemini supports at most {}; dropping {:?}",
+            sequences.len(),
+            GEMINI_MAX_STOP_SEQUENCES,
+            &sequences[GEMINI_MAX_STOP_SEQUENCES..]
+        );
+        sequences.truncate(GEMINI_MAX_STOP_SEQUENCES);
+    }
+    sequences
+}
+
 pub fn resolve_request_config(
     original_model: &str,
     mapped_model: &str,
@@ -483,6 +510,20 @@ pub fn contains_non_networking_tool(tools: &Option<Vec<Value>>) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_normalize_stop_sequences() {
+        assert_eq!(normalize_stop_sequences(&json!("END")), vec!["END"]);
+        assert_eq!(
+            normalize_stop_sequences(&json!(["a", "", "b", 3])),
+            vec!["a", "b"]
+        );
+        assert_eq!(
+            normalize_stop_sequences(&json!(["1", "2", "3", "4", "5", "6", "7"])),
+            vec!["1", "2", "3", "4", "5"]
+        );
+        assert!(normalize_stop_sequences(&Value::Null).is_empty());
+    }
+
     #[test]
     fn test_high_quality_model_auto_grounding() {
         // Auto-grounding is currently disabled by default due to conflict with image gen
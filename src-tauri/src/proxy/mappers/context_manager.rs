@@ -12,7 +12,7 @@ use tracing::{debug, info};
 /// - ASCII/English: ~4 characters per token
 /// - Unicode/CJK: ~1.5 characters per token (Chinese, Japanese, Korean are tokenized differently)
 /// - Adds 15% safety margin to prevent underestimation
-fn estimate_tokens_from_str(s: &str) -> u32 {
+pub(crate) fn estimate_tokens_from_str(s: &str) -> u32 {
     if s.is_empty() {
         return 0;
     }
@@ -458,6 +458,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            stop_sequences: None,
+            tool_choice: None,
         }
     }
 
@@ -1,5 +1,14 @@
 // 错误分类模块 - 将底层错误转换为用户友好的消息
+use axum::http::StatusCode;
 use reqwest::Error;
+use serde_json::{json, Value};
+
+use crate::proxy::error::{ApiError, ApiErrorCode};
+use crate::proxy::rate_limit::{classify_rate_limit_body, RateLimitReason};
+use crate::proxy::upstream::retry::parse_retry_delay;
+
+/// 上游错误分类响应头，监控中间件据此写入请求日志
+pub const ERROR_CLASS_HEADER: &str = "X-Error-Class";
 
 /// 分类流式响应错误并返回错误类型、英文消息和 i18n key
 /// 
@@ -41,6 +50,115 @@ pub fn classify_stream_error(error: &Error) -> (&'static str, &'static str, &'st
     }
 }
 
+/// 解析后的上游错误 (google.rpc.Status)，用于按原状态透传给客户端
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpstreamError {
+    pub status: u16,
+    /// `error.status`，如 RESOURCE_EXHAUSTED / INVALID_ARGUMENT
+    pub rpc_status: Option<String>,
+    pub message: String,
+    /// 建议等待秒数: RetryInfo.retryDelay > quotaResetDelay > Retry-After 响应头
+    pub retry_after_secs: Option<u64>,
+    /// 仅限流错误 (429 / RESOURCE_EXHAUSTED) 有值
+    pub reason: Option<RateLimitReason>,
+    /// 去除数组包裹后的错误体 `{"error": {...}}`，非 JSON 时为原文；供限流跟踪解析
+    pub body: String,
+}
+
+/// 解析上游错误响应体；`retry_after` 为上游的 Retry-After 响应头
+pub fn parse_upstream_error(status: u16, body: &str, retry_after: Option<&str>) -> UpstreamError {
+    let json: Option<Value> = serde_json::from_str(body.trim()).ok();
+    // streamGenerateContent 的错误可能包在数组中: [{"error": {...}}]
+    let error = json.as_ref().and_then(|v| match v {
+        Value::Array(items) => items.first().and_then(|i| i.get("error")),
+        other => other.get("error"),
+    });
+
+    let rpc_status = error
+        .and_then(|e| e.get("status"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    let message = error
+        .and_then(|e| e.get("message"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| {
+            let trimmed = body.trim();
+            if trimmed.is_empty() {
+                format!("HTTP {}", status)
+            } else {
+                trimmed.to_string()
+            }
+        });
+
+    // 部分上游以 200 以外的状态返回 RESOURCE_EXHAUSTED，统一按 429 处理
+    let status = if rpc_status.as_deref() == Some("RESOURCE_EXHAUSTED") { 429 } else { status };
+
+    let normalized = error.map(|e| json!({ "error": e }).to_string());
+    let retry_after_secs = normalized
+        .as_deref()
+        .and_then(parse_retry_delay)
+        .map(|ms| ms.div_ceil(1000).max(1))
+        .or_else(|| retry_after.and_then(|h| h.trim().parse::<u64>().ok()));
+    let body = normalized.unwrap_or_else(|| body.to_string());
+    let reason = (status == 429).then(|| classify_rate_limit_body(&body));
+
+    UpstreamError {
+        status,
+        rpc_status,
+        message,
+        retry_after_secs,
+        reason,
+        body,
+    }
+}
+
+impl UpstreamError {
+    /// 写入请求日志的错误分类
+    pub fn class(&self) -> &'static str {
+        match self.status {
+            429 => match self.reason {
+                Some(RateLimitReason::Unknown) | None => "rate_limited",
+                Some(reason) => reason.as_str(),
+            },
+            400 if self.message.to_lowercase().contains("safety") => "safety_blocked",
+            400 => "invalid_argument",
+            401 => "unauthenticated",
+            403 => "permission_denied",
+            404 => "not_found",
+            400..=499 => "client_error",
+            _ => "upstream_error",
+        }
+    }
+
+    pub fn is_quota_exhausted(&self) -> bool {
+        self.reason == Some(RateLimitReason::QuotaExhausted)
+    }
+
+    /// 附加 Retry-After、RetryInfo (Gemini 格式) 与错误分类头
+    pub fn annotate(&self, mut err: ApiError) -> ApiError {
+        if let Some(secs) = self.retry_after_secs {
+            err = err.with_header("Retry-After", &secs.to_string()).with_detail(json!({
+                "@type": "type.googleapis.com/google.rpc.RetryInfo",
+                "retryDelay": format!("{}s", secs),
+            }));
+        }
+        err.with_header(ERROR_CLASS_HEADER, self.class())
+    }
+
+    /// 按上游原状态构造客户端错误 (4xx 不再被折叠为 500)
+    pub fn to_api_error(&self) -> ApiError {
+        let err = if self.status == 429 {
+            ApiError::new(ApiErrorCode::UpstreamQuotaExhausted, self.message.clone())
+        } else {
+            let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::BAD_GATEWAY);
+            ApiError::from_status(status, self.message.clone())
+        };
+        self.annotate(err)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -112,4 +230,117 @@ mod tests {
             assert_eq!(format!("errors.stream.{}", expected_type), expected_key);
         }
     }
+
+    // 以下为抓取的真实上游错误响应
+    const QUOTA_EXHAUSTED_BODY: &str = r#"{
+  "error": {
+    "code": 429,
+    "message": "You have exhausted your capacity on this model. Your quota will reset after 2h1m1s.",
+    "status": "RESOURCE_EXHAUSTED",
+    "details": [
+      {
+        "@type": "type.googleapis.com/google.rpc.ErrorInfo",
+        "reason": "QUOTA_EXHAUSTED",
+        "domain": "cloudcode-pa.googleapis.com",
+        "metadata": {
+          "uiMessage": "true",
+          "model": "claude-sonnet-4-5-thinking",
+          "quotaResetDelay": "2h1m1.5s",
+          "quotaResetTimeStamp": "2025-01-20T10:32:15Z"
+        }
+      },
+      {
+        "@type": "type.googleapis.com/google.rpc.RetryInfo",
+        "retryDelay": "7261.5s"
+      }
+    ]
+  }
+}"#;
+
+    const RATE_LIMIT_BODY: &str = r#"[{
+  "error": {
+    "code": 429,
+    "message": "Resource has been exhausted (e.g. check quota).",
+    "status": "RESOURCE_EXHAUSTED",
+    "details": [
+      {
+        "@type": "type.googleapis.com/google.rpc.RetryInfo",
+        "retryDelay": "0.847s"
+      },
+      {
+        "@type": "type.googleapis.com/google.rpc.ErrorInfo",
+        "reason": "RATE_LIMIT_EXCEEDED",
+        "domain": "cloudcode-pa.googleapis.com",
+        "metadata": { "model": "gemini-2.5-pro" }
+      }
+    ]
+  }
+}]"#;
+
+    const INVALID_ARGUMENT_BODY: &str = r#"{
+  "error": {
+    "code": 400,
+    "message": "Request contains an invalid argument.",
+    "status": "INVALID_ARGUMENT"
+  }
+}"#;
+
+    #[test]
+    fn test_quota_exhausted_maps_to_429_with_retry_after() {
+        let parsed = parse_upstream_error(429, QUOTA_EXHAUSTED_BODY, None);
+        assert_eq!(parsed.rpc_status.as_deref(), Some("RESOURCE_EXHAUSTED"));
+        assert_eq!(parsed.retry_after_secs, Some(7262));
+        assert!(parsed.is_quota_exhausted());
+        assert_eq!(parsed.class(), "quota_exhausted");
+
+        let response = axum::response::IntoResponse::into_response(parsed.to_api_error().openai());
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["retry-after"], "7262");
+        assert_eq!(response.headers()["x-error-class"], "quota_exhausted");
+
+        let openai = parsed.to_api_error().openai().body();
+        assert_eq!(openai["error"]["type"], "rate_limit_error");
+        assert!(openai["error"]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("You have exhausted your capacity"));
+        let claude = parsed.to_api_error().claude().body();
+        assert_eq!(claude["error"]["type"], "rate_limit_error");
+        let gemini = parsed.to_api_error().gemini().body();
+        assert_eq!(gemini["error"]["details"][1]["retryDelay"], "7262s");
+    }
+
+    #[test]
+    fn test_rate_limit_reason_found_after_retry_info() {
+        let parsed = parse_upstream_error(429, RATE_LIMIT_BODY, Some("30"));
+        assert_eq!(parsed.reason, Some(RateLimitReason::RateLimitExceeded));
+        assert_eq!(parsed.retry_after_secs, Some(1));
+        assert!(parsed.body.starts_with(r#"{"error":"#));
+        assert_eq!(parsed.message, "Resource has been exhausted (e.g. check quota).");
+        assert!(!parsed.is_quota_exhausted());
+    }
+
+    #[test]
+    fn test_client_errors_keep_original_status() {
+        let parsed = parse_upstream_error(400, INVALID_ARGUMENT_BODY, None);
+        assert_eq!(parsed.class(), "invalid_argument");
+        assert_eq!(parsed.reason, None);
+        let err = parsed.to_api_error();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert_eq!(err.message, "Request contains an invalid argument.");
+
+        let safety = parse_upstream_error(
+            400,
+            r#"{"error":{"code":400,"message":"The response was blocked due to SAFETY.","status":"INVALID_ARGUMENT"}}"#,
+            None,
+        );
+        assert_eq!(safety.class(), "safety_blocked");
+
+        // 非 JSON 响应体: 原文作为消息，Retry-After 响应头作为兜底
+        let plain = parse_upstream_error(429, "Too Many Requests", Some("12"));
+        assert_eq!(plain.retry_after_secs, Some(12));
+        assert_eq!(plain.reason, Some(RateLimitReason::RateLimitExceeded));
+        assert_eq!(parse_upstream_error(502, "", None).message, "HTTP 502");
+        assert_eq!(parse_upstream_error(502, "", None).class(), "upstream_error");
+    }
 }
@@ -0,0 +1,245 @@
+// inlineData 图片校验
+// 转发前检查 contents[].parts[].inlineData 中的图片: MIME 白名单 + 解码后大小上限，
+// 设置 experimental.max_image_dimension 时对超出尺寸的 JPEG/PNG 等比缩小后重新编码，避免上游 413
+use base64::{engine::general_purpose, Engine as _};
+use image::{imageops::FilterType, ImageFormat};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::proxy::config::ExperimentalConfig;
+
+pub const ALLOWED_IMAGE_MIME_TYPES: [&str; 4] = ["image/jpeg", "image/png", "image/webp", "image/gif"];
+
+/// 单张不合规的图片 (按 content / part 下标定位)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImageViolation {
+    pub content_index: usize,
+    pub part_index: usize,
+    pub reason: String,
+}
+
+impl ImageViolation {
+    pub fn field(&self) -> String {
+        format!(
+            "contents[{}].parts[{}].inlineData",
+            self.content_index, self.part_index
+        )
+    }
+}
+
+impl std::fmt::Display for ImageViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field(), self.reason)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageValidator {
+    /// 单张图片解码后的最大字节数，0 表示不限制
+    pub max_image_bytes: usize,
+    /// 最长边上限 (像素)，0 表示不缩放
+    pub max_image_dimension: u32,
+}
+
+impl ImageValidator {
+    pub fn from_experimental(config: &ExperimentalConfig) -> Self {
+        Self {
+            max_image_bytes: config.max_image_bytes,
+            max_image_dimension: config.max_image_dimension,
+        }
+    }
+
+    /// 校验 (并按需缩放) 请求体中的全部图片，返回被缩放的图片数量
+    /// 非图片的 inlineData (音频 / PDF 等) 原样放行
+    pub fn validate(&self, body: &mut Value) -> Result<usize, Vec<ImageViolation>> {
+        let mut violations = Vec::new();
+        let mut resized = 0;
+
+        let Some(contents) = body.get_mut("contents").and_then(|c| c.as_array_mut()) else {
+            return Ok(0);
+        };
+        for (content_index, content) in contents.iter_mut().enumerate() {
+            let Some(parts) = content.get_mut("parts").and_then(|p| p.as_array_mut()) else {
+                continue;
+            };
+            for (part_index, part) in parts.iter_mut().enumerate() {
+                let Some(inline) = part.get_mut("inlineData").and_then(|d| d.as_object_mut())
+                else {
+                    continue;
+                };
+                let mime = inline
+                    .get("mimeType")
+                    .or_else(|| inline.get("mime_type"))
+                    .and_then(|m| m.as_str())
+                    .unwrap_or_default()
+                    .to_ascii_lowercase();
+                if !mime.starts_with("image/") {
+                    continue;
+                }
+                let violation = |reason: String| ImageViolation {
+                    content_index,
+                    part_index,
+                    reason,
+                };
+
+                if !ALLOWED_IMAGE_MIME_TYPES.contains(&mime.as_str()) {
+                    violations.push(violation(format!(
+                        "unsupported MIME type {} (allowed: {})",
+                        mime,
+                        ALLOWED_IMAGE_MIME_TYPES.join(", ")
+                    )));
+                    continue;
+                }
+
+                let data = inline.get("data").and_then(|d| d.as_str()).unwrap_or_default();
+                let bytes = match general_purpose::STANDARD.decode(data.trim()) {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        violations.push(violation(format!("invalid base64 data ({})", e)));
+                        continue;
+                    }
+                };
+
+                let bytes = match self.downscale(&mime, &bytes) {
+                    Some(smaller) => {
+                        inline.insert(
+                            "data".to_string(),
+                            Value::String(general_purpose::STANDARD.encode(&smaller)),
+                        );
+                        resized += 1;
+                        smaller
+                    }
+                    None => bytes,
+                };
+
+                if self.max_image_bytes > 0 && bytes.len() > self.max_image_bytes {
+                    violations.push(violation(format!(
+                        "image is {} bytes, exceeds limit of {} bytes",
+                        bytes.len(),
+                        self.max_image_bytes
+                    )));
+                }
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(resized)
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// 超出 max_image_dimension 的 JPEG/PNG 等比缩小并按原格式重新编码；无需缩放或无法解码时返回 None
+    fn downscale(&self, mime: &str, bytes: &[u8]) -> Option<Vec<u8>> {
+        if self.max_image_dimension == 0 {
+            return None;
+        }
+        let format = match mime {
+            "image/jpeg" => ImageFormat::Jpeg,
+            "image/png" => ImageFormat::Png,
+            _ => return None,
+        };
+        let img = match image::load_from_memory_with_format(bytes, format) {
+            Ok(img) => img,
+            Err(e) => {
+                tracing::warn!("[Image-Validator] Cannot decode {} for resizing: {}", mime, e);
+                return None;
+            }
+        };
+        let max = self.max_image_dimension;
+        if img.width() <= max && img.height() <= max {
+            return None;
+        }
+        let scaled = img.resize(max, max, FilterType::Triangle);
+        let mut out = std::io::Cursor::new(Vec::new());
+        if let Err(e) = scaled.write_to(&mut out, format) {
+            tracing::warn!("[Image-Validator] Failed to re-encode resized {}: {}", mime, e);
+            return None;
+        }
+        tracing::debug!(
+            "[Image-Validator] Resized {} {}x{} -> {}x{} ({} -> {} bytes)",
+            mime,
+            img.width(),
+            img.height(),
+            scaled.width(),
+            scaled.height(),
+            bytes.len(),
+            out.get_ref().len()
+        );
+        Some(out.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let img = image::DynamicImage::new_rgb8(width, height);
+        let mut out = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut out, ImageFormat::Png).unwrap();
+        out.into_inner()
+    }
+
+    fn body_with(parts: Vec<Value>) -> Value {
+        json!({"contents": [
+            {"role": "user", "parts": [{"text": "describe"}]},
+            {"role": "user", "parts": parts}
+        ]})
+    }
+
+    fn inline(mime: &str, bytes: &[u8]) -> Value {
+        json!({"inlineData": {"mimeType": mime, "data": general_purpose::STANDARD.encode(bytes)}})
+    }
+
+    fn validator(max_image_bytes: usize, max_image_dimension: u32) -> ImageValidator {
+        ImageValidator {
+            max_image_bytes,
+            max_image_dimension,
+        }
+    }
+
+    #[test]
+    fn test_violations_identify_content_index() {
+        let mut body = body_with(vec![
+            inline("image/png", &png(4, 4)),
+            inline("image/heic", b"heic"),
+            inline("image/jpeg", &[0u8; 512]),
+            inline("audio/wav", &[0u8; 4096]),
+        ]);
+        let violations = validator(256, 0).validate(&mut body).unwrap_err();
+        assert_eq!(violations.len(), 2, "{:?}", violations);
+        assert_eq!((violations[0].content_index, violations[0].part_index), (1, 1));
+        assert!(violations[0].reason.contains("image/heic"));
+        assert_eq!(violations[1].field(), "contents[1].parts[2].inlineData");
+        assert!(violations[1].reason.contains("exceeds"));
+    }
+
+    #[test]
+    fn test_large_png_is_downscaled() {
+        let mut body = body_with(vec![inline("image/png", &png(400, 100))]);
+        assert_eq!(validator(4 * 1024 * 1024, 200).validate(&mut body), Ok(1));
+
+        let data = body["contents"][1]["parts"][0]["inlineData"]["data"].as_str().unwrap();
+        let bytes = general_purpose::STANDARD.decode(data).unwrap();
+        let img = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((img.width(), img.height()), (200, 50));
+
+        // 尺寸未超限时不改写
+        let mut small = body_with(vec![inline("image/png", &png(100, 100))]);
+        let before = small.clone();
+        assert_eq!(validator(4 * 1024 * 1024, 200).validate(&mut small), Ok(0));
+        assert_eq!(small, before);
+    }
+
+    #[test]
+    fn test_invalid_base64_rejected() {
+        let mut body = json!({"contents": [{"parts": [
+            {"inlineData": {"mimeType": "image/png", "data": "not base64!!"}}
+        ]}]});
+        let violations = validator(1024, 0).validate(&mut body).unwrap_err();
+        assert_eq!(violations[0].content_index, 0);
+        assert!(validator(1024, 0).validate(&mut json!({})).is_ok());
+    }
+}
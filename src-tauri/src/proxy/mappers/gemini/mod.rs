@@ -4,6 +4,8 @@
 pub mod models;
 pub mod wrapper;
 pub mod collector; // [NEW]
+pub mod schema_validator; // functionCall 参数校验
+pub mod image_validator; // inlineData 图片校验与缩放
 
 // No public exports needed here if unused
 pub use wrapper::*;
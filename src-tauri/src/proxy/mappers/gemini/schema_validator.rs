@@ -0,0 +1,222 @@
+// 函数调用参数校验
+// 校验 Gemini 返回的 functionCall.args 是否符合请求中声明的 parameters JSON Schema；
+// 不合规时记录警告，启用 experimental.strict_function_schema_validation 时向客户端返回错误
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// 单个不合规的函数调用
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionCallViolation {
+    pub name: String,
+    pub errors: Vec<String>,
+}
+
+impl std::fmt::Display for FunctionCallViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name, self.errors.join("; "))
+    }
+}
+
+/// 从请求体的 tools[].functionDeclarations[] 中收集 函数名 -> parameters schema
+/// 需在 wrap_request 清洗 schema 之前调用，以原始声明为准
+pub fn collect_function_schemas(body: &Value) -> HashMap<String, Value> {
+    let mut schemas = HashMap::new();
+    let Some(tools) = body.get("tools").and_then(|t| t.as_array()) else {
+        return schemas;
+    };
+    for decl in tools
+        .iter()
+        .filter_map(|t| t.get("functionDeclarations").and_then(|d| d.as_array()))
+        .flatten()
+    {
+        let Some(name) = decl.get("name").and_then(|n| n.as_str()) else {
+            continue;
+        };
+        let Some(params) = decl
+            .get("parametersJsonSchema")
+            .or_else(|| decl.get("parameters"))
+        else {
+            continue;
+        };
+        schemas.insert(name.to_string(), normalize_schema(params));
+    }
+    schemas
+}
+
+/// Gemini 的 Schema 方言 (`"type": "OBJECT"`、`nullable`) 转为标准 JSON Schema
+fn normalize_schema(schema: &Value) -> Value {
+    match schema {
+        Value::Object(map) => {
+            let mut out = Map::with_capacity(map.len());
+            for (key, value) in map {
+                let normalized = match key.as_str() {
+                    "type" => match value {
+                        Value::String(t) => Value::String(t.to_ascii_lowercase()),
+                        Value::Array(ts) => Value::Array(
+                            ts.iter()
+                                .map(|t| match t {
+                                    Value::String(s) => Value::String(s.to_ascii_lowercase()),
+                                    other => other.clone(),
+                                })
+                                .collect(),
+                        ),
+                        other => other.clone(),
+                    },
+                    // 属性名映射中的键是字段名，不能当作 schema 关键字处理
+                    "properties" | "$defs" | "definitions" => match value {
+                        Value::Object(props) => Value::Object(
+                            props
+                                .iter()
+                                .map(|(k, v)| (k.clone(), normalize_schema(v)))
+                                .collect(),
+                        ),
+                        other => other.clone(),
+                    },
+                    _ => normalize_schema(value),
+                };
+                out.insert(key.clone(), normalized);
+            }
+            if out.remove("nullable") == Some(Value::Bool(true)) {
+                if let Some(Value::String(t)) = out.get("type").cloned() {
+                    out.insert(
+                        "type".to_string(),
+                        Value::Array(vec![Value::String(t), Value::String("null".to_string())]),
+                    );
+                }
+            }
+            Value::Object(out)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(normalize_schema).collect()),
+        other => other.clone(),
+    }
+}
+
+/// 校验响应 (已 unwrap) 中所有 functionCall 的参数
+/// 未声明的函数或无法编译的 schema 不做校验
+pub fn validate_function_calls(
+    response: &Value,
+    schemas: &HashMap<String, Value>,
+) -> Vec<FunctionCallViolation> {
+    let mut violations = Vec::new();
+    if schemas.is_empty() {
+        return violations;
+    }
+    let Some(candidates) = response.get("candidates").and_then(|c| c.as_array()) else {
+        return violations;
+    };
+
+    let calls = candidates
+        .iter()
+        .filter_map(|c| {
+            c.get("content")
+                .and_then(|c| c.get("parts"))
+                .and_then(|p| p.as_array())
+        })
+        .flatten()
+        .filter_map(|part| part.get("functionCall"));
+
+    for call in calls {
+        let Some(name) = call.get("name").and_then(|n| n.as_str()) else {
+            continue;
+        };
+        let Some(schema) = schemas.get(name) else {
+            continue;
+        };
+        let validator = match jsonschema::validator_for(schema) {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::debug!("[Schema-Validator] Skip {}: invalid schema ({})", name, e);
+                continue;
+            }
+        };
+        let empty = Value::Object(Map::new());
+        let args = call.get("args").unwrap_or(&empty);
+        let errors: Vec<String> = validator
+            .iter_errors(args)
+            .map(|e| {
+                let path = e.instance_path.to_string();
+                if path.is_empty() {
+                    e.to_string()
+                } else {
+                    format!("{} at {}", e, path)
+                }
+            })
+            .collect();
+        if !errors.is_empty() {
+            violations.push(FunctionCallViolation {
+                name: name.to_string(),
+                errors,
+            });
+        }
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request() -> Value {
+        json!({
+            "tools": [{
+                "functionDeclarations": [{
+                    "name": "get_weather",
+                    "parameters": {
+                        "type": "OBJECT",
+                        "properties": {
+                            "city": {"type": "STRING"},
+                            "days": {"type": "INTEGER", "nullable": true},
+                            "type": {"type": "STRING", "enum": ["c", "f"]}
+                        },
+                        "required": ["city"]
+                    }
+                }]
+            }]
+        })
+    }
+
+    fn response_with_args(args: Value) -> Value {
+        json!({
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [
+                        {"text": "checking"},
+                        {"functionCall": {"name": "get_weather", "args": args}}
+                    ]
+                }
+            }]
+        })
+    }
+
+    #[test]
+    fn test_valid_args_pass() {
+        let schemas = collect_function_schemas(&request());
+        let resp = response_with_args(json!({"city": "Paris", "days": null, "type": "c"}));
+        assert!(validate_function_calls(&resp, &schemas).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_args_reported() {
+        let schemas = collect_function_schemas(&request());
+        let resp = response_with_args(json!({"days": "three", "type": "k"}));
+        let violations = validate_function_calls(&resp, &schemas);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].name, "get_weather");
+        // 缺少 city、days 类型错误、type 不在枚举内
+        assert_eq!(violations[0].errors.len(), 3, "{:?}", violations[0].errors);
+    }
+
+    #[test]
+    fn test_undeclared_function_is_ignored() {
+        let schemas = collect_function_schemas(&request());
+        let resp = json!({
+            "candidates": [{"content": {"parts": [
+                {"functionCall": {"name": "unknown_tool", "args": {"x": 1}}}
+            ]}}]
+        });
+        assert!(validate_function_calls(&resp, &schemas).is_empty());
+        assert!(collect_function_schemas(&json!({})).is_empty());
+    }
+}
@@ -1,6 +1,8 @@
 // Gemini v1internal 包装/解包
 use serde_json::{json, Value};
 
+use crate::proxy::config::{IdentityInjectionConfig, IdentityInjectionMode, SafetySettingsPolicy};
+
 /// 包装请求体为 v1internal 格式
 pub fn wrap_request(
     body: &Value,
@@ -27,6 +29,10 @@ pub fn wrap_request(
     // 深度清理 [undefined] 字符串 (Cherry Studio 等客户端常见注入)
     crate::proxy::mappers::common_utils::deep_clean_undefined(&mut inner_request);
 
+    // [NEW] safetySettings: 缺省时注入默认值，开启覆盖时替换客户端设置，并清洗条目
+    let safety_policy = crate::proxy::config::get_safety_settings_policy();
+    apply_safety_settings(&mut inner_request, &safety_policy);
+
     // [FIX #1522] Inject dummy IDs for Claude models in Gemini protocol
     // Google v1internal requires 'id' for tool calls when the model is Claude,
     // even though the standard Gemini protocol doesn't have it.
@@ -154,57 +160,16 @@ pub fn wrap_request(
             if let Some(budget_val) = thinking_config.get("thinkingBudget") {
                 if let Some(budget) = budget_val.as_u64() {
                     let tb_config = crate::proxy::config::get_thinking_budget_config();
-                    let final_budget = match tb_config.mode {
-                        crate::proxy::config::ThinkingBudgetMode::Passthrough => {
-                            // 透传模式：不做任何修改，完全使用上游传入值
-                            tracing::debug!(
-                                "[Gemini-Wrap] Passthrough mode: keeping budget {} for model {}",
-                                budget,
-                                final_model_name
-                            );
-                            budget
-                        }
-                        crate::proxy::config::ThinkingBudgetMode::Custom => {
-                            // 自定义模式：使用全局配置的固定值
-                            // [FIX #1592] Even in Custom mode, cap to 24576 for known Gemini thinking limit
-                            let val = tb_config.custom_value as u64;
-                            let is_limited = (final_model_name.contains("gemini") || final_model_name.contains("thinking"))
-                                && !final_model_name.contains("-image");
-
-                            if is_limited && val > 24576 {
-                                tracing::warn!(
-                                    "[Gemini-Wrap] Custom mode: capping thinking_budget from {} to 24576 for model {}",
-                                    val, final_model_name
-                                );
-                                24576
-                            } else {
-                                if val != budget {
-                                    tracing::debug!(
-                                        "[Gemini-Wrap] Custom mode: overriding {} with {} for model {}",
-                                        budget, val, final_model_name
-                                    );
-                                }
-                                val
-                            }
-                        }
-                        crate::proxy::config::ThinkingBudgetMode::Auto => {
-                            // 自动模式：应用 24576 capping (除画图模型外)
-                            let is_limited = (final_model_name.contains("gemini") || final_model_name.contains("thinking"))
-                                && !final_model_name.contains("-image");
-
-                            if is_limited && budget > 24576 {
-                                tracing::info!(
-                                    "[Gemini-Wrap] Auto mode: capping thinking_budget from {} to 24576 for model {}", 
-                                    budget, final_model_name
-                                );
-                                24576
-                            } else {
-                                budget
-                            }
-                        }
-                    };
-
+                    // [FIX #1592] Gemini 思考类模型受 24576 上限约束 (画图模型除外)；
+                    // 按模型覆盖在上限之后生效 (精确匹配 > 前缀匹配)
+                    let is_limited = (final_model_name.contains("gemini") || final_model_name.contains("thinking"))
+                        && !final_model_name.contains("-image");
+                    let final_budget = tb_config.resolve_budget(&final_model_name, budget, is_limited);
                     if final_budget != budget {
+                        tracing::debug!(
+                            "[Gemini-Wrap] {:?} mode: thinking_budget {} -> {} for model {}",
+                            tb_config.mode, budget, final_budget, final_model_name
+                        );
                         thinking_config["thinkingBudget"] = json!(final_budget);
                     }
                 }
@@ -335,64 +300,8 @@ pub fn wrap_request(
         }
     } else {
         // [NEW] 只在非图像生成模式下注入 Antigravity 身份 (原始简化版)
-        let antigravity_identity = "You are Antigravity, a powerful agentic AI coding assistant designed by the Google Deepmind team working on Advanced Agentic Coding.\n\
-        You are pair programming with a USER to solve their coding task. The task may require creating a new codebase, modifying or debugging an existing codebase, or simply answering a question.\n\
-        **Absolute paths only**\n\
-        **Proactiveness**";
-
-        // [HYBRID] 检查是否已有 systemInstruction
-        if let Some(system_instruction) = inner_request.get_mut("systemInstruction") {
-            // [NEW] 补全 role: user
-            if let Some(obj) = system_instruction.as_object_mut() {
-                if !obj.contains_key("role") {
-                    obj.insert("role".to_string(), json!("user"));
-                }
-            }
-
-            if let Some(parts) = system_instruction.get_mut("parts") {
-                if let Some(parts_array) = parts.as_array_mut() {
-                    // 检查第一个 part 是否已包含 Antigravity 身份
-                    let has_antigravity = parts_array
-                        .get(0)
-                        .and_then(|p| p.get("text"))
-                        .and_then(|t| t.as_str())
-                        .map(|s| s.contains("You are Antigravity"))
-                        .unwrap_or(false);
-
-                    if !has_antigravity {
-                        // 在前面插入 Antigravity 身份
-                        parts_array.insert(0, json!({"text": antigravity_identity}));
-                    }
-
-                    // [NEW] 注入全局系统提示词 (紧跟 Antigravity 身份之后，用户指令之前)
-                    let global_prompt_config = crate::proxy::config::get_global_system_prompt();
-                    if global_prompt_config.enabled
-                        && !global_prompt_config.content.trim().is_empty()
-                    {
-                        // 插入位置：Antigravity 身份之后 (index 1)
-                        let insert_pos = if has_antigravity { 1 } else { 1 };
-                        if insert_pos <= parts_array.len() {
-                            parts_array
-                                .insert(insert_pos, json!({"text": global_prompt_config.content}));
-                        } else {
-                            parts_array.push(json!({"text": global_prompt_config.content}));
-                        }
-                    }
-                }
-            }
-        } else {
-            // 没有 systemInstruction,创建一个新的
-            let mut parts = vec![json!({"text": antigravity_identity})];
-            // [NEW] 注入全局系统提示词
-            let global_prompt_config = crate::proxy::config::get_global_system_prompt();
-            if global_prompt_config.enabled && !global_prompt_config.content.trim().is_empty() {
-                parts.push(json!({"text": global_prompt_config.content}));
-            }
-            inner_request["systemInstruction"] = json!({
-                "role": "user",
-                "parts": parts
-            });
-        }
+        let identity_config = crate::proxy::config::get_identity_injection_config();
+        inject_identity(&mut inner_request, &identity_config);
     }
 
     let final_request = json!({
@@ -443,6 +352,148 @@ mod test_fixes {
     }
 }
 
+/// safetySettings 中 Gemini API 接受的字段
+const SAFETY_SETTING_FIELDS: [&str; 3] = ["category", "threshold", "method"];
+
+/// 按策略注入/替换 safetySettings，并像清洗工具 Schema 一样清洗每个条目:
+/// 去掉缺少 category/threshold 的条目与上游不接受的字段，清洗后为空则整体移除
+fn apply_safety_settings(inner_request: &mut Value, policy: &SafetySettingsPolicy) {
+    let Some(obj) = inner_request.as_object_mut() else {
+        return;
+    };
+
+    if let Some(defaults) = &policy.defaults {
+        let client_missing = obj.get("safetySettings").map_or(true, |v| v.is_null());
+        if client_missing || policy.override_client {
+            tracing::debug!(
+                "[Gemini-Wrap] Applying {} default safety setting(s) (override: {})",
+                defaults.len(),
+                policy.override_client
+            );
+            obj.insert("safetySettings".to_string(), json!(defaults));
+        }
+    }
+
+    let Some(settings) = obj.get_mut("safetySettings") else {
+        return;
+    };
+    let cleaned: Vec<Value> = settings
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|setting| {
+            let entry = setting.as_object()?;
+            let has_string = |key: &str| entry.get(key).map_or(false, |v| v.is_string());
+            if !has_string("category") || !has_string("threshold") {
+                return None;
+            }
+            let kept: serde_json::Map<String, Value> = entry
+                .iter()
+                .filter(|(k, _)| SAFETY_SETTING_FIELDS.contains(&k.as_str()))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            Some(Value::Object(kept))
+        })
+        .collect();
+    if cleaned.is_empty() {
+        obj.remove("safetySettings");
+    } else {
+        *settings = Value::Array(cleaned);
+    }
+}
+
+/// 按身份注入配置向 systemInstruction 注入 Antigravity 身份与全局系统提示词
+/// 超出系统提示词预算时使用短版本，用户的 systemInstruction 保持不变
+fn inject_identity(inner_request: &mut Value, identity_config: &IdentityInjectionConfig) {
+    let budget = crate::proxy::mappers::system_prompt_budget::get_system_prompt_budget();
+    let global_prompt_config = crate::proxy::config::get_global_system_prompt();
+    let global_prompt = Some(global_prompt_config.content.as_str())
+        .filter(|c| global_prompt_config.enabled && !c.trim().is_empty());
+    let custom_identity = Some(identity_config.custom_template.trim())
+        .filter(|t| !t.is_empty());
+
+    // [HYBRID] 检查是否已有 systemInstruction
+    if let Some(system_instruction) = inner_request.get_mut("systemInstruction") {
+        // [NEW] 补全 role: user
+        if let Some(obj) = system_instruction.as_object_mut() {
+            if !obj.contains_key("role") {
+                obj.insert("role".to_string(), json!("user"));
+            }
+        }
+
+        if let Some(parts) = system_instruction.get_mut("parts") {
+            if let Some(parts_array) = parts.as_array_mut() {
+                // 检查第一个 part 是否已包含 Antigravity (或自定义) 身份
+                let first_text = parts_array
+                    .get(0)
+                    .and_then(|p| p.get("text"))
+                    .and_then(|t| t.as_str());
+                let has_identity = match identity_config.mode {
+                    IdentityInjectionMode::Custom => first_text
+                        .zip(custom_identity)
+                        .map(|(s, t)| s.starts_with(t))
+                        .unwrap_or(false),
+                    _ => first_text
+                        .map(|s| s.contains("You are Antigravity"))
+                        .unwrap_or(false),
+                };
+
+                let identity = match identity_config.mode {
+                    // 用户已提供 systemInstruction，不再注入
+                    IdentityInjectionMode::OnlyWhenMissing | IdentityInjectionMode::Never => {
+                        None
+                    }
+                    IdentityInjectionMode::Custom => custom_identity.map(str::to_string),
+                    IdentityInjectionMode::Always => Some(
+                        budget
+                            .identity(
+                                parts_array
+                                    .iter()
+                                    .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                                    .chain(global_prompt),
+                            )
+                            .to_string(),
+                    ),
+                };
+                let injected = match identity {
+                    Some(identity) if !has_identity => {
+                        // 在前面插入身份
+                        parts_array.insert(0, json!({"text": identity}));
+                        true
+                    }
+                    _ => false,
+                };
+
+                // [NEW] 注入全局系统提示词 (紧跟身份之后，用户指令之前)
+                if global_prompt.is_some() {
+                    let insert_pos = if injected || has_identity { 1 } else { 0 };
+                    parts_array.insert(insert_pos, json!({"text": global_prompt_config.content}));
+                }
+            }
+        }
+    } else {
+        // 没有 systemInstruction,创建一个新的
+        let identity = match identity_config.mode {
+            IdentityInjectionMode::Always | IdentityInjectionMode::OnlyWhenMissing => {
+                Some(budget.identity(global_prompt).to_string())
+            }
+            IdentityInjectionMode::Custom => custom_identity.map(str::to_string),
+            IdentityInjectionMode::Never => None,
+        };
+        let mut parts: Vec<Value> = identity.map(|t| json!({"text": t})).into_iter().collect();
+        // [NEW] 注入全局系统提示词
+        if let Some(content) = global_prompt {
+            parts.push(json!({"text": content}));
+        }
+        if !parts.is_empty() {
+            inner_request["systemInstruction"] = json!({
+                "role": "user",
+                "parts": parts
+            });
+        }
+    }
+}
+
 /// 解包响应（提取 response 字段）
 pub fn unwrap_response(response: &Value) -> Value {
     response.get("response").unwrap_or(response).clone()
@@ -660,6 +711,97 @@ mod tests {
         assert_eq!(parts.len(), 1);
     }
 
+    fn identity_config(mode: IdentityInjectionMode, template: &str) -> IdentityInjectionConfig {
+        IdentityInjectionConfig {
+            mode,
+            custom_template: template.to_string(),
+        }
+    }
+
+    fn system_texts(request: &Value) -> Vec<String> {
+        request["systemInstruction"]["parts"]
+            .as_array()
+            .map(|parts| {
+                parts
+                    .iter()
+                    .filter_map(|p| p["text"].as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_identity_injection_always() {
+        let config = identity_config(IdentityInjectionMode::Always, "");
+
+        let mut with_user = json!({"systemInstruction": {"parts": [{"text": "User prompt"}]}});
+        inject_identity(&mut with_user, &config);
+        let texts = system_texts(&with_user);
+        assert_eq!(texts.len(), 2);
+        assert!(texts[0].contains("You are Antigravity"));
+        assert_eq!(with_user["systemInstruction"]["role"], "user");
+
+        // 已包含身份时不重复注入
+        let mut existing =
+            json!({"systemInstruction": {"parts": [{"text": "You are Antigravity..."}]}});
+        inject_identity(&mut existing, &config);
+        assert_eq!(system_texts(&existing).len(), 1);
+    }
+
+    #[test]
+    fn test_identity_injection_only_when_missing() {
+        let config = identity_config(IdentityInjectionMode::OnlyWhenMissing, "");
+
+        let mut with_user = json!({"systemInstruction": {"parts": [{"text": "I am Bob"}]}});
+        inject_identity(&mut with_user, &config);
+        assert_eq!(system_texts(&with_user), vec!["I am Bob".to_string()]);
+        assert_eq!(with_user["systemInstruction"]["role"], "user");
+
+        let mut missing = json!({"contents": []});
+        inject_identity(&mut missing, &config);
+        let texts = system_texts(&missing);
+        assert_eq!(texts.len(), 1);
+        assert!(texts[0].contains("You are Antigravity"));
+    }
+
+    #[test]
+    fn test_identity_injection_never() {
+        let config = identity_config(IdentityInjectionMode::Never, "ignored");
+
+        let mut with_user = json!({"systemInstruction": {"parts": [{"text": "I am Bob"}]}});
+        inject_identity(&mut with_user, &config);
+        assert_eq!(system_texts(&with_user), vec!["I am Bob".to_string()]);
+
+        let mut missing = json!({"contents": []});
+        inject_identity(&mut missing, &config);
+        assert!(missing.get("systemInstruction").is_none());
+    }
+
+    #[test]
+    fn test_identity_injection_custom_template() {
+        let config = identity_config(IdentityInjectionMode::Custom, "You are Nova.");
+
+        let mut with_user = json!({"systemInstruction": {"parts": [{"text": "Be brief"}]}});
+        inject_identity(&mut with_user, &config);
+        assert_eq!(
+            system_texts(&with_user),
+            vec!["You are Nova.".to_string(), "Be brief".to_string()]
+        );
+
+        // 已以模板开头时不重复注入
+        inject_identity(&mut with_user, &config);
+        assert_eq!(system_texts(&with_user).len(), 2);
+
+        let mut missing = json!({"contents": []});
+        inject_identity(&mut missing, &config);
+        assert_eq!(system_texts(&missing), vec!["You are Nova.".to_string()]);
+
+        // 模板为空时不注入
+        let mut empty = json!({"contents": []});
+        inject_identity(&mut empty, &identity_config(IdentityInjectionMode::Custom, "  "));
+        assert!(empty.get("systemInstruction").is_none());
+    }
+
     #[test]
     fn test_image_generation_with_reference_images() {
         // Create 14 reference images + 1 text prompt
@@ -701,6 +843,7 @@ mod tests {
         update_thinking_budget_config(ThinkingBudgetConfig {
             mode: ThinkingBudgetMode::Custom,
             custom_value: 1024, // Distinct value
+            ..Default::default()
         });
 
         let body = json!({
@@ -740,6 +883,7 @@ mod tests {
             crate::proxy::config::ThinkingBudgetConfig {
                 mode: crate::proxy::config::ThinkingBudgetMode::Auto,
                 custom_value: 24576,
+                ..Default::default()
             },
         );
 
@@ -805,4 +949,48 @@ mod tests {
         assert_eq!(image_config_2["aspectRatio"], "1:1");
         assert_eq!(image_config_2["imageSize"], "1K");
     }
+
+    #[test]
+    fn test_safety_settings_default_and_override() {
+        let setting = |category: &str, threshold: &str| crate::proxy::config::SafetySetting {
+            category: category.to_string(),
+            threshold: threshold.to_string(),
+        };
+        let mut policy = SafetySettingsPolicy {
+            defaults: Some(vec![setting("HARM_CATEGORY_DANGEROUS_CONTENT", "BLOCK_NONE")]),
+            override_client: false,
+        };
+
+        // 客户端未传: 注入默认值
+        let mut request = json!({ "contents": [] });
+        apply_safety_settings(&mut request, &policy);
+        assert_eq!(request["safetySettings"][0]["threshold"], "BLOCK_NONE");
+
+        // 客户端已传: 保留并清洗 (去掉非法条目与多余字段)
+        let client = json!([
+            { "category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_LOW_AND_ABOVE", "extra": 1 },
+            { "category": "HARM_CATEGORY_HATE_SPEECH" },
+            "invalid"
+        ]);
+        let mut request = json!({ "safetySettings": client.clone() });
+        apply_safety_settings(&mut request, &policy);
+        assert_eq!(
+            request["safetySettings"],
+            json!([{ "category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_LOW_AND_ABOVE" }])
+        );
+
+        // 开启覆盖: 无条件替换
+        policy.override_client = true;
+        let mut request = json!({ "safetySettings": client });
+        apply_safety_settings(&mut request, &policy);
+        assert_eq!(
+            request["safetySettings"],
+            json!([{ "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "threshold": "BLOCK_NONE" }])
+        );
+
+        // 未配置默认值且清洗后为空: 移除字段
+        let mut request = json!({ "safetySettings": [{ "threshold": "OFF" }] });
+        apply_safety_settings(&mut request, &SafetySettingsPolicy::default());
+        assert!(request.get("safetySettings").is_none());
+    }
 }
@@ -9,4 +9,5 @@ pub mod estimation_calibrator;
 pub mod gemini;
 pub mod openai;
 pub mod signature_store;
+pub mod system_prompt_budget;
 pub mod tool_result_compressor;
@@ -168,6 +168,7 @@ where
     response.choices.push(Choice {
         index: 0,
         message,
+        logprobs: None,
         finish_reason: finish_reason.or(Some("stop".to_string())),
     });
 
@@ -0,0 +1,244 @@
+// OpenAI Embeddings ↔ Gemini batchEmbedContents 协议转换
+
+use base64::Engine as _;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// 未指定或使用 OpenAI 模型名时的默认 Gemini 向量模型
+pub const DEFAULT_EMBEDDING_MODEL: &str = "gemini-embedding-001";
+
+/// batchEmbedContents 单次请求的默认最大条数 (Gemini 上限为 100)
+pub const DEFAULT_EMBEDDING_BATCH_SIZE: usize = 100;
+
+static BATCH_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_EMBEDDING_BATCH_SIZE);
+
+pub fn get_embedding_batch_size() -> usize {
+    BATCH_SIZE.load(Ordering::Relaxed).max(1)
+}
+
+/// 更新拆分批次大小 (启动 / 保存配置时调用)
+pub fn update_embedding_batch_size(size: usize) {
+    BATCH_SIZE.store(size.max(1), Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    Single(String),
+    Batch(Vec<String>),
+}
+
+impl EmbeddingInput {
+    pub fn into_vec(self) -> Vec<String> {
+        match self {
+            EmbeddingInput::Single(s) => vec![s],
+            EmbeddingInput::Batch(v) => v,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmbeddingRequest {
+    pub model: Option<String>,
+    pub input: EmbeddingInput,
+    /// `float` (默认) 或 `base64`
+    pub encoding_format: Option<String>,
+    pub dimensions: Option<u32>,
+}
+
+/// 是否为向量模型 (Gemini `text-embedding-*` / `gemini-embedding-*` 等)
+pub fn is_embedding_model(model: &str) -> bool {
+    model.to_lowercase().contains("embedding")
+}
+
+/// 将 OpenAI 向量模型名 (如 `text-embedding-3-small`) 映射为 Gemini 向量模型
+pub fn map_embedding_model(model: &str) -> String {
+    let lower = model.to_lowercase();
+    if lower.starts_with("text-embedding-3") || lower.starts_with("text-embedding-ada") {
+        DEFAULT_EMBEDDING_MODEL.to_string()
+    } else {
+        model.to_string()
+    }
+}
+
+/// 构建 v1internal batchEmbedContents 请求体
+pub fn build_embedding_request(
+    inputs: &[String],
+    model: &str,
+    project_id: &str,
+    dimensions: Option<u32>,
+) -> Value {
+    let requests: Vec<Value> = inputs
+        .iter()
+        .map(|text| {
+            let mut req = json!({
+                "model": format!("models/{}", model),
+                "content": { "parts": [{ "text": text }] }
+            });
+            if let Some(dim) = dimensions {
+                req["outputDimensionality"] = json!(dim);
+            }
+            req
+        })
+        .collect();
+
+    json!({
+        "project": project_id,
+        "requestId": format!("agent-{}", uuid::Uuid::new_v4()),
+        "model": model,
+        "userAgent": "antigravity",
+        "requestType": "embedding",
+        "request": { "requests": requests }
+    })
+}
+
+/// 按顺序合并多个批次的上游响应，供 transform_embedding_response 统一转换
+pub fn merge_embedding_responses(responses: &[Value]) -> Result<Value, String> {
+    let mut merged = Vec::new();
+    for resp in responses {
+        let raw = resp.get("response").unwrap_or(resp);
+        let embeddings = raw
+            .get("embeddings")
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| "Upstream response missing 'embeddings'".to_string())?;
+        merged.extend(embeddings.iter().cloned());
+    }
+    Ok(json!({ "embeddings": merged }))
+}
+
+/// 将 Gemini 向量响应转换为 OpenAI `list` 格式
+pub fn transform_embedding_response(
+    gemini_resp: &Value,
+    model: &str,
+    inputs: &[String],
+    base64_output: bool,
+) -> Result<Value, String> {
+    let raw = gemini_resp.get("response").unwrap_or(gemini_resp);
+    let embeddings = raw
+        .get("embeddings")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| "Upstream response missing 'embeddings'".to_string())?;
+
+    let mut data = Vec::with_capacity(embeddings.len());
+    for (index, item) in embeddings.iter().enumerate() {
+        let values: Vec<f32> = item
+            .get("values")
+            .and_then(|v| v.as_array())
+            .map(|arr| arr.iter().filter_map(|x| x.as_f64()).map(|x| x as f32).collect())
+            .unwrap_or_default();
+
+        let embedding = if base64_output {
+            // OpenAI base64 格式: little-endian f32 数组
+            let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+            json!(base64::engine::general_purpose::STANDARD.encode(bytes))
+        } else {
+            json!(values)
+        };
+
+        data.push(json!({
+            "object": "embedding",
+            "index": index,
+            "embedding": embedding
+        }));
+    }
+
+    // Gemini 向量接口不返回 token 用量，按输入文本估算
+    let prompt_tokens: u32 = inputs
+        .iter()
+        .map(|s| crate::proxy::mappers::context_manager::estimate_tokens_from_str(s))
+        .sum();
+
+    Ok(json!({
+        "object": "list",
+        "data": data,
+        "model": model,
+        "usage": {
+            "prompt_tokens": prompt_tokens,
+            "total_tokens": prompt_tokens
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_single_and_batch() {
+        let single: EmbeddingRequest =
+            serde_json::from_value(json!({ "model": "text-embedding-004", "input": "hi" })).unwrap();
+        assert_eq!(single.input.into_vec(), vec!["hi".to_string()]);
+
+        let batch: EmbeddingRequest =
+            serde_json::from_value(json!({ "input": ["a", "b"], "encoding_format": "base64" }))
+                .unwrap();
+        assert_eq!(batch.encoding_format.as_deref(), Some("base64"));
+        assert_eq!(batch.input.into_vec().len(), 2);
+    }
+
+    #[test]
+    fn test_model_checks() {
+        assert!(is_embedding_model("text-embedding-004"));
+        assert!(is_embedding_model("gemini-embedding-001"));
+        assert!(!is_embedding_model("gemini-2.5-flash"));
+        assert_eq!(map_embedding_model("text-embedding-3-small"), DEFAULT_EMBEDDING_MODEL);
+        assert_eq!(map_embedding_model("text-embedding-004"), "text-embedding-004");
+    }
+
+    #[test]
+    fn test_transform_response_float_and_base64() {
+        let upstream = json!({
+            "response": {
+                "embeddings": [{ "values": [0.5, -1.0] }, { "values": [0.25] }]
+            }
+        });
+        let inputs = vec!["hello".to_string(), "world".to_string()];
+
+        let out = transform_embedding_response(&upstream, "gemini-embedding-001", &inputs, false)
+            .unwrap();
+        assert_eq!(out["object"], "list");
+        assert_eq!(out["data"][0]["embedding"], json!([0.5, -1.0]));
+        assert_eq!(out["data"][1]["index"], 1);
+        assert!(out["usage"]["prompt_tokens"].as_u64().unwrap() > 0);
+
+        let out = transform_embedding_response(&upstream, "gemini-embedding-001", &inputs, true)
+            .unwrap();
+        let encoded = out["data"][0]["embedding"].as_str().unwrap();
+        let bytes = base64::engine::general_purpose::STANDARD.decode(encoded).unwrap();
+        assert_eq!(bytes.len(), 8);
+        assert_eq!(f32::from_le_bytes(bytes[0..4].try_into().unwrap()), 0.5);
+    }
+
+    #[test]
+    fn test_merge_batches_keeps_order() {
+        let inputs: Vec<String> = (0..5).map(|i| format!("text {}", i)).collect();
+        let batches: Vec<Value> = inputs
+            .chunks(2)
+            .map(|chunk| {
+                let embeddings: Vec<Value> = chunk
+                    .iter()
+                    .map(|t| json!({ "values": [t.len() as f64, t[5..].parse::<f64>().unwrap()] }))
+                    .collect();
+                json!({ "response": { "embeddings": embeddings } })
+            })
+            .collect();
+        assert_eq!(batches.len(), 3);
+
+        let merged = merge_embedding_responses(&batches).unwrap();
+        let out = transform_embedding_response(&merged, "m", &inputs, false).unwrap();
+        let data = out["data"].as_array().unwrap();
+        assert_eq!(data.len(), 5);
+        for (i, item) in data.iter().enumerate() {
+            assert_eq!(item["index"], i);
+            assert_eq!(item["embedding"][1], json!(i as f32));
+        }
+
+        assert!(merge_embedding_responses(&[json!({"response": {}})]).is_err());
+    }
+
+    #[test]
+    fn test_transform_response_missing_embeddings() {
+        assert!(transform_embedding_response(&json!({}), "m", &[], false).is_err());
+    }
+}
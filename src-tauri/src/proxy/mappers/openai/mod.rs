@@ -6,7 +6,9 @@ pub mod request;
 pub mod response;
 pub mod streaming;
 pub mod collector; // [NEW]
+pub mod embeddings;
 pub mod thinking_recovery;
+pub mod vision; // image_url 规范化 (data URL / 远程图片 / detail)
 
 pub use models::*;
 pub use request::*;
@@ -40,6 +40,33 @@ pub struct OpenAIRequest {
     // [NEW] Thinking/Extended Thinking 支持 (兼容 Anthropic/Claude 协议)
     #[serde(default)]
     pub thinking: Option<ThinkingConfig>,
+    /// 返回 token 级对数概率 (映射为 Gemini responseLogprobs)
+    #[serde(default)]
+    pub logprobs: Option<bool>,
+    /// 每个位置返回的候选 token 数 (映射为 Gemini logprobs)
+    #[serde(default)]
+    pub top_logprobs: Option<u32>,
+    /// 流式选项 (include_usage: 结束前发送 usage 块)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+}
+
+/// `stream_options`；include_obfuscation 等其余字段忽略
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreamOptions {
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
+impl OpenAIRequest {
+    /// 客户端是否要求在流结束前返回独立的 usage 块
+    pub fn wants_stream_usage(&self) -> bool {
+        self.stream
+            && self
+                .stream_options
+                .as_ref()
+                .is_some_and(|o| o.include_usage)
+    }
 }
 
 /// Thinking 配置 (兼容 Anthropic 和 OpenAI 扩展协议)
@@ -129,9 +156,32 @@ pub struct OpenAIResponse {
 pub struct Choice {
     pub index: u32,
     pub message: OpenAIMessage,
+    /// 仅当上游返回 logprobsResult 时存在 (客户端请求 logprobs 且模型支持)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<ChoiceLogprobs>,
     pub finish_reason: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChoiceLogprobs {
+    pub content: Vec<TokenLogprob>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenLogprob {
+    pub token: String,
+    pub logprob: f64,
+    pub bytes: Option<Vec<u8>>,
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopLogprob {
+    pub token: String,
+    pub logprob: f64,
+    pub bytes: Option<Vec<u8>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenAIUsage {
     pub prompt_tokens: u32,
@@ -235,14 +235,12 @@ pub fn transform_openai_request(
                                 }
                                 OpenAIContentBlock::ImageUrl { image_url } => {
                                     if image_url.url.starts_with("data:") {
-                                        if let Some(pos) = image_url.url.find(",") {
-                                            let mime_part = &image_url.url[5..pos];
-                                            let mime_type = mime_part.split(';').next().unwrap_or("image/jpeg");
-                                            let data = &image_url.url[pos + 1..];
-                                            
-                                            parts.push(json!({
-                                                "inlineData": { "mimeType": mime_type, "data": data }
-                                            }));
+                                        // [FIX] 健壮解析 data URL (空白/URL-safe/缺 padding)，MIME 以文件头为准
+                                        match super::vision::parse_data_url(&image_url.url) {
+                                            Ok(image) => parts.push(json!({
+                                                "inlineData": { "mimeType": image.mime_type, "data": image.data }
+                                            })),
+                                            Err(e) => tracing::warn!("[OpenAI-Request] Skipping malformed image data URL: {}", e),
                                         }
                                     } else if image_url.url.starts_with("http") {
                                         parts.push(json!({
@@ -413,6 +411,16 @@ pub fn transform_openai_request(
         gen_config["candidateCount"] = json!(n);
     }
 
+    // [NEW] logprobs -> responseLogprobs / top_logprobs -> logprobs
+    // 仅 Gemini 模型支持，其余模型不透传，响应中也就不会带 logprobs
+    if request.logprobs == Some(true) && mapped_model_lower.starts_with("gemini") {
+        gen_config["responseLogprobs"] = json!(true);
+        if let Some(top) = request.top_logprobs.filter(|n| *n > 0) {
+            // Gemini 最多返回 20 个候选
+            gen_config["logprobs"] = json!(top.min(20));
+        }
+    }
+
     // 为 thinking 模型注入 thinkingConfig (使用 thinkingBudget 而非 thinkingLevel)
     if actual_include_thinking {
         // [RESOLVE #1694] Check image thinking mode
@@ -431,54 +439,16 @@ pub fn transform_openai_request(
             // [FIX #1592] 下调默认 budget 到 24576，以更好地兼容不支持 32k 的 Gemini 原生模型 (如 gemini-3-pro)
             let user_budget: i64 = user_thinking_budget.map(|b| b as i64).unwrap_or(24576);
             
-            let budget = match tb_config.mode {
-                crate::proxy::config::ThinkingBudgetMode::Passthrough => {
-                    // 透传模式：使用用户传入的值，不做任何限制
-                    tracing::debug!(
-                        "[OpenAI-Request] Passthrough mode: using caller's budget {}",
-                        user_budget
-                    );
-                    user_budget
-                }
-                crate::proxy::config::ThinkingBudgetMode::Custom => {
-                    // 自定义模式：使用全局配置的固定值
-                    let mut custom_value = tb_config.custom_value as i64;
-                    
-                    // [FIX #1592/1602] 针对 Gemini 类模型强制执行 24576 上限 (除画图模型外，见用户反馈)
-                    let is_gemini_limited = (mapped_model_lower.contains("gemini") && !mapped_model_lower.contains("-image"))
-                        || is_claude_thinking; 
-
-                    if is_gemini_limited && custom_value > 24576 {
-                        tracing::warn!(
-                            "[OpenAI-Request] Custom mode: capping thinking_budget from {} to 24576 for Gemini model {}",
-                            custom_value, mapped_model
-                        );
-                        custom_value = 24576;
-                    }
-
-                    tracing::debug!(
-                        "[OpenAI-Request] Custom mode: overriding {} with fixed value {}",
-                        user_budget,
-                        custom_value
-                    );
-                    custom_value
-                }
-                crate::proxy::config::ThinkingBudgetMode::Auto => {
-                    // [FIX #1592] 拓宽判定逻辑，确保所有 Gemini 思考模型都应用 24k 上限 (除画画模型外)
-                    let is_gemini_limited = (mapped_model_lower.contains("gemini") && !mapped_model_lower.contains("-image"))
-                        || is_claude_thinking;  
-                    
-                    if is_gemini_limited && user_budget > 24576 {
-                        tracing::info!(
-                            "[OpenAI-Request] Auto mode: capping thinking budget from {} to 24576 for model: {}", 
-                            user_budget, mapped_model
-                        );
-                        24576
-                    } else {
-                        user_budget
-                    }
-                }
-            };
+            // [FIX #1592/1602] 针对 Gemini 类模型强制执行 24576 上限 (除画图模型外)，按模型覆盖在上限之后生效
+            let is_gemini_limited = (mapped_model_lower.contains("gemini") && !mapped_model_lower.contains("-image"))
+                || is_claude_thinking;
+            let budget = tb_config.resolve_budget(mapped_model, user_budget.max(0) as u64, is_gemini_limited) as i64;
+            if budget != user_budget {
+                tracing::debug!(
+                    "[OpenAI-Request] {:?} mode: thinking budget {} -> {} for model {}",
+                    tb_config.mode, user_budget, budget, mapped_model
+                );
+            }
 
             gen_config["thinkingConfig"] = json!({
                 "includeThoughts": true,
@@ -513,10 +483,9 @@ pub fn transform_openai_request(
     }
 
     if let Some(stop) = &request.stop {
-        if stop.is_string() {
-            gen_config["stopSequences"] = json!([stop]);
-        } else if stop.is_array() {
-            gen_config["stopSequences"] = stop.clone();
+        let sequences = crate::proxy::mappers::common_utils::normalize_stop_sequences(stop);
+        if !sequences.is_empty() {
+            gen_config["stopSequences"] = json!(sequences);
         }
     }
 
@@ -526,6 +495,13 @@ pub fn transform_openai_request(
         }
     }
 
+    // [NEW] image_url.detail → mediaResolution (仅 Gemini 模型支持)
+    if mapped_model_lower.contains("gemini") {
+        if let Some(resolution) = super::vision::requested_media_resolution(&request.messages) {
+            gen_config["mediaResolution"] = json!(resolution);
+        }
+    }
+
     let mut inner_request = json!({
         "contents": contents,
         "generationConfig": gen_config,
@@ -634,12 +610,6 @@ pub fn transform_openai_request(
         }
     }
 
-    // [NEW] Antigravity 身份指令 (原始简化版)
-    let antigravity_identity = "You are Antigravity, a powerful agentic AI coding assistant designed by the Google Deepmind team working on Advanced Agentic Coding.\n\
-    You are pair programming with a USER to solve their coding task. The task may require creating a new codebase, modifying or debugging an existing codebase, or simply answering a question.\n\
-    **Absolute paths only**\n\
-    **Proactiveness**";
-
     // [HYBRID] 检查用户是否已提供 Antigravity 身份
     let user_has_antigravity = system_instructions
         .iter()
@@ -647,11 +617,6 @@ pub fn transform_openai_request(
 
     let mut parts = Vec::new();
 
-    // 1. Antigravity 身份 (如果需要, 作为独立 Part 插入)
-    if !user_has_antigravity {
-        parts.push(json!({"text": antigravity_identity}));
-    }
-
     // 2. [NEW] 注入全局系统提示词 (紧跟 Antigravity 身份之后)
     let global_prompt_config = crate::proxy::config::get_global_system_prompt();
     if global_prompt_config.enabled && !global_prompt_config.content.trim().is_empty() {
@@ -663,6 +628,14 @@ pub fn transform_openai_request(
         parts.push(json!({"text": inst}));
     }
 
+    // 1. Antigravity 身份 (如果需要, 作为独立 Part 插入到首位)
+    // [NEW] 超出系统提示词预算时使用短版本 (仅影响注入部分)
+    if !user_has_antigravity {
+        let budget = crate::proxy::mappers::system_prompt_budget::get_system_prompt_budget();
+        let identity = budget.identity(parts.iter().filter_map(|p| p["text"].as_str()));
+        parts.insert(0, json!({"text": identity}));
+    }
+
     inner_request["systemInstruction"] = json!({
         "role": "user",
         "parts": parts
@@ -759,6 +732,9 @@ mod tests {
             quality: None,
             person_generation: None,
             thinking: None,
+            logprobs: None,
+            top_logprobs: None,
+            stream_options: None,
         };
 
         // Auto mode (default) should cap gemini-3-pro thinking budget to 24576
@@ -778,6 +754,7 @@ mod tests {
         update_thinking_budget_config(ThinkingBudgetConfig {
             mode: ThinkingBudgetMode::Custom,
             custom_value: 32000,
+            ..Default::default()
         });
 
         let req = OpenAIRequest {
@@ -807,6 +784,9 @@ mod tests {
             quality: None,
             person_generation: None,
             thinking: None,
+            logprobs: None,
+            top_logprobs: None,
+            stream_options: None,
         };
 
         // 验证针对 Gemini 模型即使是 Custom 模式也会被修正为 24576
@@ -829,6 +809,32 @@ mod tests {
         update_thinking_budget_config(ThinkingBudgetConfig::default());
     }
 
+    fn stop_request(stop: Value) -> OpenAIRequest {
+        serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "user", "content": "count to ten" }],
+            "stop": stop
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_stop_string_and_array_to_stop_sequences() {
+        let (result, _, _) =
+            transform_openai_request(&stop_request(json!("5")), "test-v", "gemini-2.5-flash");
+        assert_eq!(result["request"]["generationConfig"]["stopSequences"], json!(["5"]));
+
+        let (result, _, _) = transform_openai_request(
+            &stop_request(json!(["a", "b", "c", "d", "e", "f"])),
+            "test-v",
+            "gemini-2.5-flash",
+        );
+        assert_eq!(
+            result["request"]["generationConfig"]["stopSequences"],
+            json!(["a", "b", "c", "d", "e"])
+        );
+    }
+
     #[test]
     fn test_transform_openai_request_multimodal() {
         let req = OpenAIRequest {
@@ -864,6 +870,9 @@ mod tests {
             quality: None,
             person_generation: None,
             thinking: None,
+            logprobs: None,
+            top_logprobs: None,
+            stream_options: None,
         };
 
         let (result, _sid, _msg_count) = transform_openai_request(&req, "test-v", "gemini-1.5-flash");
@@ -875,6 +884,37 @@ mod tests {
             "image/png"
         );
     }
+
+    #[test]
+    fn test_transform_mixed_images_preserve_order_and_detail() {
+        let png = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8BQDwAEhQGAhKmMIQAAAABJRU5ErkJggg==";
+        let req: OpenAIRequest = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [{
+                "role": "user",
+                "content": [
+                    { "type": "text", "text": "first" },
+                    { "type": "image_url", "image_url": { "url": format!("data:image/jpeg;base64,{}", png), "detail": "high" } },
+                    { "type": "text", "text": "second" },
+                    { "type": "image_url", "image_url": { "url": format!("data:image/png;base64,{}", png), "detail": "low" } }
+                ]
+            }]
+        }))
+        .unwrap();
+
+        let (result, _sid, _msg_count) = transform_openai_request(&req, "test-v", "gemini-2.5-flash");
+        let parts = result["request"]["contents"][0]["parts"].as_array().unwrap();
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[0]["text"], "first");
+        // 声明为 jpeg 的 png 按实际内容修正
+        assert_eq!(parts[1]["inlineData"]["mimeType"], "image/png");
+        assert_eq!(parts[2]["text"], "second");
+        assert_eq!(parts[3]["inlineData"]["data"], png);
+        assert_eq!(
+            result["request"]["generationConfig"]["mediaResolution"],
+            "MEDIA_RESOLUTION_HIGH"
+        );
+    }
     
     #[test]
     fn test_gemini_pro_thinking_injection() {
@@ -909,6 +949,9 @@ mod tests {
             size: None,
             quality: None,
             person_generation: None,
+            logprobs: None,
+            top_logprobs: None,
+            stream_options: None,
         };
 
         // Pass explicit gemini-3-pro-preview which doesn't have "-thinking" suffix
@@ -951,6 +994,9 @@ mod tests {
             size: Some("1024x1024".to_string()),
             quality: Some("hd".to_string()),
             person_generation: None,
+            logprobs: None,
+            top_logprobs: None,
+            stream_options: None,
         };
 
         // Pass gemini-3-pro-image which matches "gemini-3-pro" substring
@@ -994,6 +1040,9 @@ mod tests {
             quality: None,
             person_generation: None,
             thinking: None,
+            logprobs: None,
+            top_logprobs: None,
+            stream_options: None,
         };
 
         let (result, _sid, _msg_count) = transform_openai_request(&req, "test-p", "gemini-3-pro-high-thinking");
@@ -1041,6 +1090,9 @@ mod tests {
             size: None,
             quality: None,
             person_generation: None,
+            logprobs: None,
+            top_logprobs: None,
+            stream_options: None,
         };
 
         // Test with Flash model
@@ -1103,6 +1155,9 @@ mod tests {
             quality: None,
             person_generation: None,
             thinking: None,
+            logprobs: None,
+            top_logprobs: None,
+            stream_options: None,
         };
 
         // Simulate Vertex AI path
@@ -1152,6 +1207,9 @@ mod tests {
             quality: None,
             person_generation: None,
             thinking: None,
+            logprobs: None,
+            top_logprobs: None,
+            stream_options: None,
         };
 
         // 2. Transform request
@@ -2,6 +2,16 @@
 use super::models::*;
 use serde_json::Value;
 
+/// Gemini finishReason -> OpenAI finish_reason
+/// 命中 stopSequences 时 Gemini 同样返回 STOP，对应 OpenAI 的 "stop"
+pub fn map_finish_reason(reason: &str) -> &'static str {
+    match reason {
+        "MAX_TOKENS" => "length",
+        "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" => "content_filter",
+        _ => "stop",
+    }
+}
+
 pub fn transform_openai_response(gemini_response: &Value, session_id: Option<&str>, message_count: usize) -> OpenAIResponse {
     // 解包 response 字段
     let raw = gemini_response.get("response").unwrap_or(gemini_response);
@@ -131,15 +141,11 @@ pub fn transform_openai_response(gemini_response: &Value, session_id: Option<&st
             let finish_reason = candidate
                 .get("finishReason")
                 .and_then(|f| f.as_str())
-                .map(|f| match f {
-                    "STOP" => "stop",
-                    "MAX_TOKENS" => "length",
-                    "SAFETY" => "content_filter",
-                    "RECITATION" => "content_filter",
-                    _ => "stop",
-                })
+                .map(map_finish_reason)
                 .unwrap_or("stop");
 
+            let logprobs = candidate.get("logprobsResult").and_then(map_logprobs_result);
+
             choices.push(Choice {
                 index: idx as u32,
                 message: OpenAIMessage {
@@ -162,6 +168,7 @@ pub fn transform_openai_response(gemini_response: &Value, session_id: Option<&st
                     tool_call_id: None,
                     name: None,
                 },
+                logprobs,
                 finish_reason: Some(finish_reason.to_string()),
             });
         }
@@ -215,6 +222,53 @@ pub fn transform_openai_response(gemini_response: &Value, session_id: Option<&st
     }
 }
 
+/// Gemini logprobsResult -> OpenAI logprobs
+/// chosenCandidates 与 topCandidates 按位置一一对应；只有 avgLogprobs 时无法还原 token 级数据，返回 None
+fn map_logprobs_result(result: &Value) -> Option<ChoiceLogprobs> {
+    fn entry(c: &Value) -> Option<TopLogprob> {
+        let token = c.get("token")?.as_str()?.to_string();
+        let logprob = c.get("logProbability").and_then(|v| v.as_f64()).unwrap_or(0.0);
+        Some(TopLogprob {
+            bytes: Some(token.as_bytes().to_vec()),
+            token,
+            logprob,
+        })
+    }
+
+    let chosen = result.get("chosenCandidates")?.as_array()?;
+    let top = result
+        .get("topCandidates")
+        .and_then(|t| t.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    let content: Vec<TokenLogprob> = chosen
+        .iter()
+        .enumerate()
+        .filter_map(|(i, c)| {
+            let chosen = entry(c)?;
+            let top_logprobs = top
+                .get(i)
+                .and_then(|t| t.get("candidates"))
+                .and_then(|c| c.as_array())
+                .map(|list| list.iter().filter_map(entry).collect())
+                .unwrap_or_default();
+            Some(TokenLogprob {
+                token: chosen.token,
+                logprob: chosen.logprob,
+                bytes: chosen.bytes,
+                top_logprobs,
+            })
+        })
+        .collect();
+
+    if content.is_empty() {
+        None
+    } else {
+        Some(ChoiceLogprobs { content })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -241,6 +295,8 @@ mod tests {
         };
         assert_eq!(content, "Hello!");
         assert_eq!(result.choices[0].finish_reason, Some("stop".to_string()));
+        assert_eq!(map_finish_reason("MAX_TOKENS"), "length");
+        assert_eq!(map_finish_reason("PROHIBITED_CONTENT"), "content_filter");
     }
 
     #[test]
@@ -285,4 +341,61 @@ mod tests {
         let result = transform_openai_response(&gemini_resp, Some("session-123"), 1);
         assert!(result.usage.is_none());
     }
+
+    #[test]
+    fn test_logprobs_mapping() {
+        let gemini_resp = json!({
+            "candidates": [{
+                "content": {"parts": [{"text": "Hi there"}]},
+                "finishReason": "STOP",
+                "avgLogprobs": -0.25,
+                "logprobsResult": {
+                    "topCandidates": [
+                        {"candidates": [
+                            {"token": "Hi", "tokenId": 1, "logProbability": -0.1},
+                            {"token": "Hello", "tokenId": 2, "logProbability": -2.4}
+                        ]},
+                        {"candidates": [
+                            {"token": " there", "tokenId": 3, "logProbability": -0.4}
+                        ]}
+                    ],
+                    "chosenCandidates": [
+                        {"token": "Hi", "tokenId": 1, "logProbability": -0.1},
+                        {"token": " there", "tokenId": 3, "logProbability": -0.4}
+                    ]
+                }
+            }],
+            "modelVersion": "gemini-2.5-flash",
+            "responseId": "resp_123"
+        });
+
+        let result = transform_openai_response(&gemini_resp, None, 1);
+        let logprobs = result.choices[0].logprobs.as_ref().expect("logprobs");
+        assert_eq!(logprobs.content.len(), 2);
+        assert_eq!(logprobs.content[0].token, "Hi");
+        assert_eq!(logprobs.content[0].logprob, -0.1);
+        assert_eq!(logprobs.content[0].bytes.as_deref(), Some("Hi".as_bytes()));
+        assert_eq!(logprobs.content[0].top_logprobs.len(), 2);
+        assert_eq!(logprobs.content[0].top_logprobs[1].token, "Hello");
+        assert_eq!(logprobs.content[1].token, " there");
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["choices"][0]["logprobs"]["content"][1]["logprob"], json!(-0.4));
+    }
+
+    #[test]
+    fn test_logprobs_omitted_without_token_data() {
+        // 只有 avgLogprobs (或模型不支持) 时不输出 logprobs 字段
+        let gemini_resp = json!({
+            "candidates": [{
+                "content": {"parts": [{"text": "Hello!"}]},
+                "finishReason": "STOP",
+                "avgLogprobs": -0.3
+            }]
+        });
+        let result = transform_openai_response(&gemini_resp, None, 1);
+        assert!(result.choices[0].logprobs.is_none());
+        let json = serde_json::to_value(&result).unwrap();
+        assert!(json["choices"][0].get("logprobs").is_none());
+    }
 }
@@ -64,11 +64,14 @@ fn extract_usage_metadata(u: &Value) -> Option<super::models::OpenAIUsage> {
     })
 }
 
+/// `include_usage`: 对应 stream_options.include_usage，为 true 时在 [DONE] 之前发送
+/// `choices` 为空、携带 usage 的终止块 (此时结束块不再附带 usage)
 pub fn create_openai_sse_stream(
     mut gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
     model: String,
     session_id: String,
     message_count: usize,
+    include_usage: bool,
 ) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>> {
     let mut buffer = BytesMut::new();
     let stream_id = format!("chatcmpl-{}", Uuid::new_v4());
@@ -77,6 +80,7 @@ pub fn create_openai_sse_stream(
     let stream = async_stream::stream! {
         let mut emitted_tool_calls = std::collections::HashSet::new();
         let mut final_usage: Option<super::models::OpenAIUsage> = None;
+        let mut last_usage: Option<super::models::OpenAIUsage> = None;
         let mut error_occurred = false;
 
         let mut heartbeat_interval = tokio::time::interval(std::time::Duration::from_secs(15));
@@ -100,6 +104,7 @@ pub fn create_openai_sse_stream(
                                             let actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) { inner } else { json };
                                             if let Some(u) = actual_data.get("usageMetadata") {
                                                 final_usage = extract_usage_metadata(u);
+                                                last_usage = final_usage.clone();
                                             }
 
                                             if let Some(candidates) = actual_data.get("candidates").and_then(|c| c.as_array()) {
@@ -206,13 +211,7 @@ pub fn create_openai_sse_stream(
                                                         if !grounding_text.is_empty() { content_out.push_str(&grounding_text); }
                                                     }
 
-                                                    let gemini_finish_reason = candidate.get("finishReason").and_then(|f| f.as_str()).map(|f| match f {
-                                                        "STOP" => "stop",
-                                                        "MAX_TOKENS" => "length",
-                                                        "SAFETY" => "content_filter",
-                                                        "RECITATION" => "content_filter",
-                                                        _ => f,
-                                                    });
+                                                    let gemini_finish_reason = candidate.get("finishReason").and_then(|f| f.as_str()).map(super::response::map_finish_reason);
 
                                                     // [FIX #1575] 如果发射了工具调用，强制设置为 tool_calls
                                                     // 解决 Gemini 返回 STOP 但有工具调用时，OpenAI 客户端认为对话已结束的问题
@@ -250,8 +249,10 @@ pub fn create_openai_sse_stream(
                                                                 "finish_reason": finish_reason
                                                             }]
                                                         });
-                                                        if let Some(ref usage) = final_usage {
-                                                            openai_chunk["usage"] = serde_json::to_value(usage).unwrap();
+                                                        if !include_usage {
+                                                            if let Some(ref usage) = final_usage {
+                                                                openai_chunk["usage"] = serde_json::to_value(usage).unwrap();
+                                                            }
                                                         }
                                                         if finish_reason.is_some() { final_usage = None; }
                                                         let sse_out = format!("data: {}\n\n", serde_json::to_string(&openai_chunk).unwrap_or_default());
@@ -286,6 +287,17 @@ pub fn create_openai_sse_stream(
             }
         }
         if !error_occurred {
+            if include_usage {
+                let usage_chunk = json!({
+                    "id": &stream_id,
+                    "object": "chat.completion.chunk",
+                    "created": created_ts,
+                    "model": &model,
+                    "choices": [],
+                    "usage": last_usage.as_ref().and_then(|u| serde_json::to_value(u).ok())
+                });
+                yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&usage_chunk).unwrap_or_default())));
+            }
             yield Ok::<Bytes, String>(Bytes::from("data: [DONE]\n\n"));
         }
     };
@@ -465,3 +477,85 @@ pub fn create_codex_sse_stream(
     };
     Box::pin(stream)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GEMINI_SSE: &str = concat!(
+        "data: {\"response\":{\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Hi\"}]}}]}}\n",
+        "data: {\"response\":{\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"!\"}]},\"finishReason\":\"STOP\"}],",
+        "\"usageMetadata\":{\"promptTokenCount\":5,\"candidatesTokenCount\":2,\"totalTokenCount\":7}}}\n",
+    );
+
+    /// 收集 SSE 事件 (忽略心跳)
+    async fn collect_events(include_usage: bool) -> Vec<String> {
+        let upstream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>> =
+            Box::pin(futures::stream::iter(vec![Ok(Bytes::from(GEMINI_SSE))]));
+        let stream = create_openai_sse_stream(
+            upstream,
+            "gpt-4o".to_string(),
+            "sid-test".to_string(),
+            1,
+            include_usage,
+        );
+        stream
+            .filter_map(|item| async move { item.ok() })
+            .map(|bytes| String::from_utf8(bytes.to_vec()).unwrap())
+            .filter(|event| futures::future::ready(!event.starts_with(": ping")))
+            .collect()
+            .await
+    }
+
+    fn data(event: &str) -> Value {
+        serde_json::from_str(event.trim().trim_start_matches("data: ")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_include_usage_emits_terminal_usage_chunk() {
+        let events = collect_events(true).await;
+        assert_eq!(events.len(), 4, "{:?}", events);
+
+        let first = data(&events[0]);
+        assert_eq!(first["choices"][0]["delta"]["content"], "Hi");
+
+        let finish = data(&events[1]);
+        assert_eq!(finish["choices"][0]["finish_reason"], "stop");
+        assert!(finish.get("usage").is_none());
+
+        let usage_chunk = data(&events[2]);
+        assert_eq!(
+            usage_chunk,
+            json!({
+                "id": finish["id"],
+                "object": "chat.completion.chunk",
+                "created": finish["created"],
+                "model": "gpt-4o",
+                "choices": [],
+                "usage": { "prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7 }
+            })
+        );
+
+        assert_eq!(events[3], "data: [DONE]\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_without_include_usage_keeps_usage_on_finish_chunk() {
+        let events = collect_events(false).await;
+        assert_eq!(events.len(), 3, "{:?}", events);
+        assert_eq!(data(&events[1])["usage"]["total_tokens"], 7);
+        assert_eq!(events[2], "data: [DONE]\n\n");
+    }
+
+    #[test]
+    fn test_stream_options_parsing() {
+        let req: super::super::models::OpenAIRequest = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "stream": true,
+            "messages": [],
+            "stream_options": { "include_usage": true, "include_obfuscation": false }
+        }))
+        .unwrap();
+        assert!(req.wants_stream_usage());
+    }
+}
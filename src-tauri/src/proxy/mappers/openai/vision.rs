@@ -0,0 +1,612 @@
+// OpenAI image_url 内容规范化
+// - data: URL 健壮解析 (空白/换行、URL-safe 字母表、百分号编码、缺失 padding)，按文件头嗅探真实 MIME
+// - 远程 https 图片经 UpstreamClient 下载后转为 data: URL (带大小上限)，转换阶段统一输出 inlineData
+// - `detail` 映射为 Gemini generationConfig.mediaResolution
+use axum::http::StatusCode;
+use base64::Engine as _;
+use futures::StreamExt;
+use std::time::Duration;
+
+use super::models::{OpenAIContent, OpenAIContentBlock, OpenAIMessage, OpenAIRequest};
+use crate::proxy::error::ApiError;
+use crate::proxy::upstream::client::UpstreamClient;
+
+/// 单张图片解码后的大小上限 (Gemini inlineData 单请求上限约 20MB)
+pub const MAX_INLINE_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+/// 远程图片下载超时
+const REMOTE_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 解析后的内联图片
+#[derive(Debug, Clone, PartialEq)]
+pub struct InlineImage {
+    pub mime_type: String,
+    /// 标准 base64 (带 padding)
+    pub data: String,
+    /// 解码后的字节数
+    pub size: usize,
+}
+
+impl InlineImage {
+    fn from_bytes(bytes: &[u8], declared_mime: Option<&str>) -> Self {
+        Self {
+            mime_type: resolve_mime(sniff_image_mime(bytes), declared_mime),
+            data: base64::engine::general_purpose::STANDARD.encode(bytes),
+            size: bytes.len(),
+        }
+    }
+
+    pub fn to_data_url(&self) -> String {
+        format!("data:{};base64,{}", self.mime_type, self.data)
+    }
+}
+
+/// 图片内容块处理失败 (带出错的消息与内容块下标)
+#[derive(Debug)]
+pub enum ImagePartError {
+    TooLarge {
+        message_index: usize,
+        part_index: usize,
+        size: usize,
+        limit: usize,
+    },
+    Invalid {
+        message_index: usize,
+        part_index: usize,
+        reason: String,
+    },
+}
+
+impl ImagePartError {
+    fn param(&self) -> String {
+        let (m, p) = match self {
+            ImagePartError::TooLarge { message_index, part_index, .. }
+            | ImagePartError::Invalid { message_index, part_index, .. } => (message_index, part_index),
+        };
+        format!("messages[{}].content[{}]", m, p)
+    }
+
+    pub fn into_api_error(self) -> ApiError {
+        let param = self.param();
+        let error = match &self {
+            ImagePartError::TooLarge { part_index, size, limit, .. } => ApiError::from_status(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!(
+                    "Image at content part {} ({}) is too large: {} bytes exceeds the {} byte limit",
+                    part_index, param, size, limit
+                ),
+            ),
+            ImagePartError::Invalid { part_index, reason, .. } => ApiError::invalid_request(format!(
+                "Invalid image at content part {} ({}): {}",
+                part_index, param, reason
+            )),
+        };
+        error.with_param(param).openai()
+    }
+}
+
+/// 根据文件头识别图片类型
+pub fn sniff_image_mime(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" {
+        match &bytes[8..12] {
+            b"heic" | b"heix" | b"hevc" | b"hevx" => Some("image/heic"),
+            b"mif1" | b"msf1" | b"heif" => Some("image/heif"),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+/// 声明的 MIME 与嗅探结果不一致时以嗅探结果为准
+fn resolve_mime(sniffed: Option<&'static str>, declared: Option<&str>) -> String {
+    let declared = declared
+        .map(|m| m.trim().to_ascii_lowercase())
+        .filter(|m| !m.is_empty());
+    match (sniffed, declared) {
+        (Some(sniffed), Some(declared)) if sniffed != declared => {
+            tracing::debug!(
+                "[OpenAI-Vision] Declared mime {} does not match image content, using {}",
+                declared,
+                sniffed
+            );
+            sniffed.to_string()
+        }
+        (Some(sniffed), _) => sniffed.to_string(),
+        (None, Some(declared)) => declared,
+        (None, None) => "image/jpeg".to_string(),
+    }
+}
+
+fn percent_decode(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = |b: u8| (b as char).to_digit(16);
+            if let (Some(hi), Some(lo)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                out.push((hi * 16 + lo) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    out
+}
+
+/// 清洗 base64 载荷: 去空白、百分号解码、URL-safe 字母表转标准字母表、补齐 padding
+fn normalize_base64(payload: &str) -> Result<String, String> {
+    let decoded;
+    let payload = if payload.contains('%') {
+        decoded = String::from_utf8(percent_decode(payload))
+            .map_err(|_| "data URL payload is not valid base64".to_string())?;
+        decoded.as_str()
+    } else {
+        payload
+    };
+
+    let mut out = String::with_capacity(payload.len() + 3);
+    for c in payload.chars() {
+        match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '+' | '/' => out.push(c),
+            '-' => out.push('+'),
+            '_' => out.push('/'),
+            '=' => {}
+            c if c.is_ascii_whitespace() => {}
+            c => return Err(format!("unexpected character {:?} in base64 payload", c)),
+        }
+    }
+    match out.len() % 4 {
+        0 => {}
+        1 => return Err("truncated base64 payload".to_string()),
+        rem => out.push_str(&"=="[..4 - rem]),
+    }
+    if out.is_empty() {
+        return Err("empty image data".to_string());
+    }
+    Ok(out)
+}
+
+fn decoded_len(b64: &str) -> usize {
+    let padding = b64.bytes().rev().take_while(|b| *b == b'=').count();
+    b64.len() / 4 * 3 - padding
+}
+
+/// 解析 `data:[<mime>][;param...][;base64],<data>`
+pub fn parse_data_url(url: &str) -> Result<InlineImage, String> {
+    let rest = url
+        .get(..5)
+        .filter(|p| p.eq_ignore_ascii_case("data:"))
+        .map(|_| &url[5..])
+        .ok_or_else(|| "not a data URL".to_string())?;
+    let (header, payload) = rest
+        .split_once(',')
+        .ok_or_else(|| "data URL is missing the ',' separator".to_string())?;
+
+    let mut params = header.split(';');
+    let declared_mime = params.next().filter(|m| m.contains('/'));
+    let is_base64 = params.any(|p| p.trim().eq_ignore_ascii_case("base64"));
+
+    if !is_base64 {
+        let bytes = percent_decode(payload);
+        if bytes.is_empty() {
+            return Err("empty image data".to_string());
+        }
+        return Ok(InlineImage::from_bytes(&bytes, declared_mime));
+    }
+
+    let data = normalize_base64(payload)?;
+    // 只解码开头一小段用于嗅探，避免对大图做完整解码
+    let head = base64::engine::general_purpose::STANDARD
+        .decode(&data[..data.len().min(24)])
+        .map_err(|e| format!("invalid base64 payload: {}", e))?;
+    Ok(InlineImage {
+        mime_type: resolve_mime(sniff_image_mime(&head), declared_mime),
+        size: decoded_len(&data),
+        data,
+    })
+}
+
+enum FetchError {
+    TooLarge(usize),
+    Failed(String),
+}
+
+/// 远程图片最多跟随的重定向次数 (每一跳都重新校验目标地址)
+const MAX_REMOTE_REDIRECTS: usize = 3;
+
+/// 仅允许公网地址: 拒绝回环、私有、链路本地 (含 169.254.169.254 元数据服务)、CGNAT、组播等
+fn is_public_ip(ip: std::net::IpAddr) -> bool {
+    use std::net::IpAddr;
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b)) // 100.64.0.0/10 CGNAT
+                || (a == 192 && b == 0 && v4.octets()[2] == 0) // 192.0.0.0/24
+                || (a == 198 && (18..20).contains(&b)) // 198.18.0.0/15 基准测试
+                || a >= 240) // 240.0.0.0/4 保留
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || (first & 0xfe00) == 0xfc00 // fc00::/7 唯一本地
+                || (first & 0xffc0) == 0xfe80 // fe80::/10 链路本地
+                || (first & 0xffc0) == 0xfec0) // fec0::/10 站点本地 (已废弃)
+        }
+    }
+}
+
+/// 校验 URL 并解析出一个公网地址；任一解析结果为内网地址即拒绝
+async fn resolve_public_target(url: &reqwest::Url) -> Result<(String, std::net::SocketAddr), FetchError> {
+    if url.scheme() != "https" {
+        return Err(FetchError::Failed("only https image URLs are supported".to_string()));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| FetchError::Failed("image URL has no host".to_string()))?;
+    let host = host.trim_start_matches('[').trim_end_matches(']').to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs: Vec<std::net::SocketAddr> = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .map_err(|e| FetchError::Failed(format!("failed to resolve image host: {}", e)))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(FetchError::Failed("failed to resolve image host".to_string()));
+    }
+    if let Some(blocked) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        return Err(FetchError::Failed(format!(
+            "image host resolves to a non-public address ({})",
+            blocked.ip()
+        )));
+    }
+    Ok((host, addrs[0]))
+}
+
+/// 下载远程图片，超过 `limit` 字节立即中止
+/// 只访问公网地址: 每一跳重定向都重新解析并校验，连接固定到校验过的地址
+async fn fetch_remote_image(
+    upstream: &UpstreamClient,
+    url: &str,
+    limit: usize,
+) -> Result<InlineImage, FetchError> {
+    let mut current = reqwest::Url::parse(url)
+        .map_err(|e| FetchError::Failed(format!("invalid image URL: {}", e)))?;
+    let mut redirects = 0;
+    let response = loop {
+        let (host, addr) = resolve_public_target(&current).await?;
+        let client = upstream
+            .pinned_fetch_client(&host, addr)
+            .map_err(|e| FetchError::Failed(format!("failed to build fetch client: {}", e)))?;
+        let response = client
+            .get(current.clone())
+            .timeout(REMOTE_FETCH_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| FetchError::Failed(format!("failed to fetch image: {}", e)))?;
+        if !response.status().is_redirection() {
+            break response;
+        }
+        redirects += 1;
+        if redirects > MAX_REMOTE_REDIRECTS {
+            return Err(FetchError::Failed("failed to fetch image: too many redirects".to_string()));
+        }
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| FetchError::Failed("failed to fetch image: redirect without Location".to_string()))?;
+        current = current
+            .join(location)
+            .map_err(|e| FetchError::Failed(format!("invalid redirect location: {}", e)))?;
+    };
+    if !response.status().is_success() {
+        return Err(FetchError::Failed(format!(
+            "failed to fetch image: HTTP {}",
+            response.status()
+        )));
+    }
+    if let Some(len) = response.content_length() {
+        if len as usize > limit {
+            return Err(FetchError::TooLarge(len as usize));
+        }
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.split(';').next().unwrap_or(v).to_string());
+
+    let mut bytes = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| FetchError::Failed(format!("failed to read image: {}", e)))?;
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > limit {
+            return Err(FetchError::TooLarge(bytes.len()));
+        }
+    }
+    if bytes.is_empty() {
+        return Err(FetchError::Failed("remote image is empty".to_string()));
+    }
+    Ok(InlineImage::from_bytes(&bytes, content_type.as_deref()))
+}
+
+/// 发送前预处理所有 image_url 内容块 (转换函数本身保持同步):
+/// data: URL 规范化并校验大小，远程 https 图片下载后替换为 data: URL。内容块顺序不变。
+pub async fn prepare_image_parts(
+    request: &mut OpenAIRequest,
+    upstream: &UpstreamClient,
+) -> Result<(), ImagePartError> {
+    prepare_image_parts_with_limit(request, upstream, MAX_INLINE_IMAGE_BYTES).await
+}
+
+async fn prepare_image_parts_with_limit(
+    request: &mut OpenAIRequest,
+    upstream: &UpstreamClient,
+    limit: usize,
+) -> Result<(), ImagePartError> {
+    let mut remote = Vec::new();
+
+    for (message_index, msg) in request.messages.iter_mut().enumerate() {
+        let Some(OpenAIContent::Array(blocks)) = msg.content.as_mut() else {
+            continue;
+        };
+        for (part_index, block) in blocks.iter_mut().enumerate() {
+            let OpenAIContentBlock::ImageUrl { image_url } = block else {
+                continue;
+            };
+            let url = image_url.url.trim();
+            if url.get(..5).is_some_and(|p| p.eq_ignore_ascii_case("data:")) {
+                let image = parse_data_url(url).map_err(|reason| ImagePartError::Invalid {
+                    message_index,
+                    part_index,
+                    reason,
+                })?;
+                if image.size > limit {
+                    return Err(ImagePartError::TooLarge {
+                        message_index,
+                        part_index,
+                        size: image.size,
+                        limit,
+                    });
+                }
+                image_url.url = image.to_data_url();
+            } else if url.get(..8).is_some_and(|p| p.eq_ignore_ascii_case("https://")) {
+                remote.push((message_index, part_index, url.to_string()));
+            }
+        }
+    }
+
+    if remote.is_empty() {
+        return Ok(());
+    }
+
+    let results = futures::future::join_all(
+        remote
+            .iter()
+            .map(|(_, _, url)| fetch_remote_image(upstream, url, limit)),
+    )
+    .await;
+
+    for ((message_index, part_index, url), result) in remote.into_iter().zip(results) {
+        let image = match result {
+            Ok(image) => image,
+            Err(FetchError::TooLarge(size)) => {
+                return Err(ImagePartError::TooLarge {
+                    message_index,
+                    part_index,
+                    size,
+                    limit,
+                })
+            }
+            Err(FetchError::Failed(reason)) => {
+                return Err(ImagePartError::Invalid {
+                    message_index,
+                    part_index,
+                    reason,
+                })
+            }
+        };
+        tracing::debug!(
+            "[OpenAI-Vision] Fetched remote image {} ({} bytes, {})",
+            url,
+            image.size,
+            image.mime_type
+        );
+        if let Some(OpenAIContent::Array(blocks)) = request.messages[message_index].content.as_mut() {
+            if let Some(OpenAIContentBlock::ImageUrl { image_url }) = blocks.get_mut(part_index) {
+                image_url.url = image.to_data_url();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `detail` → Gemini mediaResolution
+fn media_resolution_rank(detail: &str) -> Option<(u8, &'static str)> {
+    match detail.trim().to_ascii_lowercase().as_str() {
+        "low" => Some((1, "MEDIA_RESOLUTION_LOW")),
+        "medium" => Some((2, "MEDIA_RESOLUTION_MEDIUM")),
+        "high" => Some((3, "MEDIA_RESOLUTION_HIGH")),
+        // "auto" 交由上游决定
+        _ => None,
+    }
+}
+
+/// 请求级 mediaResolution: Gemini 只支持整请求设置，多张图取最高的 detail
+pub fn requested_media_resolution(messages: &[OpenAIMessage]) -> Option<&'static str> {
+    messages
+        .iter()
+        .filter_map(|msg| match &msg.content {
+            Some(OpenAIContent::Array(blocks)) => Some(blocks),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|block| match block {
+            OpenAIContentBlock::ImageUrl { image_url } => {
+                image_url.detail.as_deref().and_then(media_resolution_rank)
+            }
+            _ => None,
+        })
+        .max_by_key(|(rank, _)| *rank)
+        .map(|(_, level)| level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::mappers::openai::OpenAIImageUrl;
+
+    const PNG_1PX: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8BQDwAEhQGAhKmMIQAAAABJRU5ErkJggg==";
+
+    fn image_block(url: &str, detail: Option<&str>) -> OpenAIContentBlock {
+        OpenAIContentBlock::ImageUrl {
+            image_url: OpenAIImageUrl {
+                url: url.to_string(),
+                detail: detail.map(|d| d.to_string()),
+            },
+        }
+    }
+
+    fn user_message(blocks: Vec<OpenAIContentBlock>) -> OpenAIMessage {
+        OpenAIMessage {
+            role: "user".to_string(),
+            content: Some(OpenAIContent::Array(blocks)),
+            reasoning_content: None,
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_data_url_sniffs_and_normalizes() {
+        // 声明为 jpeg 但实际是 png，且带换行、缺 padding
+        let wrapped = format!(
+            "data:image/jpeg;base64,{}\n{}",
+            &PNG_1PX[..40],
+            PNG_1PX[40..].trim_end_matches('=')
+        );
+        let image = parse_data_url(&wrapped).unwrap();
+        assert_eq!(image.mime_type, "image/png");
+        assert_eq!(image.data, PNG_1PX);
+        assert_eq!(image.size, 70);
+
+        // URL-safe 字母表 + 百分号编码的 padding
+        let url_safe = format!(
+            "data:image/png;base64,{}",
+            PNG_1PX.replace('+', "-").replace('/', "_").replace('=', "%3D")
+        );
+        assert_eq!(parse_data_url(&url_safe).unwrap().data, PNG_1PX);
+
+        // 非 base64 data URL
+        let raw = parse_data_url("data:image/gif,GIF89a%01%00").unwrap();
+        assert_eq!(raw.mime_type, "image/gif");
+        assert_eq!(raw.size, 8);
+
+        assert!(parse_data_url("data:image/png;base64").is_err());
+        assert!(parse_data_url("data:image/png;base64,abc$").is_err());
+    }
+
+    #[test]
+    fn test_requested_media_resolution_takes_highest_detail() {
+        let messages = vec![user_message(vec![
+            image_block("data:image/png;base64,AAAA", Some("low")),
+            OpenAIContentBlock::Text { text: "hi".to_string() },
+            image_block("data:image/png;base64,AAAA", Some("high")),
+            image_block("data:image/png;base64,AAAA", Some("auto")),
+        ])];
+        assert_eq!(requested_media_resolution(&messages), Some("MEDIA_RESOLUTION_HIGH"));
+        assert_eq!(
+            requested_media_resolution(&[user_message(vec![image_block("x", Some("auto"))])]),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prepare_rejects_oversized_part_with_index() {
+        let mut request: OpenAIRequest = serde_json::from_value(serde_json::json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "user", "content": "hi" }]
+        }))
+        .unwrap();
+        request.messages.push(user_message(vec![
+            OpenAIContentBlock::Text { text: "compare".to_string() },
+            image_block(&format!("data:image/png;base64,{}", PNG_1PX), None),
+            image_block(&format!("data:image/png;base64,{}", PNG_1PX), Some("high")),
+        ]));
+        let upstream = UpstreamClient::new(None, None);
+
+        prepare_image_parts_with_limit(&mut request, &upstream, 1024).await.unwrap();
+
+        let err = prepare_image_parts_with_limit(&mut request, &upstream, 16)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ImagePartError::TooLarge { message_index: 1, part_index: 1, size: 70, limit: 16 }
+        ));
+        assert_eq!(err.into_api_error().status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn test_is_public_ip_rejects_internal_ranges() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{} should be blocked", ip);
+        }
+        for ip in ["8.8.8.8", "142.250.1.1", "2606:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{} should be allowed", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_remote_image_blocks_internal_hosts() {
+        let upstream = UpstreamClient::new(None, None);
+        for url in [
+            "https://169.254.169.254/latest/meta-data/",
+            "https://127.0.0.1:8045/api/config",
+            "https://[::1]/image.png",
+            "http://example.com/image.png",
+        ] {
+            match fetch_remote_image(&upstream, url, 1024).await {
+                Err(FetchError::Failed(_)) => {}
+                other => panic!("{} should be rejected before connecting: {:?}", url, other.is_ok()),
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+// 系统提示词预算
+// 启用 enable_usage_scaling (上下文压缩) 时，若系统指令整体估算 Token 超出 system_prompt_max_tokens，
+// 注入的 Antigravity 身份替换为短版本；用户提供的 systemInstruction 不受影响
+use std::sync::{OnceLock, RwLock};
+
+use super::context_manager::estimate_tokens_from_str;
+use crate::proxy::config::ProxyConfig;
+
+/// 注入的 Antigravity 身份 (原始简化版)
+pub const ANTIGRAVITY_IDENTITY: &str = "You are Antigravity, a powerful agentic AI coding assistant designed by the Google Deepmind team working on Advanced Agentic Coding.\n\
+    You are pair programming with a USER to solve their coding task. The task may require creating a new codebase, modifying or debugging an existing codebase, or simply answering a question.\n\
+    **Absolute paths only**\n\
+    **Proactiveness**";
+
+/// 默认短版本身份 (约 30 tokens，可通过 identity_short 覆盖)
+pub const DEFAULT_IDENTITY_SHORT: &str =
+    "You are Antigravity, an agentic AI coding assistant by Google Deepmind. Use absolute paths only. Be proactive.";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemPromptBudget {
+    /// 跟随 experimental.enable_usage_scaling
+    pub enabled: bool,
+    pub max_tokens: usize,
+    pub identity_short: Option<String>,
+}
+
+impl Default for SystemPromptBudget {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_tokens: 500,
+            identity_short: None,
+        }
+    }
+}
+
+impl SystemPromptBudget {
+    pub fn from_proxy_config(config: &ProxyConfig) -> Self {
+        Self {
+            enabled: config.experimental.enable_usage_scaling,
+            max_tokens: config.system_prompt_max_tokens,
+            identity_short: config
+                .identity_short
+                .as_ref()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty()),
+        }
+    }
+
+    /// 选择要注入的身份文本
+    /// `other_parts` 为与身份一同发送的其余系统指令 (全局提示词、用户指令)
+    pub fn identity<'a, I, S>(&'a self, other_parts: I) -> &'a str
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        if !self.enabled {
+            return ANTIGRAVITY_IDENTITY;
+        }
+        let total = other_parts
+            .into_iter()
+            .map(|s| estimate_tokens_from_str(s.as_ref()) as usize)
+            .sum::<usize>()
+            + estimate_tokens_from_str(ANTIGRAVITY_IDENTITY) as usize;
+        if total <= self.max_tokens {
+            return ANTIGRAVITY_IDENTITY;
+        }
+        tracing::debug!(
+            "[System-Prompt-Budget] Estimated system prompt {} tokens > {}, using short identity",
+            total,
+            self.max_tokens
+        );
+        self.identity_short.as_deref().unwrap_or(DEFAULT_IDENTITY_SHORT)
+    }
+}
+
+fn store() -> &'static RwLock<SystemPromptBudget> {
+    static BUDGET: OnceLock<RwLock<SystemPromptBudget>> = OnceLock::new();
+    BUDGET.get_or_init(|| RwLock::new(SystemPromptBudget::default()))
+}
+
+pub fn get_system_prompt_budget() -> SystemPromptBudget {
+    store().read().map(|b| b.clone()).unwrap_or_default()
+}
+
+/// 更新系统提示词预算 (启动 / 保存配置时调用)
+pub fn update_system_prompt_budget(config: &ProxyConfig) {
+    if let Ok(mut budget) = store().write() {
+        *budget = SystemPromptBudget::from_proxy_config(config);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(enabled: bool, max_tokens: usize, short: Option<&str>) -> SystemPromptBudget {
+        SystemPromptBudget {
+            enabled,
+            max_tokens,
+            identity_short: short.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_short_identity_only_when_over_budget() {
+        let long_user_prompt = "x".repeat(4000);
+
+        assert_eq!(budget(true, 500, None).identity(["be concise"]), ANTIGRAVITY_IDENTITY);
+        assert_eq!(
+            budget(true, 500, None).identity([long_user_prompt.as_str()]),
+            DEFAULT_IDENTITY_SHORT
+        );
+        assert_eq!(
+            budget(true, 500, Some("You are Antigravity.")).identity([long_user_prompt.as_str()]),
+            "You are Antigravity."
+        );
+        // 未启用 usage scaling 时保持完整身份
+        assert_eq!(
+            budget(false, 500, None).identity([long_user_prompt.as_str()]),
+            ANTIGRAVITY_IDENTITY
+        );
+    }
+
+    #[test]
+    fn test_short_identity_is_much_smaller() {
+        assert!(estimate_tokens_from_str(DEFAULT_IDENTITY_SHORT) <= 50);
+        assert!(
+            estimate_tokens_from_str(DEFAULT_IDENTITY_SHORT)
+                < estimate_tokens_from_str(ANTIGRAVITY_IDENTITY)
+        );
+    }
+}
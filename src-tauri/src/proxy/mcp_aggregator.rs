@@ -0,0 +1,467 @@
+// 聚合 MCP 服务 (/mcp/abv/mcp)
+// 将 z.ai 的 web_search_prime / web_reader 远程 MCP、内置 Vision 工具与本地工具 (账号状态、配额查询)
+// 合并为一个 MCP 端点，工具名按来源加命名空间 (`zai.webSearchPrime`、`abv.account_status`)；
+// 某个来源不可用时只跳过该来源的工具，不影响其他来源
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+use tokio::time::Duration;
+
+use crate::proxy::config::{UpstreamProxyConfig, ZaiConfig};
+use crate::proxy::TokenManager;
+
+pub const ZAI_NAMESPACE: &str = "zai";
+pub const LOCAL_NAMESPACE: &str = "abv";
+
+const UPSTREAM_PROTOCOL_VERSION: &str = "2025-03-26";
+
+/// 工具来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ToolProvider {
+    ZaiWebSearch,
+    ZaiWebReader,
+    ZaiVision,
+    Local,
+}
+
+impl ToolProvider {
+    fn namespace(self) -> &'static str {
+        match self {
+            ToolProvider::Local => LOCAL_NAMESPACE,
+            _ => ZAI_NAMESPACE,
+        }
+    }
+
+    /// 远程 MCP 服务地址 (Vision 与本地工具在进程内实现)
+    fn upstream_url(self) -> Option<&'static str> {
+        match self {
+            ToolProvider::ZaiWebSearch => Some("https://api.z.ai/api/mcp/web_search_prime/mcp"),
+            ToolProvider::ZaiWebReader => Some("https://api.z.ai/api/mcp/web_reader/mcp"),
+            _ => None,
+        }
+    }
+}
+
+/// 调用工具所需的运行时配置快照
+pub struct McpContext {
+    pub zai: ZaiConfig,
+    pub upstream_proxy: UpstreamProxyConfig,
+    pub timeout_secs: u64,
+    pub token_manager: Arc<TokenManager>,
+}
+
+impl McpContext {
+    /// 当前可用的工具来源 (按 z.ai MCP 开关过滤)
+    fn providers(&self) -> Vec<ToolProvider> {
+        let mut providers = Vec::new();
+        let zai_ready = self.zai.enabled && !self.zai.api_key.trim().is_empty() && self.zai.mcp.enabled;
+        if zai_ready && self.zai.mcp.web_search_enabled {
+            providers.push(ToolProvider::ZaiWebSearch);
+        }
+        if zai_ready && self.zai.mcp.web_reader_enabled {
+            providers.push(ToolProvider::ZaiWebReader);
+        }
+        if zai_ready && self.zai.mcp.vision_enabled {
+            providers.push(ToolProvider::ZaiVision);
+        }
+        providers.push(ToolProvider::Local);
+        providers
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct McpAggregatorState {
+    /// 客户端会话 (initialize 时创建)
+    sessions: Mutex<HashMap<String, std::time::Instant>>,
+    /// 与各远程 MCP 服务的会话 ID (失效时重新 initialize)
+    upstream_sessions: Mutex<HashMap<ToolProvider, Option<String>>>,
+    /// 带命名空间的工具名 -> (来源, 原始工具名)，由 tools/list 刷新
+    routes: RwLock<HashMap<String, (ToolProvider, String)>>,
+}
+
+impl McpAggregatorState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn create_session(&self) -> String {
+        let session_id = uuid::Uuid::new_v4().to_string();
+        self.sessions
+            .lock()
+            .await
+            .insert(session_id.clone(), std::time::Instant::now());
+        session_id
+    }
+
+    pub async fn has_session(&self, session_id: &str) -> bool {
+        self.sessions.lock().await.contains_key(session_id)
+    }
+
+    pub async fn remove_session(&self, session_id: &str) {
+        self.sessions.lock().await.remove(session_id);
+    }
+
+    /// 合并所有来源的工具列表；单个来源失败时记录警告并跳过
+    pub async fn list_tools(&self, ctx: &McpContext) -> Vec<Value> {
+        let providers = ctx.providers();
+        let results =
+            futures::future::join_all(providers.iter().map(|p| self.provider_tools(ctx, *p))).await;
+
+        let mut tools = Vec::new();
+        let mut routes = HashMap::new();
+        for (provider, result) in providers.into_iter().zip(results) {
+            match result {
+                Ok(provider_tools) => {
+                    for tool in provider_tools {
+                        let Some(name) = tool.get("name").and_then(|v| v.as_str()) else {
+                            continue;
+                        };
+                        let namespaced = format!("{}.{}", provider.namespace(), name);
+                        routes.insert(namespaced.clone(), (provider, name.to_string()));
+                        let mut tool = tool.clone();
+                        tool["name"] = json!(namespaced);
+                        tools.push(tool);
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("[MCP-Aggregator] Skipping tools from {:?}: {}", provider, e);
+                }
+            }
+        }
+        *self.routes.write().await = routes;
+        tools
+    }
+
+    /// 按命名空间路由工具调用；返回 MCP tools/call 的 result
+    pub async fn call_tool(&self, ctx: &McpContext, name: &str, arguments: &Value) -> Result<Value, String> {
+        let mut route = self.routes.read().await.get(name).cloned();
+        if route.is_none() {
+            // 客户端可能未先调用 tools/list (或配置已变更)，刷新一次路由表
+            self.list_tools(ctx).await;
+            route = self.routes.read().await.get(name).cloned();
+        }
+        let Some((provider, tool_name)) = route else {
+            return Err(format!("Unknown tool: {}", name));
+        };
+        if !ctx.providers().contains(&provider) {
+            return Err(format!("Tool {} is currently disabled", name));
+        }
+
+        match provider {
+            ToolProvider::Local => call_local_tool(ctx, &tool_name, arguments).await,
+            ToolProvider::ZaiVision => {
+                crate::proxy::zai_vision_tools::call_tool(
+                    &ctx.zai,
+                    ctx.upstream_proxy.clone(),
+                    ctx.timeout_secs,
+                    &tool_name,
+                    arguments,
+                )
+                .await
+            }
+            _ => {
+                self.upstream_request(
+                    ctx,
+                    provider,
+                    "tools/call",
+                    json!({ "name": tool_name, "arguments": arguments }),
+                )
+                .await
+            }
+        }
+    }
+
+    async fn provider_tools(&self, ctx: &McpContext, provider: ToolProvider) -> Result<Vec<Value>, String> {
+        match provider {
+            ToolProvider::Local => Ok(local_tool_specs()),
+            ToolProvider::ZaiVision => Ok(crate::proxy::zai_vision_tools::tool_specs()),
+            _ => {
+                let result = self.upstream_request(ctx, provider, "tools/list", json!({})).await?;
+                Ok(result
+                    .get("tools")
+                    .and_then(|t| t.as_array())
+                    .cloned()
+                    .unwrap_or_default())
+            }
+        }
+    }
+
+    /// 向远程 MCP 服务发送请求；会话失效时重新 initialize 并重试一次
+    async fn upstream_request(
+        &self,
+        ctx: &McpContext,
+        provider: ToolProvider,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, String> {
+        let url = provider
+            .upstream_url()
+            .ok_or_else(|| format!("{:?} has no upstream MCP server", provider))?;
+        let client = build_client(&ctx.upstream_proxy, ctx.timeout_secs)?;
+
+        for attempt in 0..2 {
+            let cached = self.upstream_sessions.lock().await.get(&provider).cloned();
+            let session = match cached {
+                Some(session) => session,
+                None => {
+                    let session = upstream_initialize(&client, url, &ctx.zai.api_key).await?;
+                    self.upstream_sessions
+                        .lock()
+                        .await
+                        .insert(provider, session.clone());
+                    session
+                }
+            };
+
+            let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+            match upstream_post(&client, url, &ctx.zai.api_key, session.as_deref(), &request).await {
+                Ok((_, Some(response))) => {
+                    if let Some(error) = response.get("error") {
+                        return Err(format!("Upstream MCP error: {}", error));
+                    }
+                    return Ok(response.get("result").cloned().unwrap_or(Value::Null));
+                }
+                Ok((_, None)) => return Err("Upstream MCP returned no response".to_string()),
+                Err(e) => {
+                    self.upstream_sessions.lock().await.remove(&provider);
+                    if attempt == 1 {
+                        return Err(e);
+                    }
+                    tracing::debug!("[MCP-Aggregator] {:?} request failed, re-initializing: {}", provider, e);
+                }
+            }
+        }
+        unreachable!()
+    }
+}
+
+fn build_client(upstream_proxy: &UpstreamProxyConfig, timeout_secs: u64) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder().timeout(Duration::from_secs(timeout_secs.max(5)));
+    if upstream_proxy.enabled && !upstream_proxy.url.is_empty() {
+        let url = crate::proxy::config::normalize_proxy_url(&upstream_proxy.url);
+        let proxy = reqwest::Proxy::all(&url).map_err(|e| format!("Invalid upstream proxy url: {}", e))?;
+        builder = builder.proxy(proxy);
+    }
+    builder.build().map_err(|e| format!("Failed to build HTTP client: {}", e))
+}
+
+/// 建立远程 MCP 会话，返回服务端分配的 Mcp-Session-Id (无状态服务为 None)
+async fn upstream_initialize(client: &reqwest::Client, url: &str, api_key: &str) -> Result<Option<String>, String> {
+    let initialize = json!({
+        "jsonrpc": "2.0",
+        "id": 0,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": UPSTREAM_PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": { "name": "antigravity-manager", "version": env!("CARGO_PKG_VERSION") }
+        }
+    });
+    let (session, response) = upstream_post(client, url, api_key, None, &initialize).await?;
+    if let Some(error) = response.as_ref().and_then(|r| r.get("error")) {
+        return Err(format!("Upstream MCP initialize failed: {}", error));
+    }
+    let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+    upstream_post(client, url, api_key, session.as_deref(), &initialized).await?;
+    Ok(session)
+}
+
+/// 发送一条 JSON-RPC 消息，响应可能是 JSON 或 SSE 流 (streamable HTTP 传输)
+async fn upstream_post(
+    client: &reqwest::Client,
+    url: &str,
+    api_key: &str,
+    session: Option<&str>,
+    message: &Value,
+) -> Result<(Option<String>, Option<Value>), String> {
+    let mut request = client
+        .post(url)
+        .bearer_auth(api_key.trim())
+        .header("accept", "application/json, text/event-stream")
+        .json(message);
+    if let Some(session) = session {
+        request = request.header("mcp-session-id", session);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Upstream request failed: {}", e))?;
+    let status = response.status();
+    let session = response
+        .headers()
+        .get("mcp-session-id")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+        .or_else(|| session.map(|s| s.to_string()));
+    let is_sse = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|ct| ct.starts_with("text/event-stream"))
+        .unwrap_or(false);
+    let text = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read upstream response: {}", e))?;
+    if !status.is_success() {
+        return Err(format!("Upstream returned {}: {}", status, text));
+    }
+    if message.get("id").is_none() || text.trim().is_empty() {
+        return Ok((session, None));
+    }
+    let parsed = if is_sse {
+        parse_sse_response(&text, &message["id"])
+    } else {
+        serde_json::from_str(&text).ok()
+    };
+    Ok((session, parsed))
+}
+
+/// 从 SSE 响应体中取出与请求 id 对应的 JSON-RPC 消息
+fn parse_sse_response(body: &str, id: &Value) -> Option<Value> {
+    body.lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .filter_map(|data| serde_json::from_str::<Value>(data.trim()).ok())
+        .find(|msg| msg.get("id") == Some(id))
+}
+
+fn local_tool_specs() -> Vec<Value> {
+    vec![
+        json!({
+            "name": "account_status",
+            "description": "List proxy accounts with their enabled state, rate-limit status and subscription tier.",
+            "inputSchema": { "type": "object", "properties": {} }
+        }),
+        json!({
+            "name": "quota_lookup",
+            "description": "Look up remaining model quota (percentage and reset time) per account.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "email": { "type": "string", "description": "Only return this account" },
+                    "model": { "type": "string", "description": "Only return models whose name contains this text" }
+                }
+            }
+        }),
+    ]
+}
+
+async fn call_local_tool(ctx: &McpContext, name: &str, arguments: &Value) -> Result<Value, String> {
+    let accounts = tokio::task::spawn_blocking(crate::modules::account::list_accounts)
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let payload = match name {
+        "account_status" => {
+            let current = crate::modules::account::get_current_account_id().ok().flatten();
+            let list: Vec<Value> = accounts
+                .iter()
+                .map(|a| {
+                    json!({
+                        "email": a.email,
+                        "name": a.name,
+                        "current": current.as_deref() == Some(a.id.as_str()),
+                        "disabled": a.disabled,
+                        "proxy_disabled": a.proxy_disabled,
+                        "rate_limit_reset_seconds": ctx.token_manager.get_rate_limit_reset_seconds(&a.id),
+                        "subscription_tier": a.quota.as_ref().and_then(|q| q.subscription_tier.clone()),
+                    })
+                })
+                .collect();
+            json!({
+                "total": accounts.len(),
+                "available": accounts.iter().filter(|a| !a.disabled && !a.proxy_disabled).count(),
+                "accounts": list,
+            })
+        }
+        "quota_lookup" => {
+            let email = arguments.get("email").and_then(|v| v.as_str());
+            let model = arguments.get("model").and_then(|v| v.as_str()).map(|m| m.to_lowercase());
+            let list: Vec<Value> = accounts
+                .iter()
+                .filter(|a| email.map_or(true, |e| a.email.eq_ignore_ascii_case(e)))
+                .map(|a| {
+                    let models: Vec<Value> = a
+                        .quota
+                        .iter()
+                        .flat_map(|q| q.models.iter())
+                        .filter(|m| model.as_deref().map_or(true, |f| m.name.to_lowercase().contains(f)))
+                        .map(|m| json!({ "model": m.name, "percentage": m.percentage, "reset_time": m.reset_time }))
+                        .collect();
+                    json!({
+                        "email": a.email,
+                        "last_updated": a.quota.as_ref().map(|q| q.last_updated),
+                        "models": models,
+                    })
+                })
+                .collect();
+            if list.is_empty() {
+                return Err(format!("No account matches {}", email.unwrap_or("the filter")));
+            }
+            json!({ "accounts": list })
+        }
+        _ => return Err(format!("Unknown tool: {}.{}", LOCAL_NAMESPACE, name)),
+    };
+
+    Ok(json!({
+        "content": [ { "type": "text", "text": serde_json::to_string_pretty(&payload).unwrap_or_default() } ],
+        "structuredContent": payload,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(zai: ZaiConfig) -> McpContext {
+        McpContext {
+            zai,
+            upstream_proxy: UpstreamProxyConfig::default(),
+            timeout_secs: 5,
+            token_manager: Arc::new(TokenManager::new(std::env::temp_dir())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_tools_namespaces_local_and_vision_tools() {
+        let mut zai = ZaiConfig::default();
+        zai.enabled = true;
+        zai.api_key = "key".to_string();
+        zai.mcp.enabled = true;
+        zai.mcp.vision_enabled = true;
+        let ctx = context(zai);
+
+        let state = McpAggregatorState::new();
+        let names: Vec<String> = state
+            .list_tools(&ctx)
+            .await
+            .iter()
+            .map(|t| t["name"].as_str().unwrap().to_string())
+            .collect();
+        assert!(names.contains(&"abv.account_status".to_string()));
+        assert!(names.contains(&"abv.quota_lookup".to_string()));
+        assert!(names.contains(&"zai.ui_to_artifact".to_string()));
+
+        let err = state.call_tool(&ctx, "zai.unknown", &json!({})).await.unwrap_err();
+        assert!(err.contains("Unknown tool"));
+    }
+
+    #[tokio::test]
+    async fn test_disabled_zai_exposes_only_local_tools() {
+        let ctx = context(ZaiConfig::default());
+        let tools = McpAggregatorState::new().list_tools(&ctx).await;
+        assert!(tools
+            .iter()
+            .all(|t| t["name"].as_str().unwrap().starts_with("abv.")));
+    }
+
+    #[test]
+    fn test_parse_sse_response_matches_id() {
+        let body = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\n\
+                    event: message\ndata: {\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{\"tools\":[]}}\n\n";
+        let msg = parse_sse_response(body, &json!(1)).unwrap();
+        assert_eq!(msg["result"]["tools"], json!([]));
+        assert!(parse_sse_response(body, &json!(2)).is_none());
+    }
+}
@@ -0,0 +1,189 @@
+// 访问日志中间件
+// access_log_format = combined 时，每个反代请求在响应体发送完毕 (或客户端断开) 后
+// 向独立的 access.log 写入一行 Apache Combined Log Format，便于 nginx / HAProxy 日志管道解析
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use futures::StreamExt;
+use once_cell::sync::Lazy;
+use std::io::Write;
+
+use crate::proxy::config::AccessLogFormat;
+use crate::proxy::server::AppState;
+
+/// access.log 写入器: 与主日志同目录、同样按天滚动，由 logger::cleanup_old_logs 统一清理
+static ACCESS_LOG_WRITER: Lazy<Option<tracing_appender::non_blocking::NonBlocking>> =
+    Lazy::new(|| {
+        let log_dir = match crate::modules::logger::get_log_dir() {
+            Ok(dir) => dir,
+            Err(e) => {
+                tracing::warn!("[Access-Log] Cannot open access.log: {}", e);
+                return None;
+            }
+        };
+        let appender = tracing_appender::rolling::daily(log_dir, "access.log");
+        let (writer, guard) = tracing_appender::non_blocking(appender);
+        // 与主日志相同，guard 保留到进程退出
+        std::mem::forget(guard);
+        Some(writer)
+    });
+
+/// 一条访问记录
+struct AccessLogEntry {
+    remote_ip: String,
+    account_id: Option<String>,
+    timestamp: chrono::DateTime<chrono::Local>,
+    request_line: String,
+    status: u16,
+    response_bytes: u64,
+    referer: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl AccessLogEntry {
+    /// `{remote_ip} - {account_id} [{timestamp}] "{method} {path} {version}" {status} {bytes} "{referer}" "{user_agent}"`
+    fn format_combined(&self) -> String {
+        format!(
+            "{} - {} [{}] \"{}\" {} {} \"{}\" \"{}\"\n",
+            self.remote_ip,
+            self.account_id.as_deref().map(escape).unwrap_or_else(|| "-".to_string()),
+            self.timestamp.format("%d/%b/%Y:%H:%M:%S %z"),
+            escape(&self.request_line),
+            self.status,
+            // CLF 约定: 无响应体时记为 "-"
+            if self.response_bytes == 0 {
+                "-".to_string()
+            } else {
+                self.response_bytes.to_string()
+            },
+            self.referer.as_deref().map(escape).unwrap_or_else(|| "-".to_string()),
+            self.user_agent.as_deref().map(escape).unwrap_or_else(|| "-".to_string()),
+        )
+    }
+}
+
+/// 待写入的访问记录；响应体结束或被丢弃时写出
+struct PendingAccessLog(AccessLogEntry);
+
+impl Drop for PendingAccessLog {
+    fn drop(&mut self) {
+        if let Some(writer) = ACCESS_LOG_WRITER.as_ref() {
+            let mut writer = writer.clone();
+            let _ = writer.write_all(self.0.format_combined().as_bytes());
+        }
+    }
+}
+
+/// 转义引号、反斜杠与控制字符，避免伪造日志行
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => out.push_str(&format!("\\x{:02x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn header(request: &Request, name: &str) -> Option<String> {
+    request
+        .headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_string())
+}
+
+pub async fn access_log_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if crate::proxy::get_access_log_format() != AccessLogFormat::Combined {
+        return next.run(request).await;
+    }
+
+    let remote_ip = crate::proxy::middleware::ip_filter::extract_client_ip(
+        &request,
+        &*state.security.read().await,
+    )
+    .unwrap_or_else(|| "-".to_string());
+    let path = request
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let request_line = format!("{} {} {:?}", request.method(), path, request.version());
+    let referer = header(&request, "referer");
+    let user_agent = header(&request, "user-agent");
+    let timestamp = chrono::Local::now();
+
+    let response = next.run(request).await;
+
+    // 处理器在响应头中回传实际使用的账号邮箱，这里换算为账号 ID
+    let account_id = response
+        .headers()
+        .get("x-account-email")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|email| state.token_manager.email_to_account_id(email));
+    let mut pending = PendingAccessLog(AccessLogEntry {
+        remote_ip,
+        account_id,
+        timestamp,
+        request_line,
+        status: response.status().as_u16(),
+        response_bytes: 0,
+        referer,
+        user_agent,
+    });
+
+    // 统计实际发送的字节数；pending 随流一起被丢弃时写出日志 (含流式响应与客户端中途断开)
+    let (parts, body) = response.into_parts();
+    let stream = body.into_data_stream().map(move |chunk| {
+        if let Ok(bytes) = &chunk {
+            pending.0.response_bytes += bytes.len() as u64;
+        }
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_combined_format_line() {
+        let entry = AccessLogEntry {
+            remote_ip: "203.0.113.7".to_string(),
+            account_id: Some("acc-7f3a".to_string()),
+            timestamp: chrono::Local.with_ymd_and_hms(2024, 3, 5, 13, 55, 36).unwrap(),
+            request_line: "POST /v1/chat/completions HTTP/1.1".to_string(),
+            status: 200,
+            response_bytes: 2326,
+            referer: None,
+            user_agent: Some("curl/8.4.0 \"quoted\"".to_string()),
+        };
+        let line = entry.format_combined();
+
+        let offset = chrono::Local
+            .with_ymd_and_hms(2024, 3, 5, 13, 55, 36)
+            .unwrap()
+            .format("%z")
+            .to_string();
+        assert_eq!(
+            line,
+            format!(
+                "203.0.113.7 - acc-7f3a [05/Mar/2024:13:55:36 {}] \"POST /v1/chat/completions HTTP/1.1\" 200 2326 \"-\" \"curl/8.4.0 \\\"quoted\\\"\"\n",
+                offset
+            )
+        );
+    }
+}
@@ -0,0 +1,57 @@
+// 账号并发中间件 - 为每个反代请求建立并发上下文 (优先级 + 许可槽位 + 客户端 IP)
+// 处理器选定账号后通过 `concurrency::acquire_account_slot` 申请许可，
+// 许可随响应体一起释放，流式响应在流结束前持续占用名额
+
+use axum::{
+    body::{Body, HttpBody},
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use futures::StreamExt;
+
+use crate::proxy::concurrency::{AccountPermit, RequestPriority, RequestScope};
+use crate::proxy::server::AppState;
+use crate::proxy::sticky_config::StickyMode;
+
+pub async fn account_concurrency_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // [NEW] Ip 粘性会话: 在请求上下文中记录客户端 IP，供账号调度作为会话键
+    // [FIX] 与 IP 过滤 / 访问日志共用同一套客户端 IP 解析 (受 trusted_proxies 约束)
+    let sticky = state.token_manager.get_sticky_config().await;
+    let client_ip = if sticky.sticky_mode == StickyMode::Ip {
+        crate::proxy::middleware::ip_filter::extract_client_ip(
+            &request,
+            &*state.security.read().await,
+        )
+        .and_then(|ip| ip.parse().ok())
+    } else {
+        None
+    };
+
+    let scope = RequestScope::new(RequestPriority::from_headers(request.headers()))
+        .with_client_ip(client_ip);
+    let response = scope.clone().run(next.run(request)).await;
+
+    match scope.take_permit() {
+        Some(permit) => hold_permit_until_body_done(response, permit),
+        None => response,
+    }
+}
+
+fn hold_permit_until_body_done(response: Response, permit: AccountPermit) -> Response {
+    // 非流式响应体已在内存中，直接释放许可
+    if response.body().size_hint().exact().is_some() {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let stream = body.into_data_stream().map(move |chunk| {
+        let _held = &permit;
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(stream))
+}
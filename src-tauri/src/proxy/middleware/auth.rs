@@ -2,40 +2,80 @@
 use axum::{
     extract::State,
     extract::Request,
-    http::{header, StatusCode},
+    http::header,
     middleware::Next,
     response::Response,
 };
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::modules::admin_users::AdminRole;
+use crate::proxy::error::{ApiError, ApiErrorCode, ApiErrorFormat};
 use crate::proxy::{ProxyAuthMode, ProxySecurityConfig};
 
+/// 鉴权失败: 按路由选择对应协议的错误 envelope
+fn auth_error(path: &str) -> ApiError {
+    ApiError::new(ApiErrorCode::AuthInvalidKey, "Invalid or missing API key")
+        .format(ApiErrorFormat::for_path(path))
+}
+
 /// API Key 认证中间件 (代理接口使用，遵循 auth_mode)
 pub async fn auth_middleware(
     state: State<Arc<RwLock<ProxySecurityConfig>>>,
     request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, ApiError> {
     auth_middleware_internal(state, request, next, false).await
 }
 
 /// 管理接口认证中间件 (管理接口使用，强制严格鉴权)
 pub async fn admin_auth_middleware(
     state: State<Arc<RwLock<ProxySecurityConfig>>>,
-    request: Request,
+    mut request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, ApiError> {
+    // [NEW] 注入操作者 IP，供审计日志使用 (受 trusted_proxies 约束)
+    let ip = crate::proxy::middleware::ip_filter::extract_client_ip(&request, &*state.read().await)
+        .unwrap_or_else(|| "unknown".to_string());
+    request.extensions_mut().insert(AdminActor { ip, session: None });
     auth_middleware_internal(state, request, next, true).await
 }
 
+/// 管理用户角色校验中间件 (位于 admin_auth_middleware 内层)
+/// 旧凭据 (admin_password / api_key / 管理 JWT) 没有会话，视为完整管理员权限
+pub async fn admin_role_middleware(request: Request, next: Next) -> Result<Response, ApiError> {
+    let session = request
+        .extensions()
+        .get::<AdminActor>()
+        .and_then(|actor| actor.session.as_ref());
+    if let Some(session) = session {
+        let method = request.method();
+        let path = request.uri().path();
+        if !session.role.permits(method, path) {
+            tracing::warn!(
+                "Admin user '{}' ({}) denied: {} {}",
+                session.username,
+                session.role.as_str(),
+                method,
+                path
+            );
+            return Err(ApiError::new(
+                ApiErrorCode::PermissionDenied,
+                format!("Role '{}' is not allowed to perform this action", session.role.as_str()),
+            )
+            .format(ApiErrorFormat::for_path(path)));
+        }
+    }
+    Ok(next.run(request).await)
+}
+
 /// 内部认证逻辑
 async fn auth_middleware_internal(
     State(security): State<Arc<RwLock<ProxySecurityConfig>>>,
-    request: Request,
+    mut request: Request,
     next: Next,
     force_strict: bool,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, ApiError> {
     let method = request.method().clone();
     let path = request.uri().path().to_string();
 
@@ -74,6 +114,15 @@ async fn auth_middleware_internal(
                         .and_then(|h| h.to_str().ok())
                 });
             
+            // [NEW] 附加 API Key 同样记录标签
+            if let Some(label) = api_key
+                .and_then(|k| security.labeled_key(k))
+                .map(|label| label.to_string())
+            {
+                request.extensions_mut().insert(ApiKeyLabel(label));
+                return Ok(next.run(request).await);
+            }
+
             if let Some(token) = api_key {
                 // 尝试验证是否为 User Token（不阻止请求，只记录）
                 if let Ok(Some(user_token)) = crate::modules::user_token_db::get_token_by_value(token) {
@@ -106,6 +155,17 @@ async fn auth_middleware_internal(
         // 管理接口 (/api/*)
         // 1. 如果全局鉴权关闭，则管理接口也放行 (除非是强制局域网模式)
         if matches!(effective_mode, ProxyAuthMode::Off) {
+            // [NEW] 仍识别管理用户会话，供自助接口与审计使用 (不做角色限制)
+            let token = request
+                .headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|h| h.to_str().ok())
+                .and_then(|s| s.strip_prefix("Bearer "));
+            if let Some(session) = token.and_then(|t| resolve_admin_session(&security, t)) {
+                if let Some(actor) = request.extensions_mut().get_mut::<AdminActor>() {
+                    actor.session = Some(session);
+                }
+            }
             return Ok(next.run(request).await);
         }
 
@@ -134,51 +194,88 @@ async fn auth_middleware_internal(
                 .and_then(|h| h.to_str().ok())
         });
 
-    if security.api_key.is_empty() && (security.admin_password.is_none() || security.admin_password.as_ref().unwrap().is_empty()) {
+    // [NEW] 反代接口在仅配置了附加 API Key 时同样可用
+    let has_labeled_keys = !force_strict && security.has_labeled_keys();
+    // [NEW] 已创建管理用户时，管理接口可仅凭会话令牌访问
+    let has_admin_users = force_strict && !security.admin_session_secret.is_empty();
+    if security.api_key.is_empty() && !has_labeled_keys && !has_admin_users && (security.admin_password.is_none() || security.admin_password.as_ref().unwrap().is_empty()) {
         if force_strict {
              tracing::error!("Admin auth is required but both api_key and admin_password are empty; denying request");
-             return Err(StatusCode::UNAUTHORIZED);
+             return Err(auth_error(&path));
         }
         tracing::error!("Proxy auth is enabled but api_key is empty; denying request");
-        return Err(StatusCode::UNAUTHORIZED);
+        return Err(auth_error(&path));
     }
 
+    // [NEW] 命中的附加 API Key 标签 (管理接口不接受附加 Key)
+    let key_label = if force_strict {
+        None
+    } else {
+        api_key.and_then(|k| security.labeled_key(k)).map(|label| label.to_string())
+    };
+
+    // [NEW] 管理用户会话 (旧凭据未通过时尝试)
+    let mut admin_session = None;
+
     // 认证逻辑
     let authorized = if force_strict {
         // 管理接口：优先使用独立的 admin_password，如果没有则回退使用 api_key
-        match &security.admin_password {
+        let static_ok = match &security.admin_password {
             Some(pwd) if !pwd.is_empty() => {
                 api_key.map(|k| k == pwd).unwrap_or(false)
             }
             _ => {
-                // 回退使用 api_key
-                api_key.map(|k| k == security.api_key).unwrap_or(false)
+                // 回退使用 api_key (轮换宽限期内旧 Key 同样有效)
+                api_key.map(|k| security.accepts_api_key(k)).unwrap_or(false)
             }
+        };
+        // [NEW] 也接受 /api/auth/token 签发的短期 JWT
+        let legacy_ok = static_ok
+            || api_key
+                .filter(|k| crate::proxy::admin_jwt::looks_like_jwt(k))
+                .map(|k| {
+                    // 轮换宽限期内，旧 Key 签发的 JWT 仍然有效
+                    let result = crate::proxy::admin_jwt::verify_token(&security.api_key, k)
+                        .or_else(|e| match security.previous_api_key() {
+                            Some(previous) => crate::proxy::admin_jwt::verify_token(previous, k),
+                            None => Err(e),
+                        });
+                    match result {
+                        Ok(_) => true,
+                        Err(e) => {
+                            tracing::debug!("Admin JWT rejected: {}", e);
+                            false
+                        }
+                    }
+                })
+                .unwrap_or(false);
+        // 旧的 admin_password / api_key / 管理 JWT 视为完整管理员权限
+        if !legacy_ok {
+            admin_session = api_key.and_then(|k| resolve_admin_session(&security, k));
         }
+        legacy_ok || admin_session.is_some()
     } else {
-        // AI 代理接口：仅允许使用 api_key
-        api_key.map(|k| k == security.api_key).unwrap_or(false)
+        // AI 代理接口：api_key (轮换宽限期内旧 Key 同样有效) 或未禁用的附加 Key
+        api_key.map(|k| security.accepts_api_key(k)).unwrap_or(false) || key_label.is_some()
     };
 
     if authorized {
+        // 角色校验由内层的 admin_role_middleware 完成
+        if let Some(session) = admin_session {
+            if let Some(actor) = request.extensions_mut().get_mut::<AdminActor>() {
+                actor.session = Some(session);
+            }
+        }
+        if let Some(label) = key_label {
+            request.extensions_mut().insert(ApiKeyLabel(label));
+        }
         Ok(next.run(request).await)
     } else if !force_strict && api_key.is_some() {
         // 尝试验证 UserToken
         let token = api_key.unwrap();
         
-        // 提取 IP (复用逻辑)
-        let client_ip = request
-            .headers()
-            .get("x-forwarded-for")
-            .and_then(|v| v.to_str().ok())
-            .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
-            .or_else(|| {
-                request
-                    .headers()
-                    .get("x-real-ip")
-                    .and_then(|v| v.to_str().ok())
-                    .map(|s| s.to_string())
-            })
+        // 提取 IP (复用 ip_filter 逻辑，受 trusted_proxies 约束)
+        let client_ip = crate::proxy::middleware::ip_filter::extract_client_ip(&request, &security)
             .unwrap_or_else(|| "127.0.0.1".to_string()); // Default fallback
 
         // 验证 Token
@@ -206,23 +303,75 @@ async fn auth_middleware_internal(
                     
                     Ok(response)
                 } else {
-                    Err(StatusCode::UNAUTHORIZED)
+                    Err(auth_error(&path))
                 }
             }
             Ok((false, reason)) => {
                 tracing::warn!("UserToken rejected: {:?}", reason);
-                Err(StatusCode::UNAUTHORIZED)
+                Err(auth_error(&path))
             }
             Err(e) => {
                 tracing::error!("UserToken validation error: {}", e);
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
+                Err(ApiError::internal(format!("UserToken validation error: {}", e))
+                    .format(ApiErrorFormat::for_path(&path)))
             }
         }
     } else {
-        Err(StatusCode::UNAUTHORIZED)
+        Err(auth_error(&path))
     }
 }
 
+/// 校验管理用户会话令牌，并确认用户仍存在且会话未被吊销
+fn resolve_admin_session(security: &ProxySecurityConfig, token: &str) -> Option<AdminSession> {
+    if security.admin_session_secret.is_empty() || !crate::proxy::admin_jwt::looks_like_jwt(token) {
+        return None;
+    }
+    let claims = match crate::proxy::admin_jwt::verify_session(&security.admin_session_secret, token) {
+        Ok(claims) => claims,
+        Err(e) => {
+            tracing::debug!("Admin session rejected: {}", e);
+            return None;
+        }
+    };
+    match crate::modules::admin_users::get_user(&claims.sub) {
+        Ok(Some(user)) if user.session_version == claims.ver => Some(AdminSession {
+            username: user.username,
+            role: user.role,
+            jti: claims.jti,
+            exp: claims.exp,
+        }),
+        Ok(_) => {
+            tracing::debug!("Admin session for '{}' is no longer valid", claims.sub);
+            None
+        }
+        Err(e) => {
+            tracing::error!("Admin user lookup failed: {}", e);
+            None
+        }
+    }
+}
+
+/// 管理接口操作者信息 (由 admin_auth_middleware 注入，供审计日志使用)
+#[derive(Clone, Debug)]
+pub struct AdminActor {
+    pub ip: String,
+    /// 通过管理用户会话登录时的用户信息；旧凭据 (admin_password / api_key) 为 None
+    pub session: Option<AdminSession>,
+}
+
+/// 已登录的管理用户会话
+#[derive(Clone, Debug)]
+pub struct AdminSession {
+    pub username: String,
+    pub role: AdminRole,
+    pub jti: String,
+    pub exp: i64,
+}
+
+/// 命中的附加 API Key 标签 (传递给 Monitor 用于用量归属)
+#[derive(Clone, Debug)]
+pub struct ApiKeyLabel(pub String);
+
 /// 用户令牌身份信息 (传递给 Monitor 使用)
 #[derive(Clone, Debug)]
 pub struct UserTokenIdentity {
@@ -241,10 +390,17 @@ mod tests {
         let security = Arc::new(RwLock::new(ProxySecurityConfig {
             auth_mode: ProxyAuthMode::Strict,
             api_key: "sk-api".to_string(),
+            api_key_previous: None,
+            api_key_previous_expires_at: None,
+            api_keys: Vec::new(),
             admin_password: Some("admin123".to_string()),
+            jwt_expiry_seconds: 3600,
+            admin_session_secret: String::new(),
             allow_lan_access: true,
             port: 8045,
             security_monitor: crate::proxy::config::SecurityMonitorConfig::default(),
+            cidr_rules: crate::proxy::security::CidrRules::default(),
+            cors: crate::proxy::config::CorsConfig::default(),
         }));
 
         // 模拟请求 - 管理接口使用正确的管理密码
@@ -262,4 +418,79 @@ mod tests {
     fn test_auth_placeholder() {
         assert!(true);
     }
+
+    /// 按角色走一遍 admin_role_middleware，返回状态码
+    async fn role_status(role: Option<AdminRole>, method: &str, uri: &str) -> axum::http::StatusCode {
+        use axum::routing::{any, get, post};
+        use tower::ServiceExt;
+
+        let admin_routes = axum::Router::new()
+            .route("/config", get(|| async { "ok" }).post(|| async { "ok" }))
+            .route("/config/reload", post(|| async { "ok" }))
+            .route("/proxy/api-key/rotate", post(|| async { "ok" }))
+            .route("/proxy/keys", any(|| async { "ok" }))
+            .route("/proxy/keys/:label", any(|| async { "ok" }))
+            .route("/system/backups/:name/restore", post(|| async { "ok" }))
+            .route("/accounts/import/db", post(|| async { "ok" }))
+            .layer(axum::middleware::from_fn(admin_role_middleware))
+            .layer(axum::middleware::from_fn(move |mut req: Request, next: Next| async move {
+                req.extensions_mut().insert(AdminActor {
+                    ip: "127.0.0.1".to_string(),
+                    session: role.map(|role| AdminSession {
+                        username: "tester".to_string(),
+                        role,
+                        jti: "jti".to_string(),
+                        exp: i64::MAX,
+                    }),
+                });
+                next.run(req).await
+            }));
+        let app = axum::Router::new().nest("/api", admin_routes);
+        app.oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+    }
+
+    #[tokio::test]
+    async fn test_admin_role_middleware_sensitive_routes() {
+        use axum::http::StatusCode;
+
+        let sensitive = [
+            ("POST", "/api/config"),
+            ("POST", "/api/config/reload"),
+            ("POST", "/api/proxy/api-key/rotate"),
+            ("GET", "/api/proxy/keys"),
+            ("POST", "/api/proxy/keys"),
+            ("DELETE", "/api/proxy/keys/ci"),
+            ("POST", "/api/system/backups/backup-1.json/restore"),
+            ("POST", "/api/accounts/import/db"),
+        ];
+        for (method, uri) in sensitive {
+            for role in [AdminRole::Viewer, AdminRole::Operator] {
+                assert_eq!(
+                    role_status(Some(role), method, uri).await,
+                    StatusCode::FORBIDDEN,
+                    "{} {} should be denied for {}",
+                    method,
+                    uri,
+                    role.as_str()
+                );
+            }
+            assert_eq!(role_status(Some(AdminRole::Admin), method, uri).await, StatusCode::OK);
+            // 旧凭据 (无会话) 保持完整权限
+            assert_eq!(role_status(None, method, uri).await, StatusCode::OK);
+        }
+
+        // 读取配置对所有角色开放 (敏感字段由 handler 脱敏)
+        for role in [AdminRole::Viewer, AdminRole::Operator, AdminRole::Admin] {
+            assert_eq!(role_status(Some(role), "GET", "/api/config").await, StatusCode::OK);
+        }
+    }
 }
@@ -0,0 +1,26 @@
+// 每日 Token 预算中间件 - 全局预算耗尽后拒绝新的生成请求 (429 + 午夜重置时间)
+// 单账号预算不在这里处理，由 TokenManager 在轮换时排除超额账号
+
+use axum::{
+    extract::Request,
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::proxy::budget::BudgetTracker;
+use crate::proxy::error::ApiErrorFormat;
+
+pub async fn budget_middleware(request: Request, next: Next) -> Response {
+    // 只拦截会消耗 Token 的请求，模型列表等查询不受影响
+    let path = request.uri().path();
+    if request.method() == Method::POST && !path.contains("event_logging") {
+        if let Err(exceeded) = BudgetTracker::global().check_global() {
+            return exceeded
+                .into_api_error()
+                .format(ApiErrorFormat::for_path(path))
+                .into_response();
+        }
+    }
+    next.run(request).await
+}
@@ -1,33 +1,361 @@
 // CORS 中间件
-use tower_http::cors::{CorsLayer, Any};
-use axum::http::Method;
-
-/// 创建 CORS layer
-pub fn cors_layer() -> CorsLayer {
-    CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods([
-            Method::GET,
-            Method::POST,
-            Method::PUT,
-            Method::DELETE,
-            Method::HEAD,
-            Method::OPTIONS,
-            Method::PATCH,
-        ])
-        .allow_headers(Any)
-        .allow_credentials(false)
-        .max_age(std::time::Duration::from_secs(3600))
+// 根据 `proxy.cors` 配置按来源与路径放行，配置随 update_security 热更新
+// 凭据 (Allow-Credentials) 只下发给管理 API，反代路由一律不携带
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::proxy::config::CorsConfig;
+use crate::proxy::ProxySecurityConfig;
+
+const ALLOWED_METHODS: &str = "GET, POST, PUT, DELETE, HEAD, OPTIONS, PATCH";
+
+/// 管理 API 路径前缀 (管理界面需要携带凭据)
+const ADMIN_PATH_PREFIX: &str = "/api/";
+
+/// 路径允许的方法列表: `path_methods` 中最长前缀匹配，未命中时为 None (允许全部)
+fn methods_for_path<'a>(config: &'a CorsConfig, path: &str) -> Option<&'a [String]> {
+    config
+        .path_methods
+        .iter()
+        .filter(|(prefix, _)| !prefix.is_empty() && path.starts_with(prefix.as_str()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, methods)| methods.as_slice())
+}
+
+/// 来源匹配结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OriginMatch {
+    /// 未列入 allowed_origins
+    Denied,
+    /// 通过 `*` 放行 (按规范不能携带凭据)
+    Wildcard,
+    /// 通过精确来源 / 主机名规则放行
+    Explicit,
+}
+
+/// 判断 Origin 是否被允许；显式规则优先于 `*`，以便在同时配置时仍可携带凭据
+pub fn match_origin(config: &CorsConfig, origin: &str) -> OriginMatch {
+    let origin = origin.trim().trim_end_matches('/');
+    let host = origin_host(origin);
+    let mut wildcard = false;
+
+    for rule in config.allowed_origins.iter().map(|r| r.trim()) {
+        if rule == "*" {
+            wildcard = true;
+            continue;
+        }
+        let matched = if rule.contains("://") {
+            rule.trim_end_matches('/').eq_ignore_ascii_case(origin)
+        } else if let Some(suffix) = rule.strip_prefix("*.") {
+            host.as_deref().map_or(false, |h| {
+                h.len() > suffix.len() + 1
+                    && h.ends_with(&suffix.to_ascii_lowercase())
+                    && h.as_bytes()[h.len() - suffix.len() - 1] == b'.'
+            })
+        } else {
+            host.as_deref().map_or(false, |h| h.eq_ignore_ascii_case(rule))
+        };
+        if matched {
+            return OriginMatch::Explicit;
+        }
+    }
+
+    if wildcard {
+        OriginMatch::Wildcard
+    } else {
+        OriginMatch::Denied
+    }
+}
+
+/// 从 `scheme://host[:port]` 中提取小写主机名
+fn origin_host(origin: &str) -> Option<String> {
+    let rest = origin.split_once("://").map(|(_, r)| r)?;
+    let host = if let Some(v6) = rest.strip_prefix('[') {
+        v6.split(']').next()?
+    } else {
+        rest.split(':').next()?
+    };
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
+/// CORS 中间件
+pub async fn cors_middleware(
+    State(security): State<Arc<RwLock<ProxySecurityConfig>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(origin) = request
+        .headers()
+        .get(header::ORIGIN)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+    else {
+        // 非跨域请求直接放行
+        return next.run(request).await;
+    };
+
+    let config = security.read().await.cors.clone();
+    let matched = match_origin(&config, &origin);
+    let path = request.uri().path().to_string();
+    let allow_credentials = config.allow_credentials && path.starts_with(ADMIN_PATH_PREFIX);
+    let is_preflight = request.method() == Method::OPTIONS
+        && request
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+
+    if is_preflight {
+        if matched == OriginMatch::Denied {
+            tracing::debug!("[CORS] Rejected preflight from unlisted origin: {}", origin);
+            return (StatusCode::FORBIDDEN, "CORS origin not allowed").into_response();
+        }
+
+        let path_methods = methods_for_path(&config, &path);
+        if let Some(methods) = path_methods {
+            let requested = request
+                .headers()
+                .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default();
+            if !methods.iter().any(|m| m.trim().eq_ignore_ascii_case(requested)) {
+                tracing::debug!("[CORS] Rejected preflight {} {} from {}", requested, path, origin);
+                return (StatusCode::FORBIDDEN, "CORS method not allowed").into_response();
+            }
+        }
+
+        let mut response = StatusCode::NO_CONTENT.into_response();
+        let headers = response.headers_mut();
+        apply_origin_headers(headers, allow_credentials, matched, &origin);
+        let allowed_methods = match path_methods {
+            Some(methods) => HeaderValue::from_str(
+                &methods.iter().map(|m| m.trim().to_ascii_uppercase()).collect::<Vec<_>>().join(", "),
+            )
+            .unwrap_or_else(|_| HeaderValue::from_static(ALLOWED_METHODS)),
+            None => HeaderValue::from_static(ALLOWED_METHODS),
+        };
+        headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, allowed_methods);
+        if let Some(req_headers) = request
+            .headers()
+            .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+        {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, req_headers.clone());
+        }
+        headers.insert(
+            header::ACCESS_CONTROL_MAX_AGE,
+            HeaderValue::from(config.max_age_seconds),
+        );
+        headers.append(
+            header::VARY,
+            HeaderValue::from_static("Access-Control-Request-Method, Access-Control-Request-Headers"),
+        );
+        return response;
+    }
+
+    let mut response = next.run(request).await;
+    if matched != OriginMatch::Denied {
+        apply_origin_headers(response.headers_mut(), allow_credentials, matched, &origin);
+    }
+    response
+}
+
+fn apply_origin_headers(
+    headers: &mut HeaderMap,
+    allow_credentials: bool,
+    matched: OriginMatch,
+    origin: &str,
+) {
+    match matched {
+        OriginMatch::Wildcard => {
+            // 规范要求: Allow-Origin 为 `*` 时不得下发 Allow-Credentials
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                HeaderValue::from_static("*"),
+            );
+        }
+        OriginMatch::Explicit => {
+            if let Ok(value) = HeaderValue::from_str(origin) {
+                headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, value);
+            }
+            if allow_credentials {
+                headers.insert(
+                    header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                    HeaderValue::from_static("true"),
+                );
+            }
+            headers.append(header::VARY, HeaderValue::from_static("Origin"));
+        }
+        OriginMatch::Denied => {}
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::proxy::ProxyConfig;
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app(cors: CorsConfig) -> Router {
+        let security = Arc::new(RwLock::new(ProxySecurityConfig::from_proxy_config(
+            &ProxyConfig {
+                cors,
+                ..Default::default()
+            },
+        )));
+        Router::new()
+            .route("/v1/models", get(|| async { "ok" }))
+            .route("/api/accounts", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(security, cors_middleware))
+    }
+
+    fn preflight(origin: &str) -> Request {
+        preflight_to(origin, "/api/accounts", "POST")
+    }
+
+    fn preflight_to(origin: &str, uri: &str, method: &str) -> Request {
+        Request::builder()
+            .method(Method::OPTIONS)
+            .uri(uri)
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, method)
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "authorization")
+            .body(Body::empty())
+            .unwrap()
+    }
 
     #[test]
-    fn test_cors_layer_creation() {
-        let _layer = cors_layer();
-        // Layer 创建成功
-        assert!(true);
+    fn test_match_origin_rules() {
+        let config = CorsConfig {
+            allowed_origins: vec![
+                "https://app.example.com".to_string(),
+                "localhost".to_string(),
+                "*.internal.dev".to_string(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(match_origin(&config, "https://app.example.com"), OriginMatch::Explicit);
+        assert_eq!(match_origin(&config, "http://app.example.com"), OriginMatch::Denied);
+        assert_eq!(match_origin(&config, "http://localhost:5173"), OriginMatch::Explicit);
+        assert_eq!(match_origin(&config, "https://a.internal.dev"), OriginMatch::Explicit);
+        assert_eq!(match_origin(&config, "https://internal.dev"), OriginMatch::Denied);
+        assert_eq!(match_origin(&config, "https://evilinternal.dev"), OriginMatch::Denied);
+        assert_eq!(match_origin(&config, "null"), OriginMatch::Denied);
+    }
+
+    #[tokio::test]
+    async fn test_unlisted_origin_preflight_forbidden() {
+        let app = app(CorsConfig {
+            allowed_origins: vec!["https://app.example.com".to_string()],
+            allow_credentials: true,
+            max_age_seconds: 600,
+            ..Default::default()
+        });
+
+        let resp = app.clone().oneshot(preflight("https://evil.example.org")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert!(resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+        let resp = app.oneshot(preflight("https://app.example.com")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_HEADERS], "authorization");
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_MAX_AGE], "600");
+    }
+
+    #[tokio::test]
+    async fn test_wildcard_disables_credentials() {
+        let app = app(CorsConfig {
+            allowed_origins: vec!["*".to_string()],
+            allow_credentials: true,
+            max_age_seconds: 3600,
+            ..Default::default()
+        });
+
+        let resp = app.clone().oneshot(preflight("https://any.example.org")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(resp.headers().get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).is_none());
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/models")
+                    .header(header::ORIGIN, "https://any.example.org")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(resp.headers().get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_credentials_only_on_admin_paths() {
+        let app = app(CorsConfig {
+            allowed_origins: vec!["https://app.example.com".to_string()],
+            allow_credentials: true,
+            ..Default::default()
+        });
+
+        let resp = app
+            .clone()
+            .oneshot(preflight_to("https://app.example.com", "/v1/models", "POST"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(resp.headers().get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).is_none());
+
+        let resp = app
+            .oneshot(preflight_to("https://app.example.com", "/api/accounts", "GET"))
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    }
+
+    #[tokio::test]
+    async fn test_path_methods_restrict_preflight() {
+        let app = app(CorsConfig {
+            allowed_origins: vec!["*".to_string()],
+            path_methods: std::collections::HashMap::from([
+                ("/v1".to_string(), vec!["GET".to_string(), "post".to_string()]),
+                ("/v1/models".to_string(), vec!["GET".to_string()]),
+            ]),
+            ..Default::default()
+        });
+
+        let resp = app
+            .clone()
+            .oneshot(preflight_to("https://any.example.org", "/v1/chat/completions", "POST"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_METHODS], "GET, POST");
+
+        // 最长前缀优先
+        let resp = app
+            .clone()
+            .oneshot(preflight_to("https://any.example.org", "/v1/models", "POST"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        // 未配置的路径允许全部方法
+        let resp = app
+            .oneshot(preflight_to("https://any.example.org", "/api/accounts", "DELETE"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_METHODS], ALLOWED_METHODS);
     }
 }
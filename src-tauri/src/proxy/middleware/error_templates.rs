@@ -0,0 +1,395 @@
+// 错误响应模板中间件 - 将常见 AI 错误替换为用户配置的模板 (ProxyConfig.error_templates)
+// 仅作用于反代路由的非流式错误响应；未识别或未配置模板的错误原样返回
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::proxy::config::ErrorTemplateConfig;
+use crate::proxy::error::{ApiError, ApiErrorCode, ApiErrorFormat};
+use crate::proxy::TokenManager;
+
+const MAX_ERROR_BODY_SIZE: usize = 1024 * 1024; // 1MB
+
+/// 上下文超长的常见错误描述 (小写匹配)
+const CONTEXT_TOO_LONG_PATTERNS: [&str; 7] = [
+    "context_too_long",
+    "context_length_exceeded",
+    "prompt is too long",
+    "context length",
+    "maximum context",
+    "input token count",
+    "exceeds the maximum number of tokens",
+];
+
+/// 可模板化的错误类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplatedError {
+    QuotaExceeded,
+    ModelNotFound,
+    ContextTooLong,
+}
+
+impl TemplatedError {
+    fn template(self, config: &ErrorTemplateConfig) -> Option<&str> {
+        match self {
+            TemplatedError::QuotaExceeded => config.quota_exceeded.as_deref(),
+            TemplatedError::ModelNotFound => config.model_not_found.as_deref(),
+            TemplatedError::ContextTooLong => config.context_too_long.as_deref(),
+        }
+        .filter(|t| !t.trim().is_empty())
+    }
+
+    fn code(self) -> ApiErrorCode {
+        match self {
+            TemplatedError::QuotaExceeded => ApiErrorCode::UpstreamQuotaExhausted,
+            TemplatedError::ModelNotFound => ApiErrorCode::NotFound,
+            TemplatedError::ContextTooLong => ApiErrorCode::ContextTooLong,
+        }
+    }
+}
+
+/// 按状态码与错误内容识别错误类型
+pub fn classify_error(status: u16, body: &str) -> Option<TemplatedError> {
+    let lower = body.to_ascii_lowercase();
+    if matches!(status, 400 | 413) && CONTEXT_TOO_LONG_PATTERNS.iter().any(|p| lower.contains(p)) {
+        return Some(TemplatedError::ContextTooLong);
+    }
+    match status {
+        429 => Some(TemplatedError::QuotaExceeded),
+        404 if lower.contains("model") => Some(TemplatedError::ModelNotFound),
+        _ => None,
+    }
+}
+
+/// 从协议错误 envelope 中提取错误描述 (OpenAI / Claude / Gemini / 管理接口格式)，否则返回原文
+pub fn extract_upstream_error(body: &str) -> String {
+    let Ok(json) = serde_json::from_str::<Value>(body) else {
+        return body.trim().to_string();
+    };
+    json.pointer("/error/message")
+        .or_else(|| json.get("error").filter(|e| e.is_string()))
+        .or_else(|| json.get("message"))
+        .and_then(|m| m.as_str())
+        .map(|m| m.to_string())
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+/// 模板响应的 Content-Type (按客户端 Accept 协商)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateContentType {
+    Json,
+    Text,
+    Html,
+}
+
+impl TemplateContentType {
+    /// 按 q 值选择支持的类型；未携带 Accept 或无法匹配时使用 JSON
+    pub fn negotiate(accept: Option<&str>) -> Self {
+        let mut best: Option<(f32, Self)> = None;
+        for range in accept.unwrap_or_default().split(',') {
+            let mut params = range.split(';');
+            let media = params.next().unwrap_or_default().trim().to_ascii_lowercase();
+            let q = params
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            let kind = match media.as_str() {
+                "application/json" | "application/*" | "*/*" => Self::Json,
+                "text/plain" | "text/*" => Self::Text,
+                "text/html" => Self::Html,
+                _ => continue,
+            };
+            let better = match best {
+                Some((best_q, _)) => q > best_q,
+                None => true,
+            };
+            if q > 0.0 && better {
+                best = Some((q, kind));
+            }
+        }
+        best.map(|(_, kind)| kind).unwrap_or(Self::Json)
+    }
+
+    fn header_value(self) -> HeaderValue {
+        HeaderValue::from_static(match self {
+            Self::Json => "application/json",
+            Self::Text => "text/plain; charset=utf-8",
+            Self::Html => "text/html; charset=utf-8",
+        })
+    }
+}
+
+/// 模板变量上下文
+#[derive(Debug, Default, Clone)]
+pub struct ErrorTemplateContext {
+    pub account_id: Option<String>,
+    pub model: Option<String>,
+    pub upstream_error: String,
+}
+
+/// 展开模板变量，`escape` 用于按目标格式转义变量值
+fn expand(template: &str, ctx: &ErrorTemplateContext, escape: impl Fn(&str) -> String) -> String {
+    template
+        .replace("{{account_id}}", &escape(ctx.account_id.as_deref().unwrap_or("")))
+        .replace("{{model}}", &escape(ctx.model.as_deref().unwrap_or("")))
+        .replace("{{upstream_error}}", &escape(&ctx.upstream_error))
+}
+
+fn escape_json(value: &str) -> String {
+    let quoted = serde_json::to_string(value).unwrap_or_default();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 渲染模板响应体
+/// JSON: 模板本身是 JSON 时直接返回 (变量值按 JSON 字符串转义)，否则作为 message 包装为协议错误格式
+pub fn render_template(
+    template: &str,
+    ctx: &ErrorTemplateContext,
+    content_type: TemplateContentType,
+    kind: TemplatedError,
+    status: axum::http::StatusCode,
+    format: ApiErrorFormat,
+) -> String {
+    match content_type {
+        TemplateContentType::Text => expand(template, ctx, str::to_string),
+        TemplateContentType::Html => expand(template, ctx, escape_html),
+        TemplateContentType::Json => {
+            let rendered = expand(template, ctx, escape_json);
+            if serde_json::from_str::<Value>(&rendered).is_ok() {
+                return rendered;
+            }
+            let message = expand(template, ctx, str::to_string);
+            ApiError::new(kind.code(), message)
+                .with_status(status)
+                .format(format)
+                .body()
+                .to_string()
+        }
+    }
+}
+
+pub async fn error_template_middleware(
+    State(token_manager): State<Arc<TokenManager>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let templates = crate::proxy::get_error_templates();
+    if templates.configured_count() == 0 {
+        return next.run(request).await;
+    }
+
+    let path = request.uri().path().to_string();
+    let accept = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+
+    let response = next.run(request).await;
+    let status = response.status();
+    let is_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.contains("text/event-stream"));
+    if !(status.is_client_error() || status.is_server_error()) || is_stream {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_ERROR_BODY_SIZE).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("[Error-Templates] Failed to buffer error body on {}: {}", path, e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let text = String::from_utf8_lossy(&bytes);
+    let Some((kind, template)) = classify_error(status.as_u16(), &text)
+        .and_then(|kind| kind.template(&templates).map(|t| (kind, t.to_string())))
+    else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let header = |name: &str| {
+        parts
+            .headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string())
+    };
+    let ctx = ErrorTemplateContext {
+        account_id: header("X-Account-Email")
+            .and_then(|email| token_manager.get_account_id_by_email(&email)),
+        model: header("X-Mapped-Model"),
+        upstream_error: extract_upstream_error(&text),
+    };
+    let content_type = TemplateContentType::negotiate(accept.as_deref());
+    let rendered = render_template(
+        &template,
+        &ctx,
+        content_type,
+        kind,
+        status,
+        ApiErrorFormat::for_path(&path),
+    );
+    tracing::debug!("[Error-Templates] Applied {:?} template on {}", kind, path);
+
+    parts.headers.insert(header::CONTENT_TYPE, content_type.header_value());
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(rendered))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, routing::post, Router};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_classify_error() {
+        assert_eq!(
+            classify_error(429, r#"{"error":{"status":"RESOURCE_EXHAUSTED"}}"#),
+            Some(TemplatedError::QuotaExceeded)
+        );
+        assert_eq!(
+            classify_error(404, "models/gemini-9 is not found"),
+            Some(TemplatedError::ModelNotFound)
+        );
+        assert_eq!(
+            classify_error(400, r#"{"error":{"code":"context_too_long"}}"#),
+            Some(TemplatedError::ContextTooLong)
+        );
+        assert_eq!(classify_error(400, "invalid tool schema"), None);
+        assert_eq!(classify_error(404, "no route"), None);
+        assert_eq!(classify_error(500, "internal"), None);
+    }
+
+    #[test]
+    fn test_negotiate_content_type() {
+        assert_eq!(TemplateContentType::negotiate(None), TemplateContentType::Json);
+        assert_eq!(TemplateContentType::negotiate(Some("*/*")), TemplateContentType::Json);
+        assert_eq!(
+            TemplateContentType::negotiate(Some("text/plain")),
+            TemplateContentType::Text
+        );
+        assert_eq!(
+            TemplateContentType::negotiate(Some("application/json;q=0.5, text/html")),
+            TemplateContentType::Html
+        );
+        assert_eq!(
+            TemplateContentType::negotiate(Some("image/png")),
+            TemplateContentType::Json
+        );
+    }
+
+    #[test]
+    fn test_render_json_escapes_values() {
+        let ctx = ErrorTemplateContext {
+            account_id: Some("acc-1".to_string()),
+            model: Some("gemini-3-pro".to_string()),
+            upstream_error: r#"quota "exhausted""#.to_string(),
+        };
+        let rendered = render_template(
+            r#"{"error": "{{model}} busy: {{upstream_error}}", "account": "{{account_id}}"}"#,
+            &ctx,
+            TemplateContentType::Json,
+            TemplatedError::QuotaExceeded,
+            StatusCode::TOO_MANY_REQUESTS,
+            ApiErrorFormat::OpenAI,
+        );
+        let json: Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(json["error"], r#"gemini-3-pro busy: quota "exhausted""#);
+        assert_eq!(json["account"], "acc-1");
+
+        // 纯文本模板在 JSON 下包装为协议错误格式
+        let wrapped = render_template(
+            "Model {{model}} is busy",
+            &ctx,
+            TemplateContentType::Json,
+            TemplatedError::QuotaExceeded,
+            StatusCode::TOO_MANY_REQUESTS,
+            ApiErrorFormat::OpenAI,
+        );
+        let json: Value = serde_json::from_str(&wrapped).unwrap();
+        assert_eq!(json["error"]["message"], "Model gemini-3-pro is busy");
+        assert_eq!(json["error"]["code"], "upstream_quota_exhausted");
+    }
+
+    #[tokio::test]
+    async fn test_middleware_applies_template_by_accept() {
+        crate::proxy::update_error_templates(ErrorTemplateConfig {
+            quota_exceeded: Some("Quota exhausted for {{model}}: {{upstream_error}}".to_string()),
+            ..Default::default()
+        });
+
+        let token_manager = Arc::new(TokenManager::new(std::env::temp_dir()));
+        let app = Router::new()
+            .route(
+                "/v1/chat/completions",
+                post(|| async {
+                    ApiError::from_status(StatusCode::TOO_MANY_REQUESTS, "RESOURCE_EXHAUSTED")
+                        .with_header("X-Mapped-Model", "gemini-3-flash")
+                        .openai()
+                }),
+            )
+            .route(
+                "/v1/messages",
+                post(|| async { ApiError::invalid_request("bad tool schema").claude() }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                token_manager,
+                error_template_middleware,
+            ));
+
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("accept", "text/plain")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers()["content-type"], "text/plain; charset=utf-8");
+        assert_eq!(resp.headers()["X-Mapped-Model"], "gemini-3-flash");
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, "Quota exhausted for gemini-3-flash: RESOURCE_EXHAUSTED");
+
+        // 未识别的错误原样返回
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"]["message"], "bad tool schema");
+
+        crate::proxy::update_error_templates(ErrorTemplateConfig::default());
+    }
+}
@@ -6,7 +6,12 @@ use axum::{
     body::Body,
 };
 use crate::proxy::server::AppState;
+use crate::proxy::ProxySecurityConfig;
+use crate::proxy::security::resolve_client_ip;
+use crate::proxy::middleware::ip_rate_limit::{rate_limited_response, IpRateLimiter};
 use crate::modules::security_db;
+use serde::Serialize;
+use std::net::IpAddr;
 
 /// IP 黑白名单过滤中间件
 pub async fn ip_filter_middleware(
@@ -15,12 +20,25 @@ pub async fn ip_filter_middleware(
     next: Next,
 ) -> Response {
     // 提取客户端 IP
-    let client_ip = extract_client_ip(&request);
+    let client_ip = extract_client_ip(&request, &*state.security.read().await);
     
     if let Some(ip) = &client_ip {
         // 读取安全配置
         let security_config = state.security.read().await;
-        
+        let parsed_ip = ip.parse::<IpAddr>().ok();
+
+        // 0. 配置文件中的 CIDR 规则 (内存匹配，无需查库)
+        let cidr_allowed = parsed_ip
+            .map(|addr| !security_config.cidr_rules.matching_allowed(addr).is_empty())
+            .unwrap_or(false);
+        if cidr_allowed
+            && (security_config.security_monitor.whitelist.enabled
+                || security_config.security_monitor.whitelist.whitelist_priority)
+        {
+            tracing::debug!("[IP Filter] IP {} matches allowed_cidrs, allowing", ip);
+            return next.run(request).await;
+        }
+
         // 1. 检查白名单 (如果启用白名单模式,只允许白名单 IP)
         if security_config.security_monitor.whitelist.enabled {
             match security_db::is_ip_in_whitelist(ip) {
@@ -61,6 +79,41 @@ pub async fn ip_filter_middleware(
 
         // 2. 检查黑名单
         if security_config.security_monitor.blacklist.enabled {
+            let blocked_net = parsed_ip.and_then(|addr| {
+                security_config
+                    .cidr_rules
+                    .matching_blocked(addr)
+                    .first()
+                    .map(|net| net.to_string())
+            });
+            if let Some(net) = blocked_net {
+                tracing::warn!("[IP Filter] IP {} matches blocked CIDR {}, blocking", ip, net);
+                let log = security_db::IpAccessLog {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    client_ip: ip.clone(),
+                    timestamp: chrono::Utc::now().timestamp(),
+                    method: Some(request.method().to_string()),
+                    path: Some(request.uri().to_string()),
+                    user_agent: request
+                        .headers()
+                        .get("user-agent")
+                        .and_then(|v| v.to_str().ok())
+                        .map(|s| s.to_string()),
+                    status: Some(403),
+                    duration: Some(0),
+                    api_key_hash: None,
+                    blocked: true,
+                    block_reason: Some(format!("IP in blocked CIDR: {}", net)),
+                    username: None,
+                };
+                tokio::spawn(async move {
+                    if let Err(e) = security_db::save_ip_access_log(&log) {
+                        tracing::error!("[IP Filter] Failed to save blocked access log: {}", e);
+                    }
+                });
+                return create_blocked_response(ip, &security_config.security_monitor.blacklist.block_message);
+            }
+
             match security_db::get_blacklist_entry_for_ip(ip) {
                 Ok(Some(entry)) => {
                     tracing::warn!("[IP Filter] IP {} is in blacklist, blocking", ip);
@@ -136,6 +189,25 @@ pub async fn ip_filter_middleware(
                 }
             }
         }
+
+        // 3. 按 IP 限流 (白名单 IP 已在上方提前放行；此处兜底仅在超限时才查询白名单)
+        let rate_limit = &security_config.security_monitor.rate_limit;
+        if rate_limit.enabled {
+            if let Err(retry_after) = IpRateLimiter::global().check(ip, rate_limit) {
+                if cidr_allowed || security_db::is_ip_in_whitelist(ip).unwrap_or(false) {
+                    tracing::debug!("[IP Filter] IP {} is whitelisted, bypassing rate limit", ip);
+                } else {
+                    tracing::warn!(
+                        "[IP Filter] IP {} exceeded rate limit ({} rpm, burst {}), retry after {:?}",
+                        ip,
+                        rate_limit.rpm,
+                        rate_limit.burst,
+                        retry_after
+                    );
+                    return rate_limited_response(ip, retry_after);
+                }
+            }
+        }
     } else {
         tracing::warn!("[IP Filter] Unable to extract client IP from request");
     }
@@ -144,30 +216,98 @@ pub async fn ip_filter_middleware(
     next.run(request).await
 }
 
-/// 从请求中提取客户端 IP
-fn extract_client_ip(request: &Request) -> Option<String> {
-    // 1. 优先从 X-Forwarded-For 提取 (取第一个 IP)
-    request
-        .headers()
-        .get("x-forwarded-for")
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
-        .or_else(|| {
-            // 2. 备选从 X-Real-IP 提取
-            request
-                .headers()
-                .get("x-real-ip")
-                .and_then(|v| v.to_str().ok())
-                .map(|s| s.to_string())
+/// IP 规则匹配结果 (供 `POST /api/security/test-ip` 调试使用)
+#[derive(Debug, Serialize)]
+pub struct IpRuleReport {
+    pub ip: String,
+    pub valid: bool,
+    pub whitelist_enabled: bool,
+    pub blacklist_enabled: bool,
+    pub matched_allowed_cidrs: Vec<String>,
+    pub matched_blocked_cidrs: Vec<String>,
+    pub in_db_whitelist: bool,
+    pub db_blacklist_pattern: Option<String>,
+    /// 中间件对该 IP 的最终处理结果: "allow" / "block"
+    pub decision: &'static str,
+}
+
+/// 按中间件相同的优先级评估一个 IP 会命中哪些规则 (不增加黑名单命中计数)
+pub fn explain_ip(security: &ProxySecurityConfig, ip: &str) -> IpRuleReport {
+    let parsed = ip.trim().parse::<IpAddr>().ok();
+    let monitor = &security.security_monitor;
+
+    let matched_allowed_cidrs: Vec<String> = parsed
+        .map(|addr| {
+            security
+                .cidr_rules
+                .matching_allowed(addr)
+                .iter()
+                .map(|n| n.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    let matched_blocked_cidrs: Vec<String> = parsed
+        .map(|addr| {
+            security
+                .cidr_rules
+                .matching_blocked(addr)
+                .iter()
+                .map(|n| n.to_string())
+                .collect()
         })
-        .or_else(|| {
-            // 3. 最后尝试从 ConnectInfo 获取 (TCP 连接 IP)
-            // 这可以解决本地开发/测试时没有代理头导致 IP 获取失败的问题
-            request
-                .extensions()
-                .get::<axum::extract::ConnectInfo<std::net::SocketAddr>>()
-                .map(|info| info.0.ip().to_string())
+        .unwrap_or_default();
+
+    let in_db_whitelist = security_db::is_ip_in_whitelist(ip).unwrap_or(false);
+    let db_blacklist_pattern = security_db::get_blacklist()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|entry| {
+            let now = chrono::Utc::now().timestamp();
+            let active = entry.expires_at.map(|t| t >= now).unwrap_or(true);
+            active
+                && (entry.ip_pattern == ip
+                    || (entry.ip_pattern.contains('/')
+                        && security_db::cidr_match(ip, &entry.ip_pattern)))
         })
+        .map(|entry| entry.ip_pattern);
+
+    let whitelisted = !matched_allowed_cidrs.is_empty() || in_db_whitelist;
+    let decision = if monitor.whitelist.enabled {
+        if whitelisted {
+            "allow"
+        } else {
+            "block"
+        }
+    } else if whitelisted && monitor.whitelist.whitelist_priority {
+        "allow"
+    } else if monitor.blacklist.enabled
+        && (!matched_blocked_cidrs.is_empty() || db_blacklist_pattern.is_some())
+    {
+        "block"
+    } else {
+        "allow"
+    };
+
+    IpRuleReport {
+        ip: ip.to_string(),
+        valid: parsed.is_some(),
+        whitelist_enabled: monitor.whitelist.enabled,
+        blacklist_enabled: monitor.blacklist.enabled,
+        matched_allowed_cidrs,
+        matched_blocked_cidrs,
+        in_db_whitelist,
+        db_blacklist_pattern,
+        decision,
+    }
+}
+
+/// 从请求中提取客户端 IP (受 `trusted_proxies` 约束，见 [`resolve_client_ip`])
+pub fn extract_client_ip(request: &Request, security: &ProxySecurityConfig) -> Option<String> {
+    let peer = request
+        .extensions()
+        .get::<axum::extract::ConnectInfo<std::net::SocketAddr>>()
+        .map(|info| info.0.ip());
+    resolve_client_ip(request.headers(), peer, &security.cidr_rules)
 }
 
 /// 创建被封禁的响应
@@ -0,0 +1,184 @@
+// 按客户端 IP 的令牌桶限流
+// 由 ip_filter 中间件在黑白名单判定之后调用，桶只保存在内存中，空闲桶定期清理
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use dashmap::DashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::proxy::config::IpRateLimitConfig;
+
+/// 清理空闲桶的最小间隔
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+pub struct IpRateLimiter {
+    buckets: DashMap<String, Bucket>,
+    last_prune: Mutex<Instant>,
+}
+
+impl IpRateLimiter {
+    pub fn new() -> Self {
+        Self {
+            buckets: DashMap::new(),
+            last_prune: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// 全局实例 (配置热更新后桶状态保留，新参数在下次取令牌时生效)
+    pub fn global() -> &'static IpRateLimiter {
+        static INSTANCE: OnceLock<IpRateLimiter> = OnceLock::new();
+        INSTANCE.get_or_init(IpRateLimiter::new)
+    }
+
+    /// 尝试为 `ip` 消耗一个令牌；被限流时返回建议的重试等待时间
+    pub fn check(&self, ip: &str, config: &IpRateLimitConfig) -> Result<(), Duration> {
+        self.check_at(ip, config, Instant::now())
+    }
+
+    fn check_at(&self, ip: &str, config: &IpRateLimitConfig, now: Instant) -> Result<(), Duration> {
+        self.maybe_prune(config, now);
+
+        let capacity = config.burst.max(1) as f64;
+        let per_sec = config.rpm.max(1) as f64 / 60.0;
+
+        let mut bucket = self.buckets.entry(ip.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            last_refill: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_sec).min(capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_sec))
+        }
+    }
+
+    /// 移除已经回满的桶 (与新建桶等价)，限制内存占用
+    fn maybe_prune(&self, config: &IpRateLimitConfig, now: Instant) {
+        {
+            let Ok(mut last) = self.last_prune.try_lock() else {
+                return;
+            };
+            if now.saturating_duration_since(*last) < PRUNE_INTERVAL {
+                return;
+            }
+            *last = now;
+        }
+
+        let refill_window = Duration::from_secs_f64(
+            config.burst.max(1) as f64 * 60.0 / config.rpm.max(1) as f64,
+        )
+        .max(PRUNE_INTERVAL);
+        let before = self.buckets.len();
+        self.buckets
+            .retain(|_, b| now.saturating_duration_since(b.last_refill) < refill_window);
+        let pruned = before.saturating_sub(self.buckets.len());
+        if pruned > 0 {
+            tracing::debug!("[IP RateLimit] Pruned {} idle buckets", pruned);
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.buckets.len()
+    }
+}
+
+impl Default for IpRateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 429 响应，`Retry-After` 向上取整到秒
+pub fn rate_limited_response(ip: &str, retry_after: Duration) -> Response {
+    let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    let secs = secs.max(1);
+    let body = serde_json::json!({
+        "error": {
+            "message": format!("Too many requests from this IP. Please retry after {} second(s).", secs),
+            "type": "rate_limit_exceeded",
+            "code": "ip_rate_limited",
+            "ip": ip,
+            "retry_after": secs,
+        }
+    });
+
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::RETRY_AFTER, secs.to_string()),
+        ],
+        body.to_string(),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(rpm: u32, burst: u32) -> IpRateLimitConfig {
+        IpRateLimitConfig {
+            enabled: true,
+            rpm,
+            burst,
+        }
+    }
+
+    #[test]
+    fn test_burst_then_refill() {
+        let limiter = IpRateLimiter::new();
+        let cfg = config(60, 3);
+        let t0 = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at("1.2.3.4", &cfg, t0).is_ok());
+        }
+        let wait = limiter.check_at("1.2.3.4", &cfg, t0).unwrap_err();
+        assert!(wait <= Duration::from_secs(1) && wait > Duration::ZERO);
+
+        // 其他 IP 不受影响
+        assert!(limiter.check_at("5.6.7.8", &cfg, t0).is_ok());
+
+        // 60 rpm = 每秒回填 1 个令牌
+        assert!(limiter.check_at("1.2.3.4", &cfg, t0 + Duration::from_secs(1)).is_ok());
+        assert!(limiter.check_at("1.2.3.4", &cfg, t0 + Duration::from_secs(1)).is_err());
+    }
+
+    #[test]
+    fn test_idle_buckets_pruned() {
+        let limiter = IpRateLimiter::new();
+        let cfg = config(600, 5);
+        let t0 = Instant::now();
+
+        limiter.check_at("10.0.0.1", &cfg, t0).unwrap();
+        limiter.check_at("10.0.0.2", &cfg, t0).unwrap();
+        assert_eq!(limiter.len(), 2);
+
+        limiter
+            .check_at("10.0.0.3", &cfg, t0 + PRUNE_INTERVAL * 2)
+            .unwrap();
+        assert_eq!(limiter.len(), 1);
+    }
+
+    #[test]
+    fn test_response_has_retry_after() {
+        let resp = rate_limited_response("1.2.3.4", Duration::from_millis(1500));
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers()[header::RETRY_AFTER], "2");
+    }
+}
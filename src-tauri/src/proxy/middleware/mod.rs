@@ -1,15 +1,28 @@
 // Middleware 模块 - Axum 中间件
 
+pub mod access_log;
+pub mod account_concurrency;
 pub mod auth;
+pub mod budget;
 pub mod cors;
+pub mod error_templates;
 pub mod logging;
 pub mod monitor;
 pub mod ip_filter;
+pub mod ip_rate_limit;
+pub mod response_headers;
+pub mod transforms;
 
 pub mod service_status;
 
-pub use cors::cors_layer;
+pub use access_log::access_log_middleware;
+pub use account_concurrency::account_concurrency_middleware;
+pub use budget::budget_middleware;
+pub use cors::cors_middleware;
+pub use error_templates::error_template_middleware;
 pub use monitor::monitor_middleware;
 pub use service_status::service_status_middleware;
-pub use auth::{auth_middleware, admin_auth_middleware};
+pub use auth::{auth_middleware, admin_auth_middleware, admin_role_middleware, AdminActor, AdminSession};
 pub use ip_filter::ip_filter_middleware;
+pub use response_headers::response_header_injection_middleware;
+pub use transforms::transform_middleware;
@@ -6,14 +6,32 @@ use axum::{
 };
 use std::time::Instant;
 use crate::proxy::server::AppState;
-use crate::proxy::monitor::ProxyRequestLog;
+use crate::proxy::monitor::{ProxyRequestLog, CANCELLED_STATUS};
 use serde_json::Value;
-use crate::proxy::middleware::auth::UserTokenIdentity;
+use crate::proxy::middleware::auth::{ApiKeyLabel, UserTokenIdentity};
 use futures::StreamExt;
+use tracing::Instrument;
 
 const MAX_REQUEST_LOG_SIZE: usize = 100 * 1024 * 1024; // 100MB
 const MAX_RESPONSE_LOG_SIZE: usize = 100 * 1024 * 1024; // 100MB for image responses
 
+/// [NEW] SSE 保活数据块: 只包含注释行 (`: ping`)、空行或 Claude `ping` 事件
+/// TTFB 计时在首个非保活数据块发给客户端时停止
+fn is_keepalive_chunk(chunk: &[u8]) -> bool {
+    let Ok(text) = std::str::from_utf8(chunk) else {
+        return false;
+    };
+    text.lines().map(str::trim).all(|line| {
+        line.is_empty()
+            || line.starts_with(':')
+            || line == "event: ping"
+            || line
+                .strip_prefix("data:")
+                .and_then(|data| serde_json::from_str::<Value>(data.trim()).ok())
+                .is_some_and(|json| json.get("type").and_then(|t| t.as_str()) == Some("ping"))
+    })
+}
+
 /// Helper function to record User Token usage
 fn record_user_token_usage(
     user_token_identity: &Option<UserTokenIdentity>,
@@ -33,6 +51,31 @@ fn record_user_token_usage(
     }
 }
 
+/// 提取缓存命中的输入 Token (Claude / OpenAI / Gemini 三种 usage 格式)
+fn extract_cached_tokens(usage: &Value) -> Option<u32> {
+    usage
+        .get("cache_read_input_tokens")
+        .or(usage.get("prompt_tokens_details").and_then(|d| d.get("cached_tokens")))
+        .or(usage.get("cachedContentTokenCount"))
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32)
+}
+
+/// 请求被管理员取消时返回给客户端的响应
+fn cancelled_response() -> Response {
+    use axum::response::IntoResponse;
+    let status = axum::http::StatusCode::from_u16(CANCELLED_STATUS)
+        .unwrap_or(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+    let body = serde_json::json!({
+        "error": {
+            "message": "Request cancelled by administrator",
+            "type": "request_cancelled",
+            "code": "request_cancelled"
+        }
+    });
+    (status, axum::Json(body)).into_response()
+}
+
 pub async fn monitor_middleware(
     State(state): State<AppState>,
     request: Request,
@@ -49,21 +92,12 @@ pub async fn monitor_middleware(
     
     let start = Instant::now();
     
-    // Extract client IP from headers (X-Forwarded-For or X-Real-IP)
+    // Extract client IP (honours trusted_proxies for X-Forwarded-For / CF-Connecting-IP)
     // IMPORTANT: Extract from Request headers, not Response headers (since we want the client's IP)
-    // Note: We need to do this BEFORE consuming the request body if possible, or extract it from the original request
-    let client_ip = request
-        .headers()
-        .get("x-forwarded-for")
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
-        .or_else(|| {
-            request
-                .headers()
-                .get("x-real-ip")
-                .and_then(|v| v.to_str().ok())
-                .map(|s| s.to_string())
-        });
+    let client_ip = crate::proxy::middleware::ip_filter::extract_client_ip(
+        &request,
+        &*state.security.read().await,
+    );
         
     let user_agent = request
         .headers()
@@ -85,6 +119,7 @@ pub async fn monitor_middleware(
     // [FIX] 从请求 extensions 提取 UserTokenIdentity (由 Auth 中间件注入)
     // 必须在处理 request body 之前提取，因为 into_parts() 后需要保留这个值
     let user_token_identity = request.extensions().get::<UserTokenIdentity>().cloned();
+    let api_key_label = request.extensions().get::<ApiKeyLabel>().map(|l| l.0.clone());
     
     let request = if method == "POST" {
         let (parts, body) = request.into_parts();
@@ -112,7 +147,27 @@ pub async fn monitor_middleware(
         request
     };
     
-    let response = next.run(request).await;
+    // [NEW] 登记为进行中请求；被取消时直接丢弃处理器 future，从而中止上游请求
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let active = state.monitor.active.register(
+        request_id.clone(),
+        method.clone(),
+        uri.clone(),
+        model.clone(),
+        client_ip.clone(),
+    );
+    let cancel = active.cancel_token();
+
+    // [NEW] 记录处理过程中解析出的生效超时 (写入日志便于排查)
+    // [NEW] 处理过程中的日志都挂在该 span 下 (JSON 日志中输出为 span.request_id)
+    let span = tracing::info_span!("request", request_id = %request_id);
+    let (mut response, timeout_secs) = tokio::select! {
+        result = crate::proxy::timeouts::track_effective_timeout(next.run(request)).instrument(span.clone()) => result,
+        _ = cancel.cancelled() => {
+            tracing::warn!("[Monitor] Request {} {} cancelled by administrator", method, uri);
+            (cancelled_response(), None)
+        }
+    };
     
     // user_token_identity 已在上面从请求 extensions 中提取
     
@@ -131,6 +186,11 @@ pub async fn monitor_middleware(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
+    active.set_response_info(
+        account_email.clone(),
+        content_type.contains("text/event-stream"),
+    );
+
     // Extract mapped model from X-Mapped-Model header if present
     let mapped_model = response
         .headers()
@@ -138,6 +198,12 @@ pub async fn monitor_middleware(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
+    // [NEW] 上游错误分类 (由 handler 写入 X-Error-Class)，仅供内部记录，读取后从响应中移除
+    let error_class = response
+        .headers_mut()
+        .remove(crate::proxy::mappers::error_classifier::ERROR_CLASS_HEADER)
+        .and_then(|v| v.to_str().ok().map(|s| s.to_string()));
+
     // Determine protocol from URL path
     let protocol = if uri.contains("/v1/messages") {
         Some("anthropic".to_string())
@@ -151,12 +217,15 @@ pub async fn monitor_middleware(
 
     // Client IP has been extracted at the beginning of the function
 
-    // Extract username from UserTokenIdentity if present
-    let username = user_token_identity.as_ref().map(|identity| identity.username.clone());
+    // Extract username from UserTokenIdentity if present, otherwise the matched API key label
+    let username = user_token_identity
+        .as_ref()
+        .map(|identity| identity.username.clone())
+        .or(api_key_label);
 
     let monitor = state.monitor.clone();
     let mut log = ProxyRequestLog {
-        id: uuid::Uuid::new_v4().to_string(),
+        id: request_id,
         timestamp: chrono::Utc::now().timestamp_millis(),
         method,
         url: uri,
@@ -171,8 +240,12 @@ pub async fn monitor_middleware(
         response_body: None,
         input_tokens: None,
         output_tokens: None,
+        cached_tokens: None,
+        estimated_cost_usd: None,
         protocol,
         username,
+        timeout_secs,
+        error_class,
     };
 
 
@@ -184,9 +257,27 @@ pub async fn monitor_middleware(
         tokio::spawn(async move {
             let mut all_stream_data = Vec::new();
             let mut last_few_bytes = Vec::new();
+            let mut cancelled = false;
+            let mut stream_error: Option<String> = None;
+            let mut ttfb_ms: Option<u64> = None;
             
-            while let Some(chunk_res) = stream.next().await {
+            loop {
+                let chunk_res = tokio::select! {
+                    biased;
+                    _ = cancel.cancelled() => {
+                        cancelled = true;
+                        break;
+                    }
+                    next = stream.next() => match next {
+                        Some(chunk_res) => chunk_res,
+                        None => break,
+                    },
+                };
                 if let Ok(chunk) = chunk_res {
+                    active.add_streamed_bytes(chunk.len());
+                    if ttfb_ms.is_none() && !is_keepalive_chunk(&chunk) {
+                        ttfb_ms = Some(start.elapsed().as_millis() as u64);
+                    }
                     all_stream_data.extend_from_slice(&chunk);
                     
                     if chunk.len() > 8192 {
@@ -199,9 +290,28 @@ pub async fn monitor_middleware(
                     }
                     let _ = tx.send(Ok::<_, axum::Error>(chunk)).await;
                 } else if let Err(e) = chunk_res {
+                    stream_error.get_or_insert_with(|| e.to_string());
                     let _ = tx.send(Err(axum::Error::new(e))).await;
                 }
             }
+
+            // [NEW] 流式耗时: 只统计正常结束的成功流，取消/错误流会拉偏分位数
+            if !cancelled && stream_error.is_none() && status < 400 {
+                monitor
+                    .streaming
+                    .record(ttfb_ms, start.elapsed().as_millis() as u64);
+            }
+
+            if cancelled {
+                // 立即释放上游连接，并以错误结束下游流
+                drop(stream);
+                tracing::warn!("[Monitor] Stream {} cancelled by administrator", log.url);
+                let _ = tx
+                    .send(Err(axum::Error::new(std::io::Error::other(
+                        "request cancelled by administrator",
+                    ))))
+                    .await;
+            }
             
             // Parse and consolidate stream data into readable format
             if let Ok(full_response) = std::str::from_utf8(&all_stream_data) {
@@ -340,6 +450,7 @@ pub async fn monitor_middleware(
                             .or(json.get("usageMetadata"))
                             .or(json.get("response").and_then(|r| r.get("usage")))
                         {
+                            log.cached_tokens = extract_cached_tokens(usage);
                             log.input_tokens = usage.get("prompt_tokens")
                                 .or(usage.get("input_tokens"))
                                 .or(usage.get("promptTokenCount"))
@@ -408,6 +519,7 @@ pub async fn monitor_middleware(
                                     .or(json.get("usageMetadata"))
                                     .or(json.get("response").and_then(|r| r.get("usage")))
                                 {
+                                    log.cached_tokens = extract_cached_tokens(usage);
                                     log.input_tokens = usage.get("prompt_tokens")
                                         .or(usage.get("input_tokens"))
                                         .or(usage.get("promptTokenCount"))
@@ -429,12 +541,25 @@ pub async fn monitor_middleware(
             if log.status >= 400 {
                 log.error = Some("Stream Error or Failed".to_string());
             }
+            // [NEW] 流中途出错 (如背压中断): 响应头已发出，状态码保持不变，在错误与错误分类中记录
+            if let Some(err) = stream_error {
+                if err.starts_with(crate::proxy::handlers::common::BACKPRESSURE_ABORT_MESSAGE) {
+                    log.error_class = Some(
+                        crate::proxy::handlers::common::BACKPRESSURE_ABORT_CLASS.to_string(),
+                    );
+                }
+                log.error = Some(err);
+            }
+            if cancelled {
+                log.status = CANCELLED_STATUS;
+                log.error = Some("Cancelled by administrator".to_string());
+            }
 
             // Record User Token Usage
             record_user_token_usage(&user_token_identity, &log, user_agent.clone());
 
             monitor.log_request(log).await;
-        });
+        }.instrument(span));
 
         Response::from_parts(parts, Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx)))
     } else if content_type.contains("application/json") || content_type.contains("text/") {
@@ -445,6 +570,7 @@ pub async fn monitor_middleware(
                     if let Ok(json) = serde_json::from_str::<Value>(&s) {
                         // 支持 OpenAI "usage" 或 Gemini "usageMetadata"
                         if let Some(usage) = json.get("usage").or(json.get("usageMetadata")) {
+                            log.cached_tokens = extract_cached_tokens(usage);
                             log.input_tokens = usage.get("prompt_tokens")
                                 .or(usage.get("input_tokens"))
                                 .or(usage.get("promptTokenCount"))
@@ -499,3 +625,36 @@ pub async fn monitor_middleware(
         response
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_extract_cached_tokens() {
+        assert_eq!(
+            extract_cached_tokens(&json!({"input_tokens": 10, "cache_read_input_tokens": 90})),
+            Some(90)
+        );
+        assert_eq!(
+            extract_cached_tokens(&json!({"prompt_tokens": 100, "prompt_tokens_details": {"cached_tokens": 64}})),
+            Some(64)
+        );
+        assert_eq!(
+            extract_cached_tokens(&json!({"promptTokenCount": 100, "cachedContentTokenCount": 32})),
+            Some(32)
+        );
+        assert_eq!(extract_cached_tokens(&json!({"input_tokens": 10})), None);
+    }
+
+    #[test]
+    fn test_is_keepalive_chunk() {
+        assert!(is_keepalive_chunk(b": ping\n\n"));
+        assert!(is_keepalive_chunk(b"event: ping\ndata: {\"type\": \"ping\"}\n\n"));
+        assert!(!is_keepalive_chunk(b"event: message_start\ndata: {\"type\": \"message_start\"}\n\n"));
+        assert!(!is_keepalive_chunk(b"data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}]}\n\n"));
+        // 保活与内容同一数据块时视为已出首字节
+        assert!(!is_keepalive_chunk(b": ping\n\ndata: {\"candidates\":[]}\n\n"));
+    }
+}
@@ -0,0 +1,182 @@
+// 响应头注入中间件 - 为反代响应追加用户配置的自定义响应头
+// 仅挂载在 AI 代理路由上，管理 API (/api/*) 不受影响
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::proxy::TokenManager;
+
+/// 模板变量上下文
+#[derive(Debug, Default, Clone)]
+pub struct HeaderTemplateContext {
+    pub account_id: Option<String>,
+    pub model: Option<String>,
+    pub request_id: String,
+}
+
+/// 展开模板变量 `{{account_id}}` / `{{model}}` / `{{request_id}}`，未知值替换为空串
+pub fn expand_header_template(template: &str, ctx: &HeaderTemplateContext) -> String {
+    template
+        .replace("{{account_id}}", ctx.account_id.as_deref().unwrap_or(""))
+        .replace("{{model}}", ctx.model.as_deref().unwrap_or(""))
+        .replace("{{request_id}}", &ctx.request_id)
+}
+
+/// 将配置的响应头写入 `headers` (同名头会被覆盖)，非法的头名/值会被跳过
+pub fn inject_headers(
+    headers: &mut HeaderMap,
+    templates: &HashMap<String, String>,
+    ctx: &HeaderTemplateContext,
+) {
+    for (name, template) in templates {
+        let Ok(name) = HeaderName::from_bytes(name.trim().as_bytes()) else {
+            tracing::debug!("[Response-Headers] Skipping invalid header name: {}", name);
+            continue;
+        };
+        let value = expand_header_template(template, ctx);
+        match HeaderValue::from_str(&value) {
+            Ok(value) => {
+                headers.insert(name, value);
+            }
+            Err(_) => {
+                tracing::debug!("[Response-Headers] Skipping invalid value for {}", name);
+            }
+        }
+    }
+}
+
+pub async fn response_header_injection_middleware(
+    State(token_manager): State<Arc<TokenManager>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let templates = crate::proxy::get_response_headers();
+    if templates.is_empty() {
+        return next.run(request).await;
+    }
+
+    // 优先沿用调用方传入的 X-Request-Id，便于网关侧链路追踪
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let mut response = next.run(request).await;
+
+    let model = response
+        .headers()
+        .get("X-Mapped-Model")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let account_id = response
+        .headers()
+        .get("X-Account-Email")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|email| token_manager.get_account_id_by_email(email));
+
+    let ctx = HeaderTemplateContext {
+        account_id,
+        model,
+        request_id,
+    };
+    inject_headers(response.headers_mut(), &templates, &ctx);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, response::IntoResponse, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_expand_header_template() {
+        let ctx = HeaderTemplateContext {
+            account_id: Some("acc-1".to_string()),
+            model: Some("gemini-3-pro".to_string()),
+            request_id: "req-42".to_string(),
+        };
+        assert_eq!(
+            expand_header_template("{{account_id}}/{{model}}/{{request_id}}", &ctx),
+            "acc-1/gemini-3-pro/req-42"
+        );
+        assert_eq!(expand_header_template("static", &ctx), "static");
+
+        let empty = HeaderTemplateContext {
+            request_id: "r".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(expand_header_template("a={{account_id}}", &empty), "a=");
+    }
+
+    #[tokio::test]
+    async fn test_headers_injected_only_on_proxy_routes() {
+        let mut headers = HashMap::new();
+        headers.insert("X-Gateway".to_string(), "antigravity".to_string());
+        headers.insert("X-Upstream-Model".to_string(), "{{model}}".to_string());
+        headers.insert("X-Trace".to_string(), "{{request_id}}".to_string());
+        crate::proxy::update_response_headers(headers);
+
+        let token_manager = Arc::new(TokenManager::new(std::env::temp_dir()));
+        let proxy_routes = Router::new()
+            .route(
+                "/v1/ping",
+                get(|| async { ([("X-Mapped-Model", "gemini-3-flash")], "pong") }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                token_manager,
+                response_header_injection_middleware,
+            ))
+            // 模拟外层 auth: 拒绝未携带 key 的请求
+            .layer(axum::middleware::from_fn(|request: Request, next: Next| async move {
+                if request.headers().contains_key("x-api-key") {
+                    next.run(request).await
+                } else {
+                    axum::http::StatusCode::UNAUTHORIZED.into_response()
+                }
+            }));
+        let admin_routes = Router::new().route("/ping", get(|| async { "pong" }));
+        let app = Router::new().nest("/api", admin_routes).merge(proxy_routes);
+
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/ping")
+                    .header("x-api-key", "sk-test")
+                    .header("x-request-id", "req-1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()["X-Gateway"], "antigravity");
+        assert_eq!(resp.headers()["X-Upstream-Model"], "gemini-3-flash");
+        assert_eq!(resp.headers()["X-Trace"], "req-1");
+
+        // 鉴权拒绝的响应不注入自定义头
+        let resp = app
+            .clone()
+            .oneshot(Request::builder().uri("/v1/ping").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), axum::http::StatusCode::UNAUTHORIZED);
+        assert!(resp.headers().get("X-Gateway").is_none());
+
+        let resp = app
+            .oneshot(Request::builder().uri("/api/ping").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert!(resp.headers().get("X-Gateway").is_none());
+
+        crate::proxy::update_response_headers(HashMap::new());
+    }
+}
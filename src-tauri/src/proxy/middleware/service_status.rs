@@ -4,15 +4,54 @@ use axum::{
     response::{IntoResponse, Response},
     http::StatusCode,
 };
+use serde::Serialize;
+use crate::proxy::error::{ApiError, ApiErrorCode, ApiErrorFormat};
 use crate::proxy::server::AppState;
 
+/// 维护模式默认的 Retry-After (秒)
+pub const DEFAULT_MAINTENANCE_RETRY_AFTER_SECS: u64 = 300;
+
+/// [NEW] 维护模式: AI 代理路由返回 503，管理 API 保持可用 (内存状态，重启后失效)
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceState {
+    pub enabled: bool,
+    pub reason: Option<String>,
+    pub retry_after_secs: u64,
+    /// 进入维护模式的时间 (unix 秒)
+    pub since: Option<i64>,
+}
+
+impl Default for MaintenanceState {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            reason: None,
+            retry_after_secs: DEFAULT_MAINTENANCE_RETRY_AFTER_SECS,
+            since: None,
+        }
+    }
+}
+
+impl MaintenanceState {
+    /// 按路由协议返回 503 错误，携带 Retry-After
+    pub fn error(&self, path: &str) -> ApiError {
+        let message = match self.reason.as_deref().filter(|r| !r.trim().is_empty()) {
+            Some(reason) => format!("Proxy is under maintenance: {}", reason),
+            None => "Proxy is under maintenance".to_string(),
+        };
+        ApiError::new(ApiErrorCode::ServiceUnavailable, message)
+            .with_header("retry-after", &self.retry_after_secs.to_string())
+            .format(ApiErrorFormat::for_path(path))
+    }
+}
+
 pub async fn service_status_middleware(
     State(state): State<AppState>,
     request: Request,
     next: Next,
 ) -> Response {
     let path = request.uri().path();
-    
+
     // Always allow Admin API and Auth callback
     if path.starts_with("/api/") || path == "/auth/callback" || path == "/health" {
         return next.run(request).await;
@@ -31,5 +70,34 @@ pub async fn service_status_middleware(
             .into_response();
     }
 
+    // [NEW] 维护模式: 只拦截 AI 代理路由
+    let maintenance = state.maintenance_mode.read().await.clone();
+    if maintenance.enabled {
+        tracing::debug!("[Maintenance] Rejected {}", path);
+        return maintenance.error(path).into_response();
+    }
+
+    // [NEW] 配置热更新进行中时等待其完成，保证请求看到完整的新配置
+    // [FIX] 读锁在进入处理器前立即释放: 长请求 / SSE 流不会阻塞后续的热更新写锁
+    drop(state.config_apply.read().await);
+
     next.run(request).await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maintenance_error_has_retry_after() {
+        let state = MaintenanceState {
+            enabled: true,
+            reason: Some("upstream incident".to_string()),
+            retry_after_secs: 120,
+            since: Some(0),
+        };
+        let response = state.error("/v1/chat/completions").into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()["retry-after"], "120");
+    }
+}
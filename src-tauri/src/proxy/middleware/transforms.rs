@@ -0,0 +1,97 @@
+// JSON 改写中间件 - 应用 ProxyConfig.transforms 中的规则
+// 请求规则在 handler (协议转换) 之前执行；响应规则仅作用于非流式 JSON 响应
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderMap, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+
+use crate::proxy::error::ApiError;
+use crate::proxy::transforms::{apply_rules, get_transforms_config};
+
+const MAX_TRANSFORM_BODY_SIZE: usize = 100 * 1024 * 1024; // 100MB
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|ct| ct.to_ascii_lowercase().starts_with("application/json"))
+        .unwrap_or(false)
+}
+
+pub async fn transform_middleware(request: Request, next: Next) -> Response {
+    let config = get_transforms_config();
+    if config.request.is_empty() && config.response.is_empty() {
+        return next.run(request).await;
+    }
+
+    let route = request.uri().path().to_string();
+    // Gemini 原生路由的模型在路径中: /v1beta/models/{model}:generateContent
+    let mut model = route
+        .split("/v1beta/models/")
+        .nth(1)
+        .and_then(|s| s.split(':').next())
+        .map(|s| s.to_string());
+
+    let request = if request.method() == Method::POST && is_json(request.headers()) {
+        let (mut parts, body) = request.into_parts();
+        let bytes = match axum::body::to_bytes(body, MAX_TRANSFORM_BODY_SIZE).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                return ApiError::invalid_request(format!("Failed to read request body: {}", e))
+                    .into_response();
+            }
+        };
+        let mut bytes = bytes.to_vec();
+        // 非法 JSON 原样交给 handler，由其返回协议对应的错误
+        if let Ok(mut json) = serde_json::from_slice::<Value>(&bytes) {
+            if model.is_none() {
+                model = json.get("model").and_then(|m| m.as_str()).map(|s| s.to_string());
+            }
+            let applied = apply_rules(&config.request, &route, model.as_deref(), &mut json);
+            if applied > 0 {
+                tracing::debug!("[Transforms] Applied {} request action(s) on {}", applied, route);
+                if let Ok(rewritten) = serde_json::to_vec(&json) {
+                    bytes = rewritten;
+                    parts.headers.remove(header::CONTENT_LENGTH);
+                }
+            }
+        }
+        Request::from_parts(parts, Body::from(bytes))
+    } else {
+        request
+    };
+
+    let response = next.run(request).await;
+    if config.response.is_empty() || !is_json(response.headers()) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_TRANSFORM_BODY_SIZE).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("[Transforms] Failed to buffer response body on {}: {}", route, e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let Ok(mut json) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    let applied = apply_rules(&config.response, &route, model.as_deref(), &mut json);
+    if applied == 0 {
+        return Response::from_parts(parts, Body::from(bytes));
+    }
+    tracing::debug!("[Transforms] Applied {} response action(s) on {}", applied, route);
+    match serde_json::to_vec(&json) {
+        Ok(rewritten) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(rewritten))
+        }
+        Err(_) => Response::from_parts(parts, Body::from(bytes)),
+    }
+}
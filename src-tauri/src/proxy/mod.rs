@@ -1,6 +1,9 @@
 // proxy 模块 - API 反代服务
 
 // 现有模块 (保留)
+pub mod admin_jwt; // 管理接口短期 JWT
+pub mod api_key_rotation; // API Key 轮换与宽限期
+pub mod api_keys; // 附加 API Key (带标签，可单独禁用)
 pub mod config;
 pub mod project_resolver;
 pub mod security;
@@ -9,22 +12,34 @@ pub mod token_manager;
 
 // 新架构模块
 pub mod audio; // 音频处理模块
+pub mod budget; // 每日 Token 预算
 pub mod cli_sync; // CLI 配置同步 (v3.3.35)
 pub mod droid_sync; // Droid (Factory CLI) 配置同步
 pub mod common; // 公共工具
+pub mod concurrency; // 账号并发限制与优先级排队
+pub mod config_validation; // 配置预检 (dry-run)
+pub mod cost; // 按模型单价估算请求费用
 pub mod debug_logger;
+pub mod error; // 统一 API 错误类型与错误码
 pub mod handlers; // API 端点处理器
+pub mod listener; // 监听地址解析与绑定 (TCP / unix socket)
 pub mod mappers; // 协议转换器
+pub mod mcp_aggregator; // 聚合 MCP 端点 (z.ai 远程工具 + 本地工具)
 pub mod middleware; // Axum 中间件
+pub mod model_list_cache; // 模型列表缓存 (TTL + 后台刷新)
 pub mod monitor; // 监控
 pub mod opencode_sync; // OpenCode 配置同步
 pub mod providers; // Extra upstream providers (z.ai, etc.)
 pub mod proxy_pool; // 代理池管理器
 pub mod rate_limit; // 限流跟踪
+pub mod response_cache; // 幂等接口响应缓存 (countTokens)
 pub mod session_manager; // 会话指纹管理
 pub mod signature_cache; // Signature Cache (v3.3.16)
 pub mod sticky_config; // 粘性调度配置
+pub mod timeouts; // 按路由/模型的请求超时策略
+pub mod transforms; // 请求/响应 JSON 改写规则
 pub mod upstream; // 上游客户端
+pub mod webhook; // 账号事件 Webhook 通知
 pub mod zai_vision_mcp; // Built-in Vision MCP server state
 pub mod zai_vision_tools; // Built-in Vision MCP tools (z.ai vision API) // 调试日志
 
@@ -32,7 +47,17 @@ pub use config::get_global_system_prompt;
 pub use config::get_thinking_budget_config;
 pub use config::update_global_system_prompt_config;
 pub use config::update_thinking_budget_config;
+pub use config::get_claude_thinking_config;
+pub use config::update_claude_thinking_config;
+pub use config::apply_global_proxy_config;
 pub use config::{get_image_thinking_mode, update_image_thinking_mode};
+pub use config::{get_identity_injection_config, update_identity_injection_config};
+pub use config::{get_response_headers, update_response_headers};
+pub use config::{get_error_templates, update_error_templates};
+pub use config::{get_access_log_format, update_access_log_format};
+pub use config::{get_safety_settings_policy, update_safety_settings_policy};
+pub use config::{get_model_account_tags, update_model_account_tags};
+pub use config::{resolve_model_route, update_model_routes};
 pub use config::ProxyAuthMode;
 pub use config::ProxyConfig;
 pub use config::ProxyPoolConfig;
@@ -0,0 +1,187 @@
+// 模型列表缓存
+// `/v1/models`、`/v1beta/models` 的响应在 TTL 内直接复用；过期后先返回旧数据并在后台刷新，
+// 配置保存或手动刷新时整体失效
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+/// 默认缓存时间 (秒)
+pub const DEFAULT_MODEL_LIST_CACHE_TTL_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModelListKind {
+    /// OpenAI 与 Claude 共用的 `{"object": "list", "data": [...]}` 格式
+    OpenAi,
+    /// Gemini `{"models": [...]}` 格式
+    Gemini,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CacheLookup {
+    Fresh(Value),
+    /// 已过期，调用方应返回该数据并触发后台刷新
+    Stale(Value),
+    Miss,
+}
+
+pub struct ModelListCache {
+    ttl_secs: AtomicU64,
+    /// 每次失效时递增，用于丢弃失效前开始构建的结果
+    generation: AtomicU64,
+    entries: RwLock<HashMap<ModelListKind, (Value, Instant)>>,
+    refreshing: Mutex<HashSet<ModelListKind>>,
+    /// 命中 (含过期后返回旧数据) / 未命中次数
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ModelListCache {
+    pub fn new(ttl_secs: u64) -> Self {
+        Self {
+            ttl_secs: AtomicU64::new(ttl_secs),
+            generation: AtomicU64::new(0),
+            entries: RwLock::new(HashMap::new()),
+            refreshing: Mutex::new(HashSet::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn global() -> Arc<ModelListCache> {
+        static INSTANCE: OnceLock<Arc<ModelListCache>> = OnceLock::new();
+        INSTANCE
+            .get_or_init(|| Arc::new(ModelListCache::new(DEFAULT_MODEL_LIST_CACHE_TTL_SECS)))
+            .clone()
+    }
+
+    /// 更新 TTL (0 表示禁用缓存)，并使现有缓存失效
+    pub fn configure(&self, ttl_secs: u64) {
+        self.ttl_secs.store(ttl_secs, Ordering::Relaxed);
+        self.invalidate();
+    }
+
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if let Ok(mut entries) = self.entries.write() {
+            entries.clear();
+        }
+        tracing::debug!("[Model-List] Cache invalidated");
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    pub fn lookup(&self, kind: ModelListKind) -> CacheLookup {
+        let result = self.lookup_at(kind, Instant::now());
+        let counter = if matches!(result, CacheLookup::Miss) {
+            &self.misses
+        } else {
+            &self.hits
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        result
+    }
+
+    pub fn stats(&self) -> crate::proxy::response_cache::CacheStats {
+        crate::proxy::response_cache::CacheStats::new(
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+
+    fn lookup_at(&self, kind: ModelListKind, now: Instant) -> CacheLookup {
+        let ttl = self.ttl_secs.load(Ordering::Relaxed);
+        if ttl == 0 {
+            return CacheLookup::Miss;
+        }
+        let Ok(entries) = self.entries.read() else {
+            return CacheLookup::Miss;
+        };
+        match entries.get(&kind) {
+            Some((value, at)) if now.saturating_duration_since(*at) < Duration::from_secs(ttl) => {
+                CacheLookup::Fresh(value.clone())
+            }
+            Some((value, _)) => CacheLookup::Stale(value.clone()),
+            None => CacheLookup::Miss,
+        }
+    }
+
+    /// 写入缓存；`generation` 为开始构建时的值，期间发生过失效则丢弃
+    pub fn store(&self, kind: ModelListKind, value: Value, generation: u64) {
+        if self.ttl_secs.load(Ordering::Relaxed) == 0 || self.generation() != generation {
+            return;
+        }
+        if let Ok(mut entries) = self.entries.write() {
+            entries.insert(kind, (value, Instant::now()));
+        }
+    }
+
+    /// 标记后台刷新开始；已有刷新在进行时返回 false
+    pub fn begin_refresh(&self, kind: ModelListKind) -> bool {
+        self.refreshing
+            .lock()
+            .map(|mut set| set.insert(kind))
+            .unwrap_or(false)
+    }
+
+    pub fn end_refresh(&self, kind: ModelListKind) {
+        if let Ok(mut set) = self.refreshing.lock() {
+            set.remove(&kind);
+        }
+    }
+}
+
+impl Default for ModelListCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MODEL_LIST_CACHE_TTL_SECS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fresh_then_stale() {
+        let cache = ModelListCache::new(300);
+        let kind = ModelListKind::Gemini;
+        assert_eq!(cache.lookup(kind), CacheLookup::Miss);
+
+        cache.store(kind, json!({"models": []}), cache.generation());
+        let now = Instant::now();
+        assert!(matches!(cache.lookup_at(kind, now), CacheLookup::Fresh(_)));
+        assert!(matches!(
+            cache.lookup_at(kind, now + Duration::from_secs(301)),
+            CacheLookup::Stale(_)
+        ));
+        assert_eq!(cache.lookup(ModelListKind::OpenAi), CacheLookup::Miss);
+
+        // 同一时间只允许一个后台刷新
+        assert!(cache.begin_refresh(kind));
+        assert!(!cache.begin_refresh(kind));
+        cache.end_refresh(kind);
+        assert!(cache.begin_refresh(kind));
+    }
+
+    #[test]
+    fn test_invalidate_discards_inflight_result() {
+        let cache = ModelListCache::new(300);
+        let kind = ModelListKind::OpenAi;
+        let generation = cache.generation();
+        cache.invalidate();
+        cache.store(kind, json!({"data": []}), generation);
+        assert_eq!(cache.lookup(kind), CacheLookup::Miss);
+    }
+
+    #[test]
+    fn test_zero_ttl_disables_cache() {
+        let cache = ModelListCache::new(300);
+        cache.configure(0);
+        cache.store(ModelListKind::OpenAi, json!({}), cache.generation());
+        assert_eq!(cache.lookup(ModelListKind::OpenAi), CacheLookup::Miss);
+    }
+}
@@ -2,7 +2,13 @@ use serde::{Serialize, Deserialize};
 use std::collections::VecDeque;
 use tokio::sync::RwLock;
 use tauri::Emitter;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use dashmap::DashMap;
+use tokio_util::sync::CancellationToken;
+
+/// 被管理员取消的请求在日志中使用的状态码 (与上游失败区分)
+pub const CANCELLED_STATUS: u16 = 499;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProxyRequestLog {
@@ -21,8 +27,16 @@ pub struct ProxyRequestLog {
     pub response_body: Option<String>,
     pub input_tokens: Option<u32>,
     pub output_tokens: Option<u32>,
+    #[serde(default)]
+    pub cached_tokens: Option<u32>,   // 上游缓存命中的输入 Token
+    #[serde(default)]
+    pub estimated_cost_usd: Option<f64>, // 按模型单价估算的费用 (美元)
     pub protocol: Option<String>,     // 协议类型: "openai", "anthropic", "gemini"
     pub username: Option<String>,     // User token username
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,    // 本次请求生效的超时 (秒)
+    #[serde(default)]
+    pub error_class: Option<String>,  // 上游错误分类 (如 quota_exhausted / invalid_argument)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -30,6 +44,375 @@ pub struct ProxyStats {
     pub total_requests: u64,
     pub success_count: u64,
     pub error_count: u64,
+    /// Anthropic 请求被分发到 z.ai 的次数 (进程启动以来)
+    #[serde(default)]
+    pub zai_requests: u64,
+    /// Anthropic 请求走 Google 账号池的次数 (进程启动以来)
+    #[serde(default)]
+    pub google_requests: u64,
+    /// z.ai 上游返回错误或请求失败的次数
+    #[serde(default)]
+    pub zai_errors: u64,
+    /// Fallback 模式下因 Google 账号池不可用而切换到 z.ai 的次数
+    #[serde(default)]
+    pub fallback_triggered: u64,
+    /// z.ai 错误率 (zai_errors / zai_requests)，无请求时为 0
+    #[serde(default)]
+    pub zai_error_rate: f64,
+    /// Gemini functionCall 参数不符合声明 schema 的次数
+    #[serde(default)]
+    pub function_schema_violations: u64,
+    /// [NEW] 上游请求中新建连接的次数 (进程启动以来)
+    #[serde(default)]
+    pub upstream_new_connections: u64,
+    /// [NEW] 上游请求复用已有连接的次数
+    #[serde(default)]
+    pub upstream_reused_connections: u64,
+    /// [NEW] 连接复用率 (reused / 上游请求数)，无请求时为 0
+    #[serde(default)]
+    pub connection_reuse_rate: f64,
+    /// [NEW] 流式请求首字节时间 (请求进入 → 首个非保活事件) 的 p50 / p95 (ms)，基于最近的样本窗口
+    #[serde(default)]
+    pub stream_ttfb_p50_ms: u64,
+    #[serde(default)]
+    pub stream_ttfb_p95_ms: u64,
+    /// [NEW] 流式请求总时长 (请求进入 → 流结束) 的 p50 / p95 (ms)
+    #[serde(default)]
+    pub stream_duration_p50_ms: u64,
+    #[serde(default)]
+    pub stream_duration_p95_ms: u64,
+    /// [NEW] 参与统计的流式请求样本数
+    #[serde(default)]
+    pub stream_samples: u64,
+    /// [NEW] 客户端读取过慢、流式缓冲区持续写满而被中断的连接数 (BACKPRESSURE_ABORT)
+    #[serde(default)]
+    pub backpressure_aborts: u64,
+}
+
+/// z.ai / Google 分发计数器
+/// 与请求日志无关，即使监控关闭也会计数，用于评估 ZaiDispatchMode 的实际效果
+#[derive(Debug, Default)]
+pub struct DispatchCounters {
+    zai_requests: AtomicU64,
+    google_requests: AtomicU64,
+    zai_errors: AtomicU64,
+    fallback_triggered: AtomicU64,
+    function_schema_violations: AtomicU64,
+    backpressure_aborts: AtomicU64,
+}
+
+impl DispatchCounters {
+    fn apply_to(&self, stats: &mut ProxyStats) {
+        stats.zai_requests = self.zai_requests.load(Ordering::Relaxed);
+        stats.google_requests = self.google_requests.load(Ordering::Relaxed);
+        stats.zai_errors = self.zai_errors.load(Ordering::Relaxed);
+        stats.fallback_triggered = self.fallback_triggered.load(Ordering::Relaxed);
+        stats.function_schema_violations = self.function_schema_violations.load(Ordering::Relaxed);
+        stats.backpressure_aborts = self.backpressure_aborts.load(Ordering::Relaxed);
+        stats.zai_error_rate = if stats.zai_requests > 0 {
+            stats.zai_errors as f64 / stats.zai_requests as f64
+        } else {
+            0.0
+        };
+    }
+
+    fn reset(&self) {
+        self.zai_requests.store(0, Ordering::Relaxed);
+        self.google_requests.store(0, Ordering::Relaxed);
+        self.zai_errors.store(0, Ordering::Relaxed);
+        self.fallback_triggered.store(0, Ordering::Relaxed);
+        self.function_schema_violations.store(0, Ordering::Relaxed);
+        self.backpressure_aborts.store(0, Ordering::Relaxed);
+    }
+}
+
+/// 上游连接复用计数器 (由 UpstreamClient 记录，与请求日志无关)
+/// 新建连接通过 DNS 解析次数近似: reqwest 只在建立新连接时解析主机名，
+/// 复用池中的连接 (含 HTTP/2 多路复用) 不会触发解析；socks 代理直连不计入
+#[derive(Debug, Default)]
+pub struct ConnectionCounters {
+    upstream_requests: AtomicU64,
+    new_connections: AtomicU64,
+}
+
+impl ConnectionCounters {
+    pub fn global() -> &'static ConnectionCounters {
+        static INSTANCE: std::sync::OnceLock<ConnectionCounters> = std::sync::OnceLock::new();
+        INSTANCE.get_or_init(ConnectionCounters::default)
+    }
+
+    pub fn record_request(&self) {
+        self.upstream_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_new_connection(&self) {
+        self.new_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn apply_to(&self, stats: &mut ProxyStats) {
+        let requests = self.upstream_requests.load(Ordering::Relaxed);
+        let new_connections = self.new_connections.load(Ordering::Relaxed);
+        stats.upstream_new_connections = new_connections;
+        stats.upstream_reused_connections = requests.saturating_sub(new_connections);
+        stats.connection_reuse_rate = if requests > 0 {
+            stats.upstream_reused_connections as f64 / requests as f64
+        } else {
+            0.0
+        };
+    }
+
+    fn reset(&self) {
+        self.upstream_requests.store(0, Ordering::Relaxed);
+        self.new_connections.store(0, Ordering::Relaxed);
+    }
+}
+
+/// 流式耗时样本窗口大小 (只保留最近的样本)
+const STREAM_TIMING_WINDOW: usize = 1000;
+
+/// 流式请求耗时样本 (TTFB / 总时长)
+/// 用于区分 "上游迟迟不开始输出" 与 "上游输出缓慢"；与请求日志无关，即使监控关闭也会记录
+#[derive(Debug, Default)]
+pub struct StreamTimings {
+    ttfb_ms: std::sync::Mutex<VecDeque<u64>>,
+    duration_ms: std::sync::Mutex<VecDeque<u64>>,
+}
+
+impl StreamTimings {
+    /// 记录一次完成的流；没有产出任何非保活事件时 ttfb 为 None
+    pub fn record(&self, ttfb_ms: Option<u64>, duration_ms: u64) {
+        if let Some(ttfb) = ttfb_ms {
+            push_sample(&self.ttfb_ms, ttfb);
+        }
+        push_sample(&self.duration_ms, duration_ms);
+    }
+
+    fn apply_to(&self, stats: &mut ProxyStats) {
+        let (ttfb_p50, ttfb_p95, _) = percentiles(&self.ttfb_ms);
+        let (duration_p50, duration_p95, samples) = percentiles(&self.duration_ms);
+        stats.stream_ttfb_p50_ms = ttfb_p50;
+        stats.stream_ttfb_p95_ms = ttfb_p95;
+        stats.stream_duration_p50_ms = duration_p50;
+        stats.stream_duration_p95_ms = duration_p95;
+        stats.stream_samples = samples as u64;
+    }
+
+    fn reset(&self) {
+        for samples in [&self.ttfb_ms, &self.duration_ms] {
+            if let Ok(mut samples) = samples.lock() {
+                samples.clear();
+            }
+        }
+    }
+}
+
+fn push_sample(samples: &std::sync::Mutex<VecDeque<u64>>, value: u64) {
+    if let Ok(mut samples) = samples.lock() {
+        if samples.len() >= STREAM_TIMING_WINDOW {
+            samples.pop_front();
+        }
+        samples.push_back(value);
+    }
+}
+
+/// 最近邻秩法计算 (p50, p95, 样本数)，无样本时为 0
+fn percentiles(samples: &std::sync::Mutex<VecDeque<u64>>) -> (u64, u64, usize) {
+    let mut sorted: Vec<u64> = match samples.lock() {
+        Ok(samples) => samples.iter().copied().collect(),
+        Err(_) => return (0, 0, 0),
+    };
+    if sorted.is_empty() {
+        return (0, 0, 0);
+    }
+    sorted.sort_unstable();
+    let rank = |p: f64| sorted[((p * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len()) - 1];
+    (rank(0.50), rank(0.95), sorted.len())
+}
+
+/// 正在处理中的请求 (`GET /api/proxy/active-requests`)
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveRequest {
+    pub id: String,
+    pub method: String,
+    pub url: String,
+    pub model: Option<String>,
+    pub account_email: Option<String>,
+    pub client_ip: Option<String>,
+    pub started_at: i64, // ms
+    pub elapsed_ms: u64,
+    pub streamed_bytes: u64,
+    pub streaming: bool,
+}
+
+struct ActiveEntry {
+    info: ActiveRequest,
+    started: std::time::Instant,
+    streamed_bytes: Arc<AtomicU64>,
+    cancel: CancellationToken,
+}
+
+/// 优雅停机排空结果
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub struct DrainOutcome {
+    /// 超时前自然完成的请求数
+    pub drained: usize,
+    /// 超时后被强制取消的请求数
+    pub cancelled: usize,
+}
+
+/// 进行中请求登记表，由 monitor 中间件注册，`ActiveRequestGuard` 释放时自动注销
+#[derive(Default)]
+pub struct ActiveRequestRegistry {
+    entries: Arc<DashMap<String, ActiveEntry>>,
+    /// [NEW] 在途请求计数 (注册 +1，Guard 释放 -1；流式请求在流结束后才释放)
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl ActiveRequestRegistry {
+    pub fn register(
+        &self,
+        id: String,
+        method: String,
+        url: String,
+        model: Option<String>,
+        client_ip: Option<String>,
+    ) -> ActiveRequestGuard {
+        let cancel = CancellationToken::new();
+        let streamed_bytes = Arc::new(AtomicU64::new(0));
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        self.entries.insert(
+            id.clone(),
+            ActiveEntry {
+                info: ActiveRequest {
+                    id: id.clone(),
+                    method,
+                    url,
+                    model,
+                    account_email: None,
+                    client_ip,
+                    started_at: chrono::Utc::now().timestamp_millis(),
+                    elapsed_ms: 0,
+                    streamed_bytes: 0,
+                    streaming: false,
+                },
+                started: std::time::Instant::now(),
+                streamed_bytes: streamed_bytes.clone(),
+                cancel: cancel.clone(),
+            },
+        );
+        ActiveRequestGuard {
+            id,
+            entries: self.entries.clone(),
+            in_flight: self.in_flight.clone(),
+            cancel,
+            streamed_bytes,
+        }
+    }
+
+    /// 按开始时间排序的进行中请求快照
+    pub fn list(&self) -> Vec<ActiveRequest> {
+        let mut list: Vec<ActiveRequest> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let mut info = entry.info.clone();
+                info.elapsed_ms = entry.started.elapsed().as_millis() as u64;
+                info.streamed_bytes = entry.streamed_bytes.load(Ordering::Relaxed);
+                info
+            })
+            .collect();
+        list.sort_by_key(|r| r.started_at);
+        list
+    }
+
+    /// 取消指定请求，返回是否找到该请求
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.entries.get(id) {
+            Some(entry) => {
+                entry.cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 在途请求数 (无锁读取，供停机排空轮询)
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// 取消全部进行中请求，返回取消数量
+    pub fn cancel_all(&self) -> usize {
+        let mut cancelled = 0;
+        for entry in self.entries.iter() {
+            if !entry.cancel.is_cancelled() {
+                entry.cancel.cancel();
+                cancelled += 1;
+            }
+        }
+        cancelled
+    }
+
+    /// 等待在途请求完成，超过 timeout 后取消剩余请求
+    /// 调用前应先停止接收新请求 (is_running = false)
+    pub async fn drain(&self, timeout: std::time::Duration) -> DrainOutcome {
+        let initial = self.in_flight();
+        let deadline = tokio::time::Instant::now() + timeout;
+        while self.in_flight() > 0 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        let cancelled = if self.in_flight() > 0 { self.cancel_all() } else { 0 };
+        DrainOutcome {
+            drained: initial.saturating_sub(cancelled),
+            cancelled,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// 单个进行中请求的句柄 (Drop 时从登记表移除)
+pub struct ActiveRequestGuard {
+    id: String,
+    entries: Arc<DashMap<String, ActiveEntry>>,
+    in_flight: Arc<AtomicUsize>,
+    cancel: CancellationToken,
+    streamed_bytes: Arc<AtomicU64>,
+}
+
+impl ActiveRequestGuard {
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// 响应头到达后补充账号信息并标记流式状态
+    pub fn set_response_info(&self, account_email: Option<String>, streaming: bool) {
+        if let Some(mut entry) = self.entries.get_mut(&self.id) {
+            entry.info.account_email = account_email;
+            entry.info.streaming = streaming;
+        }
+    }
+
+    pub fn add_streamed_bytes(&self, bytes: usize) {
+        self.streamed_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+}
+
+impl Drop for ActiveRequestGuard {
+    fn drop(&mut self) {
+        self.entries.remove(&self.id);
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 pub struct ProxyMonitor {
@@ -37,6 +420,9 @@ pub struct ProxyMonitor {
     pub stats: RwLock<ProxyStats>,
     pub max_logs: usize,
     pub enabled: AtomicBool,
+    pub dispatch: DispatchCounters,
+    pub active: ActiveRequestRegistry,
+    pub streaming: StreamTimings,
     app_handle: Option<tauri::AppHandle>,
 }
 
@@ -66,6 +452,9 @@ impl ProxyMonitor {
             stats: RwLock::new(ProxyStats::default()),
             max_logs,
             enabled: AtomicBool::new(false), // Default to disabled
+            dispatch: DispatchCounters::default(),
+            active: ActiveRequestRegistry::default(),
+            streaming: StreamTimings::default(),
             app_handle,
         }
     }
@@ -78,21 +467,104 @@ impl ProxyMonitor {
         self.enabled.load(Ordering::Relaxed)
     }
 
-    pub async fn log_request(&self, log: ProxyRequestLog) {
+    /// 记录一次 Anthropic 请求的分发目标
+    pub fn record_dispatch(&self, to_zai: bool) {
+        if to_zai {
+            self.dispatch.zai_requests.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.dispatch.google_requests.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// 记录一次 Fallback 模式下的兜底切换
+    pub fn record_fallback(&self) {
+        self.dispatch.fallback_triggered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录一次 z.ai 上游错误
+    pub fn record_zai_error(&self) {
+        self.dispatch.zai_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录一次 functionCall 参数校验失败
+    pub fn record_function_schema_violation(&self) {
+        self.dispatch
+            .function_schema_violations
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录一次 BACKPRESSURE_ABORT 事件: 客户端读取过慢导致流式连接被中断
+    pub fn record_backpressure_abort(&self, account_email: &str, model: &str) {
+        self.dispatch
+            .backpressure_aborts
+            .fetch_add(1, Ordering::Relaxed);
+        tracing::warn!(
+            "[Monitor] BACKPRESSURE_ABORT: client too slow, stream aborted (account: {}, model: {})",
+            account_email,
+            model
+        );
+    }
+
+    pub async fn log_request(&self, mut log: ProxyRequestLog) {
+        // [NEW] 按实际路由的模型估算费用
+        if log.estimated_cost_usd.is_none() {
+            if let (Some(model), Some(input), Some(output)) = (
+                log.mapped_model.as_deref().or(log.model.as_deref()),
+                log.input_tokens,
+                log.output_tokens,
+            ) {
+                log.estimated_cost_usd =
+                    crate::proxy::cost::CostEstimator::global().estimate(model, input, output);
+            }
+        }
+
         if let (Some(account), Some(input), Some(output)) = (
             &log.account_email,
             log.input_tokens,
             log.output_tokens,
         ) {
             let model = log.model.clone().unwrap_or_else(|| "unknown".to_string());
+            let mapped_model = log.mapped_model.clone();
             let account = account.clone();
+            let cached = log.cached_tokens.unwrap_or(0);
+            let cost = log.estimated_cost_usd.unwrap_or(0.0);
+            // [NEW] 每日预算增量累加 (与 token_stats 入库同源)
+            crate::proxy::budget::BudgetTracker::global()
+                .record(&account, input as u64 + output as u64);
             tokio::spawn(async move {
-                if let Err(e) = crate::modules::token_stats::record_usage(&account, &model, input, output) {
+                if let Err(e) = crate::modules::token_stats::record_usage(
+                    &account,
+                    &model,
+                    mapped_model.as_deref(),
+                    input,
+                    output,
+                    cached,
+                    cost,
+                ) {
                     tracing::debug!("Failed to record token stats: {}", e);
                 }
             });
         }
 
+        // [NEW] 结构化访问日志 (不依赖 SQLite 日志库，配合 ABV_LOG_FORMAT=json 采集)
+        if crate::proxy::get_access_log_format() == crate::proxy::config::AccessLogFormat::Json {
+            tracing::info!(
+                target: "access_log",
+                request_id = %log.id,
+                method = %log.method,
+                path = %log.url,
+                status = log.status,
+                account = log.account_email.as_deref().unwrap_or(""),
+                model = log.model.as_deref().unwrap_or(""),
+                mapped_model = log.mapped_model.as_deref().unwrap_or(""),
+                latency_ms = log.duration,
+                input_tokens = log.input_tokens.unwrap_or(0),
+                output_tokens = log.output_tokens.unwrap_or(0),
+                cached_tokens = log.cached_tokens.unwrap_or(0),
+                "access"
+            );
+        }
+
         if !self.is_enabled() {
             return;
         }
@@ -153,7 +625,15 @@ impl ProxyMonitor {
                 log_to_save.output_tokens,
             ) {
                 let model = log_to_save.model.clone().unwrap_or_else(|| "unknown".to_string());
-                if let Err(e) = crate::modules::token_stats::record_usage(account, &model, input, output) {
+                if let Err(e) = crate::modules::token_stats::record_usage(
+                    account,
+                    &model,
+                    log_to_save.mapped_model.as_deref(),
+                    input,
+                    output,
+                    log_to_save.cached_tokens.unwrap_or(0),
+                    log_to_save.estimated_cost_usd.unwrap_or(0.0),
+                ) {
                     tracing::debug!("Failed to record token stats: {}", e);
                 }
             }
@@ -177,8 +657,12 @@ impl ProxyMonitor {
                 response_body: None, // Don't send body in event
                 input_tokens: log.input_tokens,
                 output_tokens: log.output_tokens,
+                cached_tokens: log.cached_tokens,
+                estimated_cost_usd: log.estimated_cost_usd,
                 protocol: log.protocol.clone(),
                 username: log.username.clone(),
+                timeout_secs: log.timeout_secs,
+                error_class: log.error_class.clone(),
             };
             let _ = app.emit("proxy://request", &log_summary);
         }
@@ -211,7 +695,7 @@ impl ProxyMonitor {
             crate::modules::proxy_db::get_stats()
         }).await;
 
-        match db_result {
+        let mut stats = match db_result {
             Ok(Ok(stats)) => stats,
             Ok(Err(e)) => {
                 tracing::error!("Failed to get stats from DB: {}", e);
@@ -221,7 +705,11 @@ impl ProxyMonitor {
                 tracing::error!("Spawn blocking failed for get_stats: {}", e);
                 self.stats.read().await.clone()
             }
-        }
+        };
+        self.dispatch.apply_to(&mut stats);
+        ConnectionCounters::global().apply_to(&mut stats);
+        self.streaming.apply_to(&mut stats);
+        stats
     }
     
     pub async fn get_logs_filtered(
@@ -250,6 +738,9 @@ impl ProxyMonitor {
         logs.clear();
         let mut stats = self.stats.write().await;
         *stats = ProxyStats::default();
+        self.dispatch.reset();
+        ConnectionCounters::global().reset();
+        self.streaming.reset();
 
         let _ = tokio::task::spawn_blocking(|| {
             if let Err(e) = crate::modules::proxy_db::clear_logs() {
@@ -257,4 +748,96 @@ impl ProxyMonitor {
             }
         }).await;
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_active_request_lifecycle() {
+        let registry = ActiveRequestRegistry::default();
+        let guard = registry.register(
+            "req-1".to_string(),
+            "POST".to_string(),
+            "/v1/messages".to_string(),
+            Some("claude-sonnet-4-5".to_string()),
+            Some("127.0.0.1".to_string()),
+        );
+        guard.set_response_info(Some("a@example.com".to_string()), true);
+        guard.add_streamed_bytes(128);
+
+        let list = registry.list();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].account_email.as_deref(), Some("a@example.com"));
+        assert_eq!(list[0].streamed_bytes, 128);
+        assert!(list[0].streaming);
+
+        assert!(registry.cancel("req-1"));
+        assert!(guard.is_cancelled());
+        assert!(!registry.cancel("missing"));
+
+        drop(guard);
+        assert!(registry.is_empty());
+        assert_eq!(registry.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_drain_cancels_after_timeout() {
+        let registry = ActiveRequestRegistry::default();
+        assert_eq!(
+            registry.drain(std::time::Duration::from_secs(5)).await,
+            DrainOutcome { drained: 0, cancelled: 0 }
+        );
+
+        let finishing = registry.register("a".into(), "POST".into(), "/v1/messages".into(), None, None);
+        let stuck = registry.register("b".into(), "POST".into(), "/v1/messages".into(), None, None);
+        assert_eq!(registry.in_flight(), 2);
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            drop(finishing);
+        });
+
+        let outcome = registry.drain(std::time::Duration::from_millis(400)).await;
+        assert_eq!(outcome, DrainOutcome { drained: 1, cancelled: 1 });
+        assert!(stuck.is_cancelled());
+    }
+
+    #[test]
+    fn test_connection_reuse_rate() {
+        let counters = ConnectionCounters::default();
+        for _ in 0..4 {
+            counters.record_request();
+        }
+        counters.record_new_connection();
+
+        let mut stats = ProxyStats::default();
+        counters.apply_to(&mut stats);
+        assert_eq!(stats.upstream_new_connections, 1);
+        assert_eq!(stats.upstream_reused_connections, 3);
+        assert_eq!(stats.connection_reuse_rate, 0.75);
+    }
+
+    #[test]
+    fn test_stream_timing_percentiles() {
+        let timings = StreamTimings::default();
+        for i in 1..=100 {
+            timings.record(Some(i * 10), i * 100);
+        }
+        // 只有保活事件的流不计入 TTFB
+        timings.record(None, 20_000);
+
+        let mut stats = ProxyStats::default();
+        timings.apply_to(&mut stats);
+        assert_eq!(stats.stream_ttfb_p50_ms, 500);
+        assert_eq!(stats.stream_ttfb_p95_ms, 950);
+        assert_eq!(stats.stream_duration_p50_ms, 5_100);
+        assert_eq!(stats.stream_duration_p95_ms, 9_600);
+        assert_eq!(stats.stream_samples, 101);
+
+        timings.reset();
+        let mut stats = ProxyStats::default();
+        timings.apply_to(&mut stats);
+        assert_eq!((stats.stream_ttfb_p50_ms, stats.stream_samples), (0, 0));
+    }
+}
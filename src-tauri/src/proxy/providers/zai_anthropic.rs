@@ -10,6 +10,14 @@ use tokio::time::Duration;
 
 use crate::proxy::server::AppState;
 
+/// Map an incoming Anthropic model id to a z.ai model id.
+///
+/// Resolution order:
+/// 1. Exact key in `model_mapping` (as sent, then lowercased).
+/// 2. Wildcard key in `model_mapping` (e.g. `claude-3-5-*`); the pattern with the most
+///    non-`*` characters wins, ties are broken by the lexicographically smallest pattern.
+/// 3. Explicit passthrough: `zai:<id>`, `glm-*` and non-Claude ids are sent as-is.
+/// 4. `ZaiModelDefaults` by family (opus / haiku / sonnet).
 fn map_model_for_zai(original: &str, state: &crate::proxy::ZaiConfig) -> String {
     let m = original.to_lowercase();
     if let Some(mapped) = state.model_mapping.get(original) {
@@ -18,6 +26,9 @@ fn map_model_for_zai(original: &str, state: &crate::proxy::ZaiConfig) -> String
     if let Some(mapped) = state.model_mapping.get(&m) {
         return mapped.clone();
     }
+    if let Some(mapped) = match_zai_wildcard(original, &m, &state.model_mapping) {
+        return mapped;
+    }
     if m.starts_with("zai:") {
         return original[4..].to_string();
     }
@@ -36,6 +47,36 @@ fn map_model_for_zai(original: &str, state: &crate::proxy::ZaiConfig) -> String
     state.models.sonnet.clone()
 }
 
+fn match_zai_wildcard(
+    original: &str,
+    lower: &str,
+    mapping: &std::collections::HashMap<String, String>,
+) -> Option<String> {
+    use crate::proxy::common::model_mapping::wildcard_match;
+
+    let mut best: Option<(&str, &str, usize)> = None;
+    for (pattern, target) in mapping.iter() {
+        if !pattern.contains('*') {
+            continue;
+        }
+        if !wildcard_match(pattern, original) && !wildcard_match(pattern, lower) {
+            continue;
+        }
+        let specificity = pattern.chars().count() - pattern.matches('*').count();
+        let better = match best {
+            None => true,
+            Some((best_pattern, _, best_spec)) => {
+                specificity > best_spec
+                    || (specificity == best_spec && pattern.as_str() < best_pattern)
+            }
+        };
+        if better {
+            best = Some((pattern.as_str(), target.as_str(), specificity));
+        }
+    }
+    best.map(|(_, target, _)| target.to_string())
+}
+
 fn join_base_url(base: &str, path: &str) -> Result<String, String> {
     let base = base.trim_end_matches('/');
     let path = if path.starts_with('/') {
@@ -110,6 +151,165 @@ fn set_zai_auth(headers: &mut HeaderMap, incoming: &HeaderMap, api_key: &str) {
     }
 }
 
+/// z.ai 模型列表缓存有效期
+const ZAI_MODELS_TTL: Duration = Duration::from_secs(600);
+
+/// z.ai 上游返回的模型信息
+#[derive(Debug, Clone)]
+pub struct ZaiModelInfo {
+    pub id: String,
+    pub context_window: Option<u64>,
+}
+
+struct ZaiModelsCache {
+    base_url: String,
+    fetched_at: std::time::Instant,
+    models: Vec<ZaiModelInfo>,
+}
+
+static ZAI_MODELS_CACHE: once_cell::sync::Lazy<tokio::sync::Mutex<Option<ZaiModelsCache>>> =
+    once_cell::sync::Lazy::new(|| tokio::sync::Mutex::new(None));
+
+/// 获取 z.ai 可用模型列表 (带 TTL 缓存)
+///
+/// 拉取失败时优先返回旧缓存，否则退回到配置中的默认模型与映射目标。
+pub async fn list_zai_models(
+    zai: &crate::proxy::ZaiConfig,
+    upstream_proxy: Option<crate::proxy::config::UpstreamProxyConfig>,
+    force_refresh: bool,
+) -> Vec<ZaiModelInfo> {
+    if !force_refresh {
+        if let Some(c) = ZAI_MODELS_CACHE.lock().await.as_ref() {
+            if c.base_url == zai.base_url && c.fetched_at.elapsed() < ZAI_MODELS_TTL {
+                return c.models.clone();
+            }
+        }
+    }
+
+    // [FIX] 网络请求期间不持有缓存锁，慢请求不会阻塞其他调用方读取缓存
+    let fetched = fetch_zai_models(zai, upstream_proxy).await;
+    let mut cache = ZAI_MODELS_CACHE.lock().await;
+    match fetched {
+        Ok(models) => {
+            *cache = Some(ZaiModelsCache {
+                base_url: zai.base_url.clone(),
+                fetched_at: std::time::Instant::now(),
+                models: models.clone(),
+            });
+            models
+        }
+        Err(e) => {
+            tracing::warn!("[z.ai] Failed to fetch model list: {}", e);
+            match cache.as_ref() {
+                Some(c) if c.base_url == zai.base_url => c.models.clone(),
+                _ => configured_zai_models(zai),
+            }
+        }
+    }
+}
+
+async fn fetch_zai_models(
+    zai: &crate::proxy::ZaiConfig,
+    upstream_proxy: Option<crate::proxy::config::UpstreamProxyConfig>,
+) -> Result<Vec<ZaiModelInfo>, String> {
+    if zai.api_key.trim().is_empty() {
+        return Err("z.ai api_key is not set".to_string());
+    }
+    let url = join_base_url(&zai.base_url, "/v1/models")?;
+    let client = build_client(upstream_proxy, 15)?;
+    let resp = client
+        .get(&url)
+        .header("x-api-key", zai.api_key.trim())
+        .header(header::AUTHORIZATION, format!("Bearer {}", zai.api_key.trim()))
+        .header("anthropic-version", "2023-06-01")
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+    if !resp.status().is_success() {
+        return Err(format!("HTTP {}", resp.status()));
+    }
+    let body: Value = resp
+        .json()
+        .await
+        .map_err(|e| format!("Invalid JSON: {}", e))?;
+    Ok(parse_zai_models(&body))
+}
+
+/// 健康探测: 拉取 `/v1/models` (不计费)。
+/// 404/405 说明上游可达但不提供模型列表端点，视为健康；401/403、5xx 与网络错误视为不健康
+pub(crate) async fn probe_zai(
+    zai: &crate::proxy::ZaiConfig,
+    upstream_proxy: Option<crate::proxy::config::UpstreamProxyConfig>,
+) -> Result<(), String> {
+    if zai.api_key.trim().is_empty() {
+        return Err("z.ai api_key is not set".to_string());
+    }
+    let url = join_base_url(&zai.base_url, "/v1/models")?;
+    let client = build_client(upstream_proxy, 15)?;
+    let resp = client
+        .get(&url)
+        .header("x-api-key", zai.api_key.trim())
+        .header(header::AUTHORIZATION, format!("Bearer {}", zai.api_key.trim()))
+        .header("anthropic-version", "2023-06-01")
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+    let status = resp.status();
+    if probe_status_ok(status.as_u16()) {
+        return Ok(());
+    }
+    let text = resp.text().await.unwrap_or_default();
+    let detail: String = text.trim().chars().take(200).collect();
+    if detail.is_empty() {
+        Err(format!("HTTP {}", status))
+    } else {
+        Err(format!("HTTP {}: {}", status, detail))
+    }
+}
+
+fn probe_status_ok(status: u16) -> bool {
+    (200..300).contains(&status) || status == 404 || status == 405
+}
+
+/// 兼容 Anthropic (`data[].id`) 与 OpenAI 风格的模型列表响应
+fn parse_zai_models(body: &Value) -> Vec<ZaiModelInfo> {
+    body.get("data")
+        .and_then(|d| d.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|m| {
+                    let id = m.get("id").and_then(|v| v.as_str())?;
+                    let context_window = ["context_window", "context_length", "max_context_tokens"]
+                        .iter()
+                        .find_map(|k| m.get(*k).and_then(|v| v.as_u64()));
+                    Some(ZaiModelInfo {
+                        id: id.to_string(),
+                        context_window,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn configured_zai_models(zai: &crate::proxy::ZaiConfig) -> Vec<ZaiModelInfo> {
+    let mut ids: Vec<String> = vec![
+        zai.models.opus.clone(),
+        zai.models.sonnet.clone(),
+        zai.models.haiku.clone(),
+    ];
+    ids.extend(zai.model_mapping.values().cloned());
+    ids.sort();
+    ids.dedup();
+    ids.into_iter()
+        .filter(|id| !id.is_empty())
+        .map(|id| ZaiModelInfo {
+            id,
+            context_window: None,
+        })
+        .collect()
+}
+
 /// Recursively remove cache_control from all nested objects/arrays
 /// [FIX #290] This is a defensive fix that works regardless of serde annotations
 pub fn deep_remove_cache_control(value: &mut Value) {
@@ -204,6 +404,7 @@ pub async fn forward_anthropic_json(
     let resp = match req.send().await {
         Ok(r) => r,
         Err(e) => {
+            state.monitor.record_zai_error();
             return (
                 StatusCode::BAD_GATEWAY,
                 format!("Upstream request failed: {}", e),
@@ -213,6 +414,9 @@ pub async fn forward_anthropic_json(
     };
 
     let status = StatusCode::from_u16(resp.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+    if !status.is_success() {
+        state.monitor.record_zai_error();
+    }
 
     let mut out = Response::builder().status(status);
     if let Some(ct) = resp.headers().get(header::CONTENT_TYPE) {
@@ -229,3 +433,78 @@ pub async fn forward_anthropic_json(
         (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response").into_response()
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::ZaiConfig;
+
+    fn config_with(mapping: &[(&str, &str)]) -> ZaiConfig {
+        let mut cfg = ZaiConfig::default();
+        for (k, v) in mapping {
+            cfg.model_mapping.insert(k.to_string(), v.to_string());
+        }
+        cfg
+    }
+
+    #[test]
+    fn test_probe_status_classification() {
+        assert!(probe_status_ok(200));
+        assert!(probe_status_ok(404));
+        assert!(!probe_status_ok(401));
+        assert!(!probe_status_ok(403));
+        assert!(!probe_status_ok(503));
+    }
+
+    #[test]
+    fn test_zai_exact_mapping_beats_wildcard() {
+        let cfg = config_with(&[
+            ("claude-3-5-sonnet-20241022", "glm-exact"),
+            ("claude-3-5-*", "glm-4.6"),
+        ]);
+        assert_eq!(map_model_for_zai("claude-3-5-sonnet-20241022", &cfg), "glm-exact");
+        assert_eq!(map_model_for_zai("claude-3-5-haiku-20241022", &cfg), "glm-4.6");
+    }
+
+    #[test]
+    fn test_zai_most_specific_wildcard_wins() {
+        let cfg = config_with(&[
+            ("claude-*", "glm-generic"),
+            ("claude-*-opus-*", "glm-opus"),
+        ]);
+        assert_eq!(map_model_for_zai("claude-3-opus-20240229", &cfg), "glm-opus");
+        assert_eq!(map_model_for_zai("claude-3-sonnet-20240229", &cfg), "glm-generic");
+        // 大小写不敏感回退
+        assert_eq!(map_model_for_zai("Claude-3-Opus-20240229", &cfg), "glm-opus");
+    }
+
+    #[test]
+    fn test_parse_zai_models_and_fallback() {
+        let body = serde_json::json!({
+            "data": [
+                { "id": "glm-4.7", "context_window": 200000 },
+                { "id": "glm-4.5-air" },
+                { "object": "model" }
+            ]
+        });
+        let models = parse_zai_models(&body);
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].context_window, Some(200000));
+        assert_eq!(models[1].context_window, None);
+
+        let cfg = config_with(&[("claude-3-5-*", "glm-4.6")]);
+        let fallback: Vec<String> = configured_zai_models(&cfg).into_iter().map(|m| m.id).collect();
+        assert!(fallback.contains(&"glm-4.6".to_string()));
+        assert!(fallback.contains(&cfg.models.sonnet));
+    }
+
+    #[test]
+    fn test_zai_defaults_when_no_mapping_matches() {
+        let cfg = config_with(&[("claude-3-5-*", "glm-4.6")]);
+        assert_eq!(map_model_for_zai("claude-opus-4-6", &cfg), cfg.models.opus);
+        assert_eq!(map_model_for_zai("claude-haiku-4-5", &cfg), cfg.models.haiku);
+        assert_eq!(map_model_for_zai("claude-sonnet-4-5", &cfg), cfg.models.sonnet);
+        assert_eq!(map_model_for_zai("glm-4.5", &cfg), "glm-4.5");
+        assert_eq!(map_model_for_zai("zai:glm-4.7", &cfg), "glm-4.7");
+    }
+}
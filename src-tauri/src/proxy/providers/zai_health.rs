@@ -0,0 +1,165 @@
+// z.ai 提供商健康探测
+// 定期拉取 z.ai 的 `/v1/models` (不计费) 验证 API Key 与连通性；
+// 不健康时 Pooled 模式跳过 z.ai 槽位，Fallback 模式不再兜底，仅在状态切换时打印一次告警
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock, Weak};
+use std::time::Duration;
+
+use crate::proxy::config::UpstreamProxyConfig;
+use crate::proxy::{ZaiConfig, ZaiDispatchMode};
+
+/// 后台探测间隔
+pub const ZAI_PROBE_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ZaiHealthSnapshot {
+    pub healthy: bool,
+    /// 最近一次探测时间 (Unix 秒)，未探测过为 None
+    pub last_probe_at: Option<i64>,
+    pub last_probe_error: Option<String>,
+}
+
+impl Default for ZaiHealthSnapshot {
+    fn default() -> Self {
+        Self {
+            healthy: true,
+            last_probe_at: None,
+            last_probe_error: None,
+        }
+    }
+}
+
+pub struct ZaiHealth {
+    /// 请求路径上的快速读取
+    healthy: AtomicBool,
+    snapshot: RwLock<ZaiHealthSnapshot>,
+}
+
+impl ZaiHealth {
+    pub fn new() -> Self {
+        Self {
+            healthy: AtomicBool::new(true),
+            snapshot: RwLock::new(ZaiHealthSnapshot::default()),
+        }
+    }
+
+    /// 未探测或 z.ai 未启用时视为健康，不影响调度
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    pub fn snapshot(&self) -> ZaiHealthSnapshot {
+        self.snapshot
+            .read()
+            .map(|s| s.clone())
+            .unwrap_or_default()
+    }
+
+    /// 记录探测结果，健康状态发生变化时打印一次日志
+    pub fn record(&self, result: Result<(), String>, mode: &ZaiDispatchMode) -> ZaiHealthSnapshot {
+        let healthy = result.is_ok();
+        let was_healthy = self.healthy.swap(healthy, Ordering::Relaxed);
+        let snapshot = ZaiHealthSnapshot {
+            healthy,
+            last_probe_at: Some(chrono::Utc::now().timestamp()),
+            last_probe_error: result.err(),
+        };
+
+        if was_healthy && !healthy {
+            let effect = match mode {
+                ZaiDispatchMode::Pooled => "skipping z.ai slot in pooled dispatch",
+                ZaiDispatchMode::Fallback => "fallback to z.ai disabled",
+                _ => "requests to z.ai are likely to fail",
+            };
+            tracing::warn!(
+                "[z.ai] Provider marked unhealthy ({}): {}",
+                effect,
+                snapshot.last_probe_error.as_deref().unwrap_or_default()
+            );
+        } else if !was_healthy && healthy {
+            tracing::info!("[z.ai] Provider healthy again, resuming normal dispatch");
+        }
+
+        if let Ok(mut current) = self.snapshot.write() {
+            *current = snapshot.clone();
+        }
+        snapshot
+    }
+
+    /// z.ai 未启用时清除探测状态
+    pub fn reset(&self) {
+        self.healthy.store(true, Ordering::Relaxed);
+        if let Ok(mut current) = self.snapshot.write() {
+            *current = ZaiHealthSnapshot::default();
+        }
+    }
+
+    /// 立即探测一次；z.ai 未启用或未配置 Key 时重置为默认状态
+    pub async fn probe(
+        &self,
+        zai: &ZaiConfig,
+        upstream_proxy: Option<UpstreamProxyConfig>,
+    ) -> ZaiHealthSnapshot {
+        if !zai.enabled || zai.dispatch_mode == ZaiDispatchMode::Off {
+            self.reset();
+            return self.snapshot();
+        }
+        let result = super::zai_anthropic::probe_zai(zai, upstream_proxy).await;
+        self.record(result, &zai.dispatch_mode)
+    }
+}
+
+impl Default for ZaiHealth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 启动后台探测任务；服务停止 (z.ai 配置状态被释放) 后自动退出
+pub fn spawn_probe_loop(
+    health: Arc<ZaiHealth>,
+    zai: Weak<tokio::sync::RwLock<ZaiConfig>>,
+    upstream_proxy: Arc<tokio::sync::RwLock<UpstreamProxyConfig>>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(ZAI_PROBE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let Some(zai) = zai.upgrade() else {
+                tracing::debug!("[z.ai] Proxy stopped, health probe exiting");
+                break;
+            };
+            let config = zai.read().await.clone();
+            drop(zai);
+            let proxy = upstream_proxy.read().await.clone();
+            health.probe(&config, Some(proxy)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_transitions() {
+        let health = ZaiHealth::new();
+        assert!(health.is_healthy());
+        assert!(health.snapshot().last_probe_at.is_none());
+
+        let snapshot = health.record(Err("HTTP 401".to_string()), &ZaiDispatchMode::Pooled);
+        assert!(!snapshot.healthy);
+        assert!(!health.is_healthy());
+        assert_eq!(health.snapshot().last_probe_error.as_deref(), Some("HTTP 401"));
+
+        health.record(Ok(()), &ZaiDispatchMode::Pooled);
+        assert!(health.is_healthy());
+        assert!(health.snapshot().last_probe_error.is_none());
+
+        health.record(Err("timeout".to_string()), &ZaiDispatchMode::Fallback);
+        health.reset();
+        assert!(health.is_healthy());
+        assert!(health.snapshot().last_probe_at.is_none());
+    }
+}
@@ -17,6 +17,71 @@ pub enum RateLimitReason {
     Unknown,
 }
 
+impl RateLimitReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitReason::QuotaExhausted => "quota_exhausted",
+            RateLimitReason::RateLimitExceeded => "rate_limit_exceeded",
+            RateLimitReason::ModelCapacityExhausted => "model_capacity_exhausted",
+            RateLimitReason::ServerError => "server_error",
+            RateLimitReason::Unknown => "unknown",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "quota_exhausted" => RateLimitReason::QuotaExhausted,
+            "rate_limit_exceeded" => RateLimitReason::RateLimitExceeded,
+            "model_capacity_exhausted" => RateLimitReason::ModelCapacityExhausted,
+            "server_error" => RateLimitReason::ServerError,
+            _ => RateLimitReason::Unknown,
+        }
+    }
+}
+
+/// 从 429 响应体判断限流原因 (google.rpc.ErrorInfo.reason 优先，其次按消息文本匹配)
+pub fn classify_rate_limit_body(body: &str) -> RateLimitReason {
+    // 尝试从 JSON 中提取 reason 字段
+    let trimmed = body.trim();
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        if let Ok(json) = serde_json::from_str::<serde_json::Value>(trimmed) {
+            // [FIX] 遍历全部 details (ErrorInfo 不一定是第一个条目)
+            if let Some(reason_str) = json.get("error")
+                .and_then(|e| e.get("details"))
+                .and_then(|d| d.as_array())
+                .and_then(|a| a.iter().find_map(|o| o.get("reason").and_then(|v| v.as_str()))) {
+                
+                return match reason_str {
+                    "QUOTA_EXHAUSTED" => RateLimitReason::QuotaExhausted,
+                    "RATE_LIMIT_EXCEEDED" => RateLimitReason::RateLimitExceeded,
+                    "MODEL_CAPACITY_EXHAUSTED" => RateLimitReason::ModelCapacityExhausted,
+                    _ => RateLimitReason::Unknown,
+                };
+            }
+            // [NEW] 尝试从 message 字段进行文本匹配（防止 missed reason）
+             if let Some(msg) = json.get("error")
+                .and_then(|e| e.get("message"))
+                .and_then(|v| v.as_str()) {
+                let msg_lower = msg.to_lowercase();
+                if msg_lower.contains("per minute") || msg_lower.contains("rate limit") {
+                    return RateLimitReason::RateLimitExceeded;
+                }
+             }
+        }
+    }
+    
+    // 如果无法从 JSON 解析，尝试从消息文本判断
+    let body_lower = body.to_lowercase();
+    // [FIX] 优先判断分钟级限制，避免将 TPM 误判为 Quota
+    if body_lower.contains("per minute") || body_lower.contains("rate limit") || body_lower.contains("too many requests") {
+         RateLimitReason::RateLimitExceeded
+    } else if body_lower.contains("exhausted") || body_lower.contains("quota") {
+        RateLimitReason::QuotaExhausted
+    } else {
+        RateLimitReason::Unknown
+    }
+}
+
 /// 限流信息
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
@@ -55,6 +120,20 @@ impl RateLimitTracker {
             failure_counts: DashMap::new(),
         }
     }
+
+    /// 账号当前未过期的连续失败次数 (用于判断熔断是否已升级到最高档)
+    pub fn failure_count(&self, account_id: &str) -> u32 {
+        self.failure_counts
+            .get(account_id)
+            .filter(|entry| {
+                SystemTime::now()
+                    .duration_since(entry.1)
+                    .map(|d| d.as_secs() <= FAILURE_COUNT_EXPIRY_SECONDS)
+                    .unwrap_or(true)
+            })
+            .map(|entry| entry.0)
+            .unwrap_or(0)
+    }
     
     /// 生成限流 Key
     /// - 账号级: "account_id"
@@ -326,46 +405,7 @@ impl RateLimitTracker {
     
     /// 解析限流原因类型
     fn parse_rate_limit_reason(&self, body: &str) -> RateLimitReason {
-        // 尝试从 JSON 中提取 reason 字段
-        let trimmed = body.trim();
-        if trimmed.starts_with('{') || trimmed.starts_with('[') {
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(trimmed) {
-                if let Some(reason_str) = json.get("error")
-                    .and_then(|e| e.get("details"))
-                    .and_then(|d| d.as_array())
-                    .and_then(|a| a.get(0))
-                    .and_then(|o| o.get("reason"))
-                    .and_then(|v| v.as_str()) {
-                    
-                    return match reason_str {
-                        "QUOTA_EXHAUSTED" => RateLimitReason::QuotaExhausted,
-                        "RATE_LIMIT_EXCEEDED" => RateLimitReason::RateLimitExceeded,
-                        "MODEL_CAPACITY_EXHAUSTED" => RateLimitReason::ModelCapacityExhausted,
-                        _ => RateLimitReason::Unknown,
-                    };
-                }
-                // [NEW] 尝试从 message 字段进行文本匹配（防止 missed reason）
-                 if let Some(msg) = json.get("error")
-                    .and_then(|e| e.get("message"))
-                    .and_then(|v| v.as_str()) {
-                    let msg_lower = msg.to_lowercase();
-                    if msg_lower.contains("per minute") || msg_lower.contains("rate limit") {
-                        return RateLimitReason::RateLimitExceeded;
-                    }
-                 }
-            }
-        }
-        
-        // 如果无法从 JSON 解析，尝试从消息文本判断
-        let body_lower = body.to_lowercase();
-        // [FIX] 优先判断分钟级限制，避免将 TPM 误判为 Quota
-        if body_lower.contains("per minute") || body_lower.contains("rate limit") || body_lower.contains("too many requests") {
-             RateLimitReason::RateLimitExceeded
-        } else if body_lower.contains("exhausted") || body_lower.contains("quota") {
-            RateLimitReason::QuotaExhausted
-        } else {
-            RateLimitReason::Unknown
-        }
+        classify_rate_limit_body(body)
     }
     
     /// 通用时间解析函数：支持 "2h1m1s" 等所有格式组合
@@ -542,6 +582,46 @@ impl RateLimitTracker {
         count
     }
     
+    /// 导出某账号仍有效的限流记录 (账号级 + 模型级)，用于多实例共享
+    pub fn active_lockouts(&self, account_id: &str) -> Vec<(String, RateLimitInfo)> {
+        let now = SystemTime::now();
+        let model_prefix = format!("{}:", account_id);
+        self.limits
+            .iter()
+            .filter(|e| e.key() == account_id || e.key().starts_with(&model_prefix))
+            .filter(|e| e.value().reset_time > now)
+            .map(|e| (e.key().clone(), e.value().clone()))
+            .collect()
+    }
+
+    /// 合并其他实例同步来的限流记录；只延长、不缩短本地锁定时间
+    pub fn merge_lockout(
+        &self,
+        account_id: &str,
+        model: Option<String>,
+        reset_time: SystemTime,
+        reason: RateLimitReason,
+    ) {
+        let now = SystemTime::now();
+        if reset_time <= now {
+            return;
+        }
+        let key = self.get_limit_key(account_id, model.as_deref());
+        if matches!(self.limits.get(&key), Some(existing) if existing.reset_time >= reset_time) {
+            return;
+        }
+        self.limits.insert(
+            key,
+            RateLimitInfo {
+                reset_time,
+                retry_after_sec: reset_time.duration_since(now).map(|d| d.as_secs()).unwrap_or(0),
+                detected_at: now,
+                reason,
+                model,
+            },
+        );
+    }
+
     /// 清除指定账号的限流记录
     pub fn clear(&self, account_id: &str) -> bool {
         self.limits.remove(account_id).is_some()
@@ -676,4 +756,29 @@ mod tests {
         let info = tracker.parse_from_error("acc2", 429, None, quota_body, None, &backoff_steps);
         assert_eq!(info.unwrap().retry_after_sec, 7200);
     }
+
+    #[test]
+    fn test_merge_lockout_only_extends() {
+        let tracker = RateLimitTracker::new();
+        let now = SystemTime::now();
+        tracker.set_lockout_until("acc3", now + Duration::from_secs(300), RateLimitReason::QuotaExhausted, None);
+
+        // 其他实例同步来的更早解锁时间不应缩短本地锁定
+        tracker.merge_lockout("acc3", None, now + Duration::from_secs(60), RateLimitReason::Unknown);
+        assert!(tracker.get_remaining_wait("acc3", None) > 200);
+
+        tracker.merge_lockout(
+            "acc3",
+            Some("gemini-2.5-pro".to_string()),
+            now + Duration::from_secs(120),
+            RateLimitReason::RateLimitExceeded,
+        );
+        let exported = tracker.active_lockouts("acc3");
+        assert_eq!(exported.len(), 2);
+        assert!(exported.iter().any(|(k, i)| k == "acc3:gemini-2.5-pro"
+            && i.reason == RateLimitReason::RateLimitExceeded));
+        assert!(tracker.active_lockouts("acc").is_empty());
+
+        assert_eq!(RateLimitReason::parse(RateLimitReason::QuotaExhausted.as_str()), RateLimitReason::QuotaExhausted);
+    }
 }
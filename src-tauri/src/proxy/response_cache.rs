@@ -0,0 +1,247 @@
+// 幂等接口响应缓存
+// countTokens 等只读接口的结果与账号无关，按 (路由, 规范化请求体哈希) 缓存 TTL 秒；
+// 模型映射或 z.ai 配置热更新时整体失效。生成类接口不经过此缓存
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+/// 默认缓存时间 (秒)
+pub const DEFAULT_RESPONSE_CACHE_TTL_SECS: u64 = 60;
+
+/// 最多缓存的条目数，超出时先清理过期条目，仍超出则清空
+const MAX_ENTRIES: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    route: &'static str,
+    hash: u64,
+}
+
+impl CacheKey {
+    /// 对象键排序后再哈希，字段顺序不同的相同请求命中同一条目
+    pub fn new(route: &'static str, body: &Value) -> Self {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        hash_canonical(body, &mut hasher);
+        Self {
+            route,
+            hash: hasher.finish(),
+        }
+    }
+}
+
+fn hash_canonical<H: Hasher>(value: &Value, state: &mut H) {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            state.write_u8(b'{');
+            for key in keys {
+                key.hash(state);
+                hash_canonical(&map[key], state);
+            }
+        }
+        Value::Array(items) => {
+            state.write_u8(b'[');
+            state.write_usize(items.len());
+            items.iter().for_each(|item| hash_canonical(item, state));
+        }
+        other => other.to_string().hash(state),
+    }
+}
+
+/// 缓存的 JSON 响应 (仅缓存 200)；header 名需为小写
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedResponse {
+    pub body: Value,
+    pub headers: Vec<(&'static str, String)>,
+}
+
+impl IntoResponse for CachedResponse {
+    fn into_response(self) -> Response {
+        let mut response = Json(self.body).into_response();
+        let headers = response.headers_mut();
+        for (name, value) in self.headers {
+            if let Ok(value) = value.parse() {
+                headers.insert(name, value);
+            }
+        }
+        headers.insert("x-cache", axum::http::HeaderValue::from_static("HIT"));
+        response
+    }
+}
+
+/// 命中率统计
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// hits / (hits + misses)，无请求时为 0
+    pub hit_ratio: f64,
+}
+
+impl CacheStats {
+    pub fn new(hits: u64, misses: u64) -> Self {
+        let total = hits + misses;
+        Self {
+            hits,
+            misses,
+            hit_ratio: if total > 0 { hits as f64 / total as f64 } else { 0.0 },
+        }
+    }
+}
+
+pub struct ResponseCache {
+    ttl_secs: AtomicU64,
+    /// 每次失效时递增，用于丢弃失效前开始计算的结果
+    generation: AtomicU64,
+    entries: RwLock<HashMap<CacheKey, (CachedResponse, Instant)>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ResponseCache {
+    pub fn new(ttl_secs: u64) -> Self {
+        Self {
+            ttl_secs: AtomicU64::new(ttl_secs),
+            generation: AtomicU64::new(0),
+            entries: RwLock::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn global() -> Arc<ResponseCache> {
+        static INSTANCE: OnceLock<Arc<ResponseCache>> = OnceLock::new();
+        INSTANCE
+            .get_or_init(|| Arc::new(ResponseCache::new(DEFAULT_RESPONSE_CACHE_TTL_SECS)))
+            .clone()
+    }
+
+    /// 更新 TTL (0 表示禁用缓存)，并使现有缓存失效
+    pub fn configure(&self, ttl_secs: u64) {
+        self.ttl_secs.store(ttl_secs, Ordering::Relaxed);
+        self.invalidate();
+    }
+
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if let Ok(mut entries) = self.entries.write() {
+            entries.clear();
+        }
+        tracing::debug!("[Response-Cache] Cache invalidated");
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<CachedResponse> {
+        self.get_at(key, Instant::now())
+    }
+
+    fn get_at(&self, key: &CacheKey, now: Instant) -> Option<CachedResponse> {
+        let ttl = self.ttl_secs.load(Ordering::Relaxed);
+        if ttl == 0 {
+            return None;
+        }
+        let hit = self.entries.read().ok().and_then(|entries| {
+            entries
+                .get(key)
+                .filter(|(_, at)| now.saturating_duration_since(*at) < Duration::from_secs(ttl))
+                .map(|(response, _)| response.clone())
+        });
+        let counter = if hit.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        hit
+    }
+
+    /// 写入缓存；`generation` 为开始计算时的值，期间发生过失效则丢弃
+    pub fn store(&self, key: CacheKey, response: CachedResponse, generation: u64) {
+        self.store_at(key, response, generation, Instant::now());
+    }
+
+    fn store_at(&self, key: CacheKey, response: CachedResponse, generation: u64, now: Instant) {
+        let ttl = self.ttl_secs.load(Ordering::Relaxed);
+        if ttl == 0 || self.generation() != generation {
+            return;
+        }
+        if let Ok(mut entries) = self.entries.write() {
+            if entries.len() >= MAX_ENTRIES {
+                let ttl = Duration::from_secs(ttl);
+                entries.retain(|_, (_, at)| now.saturating_duration_since(*at) < ttl);
+                if entries.len() >= MAX_ENTRIES {
+                    entries.clear();
+                }
+            }
+            entries.insert(key, (response, now));
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats::new(
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_RESPONSE_CACHE_TTL_SECS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cached(tokens: u64) -> CachedResponse {
+        CachedResponse {
+            body: json!({ "prompt_tokens": tokens }),
+            headers: vec![("x-mapped-model", "gemini-2.5-pro".to_string())],
+        }
+    }
+
+    #[test]
+    fn test_key_ignores_field_order_but_not_route() {
+        let a = json!({ "model": "gpt-4o", "messages": [{ "role": "user", "content": "hi" }] });
+        let b = json!({ "messages": [{ "content": "hi", "role": "user" }], "model": "gpt-4o" });
+        assert_eq!(CacheKey::new("openai", &a), CacheKey::new("openai", &b));
+        assert_ne!(CacheKey::new("openai", &a), CacheKey::new("claude", &a));
+        assert_ne!(
+            CacheKey::new("openai", &a),
+            CacheKey::new("openai", &json!({ "model": "gpt-4o", "messages": [] }))
+        );
+    }
+
+    #[test]
+    fn test_ttl_invalidation_and_stats() {
+        let cache = ResponseCache::new(60);
+        let key = CacheKey::new("openai", &json!({ "model": "gpt-4o" }));
+        assert!(cache.get(&key).is_none());
+
+        let now = Instant::now();
+        cache.store_at(key, cached(7), cache.generation(), now);
+        assert_eq!(cache.get_at(&key, now), Some(cached(7)));
+        assert!(cache.get_at(&key, now + Duration::from_secs(61)).is_none());
+        assert_eq!(cache.stats(), CacheStats::new(1, 2));
+
+        // 计算期间发生失效，结果被丢弃
+        let generation = cache.generation();
+        cache.invalidate();
+        cache.store(key, cached(8), generation);
+        assert!(cache.get(&key).is_none());
+
+        // TTL 为 0 时不缓存
+        cache.configure(0);
+        cache.store(key, cached(9), cache.generation());
+        assert!(cache.get(&key).is_none());
+    }
+}
@@ -1,13 +1,156 @@
-use crate::proxy::config::{ProxyAuthMode, ProxyConfig, SecurityMonitorConfig};
+use crate::proxy::config::{ApiKeyEntry, CorsConfig, ProxyAuthMode, ProxyConfig, SecurityMonitorConfig};
+use axum::http::HeaderMap;
+use ipnet::IpNet;
+use std::net::IpAddr;
+
+/// 配置文件中 CIDR 规则的解析结果
+/// 在加载/热更新配置时解析一次，中间件只做匹配
+#[derive(Debug, Clone, Default)]
+pub struct CidrRules {
+    pub blocked: Vec<IpNet>,
+    pub allowed: Vec<IpNet>,
+    pub trusted_proxies: Vec<IpNet>,
+}
+
+impl CidrRules {
+    /// 解析 `blocked_cidrs` / `allowed_cidrs`，非法条目只记录警告并跳过
+    pub fn from_monitor_config(config: &SecurityMonitorConfig) -> Self {
+        Self {
+            blocked: parse_cidr_list(&config.blocked_cidrs, "blocked_cidrs"),
+            allowed: parse_cidr_list(&config.allowed_cidrs, "allowed_cidrs"),
+            trusted_proxies: parse_cidr_list(&config.trusted_proxies, "trusted_proxies"),
+        }
+    }
+
+    pub fn is_trusted_proxy(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|net| net.contains(&ip))
+    }
+
+    pub fn matching_blocked(&self, ip: IpAddr) -> Vec<&IpNet> {
+        self.blocked.iter().filter(|net| net.contains(&ip)).collect()
+    }
+
+    pub fn matching_allowed(&self, ip: IpAddr) -> Vec<&IpNet> {
+        self.allowed.iter().filter(|net| net.contains(&ip)).collect()
+    }
+}
+
+/// 解析单条规则: 支持 CIDR (`192.168.1.0/24`) 与单个 IP (视为 /32 或 /128)
+pub fn parse_cidr(raw: &str) -> Option<IpNet> {
+    let raw = raw.trim();
+    if let Ok(net) = raw.parse::<IpNet>() {
+        return Some(net);
+    }
+    raw.parse::<IpAddr>().ok().map(IpNet::from)
+}
+
+/// 校验 `blocked_cidrs` / `allowed_cidrs`，返回全部非法条目的错误描述
+pub fn validate_monitor_cidrs(config: &SecurityMonitorConfig) -> Result<(), String> {
+    let invalid: Vec<String> = [
+        ("blocked_cidrs", &config.blocked_cidrs),
+        ("allowed_cidrs", &config.allowed_cidrs),
+        ("trusted_proxies", &config.trusted_proxies),
+    ]
+    .iter()
+    .flat_map(|(field, list)| {
+        list.iter()
+            .filter(|s| !s.trim().is_empty() && parse_cidr(s).is_none())
+            .map(move |s| format!("{}: {:?}", field, s))
+    })
+    .collect();
+
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Invalid CIDR entries: {}", invalid.join(", ")))
+    }
+}
+
+fn parse_cidr_list(list: &[String], field: &str) -> Vec<IpNet> {
+    list.iter()
+        .filter(|s| !s.trim().is_empty())
+        .filter_map(|s| {
+            let parsed = parse_cidr(s);
+            if parsed.is_none() {
+                tracing::warn!("[Security] Ignoring invalid CIDR in {}: {:?}", field, s);
+            }
+            parsed
+        })
+        .collect()
+}
+
+/// 解析真实客户端 IP
+///
+/// - 未配置 `trusted_proxies`: 保持旧行为 (X-Forwarded-For 首个 IP → X-Real-IP → TCP 对端)
+/// - 已配置且对端为受信代理: `CF-Connecting-IP` 优先，其次取 X-Forwarded-For 中自右向左第一个非受信跳
+/// - 已配置但对端不受信: 忽略所有转发头，直接使用 TCP 对端，防止伪造
+pub fn resolve_client_ip(
+    headers: &HeaderMap,
+    peer: Option<IpAddr>,
+    rules: &CidrRules,
+) -> Option<String> {
+    let header_str = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+    };
+
+    if rules.trusted_proxies.is_empty() {
+        return header_str("x-forwarded-for")
+            .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
+            .or_else(|| header_str("x-real-ip").map(|s| s.to_string()))
+            .or_else(|| peer.map(|ip| ip.to_string()));
+    }
+
+    let peer_ip = peer?;
+    if !rules.is_trusted_proxy(peer_ip) {
+        return Some(peer_ip.to_string());
+    }
+
+    if let Some(ip) = header_str("cf-connecting-ip").and_then(|s| s.parse::<IpAddr>().ok()) {
+        return Some(ip.to_string());
+    }
+
+    if let Some(xff) = header_str("x-forwarded-for") {
+        let hops: Vec<IpAddr> = xff
+            .split(',')
+            .filter_map(|hop| hop.trim().parse::<IpAddr>().ok())
+            .collect();
+        // 自右向左跳过受信代理；若全部受信，则取最左侧的原始客户端
+        if let Some(ip) = hops
+            .iter()
+            .rev()
+            .find(|ip| !rules.is_trusted_proxy(**ip))
+            .or_else(|| hops.first())
+        {
+            return Some(ip.to_string());
+        }
+    }
+
+    Some(peer_ip.to_string())
+}
 
 #[derive(Debug, Clone)]
 pub struct ProxySecurityConfig {
     pub auth_mode: ProxyAuthMode,
     pub api_key: String,
+    /// 轮换宽限期内仍然有效的旧 API Key
+    pub api_key_previous: Option<String>,
+    pub api_key_previous_expires_at: Option<i64>,
+    /// 附加的带标签 API Key (仅用于反代接口)
+    pub api_keys: Vec<ApiKeyEntry>,
     pub admin_password: Option<String>,
+    pub jwt_expiry_seconds: u64,
+    /// 管理用户会话令牌签名密钥 (为空表示尚未创建管理用户)
+    pub admin_session_secret: String,
     pub allow_lan_access: bool,
     pub port: u16,
     pub security_monitor: SecurityMonitorConfig,
+    /// 由 security_monitor 中的 CIDR 字符串解析而来
+    pub cidr_rules: CidrRules,
+    pub cors: CorsConfig,
 }
 
 impl ProxySecurityConfig {
@@ -15,13 +158,47 @@ impl ProxySecurityConfig {
         Self {
             auth_mode: config.auth_mode.clone(),
             api_key: config.api_key.clone(),
+            api_key_previous: config.api_key_previous.clone(),
+            api_key_previous_expires_at: config.api_key_previous_expires_at,
+            api_keys: config.api_keys.clone(),
             admin_password: config.admin_password.clone(),
+            jwt_expiry_seconds: config.jwt_expiry_seconds,
+            admin_session_secret: config.admin_session_secret.clone(),
             allow_lan_access: config.allow_lan_access,
             port: config.port,
             security_monitor: config.security_monitor.clone(),
+            cidr_rules: CidrRules::from_monitor_config(&config.security_monitor),
+            cors: config.cors.clone(),
         }
     }
 
+    /// 宽限期内的旧 API Key (已过期时返回 None)
+    pub fn previous_api_key(&self) -> Option<&str> {
+        let now = chrono::Utc::now().timestamp();
+        self.api_key_previous
+            .as_deref()
+            .filter(|k| !k.is_empty())
+            .filter(|_| matches!(self.api_key_previous_expires_at, Some(exp) if exp > now))
+    }
+
+    /// 当前 API Key 或宽限期内的旧 API Key
+    pub fn accepts_api_key(&self, key: &str) -> bool {
+        (!self.api_key.is_empty() && key == self.api_key) || self.previous_api_key() == Some(key)
+    }
+
+    /// 命中未禁用的附加 API Key 时返回其标签
+    pub fn labeled_key(&self, key: &str) -> Option<&str> {
+        self.api_keys
+            .iter()
+            .find(|entry| !entry.disabled && !entry.key.is_empty() && entry.key == key)
+            .map(|entry| entry.label.as_str())
+    }
+
+    /// 是否配置了可用的附加 API Key
+    pub fn has_labeled_keys(&self) -> bool {
+        self.api_keys.iter().any(|entry| !entry.disabled && !entry.key.is_empty())
+    }
+
     pub fn effective_auth_mode(&self) -> ProxyAuthMode {
         match self.auth_mode {
             ProxyAuthMode::Auto => {
@@ -45,10 +222,17 @@ mod tests {
         let s = ProxySecurityConfig {
             auth_mode: ProxyAuthMode::Auto,
             api_key: "sk-test".to_string(),
+            api_key_previous: None,
+            api_key_previous_expires_at: None,
+            api_keys: Vec::new(),
             admin_password: None,
+            jwt_expiry_seconds: 3600,
+            admin_session_secret: String::new(),
             allow_lan_access: false,
             port: 8080,
             security_monitor: crate::proxy::config::SecurityMonitorConfig::default(),
+            cidr_rules: CidrRules::default(),
+            cors: CorsConfig::default(),
         };
         assert!(matches!(s.effective_auth_mode(), ProxyAuthMode::Off));
     }
@@ -58,15 +242,184 @@ mod tests {
         let s = ProxySecurityConfig {
             auth_mode: ProxyAuthMode::Auto,
             api_key: "sk-test".to_string(),
+            api_key_previous: None,
+            api_key_previous_expires_at: None,
+            api_keys: Vec::new(),
             admin_password: None,
+            jwt_expiry_seconds: 3600,
+            admin_session_secret: String::new(),
             allow_lan_access: true,
             port: 8080,
             security_monitor: crate::proxy::config::SecurityMonitorConfig::default(),
+            cidr_rules: CidrRules::default(),
+            cors: CorsConfig::default(),
         };
         assert!(matches!(
             s.effective_auth_mode(),
             ProxyAuthMode::AllExceptHealth
         ));
     }
-}
 
+    #[test]
+    fn previous_api_key_accepted_until_expiry() {
+        let now = chrono::Utc::now().timestamp();
+        let mut config = ProxyConfig::default();
+        config.api_key = "sk-new".to_string();
+        config.api_key_previous = Some("sk-old".to_string());
+        config.api_key_previous_expires_at = Some(now + 60);
+
+        let s = ProxySecurityConfig::from_proxy_config(&config);
+        assert!(s.accepts_api_key("sk-new"));
+        assert!(s.accepts_api_key("sk-old"));
+        assert!(!s.accepts_api_key("sk-other"));
+
+        config.api_key_previous_expires_at = Some(now - 1);
+        let s = ProxySecurityConfig::from_proxy_config(&config);
+        assert!(!s.accepts_api_key("sk-old"));
+        assert!(s.previous_api_key().is_none());
+    }
+
+    #[test]
+    fn labeled_keys_accepted_unless_disabled() {
+        let mut config = ProxyConfig::default();
+        config.api_keys = vec![
+            ApiKeyEntry {
+                key: "sk-alice".to_string(),
+                label: "alice".to_string(),
+                disabled: false,
+            },
+            ApiKeyEntry {
+                key: "sk-bob".to_string(),
+                label: "bob".to_string(),
+                disabled: true,
+            },
+        ];
+        let s = ProxySecurityConfig::from_proxy_config(&config);
+        assert_eq!(s.labeled_key("sk-alice"), Some("alice"));
+        assert_eq!(s.labeled_key("sk-bob"), None);
+        assert!(s.has_labeled_keys());
+        // 附加 Key 不等同于主 Key (管理接口回退时不接受)
+        assert!(!s.accepts_api_key("sk-alice"));
+    }
+
+    #[test]
+    fn cidr_rules_parse_and_match() {
+        let cfg = SecurityMonitorConfig {
+            blocked_cidrs: vec![
+                "10.0.0.0/8".to_string(),
+                "203.0.113.7".to_string(),
+                "not-a-cidr".to_string(),
+                "2001:db8::/32".to_string(),
+            ],
+            allowed_cidrs: vec!["192.168.1.0/24".to_string()],
+            ..Default::default()
+        };
+        let rules = CidrRules::from_monitor_config(&cfg);
+
+        // 非法条目被跳过而不是报错
+        assert_eq!(rules.blocked.len(), 3);
+        assert_eq!(rules.allowed.len(), 1);
+
+        assert_eq!(rules.matching_blocked("10.1.2.3".parse().unwrap()).len(), 1);
+        assert_eq!(rules.matching_blocked("203.0.113.7".parse().unwrap()).len(), 1);
+        assert!(rules.matching_blocked("203.0.113.8".parse().unwrap()).is_empty());
+        assert_eq!(rules.matching_blocked("2001:db8::1".parse().unwrap()).len(), 1);
+        assert_eq!(rules.matching_allowed("192.168.1.200".parse().unwrap()).len(), 1);
+        assert!(rules.matching_allowed("192.168.2.1".parse().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn invalid_cidrs_rejected_at_config_load() {
+        let cfg = SecurityMonitorConfig {
+            blocked_cidrs: vec!["10.0.0.0/8".to_string(), "10.0.0.0/33".to_string()],
+            allowed_cidrs: vec!["fd00::/8".to_string(), "bogus".to_string()],
+            ..Default::default()
+        };
+
+        let err = validate_monitor_cidrs(&cfg).unwrap_err();
+        assert!(err.contains("10.0.0.0/33"));
+        assert!(err.contains("bogus"));
+
+        let valid = SecurityMonitorConfig {
+            blocked_cidrs: vec!["10.0.0.0/8".to_string()],
+            allowed_cidrs: vec!["fd00::/8".to_string()],
+            ..Default::default()
+        };
+        assert!(validate_monitor_cidrs(&valid).is_ok());
+    }
+
+    #[test]
+    fn exact_ip_is_single_host_network() {
+        assert_eq!(parse_cidr("8.8.8.8").unwrap().prefix_len(), 32);
+        assert_eq!(parse_cidr("2001:db8::1").unwrap().prefix_len(), 128);
+        assert!(parse_cidr("2001:db8::/129").is_none());
+    }
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (k, v) in pairs {
+            map.insert(
+                axum::http::HeaderName::from_bytes(k.as_bytes()).unwrap(),
+                v.parse().unwrap(),
+            );
+        }
+        map
+    }
+
+    #[test]
+    fn client_ip_legacy_behavior_without_trusted_proxies() {
+        let rules = CidrRules::default();
+        let h = headers(&[("x-forwarded-for", "1.2.3.4, 10.0.0.1")]);
+        assert_eq!(
+            resolve_client_ip(&h, Some("127.0.0.1".parse().unwrap()), &rules).as_deref(),
+            Some("1.2.3.4")
+        );
+        assert_eq!(
+            resolve_client_ip(&HeaderMap::new(), Some("127.0.0.1".parse().unwrap()), &rules)
+                .as_deref(),
+            Some("127.0.0.1")
+        );
+    }
+
+    #[test]
+    fn client_ip_from_trusted_proxy_uses_rightmost_untrusted_hop() {
+        let rules = CidrRules::from_monitor_config(&SecurityMonitorConfig {
+            trusted_proxies: vec!["10.0.0.0/8".to_string(), "::1".to_string()],
+            ..Default::default()
+        });
+        let peer = Some("10.0.0.5".parse().unwrap());
+
+        // 最左侧为客户端伪造值，应取最右侧的非受信跳
+        let h = headers(&[("x-forwarded-for", "6.6.6.6, 203.0.113.9, 10.0.0.2")]);
+        assert_eq!(resolve_client_ip(&h, peer, &rules).as_deref(), Some("203.0.113.9"));
+
+        let h = headers(&[
+            ("cf-connecting-ip", "198.51.100.7"),
+            ("x-forwarded-for", "203.0.113.9"),
+        ]);
+        assert_eq!(resolve_client_ip(&h, peer, &rules).as_deref(), Some("198.51.100.7"));
+
+        let h = headers(&[("x-forwarded-for", "2001:db8::7")]);
+        assert_eq!(
+            resolve_client_ip(&h, Some("::1".parse().unwrap()), &rules).as_deref(),
+            Some("2001:db8::7")
+        );
+    }
+
+    #[test]
+    fn client_ip_headers_ignored_from_untrusted_peer() {
+        let rules = CidrRules::from_monitor_config(&SecurityMonitorConfig {
+            trusted_proxies: vec!["10.0.0.0/8".to_string()],
+            ..Default::default()
+        });
+        let h = headers(&[
+            ("x-forwarded-for", "192.168.1.1"),
+            ("cf-connecting-ip", "192.168.1.1"),
+        ]);
+        assert_eq!(
+            resolve_client_ip(&h, Some("203.0.113.50".parse().unwrap()), &rules).as_deref(),
+            Some("203.0.113.50")
+        );
+        assert_eq!(resolve_client_ip(&h, None, &rules), None);
+    }
+}
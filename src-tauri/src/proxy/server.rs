@@ -1,11 +1,13 @@
 use crate::models::AppConfig;
-use crate::modules::{account, config, logger, migration, proxy_db, security_db, token_stats};
+use crate::modules::{account, audit_log, config, logger, migration, proxy_db, security_db, token_stats};
+use crate::proxy::error::{ApiError, ApiErrorCode};
+use crate::proxy::middleware::AdminActor;
 use crate::proxy::TokenManager;
 use axum::{
-    extract::{DefaultBodyLimit, Path, Query, State},
+    extract::{DefaultBodyLimit, Extension, Path, Query, State},
     http::{HeaderMap, StatusCode},
     response::{Html, IntoResponse, Json, Response},
-    routing::{any, delete, get, post},
+    routing::{any, delete, get, patch, post},
     Router,
 };
 use futures::TryFutureExt;
@@ -104,6 +106,7 @@ pub struct AppState {
     pub zai: Arc<RwLock<crate::proxy::ZaiConfig>>,
     pub provider_rr: Arc<AtomicUsize>,
     pub zai_vision_mcp: Arc<crate::proxy::zai_vision_mcp::ZaiVisionMcpState>,
+    pub mcp_aggregator: Arc<crate::proxy::mcp_aggregator::McpAggregatorState>, // [NEW] 聚合 MCP 端点状态
     pub monitor: Arc<crate::proxy::monitor::ProxyMonitor>,
     pub experimental: Arc<RwLock<crate::proxy::config::ExperimentalConfig>>,
     pub debug_logging: Arc<RwLock<crate::proxy::config::DebugLoggingConfig>>,
@@ -113,9 +116,16 @@ pub struct AppState {
     pub security: Arc<RwLock<crate::proxy::ProxySecurityConfig>>,              // [NEW] 安全配置状态
     pub cloudflared_state: Arc<crate::commands::cloudflared::CloudflaredState>, // [NEW] Cloudflared 插件状态
     pub is_running: Arc<RwLock<bool>>, // [NEW] 运行状态标识
+    pub maintenance_mode: Arc<RwLock<crate::proxy::middleware::service_status::MaintenanceState>>, // [NEW] 维护模式 (仅拦截 AI 路由)
     pub port: u16,                     // [NEW] 本地监听端口 (v4.0.8 修复)
+    pub bind_addresses: Arc<Vec<String>>, // [NEW] 全部监听地址 (含 unix socket)
     pub proxy_pool_state: Arc<tokio::sync::RwLock<crate::proxy::config::ProxyPoolConfig>>, // [FIX Web Mode]
     pub proxy_pool_manager: Arc<crate::proxy::proxy_pool::ProxyPoolManager>, // [FIX Web Mode]
+    pub webhooks: Arc<crate::proxy::webhook::WebhookDispatcher>, // [NEW] 账号事件 Webhook 通知
+    pub model_list_cache: Arc<crate::proxy::model_list_cache::ModelListCache>, // [NEW] 模型列表缓存
+    pub zai_health: Arc<crate::proxy::providers::zai_health::ZaiHealth>, // [NEW] z.ai 健康探测结果
+    pub config_apply: Arc<RwLock<()>>, // [NEW] 配置热更新期间持有写锁，新请求等待更新完成
+    pub shutdown_tx: Arc<tokio::sync::Mutex<Option<oneshot::Sender<std::time::Duration>>>>, // [NEW] 停止监听并排空连接 (携带排空超时)
 }
 
 // 为 AppState 实现 FromRef，以便中间件提取 security 状态
@@ -125,11 +135,6 @@ impl axum::extract::FromRef<AppState> for Arc<RwLock<crate::proxy::ProxySecurity
     }
 }
 
-#[derive(Serialize)]
-struct ErrorResponse {
-    error: String,
-}
-
 #[derive(Serialize)]
 struct AccountResponse {
     id: String,
@@ -150,6 +155,11 @@ struct AccountResponse {
     quota: Option<QuotaResponse>,
     device_bound: bool,
     last_used: i64,
+    tags: Vec<String>,
+    notes: Option<String>,
+    last_warmup: Option<crate::models::WarmupRecord>,
+    /// [NEW] 连续硬失败次数 (达到阈值后自动禁用反代)
+    consecutive_failures: u32,
 }
 
 #[derive(Serialize)]
@@ -169,6 +179,8 @@ struct ModelQuota {
 
 #[derive(Serialize)]
 struct AccountListResponse {
+    /// 筛选后 (分页前) 的账号总数
+    total: usize,
     accounts: Vec<AccountResponse>,
     current_account_id: Option<String>,
 }
@@ -177,6 +189,7 @@ use crate::models::{AccountExportItem, AccountExportResponse};
 fn to_account_response(
     account: &crate::models::account::Account,
     current_id: &Option<String>,
+    consecutive_failures: u32,
 ) -> AccountResponse {
     AccountResponse {
         id: account.id.clone(),
@@ -209,18 +222,23 @@ fn to_account_response(
         validation_blocked: account.validation_blocked,
         validation_blocked_until: account.validation_blocked_until,
         validation_blocked_reason: account.validation_blocked_reason.clone(),
+        tags: account.tags.clone(),
+        notes: account.notes.clone(),
+        last_warmup: account.last_warmup.clone(),
+        consecutive_failures,
     }
 }
 
 /// Axum 服务器实例
 #[derive(Clone)]
 pub struct AxumServer {
-    shutdown_tx: Arc<tokio::sync::Mutex<Option<oneshot::Sender<()>>>>,
+    shutdown_tx: Arc<tokio::sync::Mutex<Option<oneshot::Sender<std::time::Duration>>>>,
     custom_mapping: Arc<tokio::sync::RwLock<std::collections::HashMap<String, String>>>,
     proxy_state: Arc<tokio::sync::RwLock<crate::proxy::config::UpstreamProxyConfig>>,
     upstream: Arc<crate::proxy::upstream::client::UpstreamClient>,
     security_state: Arc<RwLock<crate::proxy::ProxySecurityConfig>>,
     zai_state: Arc<RwLock<crate::proxy::ZaiConfig>>,
+    zai_health: Arc<crate::proxy::providers::zai_health::ZaiHealth>,
     experimental: Arc<RwLock<crate::proxy::config::ExperimentalConfig>>,
     debug_logging: Arc<RwLock<crate::proxy::config::DebugLoggingConfig>>,
     pub cloudflared_state: Arc<crate::commands::cloudflared::CloudflaredState>,
@@ -228,19 +246,30 @@ pub struct AxumServer {
     pub token_manager: Arc<TokenManager>, // [NEW] 暴露出 TokenManager 供反代服务复用
     pub proxy_pool_state: Arc<tokio::sync::RwLock<crate::proxy::config::ProxyPoolConfig>>, // [NEW] 代理池配置状态
     pub proxy_pool_manager: Arc<crate::proxy::proxy_pool::ProxyPoolManager>, // [NEW] 暴露代理池管理器供命令调用
+    config_watcher: Option<Arc<crate::modules::config::FileWatcher>>, // [NEW] proxy.config_file_watch 开启时的配置文件监听
+    reload_signal: Option<tokio::task::AbortHandle>, // [NEW] SIGHUP 重新加载配置 (仅 unix)
+    provider_rr: Arc<AtomicUsize>,
+    state_persist: tokio::task::AbortHandle, // [NEW] 定期保存调度状态
 }
 
+/// 调度状态 (轮询游标与会话绑定) 的保存间隔
+const SCHEDULER_STATE_SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
 impl AxumServer {
     pub async fn update_mapping(&self, config: &crate::proxy::config::ProxyConfig) {
         {
             let mut m = self.custom_mapping.write().await;
             *m = config.custom_mapping.clone();
         }
+        crate::proxy::model_list_cache::ModelListCache::global().invalidate();
+        crate::proxy::response_cache::ResponseCache::global().invalidate();
         tracing::debug!("模型映射 (Custom) 已全量热更新");
     }
 
     /// 更新代理配置
     pub async fn update_proxy(&self, new_config: crate::proxy::config::UpstreamProxyConfig) {
+        // [NEW] 重建上游客户端 (原子替换，在途请求继续使用旧连接)
+        self.upstream.update_proxy_config(new_config.clone());
         let mut proxy = self.proxy_state.write().await;
         *proxy = new_config;
         tracing::info!("上游代理配置已热更新");
@@ -256,16 +285,28 @@ impl AxumServer {
     pub async fn update_security(&self, config: &crate::proxy::config::ProxyConfig) {
         let mut sec = self.security_state.write().await;
         *sec = crate::proxy::ProxySecurityConfig::from_proxy_config(config);
-        tracing::info!("反代服务安全配置已热更新");
+        tracing::info!("反代服务安全配置已热更新 (含 CORS 策略)");
     }
 
     pub async fn update_zai(&self, config: &crate::proxy::config::ProxyConfig) {
-        let mut zai = self.zai_state.write().await;
-        *zai = config.zai.clone();
+        {
+            let mut zai = self.zai_state.write().await;
+            *zai = config.zai.clone();
+        }
+        crate::proxy::response_cache::ResponseCache::global().invalidate();
         tracing::info!("z.ai 配置已热更新");
+
+        // [NEW] Key 或地址可能已修正，立即重新探测
+        let health = self.zai_health.clone();
+        let zai = config.zai.clone();
+        let upstream_proxy = self.proxy_state.read().await.clone();
+        tokio::spawn(async move {
+            health.probe(&zai, Some(upstream_proxy)).await;
+        });
     }
 
     pub async fn update_experimental(&self, config: &crate::proxy::config::ProxyConfig) {
+        apply_signature_persistence(config.experimental.clone()).await;
         let mut exp = self.experimental.write().await;
         *exp = config.experimental.clone();
         tracing::info!("实验性配置已热更新");
@@ -281,7 +322,14 @@ impl AxumServer {
         self.upstream
             .set_user_agent_override(config.user_agent_override.clone())
             .await;
-        tracing::info!("User-Agent 配置已热更新: {:?}", config.user_agent_override);
+        self.upstream
+            .set_user_agent_pool(config.user_agent_pool.clone())
+            .await;
+        tracing::info!(
+            "User-Agent 配置已热更新: {:?} (轮换池 {} 个)",
+            config.user_agent_override,
+            config.user_agent_pool.len()
+        );
     }
 
     pub async fn set_running(&self, running: bool) {
@@ -294,11 +342,13 @@ impl AxumServer {
     pub async fn start(
         host: String,
         port: u16,
+        bind_addresses: Vec<String>,
         token_manager: Arc<TokenManager>,
         custom_mapping: std::collections::HashMap<String, String>,
-        _request_timeout: u64,
+        request_timeout: u64,
         upstream_proxy: crate::proxy::config::UpstreamProxyConfig,
         user_agent_override: Option<String>,
+        user_agent_pool: Vec<String>,
         security_config: crate::proxy::ProxySecurityConfig,
         zai_config: crate::proxy::ZaiConfig,
         monitor: Arc<crate::proxy::monitor::ProxyMonitor>,
@@ -308,7 +358,13 @@ impl AxumServer {
         integration: crate::modules::integration::SystemManager,
         cloudflared_state: Arc<crate::commands::cloudflared::CloudflaredState>,
         proxy_pool_config: crate::proxy::config::ProxyPoolConfig, // [NEW]
+        config_file_watch: bool,
     ) -> Result<(Self, tokio::task::JoinHandle<()>), String> {
+        // bind_addresses 为空时沿用 host (由 allow_lan_access 决定)
+        let bind_targets =
+            crate::proxy::listener::resolve_bind_targets(&bind_addresses, &host, port)?;
+        let bound_addresses: Arc<Vec<String>> =
+            Arc::new(bind_targets.iter().map(|t| t.to_string()).collect());
         let custom_mapping_state = Arc::new(tokio::sync::RwLock::new(custom_mapping));
         let proxy_state = Arc::new(tokio::sync::RwLock::new(upstream_proxy.clone()));
         let proxy_pool_state = Arc::new(tokio::sync::RwLock::new(proxy_pool_config));
@@ -316,18 +372,38 @@ impl AxumServer {
     
     // Start health check loop
     proxy_pool_manager.clone().start_health_check_loop();
+        // 重启前处于 API Key 轮换宽限期时，继续在到期后清除旧 Key
+        let previous_key_expiry = security_config
+            .api_key_previous
+            .as_ref()
+            .and(security_config.api_key_previous_expires_at);
         let security_state = Arc::new(RwLock::new(security_config));
+        if let Some(expires_at) = previous_key_expiry {
+            crate::proxy::api_key_rotation::schedule_expiry(security_state.clone(), expires_at);
+        }
         let zai_state = Arc::new(RwLock::new(zai_config));
         let provider_rr = Arc::new(AtomicUsize::new(0));
+        // [NEW] 恢复上次运行的调度状态，避免重启后总是先命中同一个账号
+        match token_manager.load_state().await {
+            Ok(Some(rr)) => provider_rr.store(rr, std::sync::atomic::Ordering::Relaxed),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to restore scheduler state: {}", e),
+        }
         let zai_vision_mcp_state = Arc::new(crate::proxy::zai_vision_mcp::ZaiVisionMcpState::new());
+        crate::proxy::signature_cache::apply_persistence_config(&experimental_config);
         let experimental_state = Arc::new(RwLock::new(experimental_config));
         let debug_logging_state = Arc::new(RwLock::new(debug_logging));
         let is_running_state = Arc::new(RwLock::new(true));
 
+        // 创建关闭通道
+        // 停止信号携带排空超时
+        let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<std::time::Duration>();
+        let shutdown_tx = Arc::new(tokio::sync::Mutex::new(Some(shutdown_tx)));
+
         let state = AppState {
             token_manager: token_manager.clone(),
             custom_mapping: custom_mapping_state.clone(),
-            request_timeout: 300, // 5分钟超时
+            request_timeout, // 全局默认超时 (按模型覆盖见 timeouts 模块)
             thought_signature_map: Arc::new(tokio::sync::Mutex::new(
                 std::collections::HashMap::new(),
             )),
@@ -341,11 +417,15 @@ impl AxumServer {
                 if user_agent_override.is_some() {
                     u.set_user_agent_override(user_agent_override).await;
                 }
+                if !user_agent_pool.is_empty() {
+                    u.set_user_agent_pool(user_agent_pool).await;
+                }
                 u
             },
             zai: zai_state.clone(),
             provider_rr: provider_rr.clone(),
             zai_vision_mcp: zai_vision_mcp_state,
+            mcp_aggregator: Arc::new(crate::proxy::mcp_aggregator::McpAggregatorState::new()),
             monitor: monitor.clone(),
             experimental: experimental_state.clone(),
             debug_logging: debug_logging_state.clone(),
@@ -357,16 +437,32 @@ impl AxumServer {
             security: security_state.clone(),
             cloudflared_state: cloudflared_state.clone(),
             is_running: is_running_state.clone(),
+            maintenance_mode: Arc::new(RwLock::new(Default::default())),
+            config_apply: Arc::new(RwLock::new(())),
+            shutdown_tx: shutdown_tx.clone(),
             port,
+            bind_addresses: bound_addresses.clone(),
             proxy_pool_state: proxy_pool_state.clone(),
             proxy_pool_manager: proxy_pool_manager.clone(),
+            webhooks: crate::proxy::webhook::WebhookDispatcher::global(),
+            model_list_cache: crate::proxy::model_list_cache::ModelListCache::global(),
+            zai_health: Arc::new(crate::proxy::providers::zai_health::ZaiHealth::new()),
         };
 
+        // [NEW] z.ai 后台健康探测 (启动时立即执行一次)
+        crate::proxy::providers::zai_health::spawn_probe_loop(
+            state.zai_health.clone(),
+            Arc::downgrade(&zai_state),
+            proxy_state.clone(),
+        );
+
         // 构建路由 - 使用新架构的 handlers！
         use crate::proxy::handlers;
         use crate::proxy::middleware::{
-            admin_auth_middleware, auth_middleware, cors_layer, ip_filter_middleware,
-            monitor_middleware, service_status_middleware,
+            account_concurrency_middleware, admin_auth_middleware, admin_role_middleware, auth_middleware, budget_middleware,
+            access_log_middleware, cors_middleware, error_template_middleware, ip_filter_middleware,
+            monitor_middleware,
+            response_header_injection_middleware, service_status_middleware, transform_middleware,
         };
 
         // 1. 构建主 AI 代理路由 (遵循 auth_mode 配置)
@@ -379,11 +475,23 @@ impl AxumServer {
                 "/v1/chat/completions",
                 post(handlers::openai::handle_chat_completions),
             )
+            .route(
+                "/v1/chat/completions/estimate",
+                post(handlers::openai::handle_chat_completions_estimate),
+            ) // Dry-run token 估算
+            .route(
+                "/v1/chat/completions/count_tokens",
+                post(handlers::openai::handle_chat_completions_count_tokens),
+            ) // 上游 countTokens (含系统提示词与工具)
             .route(
                 "/v1/completions",
                 post(handlers::openai::handle_completions),
             )
             .route("/v1/responses", post(handlers::openai::handle_completions)) // 兼容 Codex CLI
+            .route(
+                "/v1/embeddings",
+                post(handlers::openai::handle_embeddings),
+            ) // 向量 API
             .route(
                 "/v1/images/generations",
                 post(handlers::openai::handle_images_generations),
@@ -416,6 +524,11 @@ impl AxumServer {
                 "/mcp/zai-mcp-server/mcp",
                 any(handlers::mcp::handle_zai_mcp_server),
             )
+            // [NEW] 聚合 MCP: 一个端点提供 z.ai 远程工具、Vision 工具与本地账号/配额工具
+            .route(
+                "/mcp/abv/mcp",
+                any(handlers::mcp::handle_abv_mcp),
+            )
             // Gemini Protocol (Native)
             .route("/v1beta/models", get(handlers::gemini::handle_list_models))
             // Handle both GET (get info) and POST (generateContent with colon) at the same route
@@ -436,13 +549,29 @@ impl AxumServer {
             .route("/v1/api/event_logging", post(silent_ok_handler))
             // 应用 AI 服务特定的层
             // 注意：Axum layer 执行顺序是从下往上（洋葱模型）
-            // 请求: ip_filter -> auth -> monitor -> handler
-            // 响应: handler -> monitor -> auth -> ip_filter
-            // monitor 需要在 auth 之后执行才能获取 UserTokenIdentity
+            // 请求: ip_filter -> auth -> headers -> error_templates -> monitor -> budget -> concurrency -> transforms -> handler
+            // 响应: handler -> transforms -> concurrency -> budget -> monitor -> error_templates -> headers -> auth -> ip_filter (之后才是全局 CORS)
+            // monitor 需要在 auth 之后执行才能获取 UserTokenIdentity；error_templates 在 monitor 之外，日志保留上游原始错误
+            .layer(axum::middleware::from_fn(transform_middleware))
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                account_concurrency_middleware,
+            ))
+            .layer(axum::middleware::from_fn(budget_middleware))
             .layer(axum::middleware::from_fn_with_state(
                 state.clone(),
                 monitor_middleware,
             ))
+            .layer(axum::middleware::from_fn_with_state(
+                state.token_manager.clone(),
+                error_template_middleware,
+            ))
+            // [NEW] 自定义响应头注入 (仅反代路由，管理 API 不受影响)
+            // [FIX] 位于 auth 之内: 鉴权 / IP 过滤拒绝的响应不携带自定义头
+            .layer(axum::middleware::from_fn_with_state(
+                state.token_manager.clone(),
+                response_header_injection_middleware,
+            ))
             .layer(axum::middleware::from_fn_with_state(
                 state.clone(),
                 auth_middleware,
@@ -450,11 +579,18 @@ impl AxumServer {
             .layer(axum::middleware::from_fn_with_state(
                 state.clone(),
                 ip_filter_middleware,
+            ))
+            // [NEW] Combined 格式访问日志 (最外层，记录包括鉴权失败在内的所有反代请求)
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                access_log_middleware,
             ));
 
         // 2. 构建管理 API (强制鉴权)
         let admin_routes = Router::new()
             .route("/health", get(health_check_handler))
+            .route("/errors", get(admin_list_error_codes))
+            .route("/budget/status", get(admin_get_budget_status))
             .route(
                 "/accounts",
                 get(admin_list_accounts).post(admin_add_account),
@@ -462,8 +598,20 @@ impl AxumServer {
             .route("/accounts/current", get(admin_get_current_account))
             .route("/accounts/switch", post(admin_switch_account))
             .route("/accounts/refresh", post(admin_refresh_all_quotas))
-            .route("/accounts/:accountId", delete(admin_delete_account))
+            .route("/accounts/health-check", post(admin_health_check_accounts))
+            .route("/accounts/idle-check", post(admin_idle_check_accounts))
+            .route("/accounts/tags", get(admin_list_account_tags))
+            .route("/accounts/:accountId/tags", post(admin_update_account_tags))
+            .route("/accounts/:accountId/notes", patch(admin_update_account_notes))
+            .route(
+                "/accounts/:accountId",
+                delete(admin_delete_account).patch(admin_patch_account),
+            )
             .route("/accounts/:accountId/bind-device", post(admin_bind_device))
+            .route(
+                "/accounts/bind-device/bulk",
+                get(admin_get_bulk_bind_progress).post(admin_bulk_bind_device),
+            )
             .route(
                 "/accounts/:accountId/device-profiles",
                 get(admin_get_device_profiles),
@@ -502,10 +650,19 @@ impl AxumServer {
             .route("/stats/weekly", get(admin_get_token_stats_weekly))
             .route("/stats/accounts", get(admin_get_token_stats_by_account))
             .route("/stats/models", get(admin_get_token_stats_by_model))
+            .route("/stats/forecast", get(admin_get_quota_forecast))
+            .route("/stats/failovers", get(admin_get_failover_events))
             .route("/config", get(admin_get_config).post(admin_save_config))
+            .route("/config/reload", post(admin_reload_config))
+            .route("/config/schema", get(admin_get_config_schema))
+            .route("/config/validate", post(admin_validate_config))
+            .route("/audit-log", get(admin_get_audit_log))
+            .route("/audit-log/export", post(admin_export_audit_log))
             .route("/proxy/cli/status", post(admin_get_cli_sync_status))
             .route("/proxy/cli/sync", post(admin_execute_cli_sync))
             .route("/proxy/cli/restore", post(admin_execute_cli_restore))
+            .route("/proxy/cli/sync-all", post(admin_execute_cli_sync_all))
+            .route("/proxy/cli/restore-all", post(admin_execute_cli_restore_all))
             .route("/proxy/cli/config", post(admin_get_cli_config_content))
             .route("/proxy/opencode/status", post(admin_get_opencode_sync_status))
             .route("/proxy/opencode/sync", post(admin_execute_opencode_sync))
@@ -524,8 +681,20 @@ impl AxumServer {
             .route("/proxy/health-check/trigger", post(admin_trigger_proxy_health_check))
             .route("/proxy/start", post(admin_start_proxy_service))
             .route("/proxy/stop", post(admin_stop_proxy_service))
+            .route("/proxy/maintenance", post(admin_set_maintenance_mode))
             .route("/proxy/mapping", post(admin_update_model_mapping))
+            .route("/proxy/mapping/test", post(admin_test_model_mapping))
             .route("/proxy/api-key/generate", post(admin_generate_api_key))
+            .route("/proxy/api-key/rotate", post(admin_rotate_api_key))
+            .route("/proxy/keys", get(admin_list_api_keys).post(admin_create_api_key))
+            .route(
+                "/proxy/keys/:label",
+                patch(admin_update_api_key).delete(admin_delete_api_key),
+            )
+            .route(
+                "/proxy/api-key/rotation-status",
+                get(admin_get_api_key_rotation_status),
+            )
             .route(
                 "/proxy/session-bindings/clear",
                 post(admin_clear_proxy_session_bindings),
@@ -545,6 +714,7 @@ impl AxumServer {
             .route("/accounts/oauth/cancel", post(admin_cancel_oauth_login))
             .route("/accounts/oauth/submit-code", post(admin_submit_oauth_code))
             .route("/zai/models/fetch", post(admin_fetch_zai_models))
+            .route("/zai/probe", post(admin_probe_zai))
             .route(
                 "/proxy/monitor/toggle",
                 post(admin_set_proxy_monitor_enabled),
@@ -559,8 +729,16 @@ impl AxumServer {
             )
             .route("/proxy/cloudflared/start", post(admin_cloudflared_start))
             .route("/proxy/cloudflared/stop", post(admin_cloudflared_stop))
+            .route("/proxy/cloudflared/logs", get(admin_cloudflared_get_logs))
             .route("/system/open-folder", post(admin_open_folder))
             .route("/proxy/stats", get(admin_get_proxy_stats))
+            .route("/proxy/active-requests", get(admin_get_active_requests))
+            .route(
+                "/proxy/active-requests/:requestId/cancel",
+                post(admin_cancel_active_request),
+            )
+            .route("/webhooks/test", post(admin_test_webhooks))
+            .route("/proxy/models/refresh", post(admin_refresh_model_list))
             .route("/logs", get(admin_get_proxy_logs_filtered))
             .route("/logs/count", get(admin_get_proxy_logs_count_filtered))
             .route("/logs/clear", post(admin_clear_proxy_logs))
@@ -571,6 +749,7 @@ impl AxumServer {
             .route("/debug/enabled", get(admin_is_debug_console_enabled))
             .route("/debug/logs", get(admin_get_debug_console_logs))
             .route("/debug/logs/clear", post(admin_clear_debug_console_logs))
+            .route("/debug/captures", get(admin_list_debug_captures))
             .route("/stats/token/clear", post(admin_clear_token_stats))
             .route("/stats/token/hourly", get(admin_get_token_stats_hourly))
             .route("/stats/token/daily", get(admin_get_token_stats_daily))
@@ -580,6 +759,7 @@ impl AxumServer {
                 get(admin_get_token_stats_by_account),
             )
             .route("/stats/token/summary", get(admin_get_token_stats_summary))
+            .route("/stats/token/export", get(admin_export_token_stats))
             .route("/stats/token/by-model", get(admin_get_token_stats_by_model))
             .route(
                 "/stats/token/model-trend/hourly",
@@ -605,9 +785,23 @@ impl AxumServer {
                 "/accounts/:accountId/toggle-proxy",
                 post(admin_toggle_proxy_status),
             )
+            .route(
+                "/accounts/:accountId/proxy-history",
+                get(admin_get_proxy_disable_history),
+            )
             .route("/accounts/warmup", post(admin_warm_up_all_accounts))
+            .route(
+                "/accounts/warmup/schedule",
+                get(admin_get_warmup_schedule).post(admin_update_warmup_schedule),
+            )
             .route("/accounts/:accountId/warmup", post(admin_warm_up_account))
+            .route("/accounts/:accountId/test", post(admin_test_account))
             .route("/system/data-dir", get(admin_get_data_dir_path))
+            .route(
+                "/system/backups",
+                get(admin_list_backups).post(admin_create_backup),
+            )
+            .route("/system/backups/:name/restore", post(admin_restore_backup))
             .route("/system/updates/settings", get(admin_get_update_settings))
             .route(
                 "/system/updates/check-status",
@@ -645,6 +839,7 @@ impl AxumServer {
             .route("/security/whitelist/clear", post(admin_clear_ip_whitelist))
             .route("/security/whitelist/check", get(admin_check_ip_in_whitelist))
             .route("/security/config", get(admin_get_security_config).post(admin_update_security_config))
+            .route("/security/test-ip", post(admin_test_ip_rules))
             // User Tokens
             .route("/user-tokens", get(admin_list_user_tokens).post(admin_create_user_token))
             .route("/user-tokens/summary", get(admin_get_user_token_summary))
@@ -652,6 +847,20 @@ impl AxumServer {
             .route("/user-tokens/:id", delete(admin_delete_user_token).patch(admin_update_user_token))
             // OAuth (Web) - Admin 接口
             .route("/auth/url", get(admin_prepare_oauth_url_web))
+            .route("/auth/token/revoke", post(admin_revoke_token))
+            // [NEW] 管理用户: 自助接口 (所有角色) 与用户管理 (仅 admin)
+            .route("/auth/me", get(admin_get_current_user))
+            .route("/auth/password", post(admin_change_own_password))
+            .route("/auth/logout", post(admin_logout))
+            .route("/admin/users", get(admin_list_admin_users).post(admin_create_admin_user))
+            .route(
+                "/admin/users/:username",
+                patch(admin_update_admin_user).delete(admin_delete_admin_user),
+            )
+            .route("/admin/users/:username/password", post(admin_reset_admin_user_password))
+            .route("/admin/users/:username/sessions/revoke", post(admin_revoke_admin_user_sessions))
+            // [NEW] 管理用户角色校验 (内层，依赖鉴权层注入的会话)
+            .layer(axum::middleware::from_fn(admin_role_middleware))
             // 应用管理特定鉴权层 (强制校验)
             .layer(axum::middleware::from_fn_with_state(
                 state.clone(),
@@ -671,12 +880,19 @@ impl AxumServer {
             .merge(proxy_routes)
             // 公开路由 (无需鉴权)
             .route("/auth/callback", get(handle_oauth_callback))
+            // [NEW] 以管理密码换取短期 JWT (自身校验密码，不经过 admin_auth_middleware)
+            .route("/api/auth/token", post(admin_issue_token))
+            // [NEW] 管理用户登录 (用户名 + 密码换取会话令牌)
+            .route("/api/auth/login", post(admin_login))
             // 应用全局监控与状态层 (外层)
             .layer(axum::middleware::from_fn_with_state(
                 state.clone(),
                 service_status_middleware,
             ))
-            .layer(cors_layer())
+            .layer(axum::middleware::from_fn_with_state(
+                security_state.clone(),
+                cors_middleware,
+            ))
             .layer(DefaultBodyLimit::max(max_body_size)) // 放宽 body 大小限制
             .with_state(state.clone());
 
@@ -691,24 +907,59 @@ impl AxumServer {
             app
         };
 
-        // 绑定地址
-        let addr = format!("{}:{}", host, port);
-        let listener = tokio::net::TcpListener::bind(&addr)
-            .await
-            .map_err(|e| format!("地址 {} 绑定失败: {}", addr, e))?;
+        // 绑定地址 (任一地址失败则启动失败)
+        let listeners = crate::proxy::listener::bind_all(&bind_targets).await?;
+        for target in &bind_targets {
+            match target {
+                crate::proxy::listener::BindTarget::Tcp(addr) => {
+                    tracing::info!("反代服务器启动在 http://{}", addr)
+                }
+                crate::proxy::listener::BindTarget::Unix(_) => {
+                    tracing::info!("反代服务器启动在 {}", target)
+                }
+            }
+        }
+
+        // [NEW] 监听配置文件变更，走与 admin_save_config 相同的校验与热更新路径
+        let config_watcher = if config_file_watch {
+            let watch_state = state.clone();
+            let watcher = crate::modules::config::config_file_path().and_then(|path| {
+                crate::modules::config::FileWatcher::start(path, move |new_config| {
+                    let state = watch_state.clone();
+                    async move {
+                        if let Err(e) = validate_app_config(&new_config) {
+                            tracing::warn!("[Config-Watch] 配置文件校验失败，继续使用当前配置: {}", e);
+                            return;
+                        }
+                        apply_app_config(&state, &new_config).await;
+                        tracing::info!("[Config-Watch] 配置文件变更已热更新");
+                    }
+                })
+            });
+            match watcher {
+                Ok(w) => Some(Arc::new(w)),
+                Err(e) => {
+                    tracing::warn!("[Config-Watch] 无法监听配置文件: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
 
-        tracing::info!("反代服务器启动在 http://{}", addr);
+        // [NEW] SIGHUP: 从磁盘重新加载配置并热更新
+        let reload_signal = spawn_sighup_reload(state.clone());
 
-        // 创建关闭通道
-        let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>();
+        let state_persist = spawn_scheduler_state_persist(token_manager.clone(), provider_rr.clone());
 
         let server_instance = Self {
-            shutdown_tx: Arc::new(tokio::sync::Mutex::new(Some(shutdown_tx))),
+            shutdown_tx,
             custom_mapping: custom_mapping_state.clone(),
             proxy_state,
             upstream: state.upstream.clone(),
             security_state,
             zai_state,
+            zai_health: state.zai_health.clone(),
             experimental: experimental_state.clone(),
             debug_logging: debug_logging_state.clone(),
             cloudflared_state,
@@ -716,68 +967,252 @@ impl AxumServer {
             token_manager: token_manager.clone(),
             proxy_pool_state,
             proxy_pool_manager,
+            config_watcher,
+            reload_signal,
+            provider_rr,
+            state_persist,
         };
 
         // 在新任务中启动服务器
         let handle = tokio::spawn(async move {
-            use hyper::server::conn::http1;
-            use hyper_util::rt::TokioIo;
-            use hyper_util::service::TowerToHyperService;
+            // [NEW] 活跃连接集合 + 排空信号，用于优雅停机
+            let mut connections = tokio::task::JoinSet::new();
+            let (drain_tx, drain_rx) = tokio::sync::watch::channel(false);
+
+            // 每个监听地址一个 accept 循环，共享同一个 Router；接收到的连接交给主循环统一管理
+            let (conn_tx, mut conn_rx) = tokio::sync::mpsc::channel::<ConnectionFuture>(256);
+            let (stop_accept_tx, stop_accept_rx) = tokio::sync::watch::channel(false);
+            let mut accept_loops = tokio::task::JoinSet::new();
+            for listener in listeners {
+                accept_loops.spawn(accept_loop(
+                    listener,
+                    app.clone(),
+                    conn_tx.clone(),
+                    drain_rx.clone(),
+                    stop_accept_rx.clone(),
+                ));
+            }
+            drop(conn_tx);
 
-            loop {
+            let grace_period = loop {
                 tokio::select! {
-                    res = listener.accept() => {
-                        match res {
-                            Ok((stream, remote_addr)) => {
-                                let io = TokioIo::new(stream);
-                                
-                                // 注入 ConnectInfo (用于获取真实 IP)
-                                use tower::ServiceExt;
-                                use hyper::body::Incoming;
-                                let app_with_info = app.clone().map_request(move |mut req: axum::http::Request<Incoming>| {
-                                    req.extensions_mut().insert(axum::extract::ConnectInfo(remote_addr));
-                                    req
-                                });
-
-                                let service = TowerToHyperService::new(app_with_info);
-
-                                tokio::task::spawn(async move {
-                                    if let Err(err) = http1::Builder::new()
-                                        .serve_connection(io, service)
-                                        .with_upgrades() // 支持 WebSocket (如果以后需要)
-                                        .await
-                                    {
-                                        debug!("连接处理结束或出错: {:?}", err);
-                                    }
-                                });
-                            }
-                            Err(e) => {
-                                error!("接收连接失败: {:?}", e);
-                            }
-                        }
+                    Some(conn) = conn_rx.recv() => {
+                        connections.spawn(conn);
                     }
-                    _ = &mut shutdown_rx => {
+                    // 回收已结束的连接任务，保持活跃连接计数准确
+                    Some(_) = connections.join_next(), if !connections.is_empty() => {}
+                    drain_timeout = &mut shutdown_rx => {
                         tracing::info!("反代服务器停止监听");
-                        break;
+                        // 发送端被丢弃 (未调用 stop) 时不等待
+                        break drain_timeout.unwrap_or_default();
                     }
                 }
+            };
+
+            // 关闭监听端口 (同时删除 unix socket 文件)，排空存量连接
+            let _ = stop_accept_tx.send(true);
+            while accept_loops.join_next().await.is_some() {}
+            while let Ok(conn) = conn_rx.try_recv() {
+                connections.spawn(conn);
             }
+            let active = connections.len();
+            if active == 0 {
+                return;
+            }
+            tracing::info!(
+                "等待 {} 个活跃连接完成 (宽限期 {}s)",
+                active,
+                grace_period.as_secs()
+            );
+            let _ = drain_tx.send(true);
+
+            let drained = tokio::time::timeout(grace_period, async {
+                while connections.join_next().await.is_some() {}
+            })
+            .await;
+
+            let forced = connections.len();
+            if drained.is_err() {
+                connections.abort_all();
+            }
+            tracing::info!(
+                "优雅停机完成: {} 个连接已排空, {} 个连接被强制关闭",
+                active - forced,
+                forced
+            );
         });
 
         Ok((server_instance, handle))
     }
 
-    /// 停止服务器
-    pub fn stop(&self) {
-        let tx_mutex = self.shutdown_tx.clone();
-        tokio::spawn(async move {
-            let mut lock = tx_mutex.lock().await;
-            if let Some(tx) = lock.take() {
-                let _ = tx.send(());
-                tracing::info!("Axum server 停止信号已发送");
+    /// 停止服务器: 立即停止监听，存量连接最多等待 drain_timeout 后强制关闭
+    pub async fn stop(&self, drain_timeout: std::time::Duration) {
+        if let Some(watcher) = &self.config_watcher {
+            watcher.stop();
+        }
+        if let Some(handle) = &self.reload_signal {
+            handle.abort();
+        }
+        self.state_persist.abort();
+        if let Err(e) = self
+            .token_manager
+            .save_state(self.provider_rr.load(std::sync::atomic::Ordering::Relaxed))
+        {
+            tracing::warn!("Failed to save scheduler state: {}", e);
+        }
+        let mut lock = self.shutdown_tx.lock().await;
+        if let Some(tx) = lock.take() {
+            let _ = tx.send(drain_timeout);
+            tracing::info!(
+                "Axum server 停止信号已发送 (排空超时 {}s)",
+                drain_timeout.as_secs()
+            );
+        }
+    }
+}
+
+/// 停机宽限期: 停止监听后等待存量请求完成的最长时间
+/// 环境变量 ABV_SHUTDOWN_GRACE_SECS 优先，否则使用配置的 proxy.shutdown_drain_seconds
+pub fn shutdown_grace_period(configured_secs: u64) -> std::time::Duration {
+    let secs = std::env::var("ABV_SHUTDOWN_GRACE_SECS")
+        .ok()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .unwrap_or(configured_secs);
+    std::time::Duration::from_secs(secs)
+}
+
+/// 定期保存调度状态，异常退出时最多丢失一个保存间隔内的变化
+fn spawn_scheduler_state_persist(
+    token_manager: Arc<TokenManager>,
+    provider_rr: Arc<AtomicUsize>,
+) -> tokio::task::AbortHandle {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SCHEDULER_STATE_SAVE_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let rr = provider_rr.load(std::sync::atomic::Ordering::Relaxed);
+            if let Err(e) = token_manager.save_state(rr) {
+                tracing::warn!("Failed to save scheduler state: {}", e);
             }
-        });
+        }
+    })
+    .abort_handle()
+}
+
+/// 收到 SIGHUP 时重新加载配置文件 (与 POST /api/config/reload 相同)
+#[cfg(unix)]
+fn spawn_sighup_reload(state: AppState) -> Option<tokio::task::AbortHandle> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(s) => s,
+        Err(e) => {
+            tracing::warn!("[Config-Reload] 无法注册 SIGHUP 处理: {}", e);
+            return None;
+        }
+    };
+    let handle = tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            tracing::info!("[Config-Reload] 收到 SIGHUP，重新加载配置文件");
+            if let Err(e) = reload_config_from_disk(&state).await {
+                tracing::warn!("[Config-Reload] 重新加载失败，继续使用当前配置: {}", e);
+            }
+        }
+    });
+    Some(handle.abort_handle())
+}
+
+#[cfg(not(unix))]
+fn spawn_sighup_reload(_state: AppState) -> Option<tokio::task::AbortHandle> {
+    None
+}
+
+type ConnectionFuture = std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>>;
+
+/// 构建单个连接的处理 future (TCP 与 unix socket 共用)
+fn serve_connection<I>(
+    io: I,
+    remote_addr: std::net::SocketAddr,
+    app: Router,
+    mut drain_rx: tokio::sync::watch::Receiver<bool>,
+) -> ConnectionFuture
+where
+    I: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    use hyper::body::Incoming;
+    use hyper::server::conn::http1;
+    use hyper_util::rt::TokioIo;
+    use hyper_util::service::TowerToHyperService;
+    use tower::ServiceExt;
+
+    // 注入 ConnectInfo (用于获取真实 IP)
+    let app_with_info = app.map_request(move |mut req: axum::http::Request<Incoming>| {
+        req.extensions_mut().insert(axum::extract::ConnectInfo(remote_addr));
+        req
+    });
+    let service = TowerToHyperService::new(app_with_info);
+
+    Box::pin(async move {
+        let conn = http1::Builder::new()
+            .serve_connection(TokioIo::new(io), service)
+            .with_upgrades(); // 支持 WebSocket (如果以后需要)
+        tokio::pin!(conn);
+
+        let result = tokio::select! {
+            res = conn.as_mut() => res,
+            _ = drain_rx.changed() => {
+                // 停机: 不再接受新请求，等待当前请求完成
+                conn.as_mut().graceful_shutdown();
+                conn.as_mut().await
+            }
+        };
+        if let Err(err) = result {
+            debug!("连接处理结束或出错: {:?}", err);
+        }
+    })
+}
+
+/// 单个监听地址的 accept 循环，收到停止信号后关闭监听 (unix socket 同时删除文件)
+async fn accept_loop(
+    listener: crate::proxy::listener::BoundListener,
+    app: Router,
+    conn_tx: tokio::sync::mpsc::Sender<ConnectionFuture>,
+    drain_rx: tokio::sync::watch::Receiver<bool>,
+    mut stop_rx: tokio::sync::watch::Receiver<bool>,
+) {
+    use crate::proxy::listener::BoundListener;
+
+    loop {
+        let conn = tokio::select! {
+            _ = stop_rx.changed() => break,
+            conn = async {
+                match &listener {
+                    BoundListener::Tcp(l) => l.accept().await.map(|(stream, remote_addr)| {
+                        serve_connection(stream, remote_addr, app.clone(), drain_rx.clone())
+                    }),
+                    // unix socket 无对端 IP，按本机连接处理 (前置 nginx 时依赖 trusted_proxies 读取真实 IP)
+                    #[cfg(unix)]
+                    BoundListener::Unix(l, _) => l.accept().await.map(|(stream, _)| {
+                        let local = std::net::SocketAddr::from(([127, 0, 0, 1], 0));
+                        serve_connection(stream, local, app.clone(), drain_rx.clone())
+                    }),
+                }
+            } => conn,
+        };
+        match conn {
+            Ok(conn) => {
+                if conn_tx.send(conn).await.is_err() {
+                    break;
+                }
+            }
+            Err(e) => {
+                error!("接收连接失败: {:?}", e);
+            }
+        }
     }
+
+    listener.cleanup();
 }
 
 // ===== API 处理器 (旧代码已移除，由 src/proxy/handlers/* 接管) =====
@@ -791,6 +1226,18 @@ async fn health_check_handler() -> Response {
     .into_response()
 }
 
+/// 列出所有机器可读错误码及其在各协议 envelope 中的映射
+async fn admin_list_error_codes() -> impl IntoResponse {
+    Json(serde_json::json!({
+        "codes": crate::proxy::error::error_catalog(),
+    }))
+}
+
+/// 每日 Token 预算消耗情况 (全局 + 各账号)
+async fn admin_get_budget_status() -> impl IntoResponse {
+    Json(crate::proxy::budget::BudgetTracker::global().status())
+}
+
 /// 静默成功处理器 (用于拦截遥测日志等)
 async fn silent_ok_handler() -> Response {
     StatusCode::OK.into_response()
@@ -802,15 +1249,47 @@ async fn silent_ok_handler() -> Response {
 
 // [整合清理] 旧模型定义与映射器已上移
 
+#[derive(Deserialize)]
+struct ListAccountsQuery {
+    tag: Option<String>,
+    /// 按邮箱或备注搜索 (大小写不敏感的子串匹配)
+    search: Option<String>,
+    disabled: Option<bool>,
+    proxy_disabled: Option<bool>,
+    /// active | disabled | forbidden
+    status: Option<crate::modules::account::AccountStatusFilter>,
+    sort: Option<crate::modules::account::AccountSortKey>,
+    order: Option<crate::modules::account::SortOrder>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
 async fn admin_list_accounts(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let accounts = state.account_service.list_accounts().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    Query(query): Query<ListAccountsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let search = query.search.as_deref().map(str::trim).filter(|s| !s.is_empty());
+    let accounts = match search {
+        // [NEW] 先在索引中匹配邮箱/备注，只加载命中的账号
+        Some(keyword) => state.account_service.search_accounts(keyword),
+        None => state.account_service.list_accounts(),
+    }
+    .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    // [NEW] 筛选 / 排序 / 分页在构建响应之前完成；未传参数时保持原顺序返回全部账号
+    let (total, accounts) = crate::modules::account::apply_list_options(
+        accounts,
+        &crate::modules::account::AccountListOptions {
+            sort: query.sort,
+            order: query.order,
+            disabled: query.disabled,
+            proxy_disabled: query.proxy_disabled,
+            status: query.status,
+            tag: query.tag,
+            limit: query.limit,
+            offset: query.offset,
+        },
+    );
 
     let current_id = state.account_service.get_current_id().ok().flatten();
 
@@ -851,11 +1330,16 @@ async fn admin_list_accounts(
                 quota,
                 device_bound: acc.device_profile.is_some(),
                 last_used: acc.last_used,
+                tags: acc.tags,
+                notes: acc.notes,
+                last_warmup: acc.last_warmup,
+                consecutive_failures: state.token_manager.consecutive_failures(&acc.id),
             }
         })
         .collect();
 
     Ok(Json(AccountListResponse {
+        total,
         current_account_id: current_id,
         accounts: account_responses,
     }))
@@ -870,27 +1354,29 @@ struct ExportAccountsRequest {
 
 async fn admin_export_accounts(
     State(_state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<ExportAccountsRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let response = account::export_accounts_by_ids(&payload.account_ids).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
-
+) -> Result<impl IntoResponse, ApiError> {
+    let response = account::export_accounts_by_ids(&payload.account_ids)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    audit_log::record(
+        "account.export",
+        &actor.ip,
+        None,
+        None,
+        Some(serde_json::json!({ "account_ids": payload.account_ids })),
+    );
     Ok(Json(response))
 }
 
 async fn admin_get_current_account(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let current_id = state.account_service.get_current_id().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+) -> Result<impl IntoResponse, ApiError> {
+    let current_id = state
+        .account_service
+        .get_current_id()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     let response = if let Some(id) = current_id {
         let acc = account::load_account(&id).ok();
@@ -928,6 +1414,10 @@ async fn admin_get_current_account(
                 quota,
                 device_bound: acc.device_profile.is_some(),
                 last_used: acc.last_used,
+                tags: acc.tags,
+                notes: acc.notes,
+                last_warmup: acc.last_warmup,
+                consecutive_failures: state.token_manager.consecutive_failures(&acc.id),
             }
         })
     } else {
@@ -945,18 +1435,21 @@ struct AddAccountRequest {
 
 async fn admin_add_account(
     State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<AddAccountRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let account = state
         .account_service
         .add_account(&payload.refresh_token)
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
+        .map_err(ApiError::from_account_error)?;
+    audit_log::record(
+        "account.add",
+        &actor.ip,
+        Some(&account.id),
+        None,
+        Some(serde_json::json!({ "email": account.email })),
+    );
 
     // [FIX #1166] 账号变动后立即重新加载 TokenManager
     if let Err(e) = state.token_manager.load_accounts().await {
@@ -966,28 +1459,30 @@ async fn admin_add_account(
         ));
     }
 
-    let current_id = state.account_service.get_current_id().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
-    Ok(Json(to_account_response(&account, &current_id)))
+    let current_id = state
+        .account_service
+        .get_current_id()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(to_account_response(&account, &current_id, state.token_manager.consecutive_failures(&account.id))))
 }
 
 async fn admin_delete_account(
     State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Path(account_id): Path<String>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
+    let email = crate::modules::load_account(&account_id).ok().map(|a| a.email);
     state
         .account_service
         .delete_account(&account_id)
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
+        .map_err(ApiError::from_account_error)?;
+    audit_log::record(
+        "account.delete",
+        &actor.ip,
+        Some(&account_id),
+        email.map(|email| serde_json::json!({ "email": email })),
+        None,
+    );
 
     // [FIX #1166] 账号变动后立即重新加载 TokenManager
     if let Err(e) = state.token_manager.load_accounts().await {
@@ -1008,16 +1503,15 @@ struct SwitchRequest {
 
 async fn admin_switch_account(
     State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<SwitchRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     {
         let switching = state.switching.read().await;
         if *switching {
-            return Err((
-                StatusCode::CONFLICT,
-                Json(ErrorResponse {
-                    error: "Another switch operation is already in progress".to_string(),
-                }),
+            return Err(ApiError::new(
+                ApiErrorCode::AccountSwitchInProgress,
+                "Another switch operation is already in progress",
             ));
         }
     }
@@ -1029,6 +1523,7 @@ async fn admin_switch_account(
 
     let account_id = payload.account_id.clone();
     logger::log_info(&format!("[API] Starting account switch: {}", account_id));
+    let previous_id = state.account_service.get_current_id().ok().flatten();
 
     let result = state.account_service.switch_account(&account_id).await;
 
@@ -1040,9 +1535,16 @@ async fn admin_switch_account(
     match result {
         Ok(()) => {
             logger::log_info(&format!("[API] Account switch successful: {}", account_id));
+            audit_log::record(
+                "account.switch",
+                &actor.ip,
+                Some(&account_id),
+                previous_id.map(|id| serde_json::json!({ "current_account_id": id })),
+                Some(serde_json::json!({ "current_account_id": account_id })),
+            );
 
             // [FIX #1166] 账号切换后立即同步内存状态
-            state.token_manager.clear_all_sessions();
+            state.token_manager.clear_all_sessions().await;
             if let Err(e) = state.token_manager.load_accounts().await {
                 logger::log_error(&format!(
                     "[API] Failed to reload accounts after switch: {}",
@@ -1054,139 +1556,683 @@ async fn admin_switch_account(
         }
         Err(e) => {
             logger::log_error(&format!("[API] Account switch failed: {}", e));
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            ))
+            Err(ApiError::from_account_error(e))
         }
     }
 }
 
-async fn admin_refresh_all_quotas() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)>
-{
+async fn admin_refresh_all_quotas(
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
     logger::log_info("[API] Starting refresh of all account quotas");
-    let stats = account::refresh_all_quotas_logic().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    let stats = account::refresh_all_quotas_logic()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
+    audit_log::record("account.refresh_all_quotas", &actor.ip, None, None, None);
     Ok(Json(stats))
 }
 
-// --- OAuth Handlers ---
+#[derive(Deserialize)]
+struct IssueTokenRequest {
+    password: String,
+}
+
+/// POST /api/auth/token - 以管理密码 (未设置时为 api_key) 换取短期 JWT
+/// [FIX] 按客户端 IP 限制密码错误次数，锁定期间直接拒绝；返回解析出的客户端 IP
+fn check_login_throttle(
+    headers: &HeaderMap,
+    connect_info: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>,
+    security: &crate::proxy::ProxySecurityConfig,
+) -> Result<String, ApiError> {
+    let client_ip = crate::proxy::security::resolve_client_ip(
+        headers,
+        connect_info.map(|info| info.0.ip()),
+        &security.cidr_rules,
+    )
+    .unwrap_or_else(|| "unknown".to_string());
+    if let Err(retry_after) = crate::proxy::admin_jwt::login_throttle().check(&client_ip) {
+        let secs = retry_after.as_secs().max(1);
+        return Err(ApiError::from_status(
+            StatusCode::TOO_MANY_REQUESTS,
+            format!("Too many failed attempts, retry after {}s", secs),
+        )
+        .with_header("Retry-After", &secs.to_string()));
+    }
+    Ok(client_ip)
+}
 
-async fn admin_prepare_oauth_url(
+async fn admin_issue_token(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let url = state
-        .account_service
-        .prepare_oauth_url()
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
-    Ok(Json(serde_json::json!({ "url": url })))
+    connect_info: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>,
+    headers: HeaderMap,
+    Json(payload): Json<IssueTokenRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let security = state.security.read().await.clone();
+    let client_ip = check_login_throttle(&headers, connect_info, &security)?;
+    let throttle = crate::proxy::admin_jwt::login_throttle();
+
+    let expected = match &security.admin_password {
+        Some(pwd) if !pwd.is_empty() => pwd.as_str(),
+        _ => security.api_key.as_str(),
+    };
+    if expected.is_empty()
+        || !crate::proxy::admin_jwt::secrets_equal(&payload.password, expected)
+    {
+        throttle.record_failure(&client_ip);
+        logger::log_warn(&format!("[API] Invalid admin password from {}", client_ip));
+        return Err(ApiError::new(ApiErrorCode::AuthInvalidKey, "Invalid password"));
+    }
+    throttle.record_success(&client_ip);
+
+    let (token, claims) =
+        crate::proxy::admin_jwt::issue_token(&security.api_key, security.jwt_expiry_seconds)
+            .map_err(|e| ApiError::from_status(StatusCode::BAD_REQUEST, e))?;
+    logger::log_info(&format!("[API] Issued admin token (jti: {})", claims.jti));
+
+    Ok(Json(serde_json::json!({
+        "token": token,
+        "token_type": "Bearer",
+        "jti": claims.jti,
+        "expires_in": claims.exp - claims.iat,
+        "expires_at": claims.exp,
+    })))
 }
 
-async fn admin_start_oauth_login(
-    State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let account = state
-        .account_service
-        .start_oauth_login()
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
-    let current_id = state.account_service.get_current_id().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
-    Ok(Json(to_account_response(&account, &current_id)))
+#[derive(Deserialize)]
+struct RevokeTokenRequest {
+    token: String,
 }
 
-async fn admin_complete_oauth_login(
+/// POST /api/auth/token/revoke - 吊销 JWT (JTI 保留至令牌原过期时间)
+async fn admin_revoke_token(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let account = state
-        .account_service
-        .complete_oauth_login()
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
-    let current_id = state.account_service.get_current_id().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
+    Extension(actor): Extension<AdminActor>,
+    Json(payload): Json<RevokeTokenRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    // [FIX] 轮换宽限期内旧 Key 签发的令牌仍可访问管理接口，也必须能被吊销
+    let claims = {
+        let security = state.security.read().await;
+        crate::proxy::admin_jwt::decode_token_with_previous(
+            &security.api_key,
+            security.previous_api_key(),
+            &payload.token,
         )
-    })?;
-    Ok(Json(to_account_response(&account, &current_id)))
-}
+    }
+    .map_err(|e| ApiError::from_status(StatusCode::BAD_REQUEST, format!("Invalid token: {}", e)))?;
+    crate::proxy::admin_jwt::revocations().revoke(claims.jti.clone(), claims.exp);
+    logger::log_info(&format!("[API] Revoked admin token (jti: {})", claims.jti));
 
-async fn admin_cancel_oauth_login(
-    State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    state.account_service.cancel_oauth_login();
-    Ok(StatusCode::OK)
+    audit_log::record("admin_token.revoke", &actor.ip, Some(&claims.jti), None, None);
+    Ok(Json(serde_json::json!({ "revoked": true, "jti": claims.jti })))
 }
 
 #[derive(Deserialize)]
-struct SubmitCodeRequest {
-    code: String,
-    state: Option<String>,
+struct AdminLoginRequest {
+    username: String,
+    password: String,
 }
 
-async fn admin_submit_oauth_code(
+/// POST /api/auth/login - 管理用户登录，返回会话令牌 (有效期同 jwt_expiry_seconds)
+async fn admin_login(
     State(state): State<AppState>,
-    Json(payload): Json<SubmitCodeRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    state
-        .account_service
-        .submit_oauth_code(payload.code, payload.state)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
-    Ok(StatusCode::OK)
-}
+    connect_info: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>,
+    headers: HeaderMap,
+    Json(payload): Json<AdminLoginRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let security = state.security.read().await.clone();
+    let invalid = || ApiError::new(ApiErrorCode::AuthInvalidKey, "Invalid username or password");
+    if security.admin_session_secret.is_empty() {
+        return Err(invalid());
+    }
+    let client_ip = check_login_throttle(&headers, connect_info, &security)?;
+    let throttle = crate::proxy::admin_jwt::login_throttle();
 
-#[derive(Deserialize)]
-struct BindDeviceRequest {
-    #[serde(default = "default_bind_mode")]
-    mode: String,
+    // argon2 校验较耗 CPU，放到阻塞线程
+    let user = tokio::task::spawn_blocking(move || {
+        crate::modules::admin_users::authenticate(&payload.username, &payload.password)
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?
+    .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let Some(user) = user else {
+        throttle.record_failure(&client_ip);
+        return Err(invalid());
+    };
+    throttle.record_success(&client_ip);
+
+    let (token, claims) = crate::proxy::admin_jwt::issue_session(
+        &security.admin_session_secret,
+        &user.username,
+        user.session_version,
+        security.jwt_expiry_seconds,
+    )
+    .map_err(ApiError::internal)?;
+    logger::log_info(&format!(
+        "[API] Admin user '{}' logged in (jti: {})",
+        user.username, claims.jti
+    ));
+
+    Ok(Json(serde_json::json!({
+        "token": token,
+        "token_type": "Bearer",
+        "username": user.username,
+        "role": user.role,
+        "jti": claims.jti,
+        "expires_in": claims.exp - claims.iat,
+        "expires_at": claims.exp,
+    })))
 }
 
-fn default_bind_mode() -> String {
-    "generate".to_string()
+/// 自助接口要求以管理用户会话访问
+fn require_session(actor: &AdminActor) -> Result<&crate::proxy::middleware::AdminSession, ApiError> {
+    actor.session.as_ref().ok_or_else(|| {
+        ApiError::invalid_request("This endpoint requires an admin user session")
+    })
 }
 
-async fn admin_bind_device(
+/// GET /api/auth/me - 当前登录身份 (旧凭据视为完整管理员)
+async fn admin_get_current_user(Extension(actor): Extension<AdminActor>) -> impl IntoResponse {
+    match &actor.session {
+        Some(session) => Json(serde_json::json!({
+            "username": session.username,
+            "role": session.role,
+            "legacy": false,
+        })),
+        None => Json(serde_json::json!({
+            "username": null,
+            "role": crate::modules::admin_users::AdminRole::Admin,
+            "legacy": true,
+        })),
+    }
+}
+
+#[derive(Deserialize)]
+struct ChangeOwnPasswordRequest {
+    current_password: String,
+    new_password: String,
+}
+
+/// POST /api/auth/password - 修改自己的密码 (现有会话全部失效，需重新登录)
+async fn admin_change_own_password(
+    Extension(actor): Extension<AdminActor>,
+    Json(payload): Json<ChangeOwnPasswordRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let username = require_session(&actor)?.username.clone();
+    let target = username.clone();
+    let changed = tokio::task::spawn_blocking(move || {
+        match crate::modules::admin_users::authenticate(&target, &payload.current_password)? {
+            Some(_) => crate::modules::admin_users::set_password(&target, &payload.new_password),
+            None => Ok(false),
+        }
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?
+    .map_err(ApiError::invalid_request)?;
+    if !changed {
+        return Err(ApiError::new(
+            ApiErrorCode::AuthInvalidKey,
+            "Current password is incorrect",
+        ));
+    }
+
+    logger::log_info(&format!("[API] Admin user '{}' changed password", username));
+    audit_log::record("admin_user.password", &actor.ip, Some(&username), None, None);
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize, Default)]
+struct LogoutRequest {
+    /// 同时吊销该用户的全部会话
+    #[serde(default)]
+    all: bool,
+}
+
+/// POST /api/auth/logout - 吊销当前会话 (all=true 时吊销全部会话)
+async fn admin_logout(
+    Extension(actor): Extension<AdminActor>,
+    payload: Option<Json<LogoutRequest>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let session = require_session(&actor)?;
+    let all = payload.map(|Json(p)| p.all).unwrap_or(false);
+    crate::proxy::admin_jwt::revocations().revoke(session.jti.clone(), session.exp);
+    if all {
+        crate::modules::admin_users::revoke_sessions(&session.username)
+            .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    }
+    logger::log_info(&format!(
+        "[API] Admin user '{}' logged out{}",
+        session.username,
+        if all { " (all sessions)" } else { "" }
+    ));
+    audit_log::record(
+        "admin_user.logout",
+        &actor.ip,
+        Some(&session.username),
+        None,
+        Some(serde_json::json!({ "all": all })),
+    );
+    Ok(Json(serde_json::json!({ "revoked": true, "all": all })))
+}
+
+/// 首次创建管理用户时生成并持久化会话签名密钥
+async fn ensure_admin_session_secret(state: &AppState) -> Result<(), ApiError> {
+    if !state.security.read().await.admin_session_secret.is_empty() {
+        return Ok(());
+    }
+    let mut app_config = config::load_app_config()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    if app_config.proxy.admin_session_secret.is_empty() {
+        app_config.proxy.admin_session_secret = crate::proxy::admin_jwt::generate_session_secret();
+        config::save_app_config(&app_config)
+            .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    }
+    let mut security = state.security.write().await;
+    *security = crate::proxy::ProxySecurityConfig::from_proxy_config(&app_config.proxy);
+    Ok(())
+}
+
+fn admin_user_not_found(username: &str) -> ApiError {
+    ApiError::from_status(
+        StatusCode::NOT_FOUND,
+        format!("Admin user '{}' not found", username),
+    )
+}
+
+async fn admin_list_admin_users() -> Result<impl IntoResponse, ApiError> {
+    let users = crate::modules::admin_users::list_users()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(users))
+}
+
+#[derive(Deserialize)]
+struct CreateAdminUserRequest {
+    username: String,
+    password: String,
+    role: crate::modules::admin_users::AdminRole,
+}
+
+/// POST /api/admin/users - 创建管理用户
+async fn admin_create_admin_user(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+    Json(payload): Json<CreateAdminUserRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    ensure_admin_session_secret(&state).await?;
+    let user = tokio::task::spawn_blocking(move || {
+        crate::modules::admin_users::create_user(payload.username.trim(), &payload.password, payload.role)
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?
+    .map_err(ApiError::invalid_request)?;
+
+    logger::log_info(&format!(
+        "[API] Created admin user '{}' ({})",
+        user.username,
+        user.role.as_str()
+    ));
+    audit_log::record(
+        "admin_user.create",
+        &actor.ip,
+        Some(&user.username),
+        None,
+        Some(serde_json::json!({ "role": user.role })),
+    );
+    Ok((StatusCode::CREATED, Json(user)))
+}
+
+#[derive(Deserialize)]
+struct UpdateAdminUserRequest {
+    role: crate::modules::admin_users::AdminRole,
+}
+
+/// PATCH /api/admin/users/:username - 修改角色 (对已登录会话立即生效)
+async fn admin_update_admin_user(
+    Extension(actor): Extension<AdminActor>,
+    Path(username): Path<String>,
+    Json(payload): Json<UpdateAdminUserRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let old = crate::modules::admin_users::get_user(&username)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or_else(|| admin_user_not_found(&username))?;
+    crate::modules::admin_users::set_role(&username, payload.role)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    logger::log_info(&format!(
+        "[API] Admin user '{}' role changed to {}",
+        username,
+        payload.role.as_str()
+    ));
+    audit_log::record(
+        "admin_user.update",
+        &actor.ip,
+        Some(&username),
+        Some(serde_json::json!({ "role": old.role })),
+        Some(serde_json::json!({ "role": payload.role })),
+    );
+    Ok(StatusCode::OK)
+}
+
+async fn admin_delete_admin_user(
+    Extension(actor): Extension<AdminActor>,
+    Path(username): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let deleted = crate::modules::admin_users::delete_user(&username)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    if !deleted {
+        return Err(admin_user_not_found(&username));
+    }
+    logger::log_info(&format!("[API] Deleted admin user '{}'", username));
+    audit_log::record("admin_user.delete", &actor.ip, Some(&username), None, None);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct ResetAdminUserPasswordRequest {
+    new_password: String,
+}
+
+/// POST /api/admin/users/:username/password - 重置其他用户密码 (其会话全部失效)
+async fn admin_reset_admin_user_password(
+    Extension(actor): Extension<AdminActor>,
+    Path(username): Path<String>,
+    Json(payload): Json<ResetAdminUserPasswordRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let target = username.clone();
+    let updated = tokio::task::spawn_blocking(move || {
+        crate::modules::admin_users::set_password(&target, &payload.new_password)
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?
+    .map_err(ApiError::invalid_request)?;
+    if !updated {
+        return Err(admin_user_not_found(&username));
+    }
+
+    logger::log_info(&format!("[API] Reset password of admin user '{}'", username));
+    audit_log::record("admin_user.password", &actor.ip, Some(&username), None, None);
+    Ok(StatusCode::OK)
+}
+
+/// POST /api/admin/users/:username/sessions/revoke - 吊销该用户的全部会话
+async fn admin_revoke_admin_user_sessions(
+    Extension(actor): Extension<AdminActor>,
+    Path(username): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let revoked = crate::modules::admin_users::revoke_sessions(&username)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    if !revoked {
+        return Err(admin_user_not_found(&username));
+    }
+
+    logger::log_info(&format!("[API] Revoked all sessions of admin user '{}'", username));
+    audit_log::record("admin_user.revoke_sessions", &actor.ip, Some(&username), None, None);
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+struct UpdateTagsRequest {
+    tags: Vec<String>,
+}
+
+async fn admin_update_account_tags(
+    Extension(actor): Extension<AdminActor>,
     Path(account_id): Path<String>,
-    Json(payload): Json<BindDeviceRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let result = account::bind_device_profile(&account_id, &payload.mode).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
+    Json(payload): Json<UpdateTagsRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let tags = account::update_account_tags(&account_id, &payload.tags).map_err(|e| {
+        if e.contains("too long") {
+            ApiError::invalid_request(e)
+        } else {
+            ApiError::from_account_error(e)
+        }
     })?;
+    logger::log_info(&format!("[API] 账号 {} 标签已更新: {:?}", account_id, tags));
+    audit_log::record(
+        "account.update_tags",
+        &actor.ip,
+        Some(&account_id),
+        None,
+        Some(serde_json::json!({ "tags": tags })),
+    );
+    Ok(Json(serde_json::json!({ "tags": tags })))
+}
+
+#[derive(Deserialize)]
+struct UpdateNotesRequest {
+    notes: Option<String>,
+}
+
+async fn admin_update_account_notes(
+    Extension(actor): Extension<AdminActor>,
+    Path(account_id): Path<String>,
+    Json(payload): Json<UpdateNotesRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let notes = account::update_account_notes(&account_id, payload.notes.as_deref()).map_err(|e| {
+        if e.contains("too long") {
+            ApiError::invalid_request(e)
+        } else {
+            ApiError::from_account_error(e)
+        }
+    })?;
+    logger::log_info(&format!("[API] 账号 {} 备注已更新", account_id));
+    audit_log::record("account.update_notes", &actor.ip, Some(&account_id), None, None);
+    Ok(Json(serde_json::json!({ "notes": notes })))
+}
+
+/// PATCH /api/accounts/:accountId - 部分更新名称 / 备注 / 标签 / 受保护模型
+async fn admin_patch_account(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+    Path(account_id): Path<String>,
+    Json(payload): Json<crate::models::PatchAccountRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let account = account::patch_account(&account_id, payload).map_err(|e| {
+        if e.contains("too long") || e.starts_with("Invalid protected model") {
+            ApiError::invalid_request(e)
+        } else {
+            ApiError::from_account_error(e)
+        }
+    })?;
+    logger::log_info(&format!("[API] 账号 {} 信息已更新", account.email));
+
+    let current_id = state
+        .account_service
+        .get_current_id()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("account.patch", &actor.ip, Some(&account_id), None, None);
+    Ok(Json(to_account_response(
+        &account,
+        &current_id,
+        state.token_manager.consecutive_failures(&account.id),
+    )))
+}
+
+async fn admin_list_account_tags() -> Result<impl IntoResponse, ApiError> {
+    let tags = account::list_all_tags()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(tags))
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct HealthCheckRequest {
+    #[serde(default)]
+    account_ids: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct HealthCheckQuery {
+    #[serde(default)]
+    auto_disable: bool,
+}
+
+/// 批量校验 refresh_token，识别已被吊销的账号
+async fn admin_health_check_accounts(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+    Query(query): Query<HealthCheckQuery>,
+    payload: Option<Json<HealthCheckRequest>>,
+) -> Result<impl IntoResponse, ApiError> {
+    const MAX_CONCURRENT: usize = 4;
+
+    let request = payload.map(|Json(p)| p).unwrap_or_default();
+    logger::log_info(&format!(
+        "[API] Starting account health check (auto_disable: {})",
+        query.auto_disable
+    ));
+
+    let report = state
+        .token_manager
+        .health_check_accounts(request.account_ids.as_deref(), query.auto_disable, MAX_CONCURRENT)
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    audit_log::record(
+        "account.health_check",
+        &actor.ip,
+        None,
+        None,
+        Some(serde_json::json!({ "auto_disable": query.auto_disable })),
+    );
+    Ok(Json(report))
+}
+
+// [NEW] 立即执行闲置账号扫描，返回本次被自动停用的账号
+async fn admin_idle_check_accounts(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    let app_config = config::load_app_config()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let Some(days) = app_config.proxy.idle_disable_after_days.filter(|d| *d > 0) else {
+        return Err(ApiError::from_status(
+            StatusCode::BAD_REQUEST,
+            "idle_disable_after_days is not configured".to_string(),
+        ));
+    };
+
+    let disabled = account::disable_idle_accounts(days)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    // 同步到运行中的反代服务
+    for item in &disabled {
+        let _ = state.token_manager.reload_account(&item.account_id).await;
+    }
+
+    audit_log::record(
+        "account.idle_check",
+        &actor.ip,
+        None,
+        None,
+        Some(serde_json::json!({ "idle_disable_after_days": days, "disabled": disabled.len() })),
+    );
+    Ok(Json(serde_json::json!({
+        "idle_disable_after_days": days,
+        "disabled": disabled,
+    })))
+}
+
+// --- OAuth Handlers ---
+
+async fn admin_prepare_oauth_url(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let url = state
+        .account_service
+        .prepare_oauth_url()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(serde_json::json!({ "url": url })))
+}
+
+async fn admin_start_oauth_login(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    let account = state
+        .account_service
+        .start_oauth_login()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let current_id = state
+        .account_service
+        .get_current_id()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("account.add_oauth", &actor.ip, Some(&account.email), None, None);
+    Ok(Json(to_account_response(&account, &current_id, state.token_manager.consecutive_failures(&account.id))))
+}
+
+async fn admin_complete_oauth_login(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    let account = state
+        .account_service
+        .complete_oauth_login()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let current_id = state
+        .account_service
+        .get_current_id()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("account.add_oauth", &actor.ip, Some(&account.email), None, None);
+    Ok(Json(to_account_response(&account, &current_id, state.token_manager.consecutive_failures(&account.id))))
+}
+
+async fn admin_cancel_oauth_login(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    state.account_service.cancel_oauth_login();
+    audit_log::record("oauth.cancel", &actor.ip, None, None, None);
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+struct SubmitCodeRequest {
+    code: String,
+    state: Option<String>,
+}
+
+async fn admin_submit_oauth_code(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+    Json(payload): Json<SubmitCodeRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    state
+        .account_service
+        .submit_oauth_code(payload.code, payload.state)
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("oauth.submit_code", &actor.ip, None, None, None);
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+struct BindDeviceRequest {
+    #[serde(default = "default_bind_mode")]
+    mode: String,
+}
+
+fn default_bind_mode() -> String {
+    "generate".to_string()
+}
+
+async fn admin_bind_device(
+    Extension(actor): Extension<AdminActor>,
+    Path(account_id): Path<String>,
+    Json(payload): Json<BindDeviceRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let result = account::bind_device_profile(&account_id, &payload.mode)
+        .map_err(ApiError::from_account_error)?;
+    audit_log::record(
+        "account.bind_device",
+        &actor.ip,
+        Some(&account_id),
+        None,
+        Some(serde_json::json!({ "mode": payload.mode })),
+    );
 
     Ok(Json(serde_json::json!({
         "success": true,
@@ -1195,6 +2241,26 @@ async fn admin_bind_device(
     })))
 }
 
+// [NEW] 批量绑定设备指纹 (串行执行，进度落盘，可通过 resume 续跑)
+async fn admin_bulk_bind_device(
+    Extension(actor): Extension<AdminActor>,
+    Json(payload): Json<account::BulkDeviceBindRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let report = tokio::task::spawn_blocking(move || account::bulk_bind_device_profiles(payload))
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| ApiError::from_status(StatusCode::BAD_REQUEST, e))?;
+
+    audit_log::record("account.bulk_bind_device", &actor.ip, None, None, None);
+    Ok(Json(report))
+}
+
+async fn admin_get_bulk_bind_progress() -> Result<impl IntoResponse, ApiError> {
+    let progress = account::load_bulk_bind_progress()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(serde_json::json!({ "pending": progress })))
+}
+
 #[derive(Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 struct LogsRequest {
@@ -1208,25 +2274,13 @@ struct LogsRequest {
     errors_only: bool,
 }
 
-async fn admin_get_logs(
-    Query(params): Query<LogsRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+async fn admin_get_logs(Query(params): Query<LogsRequest>) -> Result<impl IntoResponse, ApiError> {
     let limit = if params.limit == 0 { 50 } else { params.limit };
-    let total =
-        proxy_db::get_logs_count_filtered(&params.filter, params.errors_only).map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
+    let total = proxy_db::get_logs_count_filtered(&params.filter, params.errors_only)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     let logs =
         proxy_db::get_logs_filtered(&params.filter, params.errors_only, limit, params.offset)
-            .map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse { error: e }),
-                )
-            })?;
+            .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     Ok(Json(serde_json::json!({
         "total": total,
@@ -1234,36 +2288,200 @@ async fn admin_get_logs(
     })))
 }
 
-async fn admin_get_config() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let cfg = config::load_app_config().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+async fn admin_get_config(
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut cfg = config::load_app_config()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    // [NEW] 非 admin 会话 (viewer / operator) 不返回凭据
+    let is_admin = actor
+        .session
+        .as_ref()
+        .map_or(true, |session| session.role == crate::modules::admin_users::AdminRole::Admin);
+    if !is_admin {
+        redact_config_secrets(&mut cfg);
+    }
     Ok(Json(cfg))
 }
 
+/// 清空配置中的凭据字段 (仅用于展示，脱敏后的配置不可回写)
+fn redact_config_secrets(cfg: &mut AppConfig) {
+    cfg.proxy.api_key.clear();
+    cfg.proxy.api_key_previous = None;
+    cfg.proxy.admin_password = None;
+    cfg.proxy.admin_session_secret.clear();
+    for entry in cfg.proxy.api_keys.iter_mut() {
+        entry.key.clear();
+    }
+}
+
+/// [NEW] 配置文件的 JSON Schema，直接由 AppConfig 的 serde 结构生成 (含默认值与枚举取值)
+async fn admin_get_config_schema() -> impl IntoResponse {
+    Json(schemars::schema_for!(AppConfig))
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct SaveConfigWrapper {
     config: AppConfig,
 }
 
+/// 保存前的配置校验 (管理接口与配置文件监听共用)
+fn validate_app_config(new_config: &crate::models::AppConfig) -> Result<(), String> {
+    crate::proxy::transforms::validate_transforms(&new_config.proxy.transforms)?;
+    new_config.proxy.warmup_schedule.validate()?;
+    crate::proxy::security::validate_monitor_cidrs(&new_config.proxy.security_monitor)?;
+    Ok(())
+}
+
 async fn admin_save_config(
     State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<SaveConfigWrapper>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let new_config = payload.config;
+) -> Result<impl IntoResponse, ApiError> {
+    let mut new_config = payload.config;
+    validate_app_config(&new_config).map_err(|e| ApiError::from_status(StatusCode::BAD_REQUEST, e))?;
+    let old_config = config::load_app_config().ok();
+    // [NEW] 前端未回传会话签名密钥时保留原值，避免已登录的管理用户全部掉线
+    if new_config.proxy.admin_session_secret.is_empty() {
+        if let Some(old) = &old_config {
+            new_config.proxy.admin_session_secret = old.proxy.admin_session_secret.clone();
+        }
+    }
     // 1. 持久化
-    config::save_app_config(&new_config).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
+    config::save_app_config(&new_config)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    apply_app_config(&state, &new_config).await;
+    audit_config_change("config.save", &actor, old_config.as_ref(), &new_config);
+
+    Ok(StatusCode::OK)
+}
+
+/// 审计配置变更: 只记录发生变化的字段
+fn audit_config_change(action: &str, actor: &AdminActor, old: Option<&AppConfig>, new: &AppConfig) {
+    let new_value = serde_json::to_value(new).unwrap_or_default();
+    let (old_value, new_value) = match old.and_then(|c| serde_json::to_value(c).ok()) {
+        Some(old_value) => {
+            if old_value == new_value {
+                return;
+            }
+            let (o, n) = audit_log::diff_values(&old_value, &new_value);
+            (Some(o), n)
+        }
+        None => (None, new_value),
+    };
+    audit_log::record(action, &actor.ip, None, old_value, Some(new_value));
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct AuditLogQuery {
+    action: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    offset: usize,
+}
+
+/// GET /api/audit-log - 分页查询管理操作审计日志 (最新在前)，可按 action 过滤
+async fn admin_get_audit_log(Query(q): Query<AuditLogQuery>) -> Result<impl IntoResponse, ApiError> {
+    let limit = q.limit.unwrap_or(50).clamp(1, 500);
+    let page = tokio::task::spawn_blocking(move || {
+        audit_log::get_audit_logs(q.action.as_deref().filter(|a| !a.is_empty()), limit, q.offset)
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?
+    .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(page))
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct AuditLogExportRequest {
+    action: Option<String>,
+}
+
+/// POST /api/audit-log/export - 以 JSON 附件导出全部审计记录 (时间正序)
+async fn admin_export_audit_log(
+    Extension(actor): Extension<AdminActor>,
+    payload: Option<Json<AuditLogExportRequest>>,
+) -> Result<Response, ApiError> {
+    let action = payload.and_then(|Json(p)| p.action).filter(|a| !a.is_empty());
+    let entries = tokio::task::spawn_blocking(move || audit_log::export_audit_logs(action.as_deref()))
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))?
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let body = serde_json::to_vec_pretty(&entries).map_err(|e| ApiError::internal(e.to_string()))?;
+
+    let filename = format!("audit_log_{}.json", chrono::Utc::now().format("%Y%m%d%H%M%S"));
+    audit_log::record("audit_log.export", &actor.ip, None, None, None);
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(axum::http::header::CONTENT_TYPE, "application/json")
+        .header(
+            axum::http::header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
         )
-    })?;
+        .body(axum::body::Body::from(body))
+        .map_err(|e| ApiError::internal(e.to_string()))
+}
+
+/// POST /api/config/validate - 预检候选配置，不保存也不热更新
+async fn admin_validate_config(
+    State(state): State<AppState>,
+    Json(payload): Json<SaveConfigWrapper>,
+) -> impl IntoResponse {
+    Json(crate::proxy::config_validation::validate_config(
+        &payload.config,
+        &state.bind_addresses,
+    ))
+}
+
+/// 从磁盘重新读取配置，校验通过后走与 admin_save_config 相同的热更新流程
+async fn reload_config_from_disk(state: &AppState) -> Result<crate::models::AppConfig, String> {
+    let new_config = config::load_app_config()?;
+    validate_app_config(&new_config)?;
+    apply_app_config(state, &new_config).await;
+    logger::log_info("[Config-Reload] 配置文件已重新加载");
+    Ok(new_config)
+}
+
+/// POST /api/config/reload - 手动编辑配置文件后无需重启即可生效
+async fn admin_reload_config(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    reload_config_from_disk(&state)
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::BAD_REQUEST, e))?;
+    audit_log::record("config.reload", &actor.ip, None, None, None);
+    Ok(Json(serde_json::json!({ "reloaded": true })))
+}
+
+/// 签名缓存持久化设置 (打开/加载 SQLite) 放到阻塞线程执行
+async fn apply_signature_persistence(experimental: crate::proxy::config::ExperimentalConfig) {
+    if let Err(e) = tokio::task::spawn_blocking(move || {
+        crate::proxy::signature_cache::apply_persistence_config(&experimental)
+    })
+    .await
+    {
+        tracing::warn!("[SignatureCache] Failed to apply persistence config: {}", e);
+    }
+}
+
+/// 将新配置热更新到运行中的服务 (管理接口保存与配置文件监听共用)
+async fn apply_app_config(state: &AppState, new_config: &crate::models::AppConfig) {
+    // 更新期间持有写锁: 并发的保存/重新加载串行执行，新进入的 AI 请求等待更新完成，
+    // 不会读到一半新一半旧的配置
+    // [FIX] 环境变量覆盖只作用于运行时配置，不回写文件
+    let runtime_config = config::with_env_overrides(new_config.clone());
+    let new_config = &runtime_config;
+
+    // [FIX] 签名缓存持久化会读写 SQLite，在阻塞线程中、获取写锁之前完成
+    apply_signature_persistence(new_config.proxy.experimental.clone()).await;
+
+    let _apply_guard = state.config_apply.write().await;
 
-    // 2. 热更新内存状态
+    // 热更新内存状态
     // 这里我们直接复用内部组件的 update 方法
     // 注意：AppState 本身持有各个组件的 Arc<RwLock> 或直接持有引用
 
@@ -1274,13 +2492,16 @@ async fn admin_save_config(
     // 更新模型映射
     {
         let mut mapping = state.custom_mapping.write().await;
-        *mapping = new_config.clone().proxy.custom_mapping;
+        *mapping = new_config.proxy.custom_mapping.clone();
     }
 
-    // 更新上游代理
+    // 更新上游代理 (同时重建上游客户端)
+    state
+        .upstream
+        .update_proxy_config(new_config.proxy.upstream_proxy.clone());
     {
         let mut proxy = state.upstream_proxy.write().await;
-        *proxy = new_config.clone().proxy.upstream_proxy;
+        *proxy = new_config.proxy.upstream_proxy.clone();
     }
 
     // 更新安全策略
@@ -1292,22 +2513,52 @@ async fn admin_save_config(
     // 更新 z.ai 配置
     {
         let mut zai = state.zai.write().await;
-        *zai = new_config.clone().proxy.zai;
+        *zai = new_config.proxy.zai.clone();
     }
 
     // 更新实验性配置
     {
         let mut exp = state.experimental.write().await;
-        *exp = new_config.clone().proxy.experimental;
+        *exp = new_config.proxy.experimental.clone();
     }
 
-    Ok(StatusCode::OK)
+    // 更新 User-Agent 覆盖与轮换池
+    state
+        .upstream
+        .set_user_agent_override(new_config.proxy.user_agent_override.clone())
+        .await;
+    state
+        .upstream
+        .set_user_agent_pool(new_config.proxy.user_agent_pool.clone())
+        .await;
+
+    // 更新调试日志配置
+    {
+        let mut debug_logging = state.debug_logging.write().await;
+        *debug_logging = new_config.proxy.debug_logging.clone();
+    }
+
+    // [FIX] 全局配置与 Tauri 保存配置共用同一更新列表 (含 Thinking Budget / 系统提示词 / 图像思维模式)
+    crate::proxy::apply_global_proxy_config(&new_config.proxy);
+
+    // 更新账号并发限制
+    state
+        .token_manager
+        .update_concurrency_config(new_config.proxy.concurrency.clone());
+    state
+        .token_manager
+        .update_auto_disable_threshold(new_config.proxy.auto_disable_failure_threshold);
+
+    // 更新多实例协调模式
+    state
+        .token_manager
+        .update_coordination_mode(new_config.proxy.coordination_mode);
 }
 
 // [FIX Web Mode] Get proxy pool config
 async fn admin_get_proxy_pool_config(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let config = state.proxy_pool_state.read().await;
     Ok(Json(config.clone()))
 }
@@ -1315,7 +2566,7 @@ async fn admin_get_proxy_pool_config(
 // [FIX Web Mode] Get all account proxy bindings
 async fn admin_get_all_account_bindings(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let bindings = state.proxy_pool_manager.get_all_bindings_snapshot();
     Ok(Json(bindings))
 }
@@ -1330,12 +2581,21 @@ struct BindAccountProxyRequest {
 
 async fn admin_bind_account_proxy(
     State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<BindAccountProxyRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
+    let BindAccountProxyRequest { account_id, proxy_id } = payload;
     state.proxy_pool_manager
-        .bind_account_to_proxy(payload.account_id, payload.proxy_id)
+        .bind_account_to_proxy(account_id.clone(), proxy_id.clone())
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record(
+        "account.bind_proxy",
+        &actor.ip,
+        Some(&account_id),
+        None,
+        Some(serde_json::json!({ "proxy_id": proxy_id })),
+    );
     Ok(StatusCode::OK)
 }
 
@@ -1348,9 +2608,11 @@ struct UnbindAccountProxyRequest {
 
 async fn admin_unbind_account_proxy(
     State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<UnbindAccountProxyRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    state.proxy_pool_manager.unbind_account_proxy(payload.account_id).await;
+) -> Result<impl IntoResponse, ApiError> {
+    state.proxy_pool_manager.unbind_account_proxy(payload.account_id.clone()).await;
+    audit_log::record("account.unbind_proxy", &actor.ip, Some(&payload.account_id), None, None);
     Ok(StatusCode::OK)
 }
 
@@ -1358,7 +2620,7 @@ async fn admin_unbind_account_proxy(
 async fn admin_get_account_proxy_binding(
     State(state): State<AppState>,
     Path(account_id): Path<String>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let binding = state.proxy_pool_manager.get_account_binding(&account_id);
     Ok(Json(binding))
 }
@@ -1366,16 +2628,17 @@ async fn admin_get_account_proxy_binding(
 // [FIX Web Mode] Trigger proxy pool health check
 async fn admin_trigger_proxy_health_check(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    state.proxy_pool_manager.health_check().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    state
+        .proxy_pool_manager
+        .health_check()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     // 返回更新后的代理池配置（包含健康状态）
     let config = state.proxy_pool_state.read().await;
+    audit_log::record("proxy_pool.health_check", &actor.ip, None, None, None);
     Ok(Json(serde_json::json!({
         "success": true,
         "message": "Health check completed",
@@ -1385,20 +2648,34 @@ async fn admin_trigger_proxy_health_check(
 
 async fn admin_get_proxy_status(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     // 在 Headless/Axum 模式下，AxumServer 既然在运行，通常就是 running
     let active_accounts = state.token_manager.len();
 
     let is_running = { *state.is_running.read().await };
+    let maintenance = state.maintenance_mode.read().await.clone();
+    let zai_health = state.zai_health.snapshot();
+    let forecast_warnings = crate::modules::quota_forecast::current_warnings();
     Ok(Json(serde_json::json!({
         "running": is_running,
+        "maintenance_mode": maintenance.enabled,
+        "maintenance_reason": maintenance.reason,
+        "maintenance_since": maintenance.since,
         "port": state.port,
         "base_url": format!("http://127.0.0.1:{}", state.port),
+        "bind_addresses": state.bind_addresses.as_ref(),
         "active_accounts": active_accounts,
+        "zai_healthy": zai_health.healthy,
+        "last_probe_error": zai_health.last_probe_error,
+        "quota_forecast_warning": !forecast_warnings.is_empty(),
+        "quota_forecast_warning_models": forecast_warnings,
     })))
 }
 
-async fn admin_start_proxy_service(State(state): State<AppState>) -> impl IntoResponse {
+async fn admin_start_proxy_service(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+) -> impl IntoResponse {
     // 1. 持久化配置 (修复 #1166)
     if let Ok(mut config) = crate::modules::config::load_app_config() {
         config.proxy.auto_start = true;
@@ -1410,89 +2687,378 @@ async fn admin_start_proxy_service(State(state): State<AppState>) -> impl IntoRe
         logger::log_error(&format!("[API] 启用服务并加载账号失败: {}", e));
     }
 
-    let mut running = state.is_running.write().await;
-    *running = true;
-    logger::log_info("[API] 反代服务功能已启用 (持久化已同步)");
-    StatusCode::OK
+    let mut running = state.is_running.write().await;
+    *running = true;
+    logger::log_info("[API] 反代服务功能已启用 (持久化已同步)");
+    audit_log::record("proxy.start", &actor.ip, None, None, None);
+    StatusCode::OK
+}
+
+#[derive(Deserialize)]
+struct StopProxyQuery {
+    /// 等待在途请求完成 (最长 shutdown_drain_seconds) 后再结束
+    #[serde(default)]
+    drain: bool,
+}
+
+async fn admin_stop_proxy_service(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+    Query(query): Query<StopProxyQuery>,
+) -> Response {
+    // 1. 持久化配置 (修复 #1166)
+    let mut drain_seconds = crate::proxy::config::ProxyConfig::default().shutdown_drain_seconds;
+    if let Ok(mut config) = crate::modules::config::load_app_config() {
+        config.proxy.auto_start = false;
+        drain_seconds = config.proxy.shutdown_drain_seconds;
+        let _ = crate::modules::config::save_app_config(&config);
+    }
+
+    // 2. 停止接收新的 AI 请求 (service_status 中间件返回 503)
+    {
+        let mut running = state.is_running.write().await;
+        *running = false;
+    }
+    logger::log_info("[API] 反代服务功能已禁用 (Axum 模式 / 持久化已同步)");
+
+    audit_log::record(
+        "proxy.stop",
+        &actor.ip,
+        None,
+        None,
+        Some(serde_json::json!({ "drain": query.drain })),
+    );
+    if !query.drain {
+        return StatusCode::OK.into_response();
+    }
+
+    // 3. [NEW] 优雅停机: 先关闭监听 (不再接受新连接)，存量连接最多等待宽限期，
+    // 超时后取消剩余请求并强制关闭连接。管理 API 与反代共用监听端口，之后需重启进程
+    let grace_period = shutdown_grace_period(drain_seconds);
+    let in_flight = state.monitor.active.in_flight();
+    if let Some(tx) = state.shutdown_tx.lock().await.take() {
+        let _ = tx.send(grace_period);
+        logger::log_info(&format!(
+            "[API] 已停止监听，等待在途请求完成 (宽限期 {}s)",
+            grace_period.as_secs()
+        ));
+    }
+    let monitor = state.monitor.clone();
+    tokio::spawn(async move {
+        let outcome = monitor.active.drain(grace_period).await;
+        logger::log_info(&format!(
+            "[API] 在途请求排空完成: {} 个已完成, {} 个被强制取消",
+            outcome.drained, outcome.cancelled
+        ));
+    });
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "in_flight": in_flight,
+            "drain_timeout_secs": grace_period.as_secs(),
+        })),
+    )
+        .into_response()
+}
+
+#[derive(Deserialize)]
+struct MaintenanceRequest {
+    enabled: bool,
+    #[serde(default)]
+    reason: Option<String>,
+    /// 未指定时使用默认值
+    #[serde(default)]
+    retry_after_secs: Option<u64>,
+}
+
+/// 切换维护模式: AI 代理路由返回 503 + Retry-After，管理 API 不受影响
+async fn admin_set_maintenance_mode(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+    Json(payload): Json<MaintenanceRequest>,
+) -> impl IntoResponse {
+    let mut maintenance = state.maintenance_mode.write().await;
+    let previous = serde_json::to_value(&*maintenance).ok();
+    if payload.enabled {
+        maintenance.since = maintenance
+            .since
+            .filter(|_| maintenance.enabled)
+            .or_else(|| Some(chrono::Utc::now().timestamp()));
+        maintenance.enabled = true;
+        maintenance.reason = payload.reason.filter(|r| !r.trim().is_empty());
+        maintenance.retry_after_secs = payload.retry_after_secs.unwrap_or(
+            crate::proxy::middleware::service_status::DEFAULT_MAINTENANCE_RETRY_AFTER_SECS,
+        );
+        logger::log_info(&format!(
+            "[API] 维护模式已开启 (reason: {}, retry-after: {}s)",
+            maintenance.reason.as_deref().unwrap_or("-"),
+            maintenance.retry_after_secs
+        ));
+    } else {
+        *maintenance = Default::default();
+        logger::log_info("[API] 维护模式已关闭");
+    }
+    audit_log::record(
+        "maintenance.set",
+        &actor.ip,
+        None,
+        previous,
+        serde_json::to_value(&*maintenance).ok(),
+    );
+    Json(maintenance.clone())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateMappingWrapper {
+    config: crate::proxy::config::ProxyConfig,
+}
+
+async fn admin_update_model_mapping(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+    Json(payload): Json<UpdateMappingWrapper>,
+) -> Result<impl IntoResponse, ApiError> {
+    let config = payload.config;
+
+    // 0. 拒绝无法编译的 regex: 规则
+    let invalid =
+        crate::proxy::common::model_mapping::find_invalid_mapping_rules(&config.custom_mapping);
+    if !invalid.is_empty() {
+        return Err(ApiError::from_status(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid regex mapping rules: {}", invalid.join(", ")),
+        ));
+    }
+
+    // 1. 更新内存状态 (热更新)
+    {
+        let mut mapping = state.custom_mapping.write().await;
+        *mapping = config.custom_mapping.clone();
+    }
+    state.model_list_cache.invalidate();
+    crate::proxy::response_cache::ResponseCache::global().invalidate();
+
+    // 2. 持久化到硬盘 (修复 #1149)
+    // 加载当前配置，更新 mapping，然后保存
+    let mut app_config = crate::modules::config::load_app_config()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    let old_config = app_config.clone();
+    app_config.proxy.custom_mapping = config.custom_mapping;
+
+    crate::modules::config::save_app_config(&app_config)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_config_change("model_mapping.update", &actor, Some(&old_config), &app_config);
+
+    logger::log_info("[API] 模型映射已通过 API 热更新并保存");
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+struct TestMappingRequest {
+    model: String,
+}
+
+/// 调试用: 返回给定模型名在当前 custom_mapping 下命中的规则
+async fn admin_test_model_mapping(
+    State(state): State<AppState>,
+    Json(payload): Json<TestMappingRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let model = payload.model.trim();
+    if model.is_empty() {
+        return Err(ApiError::from_status(
+            StatusCode::BAD_REQUEST,
+            "model is required".to_string(),
+        ));
+    }
+
+    let mapping = state.custom_mapping.read().await;
+    let matched = crate::proxy::common::model_mapping::explain_model_route(model, &mapping);
+    Ok(Json(serde_json::json!({
+        "model": model,
+        "target": matched.target,
+        "rule": matched.rule,
+        "pattern": matched.pattern,
+    })))
+}
+
+async fn admin_generate_api_key() -> impl IntoResponse {
+    let new_key = format!("sk-{}", uuid::Uuid::new_v4().to_string().replace("-", ""));
+    Json(new_key)
+}
+
+/// 轮换 API Key: 生成新 Key，旧 Key 在 rotation_grace_seconds 内仍然有效
+async fn admin_rotate_api_key(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut app_config = crate::modules::config::load_app_config()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    let now = chrono::Utc::now().timestamp();
+    let new_key = crate::proxy::api_key_rotation::rotate(&mut app_config.proxy, now);
+
+    crate::modules::config::save_app_config(&app_config)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    // [FIX] 与保存配置走同一热更新流程 (含环境变量覆盖与配置写锁)
+    apply_app_config(&state, &app_config).await;
+
+    if let Some(expires_at) = app_config.proxy.api_key_previous_expires_at {
+        crate::proxy::api_key_rotation::schedule_expiry(state.security.clone(), expires_at);
+    }
+
+    logger::log_info("[API] API Key 已轮换，旧 Key 进入宽限期");
+    // Key 本身不落审计库，只记录宽限期
+    audit_log::record(
+        "api_key.rotate",
+        &actor.ip,
+        None,
+        None,
+        Some(serde_json::json!({
+            "previous_expires_at": app_config.proxy.api_key_previous_expires_at
+        })),
+    );
+    Ok(Json(serde_json::json!({
+        "api_key": new_key,
+        "previous_key_expires_at": app_config.proxy.api_key_previous_expires_at,
+        "rotation": crate::proxy::api_key_rotation::status(&app_config.proxy, now),
+    })))
 }
 
-async fn admin_stop_proxy_service(State(state): State<AppState>) -> impl IntoResponse {
-    // 1. 持久化配置 (修复 #1166)
-    if let Ok(mut config) = crate::modules::config::load_app_config() {
-        config.proxy.auto_start = false;
-        let _ = crate::modules::config::save_app_config(&config);
-    }
+/// 保存附加 Key 变更并立即刷新鉴权配置
+async fn persist_api_keys(
+    state: &AppState,
+    app_config: &crate::models::AppConfig,
+) -> Result<(), ApiError> {
+    crate::modules::config::save_app_config(app_config)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let mut security = state.security.write().await;
+    *security = crate::proxy::ProxySecurityConfig::from_proxy_config(&app_config.proxy);
+    Ok(())
+}
 
-    let mut running = state.is_running.write().await;
-    *running = false;
-    logger::log_info("[API] 反代服务功能已禁用 (Axum 模式 / 持久化已同步)");
-    StatusCode::OK
+async fn admin_list_api_keys() -> Result<impl IntoResponse, ApiError> {
+    let app_config = crate::modules::config::load_app_config()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(crate::proxy::api_keys::list(&app_config.proxy)))
 }
 
 #[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct UpdateMappingWrapper {
-    config: crate::proxy::config::ProxyConfig,
+struct CreateApiKeyRequest {
+    label: String,
+    /// 不填则自动生成
+    key: Option<String>,
 }
 
-async fn admin_update_model_mapping(
+/// 新增带标签的 Key，完整 Key 仅在创建时返回一次
+async fn admin_create_api_key(
     State(state): State<AppState>,
-    Json(payload): Json<UpdateMappingWrapper>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let config = payload.config;
-
-    // 1. 更新内存状态 (热更新)
-    {
-        let mut mapping = state.custom_mapping.write().await;
-        *mapping = config.custom_mapping.clone();
-    }
+    Extension(actor): Extension<AdminActor>,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut app_config = crate::modules::config::load_app_config()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let entry = crate::proxy::api_keys::add(&mut app_config.proxy, &payload.label, payload.key)?;
+    persist_api_keys(&state, &app_config).await?;
 
-    // 2. 持久化到硬盘 (修复 #1149)
-    // 加载当前配置，更新 mapping，然后保存
-    let mut app_config = crate::modules::config::load_app_config().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    logger::log_info(&format!("[API] 已新增 API Key: {}", entry.label));
+    audit_log::record("api_key.create", &actor.ip, Some(&entry.label), None, None);
+    Ok((StatusCode::CREATED, Json(entry)))
+}
 
-    app_config.proxy.custom_mapping = config.custom_mapping;
+#[derive(Deserialize)]
+struct UpdateApiKeyRequest {
+    label: Option<String>,
+    disabled: Option<bool>,
+}
 
-    crate::modules::config::save_app_config(&app_config).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+async fn admin_update_api_key(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+    Path(label): Path<String>,
+    Json(payload): Json<UpdateApiKeyRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut app_config = crate::modules::config::load_app_config()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let old = app_config
+        .proxy
+        .api_keys
+        .iter()
+        .find(|e| e.label == label)
+        .map(crate::proxy::api_keys::ApiKeySummary::from);
+    let updated = crate::proxy::api_keys::update(
+        &mut app_config.proxy,
+        &label,
+        payload.label.as_deref(),
+        payload.disabled,
+    )?;
+    persist_api_keys(&state, &app_config).await?;
+
+    logger::log_info(&format!("[API] 已更新 API Key: {}", label));
+    audit_log::record(
+        "api_key.update",
+        &actor.ip,
+        Some(&label),
+        old.map(|o| serde_json::json!({ "label": o.label, "disabled": o.disabled })),
+        Some(serde_json::json!({ "label": updated.label, "disabled": updated.disabled })),
+    );
+    Ok(Json(updated))
+}
+
+async fn admin_delete_api_key(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+    Path(label): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut app_config = crate::modules::config::load_app_config()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    crate::proxy::api_keys::remove(&mut app_config.proxy, &label)?;
+    persist_api_keys(&state, &app_config).await?;
 
-    logger::log_info("[API] 模型映射已通过 API 热更新并保存");
-    Ok(StatusCode::OK)
+    logger::log_info(&format!("[API] 已删除 API Key: {}", label));
+    audit_log::record("api_key.delete", &actor.ip, Some(&label), None, None);
+    Ok(StatusCode::NO_CONTENT)
 }
 
-async fn admin_generate_api_key() -> impl IntoResponse {
-    let new_key = format!("sk-{}", uuid::Uuid::new_v4().to_string().replace("-", ""));
-    Json(new_key)
+async fn admin_get_api_key_rotation_status() -> Result<impl IntoResponse, ApiError> {
+    let app_config = crate::modules::config::load_app_config()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(crate::proxy::api_key_rotation::status(
+        &app_config.proxy,
+        chrono::Utc::now().timestamp(),
+    )))
 }
 
-async fn admin_clear_proxy_session_bindings(State(state): State<AppState>) -> impl IntoResponse {
-    state.token_manager.clear_all_sessions();
+async fn admin_clear_proxy_session_bindings(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+) -> impl IntoResponse {
+    state.token_manager.clear_all_sessions().await;
     logger::log_info("[API] 已清除所有会话绑定");
+    audit_log::record("session_binding.clear_all", &actor.ip, None, None, None);
     StatusCode::OK
 }
 
-async fn admin_clear_all_rate_limits(State(state): State<AppState>) -> impl IntoResponse {
-    state.token_manager.clear_all_rate_limits();
+async fn admin_clear_all_rate_limits(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+) -> impl IntoResponse {
+    state.token_manager.clear_all_rate_limits().await;
     logger::log_info("[API] 已清除所有限流记录");
+    audit_log::record("rate_limit.clear_all", &actor.ip, None, None, None);
     StatusCode::OK
 }
 
 async fn admin_clear_rate_limit(
     State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Path(account_id): Path<String>,
 ) -> impl IntoResponse {
-    let cleared = state.token_manager.clear_rate_limit(&account_id);
+    let cleared = state.token_manager.clear_rate_limit(&account_id).await;
     if cleared {
         logger::log_info(&format!("[API] 已清除账号 {} 的限流记录", account_id));
+        audit_log::record("rate_limit.clear", &actor.ip, Some(&account_id), None, None);
         StatusCode::OK
     } else {
         StatusCode::NOT_FOUND
@@ -1512,29 +3078,44 @@ struct SetPreferredAccountRequest {
 
 async fn admin_set_preferred_account(
     State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<SetPreferredAccountRequest>,
 ) -> impl IntoResponse {
     state
         .token_manager
-        .set_preferred_account(payload.account_id)
+        .set_preferred_account(payload.account_id.clone())
         .await;
+    audit_log::record(
+        "account.set_preferred",
+        &actor.ip,
+        payload.account_id.as_deref(),
+        None,
+        None,
+    );
     StatusCode::OK
 }
 
+// [NEW] 立即探测 z.ai 健康状态 (修正 API Key 后无需等待下一轮后台探测)
+async fn admin_probe_zai(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let zai = state.zai.read().await.clone();
+    let upstream_proxy = state.upstream_proxy.read().await.clone();
+    let snapshot = state.zai_health.probe(&zai, Some(upstream_proxy)).await;
+    logger::log_info(&format!(
+        "[API] z.ai 健康探测完成: healthy={}",
+        snapshot.healthy
+    ));
+    Ok(Json(snapshot))
+}
+
 async fn admin_fetch_zai_models(
     Path(id): Path<String>,
     Json(payload): Json<serde_json::Value>, // 复用前端传来的参数
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     // 这里简单实现，如果需要更复杂的抓取逻辑，可以调用 zai 模块
     // 目前前端 fetch_zai_models 本质上也是一个工具函数，
     // 我们可以在后端通过 reqwest 代理抓取。
     let zai_config = payload.get("zai").ok_or_else(|| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "Missing zai config".to_string(),
-            }),
-        )
+        ApiError::from_status(StatusCode::BAD_REQUEST, "Missing zai config".to_string())
     })?;
 
     let api_key = zai_config
@@ -1553,23 +3134,12 @@ async fn admin_fetch_zai_models(
         .header("Authorization", format!("Bearer {}", api_key))
         .send()
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: e.to_string(),
-                }),
-            )
-        })?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    let data: serde_json::Value = resp.json().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
-        )
-    })?;
+    let data: serde_json::Value = resp
+        .json()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     // 提取模型 ID 列表
     let models = data
@@ -1590,6 +3160,7 @@ async fn admin_fetch_zai_models(
 
 async fn admin_set_proxy_monitor_enabled(
     State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<serde_json::Value>,
 ) -> impl IntoResponse {
     let enabled = payload
@@ -1603,12 +3174,19 @@ async fn admin_set_proxy_monitor_enabled(
         logger::log_info(&format!("[API] 监控状态已设置为: {}", enabled));
     }
 
+    audit_log::record(
+        "monitor.set_enabled",
+        &actor.ip,
+        None,
+        None,
+        Some(serde_json::json!({ "enabled": enabled })),
+    );
     StatusCode::OK
 }
 
 async fn admin_get_proxy_logs_count_filtered(
     Query(params): Query<LogsRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let res = tokio::task::spawn_blocking(move || {
         proxy_db::get_logs_count_filtered(&params.filter, params.errors_only)
     })
@@ -1616,20 +3194,28 @@ async fn admin_get_proxy_logs_count_filtered(
 
     match res {
         Ok(Ok(count)) => Ok(Json(count)),
-        Ok(Err(e)) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )),
-        Err(e) => Err((
+        Ok(Err(e)) => Err(ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e)),
+        Err(e) => Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            e.to_string(),
         )),
     }
 }
 
-async fn admin_clear_proxy_logs() -> impl IntoResponse {
+/// 强制使模型列表缓存失效，下次请求时重新构建
+async fn admin_refresh_model_list(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+) -> impl IntoResponse {
+    state.model_list_cache.invalidate();
+    logger::log_info("[API] 模型列表缓存已失效");
+    audit_log::record("model_list.refresh", &actor.ip, None, None, None);
+    StatusCode::OK
+}
+
+async fn admin_clear_proxy_logs(
+    Extension(actor): Extension<AdminActor>,
+) -> impl IntoResponse {
     let _ = tokio::task::spawn_blocking(|| {
         if let Err(e) = proxy_db::clear_logs() {
             logger::log_error(&format!("[API] 清除反代日志失败: {}", e));
@@ -1637,27 +3223,23 @@ async fn admin_clear_proxy_logs() -> impl IntoResponse {
     })
     .await;
     logger::log_info("[API] 已清除所有反代日志");
+    audit_log::record("proxy_log.clear", &actor.ip, None, None, None);
     StatusCode::OK
 }
 
 async fn admin_get_proxy_log_detail(
     Path(log_id): Path<String>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let res =
         tokio::task::spawn_blocking(move || crate::modules::proxy_db::get_log_detail(&log_id))
             .await;
 
     match res {
         Ok(Ok(log)) => Ok(Json(log)),
-        Ok(Err(e)) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )),
-        Err(e) => Err((
+        Ok(Err(e)) => Err(ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e)),
+        Err(e) => Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            e.to_string(),
         )),
     }
 }
@@ -1673,15 +3255,23 @@ struct LogsFilterQuery {
     limit: usize,
     #[serde(default)]
     offset: usize,
+    /// timestamp | latency | status (默认 timestamp)
+    #[serde(default, alias = "sort_by")]
+    sort_by: crate::modules::proxy_db::LogSortBy,
+    /// asc | desc (默认 desc)
+    #[serde(default)]
+    order: crate::modules::proxy_db::LogSortOrder,
 }
 
 async fn admin_get_proxy_logs_filtered(
     Query(params): Query<LogsFilterQuery>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let res = tokio::task::spawn_blocking(move || {
-        crate::modules::proxy_db::get_logs_filtered(
+        crate::modules::proxy_db::get_logs_filtered_sorted(
             &params.filter,
             params.errors_only,
+            params.sort_by,
+            params.order,
             params.limit,
             params.offset,
         )
@@ -1690,24 +3280,64 @@ async fn admin_get_proxy_logs_filtered(
 
     match res {
         Ok(Ok(logs)) => Ok(Json(logs)),
-        Ok(Err(e)) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )),
-        Err(e) => Err((
+        Ok(Err(e)) => Err(ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e)),
+        Err(e) => Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            e.to_string(),
         )),
     }
 }
 
 async fn admin_get_proxy_stats(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let stats = state.monitor.get_stats().await;
-    Ok(Json(stats))
+    let mut body = serde_json::to_value(stats).unwrap_or_else(|_| serde_json::json!({}));
+    // [NEW] 各账号当前在途/排队请求数
+    body["in_flight_by_account"] = serde_json::json!(state.token_manager.in_flight_counts());
+    // [NEW] 上游连接池状态
+    body["connection_pool"] = serde_json::json!(state.upstream.pool_stats());
+    // [NEW] 模型列表与 countTokens 响应缓存命中率
+    body["response_cache"] = serde_json::json!({
+        "model_list": state.model_list_cache.stats(),
+        "count_tokens": crate::proxy::response_cache::ResponseCache::global().stats(),
+    });
+    Ok(Json(body))
+}
+
+async fn admin_get_active_requests(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.monitor.active.list())
+}
+
+async fn admin_cancel_active_request(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+    Path(request_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    if !state.monitor.active.cancel(&request_id) {
+        return Err(ApiError::from_status(
+            StatusCode::NOT_FOUND,
+            format!("Request {} is not in flight", request_id),
+        ));
+    }
+    logger::log_info(&format!("[API] 已取消进行中的请求 {}", request_id));
+    audit_log::record("request.cancel", &actor.ip, Some(&request_id), None, None);
+    Ok(StatusCode::OK)
+}
+
+async fn admin_test_webhooks(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    let results = state.webhooks.send_test().await;
+    if results.is_empty() {
+        return Err(ApiError::from_status(
+            StatusCode::BAD_REQUEST,
+            "No webhooks configured".to_string(),
+        ));
+    }
+    audit_log::record("webhook.test", &actor.ip, None, None, None);
+    Ok(Json(serde_json::json!({ "results": results })))
 }
 
 async fn admin_get_data_dir_path() -> impl IntoResponse {
@@ -1719,35 +3349,29 @@ async fn admin_get_data_dir_path() -> impl IntoResponse {
 
 // --- User Token Handlers ---
 
-async fn admin_list_user_tokens() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let tokens = crate::commands::user_token::list_user_tokens().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+async fn admin_list_user_tokens() -> Result<impl IntoResponse, ApiError> {
+    let tokens = crate::commands::user_token::list_user_tokens()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     Ok(Json(tokens))
 }
 
-async fn admin_get_user_token_summary() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let summary = crate::commands::user_token::get_user_token_summary().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+async fn admin_get_user_token_summary() -> Result<impl IntoResponse, ApiError> {
+    let summary = crate::commands::user_token::get_user_token_summary()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     Ok(Json(summary))
 }
 
 async fn admin_create_user_token(
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<crate::commands::user_token::CreateTokenRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let token = crate::commands::user_token::create_user_token(payload).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+) -> Result<impl IntoResponse, ApiError> {
+    let request = serde_json::to_value(&payload).ok();
+    let token = crate::commands::user_token::create_user_token(payload)
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("user_token.create", &actor.ip, Some(&token.id), None, request);
     Ok(Json(token))
 }
 
@@ -1758,110 +3382,85 @@ struct RenewTokenRequest {
 }
 
 async fn admin_renew_user_token(
+    Extension(actor): Extension<AdminActor>,
     Path(id): Path<String>,
     Json(payload): Json<RenewTokenRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    crate::commands::user_token::renew_user_token(id, payload.expires_type).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+) -> Result<impl IntoResponse, ApiError> {
+    crate::commands::user_token::renew_user_token(id.clone(), payload.expires_type)
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("user_token.renew", &actor.ip, Some(&id), None, None);
     Ok(StatusCode::OK)
 }
 
 async fn admin_delete_user_token(
+    Extension(actor): Extension<AdminActor>,
     Path(id): Path<String>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    crate::commands::user_token::delete_user_token(id).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+) -> Result<impl IntoResponse, ApiError> {
+    crate::commands::user_token::delete_user_token(id.clone())
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("user_token.delete", &actor.ip, Some(&id), None, None);
     Ok(StatusCode::NO_CONTENT)
 }
 
 async fn admin_update_user_token(
+    Extension(actor): Extension<AdminActor>,
     Path(id): Path<String>,
     Json(payload): Json<crate::commands::user_token::UpdateTokenRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    crate::commands::user_token::update_user_token(id, payload).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+) -> Result<impl IntoResponse, ApiError> {
+    crate::commands::user_token::update_user_token(id.clone(), payload)
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("user_token.update", &actor.ip, Some(&id), None, None);
     Ok(StatusCode::OK)
 }
 
-async fn admin_should_check_updates() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)>
-{
-    let settings = crate::modules::update_checker::load_update_settings().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+async fn admin_should_check_updates() -> Result<impl IntoResponse, ApiError> {
+    let settings = crate::modules::update_checker::load_update_settings()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     let should = crate::modules::update_checker::should_check_for_updates(&settings);
     Ok(Json(should))
 }
 
-async fn admin_get_antigravity_path() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)>
-{
+async fn admin_get_antigravity_path() -> Result<impl IntoResponse, ApiError> {
     let path = crate::commands::get_antigravity_path(Some(true))
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     Ok(Json(path))
 }
 
-async fn admin_get_antigravity_args() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)>
-{
-    let args = crate::commands::get_antigravity_args().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+async fn admin_get_antigravity_args() -> Result<impl IntoResponse, ApiError> {
+    let args = crate::commands::get_antigravity_args()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     Ok(Json(args))
 }
 
 async fn admin_clear_antigravity_cache(
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let res = crate::commands::clear_antigravity_cache().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    let res = crate::commands::clear_antigravity_cache()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("cache.clear_antigravity", &actor.ip, None, None, None);
     Ok(Json(res))
 }
 
-async fn admin_get_antigravity_cache_paths(
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+async fn admin_get_antigravity_cache_paths() -> Result<impl IntoResponse, ApiError> {
     let res = crate::commands::get_antigravity_cache_paths()
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     Ok(Json(res))
 }
 
-async fn admin_clear_log_cache() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    crate::commands::clear_log_cache().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+async fn admin_clear_log_cache(
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    crate::commands::clear_log_cache()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("log_cache.clear", &actor.ip, None, None, None);
     Ok(StatusCode::OK)
 }
 
@@ -1876,132 +3475,149 @@ struct StatsPeriodQuery {
 
 async fn admin_get_token_stats_hourly(
     Query(p): Query<StatsPeriodQuery>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let hours = p.hours.unwrap_or(24);
     let res = tokio::task::spawn_blocking(move || token_stats::get_hourly_stats(hours)).await;
 
     match res {
         Ok(Ok(stats)) => Ok(Json(stats)),
-        Ok(Err(e)) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )),
-        Err(e) => Err((
+        Ok(Err(e)) => Err(ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e)),
+        Err(e) => Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            e.to_string(),
         )),
     }
 }
 
 async fn admin_get_token_stats_daily(
     Query(p): Query<StatsPeriodQuery>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let days = p.days.unwrap_or(7);
     let res = tokio::task::spawn_blocking(move || token_stats::get_daily_stats(days)).await;
 
     match res {
         Ok(Ok(stats)) => Ok(Json(stats)),
-        Ok(Err(e)) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )),
-        Err(e) => Err((
+        Ok(Err(e)) => Err(ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e)),
+        Err(e) => Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            e.to_string(),
         )),
     }
 }
 
 async fn admin_get_token_stats_weekly(
     Query(p): Query<StatsPeriodQuery>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let weeks = p.weeks.unwrap_or(4);
     let res = tokio::task::spawn_blocking(move || token_stats::get_weekly_stats(weeks)).await;
 
     match res {
         Ok(Ok(stats)) => Ok(Json(stats)),
-        Ok(Err(e)) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )),
-        Err(e) => Err((
+        Ok(Err(e)) => Err(ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e)),
+        Err(e) => Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            e.to_string(),
         )),
     }
 }
 
 async fn admin_get_token_stats_by_account(
     Query(p): Query<StatsPeriodQuery>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let hours = p.hours.unwrap_or(168);
     let res = tokio::task::spawn_blocking(move || token_stats::get_account_stats(hours)).await;
 
     match res {
         Ok(Ok(stats)) => Ok(Json(stats)),
-        Ok(Err(e)) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )),
-        Err(e) => Err((
+        Ok(Err(e)) => Err(ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e)),
+        Err(e) => Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            e.to_string(),
         )),
     }
 }
 
 async fn admin_get_token_stats_summary(
     Query(p): Query<StatsPeriodQuery>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let hours = p.hours.unwrap_or(168);
     let res = tokio::task::spawn_blocking(move || token_stats::get_summary_stats(hours)).await;
 
     match res {
         Ok(Ok(stats)) => Ok(Json(stats)),
-        Ok(Err(e)) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )),
-        Err(e) => Err((
+        Ok(Err(e)) => Err(ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e)),
+        Err(e) => Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            e.to_string(),
         )),
     }
 }
 
 async fn admin_get_token_stats_by_model(
     Query(p): Query<StatsPeriodQuery>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let hours = p.hours.unwrap_or(168);
     let res = tokio::task::spawn_blocking(move || token_stats::get_model_stats(hours)).await;
 
     match res {
         Ok(Ok(stats)) => Ok(Json(stats)),
-        Ok(Err(e)) => Err((
+        Ok(Err(e)) => Err(ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e)),
+        Err(e) => Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
+            e.to_string(),
         )),
-        Err(e) => Err((
+    }
+}
+
+async fn admin_get_quota_forecast() -> Result<impl IntoResponse, ApiError> {
+    let res = tokio::task::spawn_blocking(crate::modules::quota_forecast::refresh_forecast).await;
+
+    match res {
+        Ok(Ok(forecast)) => Ok(Json(forecast)),
+        Ok(Err(e)) => Err(ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e)),
+        Err(e) => Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            e.to_string(),
         )),
     }
 }
 
-async fn admin_get_token_stats_model_trend_hourly(
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+#[derive(Deserialize)]
+struct FailoverQuery {
+    /// 查询范围 (小时)，默认 24
+    range: Option<i64>,
+}
+
+/// 账号故障转移记录，附带按失败账号的次数汇总
+async fn admin_get_failover_events(
+    Query(q): Query<FailoverQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let range = q.range.unwrap_or(24).max(1);
+    let events = tokio::task::spawn_blocking(move || proxy_db::get_failover_events(range))
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    let mut counts: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
+    for event in &events {
+        *counts.entry(event.from_account.as_str()).or_default() += 1;
+    }
+    let mut by_account: Vec<(&str, usize)> = counts.into_iter().collect();
+    by_account.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    let by_account: Vec<serde_json::Value> = by_account
+        .into_iter()
+        .map(|(account, count)| serde_json::json!({ "account": account, "count": count }))
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "range_hours": range,
+        "total": events.len(),
+        "by_account": by_account,
+        "events": events,
+    })))
+}
+
+async fn admin_get_token_stats_model_trend_hourly() -> Result<impl IntoResponse, ApiError> {
     let res = tokio::task::spawn_blocking(|| {
         token_stats::get_model_trend_hourly(24) // Default 24 hours
     })
@@ -2009,21 +3625,15 @@ async fn admin_get_token_stats_model_trend_hourly(
 
     match res {
         Ok(Ok(stats)) => Ok(Json(stats)),
-        Ok(Err(e)) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )),
-        Err(e) => Err((
+        Ok(Err(e)) => Err(ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e)),
+        Err(e) => Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            e.to_string(),
         )),
     }
 }
 
-async fn admin_get_token_stats_model_trend_daily(
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+async fn admin_get_token_stats_model_trend_daily() -> Result<impl IntoResponse, ApiError> {
     let res = tokio::task::spawn_blocking(|| {
         token_stats::get_model_trend_daily(7) // Default 7 days
     })
@@ -2031,21 +3641,15 @@ async fn admin_get_token_stats_model_trend_daily(
 
     match res {
         Ok(Ok(stats)) => Ok(Json(stats)),
-        Ok(Err(e)) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )),
-        Err(e) => Err((
+        Ok(Err(e)) => Err(ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e)),
+        Err(e) => Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            e.to_string(),
         )),
     }
 }
 
-async fn admin_get_token_stats_account_trend_hourly(
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+async fn admin_get_token_stats_account_trend_hourly() -> Result<impl IntoResponse, ApiError> {
     let res = tokio::task::spawn_blocking(|| {
         token_stats::get_account_trend_hourly(24) // Default 24 hours
     })
@@ -2053,21 +3657,15 @@ async fn admin_get_token_stats_account_trend_hourly(
 
     match res {
         Ok(Ok(stats)) => Ok(Json(stats)),
-        Ok(Err(e)) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )),
-        Err(e) => Err((
+        Ok(Err(e)) => Err(ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e)),
+        Err(e) => Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            e.to_string(),
         )),
     }
 }
 
-async fn admin_get_token_stats_account_trend_daily(
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+async fn admin_get_token_stats_account_trend_daily() -> Result<impl IntoResponse, ApiError> {
     let res = tokio::task::spawn_blocking(|| {
         token_stats::get_account_trend_daily(7) // Default 7 days
     })
@@ -2075,39 +3673,272 @@ async fn admin_get_token_stats_account_trend_daily(
 
     match res {
         Ok(Ok(stats)) => Ok(Json(stats)),
-        Ok(Err(e)) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )),
-        Err(e) => Err((
+        Ok(Err(e)) => Err(ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e)),
+        Err(e) => Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
+            e.to_string(),
         )),
     }
 }
 
-async fn admin_clear_token_stats() -> impl IntoResponse {
-    let res = tokio::task::spawn_blocking(|| {
-        // Clear databases (brute force)
-        if let Ok(path) = token_stats::get_db_path() {
-            let _ = std::fs::remove_file(path);
+#[derive(Deserialize, Debug, Default)]
+struct TokenStatsExportQuery {
+    /// csv (默认) | json (NDJSON)
+    format: Option<String>,
+    /// 区间起点 (Unix 秒)，默认 30 天前
+    start_ts: Option<i64>,
+    /// 区间终点 (Unix 秒，不含)，默认当前时间
+    end_ts: Option<i64>,
+    /// hour (默认) | day
+    granularity: Option<String>,
+}
+
+/// 将单条记录编码为一行 CSV (含换行)
+fn csv_line<S: Serialize>(record: S) -> Option<Vec<u8>> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .buffer_capacity(256)
+        .from_writer(Vec::new());
+    writer.serialize(record).ok()?;
+    writer.into_inner().ok()
+}
+
+/// CSV 列顺序即字段顺序
+#[derive(Serialize)]
+struct TokenStatsExportRecord {
+    timestamp: i64,
+    account_id: String,
+    model: String,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    requests: u64,
+}
+
+/// 导出 Token 用量明细 (按账号 + 模型 + 时间桶聚合)，以附件形式流式下载
+async fn admin_export_token_stats(
+    Query(q): Query<TokenStatsExportQuery>,
+) -> Result<Response, ApiError> {
+    let format = q.format.as_deref().unwrap_or("csv").to_ascii_lowercase();
+    let (content_type, extension) = match format.as_str() {
+        "csv" => ("text/csv; charset=utf-8", "csv"),
+        "json" => ("application/x-ndjson", "ndjson"),
+        other => {
+            return Err(ApiError::invalid_request(format!(
+                "Unsupported format '{}', expected csv or json",
+                other
+            )))
         }
-        let _ = token_stats::init_db();
-    })
-    .await;
+    };
+    let granularity = q.granularity.as_deref().unwrap_or("hour").to_ascii_lowercase();
+    let bucket_secs = match granularity.as_str() {
+        "hour" => 3600,
+        "day" => 86400,
+        other => {
+            return Err(ApiError::invalid_request(format!(
+                "Unsupported granularity '{}', expected hour or day",
+                other
+            )))
+        }
+    };
+    let end_ts = q.end_ts.unwrap_or_else(|| chrono::Utc::now().timestamp());
+    let start_ts = q.start_ts.unwrap_or(end_ts - 30 * 86400);
+    if start_ts >= end_ts {
+        return Err(ApiError::invalid_request("start_ts must be earlier than end_ts"));
+    }
 
-    match res {
-        Ok(_) => {
-            logger::log_info("[API] 已清除所有 Token 统计数据");
-            StatusCode::OK
+    // 后台线程逐行查询并编码，通过有界通道推给响应体，避免整表载入内存
+    let (tx, rx) = tokio::sync::mpsc::channel::<Result<bytes::Bytes, std::io::Error>>(64);
+    let is_csv = extension == "csv";
+    tokio::task::spawn_blocking(move || {
+        let account_ids: std::collections::HashMap<String, String> = account::load_account_index()
+            .map(|index| index.accounts.into_iter().map(|a| (a.email, a.id)).collect())
+            .unwrap_or_default();
+
+        if is_csv {
+            let header = csv_line([
+                "timestamp",
+                "account_id",
+                "model",
+                "prompt_tokens",
+                "completion_tokens",
+                "requests",
+            ]);
+            if tx.blocking_send(Ok(bytes::Bytes::from(header.unwrap_or_default()))).is_err() {
+                return;
+            }
         }
-        Err(e) => {
-            logger::log_error(&format!("[API] 清除 Token 统计数据失败: {}", e));
-            StatusCode::INTERNAL_SERVER_ERROR
+
+        let result = token_stats::for_each_usage_bucket(start_ts, end_ts, bucket_secs, |row| {
+            let record = TokenStatsExportRecord {
+                timestamp: row.timestamp,
+                account_id: account_ids
+                    .get(&row.account_email)
+                    .cloned()
+                    .unwrap_or(row.account_email),
+                model: row.model,
+                prompt_tokens: row.prompt_tokens,
+                completion_tokens: row.completion_tokens,
+                requests: row.requests,
+            };
+            let chunk = if is_csv {
+                match csv_line(&record) {
+                    Some(line) => line,
+                    None => return false,
+                }
+            } else {
+                let mut line = serde_json::to_vec(&record).unwrap_or_default();
+                line.push(b'\n');
+                line
+            };
+            tx.blocking_send(Ok(bytes::Bytes::from(chunk))).is_ok()
+        });
+
+        if let Err(e) = result {
+            tracing::error!("[TokenStats] Export failed: {}", e);
+            let _ = tx.blocking_send(Err(std::io::Error::other(e)));
+        }
+    });
+
+    let filename = format!(
+        "token_stats_{}_{}_{}.{}",
+        granularity, start_ts, end_ts, extension
+    );
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(axum::http::header::CONTENT_TYPE, content_type)
+        .header(
+            axum::http::header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        )
+        .body(axum::body::Body::from_stream(
+            tokio_stream::wrappers::ReceiverStream::new(rx),
+        ))
+        .map_err(|e| ApiError::internal(e.to_string()))
+}
+
+#[derive(Deserialize)]
+struct ClearTokenStatsQuery {
+    /// 只删除早于该时间 (unix 秒) 的记录，缺省清空全部
+    before: Option<i64>,
+}
+
+async fn admin_clear_token_stats(
+    Extension(actor): Extension<AdminActor>,
+    Query(params): Query<ClearTokenStatsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let before = params.before;
+    let result = tokio::task::spawn_blocking(move || {
+        let result = token_stats::clear_stats(before)?;
+        // 重置依赖 Token 统计的内存缓存，后续查询立即反映删除结果
+        crate::proxy::budget::BudgetTracker::global().invalidate_usage();
+        if let Err(e) = crate::modules::quota_forecast::refresh_forecast() {
+            tracing::warn!("[API] Failed to refresh quota forecast after clearing stats: {}", e);
+        }
+        Ok::<_, String>(result)
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?
+    .map_err(|e| {
+        logger::log_error(&format!("[API] 清除 Token 统计数据失败: {}", e));
+        ApiError::internal(e)
+    })?;
+
+    let scope = match before {
+        Some(ts) => format!("早于 {} 的", ts),
+        None => "所有".to_string(),
+    };
+    logger::log_warn(&format!(
+        "[API] [Audit] 已清除{} Token 统计数据: {} 条记录, {} 条小时聚合",
+        scope, result.usage_rows, result.hourly_rows
+    ));
+
+    audit_log::record(
+        "token_stats.clear",
+        &actor.ip,
+        None,
+        None,
+        Some(serde_json::json!({
+            "before": before,
+            "deleted_rows": result.usage_rows,
+            "deleted_hourly_rows": result.hourly_rows,
+        })),
+    );
+    Ok(Json(serde_json::json!({
+        "deleted_rows": result.usage_rows,
+        "deleted_hourly_rows": result.hourly_rows,
+        "before": before,
+    })))
+}
+
+/// GET /api/system/backups - 备份列表 (最新的在前)
+async fn admin_list_backups() -> Result<impl IntoResponse, ApiError> {
+    let backups = tokio::task::spawn_blocking(crate::modules::backup::list_backups)
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))?
+        .map_err(ApiError::internal)?;
+    Ok(Json(backups))
+}
+
+/// POST /api/system/backups - 立即创建一次备份 (按配置轮换)
+async fn admin_create_backup(
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    let backup_config = config::load_app_config().map_err(ApiError::internal)?.backup;
+    let info = tokio::task::spawn_blocking(move || {
+        let info = crate::modules::backup::create_backup(backup_config.include_databases)?;
+        crate::modules::backup::rotate_backups(backup_config.keep)?;
+        Ok::<_, String>(info)
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?
+    .map_err(ApiError::internal)?;
+    logger::log_info(&format!("[API] 已创建备份 {}", info.name));
+    audit_log::record("backup.create", &actor.ip, Some(&info.name), None, None);
+    Ok(Json(info))
+}
+
+/// POST /api/system/backups/:name/restore - 校验归档后替换配置与账号数据 (需先暂停反代服务)
+async fn admin_restore_backup(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    if *state.is_running.read().await {
+        return Err(ApiError::from_status(
+            StatusCode::CONFLICT,
+            "Pause the proxy service before restoring a backup",
+        ));
+    }
+    if !crate::modules::backup::is_backup_name(&name) {
+        return Err(ApiError::invalid_request(format!("Invalid backup name: {}", name)));
+    }
+
+    let summary = tokio::task::spawn_blocking(move || crate::modules::backup::restore_backup(&name))
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))?
+        .map_err(|e| {
+            if e.starts_with("backup_not_found") {
+                ApiError::from_status(StatusCode::NOT_FOUND, e)
+            } else {
+                ApiError::invalid_request(e)
+            }
+        })?;
+    logger::log_warn(&format!(
+        "[API] 已从备份 {} 恢复 ({} 个账号，恢复前数据已备份为 {})",
+        summary.name, summary.accounts, summary.pre_restore_backup
+    ));
+
+    // 重新加载账号与配置，使恢复的数据立即生效
+    if let Err(e) = state.token_manager.load_accounts().await {
+        logger::log_warn(&format!("[API] 恢复后重新加载账号失败: {}", e));
+    }
+    if summary.config_restored {
+        if let Err(e) = reload_config_from_disk(&state).await {
+            logger::log_warn(&format!("[API] 恢复后重新加载配置失败: {}", e));
         }
     }
+    audit_log::record("backup.restore", &actor.ip, Some(&summary.name), None, None);
+    Ok(Json(summary))
 }
 
 async fn admin_get_update_settings() -> impl IntoResponse {
@@ -2122,34 +3953,31 @@ async fn admin_get_update_settings() -> impl IntoResponse {
     }
 }
 
-async fn admin_check_for_updates() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+async fn admin_check_for_updates() -> Result<impl IntoResponse, ApiError> {
     let info = crate::modules::update_checker::check_for_updates()
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     Ok(Json(info))
 }
 
 async fn admin_update_last_check_time(
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    crate::modules::update_checker::update_last_check_time().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    crate::modules::update_checker::update_last_check_time()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("update_settings.check_time", &actor.ip, None, None, None);
     Ok(StatusCode::OK)
 }
 
-async fn admin_save_update_settings(Json(settings): Json<serde_json::Value>) -> impl IntoResponse {
+async fn admin_save_update_settings(
+    Extension(actor): Extension<AdminActor>,
+    Json(settings): Json<serde_json::Value>,
+) -> impl IntoResponse {
     if let Ok(s) =
         serde_json::from_value::<crate::modules::update_checker::UpdateSettings>(settings)
     {
         let _ = crate::modules::update_checker::save_update_settings(&s);
+        audit_log::record("update_settings.save", &actor.ip, None, None, None);
         StatusCode::OK
     } else {
         StatusCode::BAD_REQUEST
@@ -2180,14 +4008,18 @@ struct BulkDeleteRequest {
 }
 
 async fn admin_delete_accounts(
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<BulkDeleteRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    crate::modules::account::delete_accounts(&payload.account_ids).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+) -> Result<impl IntoResponse, ApiError> {
+    crate::modules::account::delete_accounts(&payload.account_ids)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record(
+        "account.delete_bulk",
+        &actor.ip,
+        None,
+        Some(serde_json::json!({ "account_ids": payload.account_ids })),
+        None,
+    );
     Ok(StatusCode::OK)
 }
 
@@ -2198,15 +4030,12 @@ struct ReorderRequest {
 }
 
 async fn admin_reorder_accounts(
-    State(state): State<AppState>,
-    Json(payload): Json<ReorderRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    crate::modules::account::reorder_accounts(&payload.account_ids).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+    Json(payload): Json<ReorderRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    crate::modules::account::reorder_accounts(&payload.account_ids)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     // [FIX #1166] 排序变动后立即重新加载 TokenManager
     if let Err(e) = state.token_manager.load_accounts().await {
@@ -2216,36 +4045,22 @@ async fn admin_reorder_accounts(
         ));
     }
 
+    audit_log::record("account.reorder", &actor.ip, None, None, None);
     Ok(StatusCode::OK)
 }
 
 async fn admin_fetch_account_quota(
     Path(account_id): Path<String>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let mut account = crate::modules::load_account(&account_id).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+) -> Result<impl IntoResponse, ApiError> {
+    let mut account = crate::modules::load_account(&account_id)
+        .map_err(ApiError::from_account_error)?;
 
     let quota = crate::modules::account::fetch_quota_with_retry(&mut account)
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: e.to_string(),
-                }),
-            )
-        })?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    crate::modules::update_account_quota(&account_id, quota.clone()).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    crate::modules::update_account_quota(&account_id, quota.clone())
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     Ok(Json(quota))
 }
@@ -2259,79 +4074,254 @@ struct ToggleProxyRequest {
 
 async fn admin_toggle_proxy_status(
     State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Path(account_id): Path<String>,
     Json(payload): Json<ToggleProxyRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     crate::modules::account::toggle_proxy_status(
         &account_id,
         payload.enable,
         payload.reason.as_deref(),
     )
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
-
+    .map_err(ApiError::from_account_error)?;
+    audit_log::record(
+        "account.toggle_proxy",
+        &actor.ip,
+        Some(&account_id),
+        None,
+        Some(serde_json::json!({ "enable": payload.enable, "reason": payload.reason })),
+    );
+
+    // [NEW] 显式重新启用时清除连续硬失败计数
+    if payload.enable {
+        state.token_manager.clear_hard_failures(&account_id);
+    }
     // 同步到运行中的反代服务
     let _ = state.token_manager.reload_account(&account_id).await;
 
     Ok(StatusCode::OK)
 }
 
-async fn admin_warm_up_all_accounts() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)>
-{
-    let result = crate::commands::warm_up_all_accounts().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+async fn admin_get_proxy_disable_history(
+    Path(account_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let history = crate::modules::account::get_proxy_disable_history(&account_id)
+        .map_err(|e| ApiError::from_status(StatusCode::NOT_FOUND, e))?;
+    Ok(Json(history))
+}
+
+async fn admin_warm_up_all_accounts(
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    let result = crate::commands::warm_up_all_accounts()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("account.warm_up_all", &actor.ip, None, None, None);
     Ok(Json(result))
 }
 
+#[derive(Serialize)]
+struct WarmupScheduleResponse {
+    config: crate::proxy::config::WarmupScheduleConfig,
+    #[serde(flatten)]
+    status: crate::modules::scheduler::WarmupScheduleStatus,
+}
+
+fn warmup_schedule_response() -> Result<WarmupScheduleResponse, ApiError> {
+    let app_config = crate::modules::config::load_app_config()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(WarmupScheduleResponse {
+        config: app_config.proxy.warmup_schedule,
+        status: crate::modules::scheduler::get_warmup_schedule_status(),
+    })
+}
+
+async fn admin_get_warmup_schedule() -> Result<impl IntoResponse, ApiError> {
+    Ok(Json(warmup_schedule_response()?))
+}
+
+/// 更新定时预热配置 (后台任务每分钟读取配置，保存后即生效)
+async fn admin_update_warmup_schedule(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+    Json(schedule): Json<crate::proxy::config::WarmupScheduleConfig>,
+) -> Result<impl IntoResponse, ApiError> {
+    schedule
+        .validate()
+        .map_err(|e| ApiError::from_status(StatusCode::BAD_REQUEST, e))?;
+
+    let mut app_config = crate::modules::config::load_app_config()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let old_schedule = std::mem::replace(&mut app_config.proxy.warmup_schedule, schedule);
+    crate::modules::config::save_app_config(&app_config)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    // [FIX] 与保存配置走同一热更新流程
+    apply_app_config(&state, &app_config).await;
+
+    logger::log_info("[API] 定时预热配置已更新");
+    audit_log::record(
+        "warmup_schedule.update",
+        &actor.ip,
+        None,
+        serde_json::to_value(&old_schedule).ok(),
+        serde_json::to_value(&app_config.proxy.warmup_schedule).ok(),
+    );
+    Ok(Json(warmup_schedule_response()?))
+}
+
 async fn admin_warm_up_account(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Path(account_id): Path<String>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let result = crate::commands::warm_up_account(account_id)
+) -> Result<impl IntoResponse, ApiError> {
+    let result = crate::modules::scheduler::warm_up_and_record(&account_id)
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    // [NEW] 手动预热成功，清除连续硬失败计数
+    state.token_manager.clear_hard_failures(&account_id);
+    audit_log::record("account.warm_up", &actor.ip, Some(&account_id), None, None);
     Ok(Json(result))
 }
 
 
+/// 连通性测试默认使用的模型
+const ACCOUNT_TEST_DEFAULT_MODEL: &str = "gemini-2.5-flash";
+/// 上游错误信息最多保留的字符数
+const ACCOUNT_TEST_MAX_ERROR_CHARS: usize = 500;
+
+#[derive(Deserialize, Default)]
+struct AccountTestRequest {
+    #[serde(default)]
+    model: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AccountTestResult {
+    ok: bool,
+    latency_ms: u64,
+    model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// [NEW] 端到端连通性测试: 绕过轮询，强制使用指定账号发送一次最小的 Gemini 请求
+/// 与配额查询不同，这里验证 refresh_token -> access_token -> 上游生成的完整链路
+async fn admin_test_account(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
+    Path(account_id): Path<String>,
+    payload: Option<Json<AccountTestRequest>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let account = account::load_account(&account_id)
+        .map_err(|e| ApiError::from_status(StatusCode::NOT_FOUND, e))?;
+    let model = payload
+        .and_then(|Json(p)| p.model)
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .unwrap_or_else(|| ACCOUNT_TEST_DEFAULT_MODEL.to_string());
+
+    audit_log::record(
+        "account.test",
+        &actor.ip,
+        Some(&account_id),
+        None,
+        Some(serde_json::json!({ "model": model })),
+    );
+    let start = std::time::Instant::now();
+    let finish = |status: Option<u16>, error: Option<String>| {
+        let result = AccountTestResult {
+            ok: error.is_none(),
+            latency_ms: start.elapsed().as_millis() as u64,
+            model: model.clone(),
+            status,
+            error,
+        };
+        logger::log_info(&format!(
+            "[API] 账号 {} 连通性测试: ok={}, model={}, {}ms",
+            account.email, result.ok, result.model, result.latency_ms
+        ));
+        Json(result)
+    };
+
+    // 优先复用反代池中的 Token (含 project_id)；已禁用或未加载的账号直接刷新
+    let token = match state.token_manager.get_token_by_email(&account.email).await {
+        Ok((access_token, project_id, _, _, _)) => Ok((access_token, project_id)),
+        Err(_) => crate::modules::quota::get_valid_token_for_warmup(&account).await,
+    };
+    let (access_token, project_id) = match token {
+        Ok(t) => t,
+        Err(e) => return Ok(finish(None, Some(format!("Token refresh failed: {}", e)))),
+    };
+
+    let session_id = format!("account_test_{}", uuid::Uuid::new_v4());
+    let request = serde_json::json!({
+        "model": model,
+        "contents": [{"role": "user", "parts": [{"text": "ping"}]}],
+        "generationConfig": {"maxOutputTokens": 1, "temperature": 0}
+    });
+    let body = crate::proxy::mappers::gemini::wrapper::wrap_request(
+        &request,
+        &project_id,
+        &model,
+        Some(&session_id),
+    );
+
+    match state
+        .upstream
+        .call_v1_internal(
+            "generateContent",
+            &access_token,
+            body,
+            None,
+            Some(account.id.as_str()),
+        )
+        .await
+    {
+        Ok(call_result) => {
+            let status = call_result.response.status();
+            if status.is_success() {
+                Ok(finish(Some(status.as_u16()), None))
+            } else {
+                let text = call_result.response.text().await.unwrap_or_default();
+                let error = if text.trim().is_empty() {
+                    format!("HTTP {}", status.as_u16())
+                } else {
+                    text.chars().take(ACCOUNT_TEST_MAX_ERROR_CHARS).collect()
+                };
+                Ok(finish(Some(status.as_u16()), Some(error)))
+            }
+        }
+        Err(e) => Ok(finish(None, Some(e))),
+    }
+}
+
 async fn admin_save_http_api_settings(
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<crate::modules::http_api::HttpApiSettings>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    crate::modules::http_api::save_settings(&payload).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+) -> Result<impl IntoResponse, ApiError> {
+    crate::modules::http_api::save_settings(&payload)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record(
+        "http_api.update",
+        &actor.ip,
+        None,
+        None,
+        serde_json::to_value(&payload).ok(),
+    );
     Ok(StatusCode::OK)
 }
 
 // Cloudflared Handlers
 async fn admin_cloudflared_get_status(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     state
         .cloudflared_state
         .ensure_manager()
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     let lock = state.cloudflared_state.manager.read().await;
     if let Some(manager) = lock.as_ref() {
@@ -2351,35 +4341,51 @@ async fn admin_cloudflared_get_status(
     }
 }
 
+#[derive(Deserialize)]
+struct CloudflaredLogsQuery {
+    limit: Option<usize>,
+}
+
+async fn admin_cloudflared_get_logs(
+    State(state): State<AppState>,
+    Query(params): Query<CloudflaredLogsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    state
+        .cloudflared_state
+        .ensure_manager()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    let lock = state.cloudflared_state.manager.read().await;
+    let logs = lock
+        .as_ref()
+        .map(|manager| manager.get_logs(params.limit))
+        .unwrap_or_default();
+    Ok(Json(logs))
+}
+
 async fn admin_cloudflared_install(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
     state
         .cloudflared_state
         .ensure_manager()
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     let lock = state.cloudflared_state.manager.read().await;
     if let Some(manager) = lock.as_ref() {
-        let status = manager.install().await.map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
+        let status = manager
+            .install()
+            .await
+            .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        audit_log::record("cloudflared.install", &actor.ip, None, None, None);
         Ok(Json(status))
     } else {
-        Err((
+        Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Manager not initialized".to_string(),
-            }),
+            "Manager not initialized".to_string(),
         ))
     }
 }
@@ -2392,67 +4398,61 @@ struct CloudflaredStartRequest {
 
 async fn admin_cloudflared_start(
     State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<CloudflaredStartRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
+    // 配置校验错误 (缺少 token / hostname 非法等) 返回 400
+    crate::modules::cloudflared::validate_config(&payload.config)
+        .map_err(|e| ApiError::from_status(StatusCode::BAD_REQUEST, e))?;
+
     state
         .cloudflared_state
         .ensure_manager()
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     let lock = state.cloudflared_state.manager.read().await;
     if let Some(manager) = lock.as_ref() {
         let status = manager.start(payload.config).await.map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
+            let code = if e.starts_with("Hostname collision") {
+                StatusCode::CONFLICT
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            ApiError::from_status(code, e)
         })?;
+        audit_log::record("cloudflared.start", &actor.ip, None, None, None);
         Ok(Json(status))
     } else {
-        Err((
+        Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Manager not initialized".to_string(),
-            }),
+            "Manager not initialized".to_string(),
         ))
     }
 }
 
 async fn admin_cloudflared_stop(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
     state
         .cloudflared_state
         .ensure_manager()
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     let lock = state.cloudflared_state.manager.read().await;
     if let Some(manager) = lock.as_ref() {
-        let status = manager.stop().await.map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
+        let status = manager
+            .stop()
+            .await
+            .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        audit_log::record("cloudflared.stop", &actor.ip, None, None, None);
         Ok(Json(status))
     } else {
-        Err((
+        Err(ApiError::from_status(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Manager not initialized".to_string(),
-            }),
+            "Manager not initialized".to_string(),
         ))
     }
 }
@@ -2462,31 +4462,22 @@ async fn admin_cloudflared_stop(
 async fn admin_get_device_profiles(
     State(_state): State<AppState>,
     Path(account_id): Path<String>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let profiles = account::get_device_profiles(&account_id).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+) -> Result<impl IntoResponse, ApiError> {
+    let profiles = account::get_device_profiles(&account_id)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     Ok(Json(profiles))
 }
 
 async fn admin_list_device_versions(
     State(_state): State<AppState>,
     Path(account_id): Path<String>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let profiles = account::get_device_profiles(&account_id).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+) -> Result<impl IntoResponse, ApiError> {
+    let profiles = account::get_device_profiles(&account_id)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     Ok(Json(profiles))
 }
 
-async fn admin_preview_generate_profile(
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+async fn admin_preview_generate_profile() -> Result<impl IntoResponse, ApiError> {
     let profile = crate::modules::device::generate_profile();
     Ok(Json(profile))
 }
@@ -2526,9 +4517,10 @@ impl From<DeviceProfileApiWrapper> for crate::models::account::DeviceProfile {
 
 async fn admin_bind_device_profile_with_profile(
     State(_state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Path(account_id): Path<String>,
     Json(payload): Json<BindDeviceProfileWrapper>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     // 优先使用 payload 中的 account_id（前端发送的），如果没有则使用路径参数
     let target_account_id = if !payload.account_id.is_empty() {
         &payload.account_id
@@ -2537,63 +4529,65 @@ async fn admin_bind_device_profile_with_profile(
     };
     
     let profile: crate::models::account::DeviceProfile = payload.profile_wrapper.into();
-    
-    let result =
-        account::bind_device_profile_with_profile(target_account_id, profile, None).map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
+
+    let result = account::bind_device_profile_with_profile(target_account_id, profile, None)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("account.bind_device", &actor.ip, Some(target_account_id), None, None);
     Ok(Json(result))
 }
 
 async fn admin_restore_original_device(
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let msg = account::restore_original_device().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    let msg = account::restore_original_device()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("device.restore_original", &actor.ip, None, None, None);
     Ok(Json(msg))
 }
 
 async fn admin_restore_device_version(
     State(_state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Path((account_id, version_id)): Path<(String, String)>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let profile = account::restore_device_version(&account_id, &version_id).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+) -> Result<impl IntoResponse, ApiError> {
+    let profile = account::restore_device_version(&account_id, &version_id)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record(
+        "account.restore_device_version",
+        &actor.ip,
+        Some(&account_id),
+        None,
+        Some(serde_json::json!({ "version_id": version_id })),
+    );
     Ok(Json(profile))
 }
 
 async fn admin_delete_device_version(
     State(_state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Path((account_id, version_id)): Path<(String, String)>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    account::delete_device_version(&account_id, &version_id).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+) -> Result<impl IntoResponse, ApiError> {
+    account::delete_device_version(&account_id, &version_id)
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record(
+        "account.delete_device_version",
+        &actor.ip,
+        Some(&account_id),
+        None,
+        Some(serde_json::json!({ "version_id": version_id })),
+    );
     Ok(StatusCode::NO_CONTENT)
 }
 
-async fn admin_open_folder() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+async fn admin_open_folder(
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
     // Note: In Web mode, this may not actually open a local folder unless the backend handles it.
     // For ABV_Refactor, the backend should use opener to open it on the server (the desktop).
-    crate::commands::open_data_folder().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    crate::commands::open_data_folder()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("data_folder.open", &actor.ip, None, None, None);
     Ok(StatusCode::OK)
 }
 
@@ -2601,50 +4595,50 @@ async fn admin_open_folder() -> Result<impl IntoResponse, (StatusCode, Json<Erro
 
 async fn admin_import_v1_accounts(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let accounts = migration::import_from_v1().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    let accounts = migration::import_from_v1()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     // [FIX #1166] 导入后立即加载
     let _ = state.token_manager.load_accounts().await;
 
-    let current_id = state.account_service.get_current_id().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    let current_id = state
+        .account_service
+        .get_current_id()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     let responses: Vec<AccountResponse> = accounts
         .iter()
-        .map(|a| to_account_response(a, &current_id))
+        .map(|a| to_account_response(a, &current_id, state.token_manager.consecutive_failures(&a.id)))
         .collect();
+    audit_log::record(
+        "account.import_v1",
+        &actor.ip,
+        None,
+        None,
+        Some(serde_json::json!({ "count": accounts.len() })),
+    );
     Ok(Json(responses))
 }
 
 async fn admin_import_from_db(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let account = migration::import_from_db().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    let account = migration::import_from_db()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     // [FIX #1166] 导入后立即加载
     let _ = state.token_manager.load_accounts().await;
 
-    let current_id = state.account_service.get_current_id().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
-    Ok(Json(to_account_response(&account, &current_id)))
+    let current_id = state
+        .account_service
+        .get_current_id()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("account.import_db", &actor.ip, Some(&account.email), None, None);
+    Ok(Json(to_account_response(&account, &current_id, state.token_manager.consecutive_failures(&account.id))))
 }
 
 #[derive(Deserialize)]
@@ -2654,42 +4648,36 @@ struct CustomDbRequest {
 
 async fn admin_import_custom_db(
     State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<CustomDbRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     // [SECURITY] 禁止目录遍历
     if payload.path.contains("..") {
-        return Err((
+        return Err(ApiError::from_status(
             StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "非法路径: 不允许目录遍历".to_string(),
-            }),
+            "非法路径: 不允许目录遍历".to_string(),
         ));
     }
 
     let account = migration::import_from_custom_db_path(payload.path)
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     // [FIX #1166] 导入后立即加载
     let _ = state.token_manager.load_accounts().await;
 
-    let current_id = state.account_service.get_current_id().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
-    Ok(Json(to_account_response(&account, &current_id)))
+    let current_id = state
+        .account_service
+        .get_current_id()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("account.import_custom_db", &actor.ip, Some(&account.email), None, None);
+    Ok(Json(to_account_response(&account, &current_id, state.token_manager.consecutive_failures(&account.id))))
 }
 
 async fn admin_sync_account_from_db(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
     // 逻辑参考自 sync_account_from_db command
     let db_refresh_token = match migration::get_refresh_token_from_db() {
         Ok(token) => token,
@@ -2697,12 +4685,8 @@ async fn admin_sync_account_from_db(
             return Ok(Json(None));
         }
     };
-    let curr_account = account::get_current_account().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    let curr_account = account::get_current_account()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     if let Some(acc) = curr_account {
         if acc.token.refresh_token == db_refresh_token {
@@ -2710,23 +4694,19 @@ async fn admin_sync_account_from_db(
         }
     }
 
-    let account = migration::import_from_db().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    let account = migration::import_from_db()
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     // [FIX #1166] 同步后立即重新加载 TokenManager
     let _ = state.token_manager.load_accounts().await;
 
-    let current_id = state.account_service.get_current_id().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
-    Ok(Json(Some(to_account_response(&account, &current_id))))
+    let current_id = state
+        .account_service
+        .get_current_id()
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("account.sync_from_db", &actor.ip, Some(&account.email), None, None);
+    Ok(Json(Some(to_account_response(&account, &current_id, state.token_manager.consecutive_failures(&account.id)))))
 }
 
 // --- CLI Sync Handlers ---
@@ -2740,16 +4720,11 @@ struct CliSyncStatusRequest {
 
 async fn admin_get_cli_sync_status(
     Json(payload): Json<CliSyncStatusRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     crate::proxy::cli_sync::get_cli_sync_status(payload.app_type, payload.proxy_url)
         .await
         .map(Json)
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 #[derive(Deserialize)]
@@ -2762,17 +4737,26 @@ struct CliSyncRequest {
 }
 
 async fn admin_execute_cli_sync(
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<CliSyncRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    crate::proxy::cli_sync::execute_cli_sync(payload.app_type, payload.proxy_url, payload.api_key, payload.model)
-        .await
-        .map(|_| StatusCode::OK)
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })
+) -> Result<impl IntoResponse, ApiError> {
+    let app = payload.app_type.as_str();
+    crate::proxy::cli_sync::execute_cli_sync(
+        payload.app_type,
+        payload.proxy_url.clone(),
+        payload.api_key,
+        payload.model,
+    )
+    .await
+    .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record(
+        "cli.sync",
+        &actor.ip,
+        Some(app),
+        None,
+        Some(serde_json::json!({ "proxy_url": payload.proxy_url })),
+    );
+    Ok(StatusCode::OK)
 }
 
 #[derive(Deserialize)]
@@ -2782,17 +4766,48 @@ struct CliRestoreRequest {
 }
 
 async fn admin_execute_cli_restore(
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<CliRestoreRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
+    let app = payload.app_type.as_str();
     crate::proxy::cli_sync::execute_cli_restore(payload.app_type)
         .await
-        .map(|_| StatusCode::OK)
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("cli.restore", &actor.ip, Some(app), None, None);
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CliSyncAllRequest {
+    proxy_url: String,
+    api_key: String,
+}
+
+// [NEW] 一次同步所有已安装的 CLI，返回按 app 区分的结果
+async fn admin_execute_cli_sync_all(
+    Extension(actor): Extension<AdminActor>,
+    Json(payload): Json<CliSyncAllRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let results =
+        crate::proxy::cli_sync::execute_cli_sync_all(payload.proxy_url.clone(), payload.api_key)
+            .await;
+    audit_log::record(
+        "cli.sync_all",
+        &actor.ip,
+        None,
+        None,
+        Some(serde_json::json!({ "proxy_url": payload.proxy_url })),
+    );
+    Ok(Json(results))
+}
+
+async fn admin_execute_cli_restore_all(
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
+    let results = crate::proxy::cli_sync::execute_cli_restore_all().await;
+    audit_log::record("cli.restore_all", &actor.ip, None, None, None);
+    Ok(Json(results))
 }
 
 #[derive(Deserialize)]
@@ -2804,16 +4819,11 @@ struct CliConfigContentRequest {
 
 async fn admin_get_cli_config_content(
     Json(payload): Json<CliConfigContentRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     crate::proxy::cli_sync::get_cli_config_content(payload.app_type, payload.file_name)
         .await
         .map(Json)
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 #[derive(Deserialize)]
@@ -2942,7 +4952,7 @@ async fn handle_oauth_callback(
 async fn admin_prepare_oauth_url_web(
     headers: HeaderMap,
     State(state): State<AppState>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     let port = state.security.read().await.port;
     let host = headers.get("host").and_then(|h| h.to_str().ok());
     let proto = headers
@@ -2957,12 +4967,7 @@ async fn admin_prepare_oauth_url_web(
         redirect_uri.clone(),
         state_str.clone(),
     )
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })?;
+    .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     // 启动后台任务处理回调/手动提交的代码
     let token_manager = state.token_manager.clone();
@@ -3033,11 +5038,14 @@ async fn admin_prepare_oauth_url_web(
 
 /// 辅助函数：获取 OAuth 重定向 URI
 /// 强制使用 localhost，以绕过 Google 2.0 政策对 IP 地址和非 HTTPS 环境的拦截。
-/// 只有在显式设置了 ABV_PUBLIC_URL (例如用户配置了 HTTPS 域名) 时才会使用外部地址。
+/// 只有在显式设置了 ABV_PUBLIC_URL (例如用户配置了 HTTPS 域名) 时才会使用外部地址；
+/// 运行中的 Cloudflared 命名隧道 (固定域名) 优先于请求的 Host 头。
 fn get_oauth_redirect_uri(port: u16, _host: Option<&str>, _proto: Option<&str>) -> String {
     if let Ok(public_url) = std::env::var("ABV_PUBLIC_URL") {
         let base = public_url.trim_end_matches('/');
         format!("{}/auth/callback", base)
+    } else if let Some(tunnel_url) = crate::modules::cloudflared::named_tunnel_public_url() {
+        format!("{}/auth/callback", tunnel_url.trim_end_matches('/'))
     } else {
         // 强制返回 localhost。远程部署时，用户可通过回填功能完成授权。
         format!("http://localhost:{}/auth/callback", port)
@@ -3071,23 +5079,23 @@ struct IpAccessLogResponse {
 
 async fn admin_get_ip_access_logs(
     Query(q): Query<IpAccessLogQuery>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let offset = (q.page.max(1) - 1) * q.page_size;
-    let logs = security_db::get_ip_access_logs(
-        q.page_size,
-        offset,
-        q.search.as_deref(),
-        q.blocked_only,
-    ).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+    let logs =
+        security_db::get_ip_access_logs(q.page_size, offset, q.search.as_deref(), q.blocked_only)
+            .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     let total = logs.len(); // Simple total
     
     Ok(Json(IpAccessLogResponse { logs, total }))
 }
 
-async fn admin_clear_ip_access_logs() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+async fn admin_clear_ip_access_logs(
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
     security_db::clear_ip_access_logs()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("ip_access_log.clear", &actor.ip, None, None, None);
     Ok(StatusCode::OK)
 }
 
@@ -3099,11 +5107,11 @@ struct IpStatsResponse {
     top_ips: Vec<crate::modules::security_db::IpRanking>,
 }
 
-async fn admin_get_ip_stats() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+async fn admin_get_ip_stats() -> Result<impl IntoResponse, ApiError> {
     let stats = security_db::get_ip_stats()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     let top_ips = security_db::get_top_ips(10, 24)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     let response = IpStatsResponse {
         total_requests: stats.total_requests as usize,
@@ -3122,17 +5130,26 @@ struct IpTokenStatsQuery {
 
 async fn admin_get_ip_token_stats(
     Query(q): Query<IpTokenStatsQuery>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let stats = proxy_db::get_token_usage_by_ip(
-        q.limit.unwrap_or(100),
-        q.hours.unwrap_or(720)
-    ).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+) -> Result<impl IntoResponse, ApiError> {
+    let stats = proxy_db::get_token_usage_by_ip(q.limit.unwrap_or(100), q.hours.unwrap_or(720))
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     Ok(Json(stats))
 }
 
-async fn admin_get_ip_blacklist() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+/// 校验 IP / CIDR (IPv4 或 IPv6) 格式
+fn validate_ip_pattern(pattern: &str) -> Result<(), ApiError> {
+    if crate::proxy::security::parse_cidr(pattern).is_some() {
+        return Ok(());
+    }
+    Err(ApiError::from_status(StatusCode::BAD_REQUEST, format!(
+                "Invalid IP pattern {:?}. Use an IP address or CIDR notation (e.g., 10.0.0.0/8, fd00::/8)",
+                pattern
+            )))
+}
+
+async fn admin_get_ip_blacklist() -> Result<impl IntoResponse, ApiError> {
     let list = security_db::get_blacklist()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     Ok(Json(list))
 }
 
@@ -3144,15 +5161,19 @@ struct AddBlacklistRequest {
 }
 
 async fn admin_add_ip_to_blacklist(
+    Extension(actor): Extension<AdminActor>,
     Json(req): Json<AddBlacklistRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
+    validate_ip_pattern(&req.ip_pattern)?;
     security_db::add_to_blacklist(
         &req.ip_pattern,
         req.reason.as_deref(),
         req.expires_at,
         "manual",
-    ).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+    )
+    .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
+    audit_log::record("ip_blacklist.add", &actor.ip, Some(&req.ip_pattern), None, None);
     Ok(StatusCode::CREATED)
 }
 
@@ -3163,28 +5184,36 @@ struct RemoveIpRequest {
 }
 
 async fn admin_remove_ip_from_blacklist(
+    Extension(actor): Extension<AdminActor>,
     Query(q): Query<RemoveIpRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let entries = security_db::get_blacklist()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     
     if let Some(entry) = entries.iter().find(|e| e.ip_pattern == q.ip_pattern) {
         security_db::remove_from_blacklist(&entry.id)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+            .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     } else {
-        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: format!("IP pattern {} not found", q.ip_pattern) })));
+        return Err(ApiError::from_status(
+            StatusCode::NOT_FOUND,
+            format!("IP pattern {} not found", q.ip_pattern),
+        ));
     }
     
+    audit_log::record("ip_blacklist.remove", &actor.ip, Some(&q.ip_pattern), None, None);
     Ok(StatusCode::OK)
 }
 
-async fn admin_clear_ip_blacklist() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+async fn admin_clear_ip_blacklist(
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
     let entries = security_db::get_blacklist()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     for entry in entries {
         security_db::remove_from_blacklist(&entry.ip_pattern)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+            .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     }
+    audit_log::record("ip_blacklist.clear", &actor.ip, None, None, None);
     Ok(StatusCode::OK)
 }
 
@@ -3196,15 +5225,15 @@ struct CheckIpQuery {
 
 async fn admin_check_ip_in_blacklist(
     Query(q): Query<CheckIpQuery>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let result = security_db::is_ip_in_blacklist(&q.ip)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     Ok(Json(serde_json::json!({ "result": result })))
 }
 
-async fn admin_get_ip_whitelist() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+async fn admin_get_ip_whitelist() -> Result<impl IntoResponse, ApiError> {
     let list = security_db::get_whitelist()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     Ok(Json(list))
 }
 
@@ -3215,53 +5244,81 @@ struct AddWhitelistRequest {
 }
 
 async fn admin_add_ip_to_whitelist(
+    Extension(actor): Extension<AdminActor>,
     Json(req): Json<AddWhitelistRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    security_db::add_to_whitelist(
-        &req.ip_pattern,
-        req.description.as_deref(),
-    ).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+) -> Result<impl IntoResponse, ApiError> {
+    validate_ip_pattern(&req.ip_pattern)?;
+    security_db::add_to_whitelist(&req.ip_pattern, req.description.as_deref())
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("ip_whitelist.add", &actor.ip, Some(&req.ip_pattern), None, None);
     Ok(StatusCode::CREATED)
 }
 
 async fn admin_remove_ip_from_whitelist(
+    Extension(actor): Extension<AdminActor>,
     Query(q): Query<RemoveIpRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let entries = security_db::get_whitelist()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     
     if let Some(entry) = entries.iter().find(|e| e.ip_pattern == q.ip_pattern) {
         security_db::remove_from_whitelist(&entry.id)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+            .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     } else {
-        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: format!("IP pattern {} not found", q.ip_pattern) })));
+        return Err(ApiError::from_status(
+            StatusCode::NOT_FOUND,
+            format!("IP pattern {} not found", q.ip_pattern),
+        ));
     }
+    audit_log::record("ip_whitelist.remove", &actor.ip, Some(&q.ip_pattern), None, None);
     Ok(StatusCode::OK)
 }
 
-async fn admin_clear_ip_whitelist() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+async fn admin_clear_ip_whitelist(
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
     let entries = security_db::get_whitelist()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     for entry in entries {
         security_db::remove_from_whitelist(&entry.ip_pattern)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+            .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     }
+    audit_log::record("ip_whitelist.clear", &actor.ip, None, None, None);
     Ok(StatusCode::OK)
 }
 
 async fn admin_check_ip_in_whitelist(
     Query(q): Query<CheckIpQuery>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let result = security_db::is_ip_in_whitelist(&q.ip)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     Ok(Json(serde_json::json!({ "result": result })))
 }
 
+#[derive(Deserialize)]
+struct TestIpRequest {
+    ip: String,
+}
+
+async fn admin_test_ip_rules(
+    State(state): State<AppState>,
+    Json(req): Json<TestIpRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let security = state.security.read().await.clone();
+    let ip = req.ip;
+    let report = tokio::task::spawn_blocking(move || {
+        crate::proxy::middleware::ip_filter::explain_ip(&security, &ip)
+    })
+    .await
+    .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(report))
+}
+
 async fn admin_get_security_config(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let app_config = crate::modules::config::load_app_config()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e.to_string() })))?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     
     Ok(Json(app_config.proxy.security_monitor))
 }
@@ -3273,16 +5330,19 @@ struct UpdateSecurityConfigWrapper {
 
 async fn admin_update_security_config(
     State(state): State<AppState>,
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<UpdateSecurityConfigWrapper>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let config = payload.config;
     let mut app_config = crate::modules::config::load_app_config()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e.to_string() })))?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
         
+    let old_config = app_config.clone();
     app_config.proxy.security_monitor = config.clone();
     
     crate::modules::config::save_app_config(&app_config)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e.to_string() })))?;
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    audit_config_change("security.update", &actor, Some(&old_config), &app_config);
 
     {
         let mut sec = state.security.write().await;
@@ -3295,13 +5355,19 @@ async fn admin_update_security_config(
 
 // --- Debug Console Handlers ---
 
-async fn admin_enable_debug_console() -> impl IntoResponse {
+async fn admin_enable_debug_console(
+    Extension(actor): Extension<AdminActor>,
+) -> impl IntoResponse {
     crate::modules::log_bridge::enable_log_bridge();
+    audit_log::record("debug_console.enable", &actor.ip, None, None, None);
     StatusCode::OK
 }
 
-async fn admin_disable_debug_console() -> impl IntoResponse {
+async fn admin_disable_debug_console(
+    Extension(actor): Extension<AdminActor>,
+) -> impl IntoResponse {
     crate::modules::log_bridge::disable_log_bridge();
+    audit_log::record("debug_console.disable", &actor.ip, None, None, None);
     StatusCode::OK
 }
 
@@ -3314,11 +5380,30 @@ async fn admin_get_debug_console_logs() -> impl IntoResponse {
     Json(logs)
 }
 
-async fn admin_clear_debug_console_logs() -> impl IntoResponse {
+async fn admin_clear_debug_console_logs(
+    Extension(actor): Extension<AdminActor>,
+) -> impl IntoResponse {
     crate::modules::log_bridge::clear_log_buffer();
+    audit_log::record("debug_console.clear", &actor.ip, None, None, None);
     StatusCode::OK
 }
 
+#[derive(Deserialize)]
+struct DebugCapturesQuery {
+    limit: Option<usize>,
+}
+
+async fn admin_list_debug_captures(
+    State(state): State<AppState>,
+    Query(q): Query<DebugCapturesQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let cfg = state.debug_logging.read().await.clone();
+    let files = crate::proxy::debug_logger::list_captures(&cfg, q.limit.unwrap_or(100))
+        .await
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(files))
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct OpencodeSyncStatusRequest {
@@ -3327,16 +5412,11 @@ struct OpencodeSyncStatusRequest {
 
 async fn admin_get_opencode_sync_status(
     Json(payload): Json<OpencodeSyncStatusRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     crate::proxy::opencode_sync::get_opencode_sync_status(payload.proxy_url)
         .await
         .map(Json)
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 #[derive(Deserialize)]
@@ -3350,35 +5430,38 @@ struct OpencodeSyncRequest {
 }
 
 async fn admin_execute_opencode_sync(
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<OpencodeSyncRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     crate::proxy::opencode_sync::execute_opencode_sync(
-        payload.proxy_url,
+        payload.proxy_url.clone(),
         payload.api_key,
         Some(payload.sync_accounts),
         payload.models,
     )
     .await
-    .map(|_| StatusCode::OK)
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        )
-    })
+    .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record(
+        "cli.sync",
+        &actor.ip,
+        Some("opencode"),
+        None,
+        Some(serde_json::json!({
+            "proxy_url": payload.proxy_url,
+            "sync_accounts": payload.sync_accounts,
+        })),
+    );
+    Ok(StatusCode::OK)
 }
 
 async fn admin_execute_opencode_restore(
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
     crate::proxy::opencode_sync::execute_opencode_restore()
         .await
-        .map(|_| StatusCode::OK)
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: e }),
-            )
-        })
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("cli.restore", &actor.ip, Some("opencode"), None, None);
+    Ok(StatusCode::OK)
 }
 
 #[derive(Deserialize)]
@@ -3389,19 +5472,15 @@ struct GetOpencodeConfigRequest {
 
 async fn admin_get_opencode_config_content(
     Json(payload): Json<GetOpencodeConfigRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let file_name = payload.file_name;
-    tokio::task::spawn_blocking(move || crate::proxy::opencode_sync::read_opencode_config_content(file_name))
-        .await
-        .map_err(|e| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e.to_string() }),
-        ))?
-        .map(Json)
-        .map_err(|e| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        ))
+    tokio::task::spawn_blocking(move || {
+        crate::proxy::opencode_sync::read_opencode_config_content(file_name)
+    })
+    .await
+    .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map(Json)
+    .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 // ── Droid (Factory CLI) Sync Admin Handlers ──
@@ -3414,14 +5493,11 @@ struct DroidSyncStatusRequest {
 
 async fn admin_get_droid_sync_status(
     Json(payload): Json<DroidSyncStatusRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     crate::proxy::droid_sync::get_droid_sync_status(payload.proxy_url)
         .await
         .map(Json)
-        .map_err(|e| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        ))
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 #[derive(Deserialize)]
@@ -3431,37 +5507,35 @@ struct DroidSyncRequest {
 }
 
 async fn admin_execute_droid_sync(
+    Extension(actor): Extension<AdminActor>,
     Json(payload): Json<DroidSyncRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    crate::proxy::droid_sync::execute_droid_sync(
-        payload.custom_models,
-    )
+) -> Result<impl IntoResponse, ApiError> {
+    let count = crate::proxy::droid_sync::execute_droid_sync(payload.custom_models)
         .await
-        .map(|count| Json(serde_json::json!({ "added": count })))
-        .map_err(|e| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        ))
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record(
+        "cli.sync",
+        &actor.ip,
+        Some("droid"),
+        None,
+        Some(serde_json::json!({ "added": count })),
+    );
+    Ok(Json(serde_json::json!({ "added": count })))
 }
 
 async fn admin_execute_droid_restore(
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    Extension(actor): Extension<AdminActor>,
+) -> Result<impl IntoResponse, ApiError> {
     crate::proxy::droid_sync::execute_droid_restore()
         .await
-        .map(|_| StatusCode::OK)
-        .map_err(|e| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        ))
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    audit_log::record("cli.restore", &actor.ip, Some("droid"), None, None);
+    Ok(StatusCode::OK)
 }
 
-async fn admin_get_droid_config_content(
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+async fn admin_get_droid_config_content() -> Result<impl IntoResponse, ApiError> {
     crate::proxy::droid_sync::get_droid_config_content()
         .await
         .map(Json)
-        .map_err(|e| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse { error: e }),
-        ))
+        .map_err(|e| ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, e))
 }
@@ -1,6 +1,9 @@
+use rusqlite::{params, Connection};
 use std::collections::HashMap;
-use std::sync::{Mutex, OnceLock};
-use std::time::{Duration, SystemTime};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 // Node.js proxy uses 2 hours TTL
 const SIGNATURE_TTL: Duration = Duration::from_secs(2 * 60 * 60);
@@ -11,6 +14,10 @@ const TOOL_CACHE_LIMIT: usize = 500;      // Layer 1: Tool-specific signatures
 const FAMILY_CACHE_LIMIT: usize = 200;    // Layer 2: Model family mappings
 const SESSION_CACHE_LIMIT: usize = 1000;  // Layer 3: Session-based signatures (largest)
 
+// [NEW] Session 签名持久化: 写入防抖间隔与数据库文件名
+const PERSIST_DEBOUNCE: Duration = Duration::from_secs(5);
+const PERSIST_DB_FILE: &str = "signature_cache.db";
+
 /// Cache entry with timestamp for TTL
 #[derive(Clone, Debug)]
 struct CacheEntry<T> {
@@ -34,10 +41,16 @@ impl<T> CacheEntry<T> {
     }
 
     fn is_expired(&self) -> bool {
-        self.timestamp.elapsed().unwrap_or(Duration::ZERO) > SIGNATURE_TTL
+        self.is_expired_after(SIGNATURE_TTL)
+    }
+
+    fn is_expired_after(&self, ttl: Duration) -> bool {
+        self.timestamp.elapsed().unwrap_or(Duration::ZERO) > ttl
     }
 }
 
+type SessionMap = HashMap<String, CacheEntry<SessionSignatureEntry>>;
+
 /// Triple-layer signature cache to handle:
 /// 1. Signature recovery for tool calls (when clients strip them)
 /// 2. Cross-model compatibility checks (preventing Claude signatures on Gemini models)
@@ -57,7 +70,16 @@ pub struct SignatureCache {
     /// Key: session fingerprint (e.g., "sid-a1b2c3d4...")
     /// Value: The most recent valid thought signature for this session
     /// This prevents signature pollution between different conversations
-    session_signatures: Mutex<HashMap<String, CacheEntry<SessionSignatureEntry>>>,
+    session_signatures: Arc<Mutex<SessionMap>>,
+
+    /// [NEW] Layer 3 持久化数据库路径 (None = 未启用持久化)
+    persist_path: Arc<Mutex<Option<PathBuf>>>,
+
+    /// 是否已有待执行的防抖写入
+    persist_pending: Arc<AtomicBool>,
+
+    /// Layer 3 会话签名 TTL (秒)，可通过配置调整
+    session_ttl_secs: AtomicU64,
 }
 
 impl SignatureCache {
@@ -65,7 +87,10 @@ impl SignatureCache {
         Self {
             tool_signatures: Mutex::new(HashMap::new()),
             thinking_families: Mutex::new(HashMap::new()),
-            session_signatures: Mutex::new(HashMap::new()),
+            session_signatures: Arc::new(Mutex::new(HashMap::new())),
+            persist_path: Arc::new(Mutex::new(None)),
+            persist_pending: Arc::new(AtomicBool::new(false)),
+            session_ttl_secs: AtomicU64::new(SIGNATURE_TTL.as_secs()),
         }
     }
 
@@ -159,11 +184,13 @@ impl SignatureCache {
             return;
         }
 
+        let ttl = self.session_ttl();
+        let mut stored = false;
         if let Ok(mut cache) = self.session_signatures.lock() {
             let should_store = match cache.get(session_id) {
                 None => true,
                 Some(existing) => {
-                    if existing.is_expired() {
+                    if existing.is_expired_after(ttl) {
                         true
                     } else if message_count < existing.data.message_count {
                         // [CRITICAL] Rewind detected: user deleted messages or reverted to an earlier state.
@@ -200,12 +227,13 @@ impl SignatureCache {
                         message_count 
                     })
                 );
+                stored = true;
             }
 
             // Cleanup when limit is reached (Session cache has largest limit)
             if cache.len() > SESSION_CACHE_LIMIT {
                 let before = cache.len();
-                cache.retain(|_, v| !v.is_expired());
+                cache.retain(|_, v| !v.is_expired_after(ttl));
                 let after = cache.len();
                 if before != after {
                     tracing::info!(
@@ -217,6 +245,10 @@ impl SignatureCache {
                 }
             }
         }
+
+        if stored {
+            self.schedule_persist();
+        }
     }
 
     /// Retrieve the latest thinking signature for a session.
@@ -224,7 +256,7 @@ impl SignatureCache {
     pub fn get_session_signature(&self, session_id: &str) -> Option<String> {
         if let Ok(cache) = self.session_signatures.lock() {
             if let Some(entry) = cache.get(session_id) {
-                if !entry.is_expired() {
+                if !entry.is_expired_after(self.session_ttl()) {
                     tracing::debug!(
                         "[SignatureCache] Session {} -> HIT (len={})",
                         session_id,
@@ -241,11 +273,97 @@ impl SignatureCache {
 
     /// 删除指定会话的缓存签名
     pub fn delete_session_signature(&self, session_id: &str) {
+        let mut removed = false;
         if let Ok(mut cache) = self.session_signatures.lock() {
             if cache.remove(session_id).is_some() {
                 tracing::debug!("[SignatureCache] Deleted session signature for: {}", session_id);
+                removed = true;
             }
         }
+        if removed {
+            self.schedule_persist();
+        }
+    }
+
+    // ===== Layer 3 持久化 (SQLite) =====
+
+    fn session_ttl(&self) -> Duration {
+        Duration::from_secs(self.session_ttl_secs.load(Ordering::Relaxed))
+    }
+
+    /// 配置会话签名持久化。
+    /// `data_dir` 为 Some 时启用: 从 `<data_dir>/signature_cache.db` 加载未过期条目;
+    /// 为 None 时关闭持久化 (内存缓存保持不变)。
+    pub fn configure_persistence(&self, data_dir: Option<&Path>, ttl: Duration) -> Result<(), String> {
+        self.session_ttl_secs.store(ttl.as_secs(), Ordering::Relaxed);
+
+        let Some(data_dir) = data_dir else {
+            if let Ok(mut path) = self.persist_path.lock() {
+                if path.take().is_some() {
+                    tracing::info!("[SignatureCache] Session signature persistence disabled");
+                }
+            }
+            return Ok(());
+        };
+
+        let db_path = data_dir.join(PERSIST_DB_FILE);
+        let already_enabled = self
+            .persist_path
+            .lock()
+            .map(|p| p.as_deref() == Some(db_path.as_path()))
+            .unwrap_or(false);
+        if already_enabled {
+            return Ok(());
+        }
+
+        let loaded = load_session_entries(&db_path, ttl)?;
+        let count = loaded.len();
+        if let Ok(mut cache) = self.session_signatures.lock() {
+            for (session_id, entry) in loaded {
+                // 内存中已有更新的条目时不覆盖
+                let newer_in_memory = cache
+                    .get(&session_id)
+                    .map(|e| e.timestamp >= entry.timestamp)
+                    .unwrap_or(false);
+                if !newer_in_memory {
+                    cache.insert(session_id, entry);
+                }
+            }
+        }
+        if let Ok(mut path) = self.persist_path.lock() {
+            *path = Some(db_path.clone());
+        }
+        tracing::info!(
+            "[SignatureCache] Session signature persistence enabled ({}), restored {} entries",
+            db_path.display(),
+            count
+        );
+        Ok(())
+    }
+
+    /// 立即将会话签名写入数据库 (未启用持久化时为空操作)
+    pub fn flush_persistence(&self) -> Result<(), String> {
+        flush_sessions(&self.persist_path, &self.session_signatures, self.session_ttl())
+    }
+
+    /// 防抖写入: 多次修改合并为一次落盘
+    fn schedule_persist(&self) {
+        let enabled = self.persist_path.lock().map(|p| p.is_some()).unwrap_or(false);
+        if !enabled || self.persist_pending.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let path = self.persist_path.clone();
+        let sessions = self.session_signatures.clone();
+        let pending = self.persist_pending.clone();
+        let ttl = self.session_ttl();
+        std::thread::spawn(move || {
+            std::thread::sleep(PERSIST_DEBOUNCE);
+            pending.store(false, Ordering::Release);
+            if let Err(e) = flush_sessions(&path, &sessions, ttl) {
+                tracing::warn!("[SignatureCache] Failed to persist session signatures: {}", e);
+            }
+        });
     }
 
     /// Clear all caches (for testing or manual reset)
@@ -263,6 +381,121 @@ impl SignatureCache {
     }
 }
 
+/// 根据实验性配置应用会话签名持久化设置
+pub fn apply_persistence_config(config: &crate::proxy::config::ExperimentalConfig) {
+    let ttl = Duration::from_secs(config.signature_cache_ttl_secs);
+    let data_dir = if config.signature_cache_persistence {
+        match crate::modules::account::get_data_dir() {
+            Ok(dir) => Some(dir),
+            Err(e) => {
+                tracing::warn!("[SignatureCache] Cannot resolve data dir for persistence: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    if let Err(e) = SignatureCache::global().configure_persistence(data_dir.as_deref(), ttl) {
+        tracing::warn!("[SignatureCache] Failed to enable persistence: {}", e);
+    }
+}
+
+fn to_unix_secs(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+fn open_persist_db(db_path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_signatures (
+            session_id TEXT PRIMARY KEY,
+            signature TEXT NOT NULL,
+            message_count INTEGER NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+/// 加载未过期的会话签名，并顺带清理数据库中的过期行
+fn load_session_entries(
+    db_path: &Path,
+    ttl: Duration,
+) -> Result<Vec<(String, CacheEntry<SessionSignatureEntry>)>, String> {
+    let conn = open_persist_db(db_path)?;
+    let cutoff = to_unix_secs(SystemTime::now()) - ttl.as_secs() as i64;
+    conn.execute(
+        "DELETE FROM session_signatures WHERE created_at < ?1",
+        params![cutoff],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare("SELECT session_id, signature, message_count, created_at FROM session_signatures")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            let created_at: i64 = row.get(3)?;
+            Ok((
+                row.get::<_, String>(0)?,
+                CacheEntry {
+                    data: SessionSignatureEntry {
+                        signature: row.get(1)?,
+                        message_count: row.get::<_, i64>(2)? as usize,
+                    },
+                    timestamp: UNIX_EPOCH + Duration::from_secs(created_at.max(0) as u64),
+                },
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+/// 以内存快照整体覆盖数据库内容 (会话缓存上限 1000 条，开销可控)
+fn flush_sessions(
+    persist_path: &Mutex<Option<PathBuf>>,
+    sessions: &Mutex<SessionMap>,
+    ttl: Duration,
+) -> Result<(), String> {
+    let Some(db_path) = persist_path.lock().ok().and_then(|p| p.clone()) else {
+        return Ok(());
+    };
+    let snapshot: Vec<(String, String, usize, i64)> = match sessions.lock() {
+        Ok(cache) => cache
+            .iter()
+            .filter(|(_, v)| !v.is_expired_after(ttl))
+            .map(|(k, v)| {
+                (
+                    k.clone(),
+                    v.data.signature.clone(),
+                    v.data.message_count,
+                    to_unix_secs(v.timestamp),
+                )
+            })
+            .collect(),
+        Err(_) => return Ok(()),
+    };
+
+    let mut conn = open_persist_db(&db_path)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM session_signatures", [])
+        .map_err(|e| e.to_string())?;
+    for (session_id, signature, message_count, created_at) in &snapshot {
+        tx.execute(
+            "INSERT INTO session_signatures (session_id, signature, message_count, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![session_id, signature, *message_count as i64, created_at],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    tracing::debug!("[SignatureCache] Persisted {} session signatures", snapshot.len());
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -347,4 +580,58 @@ mod tests {
         assert!(cache.get_signature_family(&sig).is_none());
         assert!(cache.get_session_signature("sid-1").is_none());
     }
+
+    fn temp_data_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sigcache-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_session_signature_persistence_reload() {
+        let dir = temp_data_dir();
+        let sig = "p".repeat(60);
+
+        let cache = SignatureCache::new();
+        cache.configure_persistence(Some(&dir), SIGNATURE_TTL).unwrap();
+        cache.cache_session_signature("sid-persist", sig.clone(), 7);
+        cache.flush_persistence().unwrap();
+
+        // 模拟重启: 新实例从同一数据目录恢复
+        let restarted = SignatureCache::new();
+        restarted.configure_persistence(Some(&dir), SIGNATURE_TTL).unwrap();
+        assert_eq!(restarted.get_session_signature("sid-persist"), Some(sig));
+
+        // 未启用持久化的实例不加载任何内容
+        let disabled = SignatureCache::new();
+        disabled.configure_persistence(None, SIGNATURE_TTL).unwrap();
+        assert!(disabled.get_session_signature("sid-persist").is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_expired_persisted_entries_are_dropped() {
+        let dir = temp_data_dir();
+        let conn = open_persist_db(&dir.join(PERSIST_DB_FILE)).unwrap();
+        let now = to_unix_secs(SystemTime::now());
+        conn.execute(
+            "INSERT INTO session_signatures VALUES (?1, ?2, ?3, ?4)",
+            params!["sid-old", "o".repeat(60), 3, now - 3 * 60 * 60],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO session_signatures VALUES (?1, ?2, ?3, ?4)",
+            params!["sid-fresh", "f".repeat(60), 3, now - 60],
+        )
+        .unwrap();
+        drop(conn);
+
+        let cache = SignatureCache::new();
+        cache.configure_persistence(Some(&dir), SIGNATURE_TTL).unwrap();
+        assert!(cache.get_session_signature("sid-old").is_none());
+        assert!(cache.get_session_signature("sid-fresh").is_some());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
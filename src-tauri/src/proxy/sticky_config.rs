@@ -1,7 +1,8 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// 调度模式枚举
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum SchedulingMode {
     /// 缓存优先 (Cache-first): 尽可能锁定同一账号，限流时优先等待，极大提升 Prompt Caching 命中率
     CacheFirst,
@@ -17,14 +18,35 @@ impl Default for SchedulingMode {
     }
 }
 
+/// [NEW] 会话粘性的绑定依据
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum StickyMode {
+    /// 按请求携带的会话标识绑定 (metadata.user_id 或首条用户消息指纹)
+    Header,
+    /// 按客户端 IP 绑定，适用于无法携带会话标识的客户端
+    Ip,
+    /// 不建立会话绑定
+    None,
+}
+
+impl Default for StickyMode {
+    fn default() -> Self {
+        Self::Header
+    }
+}
+
 /// 粘性会话配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct StickySessionConfig {
     /// 当前调度模式
     pub mode: SchedulingMode,
     /// 缓存优先模式下的最大等待时间 (秒)
     pub max_wait_seconds: u64,
+    /// [NEW] 会话绑定依据 (Ip 模式与 IP 过滤使用同一客户端 IP，受 `security_monitor.trusted_proxies` 约束)
+    pub sticky_mode: StickyMode,
+    /// [NEW] 会话绑定闲置超过该时间 (秒) 后失效，0 = 永不过期
+    pub session_ttl_seconds: u64,
 }
 
 impl Default for StickySessionConfig {
@@ -32,6 +54,8 @@ impl Default for StickySessionConfig {
         Self {
             mode: SchedulingMode::Balance,
             max_wait_seconds: 60,
+            sticky_mode: StickyMode::Header,
+            session_ttl_seconds: 3600,
         }
     }
 }
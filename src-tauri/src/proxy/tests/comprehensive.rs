@@ -38,6 +38,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            stop_sequences: None,
+            tool_choice: None,
         };
 
         // 2. 执行转换
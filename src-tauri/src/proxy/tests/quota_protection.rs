@@ -38,6 +38,7 @@ mod tests {
             validation_blocked: false,
             validation_blocked_until: 0,
             model_quotas: std::collections::HashMap::new(),
+            tags: Vec::new(),
         }
     }
 
@@ -1139,6 +1140,7 @@ mod tests {
             validation_blocked: false,
             validation_blocked_until: 0,
             model_quotas: std::collections::HashMap::new(),
+            tags: Vec::new(),
         }
     }
 }
@@ -211,6 +211,59 @@ mod security_db_tests {
         cleanup_test_data();
     }
 
+    #[test]
+    fn test_cidr_matching_ipv6() {
+        let _ = init_db();
+        cleanup_test_data();
+
+        let _ = add_to_blacklist("2001:db8::/32", Some("Block v6 subnet"), None, "test");
+
+        assert!(is_ip_in_blacklist("2001:db8::1").unwrap(), "Should match v6 /32");
+        assert!(is_ip_in_blacklist("2001:db8:ffff::42").unwrap(), "Should match v6 /32");
+        assert!(!is_ip_in_blacklist("2001:db9::1").unwrap(), "Should not match v6 /32");
+        // IPv4 地址不会误命中 IPv6 网段
+        assert!(!is_ip_in_blacklist("32.1.13.184").unwrap(), "v4 should not match v6 range");
+
+        cleanup_test_data();
+    }
+
+    #[test]
+    fn test_config_cidr_whitelist_priority() {
+        use crate::proxy::config::{ProxyConfig, SecurityMonitorConfig};
+        use crate::proxy::middleware::ip_filter::explain_ip;
+        use crate::proxy::security::ProxySecurityConfig;
+
+        let _ = init_db();
+
+        let mut monitor = SecurityMonitorConfig {
+            blocked_cidrs: vec!["10.1.0.0/16".to_string()],
+            allowed_cidrs: vec!["10.0.0.0/8".to_string()],
+            ..Default::default()
+        };
+        monitor.blacklist.enabled = true;
+        monitor.whitelist.whitelist_priority = true;
+
+        let build = |monitor: &SecurityMonitorConfig| {
+            ProxySecurityConfig::from_proxy_config(&ProxyConfig {
+                security_monitor: monitor.clone(),
+                ..Default::default()
+            })
+        };
+
+        // 同时命中放行与封禁网段: 白名单优先时放行
+        assert_eq!(explain_ip(&build(&monitor), "10.1.2.3").decision, "allow");
+        // 范围外的 IP 不命中任何配置网段
+        let outside = explain_ip(&build(&monitor), "172.16.0.1");
+        assert!(outside.matched_allowed_cidrs.is_empty());
+        assert!(outside.matched_blocked_cidrs.is_empty());
+
+        monitor.whitelist.whitelist_priority = false;
+        assert_eq!(explain_ip(&build(&monitor), "10.1.2.3").decision, "block");
+        let only_allowed = explain_ip(&build(&monitor), "10.2.0.1");
+        assert_eq!(only_allowed.matched_allowed_cidrs, vec!["10.0.0.0/8".to_string()]);
+        assert!(only_allowed.matched_blocked_cidrs.is_empty());
+    }
+
     // ============================================================================
     // 测试类别 4: 过期时间处理
     // ============================================================================
@@ -0,0 +1,387 @@
+// 按路由类别 / 模型的请求超时策略
+// - 非流式请求: 总耗时超时
+// - 流式请求: 空闲超时 (N 秒内未收到任何上游字节)
+// - 超时返回 504，错误体中标明触发的是哪一条超时规则
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use bytes::Bytes;
+use futures::Stream;
+use serde_json::json;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::proxy::config::{ProxyConfig, TimeoutRouteClass};
+use crate::proxy::error::ApiErrorCode;
+
+type ByteStream<E> = Pin<Box<dyn Stream<Item = Result<Bytes, E>> + Send>>;
+
+#[derive(Default)]
+struct TimeoutPolicy {
+    default_secs: u64,
+    routes: HashMap<TimeoutRouteClass, u64>,
+    /// key 已归一化，且去掉了末尾的 `*`
+    models: HashMap<String, u64>,
+}
+
+fn policy() -> &'static RwLock<TimeoutPolicy> {
+    static POLICY: OnceLock<RwLock<TimeoutPolicy>> = OnceLock::new();
+    POLICY.get_or_init(|| {
+        RwLock::new(TimeoutPolicy {
+            default_secs: 120,
+            ..Default::default()
+        })
+    })
+}
+
+impl TimeoutPolicy {
+    fn from_proxy_config(config: &ProxyConfig) -> Self {
+        Self {
+            default_secs: config.request_timeout,
+            routes: config.timeouts.routes.clone(),
+            models: config
+                .model_timeouts
+                .iter()
+                .map(|(pattern, secs)| (normalize_model_name(pattern.trim_end_matches('*')), *secs))
+                .collect(),
+        }
+    }
+}
+
+/// 更新超时策略 (启动 / 保存配置时调用)
+pub fn update_timeout_policy(config: &ProxyConfig) {
+    if let Ok(mut p) = policy().write() {
+        *p = TimeoutPolicy::from_proxy_config(config);
+    }
+}
+
+/// 归一化模型名用于超时查找: 去空白、转小写、去掉 Gemini 路径中的 `models/` 前缀
+pub fn normalize_model_name(model: &str) -> String {
+    let lower = model.trim().to_ascii_lowercase();
+    match lower.strip_prefix("models/") {
+        Some(rest) => rest.to_string(),
+        None => lower,
+    }
+}
+
+/// 生效超时的来源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeoutSource {
+    /// 命中 `model_timeouts` 中的规则 (归一化后的规则，前缀匹配以 `*` 结尾)
+    Model(String),
+    /// 命中 `timeouts.routes`
+    Route,
+    /// 回退到全局 `request_timeout`
+    Default,
+}
+
+/// 超时触发的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutKind {
+    /// 非流式请求总耗时超限 / 等待上游响应头超时
+    Total,
+    /// 流式响应空闲超时
+    StreamIdle,
+}
+
+impl TimeoutKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TimeoutKind::Total => "total",
+            TimeoutKind::StreamIdle => "stream_idle",
+        }
+    }
+}
+
+/// 单个请求的生效超时
+#[derive(Debug, Clone)]
+pub struct EffectiveTimeout {
+    pub secs: u64,
+    pub route: TimeoutRouteClass,
+    pub source: TimeoutSource,
+    started: Instant,
+    idle_fired: Arc<AtomicBool>,
+}
+
+impl EffectiveTimeout {
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.secs)
+    }
+
+    /// 上游 HTTP 请求级超时覆盖: 非流式请求使用总耗时；流式请求由空闲超时控制，保持客户端默认值
+    pub fn request_override(&self, streaming: bool) -> Option<Duration> {
+        (!streaming).then(|| self.duration())
+    }
+
+    /// 距总耗时截止还剩多少时间
+    pub fn remaining(&self) -> Duration {
+        self.duration().saturating_sub(self.started.elapsed())
+    }
+
+    /// 规则描述，例如 `model:gemini-3-pro-image*`、`route:images`、`request_timeout`
+    pub fn label(&self) -> String {
+        match &self.source {
+            TimeoutSource::Model(rule) => format!("model:{}", rule),
+            TimeoutSource::Route => format!(
+                "route:{}",
+                serde_json::to_value(self.route)
+                    .ok()
+                    .and_then(|v| v.as_str().map(|s| s.to_string()))
+                    .unwrap_or_default()
+            ),
+            TimeoutSource::Default => "request_timeout".to_string(),
+        }
+    }
+
+    /// 上游流是否因空闲超时被中断
+    pub fn idle_fired(&self) -> bool {
+        self.idle_fired.load(Ordering::Relaxed)
+    }
+
+    fn error_body(&self, kind: TimeoutKind) -> serde_json::Value {
+        json!({
+            "error": {
+                "type": "timeout_error",
+                "code": ApiErrorCode::UpstreamTimeout.as_str(),
+                "timeout": self.label(),
+                "timeout_kind": kind.as_str(),
+                "timeout_secs": self.secs,
+                "message": format!(
+                    "Upstream {} timeout ({}s, {}) exceeded",
+                    kind.as_str(),
+                    self.secs,
+                    self.label()
+                ),
+            }
+        })
+    }
+
+    /// 构造 504 响应
+    pub fn error_response(&self, kind: TimeoutKind) -> Response {
+        tracing::warn!(
+            "[Timeout] {} timeout fired after {}s ({})",
+            kind.as_str(),
+            self.secs,
+            self.label()
+        );
+        (StatusCode::GATEWAY_TIMEOUT, Json(self.error_body(kind))).into_response()
+    }
+
+    /// 为上游字节流加上空闲超时: N 秒内无数据则结束流并标记已触发
+    pub fn wrap_idle_stream<E: Send + 'static>(&self, mut inner: ByteStream<E>) -> ByteStream<E> {
+        use async_stream::stream;
+        use futures::StreamExt;
+
+        let idle = self.duration();
+        let fired = self.idle_fired.clone();
+        let label = self.label();
+        Box::pin(stream! {
+            loop {
+                match tokio::time::timeout(idle, inner.next()).await {
+                    Ok(Some(item)) => yield item,
+                    Ok(None) => break,
+                    Err(_) => {
+                        tracing::warn!(
+                            "[Timeout] Upstream stream idle for {}s ({}), aborting",
+                            idle.as_secs(),
+                            label
+                        );
+                        fired.store(true, Ordering::Relaxed);
+                        break;
+                    }
+                }
+            }
+        })
+    }
+
+    /// 响应已开始发送时无法再改状态码，空闲超时后在流末尾追加一条 SSE 错误事件
+    pub fn append_idle_error<S, E>(&self, inner: S) -> impl Stream<Item = Result<Bytes, E>> + Send
+    where
+        S: Stream<Item = Result<Bytes, E>> + Send,
+        E: Send,
+    {
+        use futures::StreamExt;
+
+        let this = self.clone();
+        inner.chain(futures::stream::once(async move { this }).filter_map(|this| async move {
+            this.idle_fired().then(|| {
+                Ok(Bytes::from(format!(
+                    "event: error\ndata: {}\n\n",
+                    this.error_body(TimeoutKind::StreamIdle)
+                )))
+            })
+        }))
+    }
+}
+
+/// 查找顺序: 模型精确匹配 → 模型前缀匹配 (最长者优先) → 路由类别 → 全局 request_timeout
+fn resolve_with(
+    policy: &TimeoutPolicy,
+    route: TimeoutRouteClass,
+    models: &[&str],
+) -> (u64, TimeoutSource) {
+    let candidates: Vec<String> = models.iter().map(|m| normalize_model_name(m)).collect();
+
+    // 1. 模型精确匹配
+    for model in &candidates {
+        if let Some(secs) = policy.models.get(model) {
+            return (*secs, TimeoutSource::Model(model.clone()));
+        }
+    }
+
+    // 2. 模型前缀匹配
+    let prefix = candidates
+        .iter()
+        .flat_map(|model| {
+            policy
+                .models
+                .iter()
+                .filter(move |(pattern, _)| !pattern.is_empty() && model.starts_with(pattern.as_str()))
+        })
+        .max_by_key(|(pattern, _)| pattern.len());
+    if let Some((pattern, secs)) = prefix {
+        return (*secs, TimeoutSource::Model(format!("{}*", pattern)));
+    }
+
+    // 3. 路由类别 -> 全局默认
+    match policy.routes.get(&route) {
+        Some(secs) => (*secs, TimeoutSource::Route),
+        None => (policy.default_secs, TimeoutSource::Default),
+    }
+}
+
+/// 解析请求的生效超时 (依次尝试 `models` 中的模型名，通常为原始模型与映射后模型)
+pub fn resolve(route: TimeoutRouteClass, models: &[&str]) -> EffectiveTimeout {
+    let (secs, source) = policy()
+        .read()
+        .map(|p| resolve_with(&p, route, models))
+        .unwrap_or((120, TimeoutSource::Default));
+
+    let effective = EffectiveTimeout {
+        secs: secs.max(1),
+        route,
+        source,
+        started: Instant::now(),
+        idle_fired: Arc::new(AtomicBool::new(false)),
+    };
+    let _ = EFFECTIVE_TIMEOUT.try_with(|slot| {
+        if let Ok(mut s) = slot.lock() {
+            *s = Some(effective.secs);
+        }
+    });
+    effective
+}
+
+tokio::task_local! {
+    static EFFECTIVE_TIMEOUT: Arc<Mutex<Option<u64>>>;
+}
+
+/// 执行请求处理并返回其间解析出的生效超时 (供监控日志记录)
+pub async fn track_effective_timeout<F: std::future::Future>(fut: F) -> (F::Output, Option<u64>) {
+    let slot = Arc::new(Mutex::new(None));
+    let output = EFFECTIVE_TIMEOUT.scope(slot.clone(), fut).await;
+    let secs = slot.lock().ok().and_then(|s| *s);
+    (output, secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::config::RequestTimeoutsConfig;
+    use futures::StreamExt;
+
+    fn test_policy() -> TimeoutPolicy {
+        TimeoutPolicy::from_proxy_config(&ProxyConfig {
+            request_timeout: 120,
+            model_timeouts: HashMap::from([
+                ("gemini-3-pro-image*".to_string(), 300),
+                ("Gemini-3-Pro-Image-4K".to_string(), 600),
+                ("gemini-".to_string(), 90),
+                ("gemini-2.5-pro".to_string(), 900),
+            ]),
+            timeouts: RequestTimeoutsConfig {
+                routes: HashMap::from([
+                    (TimeoutRouteClass::Chat, 60),
+                    (TimeoutRouteClass::Images, 240),
+                ]),
+            },
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_resolve_lookup_order() {
+        let p = test_policy();
+        // 精确匹配 (大小写不敏感)
+        assert_eq!(
+            resolve_with(&p, TimeoutRouteClass::Images, &["gemini-3-pro-image-4k"]),
+            (600, TimeoutSource::Model("gemini-3-pro-image-4k".to_string()))
+        );
+        // 前缀匹配，最长者优先
+        assert_eq!(
+            resolve_with(&p, TimeoutRouteClass::Images, &["dall-e-3", "gemini-3-pro-image"]),
+            (300, TimeoutSource::Model("gemini-3-pro-image".to_string()))
+        );
+        assert_eq!(
+            resolve_with(&p, TimeoutRouteClass::Chat, &["models/gemini-2.5-pro-thinking"]),
+            (900, TimeoutSource::Model("gemini-2.5-pro*".to_string()))
+        );
+        assert_eq!(
+            resolve_with(&p, TimeoutRouteClass::Chat, &["gemini-3-flash"]),
+            (90, TimeoutSource::Model("gemini-*".to_string()))
+        );
+        // 路由类别 -> 全局默认
+        assert_eq!(
+            resolve_with(&p, TimeoutRouteClass::Chat, &["claude-sonnet-4-5"]),
+            (60, TimeoutSource::Route)
+        );
+        assert_eq!(
+            resolve_with(&p, TimeoutRouteClass::Audio, &["whisper-1"]),
+            (120, TimeoutSource::Default)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_track_records_effective_timeout() {
+        let (_, secs) = track_effective_timeout(async {
+            resolve(TimeoutRouteClass::CountTokens, &["some-model"]);
+        })
+        .await;
+        assert!(secs.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_idle_stream_fires_and_appends_error() {
+        let effective = EffectiveTimeout {
+            secs: 1,
+            route: TimeoutRouteClass::Chat,
+            source: TimeoutSource::Route,
+            started: Instant::now(),
+            idle_fired: Arc::new(AtomicBool::new(false)),
+        };
+
+        let slow: ByteStream<std::io::Error> = Box::pin(async_stream::stream! {
+            yield Ok(Bytes::from_static(b"data: 1\n\n"));
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            yield Ok(Bytes::from_static(b"data: 2\n\n"));
+        });
+
+        let chunks: Vec<Bytes> = effective
+            .append_idle_error(effective.wrap_idle_stream(slow))
+            .filter_map(|r| async move { r.ok() })
+            .collect()
+            .await;
+
+        assert!(effective.idle_fired());
+        assert_eq!(chunks.len(), 2);
+        let tail = String::from_utf8_lossy(&chunks[1]);
+        assert!(tail.contains("\"timeout_kind\":\"stream_idle\""));
+        assert!(tail.contains("route:chat"));
+    }
+}
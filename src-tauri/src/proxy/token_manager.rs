@@ -2,10 +2,13 @@
 use dashmap::DashMap;
 use std::collections::{HashSet, HashMap};
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio_util::sync::CancellationToken;
 
+use crate::proxy::concurrency::{AccountInFlight, AccountLimiter, AccountPermit, RequestPriority};
+use crate::proxy::config::{AccountConcurrencyConfig, CoordinationMode};
+use crate::modules::proxy_db;
 use crate::proxy::rate_limit::RateLimitTracker;
 use crate::proxy::sticky_config::StickySessionConfig;
 
@@ -34,6 +37,105 @@ pub struct ProxyToken {
     pub validation_blocked: bool,          // [NEW] Check for validation block (VALIDATION_REQUIRED temporary block)
     pub validation_blocked_until: i64,     // [NEW] Timestamp until which the account is blocked
     pub model_quotas: HashMap<String, i32>, // [OPTIMIZATION] In-memory cache for model-specific quotas
+    pub tags: Vec<String>,                 // [NEW] 账号分组标签
+}
+
+/// 账号健康检查结果分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountHealthStatus {
+    Healthy,
+    Revoked,
+    NetworkError,
+}
+
+/// 单个账号的健康检查详情
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AccountHealthDetail {
+    pub account_id: String,
+    pub email: String,
+    pub status: AccountHealthStatus,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 刷新失败时给出的禁用原因 (如 `refresh_token_revoked`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled_reason: Option<String>,
+    /// 是否已被自动禁用反代
+    pub auto_disabled: bool,
+}
+
+/// 批量健康检查汇总
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct AccountHealthReport {
+    pub healthy: usize,
+    pub revoked: usize,
+    pub network_error: usize,
+    pub details: Vec<AccountHealthDetail>,
+}
+
+/// refresh_token 被吊销时记录的禁用原因
+pub const REFRESH_TOKEN_REVOKED_REASON: &str = "refresh_token_revoked";
+/// 连续硬失败达到阈值时记录的禁用原因
+pub const AUTO_DISABLED_ERROR_RATE_REASON: &str = "auto_disabled_error_rate";
+/// 默认连续硬失败阈值
+pub const DEFAULT_AUTO_DISABLE_FAILURE_THRESHOLD: u32 = 5;
+
+/// 判断上游错误是否为账号级硬失败 (计入自动禁用)
+/// 仅 403 权限错误计入；VALIDATION_REQUIRED 为临时验证拦截，429 / 超时 / 5xx 等软错误不计入
+pub fn classify_hard_failure(status: u16, error_text: &str) -> Option<&'static str> {
+    if status != 403 {
+        return None;
+    }
+    if error_text.contains("VALIDATION_REQUIRED")
+        || error_text.contains("verify your account")
+        || error_text.contains("validation_url")
+    {
+        return None;
+    }
+    let lower = error_text.to_ascii_lowercase();
+    if lower.contains("suspended") || lower.contains("account_disabled") {
+        Some("account_suspended")
+    } else {
+        Some("permission_denied")
+    }
+}
+
+/// 共享协调模式下固定账号在 coord_state 中的键
+const PREFERRED_ACCOUNT_STATE_KEY: &str = "preferred_account_id";
+/// 乐观锁写入冲突时的最大重试次数
+const COORDINATION_CAS_RETRIES: usize = 5;
+/// 共享协调模式下拉取其他实例熔断状态的间隔
+const SHARED_RATE_LIMIT_SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3);
+/// [NEW] 调度状态 (轮询游标 + 会话绑定) 持久化文件，重启后恢复以保持负载均衡
+const SCHEDULER_STATE_FILE: &str = "scheduler_state.json";
+
+/// 在阻塞线程池中执行共享协调表的读写，避免同步 SQLite 调用阻塞 get_token 所在的异步线程
+async fn coord_blocking<T, F>(f: F) -> Result<T, String>
+where
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| format!("coordination task failed: {}", e))?
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct SchedulerState {
+    saved_at: i64,
+    current_index: usize,
+    /// 额外 Provider (z.ai 等) 的轮询游标，由 AxumServer 持有
+    provider_rr: usize,
+    sessions: Vec<PersistedSessionBinding>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct PersistedSessionBinding {
+    session_id: String,
+    account_id: String,
+    /// 最近一次使用时间 (unix 秒)
+    last_seen: i64,
 }
 
 pub struct TokenManager {
@@ -44,9 +146,15 @@ pub struct TokenManager {
     rate_limit_tracker: Arc<RateLimitTracker>, // 新增: 限流跟踪器
     sticky_config: Arc<tokio::sync::RwLock<StickySessionConfig>>, // 新增：调度配置
     session_accounts: Arc<DashMap<String, String>>, // 新增：会话与账号映射 (SessionID -> AccountID)
+    session_last_seen: Arc<DashMap<String, std::time::Instant>>, // [NEW] 会话最近一次使用时间 (闲置过期)
     preferred_account_id: Arc<tokio::sync::RwLock<Option<String>>>, // [FIX #820] 优先使用的账号ID（固定账号模式）
     health_scores: Arc<DashMap<String, f32>>,                       // account_id -> health_score
     circuit_breaker_config: Arc<tokio::sync::RwLock<crate::models::CircuitBreakerConfig>>, // [NEW] 熔断配置缓存
+    account_limiters: Arc<DashMap<String, Arc<AccountLimiter>>>, // [NEW] 账号级并发限制器
+    concurrency_config: Arc<std::sync::RwLock<AccountConcurrencyConfig>>,
+    coordination_mode: Arc<std::sync::RwLock<CoordinationMode>>, // [NEW] 多实例协调模式
+    hard_failures: Arc<DashMap<String, u32>>, // [NEW] account_id -> 连续硬失败次数
+    auto_disable_threshold: Arc<AtomicU32>,   // [NEW] 连续硬失败自动禁用阈值 (0 = 关闭)
     /// 支持优雅关闭时主动 abort 后台任务
     auto_cleanup_handle: Arc<tokio::sync::Mutex<Option<tokio::task::JoinHandle<()>>>>,
     cancel_token: CancellationToken,
@@ -63,11 +171,21 @@ impl TokenManager {
             rate_limit_tracker: Arc::new(RateLimitTracker::new()),
             sticky_config: Arc::new(tokio::sync::RwLock::new(StickySessionConfig::default())),
             session_accounts: Arc::new(DashMap::new()),
+            session_last_seen: Arc::new(DashMap::new()),
             preferred_account_id: Arc::new(tokio::sync::RwLock::new(None)), // [FIX #820]
             health_scores: Arc::new(DashMap::new()),
             circuit_breaker_config: Arc::new(tokio::sync::RwLock::new(
                 crate::models::CircuitBreakerConfig::default(),
             )),
+            account_limiters: Arc::new(DashMap::new()),
+            concurrency_config: Arc::new(std::sync::RwLock::new(
+                AccountConcurrencyConfig::default(),
+            )),
+            coordination_mode: Arc::new(std::sync::RwLock::new(CoordinationMode::default())),
+            hard_failures: Arc::new(DashMap::new()),
+            auto_disable_threshold: Arc::new(AtomicU32::new(
+                DEFAULT_AUTO_DISABLE_FAILURE_THRESHOLD,
+            )),
             auto_cleanup_handle: Arc::new(tokio::sync::Mutex::new(None)),
             cancel_token: CancellationToken::new(),
         }
@@ -76,10 +194,16 @@ impl TokenManager {
     /// 启动限流记录自动清理后台任务（每15秒检查并清除过期记录）
     pub async fn start_auto_cleanup(&self) {
         let tracker = self.rate_limit_tracker.clone();
+        let coordination_mode = self.coordination_mode.clone();
+        let sticky_config = self.sticky_config.clone();
+        let session_accounts = self.session_accounts.clone();
+        let session_last_seen = self.session_last_seen.clone();
         let cancel = self.cancel_token.child_token();
 
         let handle = tokio::spawn(async move {
             let mut interval = tokio::time::interval(std::time::Duration::from_secs(15));
+            // [NEW] 共享协调模式下定期拉取其他实例写入的熔断状态
+            let mut sync_interval = tokio::time::interval(SHARED_RATE_LIMIT_SYNC_INTERVAL);
             loop {
                 tokio::select! {
                     _ = cancel.cancelled() => {
@@ -94,6 +218,21 @@ impl TokenManager {
                                 cleaned
                             );
                         }
+                        // [NEW] 清理闲置过期的会话绑定 (Ip 模式下每个客户端 IP 一条)
+                        let ttl = sticky_config.read().await.session_ttl_seconds;
+                        let expired = Self::prune_idle_sessions(&session_accounts, &session_last_seen, ttl);
+                        if expired > 0 {
+                            tracing::debug!("Auto-cleanup: Removed {} idle session binding(s)", expired);
+                        }
+                    }
+                    _ = sync_interval.tick() => {
+                        let shared = coordination_mode
+                            .read()
+                            .map(|m| *m == CoordinationMode::SharedDatabase)
+                            .unwrap_or(false);
+                        if shared {
+                            Self::pull_shared_rate_limits(&tracker).await;
+                        }
                     }
                 }
             }
@@ -172,14 +311,14 @@ impl TokenManager {
             Ok(Some(token)) => {
                 self.tokens.insert(account_id.to_string(), token);
                 // [NEW] 重新加载账号时自动清除该账号的限流记录
-                self.clear_rate_limit(account_id);
+                self.clear_rate_limit(account_id).await;
                 Ok(())
             }
             Ok(None) => {
                 // [FIX] 账号被禁用或不可用时，从内存池中彻底移除 (Issue #1565)
                 // load_single_account returning None means the account should be skipped in its
                 // current state (disabled / proxy_disabled / quota_protection / validation_blocked...).
-                self.remove_account(account_id);
+                self.remove_account(account_id).await;
                 Ok(())
             }
             Err(e) => Err(format!("同步账号失败: {}", e)),
@@ -190,12 +329,12 @@ impl TokenManager {
     pub async fn reload_all_accounts(&self) -> Result<usize, String> {
         let count = self.load_accounts().await?;
         // [NEW] 重新加载所有账号时自动清除所有限流记录
-        self.clear_all_rate_limits();
+        self.clear_all_rate_limits().await;
         Ok(count)
     }
 
     /// 从内存中彻底移除指定账号及其关联数据 (Issue #1477)
-    pub fn remove_account(&self, account_id: &str) {
+    pub async fn remove_account(&self, account_id: &str) {
         // 1. 从 DashMap 中移除令牌
         if self.tokens.remove(account_id).is_some() {
             tracing::info!("[Proxy] Removed account {} from memory cache", account_id);
@@ -205,10 +344,10 @@ impl TokenManager {
         self.health_scores.remove(account_id);
 
         // 3. 清理该账号的所有限流记录
-        self.clear_rate_limit(account_id);
+        self.clear_rate_limit(account_id).await;
 
         // 4. 清理涉及该账号的所有会话绑定
-        self.session_accounts.retain(|_, v| v != account_id);
+        self.unbind_account_sessions(account_id).await;
 
         // 5. 如果是当前优先账号，也需要清理
         if let Ok(mut preferred) = self.preferred_account_id.try_write() {
@@ -217,6 +356,7 @@ impl TokenManager {
                 tracing::info!("[Proxy] Cleared preferred account status for {}", account_id);
             }
         }
+        self.publish_preferred_account(None, Some(account_id)).await;
     }
 
     /// Check if an account has been disabled on disk.
@@ -515,6 +655,11 @@ impl TokenManager {
             validation_blocked: account.get("validation_blocked").and_then(|v| v.as_bool()).unwrap_or(false),
             validation_blocked_until: account.get("validation_blocked_until").and_then(|v| v.as_i64()).unwrap_or(0),
             model_quotas,
+            tags: account
+                .get("tags")
+                .and_then(|v| v.as_array())
+                .map(|arr| arr.iter().filter_map(|t| t.as_str().map(|s| s.to_string())).collect())
+                .unwrap_or_default(),
         }))
     }
 
@@ -745,6 +890,18 @@ impl TokenManager {
             // [FIX] 触发 TokenManager 的账号重新加载信号，确保内存中的 protected_models 同步
             crate::proxy::server::trigger_account_reload(account_id);
 
+            crate::proxy::webhook::emit(
+                crate::proxy::config::WebhookEvent::QuotaExceeded,
+                serde_json::json!({
+                    "account_id": account_id,
+                    "email": account_json.get("email"),
+                    "model": model_name,
+                    "remaining_percentage": current_val,
+                    "threshold": threshold,
+                    "source": "quota_protection",
+                }),
+            );
+
             return Ok(true);
         }
 
@@ -769,6 +926,15 @@ impl TokenManager {
                 .unwrap_or("unknown")
         );
 
+        // [FIX] 经 set_proxy_disabled 解除禁用，补上禁用历史中未结束记录的 re_enabled_at
+        if let Ok(mut account) =
+            serde_json::from_value::<crate::models::Account>(account_json.clone())
+        {
+            account.set_proxy_disabled(false, None, chrono::Utc::now().timestamp());
+            if let Ok(history) = serde_json::to_value(&account.proxy_disable_history) {
+                account_json["proxy_disable_history"] = history;
+            }
+        }
         account_json["proxy_disabled"] = serde_json::Value::Bool(false);
         account_json["proxy_disabled_reason"] = serde_json::Value::Null;
         account_json["proxy_disabled_at"] = serde_json::Value::Null;
@@ -964,7 +1130,7 @@ impl TokenManager {
         // [FIX #1477] 检查并处理待删除的账号（彻底清理缓存）
         let pending_delete = crate::proxy::server::take_pending_delete_accounts();
         for account_id in pending_delete {
-            self.remove_account(&account_id);
+            self.remove_account(&account_id).await;
             tracing::info!(
                 "[Proxy] Purged deleted account {} from all caches",
                 account_id
@@ -1001,6 +1167,34 @@ impl TokenManager {
             return Err("Token pool is empty".to_string());
         }
 
+        // [NEW] 超出单账号每日 Token 预算的账号退出轮换，其余账号继续服务
+        let budget = crate::proxy::budget::BudgetTracker::global();
+        tokens_snapshot.retain(|t| !budget.is_account_exhausted(&t.email));
+        if tokens_snapshot.is_empty() {
+            return Err(format!(
+                "All accounts exceeded the daily token budget. Resets at {}",
+                crate::proxy::budget::next_reset(chrono::Local::now()).to_rfc3339()
+            ));
+        }
+        total = tokens_snapshot.len();
+
+        // [NEW] 模型要求账号标签时只在该分组内轮换 (分组内排序与选择逻辑不变)
+        let required_tags = crate::proxy::common::model_mapping::resolve_required_account_tags(
+            target_model,
+            &crate::proxy::config::get_model_account_tags(),
+        );
+        if !required_tags.is_empty() {
+            let tags: Vec<&str> = required_tags.iter().map(|t| t.as_str()).collect();
+            tokens_snapshot.retain(|t| token_has_tags(t, &tags));
+            if tokens_snapshot.is_empty() {
+                return Err(format!(
+                    "No available account carries the tag(s) {:?} required for model {}",
+                    required_tags, target_model
+                ));
+            }
+            total = tokens_snapshot.len();
+        }
+
         // ===== 【优化】Quota-First 排序: 保护低配额账号，均衡使用 =====
         // 优先级: 目标模型配额 > 健康分 > 订阅等级 > 刷新时间
         // -> 高配额账号优先被选中，避免 PRO/ULTRA 先用完丢失5小时刷新周期
@@ -1078,7 +1272,20 @@ impl TokenManager {
 
         // 0. 读取当前调度配置
         let scheduling = self.sticky_config.read().await.clone();
-        use crate::proxy::sticky_config::SchedulingMode;
+        use crate::proxy::sticky_config::{SchedulingMode, StickyMode};
+
+        // [NEW] 按 sticky_mode 确定会话键: Ip 模式以客户端 IP 代替请求携带的会话标识
+        let ip_session_key = match scheduling.sticky_mode {
+            StickyMode::Ip => {
+                crate::proxy::concurrency::current_client_ip().map(|ip| format!("ip-{}", ip))
+            }
+            _ => None,
+        };
+        let session_id = match scheduling.sticky_mode {
+            StickyMode::Header => session_id,
+            StickyMode::Ip => ip_session_key.as_deref(),
+            StickyMode::None => None,
+        };
 
         // 【新增】检查配额保护是否启用（如果关闭，则忽略 protected_models 检查）
         let quota_protection_enabled = crate::modules::config::load_app_config()
@@ -1086,7 +1293,7 @@ impl TokenManager {
             .unwrap_or(false);
 
         // ===== [FIX #820] 固定账号模式：优先使用指定账号 =====
-        let preferred_id = self.preferred_account_id.read().await.clone();
+        let preferred_id = self.current_preferred_account().await;
         if let Some(ref pref_id) = preferred_id {
             // 查找优先账号
             if let Some(preferred_token) = tokens_snapshot
@@ -1101,7 +1308,7 @@ impl TokenManager {
                             "🔒 [FIX #820] Preferred account {} is disabled on disk, purging and falling back",
                             preferred_token.email
                         );
-                        self.remove_account(&preferred_token.account_id);
+                        self.remove_account(&preferred_token.account_id).await;
                         tokens_snapshot.retain(|t| t.account_id != preferred_token.account_id);
                         total = tokens_snapshot.len();
 
@@ -1111,6 +1318,7 @@ impl TokenManager {
                                 *preferred = None;
                             }
                         }
+                        self.publish_preferred_account(None, Some(pref_id.as_str())).await;
 
                         if total == 0 {
                             return Err("Token pool is empty".to_string());
@@ -1245,7 +1453,9 @@ impl TokenManager {
                 let sid = session_id.unwrap();
 
                 // 1. 检查会话是否已绑定账号
-                if let Some(bound_id) = self.session_accounts.get(sid).map(|v| v.clone()) {
+                if let Some(bound_id) =
+                    self.session_binding(sid, scheduling.session_ttl_seconds).await
+                {
                     // 【修复】先通过 account_id 找到对应的账号，获取其 email
                     // 2. 转换 email -> account_id 检查绑定的账号是否限流
                     if let Some(bound_token) =
@@ -1263,7 +1473,7 @@ impl TokenManager {
                                 "Sticky Session: Bound account {} is rate-limited ({}s), unbinding and switching.",
                                 bound_token.email, reset_sec
                             );
-                            self.session_accounts.remove(sid);
+                            self.unbind_session(sid, Some(&bound_id)).await;
                         } else if !attempted.contains(&bound_id)
                             && !(quota_protection_enabled
                                 && bound_token.protected_models.contains(&normalized_target))
@@ -1275,7 +1485,7 @@ impl TokenManager {
                             && bound_token.protected_models.contains(&normalized_target)
                         {
                             tracing::debug!("Sticky Session: Bound account {} is quota-protected for model {} [{}], unbinding and switching.", bound_token.email, normalized_target, target_model);
-                            self.session_accounts.remove(sid);
+                            self.unbind_session(sid, Some(&bound_id)).await;
                         }
                     } else {
                         // 绑定的账号已不存在（可能被删除），解绑
@@ -1283,7 +1493,7 @@ impl TokenManager {
                             "Sticky Session: Bound account not found for session {}, unbinding",
                             sid
                         );
-                        self.session_accounts.remove(sid);
+                        self.unbind_session(sid, Some(&bound_id)).await;
                     }
                 }
             }
@@ -1350,8 +1560,7 @@ impl TokenManager {
                         // 如果是会话首次分配且需要粘性，在此建立绑定
                         if let Some(sid) = session_id {
                             if scheduling.mode != SchedulingMode::PerformanceFirst {
-                                self.session_accounts
-                                    .insert(sid.to_string(), selected.account_id.clone());
+                                self.bind_session(sid, &selected.account_id).await;
                                 tracing::debug!(
                                     "Sticky Session: Bound new account {} to session {}",
                                     selected.email,
@@ -1469,7 +1678,7 @@ impl TokenManager {
                         token.email
                     );
                     attempted.insert(token.account_id.clone());
-                    self.remove_account(&token.account_id);
+                    self.remove_account(&token.account_id).await;
                     continue;
                 }
                 OnDiskAccountState::Unknown => {
@@ -1626,6 +1835,14 @@ impl TokenManager {
         self.tokens.remove(account_id);
 
         tracing::warn!("Account disabled: {} ({:?})", account_id, path);
+        crate::proxy::webhook::emit(
+            crate::proxy::config::WebhookEvent::AccountDisabled,
+            serde_json::json!({
+                "account_id": account_id,
+                "email": content.get("email"),
+                "reason": truncate_reason(reason, 800),
+            }),
+        );
         Ok(())
     }
 
@@ -1779,7 +1996,7 @@ impl TokenManager {
         // 【替代方案】转换 email -> account_id
         let key = self.email_to_account_id(email).unwrap_or_else(|| email.to_string());
 
-        self.rate_limit_tracker.parse_from_error(
+        let info = self.rate_limit_tracker.parse_from_error(
             &key,
             status,
             retry_after_header,
@@ -1787,6 +2004,37 @@ impl TokenManager {
             None,
             &config.backoff_steps, // [NEW] 传入配置
         );
+        if let Some(info) = info {
+            self.notify_rate_limit_event(&key, email, &info, &config.backoff_steps);
+        }
+        self.publish_rate_limits(&key).await;
+    }
+
+    /// [NEW] 限流/熔断事件 Webhook 通知
+    /// [FIX] 普通 429 / 5xx 只是短暂退避，不再当作熔断上报；
+    /// 仅当连续失败次数升级到退避阶梯最高档时才发送 CircuitBreakerOpen
+    fn notify_rate_limit_event(
+        &self,
+        account_id: &str,
+        email: &str,
+        info: &crate::proxy::rate_limit::RateLimitInfo,
+        backoff_steps: &[u64],
+    ) {
+        let failures = self.rate_limit_tracker.failure_count(account_id);
+        let Some(event) = rate_limit_webhook_event(info.reason, failures, backoff_steps) else {
+            return;
+        };
+        crate::proxy::webhook::emit(
+            event,
+            serde_json::json!({
+                "account_id": account_id,
+                "email": email,
+                "reason": format!("{:?}", info.reason),
+                "model": info.model,
+                "retry_after_sec": info.retry_after_sec,
+                "consecutive_failures": failures,
+            }),
+        );
     }
 
     /// 检查账号是否在限流中 (支持模型级)
@@ -1823,7 +2071,7 @@ impl TokenManager {
 
     /// 【替代方案】通过 email 查找对应的 account_id
     /// 用于将 handlers 传入的 email 转换为 tracker 使用的 account_id
-    fn email_to_account_id(&self, email: &str) -> Option<String> {
+    pub fn email_to_account_id(&self, email: &str) -> Option<String> {
         self.tokens
             .iter()
             .find(|entry| entry.value().email == email)
@@ -1831,12 +2079,23 @@ impl TokenManager {
     }
 
     /// 清除指定账号的限流记录
-    pub fn clear_rate_limit(&self, account_id: &str) -> bool {
+    pub async fn clear_rate_limit(&self, account_id: &str) -> bool {
+        if self.is_shared_coordination() {
+            let aid = account_id.to_string();
+            if let Err(e) = coord_blocking(move || proxy_db::coord_clear_rate_limits(Some(&aid))).await {
+                tracing::warn!("[Coordination] Failed to clear shared rate limits: {}", e);
+            }
+        }
         self.rate_limit_tracker.clear(account_id)
     }
 
     /// 清除所有限流记录
-    pub fn clear_all_rate_limits(&self) {
+    pub async fn clear_all_rate_limits(&self) {
+        if self.is_shared_coordination() {
+            if let Err(e) = coord_blocking(|| proxy_db::coord_clear_rate_limits(None)).await {
+                tracing::warn!("[Coordination] Failed to clear shared rate limits: {}", e);
+            }
+        }
         self.rate_limit_tracker.clear_all();
     }
 
@@ -1846,6 +2105,11 @@ impl TokenManager {
     /// 下次失败时从最短的锁定时间开始（智能限流）。
     pub fn mark_account_success(&self, account_id: &str) {
         self.rate_limit_tracker.mark_success(account_id);
+        // 调用方可能传入邮箱，统一换算为账号 ID
+        let id = self
+            .email_to_account_id(account_id)
+            .unwrap_or_else(|| account_id.to_string());
+        self.clear_hard_failures(&id);
     }
 
     /// 检查是否有可用的 Google 账号
@@ -2065,6 +2329,31 @@ impl TokenManager {
 
         // [FIX] Convert email to account_id for consistent tracking
         let account_id = self.email_to_account_id(email).unwrap_or_else(|| email.to_string());
+        self.lock_rate_limited_account(
+            &account_id,
+            email,
+            status,
+            retry_after_header,
+            error_body,
+            model,
+            &config,
+        )
+        .await;
+        // [NEW] 共享协调模式下同步到其他实例
+        self.publish_rate_limits(&account_id).await;
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn lock_rate_limited_account(
+        &self,
+        account_id: &str,
+        email: &str,
+        status: u16,
+        retry_after_header: Option<&str>,
+        error_body: &str,
+        model: Option<&str>,
+        config: &crate::models::CircuitBreakerConfig,
+    ) {
 
         // 检查 API 是否返回了精确的重试时间
         let has_explicit_retry_time = retry_after_header.is_some() ||
@@ -2084,14 +2373,16 @@ impl TokenManager {
                     account_id
                 );
             }
-            self.rate_limit_tracker.parse_from_error(
-                &account_id,
+            if let Some(info) = self.rate_limit_tracker.parse_from_error(
+                account_id,
                 status,
                 retry_after_header,
                 error_body,
                 model.map(|s| s.to_string()),
                 &config.backoff_steps, // [NEW] 传入配置
-            );
+            ) {
+                self.notify_rate_limit_event(account_id, email, &info, &config.backoff_steps);
+            }
             return;
         }
 
@@ -2123,24 +2414,48 @@ impl TokenManager {
         // [FIX] 传入 email 而不是 account_id，因为 fetch_and_lock_with_realtime_quota 期望 email
         if self.fetch_and_lock_with_realtime_quota(email, reason, model.map(|s| s.to_string())).await {
             tracing::info!("账号 {} 已使用实时配额精确锁定", email);
+            Self::notify_quota_exceeded(account_id, email, reason, model);
             return;
         }
 
         // 实时刷新失败,尝试使用本地缓存的配额刷新时间
-        if self.set_precise_lockout(&account_id, reason, model.map(|s| s.to_string())) {
+        if self.set_precise_lockout(account_id, reason, model.map(|s| s.to_string())) {
             tracing::info!("账号 {} 已使用本地缓存配额锁定", account_id);
+            Self::notify_quota_exceeded(account_id, email, reason, model);
             return;
         }
 
         // 都失败了,回退到指数退避策略
         tracing::warn!("账号 {} 无法获取配额刷新时间,使用指数退避策略", account_id);
-        self.rate_limit_tracker.parse_from_error(
-            &account_id,
+        if let Some(info) = self.rate_limit_tracker.parse_from_error(
+            account_id,
             status,
             retry_after_header,
             error_body,
             model.map(|s| s.to_string()),
             &config.backoff_steps, // [NEW] 传入配置
+        ) {
+            self.notify_rate_limit_event(account_id, email, &info, &config.backoff_steps);
+        }
+    }
+
+    fn notify_quota_exceeded(
+        account_id: &str,
+        email: &str,
+        reason: crate::proxy::rate_limit::RateLimitReason,
+        model: Option<&str>,
+    ) {
+        if reason != crate::proxy::rate_limit::RateLimitReason::QuotaExhausted {
+            return;
+        }
+        crate::proxy::webhook::emit(
+            crate::proxy::config::WebhookEvent::QuotaExceeded,
+            serde_json::json!({
+                "account_id": account_id,
+                "email": email,
+                "reason": format!("{:?}", reason),
+                "model": model,
+            }),
         );
     }
 
@@ -2165,6 +2480,113 @@ impl TokenManager {
         tracing::debug!("Circuit breaker configuration updated");
     }
 
+    /// [NEW] 更新账号并发限制配置
+    pub fn update_concurrency_config(&self, config: AccountConcurrencyConfig) {
+        if let Ok(mut lock) = self.concurrency_config.write() {
+            tracing::debug!("Account concurrency configuration updated: {:?}", config);
+            *lock = config;
+        }
+    }
+
+    /// [NEW] 更新连续硬失败自动禁用阈值 (0 表示关闭)
+    pub fn update_auto_disable_threshold(&self, threshold: u32) {
+        self.auto_disable_threshold.store(threshold, Ordering::Relaxed);
+    }
+
+    /// [NEW] 账号当前的连续硬失败次数
+    pub fn consecutive_failures(&self, account_id: &str) -> u32 {
+        self.hard_failures.get(account_id).map(|c| *c).unwrap_or(0)
+    }
+
+    /// [NEW] 清除连续硬失败计数 (请求成功、手动预热成功或重新启用时调用)
+    pub fn clear_hard_failures(&self, account_id: &str) {
+        self.hard_failures.remove(account_id);
+    }
+
+    /// [NEW] 记录一次硬失败 (`class` 见 classify_hard_failure)
+    /// 连续次数达到阈值时通过 toggle_proxy_status 禁用反代并移出账号池，返回是否已禁用
+    pub async fn record_hard_failure(&self, account_id: &str, class: &str) -> bool {
+        let count = {
+            let mut entry = self.hard_failures.entry(account_id.to_string()).or_insert(0);
+            *entry += 1;
+            *entry
+        };
+        let threshold = self.auto_disable_threshold.load(Ordering::Relaxed);
+        tracing::debug!(
+            "Account {} hard failure ({}): {} consecutive",
+            account_id,
+            class,
+            count
+        );
+        if threshold == 0 || count < threshold {
+            return false;
+        }
+
+        match crate::modules::account::toggle_proxy_status(
+            account_id,
+            false,
+            Some(AUTO_DISABLED_ERROR_RATE_REASON),
+        ) {
+            Ok(()) => {
+                // 与手动禁用一致: 同步内存池 (禁用后 reload 会将其移出轮换)
+                if let Err(e) = self.reload_account(account_id).await {
+                    tracing::debug!("Reload after auto-disable failed for {}: {}", account_id, e);
+                    self.remove_account(account_id).await;
+                }
+                tracing::warn!(
+                    "🚫 Account {} auto-disabled for proxy after {} consecutive {} failures",
+                    account_id,
+                    count,
+                    class
+                );
+                true
+            }
+            Err(e) => {
+                tracing::error!("Failed to auto-disable account {}: {}", account_id, e);
+                false
+            }
+        }
+    }
+
+    /// [NEW] 申请账号并发许可
+    /// - Ok(None): 未启用限制
+    /// - Err(retry_after): 排队超时，建议客户端等待的时间
+    pub async fn acquire_account_permit(
+        &self,
+        account_id: &str,
+        priority: RequestPriority,
+    ) -> Result<Option<AccountPermit>, std::time::Duration> {
+        let config = self
+            .concurrency_config
+            .read()
+            .map(|c| c.clone())
+            .unwrap_or_default();
+        if config.max_per_account == 0 {
+            return Ok(None);
+        }
+
+        let limiter = self
+            .account_limiters
+            .entry(account_id.to_string())
+            .or_default()
+            .clone();
+        let timeout = std::time::Duration::from_secs(config.queue_timeout_secs);
+        limiter
+            .acquire(priority, config.max_per_account, timeout)
+            .await
+            .map(Some)
+            .ok_or_else(|| timeout.max(std::time::Duration::from_secs(1)))
+    }
+
+    /// [NEW] 各账号当前在途/排队请求数 (account_id -> 统计)
+    pub fn in_flight_counts(&self) -> HashMap<String, AccountInFlight> {
+        self.account_limiters
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().snapshot()))
+            .filter(|(_, s)| s.in_flight > 0 || s.queued_normal > 0 || s.queued_low > 0)
+            .collect()
+    }
+
     /// [NEW] 获取熔断器配置
     pub async fn get_circuit_breaker_config(&self) -> crate::models::CircuitBreakerConfig {
         self.circuit_breaker_config.read().await.clone()
@@ -2172,13 +2594,360 @@ impl TokenManager {
 
     /// 清除特定会话的粘性映射
     #[allow(dead_code)]
-    pub fn clear_session_binding(&self, session_id: &str) {
-        self.session_accounts.remove(session_id);
+    pub async fn clear_session_binding(&self, session_id: &str) {
+        self.unbind_session(session_id, None).await;
     }
 
     /// 清除所有会话的粘性映射
-    pub fn clear_all_sessions(&self) {
+    pub async fn clear_all_sessions(&self) {
         self.session_accounts.clear();
+        self.session_last_seen.clear();
+        if self.is_shared_coordination() {
+            if let Err(e) = coord_blocking(|| proxy_db::coord_clear_sessions(None)).await {
+                tracing::warn!("[Coordination] Failed to clear shared session bindings: {}", e);
+            }
+        }
+    }
+
+    /// 删除本地闲置超过 `ttl_secs` 的会话绑定，返回删除数量 (0 = 永不过期)
+    /// 只清理本地缓存；共享协调模式下数据库中的绑定由查询时的闲置检查解除
+    fn prune_idle_sessions(
+        session_accounts: &DashMap<String, String>,
+        session_last_seen: &DashMap<String, std::time::Instant>,
+        ttl_secs: u64,
+    ) -> usize {
+        if ttl_secs == 0 {
+            return 0;
+        }
+        let expired: Vec<String> = session_last_seen
+            .iter()
+            .filter(|e| e.value().elapsed().as_secs() >= ttl_secs)
+            .map(|e| e.key().clone())
+            .collect();
+        for session_id in &expired {
+            session_last_seen.remove(session_id);
+            session_accounts.remove(session_id);
+        }
+        expired.len()
+    }
+
+    /// [NEW] 保存调度状态 (轮询游标与会话绑定)，写入临时文件后替换，避免半截文件
+    pub fn save_state(&self, provider_rr: usize) -> Result<(), String> {
+        let now = chrono::Utc::now().timestamp();
+        let sessions = self
+            .session_accounts
+            .iter()
+            .map(|entry| {
+                let idle = self
+                    .session_last_seen
+                    .get(entry.key())
+                    .map(|t| t.elapsed().as_secs() as i64)
+                    .unwrap_or(0);
+                PersistedSessionBinding {
+                    session_id: entry.key().clone(),
+                    account_id: entry.value().clone(),
+                    last_seen: now - idle,
+                }
+            })
+            .collect();
+        let state = SchedulerState {
+            saved_at: now,
+            current_index: self.current_index.load(Ordering::SeqCst),
+            provider_rr,
+            sessions,
+        };
+
+        let path = self.data_dir.join(SCHEDULER_STATE_FILE);
+        let tmp_path = path.with_extension("json.tmp");
+        let content = serde_json::to_string(&state)
+            .map_err(|e| format!("Failed to serialize scheduler state: {}", e))?;
+        std::fs::write(&tmp_path, content)
+            .and_then(|_| std::fs::rename(&tmp_path, &path))
+            .map_err(|e| format!("Failed to save scheduler state: {}", e))
+    }
+
+    /// [NEW] 恢复调度状态，返回保存时的 provider_rr (文件不存在时返回 None)
+    /// 需在 load_accounts 之后调用：已不存在的账号及闲置超过粘性 TTL 的绑定会被丢弃
+    pub async fn load_state(&self) -> Result<Option<usize>, String> {
+        let path = self.data_dir.join(SCHEDULER_STATE_FILE);
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read scheduler state: {}", e)),
+        };
+        let state: SchedulerState = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse scheduler state: {}", e))?;
+
+        let ttl = self.sticky_config.read().await.session_ttl_seconds;
+        let now = chrono::Utc::now().timestamp();
+        let mut restored = 0;
+        for binding in state.sessions {
+            let idle = now.saturating_sub(binding.last_seen).max(0) as u64;
+            if (ttl > 0 && idle >= ttl) || !self.tokens.contains_key(&binding.account_id) {
+                continue;
+            }
+            let last_seen = std::time::Instant::now()
+                .checked_sub(std::time::Duration::from_secs(idle))
+                .unwrap_or_else(std::time::Instant::now);
+            self.session_accounts
+                .insert(binding.session_id.clone(), binding.account_id);
+            self.session_last_seen.insert(binding.session_id, last_seen);
+            restored += 1;
+        }
+        self.current_index.store(state.current_index, Ordering::SeqCst);
+
+        tracing::info!(
+            "Restored scheduler state: cursor {}, {} session binding(s)",
+            state.current_index,
+            restored
+        );
+        Ok(Some(state.provider_rr))
+    }
+
+    // ===== [NEW] 多实例协调 (coordination_mode = shared_database) =====
+    // 本地 DashMap / RwLock 仍作为缓存；共享模式下会话绑定与固定账号按请求从数据库读取，
+    // 熔断状态写入时同步到数据库，并由后台任务定期拉取其他实例的记录
+
+    /// 更新多实例协调模式
+    pub fn update_coordination_mode(&self, mode: CoordinationMode) {
+        if let Ok(mut lock) = self.coordination_mode.write() {
+            if *lock != mode {
+                tracing::info!("[Coordination] Coordination mode changed to {:?}", mode);
+            }
+            *lock = mode;
+        }
+    }
+
+    fn is_shared_coordination(&self) -> bool {
+        self.coordination_mode
+            .read()
+            .map(|m| *m == CoordinationMode::SharedDatabase)
+            .unwrap_or(false)
+    }
+
+    /// 查询会话绑定 (共享模式下以数据库为准并刷新本地缓存)
+    /// 闲置超过 `ttl_secs` 的绑定视为失效并解除；命中时刷新最近使用时间
+    async fn session_binding(&self, session_id: &str, ttl_secs: u64) -> Option<String> {
+        let idle_expired = ttl_secs > 0
+            && self
+                .session_last_seen
+                .get(session_id)
+                .map(|t| t.elapsed().as_secs() >= ttl_secs)
+                .unwrap_or(false);
+        if idle_expired {
+            let bound = self.session_accounts.get(session_id).map(|v| v.clone());
+            tracing::debug!("Sticky Session: Session {} idle for over {}s, unbinding", session_id, ttl_secs);
+            self.unbind_session(session_id, bound.as_deref()).await;
+            return None;
+        }
+        let bound = self.lookup_session_binding(session_id).await;
+        if bound.is_some() {
+            self.session_last_seen
+                .insert(session_id.to_string(), std::time::Instant::now());
+        }
+        bound
+    }
+
+    async fn lookup_session_binding(&self, session_id: &str) -> Option<String> {
+        if self.is_shared_coordination() {
+            let sid = session_id.to_string();
+            match coord_blocking(move || proxy_db::coord_get_session(&sid)).await {
+                Ok(Some(account_id)) => {
+                    self.session_accounts
+                        .insert(session_id.to_string(), account_id.clone());
+                    return Some(account_id);
+                }
+                Ok(None) => {
+                    self.session_accounts.remove(session_id);
+                    return None;
+                }
+                Err(e) => {
+                    tracing::warn!("[Coordination] Failed to read session binding: {}", e);
+                }
+            }
+        }
+        self.session_accounts.get(session_id).map(|v| v.clone())
+    }
+
+    /// 建立会话绑定；共享模式下若其他实例已抢先绑定，则采用已有绑定
+    async fn bind_session(&self, session_id: &str, account_id: &str) {
+        let mut effective = account_id.to_string();
+        if self.is_shared_coordination() {
+            let (sid, aid) = (session_id.to_string(), account_id.to_string());
+            match coord_blocking(move || proxy_db::coord_bind_session(&sid, &aid)).await {
+                Ok(winner) => {
+                    if winner != account_id {
+                        tracing::debug!(
+                            "[Coordination] Session {} already bound to {} by another instance",
+                            session_id,
+                            winner
+                        );
+                    }
+                    effective = winner;
+                }
+                Err(e) => {
+                    tracing::warn!("[Coordination] Failed to persist session binding: {}", e);
+                }
+            }
+        }
+        self.session_accounts.insert(session_id.to_string(), effective);
+        self.session_last_seen
+            .insert(session_id.to_string(), std::time::Instant::now());
+    }
+
+    /// 解除会话绑定；`expected_account` 用于避免误删其他实例刚写入的新绑定
+    async fn unbind_session(&self, session_id: &str, expected_account: Option<&str>) {
+        self.session_accounts.remove(session_id);
+        self.session_last_seen.remove(session_id);
+        if self.is_shared_coordination() {
+            let sid = session_id.to_string();
+            let expected = expected_account.map(|a| a.to_string());
+            let result =
+                coord_blocking(move || proxy_db::coord_unbind_session(&sid, expected.as_deref()))
+                    .await;
+            if let Err(e) = result {
+                tracing::warn!("[Coordination] Failed to remove session binding: {}", e);
+            }
+        }
+    }
+
+    async fn unbind_account_sessions(&self, account_id: &str) {
+        self.session_accounts.retain(|_, v| v != account_id);
+        self.session_last_seen
+            .retain(|k, _| self.session_accounts.contains_key(k));
+        if self.is_shared_coordination() {
+            let aid = account_id.to_string();
+            if let Err(e) = coord_blocking(move || proxy_db::coord_clear_sessions(Some(&aid))).await {
+                tracing::warn!("[Coordination] Failed to clear session bindings: {}", e);
+            }
+        }
+    }
+
+    /// 读取固定账号设置 (共享模式下以数据库为准)
+    async fn current_preferred_account(&self) -> Option<String> {
+        if self.is_shared_coordination() {
+            match coord_blocking(|| proxy_db::coord_get_state(PREFERRED_ACCOUNT_STATE_KEY)).await {
+                Ok(Some((value, _))) => {
+                    *self.preferred_account_id.write().await = value.clone();
+                    return value;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!("[Coordination] Failed to read preferred account: {}", e);
+                }
+            }
+        }
+        self.preferred_account_id.read().await.clone()
+    }
+
+    /// 以乐观锁写入共享的固定账号设置
+    /// `only_if_current` 为 Some 时，仅当共享值仍等于它时才修改 (用于清除失效账号)
+    async fn publish_preferred_account(&self, value: Option<&str>, only_if_current: Option<&str>) {
+        if !self.is_shared_coordination() {
+            return;
+        }
+        let value = value.map(|v| v.to_string());
+        let only_if_current = only_if_current.map(|v| v.to_string());
+        // [FIX] 整个乐观锁重试循环放到阻塞线程执行 (busy_timeout 下单次写入可能阻塞数秒)
+        let result = coord_blocking(move || {
+            Self::publish_preferred_account_blocking(value.as_deref(), only_if_current.as_deref());
+            Ok(())
+        })
+        .await;
+        if let Err(e) = result {
+            tracing::warn!("[Coordination] Failed to publish preferred account: {}", e);
+        }
+    }
+
+    fn publish_preferred_account_blocking(value: Option<&str>, only_if_current: Option<&str>) {
+        for _ in 0..COORDINATION_CAS_RETRIES {
+            let (current, version) = match proxy_db::coord_get_state(PREFERRED_ACCOUNT_STATE_KEY) {
+                Ok(Some((current, version))) => (current, Some(version)),
+                Ok(None) => (None, None),
+                Err(e) => {
+                    tracing::warn!("[Coordination] Failed to read preferred account: {}", e);
+                    return;
+                }
+            };
+            if only_if_current.is_some() && current.as_deref() != only_if_current {
+                return;
+            }
+            if version.is_some() && current.as_deref() == value {
+                return;
+            }
+            match proxy_db::coord_compare_and_set_state(PREFERRED_ACCOUNT_STATE_KEY, value, version) {
+                Ok(true) => return,
+                Ok(false) => continue, // 版本冲突，重新读取后重试
+                Err(e) => {
+                    tracing::warn!("[Coordination] Failed to publish preferred account: {}", e);
+                    return;
+                }
+            }
+        }
+        tracing::warn!(
+            "[Coordination] Gave up updating preferred account after {} conflicting writes",
+            COORDINATION_CAS_RETRIES
+        );
+    }
+
+    /// 将本地熔断记录同步到共享数据库
+    async fn publish_rate_limits(&self, account_id: &str) {
+        if !self.is_shared_coordination() {
+            return;
+        }
+        let limits: Vec<(String, proxy_db::CoordRateLimit)> = self
+            .rate_limit_tracker
+            .active_lockouts(account_id)
+            .into_iter()
+            .map(|(key, info)| {
+                let reset_at = info
+                    .reset_time
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_millis() as i64)
+                    .unwrap_or(0);
+                let limit = proxy_db::CoordRateLimit {
+                    account_id: account_id.to_string(),
+                    model: info.model.clone(),
+                    reset_at,
+                    reason: info.reason.as_str().to_string(),
+                };
+                (key, limit)
+            })
+            .collect();
+        if limits.is_empty() {
+            return;
+        }
+        let result = coord_blocking(move || {
+            for (key, limit) in &limits {
+                if let Err(e) = proxy_db::coord_upsert_rate_limit(key, limit) {
+                    tracing::warn!("[Coordination] Failed to publish rate limit for {}: {}", key, e);
+                }
+            }
+            Ok(())
+        })
+        .await;
+        if let Err(e) = result {
+            tracing::warn!("[Coordination] Failed to publish rate limits: {}", e);
+        }
+    }
+
+    /// 拉取其他实例写入的熔断记录并合并到本地 (只延长不缩短)
+    async fn pull_shared_rate_limits(tracker: &RateLimitTracker) {
+        match coord_blocking(proxy_db::coord_active_rate_limits).await {
+            Ok(limits) => {
+                for limit in limits {
+                    let reset_time = std::time::UNIX_EPOCH
+                        + std::time::Duration::from_millis(limit.reset_at.max(0) as u64);
+                    tracker.merge_lockout(
+                        &limit.account_id,
+                        limit.model,
+                        reset_time,
+                        crate::proxy::rate_limit::RateLimitReason::parse(&limit.reason),
+                    );
+                }
+            }
+            Err(e) => tracing::warn!("[Coordination] Failed to pull shared rate limits: {}", e),
+        }
     }
 
     // ===== [FIX #820] 固定账号模式相关方法 =====
@@ -2192,12 +2961,13 @@ impl TokenManager {
         } else {
             tracing::info!("🔄 [FIX #820] Round-robin mode enabled (no preferred account)");
         }
+        self.publish_preferred_account(account_id.as_deref(), None).await;
         *preferred = account_id;
     }
 
     /// 获取当前优先使用的账号ID
     pub async fn get_preferred_account(&self) -> Option<String> {
-        self.preferred_account_id.read().await.clone()
+        self.current_preferred_account().await
     }
 
     /// 使用 Authorization Code 交换 Refresh Token (Web OAuth)
@@ -2335,6 +3105,18 @@ impl TokenManager {
         earliest_ts
     }
 
+    /// 按标签筛选候选账号 (账号须包含全部 `required_tags`，大小写不敏感)
+    ///
+    /// 供模型亲和规则等场景在调用 `get_token` 前缩小候选范围；
+    /// `required_tags` 为空时返回全部已加载账号。
+    pub fn select_account_ids_by_tags(&self, required_tags: &[&str]) -> Vec<String> {
+        self.tokens
+            .iter()
+            .filter(|entry| token_has_tags(entry.value(), required_tags))
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
     /// Helper to find account ID by email
     pub fn get_account_id_by_email(&self, email: &str) -> Option<String> {
         for entry in self.tokens.iter() {
@@ -2370,7 +3152,7 @@ impl TokenManager {
         account["validation_blocked_reason"] = serde_json::Value::String(reason.to_string());
 
         // Clear sticky session if blocked
-        self.session_accounts.retain(|_, v| *v != account_id);
+        self.unbind_account_sessions(account_id).await;
 
         let json_str = serde_json::to_string_pretty(&account)
              .map_err(|e| format!("Failed to serialize account JSON: {}", e))?;
@@ -2393,6 +3175,130 @@ impl TokenManager {
         self.set_validation_block(account_id, block_until, reason).await
     }
 
+    /// 批量校验账号 refresh_token 是否仍然有效
+    ///
+    /// 对每个账号尝试一次 OAuth 刷新 (最多 `concurrency` 个并发)，记录耗时与结果。
+    /// `invalid_grant` 视为 refresh_token 已被吊销；`auto_disable` 为 true 时
+    /// 会将这些账号标记为禁用反代并移出账号池。
+    pub async fn health_check_accounts(
+        &self,
+        account_ids: Option<&[String]>,
+        auto_disable: bool,
+        concurrency: usize,
+    ) -> Result<AccountHealthReport, String> {
+        use futures::future::join_all;
+        use tokio::sync::Semaphore;
+
+        let accounts: Vec<crate::models::Account> = crate::modules::account::list_accounts()?
+            .into_iter()
+            .filter(|a| account_ids.map_or(true, |ids| ids.iter().any(|id| id == &a.id)))
+            .collect();
+
+        let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+        let tasks: Vec<_> = accounts
+            .into_iter()
+            .map(|account| {
+                let permit = semaphore.clone();
+                async move {
+                    let _guard = permit.acquire().await.unwrap();
+                    self.check_account_health(account, auto_disable).await
+                }
+            })
+            .collect();
+
+        let mut report = AccountHealthReport::default();
+        for detail in join_all(tasks).await {
+            match detail.status {
+                AccountHealthStatus::Healthy => report.healthy += 1,
+                AccountHealthStatus::Revoked => report.revoked += 1,
+                AccountHealthStatus::NetworkError => report.network_error += 1,
+            }
+            report.details.push(detail);
+        }
+
+        tracing::info!(
+            "[HealthCheck] {} healthy, {} revoked, {} network errors",
+            report.healthy,
+            report.revoked,
+            report.network_error
+        );
+        Ok(report)
+    }
+
+    async fn check_account_health(
+        &self,
+        account: crate::models::Account,
+        auto_disable: bool,
+    ) -> AccountHealthDetail {
+        let start = std::time::Instant::now();
+        let result = crate::modules::oauth::refresh_access_token(
+            &account.token.refresh_token,
+            Some(&account.id),
+        )
+        .await;
+        let latency_ms = start.elapsed().as_millis() as u64;
+
+        let mut detail = AccountHealthDetail {
+            account_id: account.id.clone(),
+            email: account.email.clone(),
+            status: AccountHealthStatus::Healthy,
+            latency_ms,
+            error: None,
+            disabled_reason: None,
+            auto_disabled: false,
+        };
+
+        match result {
+            Ok(token_response) => {
+                // 顺便刷新内存与磁盘中的 access_token (仅对已加载到账号池的账号)
+                if let Some(mut entry) = self.tokens.get_mut(&account.id) {
+                    let now = chrono::Utc::now().timestamp();
+                    entry.access_token = token_response.access_token.clone();
+                    entry.expires_in = token_response.expires_in;
+                    entry.timestamp = now + token_response.expires_in;
+                }
+                if self.tokens.contains_key(&account.id) {
+                    let _ = self.save_refreshed_token(&account.id, &token_response).await;
+                }
+            }
+            Err(e) if e.contains("invalid_grant") => {
+                detail.status = AccountHealthStatus::Revoked;
+                detail.error = Some(truncate_reason(&e, 300));
+                detail.disabled_reason = Some(REFRESH_TOKEN_REVOKED_REASON.to_string());
+
+                if auto_disable {
+                    match crate::modules::account::toggle_proxy_status(
+                        &account.id,
+                        false,
+                        Some(REFRESH_TOKEN_REVOKED_REASON),
+                    ) {
+                        Ok(()) => {
+                            self.remove_account(&account.id).await;
+                            detail.auto_disabled = true;
+                            tracing::warn!(
+                                "[HealthCheck] Proxy disabled for {}: refresh_token revoked",
+                                account.email
+                            );
+                        }
+                        Err(err) => {
+                            tracing::error!(
+                                "[HealthCheck] Failed to disable {}: {}",
+                                account.email,
+                                err
+                            );
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                detail.status = AccountHealthStatus::NetworkError;
+                detail.error = Some(truncate_reason(&e, 300));
+            }
+        }
+
+        detail
+    }
+
     /// Set is_forbidden status for an account (called when proxy encounters 403)
     pub async fn set_forbidden(&self, account_id: &str, reason: &str) -> Result<(), String> {
         // 1. Persist to disk - update quota.is_forbidden in account JSON
@@ -2420,7 +3326,7 @@ impl TokenManager {
         }
 
         // Clear sticky session if forbidden
-        self.session_accounts.retain(|_, v| *v != account_id);
+        self.unbind_account_sessions(account_id).await;
 
         let json_str = serde_json::to_string_pretty(&account)
             .map_err(|e| format!("Failed to serialize account JSON: {}", e))?;
@@ -2429,7 +3335,7 @@ impl TokenManager {
             .map_err(|e| format!("Failed to write account file: {}", e))?;
 
         // [FIX] 从内存池中移除账号，避免重试时再次选中
-        self.remove_account(account_id);
+        self.remove_account(account_id).await;
 
         tracing::warn!(
             "🚫 Account {} marked as forbidden (403): {}",
@@ -2441,6 +3347,13 @@ impl TokenManager {
     }
 }
 
+/// 账号是否携带全部所需标签 (大小写不敏感)
+fn token_has_tags(token: &ProxyToken, required_tags: &[&str]) -> bool {
+    required_tags
+        .iter()
+        .all(|req| token.tags.iter().any(|t| t.eq_ignore_ascii_case(req)))
+}
+
 /// 截断过长的原因字符串
 fn truncate_reason(reason: &str, max_len: usize) -> String {
     if reason.len() <= max_len {
@@ -2450,11 +3363,50 @@ fn truncate_reason(reason: &str, max_len: usize) -> String {
     }
 }
 
+/// 限流事件对应的 Webhook 事件类型 (None 表示无需通知)
+fn rate_limit_webhook_event(
+    reason: crate::proxy::rate_limit::RateLimitReason,
+    consecutive_failures: u32,
+    backoff_steps: &[u64],
+) -> Option<crate::proxy::config::WebhookEvent> {
+    use crate::proxy::config::WebhookEvent;
+    use crate::proxy::rate_limit::RateLimitReason;
+
+    match reason {
+        RateLimitReason::QuotaExhausted => Some(WebhookEvent::QuotaExceeded),
+        RateLimitReason::ServerError => None,
+        _ if consecutive_failures as usize >= backoff_steps.len().max(1) => {
+            Some(WebhookEvent::CircuitBreakerOpen)
+        }
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::cmp::Ordering;
 
+    #[test]
+    fn test_rate_limit_webhook_event_only_reports_open_breaker() {
+        use crate::proxy::config::WebhookEvent;
+        use crate::proxy::rate_limit::RateLimitReason;
+        let steps = [60, 300, 1800, 7200];
+
+        assert_eq!(
+            rate_limit_webhook_event(RateLimitReason::QuotaExhausted, 1, &steps),
+            Some(WebhookEvent::QuotaExceeded)
+        );
+        // 普通 429 / 5xx 不视为熔断
+        assert_eq!(rate_limit_webhook_event(RateLimitReason::RateLimitExceeded, 1, &steps), None);
+        assert_eq!(rate_limit_webhook_event(RateLimitReason::ServerError, 9, &steps), None);
+        // 连续失败升级到最高档才上报熔断
+        assert_eq!(
+            rate_limit_webhook_event(RateLimitReason::RateLimitExceeded, 4, &steps),
+            Some(WebhookEvent::CircuitBreakerOpen)
+        );
+    }
+
     #[tokio::test]
     async fn test_reload_account_purges_cache_when_account_becomes_proxy_disabled() {
         let tmp_root = std::env::temp_dir().join(format!(
@@ -2573,6 +3525,39 @@ mod tests {
         let _ = std::fs::remove_dir_all(&tmp_root);
     }
 
+    #[tokio::test]
+    async fn test_idle_session_bindings_expire() {
+        let manager = TokenManager::new(std::env::temp_dir());
+        let idle_since = std::time::Instant::now() - std::time::Duration::from_secs(120);
+
+        manager.bind_session("ip-203.0.113.7", "acc1").await;
+        assert_eq!(
+            manager.session_binding("ip-203.0.113.7", 60).await.as_deref(),
+            Some("acc1")
+        );
+
+        manager.session_last_seen.insert("ip-203.0.113.7".to_string(), idle_since);
+        assert_eq!(
+            manager.session_binding("ip-203.0.113.7", 0).await.as_deref(),
+            Some("acc1")
+        );
+        manager.session_last_seen.insert("ip-203.0.113.7".to_string(), idle_since);
+        assert!(manager.session_binding("ip-203.0.113.7", 60).await.is_none());
+        assert!(manager.session_accounts.get("ip-203.0.113.7").is_none());
+
+        manager.bind_session("sid1", "acc1").await;
+        manager.bind_session("sid2", "acc2").await;
+        manager.session_last_seen.insert("sid1".to_string(), idle_since);
+        let pruned = TokenManager::prune_idle_sessions(
+            &manager.session_accounts,
+            &manager.session_last_seen,
+            60,
+        );
+        assert_eq!(pruned, 1);
+        assert!(manager.session_accounts.get("sid1").is_none());
+        assert!(manager.session_accounts.get("sid2").is_some());
+    }
+
     #[tokio::test]
     async fn test_sticky_session_skips_bound_account_when_disabled_on_disk_without_reload() {
         let tmp_root = std::env::temp_dir().join(format!(
@@ -2648,6 +3633,51 @@ mod tests {
         let _ = std::fs::remove_dir_all(&tmp_root);
     }
 
+    #[tokio::test]
+    async fn test_scheduler_state_round_trip_discards_stale_bindings() {
+        let tmp_root = std::env::temp_dir().join(format!(
+            "antigravity-token-manager-test-state-{}",
+            uuid::Uuid::new_v4()
+        ));
+        std::fs::create_dir_all(&tmp_root).unwrap();
+
+        let manager = TokenManager::new(tmp_root.clone());
+        manager.tokens.insert(
+            "a@test.com".to_string(),
+            create_test_token("a@test.com", None, 1.0, None, Some(50)),
+        );
+        manager.current_index.store(7, std::sync::atomic::Ordering::SeqCst);
+        manager.bind_session("sid-live", "a@test.com").await;
+        manager.bind_session("sid-stale", "a@test.com").await;
+        manager.session_last_seen.insert(
+            "sid-stale".to_string(),
+            std::time::Instant::now() - std::time::Duration::from_secs(7200),
+        );
+        manager.bind_session("sid-gone", "removed@test.com").await;
+        manager.save_state(3).unwrap();
+
+        let restored = TokenManager::new(tmp_root.clone());
+        assert_eq!(restored.load_state().await.unwrap(), Some(3));
+        assert_eq!(restored.session_accounts.len(), 0);
+
+        restored.tokens.insert(
+            "a@test.com".to_string(),
+            create_test_token("a@test.com", None, 1.0, None, Some(50)),
+        );
+        restored.sticky_config.write().await.session_ttl_seconds = 3600;
+        assert_eq!(restored.load_state().await.unwrap(), Some(3));
+        assert_eq!(restored.current_index.load(std::sync::atomic::Ordering::SeqCst), 7);
+        assert_eq!(
+            restored.session_accounts.get("sid-live").map(|v| v.clone()),
+            Some("a@test.com".to_string())
+        );
+        assert!(restored.session_accounts.get("sid-stale").is_none());
+        assert!(restored.session_accounts.get("sid-gone").is_none());
+
+        let _ = std::fs::remove_dir_all(&tmp_root);
+        assert_eq!(TokenManager::new(tmp_root).load_state().await.unwrap(), None);
+    }
+
     /// 创建测试用的 ProxyToken
     fn create_test_token(
         email: &str,
@@ -2673,6 +3703,7 @@ mod tests {
             validation_blocked: false,
             validation_blocked_until: 0,
             model_quotas: HashMap::new(),
+            tags: Vec::new(),
         }
     }
 
@@ -2929,6 +3960,7 @@ mod tests {
             validation_blocked: false,
             validation_blocked_until: 0,
             model_quotas: HashMap::new(),
+            tags: Vec::new(),
         }
     }
 
@@ -3031,4 +4063,60 @@ mod tests {
         let result = manager.select_with_p2c(&candidates, &attempted, "claude-sonnet", false);
         assert!(result.is_none());
     }
+
+    #[test]
+    fn test_select_account_ids_by_tags() {
+        let manager = TokenManager::new(PathBuf::from("/tmp/test"));
+        let mut prod = create_test_token_with_protected("prod@test.com", Some(80), HashSet::new());
+        prod.tags = vec!["Production".to_string(), "team-a".to_string()];
+        let mut staging = create_test_token_with_protected("staging@test.com", Some(80), HashSet::new());
+        staging.tags = vec!["staging".to_string()];
+        manager.tokens.insert(prod.account_id.clone(), prod);
+        manager.tokens.insert(staging.account_id.clone(), staging);
+
+        assert_eq!(manager.select_account_ids_by_tags(&[]).len(), 2);
+        assert_eq!(
+            manager.select_account_ids_by_tags(&["production"]),
+            vec!["prod@test.com".to_string()]
+        );
+        assert_eq!(
+            manager.select_account_ids_by_tags(&["production", "team-a"]),
+            vec!["prod@test.com".to_string()]
+        );
+        assert!(manager.select_account_ids_by_tags(&["production", "staging"]).is_empty());
+    }
+
+    #[test]
+    fn test_classify_hard_failure() {
+        assert_eq!(
+            classify_hard_failure(403, r#"{"error":{"status":"PERMISSION_DENIED"}}"#),
+            Some("permission_denied")
+        );
+        assert_eq!(
+            classify_hard_failure(403, "The account has been suspended"),
+            Some("account_suspended")
+        );
+        // 临时验证拦截与软错误不计入
+        assert_eq!(classify_hard_failure(403, "VALIDATION_REQUIRED"), None);
+        assert_eq!(classify_hard_failure(429, "RESOURCE_EXHAUSTED"), None);
+        assert_eq!(classify_hard_failure(504, "timeout"), None);
+    }
+
+    #[tokio::test]
+    async fn test_hard_failure_counter_resets() {
+        let manager = TokenManager::new(std::env::temp_dir());
+        // 阈值为 0 时只计数，不禁用
+        manager.update_auto_disable_threshold(0);
+        for _ in 0..3 {
+            assert!(!manager.record_hard_failure("acc-1", "permission_denied").await);
+        }
+        assert_eq!(manager.consecutive_failures("acc-1"), 3);
+
+        manager.mark_account_success("acc-1");
+        assert_eq!(manager.consecutive_failures("acc-1"), 0);
+
+        manager.record_hard_failure("acc-1", "permission_denied").await;
+        manager.clear_hard_failures("acc-1");
+        assert_eq!(manager.consecutive_failures("acc-1"), 0);
+    }
 }
@@ -0,0 +1,397 @@
+// 请求/响应 JSON 改写规则
+// 规则来自 ProxyConfig.transforms，按声明顺序执行；字段路径为 JSON Pointer，
+// 在保存配置时校验，运行时遇到不存在或类型不符的路径只会跳过该动作
+use serde_json::{Map, Value};
+use std::sync::{OnceLock, RwLock};
+
+use crate::proxy::common::model_mapping::wildcard_match;
+use crate::proxy::config::{TransformAction, TransformRule, TransformsConfig};
+
+fn store() -> &'static RwLock<TransformsConfig> {
+    static CONFIG: OnceLock<RwLock<TransformsConfig>> = OnceLock::new();
+    CONFIG.get_or_init(|| RwLock::new(TransformsConfig::default()))
+}
+
+/// 当前生效的改写规则
+pub fn get_transforms_config() -> TransformsConfig {
+    store().read().map(|c| c.clone()).unwrap_or_default()
+}
+
+/// 热更新改写规则 (启动 / 保存配置时调用，调用前应已通过 validate_transforms)
+pub fn update_transforms_config(config: TransformsConfig) {
+    if let Ok(mut current) = store().write() {
+        if *current != config {
+            tracing::info!(
+                "[Transforms] Config updated: {} request rule(s), {} response rule(s)",
+                config.request.len(),
+                config.response.len()
+            );
+        }
+        *current = config;
+    }
+}
+
+/// 校验所有规则，返回全部错误的描述
+pub fn validate_transforms(config: &TransformsConfig) -> Result<(), String> {
+    let mut errors = Vec::new();
+    for (section, rules) in [("request", &config.request), ("response", &config.response)] {
+        for (i, rule) in rules.iter().enumerate() {
+            let label = match rule.name.as_deref().filter(|n| !n.is_empty()) {
+                Some(name) => format!("transforms.{}[{}] ({})", section, i, name),
+                None => format!("transforms.{}[{}]", section, i),
+            };
+            for (j, action) in rule.actions.iter().enumerate() {
+                if let Err(e) = validate_action(action) {
+                    errors.push(format!("{}.actions[{}]: {}", label, j, e));
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Invalid transform rules: {}", errors.join("; ")))
+    }
+}
+
+fn validate_action(action: &TransformAction) -> Result<(), String> {
+    match action {
+        TransformAction::SetField { path, .. } | TransformAction::DeleteField { path } => {
+            validate_pointer(path)
+        }
+        TransformAction::ClampNumber { path, min, max } => {
+            validate_pointer(path)?;
+            match (min, max) {
+                (None, None) => Err("clamp_number requires min and/or max".to_string()),
+                (Some(min), Some(max)) if min > max => {
+                    Err(format!("clamp_number min ({}) is greater than max ({})", min, max))
+                }
+                _ => Ok(()),
+            }
+        }
+        TransformAction::RenameField { from, to } => {
+            validate_pointer(from)?;
+            validate_pointer(to)?;
+            if to == from || to.starts_with(&format!("{}/", from)) {
+                return Err(format!("cannot rename {:?} into itself ({:?})", from, to));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// JSON Pointer 校验: 必须以 `/` 开头 (不允许改写根节点)，`~` 只能用于 `~0` / `~1` 转义
+fn validate_pointer(path: &str) -> Result<(), String> {
+    if !path.starts_with('/') {
+        return Err(format!("invalid JSON pointer {:?}: must start with '/'", path));
+    }
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        if c == '~' && !matches!(chars.next(), Some('0') | Some('1')) {
+            return Err(format!(
+                "invalid JSON pointer {:?}: '~' must be followed by '0' or '1'",
+                path
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn rule_matches(rule: &TransformRule, route: &str, model: Option<&str>) -> bool {
+    if !rule.enabled {
+        return false;
+    }
+    if let Some(pattern) = rule.route.as_deref().filter(|p| !p.is_empty()) {
+        if !wildcard_match(pattern, route) {
+            return false;
+        }
+    }
+    if let Some(pattern) = rule.model.as_deref().filter(|p| !p.is_empty()) {
+        match model {
+            Some(m) if wildcard_match(pattern, m) => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// 依次执行匹配的规则，返回实际生效的动作数
+pub fn apply_rules(
+    rules: &[TransformRule],
+    route: &str,
+    model: Option<&str>,
+    body: &mut Value,
+) -> usize {
+    let mut applied = 0;
+    for rule in rules.iter().filter(|r| rule_matches(r, route, model)) {
+        for action in &rule.actions {
+            if apply_action(action, body) {
+                applied += 1;
+            }
+        }
+    }
+    applied
+}
+
+fn apply_action(action: &TransformAction, body: &mut Value) -> bool {
+    match action {
+        TransformAction::SetField { path, value } => set_at(body, path, value.clone()),
+        TransformAction::DeleteField { path } => remove_at(body, path).is_some(),
+        TransformAction::ClampNumber { path, min, max } => {
+            let Some(target) = body.pointer_mut(path) else {
+                return false;
+            };
+            let Some(n) = target.as_f64() else {
+                return false;
+            };
+            let mut clamped = n;
+            if let Some(min) = min {
+                clamped = clamped.max(*min);
+            }
+            if let Some(max) = max {
+                clamped = clamped.min(*max);
+            }
+            if clamped == n {
+                return false;
+            }
+            // 整数字段 (如 max_tokens) 保持整数，避免上游拒绝 `8192.0`
+            *target = if (target.is_i64() || target.is_u64()) && clamped.fract() == 0.0 {
+                Value::from(clamped as i64)
+            } else {
+                serde_json::Number::from_f64(clamped)
+                    .map(Value::Number)
+                    .unwrap_or(Value::Null)
+            };
+            true
+        }
+        TransformAction::RenameField { from, to } => {
+            let Some(value) = remove_at(body, from) else {
+                return false;
+            };
+            if set_at(body, to, value.clone()) {
+                true
+            } else {
+                // 目标路径无法写入时还原
+                set_at(body, from, value);
+                false
+            }
+        }
+    }
+}
+
+fn unescape_token(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+fn remove_at(body: &mut Value, path: &str) -> Option<Value> {
+    let idx = path.rfind('/')?;
+    let key = unescape_token(&path[idx + 1..]);
+    match body.pointer_mut(&path[..idx])? {
+        Value::Object(map) => map.remove(&key),
+        Value::Array(arr) => {
+            let i = key.parse::<usize>().ok().filter(|i| *i < arr.len())?;
+            Some(arr.remove(i))
+        }
+        _ => None,
+    }
+}
+
+/// 写入字段，中间缺失的对象会被创建；数组只能写入已有下标或用 `-` 追加
+fn set_at(body: &mut Value, path: &str, value: Value) -> bool {
+    let Some(rest) = path.strip_prefix('/') else {
+        return false;
+    };
+    let tokens: Vec<String> = rest.split('/').map(unescape_token).collect();
+    let Some((last, parents)) = tokens.split_last() else {
+        return false;
+    };
+
+    let mut current = body;
+    for token in parents {
+        current = match current {
+            Value::Object(map) => map
+                .entry(token.clone())
+                .or_insert_with(|| Value::Object(Map::new())),
+            Value::Array(arr) => match token.parse::<usize>().ok().and_then(|i| arr.get_mut(i)) {
+                Some(v) => v,
+                None => return false,
+            },
+            _ => return false,
+        };
+    }
+
+    match current {
+        Value::Object(map) => {
+            map.insert(last.clone(), value);
+            true
+        }
+        Value::Array(arr) if last == "-" => {
+            arr.push(value);
+            true
+        }
+        Value::Array(arr) => match last.parse::<usize>() {
+            Ok(i) if i < arr.len() => {
+                arr[i] = value;
+                true
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rule(route: Option<&str>, model: Option<&str>, actions: Vec<TransformAction>) -> TransformRule {
+        TransformRule {
+            name: None,
+            enabled: true,
+            route: route.map(str::to_string),
+            model: model.map(str::to_string),
+            actions,
+        }
+    }
+
+    #[test]
+    fn test_actions_in_order() {
+        let rules = vec![rule(
+            None,
+            Some("gemini-*"),
+            vec![
+                TransformAction::DeleteField {
+                    path: "/frequency_penalty".to_string(),
+                },
+                TransformAction::SetField {
+                    path: "/temperature".to_string(),
+                    value: json!(0.2),
+                },
+                TransformAction::ClampNumber {
+                    path: "/max_tokens".to_string(),
+                    min: None,
+                    max: Some(8192.0),
+                },
+                TransformAction::RenameField {
+                    from: "/stop".to_string(),
+                    to: "/generationConfig/stopSequences".to_string(),
+                },
+            ],
+        )];
+        let mut body = json!({
+            "model": "gemini-2.5-pro",
+            "frequency_penalty": 0.5,
+            "temperature": 1.0,
+            "max_tokens": 65536,
+            "stop": ["END"]
+        });
+
+        let applied = apply_rules(&rules, "/v1/chat/completions", Some("gemini-2.5-pro"), &mut body);
+        assert_eq!(applied, 4);
+        assert_eq!(
+            body,
+            json!({
+                "model": "gemini-2.5-pro",
+                "temperature": 0.2,
+                "max_tokens": 8192,
+                "generationConfig": {"stopSequences": ["END"]}
+            })
+        );
+    }
+
+    #[test]
+    fn test_rule_matching() {
+        let rules = vec![rule(
+            Some("/v1/messages"),
+            Some("claude-*"),
+            vec![TransformAction::SetField {
+                path: "/metadata/source".to_string(),
+                value: json!("proxy"),
+            }],
+        )];
+
+        let mut body = json!({});
+        assert_eq!(apply_rules(&rules, "/v1/chat/completions", Some("claude-sonnet-4"), &mut body), 0);
+        assert_eq!(apply_rules(&rules, "/v1/messages", None, &mut body), 0);
+        assert_eq!(apply_rules(&rules, "/v1/messages", Some("claude-sonnet-4"), &mut body), 1);
+        assert_eq!(body, json!({"metadata": {"source": "proxy"}}));
+    }
+
+    #[test]
+    fn test_missing_paths_are_skipped() {
+        let mut body = json!({"messages": [{"role": "user"}]});
+        let actions = vec![
+            TransformAction::DeleteField {
+                path: "/nope/deeper".to_string(),
+            },
+            TransformAction::ClampNumber {
+                path: "/messages/0/role".to_string(),
+                min: Some(0.0),
+                max: None,
+            },
+            TransformAction::SetField {
+                path: "/messages/5/role".to_string(),
+                value: json!("system"),
+            },
+        ];
+        assert_eq!(apply_rules(&[rule(None, None, actions)], "/", None, &mut body), 0);
+        assert_eq!(body, json!({"messages": [{"role": "user"}]}));
+    }
+
+    #[test]
+    fn test_validation_reports_bad_pointers() {
+        let config = TransformsConfig {
+            request: vec![TransformRule {
+                name: Some("strip".to_string()),
+                ..rule(
+                    None,
+                    None,
+                    vec![
+                        TransformAction::DeleteField {
+                            path: "temperature".to_string(),
+                        },
+                        TransformAction::SetField {
+                            path: "/a~2b".to_string(),
+                            value: json!(1),
+                        },
+                    ],
+                )
+            }],
+            response: vec![rule(
+                None,
+                None,
+                vec![TransformAction::ClampNumber {
+                    path: "/x".to_string(),
+                    min: Some(2.0),
+                    max: Some(1.0),
+                }],
+            )],
+        };
+        let err = validate_transforms(&config).unwrap_err();
+        assert!(err.contains("transforms.request[0] (strip).actions[0]"));
+        assert!(err.contains("must start with '/'"));
+        assert!(err.contains("transforms.request[0] (strip).actions[1]"));
+        assert!(err.contains("transforms.response[0].actions[0]"));
+
+        assert!(validate_transforms(&TransformsConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn test_rule_deserialization() {
+        let config: TransformsConfig = serde_json::from_value(json!({
+            "request": [{
+                "model": "gemini-*",
+                "actions": [
+                    {"type": "delete_field", "path": "/frequency_penalty"},
+                    {"type": "clamp_number", "path": "/temperature", "max": 1.0}
+                ]
+            }]
+        }))
+        .unwrap();
+        assert!(config.request[0].enabled);
+        assert_eq!(config.request[0].actions.len(), 2);
+        assert!(config.response.is_empty());
+    }
+}
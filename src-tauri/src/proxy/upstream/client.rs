@@ -4,6 +4,7 @@
 use dashmap::DashMap;
 use reqwest::{header, Client, Response, StatusCode};
 use serde_json::Value;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio::time::Duration;
@@ -27,6 +28,10 @@ pub struct UpstreamCallResult {
     pub fallback_attempts: Vec<FallbackAttemptLog>,
 }
 
+fn pick_user_agent(pool: &[String], idx: usize) -> &str {
+    &pool[idx % pool.len()]
+}
+
 /// 邮箱脱敏：只显示前3位 + *** + @域名前2位 + ***
 /// 例: "userexample@gmail.com" → "use***@gm***"
 pub fn mask_email(email: &str) -> String {
@@ -56,11 +61,117 @@ const V1_INTERNAL_BASE_URL_FALLBACKS: [&str; 3] = [
     V1_INTERNAL_BASE_URL_PROD,    // 优先级 3: Prod (仅作为兜底)
 ];
 
+/// 连接池参数 (来自 UpstreamProxyConfig，默认客户端与代理池客户端共用)
+#[derive(Debug, Clone, Copy)]
+struct PoolSettings {
+    max_idle_per_host: usize,
+    idle_timeout: Duration,
+    verbose: bool,
+    http2: bool,
+    tcp_keepalive: Option<Duration>,
+}
+
+impl PoolSettings {
+    fn from_config(config: Option<&crate::proxy::config::UpstreamProxyConfig>) -> Self {
+        let defaults = crate::proxy::config::UpstreamProxyConfig::default();
+        let config = config.unwrap_or(&defaults);
+        Self {
+            max_idle_per_host: config.pool_max_idle_per_host,
+            idle_timeout: Duration::from_secs(config.pool_idle_timeout_seconds),
+            verbose: config.connection_verbose,
+            http2: config.http2_enabled,
+            tcp_keepalive: Some(config.tcp_keepalive_seconds)
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+        }
+    }
+}
+
+/// DNS 解析器: reqwest 仅在建立新连接时解析主机名，借此统计新建连接数
+struct CountingResolver;
+
+impl reqwest::dns::Resolve for CountingResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        crate::proxy::monitor::ConnectionCounters::global().record_new_connection();
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            // 端口由连接器在解析后补全
+            let addrs: Vec<std::net::SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// 本次请求依次尝试的端点: 命中模型路由时仅为路由地址，否则为默认降级列表
+fn endpoints_for_route(route: Option<&crate::proxy::config::ModelRoute>) -> Vec<&str> {
+    match route {
+        Some(route) => vec![route.normalized_base_url()],
+        None => V1_INTERNAL_BASE_URL_FALLBACKS.to_vec(),
+    }
+}
+
+/// 本次请求的 Authorization 头: 路由配置了 auth_header 时使用它；
+/// 账号 OAuth 令牌只发往 Google 官方端点，其他路由未配置 auth_header 时不携带凭据
+fn authorization_for_route(
+    route: Option<&crate::proxy::config::ModelRoute>,
+    access_token: &str,
+) -> Option<String> {
+    match route {
+        None => Some(format!("Bearer {}", access_token)),
+        Some(route) => match route.auth_header.as_deref().map(str::trim) {
+            Some(value) if !value.is_empty() => Some(value.to_string()),
+            _ if route.is_google_endpoint() => Some(format!("Bearer {}", access_token)),
+            _ => None,
+        },
+    }
+}
+
+/// 当前默认客户端及其连接池参数 (热更新时整体替换)
+struct DefaultClient {
+    client: Client,
+    settings: PoolSettings,
+    config: Option<crate::proxy::config::UpstreamProxyConfig>,
+}
+
+/// 连接池状态 (`GET /api/proxy/stats`)
+/// reqwest 未暴露连接池内部状态，idle / waiting 无法获取，恒为 None
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ConnectionPoolStats {
+    /// 正在等待上游响应头的请求数
+    pub active: usize,
+    pub idle: Option<usize>,
+    pub waiting: Option<usize>,
+    pub max_idle_per_host: usize,
+    pub idle_timeout_seconds: u64,
+    pub http2_enabled: bool,
+}
+
+/// 在途请求计数守卫: 创建时 +1，析构时 -1
+struct InFlightGuard<'a>(&'a AtomicUsize);
+
+impl<'a> InFlightGuard<'a> {
+    fn new(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Self(counter)
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 默认客户端按上游代理配置共享 (User-Agent 以请求头下发，不影响连接复用)
 pub struct UpstreamClient {
-    default_client: Client,
+    default_client: std::sync::RwLock<DefaultClient>,
+    in_flight: AtomicUsize,
     proxy_pool: Option<Arc<crate::proxy::proxy_pool::ProxyPoolManager>>,
     client_cache: DashMap<String, Client>, // proxy_id -> Client
     user_agent_override: RwLock<Option<String>>,
+    user_agent_pool: RwLock<Vec<String>>, // [NEW] 非空时按请求轮换，优先于单一覆盖
+    user_agent_rr: AtomicUsize,
 }
 
 impl UpstreamClient {
@@ -68,30 +179,61 @@ impl UpstreamClient {
         proxy_config: Option<crate::proxy::config::UpstreamProxyConfig>,
         proxy_pool: Option<Arc<crate::proxy::proxy_pool::ProxyPoolManager>>,
     ) -> Self {
-        let default_client = Self::build_client_internal(proxy_config)
+        let pool_settings = PoolSettings::from_config(proxy_config.as_ref());
+        let default_client = Self::build_client_internal(proxy_config.clone(), pool_settings)
             .expect("Failed to create default HTTP client");
 
         Self {
-            default_client,
+            default_client: std::sync::RwLock::new(DefaultClient {
+                client: default_client,
+                settings: pool_settings,
+                config: proxy_config,
+            }),
+            in_flight: AtomicUsize::new(0),
             proxy_pool,
             client_cache: DashMap::new(),
             user_agent_override: RwLock::new(None),
+            user_agent_pool: RwLock::new(Vec::new()),
+            user_agent_rr: AtomicUsize::new(0),
         }
     }
 
-    /// Internal helper to build a client with optional upstream proxy config
-    fn build_client_internal(
-        proxy_config: Option<crate::proxy::config::UpstreamProxyConfig>,
-    ) -> Result<Client, reqwest::Error> {
-        let mut builder = Client::builder()
+    /// Base builder shared by the default client and proxy pool clients
+    fn base_builder(settings: PoolSettings) -> reqwest::ClientBuilder {
+        let builder = Client::builder()
             // Connection settings (优化连接复用，减少建立开销)
             .connect_timeout(Duration::from_secs(20))
-            .pool_max_idle_per_host(16) // 每主机最多 16 个空闲连接
-            .pool_idle_timeout(Duration::from_secs(90)) // 空闲连接保持 90 秒
-            .tcp_keepalive(Duration::from_secs(60)) // TCP 保活探测 60 秒
+            .pool_max_idle_per_host(settings.max_idle_per_host) // 每主机最多保留的空闲连接
+            .pool_idle_timeout(settings.idle_timeout) // 空闲连接保持时间
+            .tcp_keepalive(settings.tcp_keepalive) // TCP 保活探测
+            .connection_verbose(settings.verbose)
+            .dns_resolver(Arc::new(CountingResolver))
             .timeout(Duration::from_secs(600))
             .user_agent(crate::constants::USER_AGENT.as_str());
+        if !settings.http2 {
+            return builder.http1_only();
+        }
+        // HTTP/2 由 TLS ALPN 协商；空闲时也发送 PING，避免长连接被中间设备回收
+        match settings.tcp_keepalive {
+            Some(interval) => builder
+                .http2_keep_alive_interval(interval)
+                .http2_keep_alive_while_idle(true),
+            None => builder,
+        }
+    }
 
+    /// Internal helper to build a client with optional upstream proxy config
+    fn build_client_internal(
+        proxy_config: Option<crate::proxy::config::UpstreamProxyConfig>,
+        settings: PoolSettings,
+    ) -> Result<Client, reqwest::Error> {
+        Self::with_upstream_proxy(Self::base_builder(settings), proxy_config.as_ref()).build()
+    }
+
+    fn with_upstream_proxy(
+        mut builder: reqwest::ClientBuilder,
+        proxy_config: Option<&crate::proxy::config::UpstreamProxyConfig>,
+    ) -> reqwest::ClientBuilder {
         if let Some(config) = proxy_config {
             if config.enabled && !config.url.is_empty() {
                 let url = crate::proxy::config::normalize_proxy_url(&config.url);
@@ -101,8 +243,20 @@ impl UpstreamClient {
                 }
             }
         }
+        builder
+    }
 
-        builder.build()
+    /// [NEW] 下载用户提供的远程资源 (如图片 URL) 的一次性客户端:
+    /// 不跟随重定向 (由调用方逐跳校验)，并把 `host` 固定解析到已校验的 `addr`，避免 DNS rebinding
+    pub fn pinned_fetch_client(&self, host: &str, addr: std::net::SocketAddr) -> Result<Client, reqwest::Error> {
+        let (settings, config) = match self.default_client.read() {
+            Ok(current) => (current.settings, current.config.clone()),
+            Err(_) => (PoolSettings::from_config(None), None),
+        };
+        let builder = Self::base_builder(settings)
+            .redirect(reqwest::redirect::Policy::none())
+            .resolve(host, addr);
+        Self::with_upstream_proxy(builder, config.as_ref()).build()
     }
 
     /// Build a client with a specific PoolProxyConfig (from ProxyPool)
@@ -110,14 +264,8 @@ impl UpstreamClient {
         &self,
         proxy_config: crate::proxy::proxy_pool::PoolProxyConfig,
     ) -> Result<Client, reqwest::Error> {
-        // Reuse base settings similar to default client but with specific proxy
-        Client::builder()
-            .connect_timeout(Duration::from_secs(20))
-            .pool_max_idle_per_host(16)
-            .pool_idle_timeout(Duration::from_secs(90))
-            .tcp_keepalive(Duration::from_secs(60))
-            .timeout(Duration::from_secs(600))
-            .user_agent(crate::constants::USER_AGENT.as_str())
+        // Reuse base settings of the default client but with specific proxy
+        Self::base_builder(self.current_settings())
             .proxy(proxy_config.proxy) // Apply the specific proxy
             .build()
     }
@@ -129,8 +277,81 @@ impl UpstreamClient {
         tracing::debug!("UpstreamClient User-Agent override updated: {:?}", lock);
     }
 
-    /// Get current User-Agent
+    fn current_settings(&self) -> PoolSettings {
+        match self.default_client.read() {
+            Ok(current) => current.settings,
+            Err(_) => PoolSettings::from_config(None),
+        }
+    }
+
+    /// [NEW] 上游代理 / 连接池配置热更新: 原子替换默认客户端
+    /// 在途请求持有旧客户端的克隆，不受影响；代理池客户端缓存清空后按新参数重建
+    /// 配置未变化时保留现有客户端，避免无关的配置保存清空连接池
+    pub fn update_proxy_config(&self, config: crate::proxy::config::UpstreamProxyConfig) {
+        let unchanged = self
+            .default_client
+            .read()
+            .map(|current| current.config.as_ref() == Some(&config))
+            .unwrap_or(false);
+        if unchanged {
+            return;
+        }
+        let settings = PoolSettings::from_config(Some(&config));
+        match Self::build_client_internal(Some(config.clone()), settings) {
+            Ok(client) => {
+                if let Ok(mut current) = self.default_client.write() {
+                    *current = DefaultClient {
+                        client,
+                        settings,
+                        config: Some(config),
+                    };
+                }
+                self.client_cache.clear();
+                tracing::info!("UpstreamClient rebuilt with updated proxy/pool settings");
+            }
+            Err(e) => {
+                tracing::error!("Failed to rebuild upstream client, keeping previous one: {}", e);
+            }
+        }
+    }
+
+    /// [NEW] Connection pool stats
+    pub fn pool_stats(&self) -> ConnectionPoolStats {
+        let settings = self.current_settings();
+        ConnectionPoolStats {
+            active: self.in_flight.load(Ordering::Relaxed),
+            idle: None,
+            waiting: None,
+            max_idle_per_host: settings.max_idle_per_host,
+            idle_timeout_seconds: settings.idle_timeout.as_secs(),
+            http2_enabled: settings.http2,
+        }
+    }
+
+    /// [NEW] Set User-Agent rotation pool (empty strings are ignored)
+    pub async fn set_user_agent_pool(&self, pool: Vec<String>) {
+        let pool: Vec<String> = pool
+            .into_iter()
+            .map(|ua| ua.trim().to_string())
+            .filter(|ua| !ua.is_empty())
+            .collect();
+        let mut lock = self.user_agent_pool.write().await;
+        *lock = pool;
+        tracing::debug!("UpstreamClient User-Agent pool updated: {} entries", lock.len());
+    }
+
+    /// Get User-Agent for the next request
+    /// 轮换池非空时按轮询取值，否则使用单一覆盖或默认值
     pub async fn get_user_agent(&self) -> String {
+        let pool = self.user_agent_pool.read().await;
+        if !pool.is_empty() {
+            let idx = self.user_agent_rr.fetch_add(1, Ordering::Relaxed);
+            let ua = pick_user_agent(&pool, idx);
+            tracing::debug!("Using User-Agent from pool: {}", ua);
+            return ua.to_string();
+        }
+        drop(pool);
+
         let ua_override = self.user_agent_override.read().await;
         ua_override
             .as_ref()
@@ -180,7 +401,10 @@ impl UpstreamClient {
             }
         }
         // Fallback to default client
-        self.default_client.clone()
+        match self.default_client.read() {
+            Ok(current) => current.client.clone(),
+            Err(poisoned) => poisoned.into_inner().client.clone(),
+        }
     }
 
     /// Build v1internal URL
@@ -219,12 +443,14 @@ impl UpstreamClient {
             query_string,
             std::collections::HashMap::new(),
             account_id,
+            None,
         )
         .await
     }
 
     /// [FIX #765] 调用 v1internal API，支持透传额外的 Headers
     /// [ENHANCED] 返回 UpstreamCallResult，包含降级尝试记录，用于 debug 日志
+    /// [NEW] `timeout` 为本次请求的超时覆盖 (按模型解析)，None 时沿用客户端默认的 600 秒
     pub async fn call_v1_internal_with_headers(
         &self,
         method: &str,
@@ -233,23 +459,33 @@ impl UpstreamClient {
         query_string: Option<&str>,
         extra_headers: std::collections::HashMap<String, String>,
         account_id: Option<&str>, // [NEW] Account ID
+        timeout: Option<Duration>,
     ) -> Result<UpstreamCallResult, String> {
         // [NEW] Get client based on account (cached in proxy pool manager)
         let client = self.get_client(account_id).await;
 
+        // [NEW] 模型路由: 命中时只请求路由指定的地址，不走默认端点降级
+        let route = body
+            .get("model")
+            .and_then(|m| m.as_str())
+            .and_then(crate::proxy::config::resolve_model_route);
+        let endpoints = endpoints_for_route(route.as_ref());
+
         // 构建 Headers (所有端点复用)
         let mut headers = header::HeaderMap::new();
         headers.insert(
             header::CONTENT_TYPE,
             header::HeaderValue::from_static("application/json"),
         );
-        headers.insert(
-            header::AUTHORIZATION,
-            header::HeaderValue::from_str(&format!("Bearer {}", access_token))
-                .map_err(|e| e.to_string())?,
-        );
+        if let Some(authorization) = authorization_for_route(route.as_ref(), access_token) {
+            headers.insert(
+                header::AUTHORIZATION,
+                header::HeaderValue::from_str(&authorization).map_err(|e| e.to_string())?,
+            );
+        }
 
         // [NEW] 支持自定义 User-Agent 覆盖
+        // 每次调用只取一次 UA，端点降级重试与流式响应都沿用同一个值
         headers.insert(
             header::USER_AGENT,
             header::HeaderValue::from_str(&self.get_user_agent().await).unwrap_or_else(|e| {
@@ -272,16 +508,20 @@ impl UpstreamClient {
         let mut fallback_attempts: Vec<FallbackAttemptLog> = Vec::new();
 
         // 遍历所有端点，失败时自动切换
-        for (idx, base_url) in V1_INTERNAL_BASE_URL_FALLBACKS.iter().enumerate() {
+        for (idx, base_url) in endpoints.iter().enumerate() {
             let url = Self::build_url(base_url, method, query_string);
-            let has_next = idx + 1 < V1_INTERNAL_BASE_URL_FALLBACKS.len();
+            let has_next = idx + 1 < endpoints.len();
 
-            let response = client
-                .post(&url)
-                .headers(headers.clone())
-                .json(&body)
-                .send()
-                .await;
+            let mut request = client.post(&url).headers(headers.clone()).json(&body);
+            if let Some(timeout) = timeout {
+                request = request.timeout(timeout);
+            }
+            crate::proxy::monitor::ConnectionCounters::global().record_request();
+            let response = {
+                // 请求 future 被取消 (客户端断开) 时也要归还计数
+                let _in_flight = InFlightGuard::new(&self.in_flight);
+                request.send().await
+            };
 
             match response {
                 Ok(resp) => {
@@ -292,7 +532,7 @@ impl UpstreamClient {
                                 "✓ Upstream fallback succeeded | Endpoint: {} | Status: {} | Next endpoints available: {}",
                                 base_url,
                                 status,
-                                V1_INTERNAL_BASE_URL_FALLBACKS.len() - idx - 1
+                                endpoints.len() - idx - 1
                             );
                         } else {
                             tracing::debug!(
@@ -422,4 +662,48 @@ mod tests {
             "https://cloudcode-pa.googleapis.com/v1internal:streamGenerateContent?alt=sse"
         );
     }
+
+    #[test]
+    fn test_account_token_only_sent_to_google_routes() {
+        use crate::proxy::config::ModelRoute;
+        let route = |base_url: &str, auth_header: Option<&str>| ModelRoute {
+            pattern: "*".to_string(),
+            base_url: base_url.to_string(),
+            auth_header: auth_header.map(|h| h.to_string()),
+        };
+
+        assert_eq!(authorization_for_route(None, "ya29.x").as_deref(), Some("Bearer ya29.x"));
+        let google = route("https://daily-cloudcode-pa.googleapis.com/v1internal", None);
+        assert_eq!(
+            authorization_for_route(Some(&google), "ya29.x").as_deref(),
+            Some("Bearer ya29.x")
+        );
+        let relay = route("http://10.0.0.2:8045/v1internal", None);
+        assert_eq!(authorization_for_route(Some(&relay), "ya29.x"), None);
+        let spoofed = route("https://googleapis.com.evil.example/v1internal", None);
+        assert_eq!(authorization_for_route(Some(&spoofed), "ya29.x"), None);
+        let relay_with_key = route("http://10.0.0.2:8045/v1internal", Some(" Bearer sk-relay "));
+        assert_eq!(
+            authorization_for_route(Some(&relay_with_key), "ya29.x").as_deref(),
+            Some("Bearer sk-relay")
+        );
+    }
+
+    #[test]
+    fn test_in_flight_guard_releases_on_drop() {
+        let counter = AtomicUsize::new(0);
+        {
+            let _a = InFlightGuard::new(&counter);
+            let _b = InFlightGuard::new(&counter);
+            assert_eq!(counter.load(Ordering::Relaxed), 2);
+        }
+        assert_eq!(counter.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_pick_user_agent_round_robin() {
+        let pool = vec!["ua-a".to_string(), "ua-b".to_string()];
+        let picked: Vec<&str> = (0..4).map(|i| pick_user_agent(&pool, i)).collect();
+        assert_eq!(picked, vec!["ua-a", "ua-b", "ua-a", "ua-b"]);
+    }
 }
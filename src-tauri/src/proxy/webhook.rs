@@ -0,0 +1,235 @@
+// Webhook 通知 - 账号事件 (禁用 / 配额耗尽 / 熔断 / 新增) 推送到外部系统
+// 投递异步执行，失败按指数退避重试，不阻塞请求主流程
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+use crate::proxy::config::{WebhookConfig, WebhookEvent};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// 单次投递结果 (用于测试接口)
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookDeliveryResult {
+    pub url: String,
+    pub success: bool,
+    pub status: Option<u16>,
+    pub attempts: u32,
+    pub error: Option<String>,
+}
+
+pub struct WebhookDispatcher {
+    client: reqwest::Client,
+    webhooks: RwLock<Vec<WebhookConfig>>,
+}
+
+impl WebhookDispatcher {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            webhooks: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// 全局实例 (TokenManager / 账号模块等非 AppState 调用点通过它投递)
+    pub fn global() -> Arc<WebhookDispatcher> {
+        static INSTANCE: OnceLock<Arc<WebhookDispatcher>> = OnceLock::new();
+        INSTANCE
+            .get_or_init(|| Arc::new(WebhookDispatcher::new()))
+            .clone()
+    }
+
+    pub fn update_config(&self, webhooks: Vec<WebhookConfig>) {
+        if let Ok(mut lock) = self.webhooks.write() {
+            *lock = webhooks;
+        }
+    }
+
+    fn subscribers(&self, event: WebhookEvent) -> Vec<WebhookConfig> {
+        self.webhooks
+            .read()
+            .map(|hooks| {
+                hooks
+                    .iter()
+                    .filter(|h| !h.url.trim().is_empty())
+                    .filter(|h| h.events.is_empty() || h.events.contains(&event))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// 异步投递事件到所有订阅该事件的 Webhook
+    pub fn dispatch(&self, event: WebhookEvent, data: Value) {
+        let hooks = self.subscribers(event);
+        if hooks.is_empty() {
+            return;
+        }
+
+        let body = build_payload(&serde_json::to_value(event).unwrap_or_default(), data);
+        for hook in hooks {
+            let client = self.client.clone();
+            let body = body.clone();
+            tauri::async_runtime::spawn(async move {
+                let result = deliver(&client, &hook, &body).await;
+                if !result.success {
+                    tracing::warn!(
+                        "[Webhook] Delivery to {} failed after {} attempts: {}",
+                        hook.url,
+                        result.attempts,
+                        result.error.unwrap_or_default()
+                    );
+                }
+            });
+        }
+    }
+
+    /// 向所有已配置的 Webhook 同步发送测试事件
+    pub async fn send_test(&self) -> Vec<WebhookDeliveryResult> {
+        let hooks: Vec<WebhookConfig> = self
+            .webhooks
+            .read()
+            .map(|h| h.clone())
+            .unwrap_or_default();
+        let body = build_payload(
+            &json!("test"),
+            json!({ "message": "Antigravity Manager webhook test" }),
+        );
+
+        let mut results = Vec::with_capacity(hooks.len());
+        for hook in &hooks {
+            results.push(deliver(&self.client, hook, &body).await);
+        }
+        results
+    }
+}
+
+impl Default for WebhookDispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 便捷入口: 通过全局实例投递事件
+pub fn emit(event: WebhookEvent, data: Value) {
+    WebhookDispatcher::global().dispatch(event, data);
+}
+
+fn build_payload(event: &Value, data: Value) -> String {
+    json!({
+        "event": event,
+        "timestamp": chrono::Utc::now().timestamp(),
+        "data": data,
+    })
+    .to_string()
+}
+
+async fn deliver(client: &reqwest::Client, hook: &WebhookConfig, body: &str) -> WebhookDeliveryResult {
+    let max_attempts = hook.retry_count as u32 + 1;
+    let mut last_error = None;
+    let mut last_status = None;
+
+    for attempt in 1..=max_attempts {
+        let mut req = client
+            .post(&hook.url)
+            .header("Content-Type", "application/json")
+            .body(body.to_string());
+        if let Some(secret) = hook.secret.as_deref().filter(|s| !s.is_empty()) {
+            req = req.header(
+                "X-ABV-Signature",
+                format!("sha256={}", sign_payload(secret, body.as_bytes())),
+            );
+        }
+
+        match req.send().await {
+            Ok(resp) if resp.status().is_success() => {
+                return WebhookDeliveryResult {
+                    url: hook.url.clone(),
+                    success: true,
+                    status: Some(resp.status().as_u16()),
+                    attempts: attempt,
+                    error: None,
+                };
+            }
+            Ok(resp) => {
+                last_status = Some(resp.status().as_u16());
+                last_error = Some(format!("HTTP {}", resp.status()));
+            }
+            Err(e) => {
+                last_error = Some(e.to_string());
+            }
+        }
+
+        if attempt < max_attempts {
+            // 指数退避: 1s, 2s, 4s ...
+            tokio::time::sleep(RETRY_BASE_DELAY * 2u32.saturating_pow(attempt - 1)).await;
+        }
+    }
+
+    WebhookDeliveryResult {
+        url: hook.url.clone(),
+        success: false,
+        status: last_status,
+        attempts: max_attempts,
+        error: last_error,
+    }
+}
+
+/// HMAC-SHA256 签名 (十六进制小写)
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_payload_rfc4231() {
+        // RFC 4231 Test Case 2
+        assert_eq!(
+            sign_payload("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_subscribers_filter_by_event() {
+        let dispatcher = WebhookDispatcher::new();
+        dispatcher.update_config(vec![
+            WebhookConfig {
+                url: "http://a".to_string(),
+                events: vec![WebhookEvent::AccountDisabled],
+                secret: None,
+                retry_count: 0,
+            },
+            WebhookConfig {
+                url: "http://b".to_string(),
+                events: vec![],
+                secret: None,
+                retry_count: 0,
+            },
+        ]);
+
+        assert_eq!(dispatcher.subscribers(WebhookEvent::AccountDisabled).len(), 2);
+        let quota = dispatcher.subscribers(WebhookEvent::QuotaExceeded);
+        assert_eq!(quota.len(), 1);
+        assert_eq!(quota[0].url, "http://b");
+    }
+}
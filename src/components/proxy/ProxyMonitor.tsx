@@ -26,14 +26,31 @@ interface ProxyRequestLog {
     response_body?: string;
     input_tokens?: number;
     output_tokens?: number;
+    cached_tokens?: number;
+    estimated_cost_usd?: number;
     account_email?: string;
     protocol?: string;  // "openai" | "anthropic" | "gemini"
+    timeout_secs?: number; // 本次请求生效的超时 (秒)
+    error_class?: string; // 上游错误分类, 如 quota_exhausted / invalid_argument
 }
 
 interface ProxyStats {
     total_requests: number;
     success_count: number;
     error_count: number;
+    zai_requests?: number;
+    google_requests?: number;
+    zai_errors?: number;
+    fallback_triggered?: number;
+    zai_error_rate?: number;
+    upstream_new_connections?: number;
+    upstream_reused_connections?: number;
+    connection_reuse_rate?: number;
+    stream_ttfb_p50_ms?: number;
+    stream_ttfb_p95_ms?: number;
+    stream_duration_p50_ms?: number;
+    stream_duration_p95_ms?: number;
+    stream_samples?: number;
 }
 
 interface ProxyMonitorProps {
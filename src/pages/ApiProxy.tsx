@@ -40,9 +40,16 @@ import { CircuitBreakerConfig } from '../types/config';
 
 interface ProxyStatus {
     running: boolean;
+    maintenance_mode?: boolean;
+    maintenance_reason?: string | null;
+    maintenance_since?: number | null;
     port: number;
     base_url: string;
     active_accounts: number;
+    zai_healthy?: boolean;
+    last_probe_error?: string | null;
+    quota_forecast_warning?: boolean;
+    quota_forecast_warning_models?: string[];
 }
 
 interface CustomPreset {
@@ -198,7 +205,7 @@ export default function ApiProxy() {
     const [availableAccounts, setAvailableAccounts] = useState<Array<{ id: string; email: string }>>([]);
 
     // Cloudflared (CF隧道) states
-    const [cfStatus, setCfStatus] = useState<{ installed: boolean; version?: string; running: boolean; url?: string; error?: string }>({
+    const [cfStatus, setCfStatus] = useState<{ installed: boolean; version?: string; running: boolean; url?: string; error?: string; reconnecting?: boolean; restart_attempts?: number; max_restart_attempts?: number }>({
         installed: false,
         running: false,
     });
@@ -2107,9 +2114,15 @@ print(response.text)`;
                                                 {cfStatus.running && (
                                                     <div className="p-4 bg-green-50 dark:bg-green-900/20 rounded-xl border border-green-200 dark:border-green-800">
                                                         <div className="flex items-center gap-2 mb-2">
-                                                            <div className="w-2 h-2 rounded-full bg-green-500 animate-pulse"></div>
+                                                            <div className={cn("w-2 h-2 rounded-full animate-pulse", cfStatus.reconnecting ? "bg-amber-500" : "bg-green-500")}></div>
                                                             <span className="text-sm font-bold text-green-800 dark:text-green-200">
-                                                                {t('proxy.cloudflared.running', { defaultValue: 'Tunnel Running' })}
+                                                                {cfStatus.reconnecting
+                                                                    ? t('proxy.cloudflared.reconnecting', {
+                                                                        attempt: cfStatus.restart_attempts,
+                                                                        max: cfStatus.max_restart_attempts,
+                                                                        defaultValue: `Reconnecting (attempt ${cfStatus.restart_attempts}/${cfStatus.max_restart_attempts})`,
+                                                                    })
+                                                                    : t('proxy.cloudflared.running', { defaultValue: 'Tunnel Running' })}
                                                             </span>
                                                         </div>
                                                         {cfStatus.url && (
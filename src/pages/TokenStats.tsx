@@ -10,6 +10,7 @@ interface TokenStatsAggregated {
     total_output_tokens: number;
     total_tokens: number;
     request_count: number;
+    estimated_cost_usd?: number;
 }
 
 interface AccountTokenStats {
@@ -44,6 +45,8 @@ interface TokenStatsSummary {
     total_tokens: number;
     total_requests: number;
     unique_accounts: number;
+    total_cached_tokens?: number;
+    estimated_cost_usd?: number;
 }
 
 type TimeRange = 'hourly' | 'daily' | 'weekly';
@@ -44,11 +44,20 @@ export async function fetchAccountQuota(accountId: string): Promise<QuotaData> {
     return await invoke('fetch_account_quota', { accountId });
 }
 
+export interface AccountRefreshResult {
+    account_id: string;
+    email: string;
+    status: 'success' | 'failed' | 'skipped';
+    reason?: string;
+}
+
 export interface RefreshStats {
     total: number;
     success: number;
     failed: number;
+    skipped: number;
     details: string[];
+    results: AccountRefreshResult[];
 }
 
 export async function refreshAllQuotas(): Promise<RefreshStats> {
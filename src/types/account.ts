@@ -12,12 +12,29 @@ export interface Account {
     proxy_disabled?: boolean;
     proxy_disabled_reason?: string;
     proxy_disabled_at?: number;
+    proxy_disable_history?: ProxyDisableEvent[]; // 最近 10 次反代禁用记录
     protected_models?: string[];
     custom_label?: string;  // 用户自定义标签
+    tags?: string[];        // 分组标签
+    notes?: string;         // 运维备注
+    last_warmup?: WarmupRecord; // 最近一次预热结果
+    consecutive_failures?: number; // [NEW] 连续硬失败次数 (反代运行时)
     created_at: number;
     last_used: number;
 }
 
+export interface ProxyDisableEvent {
+    reason: string;
+    disabled_at: number;
+    re_enabled_at?: number | null;
+}
+
+export interface WarmupRecord {
+    at: number;
+    ok: boolean;
+    message: string;
+}
+
 export interface TokenData {
     access_token: string;
     refresh_token: string;
@@ -1,18 +1,50 @@
 export interface UpstreamProxyConfig {
     enabled: boolean;
     url: string;
+    pool_max_idle_per_host?: number; // [NEW] 默认 20
+    pool_idle_timeout_seconds?: number; // [NEW] 默认 90
+    connection_verbose?: boolean;
+    http2_enabled?: boolean; // [NEW] 默认 true
+    tcp_keepalive_seconds?: number; // [NEW] 默认 60，0 表示关闭
+}
+
+export interface ApiKeyEntry {
+    key: string;
+    label: string;
+    disabled?: boolean;
+}
+
+export interface ModelRoute {
+    pattern: string; // 路由后模型名的 * 通配符模式 (大小写敏感)
+    base_url: string; // v1internal 兼容的基础地址
+    auth_header?: string; // 覆盖 Authorization 请求头的完整值，为空时沿用账号令牌
 }
 
 export interface ProxyConfig {
     enabled: boolean;
     allow_lan_access?: boolean;
+    bind_addresses?: string[]; // e.g. ["127.0.0.1", "100.64.0.1:8046", "unix:/run/abv.sock"]
     auth_mode?: 'off' | 'strict' | 'all_except_health' | 'auto';
     port: number;
     api_key: string;
+    api_key_previous?: string | null; // [NEW] 轮换前的旧 Key (宽限期内有效)
+    api_key_previous_expires_at?: number | null; // 旧 Key 失效时间 (unix 秒)
+    api_keys?: ApiKeyEntry[]; // [NEW] 附加 API Key (带标签，可单独禁用)
+    rotation_grace_seconds?: number; // 轮换宽限期 (秒)，默认 3600
+    auto_disable_failure_threshold?: number; // [NEW] 连续 403 硬失败自动禁用阈值，默认 5，0 关闭
+    shutdown_drain_seconds?: number; // [NEW] 优雅停机排空超时 (秒)，默认 30
     admin_password?: string;
+    jwt_expiry_seconds?: number; // 管理 JWT 有效期 (秒)，默认 3600
+    admin_session_secret?: string; // [NEW] 管理用户会话令牌签名密钥 (创建首个管理用户时自动生成)
     auto_start: boolean;
     custom_mapping?: Record<string, string>;
+    model_account_tags?: Record<string, string[]>; // [NEW] 模型 → 账号分组标签要求 (key 语法同 custom_mapping)
+    model_routes?: ModelRoute[]; // [NEW] 模型 → 上游地址路由，按顺序首个匹配生效
+    model_list_cache_ttl_secs?: number; // [NEW] 模型列表缓存时间 (秒)，0 表示不缓存
+    response_cache_ttl_secs?: number; // [NEW] countTokens 响应缓存时间 (秒)，默认 60，0 表示不缓存
     request_timeout: number;
+    model_timeouts?: Record<string, number>; // 模型名模式 -> 秒；精确 > 前缀 > 全局 request_timeout
+    timeouts?: RequestTimeoutsConfig;
     enable_logging: boolean;
     debug_logging?: DebugLoggingConfig;
     upstream_proxy: UpstreamProxyConfig;
@@ -20,13 +52,78 @@ export interface ProxyConfig {
     scheduling?: StickySessionConfig;
     experimental?: ExperimentalConfig;
     user_agent_override?: string;
+    user_agent_pool?: string[]; // [NEW] 非空时按请求轮换 User-Agent
     saved_user_agent?: string;
     thinking_budget?: ThinkingBudgetConfig;
+    claude_thinking?: ThinkingBudgetConfig; // [NEW] Claude thinking.budget_tokens 策略 (默认 passthrough)
     global_system_prompt?: GlobalSystemPromptConfig;
     image_thinking_mode?: 'enabled' | 'disabled'; // [NEW] 图像思维模式开关
     proxy_pool?: ProxyPoolConfig;
+    response_headers?: Record<string, string>;
+    error_templates?: ErrorTemplateConfig; // [NEW] 常见 AI 错误的自定义响应模板
+    access_log_format?: 'json' | 'combined' | 'none'; // [NEW] 访问日志格式 (combined 写入 access.log)
+    default_safety_settings?: SafetySetting[] | null; // [NEW] Gemini 请求未携带 safetySettings 时注入
+    safety_settings_override?: boolean; // [NEW] 用默认值替换客户端的 safetySettings
+    concurrency?: AccountConcurrencyConfig;
+    webhooks?: WebhookConfig[];
+    cors?: CorsConfig;
+    idle_disable_after_days?: number | null; // 闲置超过该天数的账号自动停用反代
+    coordination_mode?: CoordinationMode;
+    budget?: BudgetConfig;
+    transforms?: TransformsConfig; // [NEW] 请求/响应 JSON 改写规则
+    system_prompt_max_tokens?: number; // [NEW] 启用 usage scaling 时系统提示词预算 (默认 500)
+    identity_short?: string; // [NEW] 超出预算时使用的短版本身份
+    identity_injection?: IdentityInjectionConfig; // [NEW] Antigravity 身份注入方式
+    warmup_schedule?: WarmupScheduleConfig; // [NEW] 定时预热
+    embedding_batch_size?: number; // /v1/embeddings 单次上游请求最大条数 (默认 100)
+    config_file_watch?: boolean; // 监听配置文件变更并自动热更新 (需重启服务生效)
+}
+
+// [NEW] 错误响应模板，支持 {{account_id}} / {{model}} / {{upstream_error}}
+export interface ErrorTemplateConfig {
+    quota_exceeded?: string | null;
+    model_not_found?: string | null;
+    context_too_long?: string | null;
+}
+
+/** 定时预热；daily_time 与 interval_hours 同时设置时以 daily_time 为准 */
+export interface WarmupScheduleConfig {
+    enabled: boolean;
+    interval_hours?: number | null;
+    daily_time?: string | null; // 本地时间 HH:MM
+    account_ids: string[]; // 为空表示全部账号
+    max_concurrency: number;
+}
+
+/** 每日 Token 预算 (本地午夜重置)；全局超限拒绝请求，单账号超限仅退出轮换 */
+export interface BudgetConfig {
+    daily_token_budget?: number | null;
+    per_account_daily_budget?: number | null;
+}
+
+/** 改写动作，path 为 JSON Pointer (如 /generationConfig/temperature) */
+export type TransformAction =
+    | { type: 'set_field'; path: string; value: unknown }
+    | { type: 'delete_field'; path: string }
+    | { type: 'clamp_number'; path: string; min?: number | null; max?: number | null }
+    | { type: 'rename_field'; from: string; to: string };
+
+export interface TransformRule {
+    name?: string | null;
+    enabled?: boolean;
+    route?: string | null; // 支持 * 通配
+    model?: string | null; // 支持 * 通配
+    actions: TransformAction[];
+}
+
+export interface TransformsConfig {
+    request?: TransformRule[];
+    response?: TransformRule[];
 }
 
+/** 多实例协调模式: shared_database 通过共享 SQLite 同步会话绑定 / 固定账号 / 熔断状态 */
+export type CoordinationMode = 'standalone' | 'shared_database';
+
 // ============================================================================
 // Thinking Budget 配置 (控制 AI 深度思考时的 Token 预算)
 // ============================================================================
@@ -40,12 +137,30 @@ export interface ThinkingBudgetConfig {
     mode: ThinkingBudgetMode;
     /** 自定义固定值（仅在 mode=custom 时生效），范围 1024-65536 */
     custom_value: number;
+    /** 按模型覆盖，key 为模型名或前缀（精确匹配优先，其次最长前缀） */
+    per_model_overrides?: Record<string, ThinkingBudgetOverride>;
+}
+
+/** 单个模型的 Thinking Budget 覆盖 */
+export interface ThinkingBudgetOverride {
+    mode: ThinkingBudgetMode;
+    custom_value: number;
 }
 
 // ============================================================================
 // 全局系统提示词配置
 // ============================================================================
 
+/** Antigravity 身份注入模式 */
+export type IdentityInjectionMode = 'always' | 'only_when_missing' | 'never' | 'custom';
+
+/** 身份注入配置 */
+export interface IdentityInjectionConfig {
+    mode: IdentityInjectionMode;
+    /** custom 模式下注入的身份文本 */
+    custom_template: string;
+}
+
 /** 全局系统提示词配置 */
 export interface GlobalSystemPromptConfig {
     /** 是否启用 */
@@ -57,13 +172,24 @@ export interface GlobalSystemPromptConfig {
 export interface DebugLoggingConfig {
     enabled: boolean;
     output_dir?: string;
+    max_capture_bytes?: number;
+    redact_keys?: string[]; // [NEW] 额外脱敏的字段名或 JSON Pointer
 }
 
 export type SchedulingMode = 'CacheFirst' | 'Balance' | 'PerformanceFirst';
 
+export interface SafetySetting {
+    category: string;
+    threshold: string;
+}
+
+export type StickyMode = 'Header' | 'Ip' | 'None';
+
 export interface StickySessionConfig {
     mode: SchedulingMode;
     max_wait_seconds: number;
+    sticky_mode?: StickyMode; // [NEW] 会话绑定依据 (默认 Header)
+    session_ttl_seconds?: number; // [NEW] 会话绑定闲置过期时间 (秒)，0 = 永不过期
 }
 
 export type ZaiDispatchMode = 'off' | 'exclusive' | 'pooled' | 'fallback';
@@ -106,11 +232,48 @@ export interface PinnedQuotaModelsConfig {
     models: string[];
 }
 
+export type WebhookEvent = 'account_disabled' | 'quota_exceeded' | 'circuit_breaker_open' | 'account_added';
+
+export interface WebhookConfig {
+    url: string;
+    events: WebhookEvent[];
+    secret?: string | null;
+    retry_count: number;
+}
+
+export interface AccountConcurrencyConfig {
+    max_per_account: number;
+    queue_timeout_secs: number;
+}
+
+export type TimeoutRouteClass = 'chat' | 'images' | 'audio' | 'count_tokens';
+
+export interface RequestTimeoutsConfig {
+    routes: Partial<Record<TimeoutRouteClass, number>>; // 秒，优先级低于 model_timeouts
+}
+
+export interface CorsConfig {
+    allowed_origins: string[]; // '*', 'https://app.example.com', 'app.example.com', '*.example.com'
+    allow_credentials: boolean; // 仅对管理 API (/api/*) 生效
+    max_age_seconds: number;
+    path_methods?: Record<string, string[]>; // [NEW] 路径前缀 -> 允许的方法，最长前缀优先
+}
+
 export interface ExperimentalConfig {
     enable_usage_scaling: boolean;
     context_compression_threshold_l1?: number;
     context_compression_threshold_l2?: number;
     context_compression_threshold_l3?: number;
+    stream_ping_interval_secs?: number;
+    signature_cache_persistence?: boolean;
+    signature_cache_ttl_secs?: number;
+    enable_response_cache?: boolean; // 相同音频重复转录时返回缓存结果
+    audio_cache_ttl_secs?: number;
+    strict_function_schema_validation?: boolean; // functionCall 参数不符合 schema 时返回错误
+    max_image_bytes?: number; // 单张 inlineData 图片最大字节数 (默认 4 MB，0 不限制)
+    max_image_dimension?: number; // 图片最长边上限，超出的 JPEG/PNG 等比缩小 (0 不缩放)
+    stream_buffer_chunks?: number; // Gemini 流式响应缓冲的分块数，默认 64
+    stream_backpressure_timeout_ms?: number; // 缓冲区持续写满超过该时长 (毫秒) 时中断连接，默认 30000，0 不中断
 }
 
 export interface CircuitBreakerConfig {
@@ -118,6 +281,13 @@ export interface CircuitBreakerConfig {
     backoff_steps: number[];
 }
 
+export interface BackupConfig {
+    enabled: boolean;
+    interval_hours: number; // 备份间隔 (小时)，默认 24
+    keep: number; // 保留的备份数量，默认 7
+    include_databases: boolean; // 是否包含日志/统计数据库
+}
+
 export interface AppConfig {
     language: string;
     theme: string;
@@ -133,10 +303,13 @@ export interface AppConfig {
     update_check_interval?: number; // 更新检查间隔（小时）
     accounts_page_size?: number; // 账号列表每页显示数量,默认 0 表示自动计算
     hidden_menu_items?: string[]; // 隐藏的菜单项路径列表
+    quota_refresh_concurrency?: number; // 批量刷新配额的并发数，默认 5
+    quota_forecast_warning_hours?: number; // 预计耗尽时间低于该值 (小时) 时告警，默认 2
     scheduled_warmup: ScheduledWarmupConfig;
     quota_protection: QuotaProtectionConfig; // [NEW] 配额保护配置
     pinned_quota_models: PinnedQuotaModelsConfig; // [NEW] 配额关注列表
     circuit_breaker: CircuitBreakerConfig; // [NEW] 熔断器配置
+    backup?: BackupConfig; // [NEW] 配置与账号数据定时备份
     proxy: ProxyConfig;
 }
 
@@ -144,14 +317,18 @@ export interface AppConfig {
 // Cloudflared (CF隧道) 类型定义
 // ============================================================================
 
-export type TunnelMode = 'quick' | 'auth';
+export type TunnelMode = 'quick' | 'auth' | 'named';
 
 export interface CloudflaredConfig {
     enabled: boolean;
     mode: TunnelMode;
     port: number;
     token?: string;
+    /** 命名隧道 Token (优先于 token)，与 quick 模式互斥 */
+    tunnel_token?: string;
     use_http2: boolean;
+    hostname?: string;
+    credentials_file?: string;
 }
 
 export interface CloudflaredStatus {
@@ -160,6 +337,34 @@ export interface CloudflaredStatus {
     running: boolean;
     url?: string;
     error?: string;
+    hostname?: string;
+    reconnecting?: boolean;
+    restart_attempts?: number;
+    max_restart_attempts?: number;
+    next_retry_at?: number;
+    failed?: boolean;
+}
+
+export interface CloudflaredLogLine {
+    /** Unix 毫秒 */
+    timestamp: number;
+    stream: 'stdout' | 'stderr' | 'supervisor';
+    line: string;
+}
+
+/** 正在处理中的反代请求 (GET /api/proxy/active-requests) */
+export interface ActiveRequest {
+    id: string;
+    method: string;
+    url: string;
+    model?: string | null;
+    account_email?: string | null;
+    client_ip?: string | null;
+    /** Unix 毫秒 */
+    started_at: number;
+    elapsed_ms: number;
+    streamed_bytes: number;
+    streaming: boolean;
 }
 
 // ============================================================================
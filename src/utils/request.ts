@@ -46,6 +46,8 @@ const COMMAND_MAPPING: Record<string, { url: string; method: 'GET' | 'POST' | 'D
   'load_config': { url: '/api/config', method: 'GET' },
   'save_config': { url: '/api/config', method: 'POST' },
   'get_proxy_stats': { url: '/api/proxy/stats', method: 'GET' },
+  'get_active_requests': { url: '/api/proxy/active-requests', method: 'GET' },
+  'cancel_active_request': { url: '/api/proxy/active-requests/:requestId/cancel', method: 'POST' },
   'set_proxy_monitor_enabled': { url: '/api/proxy/monitor/toggle', method: 'POST' },
 
   // Logs & Monitoring
@@ -102,6 +104,7 @@ const COMMAND_MAPPING: Record<string, { url: string; method: 'GET' | 'POST' | 'D
   'cloudflared_start': { url: '/api/proxy/cloudflared/start', method: 'POST' },
   'cloudflared_stop': { url: '/api/proxy/cloudflared/stop', method: 'POST' },
   'cloudflared_get_status': { url: '/api/proxy/cloudflared/status', method: 'GET' },
+  'cloudflared_get_logs': { url: '/api/proxy/cloudflared/logs', method: 'GET' },
 
   // Updates
   'should_check_updates': { url: '/api/system/updates/check-status', method: 'GET' },